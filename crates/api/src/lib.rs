@@ -0,0 +1,242 @@
+// a REST gateway for explorers and merchants whose integrations can't speak JSON-RPC:
+//
+//     GET  /accounts/{address}  {"address": "0x..", "balance": "0x..", "sequence": 0}
+//     GET  /blocks/{number}     a block as eth_getBlockByNumber answers it, "latest" works too
+//     GET  /txs/{hash}          a tx as eth_getTransactionByHash answers it
+//     POST /txs                 a transfer as fastpay_sendTransfer takes it, {"txHash": "0x.."}
+//
+// every route maps onto a JSON-RPC server's EthRpcImpl, so it reads the same node and admits txs
+// with the same checks. Errors come back as {"error": ".."}
+
+use std::net::SocketAddr;
+
+use alloy::primitives::{Address, B256, U256};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use jsonrpsee::types::{error::INVALID_PARAMS_CODE, ErrorObjectOwned};
+use rpc::{
+    transaction::Transaction, Block, EthRpcImpl, EthRpcServer, FastpayRpcServer, TransferRequest,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountResponse {
+    pub address: Address,
+    pub balance: U256,
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitResponse {
+    pub tx_hash: B256,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    // the request was wrong, like a malformed address or a tx the mempool turned away
+    BadRequest(String),
+    Internal(String),
+}
+
+impl From<ErrorObjectOwned> for ApiError {
+    fn from(error: ErrorObjectOwned) -> Self {
+        match error.code() {
+            INVALID_PARAMS_CODE => Self::BadRequest(error.message().to_string()),
+            _ => Self::Internal(error.message().to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            Self::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            Self::Internal(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, ApiError> {
+    value
+        .parse()
+        .map_err(|_| ApiError::BadRequest(format!("invalid {}: {}", what, value)))
+}
+
+pub fn router(rpc: EthRpcImpl) -> Router {
+    Router::new()
+        .route("/accounts/:address", get(account))
+        .route("/blocks/:number", get(block))
+        .route("/txs/:hash", get(transaction))
+        .route("/txs", post(submit))
+        .with_state(rpc)
+}
+
+pub async fn start_api_server(addr: SocketAddr, rpc: EthRpcImpl) -> anyhow::Result<()> {
+    axum::Server::bind(&addr)
+        .serve(router(rpc).into_make_service())
+        .await?;
+    Ok(())
+}
+
+async fn account(
+    State(rpc): State<EthRpcImpl>,
+    Path(address): Path<String>,
+) -> Result<Json<AccountResponse>, ApiError> {
+    let address: Address = parse(&address, "address")?;
+    // an account that doesn't exist yet has nothing, like eth_getBalance says
+    let (balance, sequence) = match rpc.accounts().get_account(&address) {
+        Some(account) => (account.balance(), account.sequence()),
+        None => (U256::ZERO, 0),
+    };
+    Ok(Json(AccountResponse {
+        address,
+        balance,
+        sequence,
+    }))
+}
+
+async fn block(
+    State(rpc): State<EthRpcImpl>,
+    Path(number): Path<String>,
+) -> Result<Json<Block>, ApiError> {
+    EthRpcServer::get_block_by_number(&rpc, number.clone(), true)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("no block {}", number)))
+}
+
+async fn transaction(
+    State(rpc): State<EthRpcImpl>,
+    Path(hash): Path<String>,
+) -> Result<Json<Transaction>, ApiError> {
+    let hash: B256 = parse(&hash, "tx hash")?;
+    EthRpcServer::get_transaction_by_hash(&rpc, hash)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("no tx {}", hash)))
+}
+
+async fn submit(
+    State(rpc): State<EthRpcImpl>,
+    Json(transfer): Json<TransferRequest>,
+) -> Result<Json<SubmitResponse>, ApiError> {
+    let tx_hash = FastpayRpcServer::send_transfer(&rpc, transfer).await?;
+    Ok(Json(SubmitResponse {
+        tx_hash: parse(&tx_hash, "tx hash")?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use block_builder::BlockBuilder;
+    use mempool::Mempool;
+    use rpc::subscription::SubscriptionConfig;
+    use state::{account::Account, sharded::ShardedState};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use tx::tx::Tx;
+
+    async fn call(router: &Router, method: &str, uri: &str, body: String) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let signer = PrivateKeySigner::random();
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        accounts
+            .write_account(
+                &signer.address(),
+                Account::new(signer.address(), U256::from(100)),
+            )
+            .unwrap();
+        let block_builder = BlockBuilder::new();
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            mempool.clone(),
+            accounts,
+            SubscriptionConfig::default(),
+        );
+        let router = router(rpc);
+
+        let uri = format!("/accounts/{}", signer.address());
+        let response = call(&router, "GET", &uri, String::new()).await;
+        let account: AccountResponse = serde_json::from_value(json(response).await).unwrap();
+        assert_eq!(account.balance, U256::from(100));
+        let response = call(&router, "GET", "/accounts/0x12", String::new()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let to = Address::repeat_byte(1);
+        let tx = Tx::new(signer.address(), to, U256::from(10), None);
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let transfer = TransferRequest {
+            from: signer.address(),
+            to,
+            amount: U256::from(10),
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+            sequence: None,
+            fee: 0,
+            chain_id: None,
+        };
+        let body = serde_json::to_string(&transfer).unwrap();
+        let response = call(&router, "POST", "/txs", body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let submitted: SubmitResponse = serde_json::from_value(json(response).await).unwrap();
+        assert_eq!(submitted.tx_hash, B256::from_slice(tx.tx_hash().as_ref()));
+        assert_eq!(mempool.len().await, 1);
+
+        // Signed by someone else
+        let forged = TransferRequest {
+            amount: U256::from(20),
+            ..transfer
+        };
+        let body = serde_json::to_string(&forged).unwrap();
+        let response = call(&router, "POST", "/txs", body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let uri = format!("/txs/{}", submitted.tx_hash);
+        let response = call(&router, "GET", &uri, String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = call(&router, "GET", "/blocks/0", String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        block_builder
+            .create_block(mempool.take_batch(10).await, Address::ZERO)
+            .await
+            .unwrap();
+        let response = call(&router, "GET", "/blocks/latest", String::new()).await;
+        assert_eq!(json(response).await["number"], "0x0");
+        let response = call(&router, "GET", &uri, String::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json(response).await["from"],
+            serde_json::json!(signer.address())
+        );
+    }
+}
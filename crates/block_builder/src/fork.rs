@@ -0,0 +1,62 @@
+// competing blocks: the store only holds the canonical chain, blocks on other branches wait in
+// memory until the fork choice rule prefers them or they are forgotten on restart
+
+use alloy::primitives::B256;
+
+use crate::Block;
+
+pub trait ForkChoice: Send + Sync {
+    // whether `candidate`, the tip of some branch, should replace the current head
+    fn prefer(&self, candidate: &Block, head: Option<&Block>) -> bool;
+}
+
+// the highest block wins, ties keep the head we already have
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LongestChain;
+
+impl ForkChoice for LongestChain {
+    fn prefer(&self, candidate: &Block, head: Option<&Block>) -> bool {
+        match head {
+            Some(head) => candidate.number > head.number,
+            None => true,
+        }
+    }
+}
+
+// emitted when an import or a reorg moves the canonical head, built blocks only go to new heads.
+// the block builder doesn't execute anything, rolling state back is up to whoever listens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadChange {
+    pub old_head: Option<B256>,
+    pub new_head: B256,
+    // no longer canonical, highest first
+    pub removed: Vec<B256>,
+    // newly canonical, lowest first
+    pub added: Vec<B256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    // already canonical or waiting on a side branch
+    Known,
+    // kept on a side branch, the head didn't move
+    Side,
+    Canonical(HeadChange),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn test_longest_chain() {
+        let block =
+            |number: u64| Block::new(U256::from(number), B256::ZERO, 0, Vec::new(), Address::ZERO);
+
+        assert!(LongestChain.prefer(&block(0), None));
+        assert!(LongestChain.prefer(&block(3), Some(&block(2))));
+        assert!(!LongestChain.prefer(&block(2), Some(&block(2))));
+        assert!(!LongestChain.prefer(&block(1), Some(&block(2))));
+    }
+}
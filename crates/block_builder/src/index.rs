@@ -0,0 +1,262 @@
+// where each canonical tx is: its hash maps to its block and position there, and every address
+// to the txs it sent or received, in chain order, see AddressIndex. Kept in memory, the block
+// builder rebuilds it from its store on start and follows reorgs
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use alloy::primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+
+use crate::Block;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxLocation {
+    pub block_number: u64,
+    pub index: usize,
+}
+
+// which side of a tx an address is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+    // sent to itself
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTx {
+    pub location: TxLocation,
+    pub tx_hash: B256,
+    pub direction: Direction,
+}
+
+// every address's txs in chain order, for listing an account's deposits and payments
+#[derive(Debug, Default)]
+pub struct AddressIndex {
+    // blocks are only added and removed at the head, so each list stays sorted
+    by_address: HashMap<Address, Vec<AddressTx>>,
+}
+
+impl AddressIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `block` has to be the new head
+    pub fn insert_block(&mut self, block: &Block) {
+        let block_number = block.number.saturating_to();
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let entry = |direction| AddressTx {
+                location: TxLocation {
+                    block_number,
+                    index,
+                },
+                tx_hash: B256::from_slice(tx.tx_hash().as_ref()),
+                direction,
+            };
+            let (from, to) = (tx.from(), tx.to());
+            if from == to {
+                self.push(from, entry(Direction::Both));
+            } else {
+                self.push(from, entry(Direction::Outgoing));
+                self.push(to, entry(Direction::Incoming));
+            }
+        }
+    }
+
+    // `block` has to be the head, it drops out of the index along with anything above it
+    pub fn remove_block(&mut self, block: &Block) {
+        let block_number: u64 = block.number.saturating_to();
+        for tx in &block.transactions {
+            for address in [tx.from(), tx.to()] {
+                if let Some(txs) = self.by_address.get_mut(&address) {
+                    let kept = txs.partition_point(|tx| tx.location.block_number < block_number);
+                    txs.truncate(kept);
+                    if txs.is_empty() {
+                        self.by_address.remove(&address);
+                    }
+                }
+            }
+        }
+    }
+
+    // the txs `address` sent or received in blocks `blocks`, oldest first
+    pub fn get(&self, address: &Address, blocks: Range<u64>) -> &[AddressTx] {
+        let Some(txs) = self.by_address.get(address) else {
+            return &[];
+        };
+        let start = txs.partition_point(|tx| tx.location.block_number < blocks.start);
+        let end = txs.partition_point(|tx| tx.location.block_number < blocks.end);
+        &txs[start..end.max(start)]
+    }
+
+    fn push(&mut self, address: Address, tx: AddressTx) {
+        self.by_address.entry(address).or_default().push(tx);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TxIndex {
+    by_hash: HashMap<B256, TxLocation>,
+    addresses: AddressIndex,
+}
+
+impl TxIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `block` has to be the new head
+    pub fn insert_block(&mut self, block: &Block) {
+        let block_number = block.number.saturating_to();
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let location = TxLocation {
+                block_number,
+                index,
+            };
+            self.by_hash
+                .insert(B256::from_slice(tx.tx_hash().as_ref()), location);
+        }
+        self.addresses.insert_block(block);
+    }
+
+    // `block` has to be the head, it drops out of the index along with anything above it
+    pub fn remove_block(&mut self, block: &Block) {
+        let block_number: u64 = block.number.saturating_to();
+        for tx in &block.transactions {
+            let hash = B256::from_slice(tx.tx_hash().as_ref());
+            // a tx can be in a block once only, but an older one may hold the same tx too
+            if self
+                .by_hash
+                .get(&hash)
+                .is_some_and(|location| location.block_number == block_number)
+            {
+                self.by_hash.remove(&hash);
+            }
+        }
+        self.addresses.remove_block(block);
+    }
+
+    pub fn get(&self, hash: &B256) -> Option<TxLocation> {
+        self.by_hash.get(hash).copied()
+    }
+
+    // the txs `address` sent or received in blocks `blocks`, oldest first
+    pub fn by_address(&self, address: &Address, blocks: Range<u64>) -> Vec<TxLocation> {
+        self.addresses
+            .get(address, blocks)
+            .iter()
+            .map(|tx| tx.location)
+            .collect()
+    }
+
+    pub fn addresses(&self) -> &AddressIndex {
+        &self.addresses
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use tx::tx::Tx;
+
+    fn block(number: u64, txs: Vec<Tx>) -> Block {
+        Block::new(U256::from(number), B256::ZERO, 0, txs, Address::ZERO)
+    }
+
+    #[test]
+    fn test_tx_index() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let carol = Address::repeat_byte(3);
+        let pay = |from, to, amount: u64| Tx::new(from, to, U256::from(amount), None);
+
+        let mut index = TxIndex::new();
+        let blocks = [
+            block(0, vec![pay(alice, bob, 1), pay(bob, carol, 2)]),
+            block(1, vec![pay(carol, alice, 3)]),
+            block(2, vec![pay(alice, alice, 4)]),
+        ];
+        for block in &blocks {
+            index.insert_block(block);
+        }
+        assert_eq!(index.len(), 4);
+        let hash = |tx: &Tx| B256::from_slice(tx.tx_hash().as_ref());
+        assert_eq!(
+            index.get(&hash(&blocks[0].transactions[1])),
+            Some(TxLocation {
+                block_number: 0,
+                index: 1
+            })
+        );
+
+        let numbers = |locations: Vec<TxLocation>| -> Vec<(u64, usize)> {
+            locations
+                .into_iter()
+                .map(|l| (l.block_number, l.index))
+                .collect()
+        };
+        // A transfer to self is listed once
+        assert_eq!(
+            numbers(index.by_address(&alice, 0..u64::MAX)),
+            vec![(0, 0), (1, 0), (2, 0)]
+        );
+        assert_eq!(numbers(index.by_address(&alice, 1..2)), vec![(1, 0)]);
+        assert!(index.by_address(&bob, 1..3).is_empty());
+        assert!(index.by_address(&alice, 3..u64::MAX).is_empty());
+
+        // Reorged out, from the head down
+        index.remove_block(&blocks[2]);
+        index.remove_block(&blocks[1]);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get(&hash(&blocks[1].transactions[0])), None);
+        assert_eq!(numbers(index.by_address(&alice, 0..u64::MAX)), vec![(0, 0)]);
+        assert_eq!(numbers(index.by_address(&carol, 0..u64::MAX)), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_address_index() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let pay = |from, to| Tx::new(from, to, U256::from(1), None);
+
+        let mut index = AddressIndex::new();
+        let first = block(0, vec![pay(alice, bob), pay(bob, alice)]);
+        index.insert_block(&first);
+        index.insert_block(&block(1, vec![pay(alice, alice)]));
+
+        let directions = |address| -> Vec<Direction> {
+            index
+                .get(&address, 0..2)
+                .iter()
+                .map(|tx| tx.direction)
+                .collect()
+        };
+        assert_eq!(
+            directions(alice),
+            vec![Direction::Outgoing, Direction::Incoming, Direction::Both]
+        );
+        assert_eq!(
+            directions(bob),
+            vec![Direction::Incoming, Direction::Outgoing]
+        );
+        assert_eq!(
+            index.get(&bob, 0..1)[1].tx_hash,
+            B256::from_slice(first.transactions[1].tx_hash().as_ref())
+        );
+    }
+}
@@ -1,13 +1,44 @@
-use alloy::primitives::{Address, B256, U256};
+use alloy::primitives::{
+    Address, Bloom, BloomInput, PrimitiveSignature, B256, BLOOM_SIZE_BYTES, U256,
+};
+use alloy::rlp::{Encodable, Header};
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
 use bytes::Bytes;
+use fork::{ForkChoice, HeadChange, ImportOutcome, LongestChain};
+use index::{AddressTx, TxIndex, TxLocation};
+use limits::{block_gas, BlockLimits};
+use merkle::{merkle_root, receipts_root, InclusionProof, ReceiptProof};
+use producers::ProducerSet;
+use receipts::{block_receipts, StoredReceipt};
+use schedule::ProducerSchedule;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tx::tx::Tx;
+use store::{BlockStore, MemoryBlockStore};
+use tokio::sync::{broadcast, RwLock};
+use tx::{
+    log::{logs_bloom, Log},
+    tx::Tx,
+};
 
-#[derive(Debug, Clone)]
+pub mod fork;
+pub mod index;
+pub mod limits;
+pub mod merkle;
+pub mod producers;
+pub mod prune;
+pub mod receipts;
+pub mod schedule;
+pub mod store;
+
+// how many new heads a slow subscriber can fall behind before it starts missing them
+const NEW_HEADS_CHANNEL_CAPACITY: usize = 256;
+// how far ahead of our clock an imported block may be
+const MAX_FUTURE_DRIFT_SECS: u64 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub number: U256,
     pub hash: B256,
@@ -15,13 +46,33 @@ pub struct Block {
     pub nonce: u64,
     pub timestamp: u64,
     pub transactions: Vec<Tx>,
+    // the merkle root of the tx hashes, what the header commits to instead of the txs. See
+    // merkle.rs
+    #[serde(default)]
+    pub tx_root: B256,
     pub state_root: B256,
     pub receipts_root: B256,
     pub logs_bloom: Bytes,
+    // every address the transactions touch, unlike the logs bloom it also covers txs that don't
+    // log anything. Wallets syncing their history skip the blocks it rules out
+    #[serde(default)]
+    pub address_bloom: Bloom,
     pub gas_used: U256,
     pub gas_limit: U256,
     pub base_fee_per_gas: Option<U256>,
     pub miner: Address,
+    // producers sign the hash, so the signatures aren't part of it and co-signers can add theirs
+    // after the block is sealed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<PrimitiveSignature>,
+    // set on a block whose txs and receipts were pruned, see prune.rs. Only its header is left,
+    // it is never served or imported as a whole block. Not part of the hash
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pruned: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
 }
 
 impl Block {
@@ -32,50 +83,328 @@ impl Block {
         transactions: Vec<Tx>,
         miner: Address,
     ) -> Self {
-        let mut hasher = Keccak256::new();
-        hasher.update(number.to_be_bytes::<32>());
-        hasher.update(parent_hash.as_slice());
-        hasher.update(timestamp.to_be_bytes());
-        hasher.update(miner.as_slice());
-
-        for tx in &transactions {
-            hasher.update(tx.tx_hash());
-        }
-
-        let hash = B256::from_slice(&hasher.finalize());
-
-        Self {
+        let address_bloom = address_bloom(&transactions);
+        let tx_hashes: Vec<B256> = transactions
+            .iter()
+            .map(|tx| B256::from_slice(&tx.tx_hash()))
+            .collect();
+        let mut block = Self {
             number,
-            hash,
+            hash: B256::ZERO,
             parent_hash,
             nonce: 0,
             timestamp,
             transactions,
+            tx_root: merkle_root(&tx_hashes),
             state_root: B256::ZERO,
             receipts_root: B256::ZERO,
             logs_bloom: Bytes::new(),
+            address_bloom,
             gas_used: U256::ZERO,
             gas_limit: U256::from(30_000_000),
             base_fee_per_gas: Some(U256::from(1_000_000_000)),
             miner,
+            signatures: Vec::new(),
+            pruned: false,
+        };
+        block.hash = block.compute_hash();
+        block
+    }
+
+    // the state after executing the block, it is part of the hash
+    pub fn with_state_root(mut self, state_root: B256) -> Self {
+        self.state_root = state_root;
+        self.hash = self.compute_hash();
+        self
+    }
+
+    // the gas the txs use, see limits.rs, and the most the block could have held
+    pub fn with_gas(mut self, gas_used: U256, gas_limit: U256) -> Self {
+        self.gas_used = gas_used;
+        self.gas_limit = gas_limit;
+        self.hash = self.compute_hash();
+        self
+    }
+
+    pub fn compute_hash(&self) -> B256 {
+        B256::from_slice(&Keccak256::digest(self.encode_header()))
+    }
+
+    // the canonical encoding of everything the block commits to, an RLP list of every header
+    // field with the tx root standing in for the txs. The base fee comes last and is left out
+    // when there is none, like in ethereum headers. Signatures aren't part of it, they sign it
+    pub fn encode_header(&self) -> Vec<u8> {
+        let fields: [&dyn Encodable; 12] = [
+            &self.number,
+            &self.parent_hash,
+            &self.nonce,
+            &self.timestamp,
+            &self.tx_root,
+            &self.state_root,
+            &self.receipts_root,
+            &self.logs_bloom,
+            &self.address_bloom,
+            &self.gas_used,
+            &self.gas_limit,
+            &self.miner,
+        ];
+        let base_fee = self
+            .base_fee_per_gas
+            .as_ref()
+            .map(|fee| fee as &dyn Encodable);
+
+        let payload_length = fields
+            .iter()
+            .chain(base_fee.iter())
+            .map(|field| field.length())
+            .sum();
+        let mut out = Vec::new();
+        Header {
+            list: true,
+            payload_length,
+        }
+        .encode(&mut out);
+        for field in fields.iter().chain(base_fee.iter()) {
+            field.encode(&mut out);
+        }
+        out
+    }
+
+    pub fn tx_hashes(&self) -> Vec<B256> {
+        self.transactions
+            .iter()
+            .map(|tx| B256::from_slice(&tx.tx_hash()))
+            .collect()
+    }
+
+    // the root of the txs the block holds, a valid block has it as its `tx_root`
+    pub fn transactions_root(&self) -> B256 {
+        merkle_root(&self.tx_hashes())
+    }
+
+    // None when the block has no tx at `index`
+    pub fn proof_for_tx(&self, index: usize) -> Option<InclusionProof> {
+        InclusionProof::new(&self.tx_hashes(), index)
+    }
+
+    // checks the proof against the header alone, the txs aren't needed
+    pub fn verify_tx_inclusion(&self, proof: &InclusionProof) -> bool {
+        proof.verify(self.tx_root)
+    }
+
+    // the receipts root of blocks sealed with their logs, it is part of the hash
+    pub fn with_receipts_root(mut self, receipts_root: B256) -> Self {
+        self.receipts_root = receipts_root;
+        self.hash = self.compute_hash();
+        self
+    }
+
+    // None when the block has no tx at `index`, `logs` are those of every tx of the block
+    pub fn receipt_proof(&self, logs: &[Vec<Log>], index: usize) -> Option<ReceiptProof> {
+        let tx_hashes = self.tx_hashes();
+        let receipts: Vec<B256> = tx_hashes
+            .iter()
+            .zip(logs)
+            .map(|(tx_hash, logs)| merkle::receipt_hash(tx_hash, logs))
+            .collect();
+        Some(ReceiptProof {
+            tx_hash: *tx_hashes.get(index)?,
+            logs: logs.get(index)?.clone(),
+            proof: InclusionProof::new(&receipts, index)?,
+        })
+    }
+
+    pub fn verify_receipt_inclusion(&self, proof: &ReceiptProof) -> bool {
+        proof.verify(self.receipts_root)
+    }
+
+    // the block without its txs, all a light client keeps. Its hash still checks out
+    pub fn header(&self) -> Block {
+        Block {
+            transactions: Vec::new(),
+            pruned: false,
+            ..self.clone()
+        }
+    }
+
+    // the header a store keeps once it prunes the block
+    pub fn pruned_header(&self) -> Block {
+        Block {
+            pruned: true,
+            ..self.header()
         }
     }
+
+    pub fn sign(&self, signer: &PrivateKeySigner) -> anyhow::Result<PrimitiveSignature> {
+        Ok(signer.sign_hash_sync(&self.hash)?)
+    }
+
+    pub fn with_signature(mut self, signature: PrimitiveSignature) -> Self {
+        self.signatures.push(signature);
+        self
+    }
+
+    // the bloom of the logs its txs emitted, it is part of the hash
+    pub fn with_logs_bloom(mut self, bloom: Bloom) -> Self {
+        self.logs_bloom = Bytes::copy_from_slice(bloom.as_slice());
+        self.hash = self.compute_hash();
+        self
+    }
+
+    // None for blocks from before logs, which have to be searched without it
+    pub fn logs_bloom_filter(&self) -> Option<Bloom> {
+        (self.logs_bloom.len() == BLOOM_SIZE_BYTES).then(|| Bloom::from_slice(&self.logs_bloom))
+    }
+
+    // whoever signed the block, signatures nothing can be recovered from are left out
+    pub fn signers(&self) -> Vec<Address> {
+        self.signatures
+            .iter()
+            .filter_map(|signature| signature.recover_address_from_prehash(&self.hash).ok())
+            .collect()
+    }
+
+    // false when none of the transactions touch `address`, true can be a false positive
+    pub fn may_involve(&self, address: &Address) -> bool {
+        self.address_bloom
+            .contains_input(BloomInput::Raw(address.as_slice()))
+    }
+}
+
+pub fn address_bloom(transactions: &[Tx]) -> Bloom {
+    let mut bloom = Bloom::ZERO;
+    for tx in transactions {
+        for address in tx.touched_addresses() {
+            bloom.accrue(BloomInput::Raw(address.as_slice()));
+        }
+    }
+    bloom
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct BlockBuilder {
-    blocks: Arc<RwLock<HashMap<U256, Block>>>,
-    blocks_by_hash: Arc<RwLock<HashMap<B256, Block>>>,
+    store: Arc<dyn BlockStore>,
+    // the number the next block gets, also serializes block creation
     latest_block_number: Arc<RwLock<U256>>,
+    new_heads: broadcast::Sender<Block>,
+    // imported blocks that aren't on the canonical chain, by hash
+    side_blocks: Arc<RwLock<HashMap<B256, Block>>>,
+    fork_choice: Arc<dyn ForkChoice>,
+    head_changes: broadcast::Sender<HeadChange>,
+    // imported blocks need signatures of enough of them, None accepts unsigned blocks
+    producers: Option<ProducerSet>,
+    // who has to sign each block in turn, None when any producer may sign any block
+    schedule: Option<ProducerSchedule>,
+    // signs the blocks this builder creates
+    signer: Option<PrivateKeySigner>,
+    // the logs of each tx of blocks that aren't canonical, by block hash: side blocks, and
+    // blocks executed before they are imported. Canonical blocks have theirs stored with them,
+    // see receipts.rs
+    logs: Arc<RwLock<HashMap<B256, Vec<Vec<Log>>>>>,
+    // where the canonical txs are, see index.rs
+    tx_index: Arc<RwLock<TxIndex>>,
+    // the blocks below it are already pruned, see prune.rs
+    pruned_below: Arc<RwLock<U256>>,
+    // what the blocks this builder creates may hold
+    limits: BlockLimits,
+}
+
+impl Default for BlockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BlockBuilder {
     pub fn new() -> Self {
-        Self {
-            blocks: Arc::new(RwLock::new(HashMap::new())),
-            blocks_by_hash: Arc::new(RwLock::new(HashMap::new())),
-            latest_block_number: Arc::new(RwLock::new(U256::ZERO)),
+        Self::with_store(MemoryBlockStore::new()).unwrap()
+    }
+
+    // picks up after the latest block already in `store`
+    pub fn with_store(store: impl BlockStore + 'static) -> anyhow::Result<Self> {
+        let (new_heads, _) = broadcast::channel(NEW_HEADS_CHANNEL_CAPACITY);
+        let (head_changes, _) = broadcast::channel(NEW_HEADS_CHANNEL_CAPACITY);
+        let next_number = match store.latest_number()? {
+            Some(latest) => latest + U256::from(1),
+            None => U256::ZERO,
+        };
+        let mut tx_index = TxIndex::new();
+        let mut number = U256::ZERO;
+        while number < next_number {
+            if let Some(block) = store.get_by_number(number)? {
+                tx_index.insert_block(&block);
+            }
+            number += U256::from(1);
         }
+
+        Ok(Self {
+            store: Arc::new(store),
+            latest_block_number: Arc::new(RwLock::new(next_number)),
+            new_heads,
+            side_blocks: Arc::new(RwLock::new(HashMap::new())),
+            fork_choice: Arc::new(LongestChain),
+            head_changes,
+            producers: None,
+            schedule: None,
+            signer: None,
+            logs: Arc::new(RwLock::new(HashMap::new())),
+            tx_index: Arc::new(RwLock::new(tx_index)),
+            pruned_below: Arc::new(RwLock::new(U256::ZERO)),
+            limits: BlockLimits::default(),
+        })
+    }
+
+    pub fn with_fork_choice(mut self, fork_choice: impl ForkChoice + 'static) -> Self {
+        self.fork_choice = Arc::new(fork_choice);
+        self
+    }
+
+    pub fn with_producers(mut self, producers: ProducerSet) -> Self {
+        self.producers = Some(producers);
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: ProducerSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    pub fn with_signer(mut self, signer: PrivateKeySigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    // blocks over the limits aren't created, see limits.rs for fitting txs in them
+    pub fn with_limits(mut self, limits: BlockLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &BlockLimits {
+        &self.limits
+    }
+
+    pub fn producers(&self) -> Option<&ProducerSet> {
+        self.producers.as_ref()
+    }
+
+    pub fn schedule(&self) -> Option<&ProducerSchedule> {
+        self.schedule.as_ref()
+    }
+
+    // whether it's `producer`'s turn to produce the next block, always without a schedule
+    pub async fn is_due(&self, producer: &Address) -> anyhow::Result<bool> {
+        let Some(schedule) = &self.schedule else {
+            return Ok(true);
+        };
+        let next_number = *self.latest_block_number.read().await;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let parent_timestamp = self
+            .head(next_number)?
+            .map_or(now, |parent| parent.timestamp);
+        Ok(schedule.may_produce(producer, next_number.saturating_to(), parent_timestamp, now))
     }
 
     pub async fn create_block(
@@ -83,20 +412,48 @@ impl BlockBuilder {
         transactions: Vec<Tx>,
         miner: Address,
     ) -> anyhow::Result<Block> {
-        let mut blocks = self.blocks.write().await;
-        let mut blocks_by_hash = self.blocks_by_hash.write().await;
+        self.create_block_with_state_root(transactions, miner, B256::ZERO)
+            .await
+    }
+
+    pub async fn create_block_with_state_root(
+        &self,
+        transactions: Vec<Tx>,
+        miner: Address,
+        state_root: B256,
+    ) -> anyhow::Result<Block> {
+        self.create_block_with_logs(transactions, miner, state_root, Vec::new())
+            .await
+    }
+
+    // `logs` line up with the transactions, each tx's logs in the order it emitted them
+    #[tracing::instrument(
+        name = "create_block",
+        skip_all,
+        fields(number = tracing::field::Empty, txs = transactions.len())
+    )]
+    pub async fn create_block_with_logs(
+        &self,
+        transactions: Vec<Tx>,
+        miner: Address,
+        state_root: B256,
+        logs: Vec<Vec<Log>>,
+    ) -> anyhow::Result<Block> {
+        self.limits.check(&transactions)?;
+        let gas_used = U256::from(block_gas(&transactions));
         let mut latest_number = self.latest_block_number.write().await;
+        tracing::Span::current().record("number", latest_number.to::<u64>());
 
         let parent_hash = if *latest_number == U256::ZERO {
             B256::ZERO
         } else {
-            blocks
-                .get(&(*latest_number - U256::from(1)))
+            self.store
+                .get_by_number(*latest_number - U256::from(1))?
                 .map(|block| block.hash)
                 .unwrap_or(B256::ZERO)
         };
 
-        let block = Block::new(
+        let mut block = Block::new(
             *latest_number,
             parent_hash,
             std::time::SystemTime::now()
@@ -105,23 +462,44 @@ impl BlockBuilder {
                 .as_secs(),
             transactions,
             miner,
-        );
+        )
+        .with_gas(gas_used, U256::from(self.limits.gas_limit))
+        .with_state_root(state_root);
+        if !logs.is_empty() {
+            let receipts_root = receipts_root(&block.tx_hashes(), &logs);
+            block = block
+                .with_logs_bloom(logs_bloom(logs.iter().flatten()))
+                .with_receipts_root(receipts_root);
+        }
+        if let Some(signer) = &self.signer {
+            let signature = block.sign(signer)?;
+            block = block.with_signature(signature);
+        }
 
-        blocks.insert(*latest_number, block.clone());
-        blocks_by_hash.insert(block.hash, block.clone());
+        self.store
+            .put_with_receipts(&block, &block_receipts(&block, &logs))?;
+        self.tx_index.write().await.insert_block(&block);
         *latest_number += U256::from(1);
+        tracing::debug!(hash = %block.hash, "block created");
+
+        // nobody listening for new heads is not an error
+        let _ = self.new_heads.send(block.clone());
 
         Ok(block)
     }
 
     pub async fn get_block(&self, number: U256) -> Option<Block> {
-        let blocks = self.blocks.read().await;
-        blocks.get(&number).cloned()
+        self.store.get_by_number(number).unwrap_or_else(|e| {
+            tracing::warn!(%number, error = %e, "failed to read the block");
+            None
+        })
     }
 
     pub async fn get_block_by_hash(&self, hash: B256) -> Option<Block> {
-        let blocks = self.blocks_by_hash.read().await;
-        blocks.get(&hash).cloned()
+        self.store.get_by_hash(hash).unwrap_or_else(|e| {
+            tracing::warn!(block = %hash, error = %e, "failed to read the block");
+            None
+        })
     }
 
     pub async fn get_latest_block(&self) -> Option<Block> {
@@ -136,12 +514,399 @@ impl BlockBuilder {
     pub async fn get_latest_block_number(&self) -> U256 {
         *self.latest_block_number.read().await
     }
+
+    // for blocks built elsewhere, recorded once they've been executed. A canonical block's are
+    // stored as its receipts, the others' kept until a reorg makes them canonical
+    pub async fn record_logs(&self, hash: B256, logs: Vec<Vec<Log>>) {
+        // held so the block can't be reorged away in between
+        let _next_number = self.latest_block_number.read().await;
+        let stored = match self.store.get_by_hash(hash) {
+            Ok(Some(block)) => self
+                .store
+                .put_receipts(block.number, &block_receipts(&block, &logs)),
+            Ok(None) => {
+                self.logs.write().await.insert(hash, logs);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = stored {
+            tracing::error!(block = %hash, error = %e, "failed to store the receipts");
+        }
+    }
+
+    // the logs of each tx of block `hash`, empty when it wasn't executed here
+    pub async fn get_logs(&self, hash: B256) -> Vec<Vec<Log>> {
+        if let Some(logs) = self.logs.read().await.get(&hash) {
+            return logs.clone();
+        }
+        self.get_block_receipts(hash)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|receipt| receipt.logs)
+            .collect()
+    }
+
+    // the receipts of canonical block `hash`, None when it isn't one or wasn't executed here
+    pub async fn get_block_receipts(&self, hash: B256) -> Option<Vec<StoredReceipt>> {
+        let block = self.get_block_by_hash(hash).await?;
+        self.store
+            .get_receipts(block.number)
+            .unwrap_or_else(|e| {
+                tracing::warn!(block = %hash, error = %e, "failed to read the receipts");
+                None
+            })
+            // stored receipts go with the block at their number, but it may have just been
+            // replaced
+            .filter(|receipts| receipts.iter().all(|receipt| receipt.block_hash == hash))
+    }
+
+    // the receipt of canonical tx `hash`
+    pub async fn get_receipt(&self, hash: B256) -> Option<StoredReceipt> {
+        self.store.get_receipt(hash).unwrap_or_else(|e| {
+            tracing::warn!(tx = %hash, error = %e, "failed to read the receipt");
+            None
+        })
+    }
+
+    // the canonical block holding tx `hash` and its index there
+    pub async fn get_tx_location(&self, hash: B256) -> Option<TxLocation> {
+        self.tx_index.read().await.get(&hash)
+    }
+
+    // the canonical txs `address` sent or received in blocks `blocks`, oldest first
+    pub async fn get_txs_by_address(
+        &self,
+        address: &Address,
+        blocks: Range<u64>,
+    ) -> Vec<TxLocation> {
+        self.tx_index.read().await.by_address(address, blocks)
+    }
+
+    // like get_txs_by_address, with the hashes and which side of each tx `address` is on
+    pub async fn get_address_txs(&self, address: &Address, blocks: Range<u64>) -> Vec<AddressTx> {
+        self.tx_index
+            .read()
+            .await
+            .addresses()
+            .get(address, blocks)
+            .to_vec()
+    }
+
+    // the canonical block holding tx `hash`, with its index there
+    pub async fn get_transaction(&self, hash: B256) -> Option<(Block, usize)> {
+        let location = self.get_tx_location(hash).await?;
+        let block = self.get_block(U256::from(location.block_number)).await?;
+        Some((block, location.index))
+    }
+
+    pub fn subscribe_new_heads(&self) -> broadcast::Receiver<Block> {
+        self.new_heads.subscribe()
+    }
+
+    pub fn subscribe_head_changes(&self) -> broadcast::Receiver<HeadChange> {
+        self.head_changes.subscribe()
+    }
+
+    // adds a block built elsewhere, it becomes the head if the fork choice rule prefers it
+    pub async fn import_block(&self, block: Block) -> anyhow::Result<ImportOutcome> {
+        let mut next_number = self.latest_block_number.write().await;
+
+        if self.find_block(block.hash).await?.is_some() {
+            return Ok(ImportOutcome::Known);
+        }
+        self.validate_header(&block).await?;
+
+        let head = self.head(*next_number)?;
+        let preferred = self.fork_choice.prefer(&block, head.as_ref());
+        let hash = block.hash;
+        self.side_blocks.write().await.insert(hash, block);
+        if !preferred {
+            return Ok(ImportOutcome::Side);
+        }
+        Ok(ImportOutcome::Canonical(
+            self.reorg(&mut next_number, hash).await?,
+        ))
+    }
+
+    // adds another producer's signature to a block this builder has, canonical or not, e.g. the
+    // backup's to one the primary produced
+    pub async fn cosign_block(
+        &self,
+        hash: B256,
+        signature: PrimitiveSignature,
+    ) -> anyhow::Result<Block> {
+        // held so the block can't be reorged away in between
+        let _next_number = self.latest_block_number.write().await;
+        let block = self
+            .find_block(hash)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("unknown block {}", hash))?;
+        let signer = signature
+            .recover_address_from_prehash(&hash)
+            .map_err(|e| anyhow::anyhow!("invalid signature for block {}: {}", hash, e))?;
+        if let Some(producers) = &self.producers {
+            if !producers.contains(&signer) {
+                anyhow::bail!("{} is not a block producer", signer);
+            }
+        }
+        if block.signers().contains(&signer) {
+            return Ok(block);
+        }
+
+        let block = block.with_signature(signature);
+        if self.store.get_by_hash(hash)?.is_some() {
+            self.store.put(&block)?;
+        } else {
+            self.side_blocks.write().await.insert(hash, block.clone());
+        }
+        Ok(block)
+    }
+
+    // everything about a block that can be checked without executing it: its hash, address
+    // bloom and producer signatures, its place after its parent and its timestamp
+    pub async fn validate_header(&self, block: &Block) -> anyhow::Result<()> {
+        if block.pruned {
+            anyhow::bail!("block {} is a pruned header, not a whole block", block.hash);
+        }
+        let expected = block.compute_hash();
+        if expected != block.hash {
+            anyhow::bail!("block {} should have hash {}", block.hash, expected);
+        }
+        if block.transactions_root() != block.tx_root {
+            anyhow::bail!(
+                "block {} doesn't hold the txs its header commits to",
+                block.hash
+            );
+        }
+        if let Some(producers) = &self.producers {
+            let signed = producers.count(&block.signers());
+            if signed < producers.threshold() {
+                anyhow::bail!(
+                    "block {} is signed by {} of the producers, {} needed",
+                    block.hash,
+                    signed,
+                    producers.threshold()
+                );
+            }
+        }
+        // not hashed, it only depends on the transactions
+        if block.address_bloom != address_bloom(&block.transactions) {
+            anyhow::bail!("block {} has the wrong address bloom", block.hash);
+        }
+        // blocks from before gas was counted claim none
+        let gas_used = U256::from(block_gas(&block.transactions));
+        if block.gas_used != U256::ZERO && block.gas_used != gas_used {
+            anyhow::bail!(
+                "block {} claims {} gas used but its txs use {}",
+                block.hash,
+                block.gas_used,
+                gas_used
+            );
+        }
+        if gas_used > block.gas_limit {
+            anyhow::bail!(
+                "block {} uses {} gas, over its limit of {}",
+                block.hash,
+                gas_used,
+                block.gas_limit
+            );
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        if block.timestamp > now + MAX_FUTURE_DRIFT_SECS {
+            anyhow::bail!(
+                "block {} is from the future, timestamp {}",
+                block.hash,
+                block.timestamp
+            );
+        }
+
+        if block.number == U256::ZERO {
+            if block.parent_hash != B256::ZERO {
+                anyhow::bail!("block {} is numbered 0 but has a parent", block.hash);
+            }
+            // there's no parent to time out after, only the scheduled producer can start a chain
+            return self.check_schedule(block, block.timestamp);
+        }
+        let parent = self.find_block(block.parent_hash).await?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "parent {} of block {} is unknown",
+                block.parent_hash,
+                block.hash
+            )
+        })?;
+        if parent.number + U256::from(1) != block.number {
+            anyhow::bail!(
+                "block {} is numbered {} but its parent is {}",
+                block.hash,
+                block.number,
+                parent.number
+            );
+        }
+        if block.timestamp < parent.timestamp {
+            anyhow::bail!(
+                "block {} is older than its parent, {} < {}",
+                block.hash,
+                block.timestamp,
+                parent.timestamp
+            );
+        }
+        self.check_schedule(block, parent.timestamp)
+    }
+
+    fn check_schedule(&self, block: &Block, parent_timestamp: u64) -> anyhow::Result<()> {
+        let Some(schedule) = &self.schedule else {
+            return Ok(());
+        };
+        let number = block.number.saturating_to();
+        if !block
+            .signers()
+            .iter()
+            .any(|signer| schedule.may_produce(signer, number, parent_timestamp, block.timestamp))
+        {
+            anyhow::bail!(
+                "block {} isn't signed by its scheduled producer {} or a fallback",
+                block.hash,
+                schedule.producer_for(number, 0)
+            );
+        }
+        Ok(())
+    }
+
+    // drops the txs and receipts of the canonical blocks below `below`, keeping their headers. See
+    // prune.rs for what the caller must not prune. Returns how many blocks it pruned
+    pub async fn prune(&self, below: U256) -> anyhow::Result<u64> {
+        // held so no reorg moves blocks in or out of the store in between
+        let next_number = self.latest_block_number.read().await;
+        let below = below.min(*next_number);
+        let mut pruned_below = self.pruned_below.write().await;
+        let mut tx_index = self.tx_index.write().await;
+        let mut pruned = 0;
+        while *pruned_below < below {
+            if let Some(block) = self.store.get_by_number(*pruned_below)? {
+                if self.store.prune(block.number)? {
+                    tx_index.remove_block(&block);
+                    pruned += 1;
+                }
+            }
+            *pruned_below += U256::from(1);
+        }
+        Ok(pruned)
+    }
+
+    // makes the known block `hash` the head, whatever the fork choice rule says
+    pub async fn reorg_to(&self, hash: B256) -> anyhow::Result<HeadChange> {
+        let mut next_number = self.latest_block_number.write().await;
+        self.reorg(&mut next_number, hash).await
+    }
+
+    // rewinds the canonical chain to `number`, the blocks above it move to a side branch
+    pub async fn set_head(&self, number: U256) -> anyhow::Result<HeadChange> {
+        let mut next_number = self.latest_block_number.write().await;
+        let block = self
+            .store
+            .get_by_number(number)?
+            .ok_or_else(|| anyhow::anyhow!("no canonical block {}", number))?;
+        self.reorg(&mut next_number, block.hash).await
+    }
+
+    async fn find_block(&self, hash: B256) -> anyhow::Result<Option<Block>> {
+        if let Some(block) = self.store.get_by_hash(hash)? {
+            return Ok(Some(block));
+        }
+        Ok(self.side_blocks.read().await.get(&hash).cloned())
+    }
+
+    fn head(&self, next_number: U256) -> anyhow::Result<Option<Block>> {
+        if next_number == U256::ZERO {
+            return Ok(None);
+        }
+        self.store.get_by_number(next_number - U256::from(1))
+    }
+
+    async fn reorg(&self, next_number: &mut U256, hash: B256) -> anyhow::Result<HeadChange> {
+        let old_head = self.head(*next_number)?.map(|block| block.hash);
+        let mut side_blocks = self.side_blocks.write().await;
+
+        // walk back from the new head until the branch joins the canonical chain, None when it
+        // doesn't even share the first block
+        let mut added = Vec::new();
+        let mut cursor = hash;
+        let fork_number = loop {
+            if let Some(block) = self.store.get_by_hash(cursor)? {
+                break Some(block.number);
+            }
+            let block = side_blocks
+                .get(&cursor)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("unknown block {}", cursor))?;
+            cursor = block.parent_hash;
+            let first = block.number == U256::ZERO;
+            added.push(block);
+            if first {
+                break None;
+            }
+        };
+        added.reverse();
+
+        let first_removed = fork_number.map_or(U256::ZERO, |number| number + U256::from(1));
+        let mut tx_index = self.tx_index.write().await;
+        let mut logs = self.logs.write().await;
+        let mut removed = Vec::new();
+        while *next_number > first_removed {
+            *next_number -= U256::from(1);
+            if let Some(block) = self.store.get_by_number(*next_number)? {
+                // the receipts leave the store with the block, its logs wait with it
+                if let Some(receipts) = self.store.get_receipts(*next_number)? {
+                    let block_logs = receipts.into_iter().map(|receipt| receipt.logs).collect();
+                    logs.insert(block.hash, block_logs);
+                }
+                self.store.remove(*next_number)?;
+                tx_index.remove_block(&block);
+                removed.push(block.hash);
+                side_blocks.insert(block.hash, block);
+            }
+        }
+        for block in &added {
+            match logs.remove(&block.hash) {
+                Some(block_logs) => self
+                    .store
+                    .put_with_receipts(block, &block_receipts(block, &block_logs))?,
+                None => self.store.put(block)?,
+            }
+            tx_index.insert_block(block);
+            side_blocks.remove(&block.hash);
+        }
+        *next_number = match added.last() {
+            Some(block) => block.number + U256::from(1),
+            None => first_removed,
+        };
+
+        let change = HeadChange {
+            old_head,
+            new_head: hash,
+            removed,
+            added: added.iter().map(|block| block.hash).collect(),
+        };
+        if old_head != Some(hash) {
+            let _ = self.head_changes.send(change.clone());
+            for block in added {
+                let _ = self.new_heads.send(block);
+            }
+        }
+        Ok(change)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use alloy::signers::local::PrivateKeySigner;
+    use receipts::ReceiptStore;
 
     #[tokio::test]
     async fn test_block_creation() {
@@ -166,6 +931,241 @@ mod tests {
         assert_eq!(block_builder.get_latest_block_number().await, U256::from(2));
     }
 
+    // A block with every header field set, and the same block with no txs or base fee
+    fn golden_blocks() -> (Block, Block) {
+        let mut block = Block::new(
+            U256::from(7),
+            B256::repeat_byte(1),
+            1_700_000_000,
+            vec![Tx::new(
+                Address::repeat_byte(2),
+                Address::repeat_byte(3),
+                U256::from(5),
+                None,
+            )],
+            Address::repeat_byte(4),
+        )
+        .with_state_root(B256::repeat_byte(5))
+        .with_logs_bloom(Bloom::repeat_byte(6));
+        block.receipts_root = B256::repeat_byte(7);
+        block.nonce = 8;
+        block.gas_used = U256::from(21_000);
+        block.hash = block.compute_hash();
+
+        let mut bare = Block::new(U256::ZERO, B256::ZERO, 0, Vec::new(), Address::ZERO);
+        bare.base_fee_per_gas = None;
+        bare.hash = bare.compute_hash();
+        (block, bare)
+    }
+
+    #[test]
+    fn test_header_golden_vectors() {
+        let (block, bare) = golden_blocks();
+        assert_eq!(
+            block.hash,
+            "0x3d411be6f2c1cd734600b491999fe122acc73f10f3ca9ed059f0063c23932859"
+                .parse::<B256>()
+                .unwrap()
+        );
+        assert_eq!(
+            bare.hash,
+            "0xf3fc8eb47ef3bec7beef1c9f794cbd3d661751112429e2bbc18698defdba8996"
+                .parse::<B256>()
+                .unwrap()
+        );
+
+        // Number, parent hash, nonce, timestamp, the tx, state and receipts roots, an empty logs
+        // bloom, then the address bloom
+        let encoded = bare.encode_header();
+        let mut expected = vec![0xf9, 0x01, 0xa6, 0x80, 0xa0];
+        expected.extend_from_slice(&[0; 32]);
+        expected.extend_from_slice(&[0x80, 0x80]);
+        for _ in 0..3 {
+            expected.push(0xa0);
+            expected.extend_from_slice(&[0; 32]);
+        }
+        expected.extend_from_slice(&[0x80, 0xb9, 0x01, 0x00]);
+        expected.extend_from_slice(&[0; 256]);
+        // Gas used, the 30M gas limit and the miner, no base fee
+        expected.extend_from_slice(&[0x80, 0x84, 0x01, 0xc9, 0xc3, 0x80, 0x94]);
+        expected.extend_from_slice(&[0; 20]);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_every_header_field_is_hashed() {
+        let (block, _) = golden_blocks();
+        let changes: Vec<fn(&mut Block)> = vec![
+            |b| b.number = U256::from(8),
+            |b| b.parent_hash = B256::repeat_byte(9),
+            |b| b.nonce = 9,
+            |b| b.timestamp += 1,
+            |b| b.tx_root = B256::ZERO,
+            |b| b.state_root = B256::ZERO,
+            |b| b.receipts_root = B256::ZERO,
+            |b| b.logs_bloom = Bytes::new(),
+            |b| b.address_bloom = Bloom::ZERO,
+            |b| b.gas_used = U256::ZERO,
+            |b| b.gas_limit = U256::from(1),
+            |b| b.base_fee_per_gas = None,
+            |b| b.miner = Address::ZERO,
+        ];
+        for change in changes {
+            let mut changed = block.clone();
+            change(&mut changed);
+            assert_ne!(changed.compute_hash(), block.hash);
+        }
+
+        // Signatures sign the hash rather than being part of it
+        let signer = PrivateKeySigner::random();
+        let signed = block.clone().with_signature(block.sign(&signer).unwrap());
+        assert_eq!(signed.compute_hash(), block.hash);
+    }
+
+    #[test]
+    fn test_tx_inclusion() {
+        let txs: Vec<Tx> = (1..=5)
+            .map(|i| Tx::new(Address::ZERO, Address::repeat_byte(i), U256::from(i), None))
+            .collect();
+        let block = Block::new(U256::ZERO, B256::ZERO, 0, txs, Address::ZERO);
+        assert_eq!(block.tx_root, block.transactions_root());
+
+        let proof = block.proof_for_tx(3).unwrap();
+        assert_eq!(
+            proof.leaf,
+            B256::from_slice(&block.transactions[3].tx_hash())
+        );
+        // A light client only needs the header
+        assert!(block.header().verify_tx_inclusion(&proof));
+
+        let other = Block::new(U256::ZERO, B256::ZERO, 0, Vec::new(), Address::ZERO);
+        assert!(!other.verify_tx_inclusion(&proof));
+        assert!(block.proof_for_tx(5).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_state_root_is_hashed() {
+        let block_builder = BlockBuilder::new();
+        let state_root = B256::repeat_byte(7);
+
+        let block = block_builder
+            .create_block_with_state_root(Vec::new(), Address::ZERO, state_root)
+            .await
+            .unwrap();
+        assert_eq!(block.state_root, state_root);
+        assert_eq!(block.hash, block.compute_hash());
+        assert_ne!(block.hash, block.clone().with_state_root(B256::ZERO).hash);
+    }
+
+    #[tokio::test]
+    async fn test_logs() {
+        let block_builder = BlockBuilder::new();
+        let empty = block_builder
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(empty.logs_bloom_filter(), None);
+        assert!(block_builder.get_logs(empty.hash).await.is_empty());
+
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let logs = vec![vec![Log::transfer(alice, bob, U256::from(5))]];
+        let block = block_builder
+            .create_block_with_logs(
+                vec![Tx::new(alice, bob, U256::from(5), None)],
+                Address::ZERO,
+                B256::ZERO,
+                logs.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(block.logs_bloom_filter(), Some(logs_bloom(&logs[0])));
+        assert_eq!(block_builder.get_logs(block.hash).await, logs);
+        assert_eq!(block.hash, block.compute_hash());
+        assert_ne!(block.hash, block.clone().with_logs_bloom(Bloom::ZERO).hash);
+
+        // The receipts root commits to the logs too
+        let proof = block.receipt_proof(&logs, 0).unwrap();
+        assert_eq!(proof.logs, logs[0]);
+        assert!(block.header().verify_receipt_inclusion(&proof));
+        assert!(!empty.verify_receipt_inclusion(&proof));
+    }
+
+    #[tokio::test]
+    async fn test_receipts_are_stored() {
+        let store = Arc::new(MemoryBlockStore::new());
+        let produced = BlockBuilder::with_store(store.clone()).unwrap();
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let logs = vec![vec![Log::transfer(alice, bob, U256::from(5))]];
+        let first = produced
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+        let block = produced
+            .create_block_with_logs(
+                vec![Tx::new(alice, bob, U256::from(5), None)],
+                Address::ZERO,
+                B256::ZERO,
+                logs.clone(),
+            )
+            .await
+            .unwrap();
+        let tx_hash = block.tx_hashes()[0];
+        assert_eq!(store.get_receipt(tx_hash).unwrap().unwrap().logs, logs[0]);
+        // Still there for a builder reopening the store
+        let reopened = BlockBuilder::with_store(store).unwrap();
+        assert_eq!(reopened.get_logs(block.hash).await, logs);
+
+        // Logs recorded before the import are stored once the block is canonical
+        let imported = BlockBuilder::new();
+        imported.import_block(first).await.unwrap();
+        imported.record_logs(block.hash, logs.clone()).await;
+        assert!(imported.get_receipt(tx_hash).await.is_none());
+        imported.import_block(block.clone()).await.unwrap();
+        let receipts = imported.get_block_receipts(block.hash).await.unwrap();
+        assert_eq!(
+            Some(receipts.clone()),
+            produced.get_block_receipts(block.hash).await
+        );
+        assert_eq!(
+            imported.get_receipt(tx_hash).await,
+            Some(receipts[0].clone())
+        );
+
+        // They leave the store when a reorg drops the block and come back with it
+        imported.set_head(U256::ZERO).await.unwrap();
+        assert!(imported.get_receipt(tx_hash).await.is_none());
+        assert_eq!(imported.get_logs(block.hash).await, logs);
+        imported.reorg_to(block.hash).await.unwrap();
+        assert_eq!(
+            imported.get_receipt(tx_hash).await,
+            Some(receipts[0].clone())
+        );
+    }
+
+    #[test]
+    fn test_address_bloom() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let carol = Address::repeat_byte(3);
+
+        let block = Block::new(
+            U256::ZERO,
+            B256::ZERO,
+            0,
+            vec![Tx::new(alice, bob, U256::from(10), None)],
+            carol,
+        );
+        assert!(block.may_involve(&alice));
+        assert!(block.may_involve(&bob));
+        // The miner isn't touched by the txs
+        assert!(!block.may_involve(&carol));
+
+        let empty = Block::new(U256::ZERO, B256::ZERO, 0, Vec::new(), carol);
+        assert_eq!(empty.address_bloom, Bloom::ZERO);
+        assert!(!empty.may_involve(&alice));
+    }
+
     #[tokio::test]
     async fn test_block_retrieval() {
         let block_builder = BlockBuilder::new();
@@ -182,4 +1182,379 @@ mod tests {
         assert_eq!(retrieved_by_hash.number, block.number);
         assert_eq!(retrieved_by_hash.hash, block.hash);
     }
+
+    #[tokio::test]
+    async fn test_resume_from_store() {
+        let miner = PrivateKeySigner::random().address();
+        let store = Arc::new(MemoryBlockStore::new());
+
+        let block_builder = BlockBuilder::with_store(store.clone()).unwrap();
+        block_builder.create_block(Vec::new(), miner).await.unwrap();
+        let last = block_builder.create_block(Vec::new(), miner).await.unwrap();
+
+        // A builder over the same store continues the chain
+        let resumed = BlockBuilder::with_store(store).unwrap();
+        assert_eq!(resumed.get_latest_block_number().await, U256::from(2));
+        let next = resumed.create_block(Vec::new(), miner).await.unwrap();
+        assert_eq!(next.parent_hash, last.hash);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_new_heads() {
+        let block_builder = BlockBuilder::new();
+        let miner = PrivateKeySigner::random().address();
+
+        let mut new_heads = block_builder.subscribe_new_heads();
+
+        let block1 = block_builder.create_block(Vec::new(), miner).await.unwrap();
+        let block2 = block_builder.create_block(Vec::new(), miner).await.unwrap();
+
+        // Heads are delivered in order
+        assert_eq!(new_heads.recv().await.unwrap().hash, block1.hash);
+        assert_eq!(new_heads.recv().await.unwrap().hash, block2.hash);
+    }
+
+    #[tokio::test]
+    async fn test_import_and_reorg() {
+        let block_builder = BlockBuilder::new();
+        let miner = Address::repeat_byte(1);
+        let mut head_changes = block_builder.subscribe_head_changes();
+
+        let a0 = block_builder.create_block(Vec::new(), miner).await.unwrap();
+        let a1 = block_builder.create_block(Vec::new(), miner).await.unwrap();
+        // A competing branch on top of a0
+        let b1 = Block::new(
+            U256::from(1),
+            a0.hash,
+            a0.timestamp,
+            Vec::new(),
+            Address::repeat_byte(2),
+        );
+        let b2 = Block::new(
+            U256::from(2),
+            b1.hash,
+            a0.timestamp,
+            Vec::new(),
+            Address::repeat_byte(2),
+        );
+
+        assert_eq!(
+            block_builder.import_block(a1.clone()).await.unwrap(),
+            ImportOutcome::Known
+        );
+        assert_eq!(
+            block_builder.import_block(b1.clone()).await.unwrap(),
+            ImportOutcome::Side
+        );
+        assert_eq!(
+            block_builder.get_block(U256::from(1)).await.unwrap().hash,
+            a1.hash
+        );
+
+        // The longer branch takes over
+        let change = match block_builder.import_block(b2.clone()).await.unwrap() {
+            ImportOutcome::Canonical(change) => change,
+            other => panic!("unexpected outcome {:?}", other),
+        };
+        assert_eq!(change.old_head, Some(a1.hash));
+        assert_eq!(change.removed, vec![a1.hash]);
+        assert_eq!(change.added, vec![b1.hash, b2.hash]);
+        assert_eq!(head_changes.recv().await.unwrap(), change);
+        assert_eq!(block_builder.get_latest_block_number().await, U256::from(3));
+        assert_eq!(
+            block_builder.get_block(U256::from(1)).await.unwrap().hash,
+            b1.hash
+        );
+        assert!(block_builder.get_block_by_hash(a1.hash).await.is_none());
+
+        let change = block_builder.reorg_to(a1.hash).await.unwrap();
+        assert_eq!(change.removed, vec![b2.hash, b1.hash]);
+        assert_eq!(change.added, vec![a1.hash]);
+        assert_eq!(block_builder.get_latest_block_number().await, U256::from(2));
+
+        let change = block_builder.set_head(U256::ZERO).await.unwrap();
+        assert_eq!(change.new_head, a0.hash);
+        assert_eq!(change.removed, vec![a1.hash]);
+
+        // Building continues from the rewound head
+        let next = block_builder.create_block(Vec::new(), miner).await.unwrap();
+        assert_eq!(next.number, U256::from(1));
+        assert_eq!(next.parent_hash, a0.hash);
+    }
+
+    #[tokio::test]
+    async fn test_tx_index() {
+        let store = Arc::new(MemoryBlockStore::new());
+        let block_builder = BlockBuilder::with_store(store.clone()).unwrap();
+        let miner = Address::repeat_byte(1);
+        let alice = Address::repeat_byte(2);
+        let pay = |amount: u64| Tx::new(alice, Address::repeat_byte(3), U256::from(amount), None);
+
+        let a0 = block_builder
+            .create_block(vec![pay(1), pay(2)], miner)
+            .await
+            .unwrap();
+        let a1 = block_builder
+            .create_block(vec![pay(3)], miner)
+            .await
+            .unwrap();
+        let hash = B256::from_slice(a0.transactions[1].tx_hash().as_ref());
+        let (block, index) = block_builder.get_transaction(hash).await.unwrap();
+        assert_eq!((block.hash, index), (a0.hash, 1));
+        assert_eq!(
+            block_builder
+                .get_txs_by_address(&alice, 1..2)
+                .await
+                .iter()
+                .map(|location| location.block_number)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+
+        // Rebuilt from the store
+        let resumed = BlockBuilder::with_store(store).unwrap();
+        assert_eq!(resumed.get_txs_by_address(&alice, 0..2).await.len(), 3);
+
+        // Txs of blocks reorged out aren't found anymore
+        let a1_tx = B256::from_slice(a1.transactions[0].tx_hash().as_ref());
+        block_builder.set_head(U256::ZERO).await.unwrap();
+        assert!(block_builder.get_tx_location(a1_tx).await.is_none());
+        assert_eq!(
+            block_builder.get_txs_by_address(&alice, 0..2).await.len(),
+            2
+        );
+        block_builder.reorg_to(a1.hash).await.unwrap();
+        assert!(block_builder.get_tx_location(a1_tx).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let block_builder = BlockBuilder::new();
+        let alice = Address::repeat_byte(2);
+        let pay = |amount: u64| Tx::new(alice, Address::repeat_byte(3), U256::from(amount), None);
+        let mut blocks = Vec::new();
+        for amount in 0..3 {
+            let logs = vec![vec![Log::transfer(
+                alice,
+                Address::repeat_byte(3),
+                U256::from(amount),
+            )]];
+            let block = block_builder
+                .create_block_with_logs(vec![pay(amount)], Address::ZERO, B256::ZERO, logs)
+                .await
+                .unwrap();
+            blocks.push(block);
+        }
+
+        assert_eq!(block_builder.prune(U256::from(2)).await.unwrap(), 2);
+        let pruned = block_builder.get_block(U256::ZERO).await.unwrap();
+        assert!(pruned.pruned);
+        assert!(pruned.transactions.is_empty());
+        assert_eq!(pruned.hash, blocks[0].hash);
+        assert!(block_builder
+            .get_block_receipts(blocks[1].hash)
+            .await
+            .is_none());
+        let tx = B256::from_slice(blocks[0].transactions[0].tx_hash().as_ref());
+        assert!(block_builder.get_transaction(tx).await.is_none());
+        assert!(block_builder.get_receipt(tx).await.is_none());
+
+        // The rest stays whole, and blocks still build on the pruned chain
+        let latest = block_builder.get_block(U256::from(2)).await.unwrap();
+        assert_eq!(latest.transactions.len(), 1);
+        assert!(block_builder
+            .get_block_receipts(latest.hash)
+            .await
+            .is_some());
+        assert_eq!(block_builder.prune(U256::from(2)).await.unwrap(), 0);
+        let next = block_builder
+            .create_block(vec![pay(4)], Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(next.parent_hash, latest.hash);
+        // Nothing past the head is pruned
+        assert_eq!(block_builder.prune(U256::from(10)).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_invalid_blocks() {
+        let block_builder = BlockBuilder::new();
+        let a0 = block_builder
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+
+        let child =
+            |timestamp| Block::new(U256::from(1), a0.hash, timestamp, Vec::new(), Address::ZERO);
+        let mut tampered = child(a0.timestamp);
+        tampered.state_root = B256::repeat_byte(1);
+        assert!(block_builder.import_block(tampered).await.is_err());
+        assert!(block_builder
+            .import_block(child(a0.timestamp - 1))
+            .await
+            .is_err());
+        assert!(block_builder
+            .import_block(child(a0.timestamp + 3600))
+            .await
+            .is_err());
+        assert!(block_builder
+            .validate_header(&child(a0.timestamp))
+            .await
+            .is_ok());
+
+        let orphan = Block::new(
+            U256::from(1),
+            B256::repeat_byte(1),
+            0,
+            Vec::new(),
+            Address::ZERO,
+        );
+        assert!(block_builder.import_block(orphan).await.is_err());
+
+        let misnumbered = Block::new(U256::from(2), a0.hash, 0, Vec::new(), Address::ZERO);
+        assert!(block_builder.import_block(misnumbered).await.is_err());
+
+        let mut hidden = child(a0.timestamp);
+        hidden.address_bloom = Bloom::ZERO;
+        hidden.transactions = vec![Tx::new(
+            Address::ZERO,
+            Address::repeat_byte(1),
+            U256::from(1),
+            None,
+        )];
+        hidden.hash = hidden.compute_hash();
+        assert!(block_builder.import_block(hidden).await.is_err());
+
+        let mut overweight = child(a0.timestamp);
+        overweight.gas_used = U256::from(1);
+        overweight.hash = overweight.compute_hash();
+        assert!(block_builder.import_block(overweight).await.is_err());
+
+        assert!(block_builder.reorg_to(B256::repeat_byte(1)).await.is_err());
+        assert!(block_builder.set_head(U256::from(5)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_block_limits() {
+        let limits = BlockLimits {
+            max_txs: 2,
+            ..BlockLimits::default()
+        };
+        let block_builder = BlockBuilder::new().with_limits(limits);
+        let txs = |n: u64| -> Vec<Tx> {
+            (0..n)
+                .map(|i| Tx::new(Address::ZERO, Address::repeat_byte(1), U256::from(i), None))
+                .collect()
+        };
+        assert!(block_builder
+            .create_block(txs(3), Address::ZERO)
+            .await
+            .is_err());
+        assert_eq!(block_builder.get_latest_block_number().await, U256::ZERO);
+
+        let block = block_builder
+            .create_block(txs(2), Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(block.gas_used, U256::from(limits::block_gas(&txs(2))));
+        assert_eq!(block.gas_limit, U256::from(limits.gas_limit));
+        assert!(BlockBuilder::new().import_block(block).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cosigned_blocks() {
+        let primary = PrivateKeySigner::random();
+        let backup = PrivateKeySigner::random();
+        let producers = ProducerSet::new(vec![primary.address(), backup.address()], 2).unwrap();
+        let produced = BlockBuilder::new()
+            .with_producers(producers.clone())
+            .with_signer(primary.clone());
+        let imported = BlockBuilder::new().with_producers(producers);
+
+        // Sealed with the primary's signature only, short of the threshold
+        let block = produced
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(block.signers(), vec![primary.address()]);
+        assert!(imported.import_block(block.clone()).await.is_err());
+
+        // Outsiders can't co-sign, the backup can, once
+        let outsider = PrivateKeySigner::random();
+        let signature = block.sign(&outsider).unwrap();
+        assert!(produced.cosign_block(block.hash, signature).await.is_err());
+        let signature = block.sign(&backup).unwrap();
+        produced.cosign_block(block.hash, signature).await.unwrap();
+        let cosigned = produced.cosign_block(block.hash, signature).await.unwrap();
+        assert_eq!(cosigned.signatures.len(), 2);
+        assert_eq!(
+            produced
+                .get_block(U256::ZERO)
+                .await
+                .unwrap()
+                .signatures
+                .len(),
+            2
+        );
+
+        assert!(matches!(
+            imported.import_block(cosigned).await.unwrap(),
+            ImportOutcome::Canonical(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_blocks() {
+        use committee::committee::Committee;
+        use schedule::Rotation;
+
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let committee = Committee::new(signers.iter().map(|s| (s.address(), 1)));
+        let schedule =
+            ProducerSchedule::from_committee(&committee, Rotation::RoundRobin, 5).unwrap();
+        let signer_of = |address: Address| signers.iter().find(|s| s.address() == address).unwrap();
+        let first = signer_of(schedule.producer_for(0, 0));
+        let second = signer_of(schedule.producer_for(1, 0));
+        let block_builder = BlockBuilder::new().with_schedule(schedule);
+
+        assert!(block_builder.is_due(&first.address()).await.unwrap());
+        assert!(!block_builder.is_due(&second.address()).await.unwrap());
+        let signed = |block: Block, signer: &PrivateKeySigner| {
+            let signature = block.sign(signer).unwrap();
+            block.with_signature(signature)
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let block = |number: u64, parent_hash: B256, timestamp: u64| {
+            Block::new(
+                U256::from(number),
+                parent_hash,
+                timestamp,
+                Vec::new(),
+                Address::ZERO,
+            )
+        };
+
+        let a0 = block(0, B256::ZERO, now - 20);
+        assert!(block_builder.import_block(a0.clone()).await.is_err());
+        assert!(block_builder
+            .import_block(signed(a0.clone(), second))
+            .await
+            .is_err());
+        let a0 = signed(a0, first);
+        block_builder.import_block(a0.clone()).await.unwrap();
+
+        // Block 1 is the second producer's, the first one has to wait out the timeout
+        assert!(block_builder
+            .import_block(signed(block(1, a0.hash, a0.timestamp + 4), first))
+            .await
+            .is_err());
+        let a1 = signed(block(1, a0.hash, a0.timestamp + 5), first);
+        assert!(matches!(
+            block_builder.import_block(a1).await.unwrap(),
+            ImportOutcome::Canonical(_)
+        ));
+    }
 }
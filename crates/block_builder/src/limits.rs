@@ -0,0 +1,125 @@
+// how much goes in a block: at most so many txs, so much gas and so many bytes. There's no
+// execution to meter, a tx's gas is a flat cost plus a cost per byte of its encoding, so big
+// multisig transfers weigh more than plain ones
+
+use tx::tx::Tx;
+
+// what every tx costs, whatever it does
+pub const TX_BASE_GAS: u64 = 21_000;
+// and per byte of its encoding, signatures included
+pub const TX_BYTE_GAS: u64 = 16;
+
+pub fn tx_gas(tx: &Tx) -> u64 {
+    TX_BASE_GAS.saturating_add(TX_BYTE_GAS.saturating_mul(tx.encoded_len() as u64))
+}
+
+pub fn block_gas(txs: &[Tx]) -> u64 {
+    txs.iter().map(tx_gas).fold(0, u64::saturating_add)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLimits {
+    pub gas_limit: u64,
+    pub max_txs: usize,
+    // the encoded txs, the header isn't counted
+    pub max_bytes: usize,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        Self {
+            gas_limit: 30_000_000,
+            max_txs: 10_000,
+            max_bytes: 1 << 20,
+        }
+    }
+}
+
+impl BlockLimits {
+    // splits `txs` into the ones that fit in a block and the leftovers. It stops at the first tx
+    // that doesn't fit rather than skipping it, a later tx of the same sender may need it first
+    pub fn fill(&self, txs: Vec<Tx>) -> (Vec<Tx>, Vec<Tx>) {
+        let mut gas = 0u64;
+        let mut bytes = 0usize;
+        let mut fitting = 0;
+        for tx in &txs {
+            let tx_gas = tx_gas(tx);
+            let tx_bytes = tx.encoded_len();
+            if fitting == self.max_txs
+                || gas.saturating_add(tx_gas) > self.gas_limit
+                || bytes.saturating_add(tx_bytes) > self.max_bytes
+            {
+                break;
+            }
+            gas += tx_gas;
+            bytes += tx_bytes;
+            fitting += 1;
+        }
+
+        let mut included = txs;
+        let leftovers = included.split_off(fitting);
+        (included, leftovers)
+    }
+
+    pub fn check(&self, txs: &[Tx]) -> anyhow::Result<()> {
+        if txs.len() > self.max_txs {
+            anyhow::bail!("{} txs, a block holds at most {}", txs.len(), self.max_txs);
+        }
+        let gas = block_gas(txs);
+        if gas > self.gas_limit {
+            anyhow::bail!("txs use {} gas, the limit is {}", gas, self.gas_limit);
+        }
+        let bytes: usize = txs.iter().map(Tx::encoded_len).sum();
+        if bytes > self.max_bytes {
+            anyhow::bail!(
+                "txs take {} bytes, a block holds at most {}",
+                bytes,
+                self.max_bytes
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    fn txs(n: u64) -> Vec<Tx> {
+        (0..n)
+            .map(|i| Tx::new(Address::ZERO, Address::repeat_byte(1), U256::from(i), None))
+            .collect()
+    }
+
+    #[test]
+    fn test_fill() {
+        let tx_len = txs(1)[0].encoded_len();
+        let gas = tx_gas(&txs(1)[0]);
+        assert_eq!(gas, TX_BASE_GAS + TX_BYTE_GAS * tx_len as u64);
+
+        let limits = |gas_limit, max_txs, max_bytes| BlockLimits {
+            gas_limit,
+            max_txs,
+            max_bytes,
+        };
+        let fill = |limits: BlockLimits| {
+            let (included, leftovers) = limits.fill(txs(5));
+            (included.len(), leftovers.len())
+        };
+        assert_eq!(fill(BlockLimits::default()), (5, 0));
+        assert_eq!(fill(limits(u64::MAX, 3, usize::MAX)), (3, 2));
+        assert_eq!(fill(limits(gas * 2 + 1, usize::MAX, usize::MAX)), (2, 3));
+        assert_eq!(fill(limits(u64::MAX, usize::MAX, tx_len * 4)), (4, 1));
+        assert_eq!(fill(limits(0, usize::MAX, usize::MAX)), (0, 5));
+
+        // Leftovers keep their order
+        let (_, leftovers) = limits(u64::MAX, 3, usize::MAX).fill(txs(5));
+        assert_eq!(leftovers[0].amount(), U256::from(3));
+
+        assert!(limits(u64::MAX, 3, usize::MAX).check(&txs(3)).is_ok());
+        assert!(limits(u64::MAX, 3, usize::MAX).check(&txs(4)).is_err());
+        assert!(limits(gas, usize::MAX, usize::MAX).check(&txs(2)).is_err());
+        assert!(limits(u64::MAX, usize::MAX, tx_len).check(&txs(2)).is_err());
+    }
+}
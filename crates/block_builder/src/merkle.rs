@@ -0,0 +1,117 @@
+// the transactions and receipts roots: merkle trees over the tx hashes, and the receipt hashes,
+// in block order, so a light client holding only a header can check a tx is in the block and what
+// it logged. See tx::merkle for the trees
+
+use alloy::primitives::{keccak256, B256};
+use serde::{Deserialize, Serialize};
+use tx::log::Log;
+pub use tx::merkle::{merkle_root, InclusionProof};
+
+// the receipt leaf of a tx: its hash, then each log with its topics and data length prefixed
+pub fn receipt_hash(tx_hash: &B256, logs: &[Log]) -> B256 {
+    let mut encoded = tx_hash.to_vec();
+    encoded.extend_from_slice(&(logs.len() as u64).to_be_bytes());
+    for log in logs {
+        encoded.extend_from_slice(log.address.as_slice());
+        encoded.extend_from_slice(&(log.topics.len() as u64).to_be_bytes());
+        for topic in &log.topics {
+            encoded.extend_from_slice(topic.as_slice());
+        }
+        encoded.extend_from_slice(&(log.data.len() as u64).to_be_bytes());
+        encoded.extend_from_slice(&log.data);
+    }
+    keccak256(encoded)
+}
+
+// `logs` line up with `tx_hashes`, each tx's logs in the order it emitted them
+pub fn receipts_root(tx_hashes: &[B256], logs: &[Vec<Log>]) -> B256 {
+    let receipts: Vec<B256> = tx_hashes
+        .iter()
+        .zip(logs)
+        .map(|(tx_hash, logs)| receipt_hash(tx_hash, logs))
+        .collect();
+    merkle_root(&receipts)
+}
+
+// what a tx logged, with the proof its receipt is under a block's receipts root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptProof {
+    pub tx_hash: B256,
+    pub logs: Vec<Log>,
+    pub proof: InclusionProof,
+}
+
+impl ReceiptProof {
+    pub fn verify(&self, root: B256) -> bool {
+        receipt_hash(&self.tx_hash, &self.logs) == self.proof.leaf && self.proof.verify(root)
+    }
+}
+
+// everything a light client needs to check a tx is in a block and what it logged, the header
+// has to be one it trusts. No receipt proof for blocks sealed without a receipts root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxProof {
+    pub header: crate::Block,
+    pub tx_proof: InclusionProof,
+    pub receipt_proof: Option<ReceiptProof>,
+}
+
+impl TxProof {
+    // whether the proofs hold against the header they come with, the header itself isn't checked
+    pub fn verify(&self) -> bool {
+        self.header.verify_tx_inclusion(&self.tx_proof)
+            && self.receipt_proof.as_ref().is_none_or(|receipt| {
+                receipt.tx_hash == self.tx_proof.leaf
+                    && receipt.proof.index == self.tx_proof.index
+                    && self.header.verify_receipt_inclusion(receipt)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    fn hashes(count: u8) -> Vec<B256> {
+        (0..count).map(B256::repeat_byte).collect()
+    }
+
+    #[test]
+    fn test_receipt_proof() {
+        let tx_hashes = hashes(3);
+        let logs = vec![
+            vec![Log::transfer(
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                U256::from(5),
+            )],
+            Vec::new(),
+            vec![Log::transfer(
+                Address::repeat_byte(2),
+                Address::repeat_byte(3),
+                U256::from(1),
+            )],
+        ];
+        let root = receipts_root(&tx_hashes, &logs);
+        let receipts: Vec<B256> = tx_hashes
+            .iter()
+            .zip(&logs)
+            .map(|(tx_hash, logs)| receipt_hash(tx_hash, logs))
+            .collect();
+
+        let proof = ReceiptProof {
+            tx_hash: tx_hashes[2],
+            logs: logs[2].clone(),
+            proof: InclusionProof::new(&receipts, 2).unwrap(),
+        };
+        assert!(proof.verify(root));
+
+        // Logs that weren't emitted don't check out
+        let mut forged = proof.clone();
+        forged.logs[0].data = U256::from(100).to_be_bytes::<32>().to_vec().into();
+        assert!(!forged.verify(root));
+    }
+}
@@ -0,0 +1,73 @@
+// the producers allowed to sign blocks and how many of them each block needs, e.g. a primary and
+// a backup sequencer either of which can keep the chain going, or both for every block
+
+use alloy::primitives::Address;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerSet {
+    // sorted and without duplicates
+    producers: Vec<Address>,
+    threshold: usize,
+}
+
+impl ProducerSet {
+    pub fn new(mut producers: Vec<Address>, threshold: usize) -> Result<Self, String> {
+        producers.sort();
+        producers.dedup();
+        if threshold == 0 || threshold > producers.len() {
+            return Err(format!(
+                "producer threshold must be between 1 and {}, got {}",
+                producers.len(),
+                threshold
+            ));
+        }
+        Ok(Self {
+            producers,
+            threshold,
+        })
+    }
+
+    pub fn producers(&self) -> &[Address] {
+        &self.producers
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.producers.binary_search(address).is_ok()
+    }
+
+    // how many distinct producers are among `signers`
+    pub fn count(&self, signers: &[Address]) -> usize {
+        let mut signers: Vec<_> = signers.iter().filter(|s| self.contains(s)).collect();
+        signers.sort();
+        signers.dedup();
+        signers.len()
+    }
+
+    pub fn is_met_by(&self, signers: &[Address]) -> bool {
+        self.count(signers) >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_producer_set() {
+        let primary = Address::repeat_byte(1);
+        let backup = Address::repeat_byte(2);
+        assert!(ProducerSet::new(vec![primary, backup], 0).is_err());
+        assert!(ProducerSet::new(vec![primary, primary], 2).is_err());
+
+        let producers = ProducerSet::new(vec![backup, primary], 2).unwrap();
+        assert_eq!(producers.producers(), &[primary, backup]);
+        assert!(!producers.is_met_by(&[primary]));
+        // The same producer twice or an outsider don't make up for a missing signature
+        assert!(!producers.is_met_by(&[primary, primary, Address::repeat_byte(3)]));
+        assert!(producers.is_met_by(&[backup, primary]));
+    }
+}
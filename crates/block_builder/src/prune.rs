@@ -0,0 +1,51 @@
+// keeping a long-running node's store from growing without bound: the txs and receipts of blocks
+// far enough below the head are dropped, their headers stay so the chain still links up and a
+// branch still finds where it joins it. The state only has a version at the head, the history it
+// is rebuilt from on restart is the blocks on top of the node's last state archive, so those are
+// never pruned. Neither are the blocks a reorg may still replace, nothing finalizes blocks but
+// depth, and FINALITY_DEPTH is as deep as pruning trusts a reorg not to reach
+
+// blocks within this many of the head are never pruned, whatever the retention
+pub const FINALITY_DEPTH: u64 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruneConfig {
+    // blocks kept whole below the head, at least FINALITY_DEPTH of them are
+    pub keep_last_n_blocks: u64,
+}
+
+impl PruneConfig {
+    pub fn new(keep_last_n_blocks: u64) -> Self {
+        Self { keep_last_n_blocks }
+    }
+
+    // the lowest block to keep whole when the next block is `next_number` and the state can be
+    // restored at block `checkpoint`. Without a checkpoint a restart replays every block, and
+    // none are pruned
+    pub fn prune_below(&self, next_number: u64, checkpoint: Option<u64>) -> u64 {
+        let Some(checkpoint) = checkpoint else {
+            return 0;
+        };
+        let keep = self.keep_last_n_blocks.max(FINALITY_DEPTH);
+        next_number.saturating_sub(keep).min(checkpoint + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_below() {
+        let config = PruneConfig::new(100);
+        assert_eq!(config.prune_below(1000, None), 0);
+        assert_eq!(config.prune_below(1000, Some(999)), 900);
+        // The blocks a restart replays stay
+        assert_eq!(config.prune_below(1000, Some(500)), 501);
+        assert_eq!(config.prune_below(50, Some(49)), 0);
+
+        // However short the retention, unfinalized blocks stay
+        let config = PruneConfig::new(0);
+        assert_eq!(config.prune_below(1000, Some(999)), 1000 - FINALITY_DEPTH);
+    }
+}
@@ -0,0 +1,101 @@
+// what executing a block's txs left behind, one receipt per tx in block order. They are stored
+// with the block, see BlockStore::put_with_receipts, so eth_getTransactionReceipt and
+// eth_getBlockReceipts read them instead of executing the block again
+
+use alloy::primitives::{B256, U256};
+use serde::{Deserialize, Serialize};
+use tx::log::Log;
+
+use crate::{limits::tx_gas, Block};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredReceipt {
+    pub tx_hash: B256,
+    pub block_hash: B256,
+    pub block_number: u64,
+    pub index: usize,
+    pub gas_used: u64,
+    // of this tx and the ones before it in the block
+    pub cumulative_gas_used: u64,
+    pub logs: Vec<Log>,
+}
+
+// `logs` line up with the block's txs, the ones past its end emitted none
+pub fn block_receipts(block: &Block, logs: &[Vec<Log>]) -> Vec<StoredReceipt> {
+    let mut cumulative_gas_used = 0u64;
+    block
+        .transactions
+        .iter()
+        .enumerate()
+        .map(|(index, tx)| {
+            let gas_used = tx_gas(tx);
+            cumulative_gas_used = cumulative_gas_used.saturating_add(gas_used);
+            StoredReceipt {
+                tx_hash: B256::from_slice(tx.tx_hash().as_ref()),
+                block_hash: block.hash,
+                block_number: block.number.saturating_to(),
+                index,
+                gas_used,
+                cumulative_gas_used,
+                logs: logs.get(index).cloned().unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+// receipts by block number and by tx hash. Block stores are receipt stores too, so a block and
+// its receipts are written together and dropped together when a reorg removes the block
+pub trait ReceiptStore: Send + Sync {
+    // replaces the receipts of block `number`
+    fn put_receipts(&self, number: U256, receipts: &[StoredReceipt]) -> anyhow::Result<()>;
+
+    // None when block `number` has none stored, e.g. it was imported but not executed yet
+    fn get_receipts(&self, number: U256) -> anyhow::Result<Option<Vec<StoredReceipt>>>;
+
+    fn get_receipt(&self, tx_hash: B256) -> anyhow::Result<Option<StoredReceipt>>;
+}
+
+impl<T: ReceiptStore + ?Sized> ReceiptStore for std::sync::Arc<T> {
+    fn put_receipts(&self, number: U256, receipts: &[StoredReceipt]) -> anyhow::Result<()> {
+        (**self).put_receipts(number, receipts)
+    }
+
+    fn get_receipts(&self, number: U256) -> anyhow::Result<Option<Vec<StoredReceipt>>> {
+        (**self).get_receipts(number)
+    }
+
+    fn get_receipt(&self, tx_hash: B256) -> anyhow::Result<Option<StoredReceipt>> {
+        (**self).get_receipt(tx_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use tx::tx::Tx;
+
+    #[test]
+    fn test_block_receipts() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let txs = vec![
+            Tx::new(alice, bob, U256::from(1), None),
+            Tx::new(bob, alice, U256::from(2), None),
+        ];
+        let block = Block::new(U256::from(4), B256::ZERO, 0, txs, Address::ZERO);
+        let logs = vec![vec![Log::transfer(alice, bob, U256::from(1))]];
+
+        let receipts = block_receipts(&block, &logs);
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].logs, logs[0]);
+        // The second tx's logs weren't given
+        assert!(receipts[1].logs.is_empty());
+        assert_eq!(receipts[1].block_number, 4);
+        assert_eq!(receipts[1].tx_hash, block.tx_hashes()[1]);
+        assert_eq!(
+            receipts[1].cumulative_gas_used,
+            receipts[0].gas_used + receipts[1].gas_used
+        );
+    }
+}
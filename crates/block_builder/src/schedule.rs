@@ -0,0 +1,169 @@
+// which committee member produces which block, so no single sequencer has to be trusted with
+// the chain. Block n belongs to one scheduled producer; if it hasn't produced the block a timeout
+// after the parent, the next member in line may, and so on one more per timeout
+
+use std::str::FromStr;
+
+use alloy::primitives::Address;
+use committee::committee::Committee;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Rotation {
+    // every member gets one block in turn
+    #[default]
+    RoundRobin,
+    // every member gets a run of blocks as long as its stake
+    StakeWeighted,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Self::RoundRobin),
+            "stake-weighted" => Ok(Self::StakeWeighted),
+            other => Err(format!(
+                "invalid rotation {}, expected round-robin or stake-weighted",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerSchedule {
+    // sorted by address, members without stake are left out of a stake weighted schedule
+    slots: Vec<(Address, u64)>,
+    total_weight: u64,
+    // seconds a scheduled producer has before the next one in line may take over
+    timeout: u64,
+}
+
+impl ProducerSchedule {
+    pub fn from_committee(
+        committee: &Committee,
+        rotation: Rotation,
+        timeout: u64,
+    ) -> Result<Self, String> {
+        if timeout == 0 {
+            return Err("the producer timeout has to be at least a second".to_string());
+        }
+        let slots: Vec<(Address, u64)> = committee
+            .authorities()
+            .map(|authority| match rotation {
+                Rotation::RoundRobin => (*authority, 1),
+                Rotation::StakeWeighted => (*authority, committee.stake(authority)),
+            })
+            .filter(|(_, weight)| *weight > 0)
+            .collect();
+        if slots.is_empty() {
+            return Err("no committee member can be scheduled to produce blocks".to_string());
+        }
+        let total_weight = slots.iter().map(|(_, weight)| weight).sum();
+        Ok(Self {
+            slots,
+            total_weight,
+            timeout,
+        })
+    }
+
+    pub fn producers(&self) -> impl Iterator<Item = &Address> {
+        self.slots.iter().map(|(producer, _)| producer)
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.slots.iter().any(|(producer, _)| producer == address)
+    }
+
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    // the producer of block `number` once `round` timeouts have passed, round 0 being the one
+    // scheduled for it
+    pub fn producer_for(&self, number: u64, round: u64) -> Address {
+        let mut position = number % self.total_weight;
+        let scheduled = self
+            .slots
+            .iter()
+            .position(|(_, weight)| {
+                if position < *weight {
+                    return true;
+                }
+                position -= weight;
+                false
+            })
+            .expect("positions are below the total weight");
+        let len = self.slots.len() as u64;
+        self.slots[((scheduled as u64 + round % len) % len) as usize].0
+    }
+
+    // whether `producer` may produce block `number` at `timestamp`, after a parent from
+    // `parent_timestamp`. The scheduled producer stays allowed after its timeout, a late block
+    // is still better than none
+    pub fn may_produce(
+        &self,
+        producer: &Address,
+        number: u64,
+        parent_timestamp: u64,
+        timestamp: u64,
+    ) -> bool {
+        let rounds = timestamp.saturating_sub(parent_timestamp) / self.timeout;
+        // once every member had its round anyone may produce, no need to go on
+        let rounds = rounds.min(self.slots.len() as u64 - 1);
+        (0..=rounds).any(|round| self.producer_for(number, round) == *producer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let committee = Committee::new([(a, 2), (b, 1), (c, 0)]);
+        assert!(ProducerSchedule::from_committee(&committee, Rotation::RoundRobin, 0).is_err());
+        assert!(ProducerSchedule::from_committee(
+            &Committee::new([(a, 0)]),
+            Rotation::StakeWeighted,
+            5
+        )
+        .is_err());
+
+        let round_robin =
+            ProducerSchedule::from_committee(&committee, Rotation::RoundRobin, 5).unwrap();
+        let order: Vec<_> = (0..4).map(|n| round_robin.producer_for(n, 0)).collect();
+        assert_eq!(order, vec![a, b, c, a]);
+
+        // Without stake c is never scheduled, a gets two blocks for b's one
+        let weighted =
+            ProducerSchedule::from_committee(&committee, Rotation::StakeWeighted, 5).unwrap();
+        assert!(!weighted.contains(&c));
+        let order: Vec<_> = (0..4).map(|n| weighted.producer_for(n, 0)).collect();
+        assert_eq!(order, vec![a, a, b, a]);
+
+        // Block 1 is b's, c may take over after one timeout and a after two
+        assert!(round_robin.may_produce(&b, 1, 100, 100));
+        assert!(!round_robin.may_produce(&c, 1, 100, 104));
+        assert!(round_robin.may_produce(&c, 1, 100, 105));
+        assert!(!round_robin.may_produce(&a, 1, 100, 105));
+        assert!(round_robin.may_produce(&a, 1, 100, 110));
+        assert!(round_robin.may_produce(&b, 1, 100, 1000));
+        assert!(!round_robin.may_produce(&Address::repeat_byte(4), 1, 100, 1000));
+    }
+
+    #[test]
+    fn test_parse_rotation() {
+        assert_eq!("round-robin".parse(), Ok(Rotation::RoundRobin));
+        assert_eq!("stake-weighted".parse(), Ok(Rotation::StakeWeighted));
+        assert!("random".parse::<Rotation>().is_err());
+    }
+}
@@ -0,0 +1,549 @@
+// where the block builder keeps its blocks and their receipts. MemoryBlockStore loses them on
+// restart, SledBlockStore keeps them on disk, indexed by number and by hash. It comes with the
+// `sled` feature
+
+use std::collections::HashMap;
+#[cfg(feature = "sled")]
+use std::path::Path;
+use std::sync::RwLock;
+
+use alloy::primitives::{B256, U256};
+
+#[cfg(feature = "sled")]
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionalTree,
+};
+
+use crate::receipts::{ReceiptStore, StoredReceipt};
+use crate::Block;
+
+pub trait BlockStore: ReceiptStore {
+    fn put(&self, block: &Block) -> anyhow::Result<()>;
+
+    // writes `block` and its receipts at once, a crash leaves both or neither
+    fn put_with_receipts(&self, block: &Block, receipts: &[StoredReceipt]) -> anyhow::Result<()>;
+
+    fn get_by_number(&self, number: U256) -> anyhow::Result<Option<Block>>;
+
+    // drops the block at `number` along with its hash and receipts, used when a reorg replaces it
+    fn remove(&self, number: U256) -> anyhow::Result<()>;
+
+    fn get_by_hash(&self, hash: B256) -> anyhow::Result<Option<Block>>;
+
+    // replaces the block at `number` with its header, marked pruned, and drops its receipts, at
+    // once. False when it was pruned already
+    fn prune(&self, number: U256) -> anyhow::Result<bool>;
+
+    // the highest block number stored, None when the store is empty
+    fn latest_number(&self) -> anyhow::Result<Option<U256>>;
+}
+
+// lets a store be shared, e.g. by a builder and a tool reading the same blocks
+impl<T: BlockStore + ?Sized> BlockStore for std::sync::Arc<T> {
+    fn put(&self, block: &Block) -> anyhow::Result<()> {
+        (**self).put(block)
+    }
+
+    fn put_with_receipts(&self, block: &Block, receipts: &[StoredReceipt]) -> anyhow::Result<()> {
+        (**self).put_with_receipts(block, receipts)
+    }
+
+    fn get_by_number(&self, number: U256) -> anyhow::Result<Option<Block>> {
+        (**self).get_by_number(number)
+    }
+
+    fn remove(&self, number: U256) -> anyhow::Result<()> {
+        (**self).remove(number)
+    }
+
+    fn get_by_hash(&self, hash: B256) -> anyhow::Result<Option<Block>> {
+        (**self).get_by_hash(hash)
+    }
+
+    fn prune(&self, number: U256) -> anyhow::Result<bool> {
+        (**self).prune(number)
+    }
+
+    fn latest_number(&self) -> anyhow::Result<Option<U256>> {
+        (**self).latest_number()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryBlockStore {
+    blocks: RwLock<HashMap<U256, Block>>,
+    numbers_by_hash: RwLock<HashMap<B256, U256>>,
+    receipts: RwLock<MemoryReceipts>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryReceipts {
+    by_number: HashMap<U256, Vec<StoredReceipt>>,
+    // the block holding each tx
+    numbers_by_tx: HashMap<B256, U256>,
+}
+
+impl MemoryReceipts {
+    fn put(&mut self, number: U256, receipts: &[StoredReceipt]) {
+        self.remove(number);
+        for receipt in receipts {
+            self.numbers_by_tx.insert(receipt.tx_hash, number);
+        }
+        self.by_number.insert(number, receipts.to_vec());
+    }
+
+    fn remove(&mut self, number: U256) {
+        for receipt in self.by_number.remove(&number).into_iter().flatten() {
+            // an older block may hold the same tx, it keeps pointing there
+            if self.numbers_by_tx.get(&receipt.tx_hash) == Some(&number) {
+                self.numbers_by_tx.remove(&receipt.tx_hash);
+            }
+        }
+    }
+}
+
+impl MemoryBlockStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlockStore for MemoryBlockStore {
+    fn put(&self, block: &Block) -> anyhow::Result<()> {
+        self.blocks
+            .write()
+            .unwrap()
+            .insert(block.number, block.clone());
+        self.numbers_by_hash
+            .write()
+            .unwrap()
+            .insert(block.hash, block.number);
+        Ok(())
+    }
+
+    fn put_with_receipts(&self, block: &Block, receipts: &[StoredReceipt]) -> anyhow::Result<()> {
+        // held across both, so no reader sees the block without its receipts
+        let mut stored = self.receipts.write().unwrap();
+        self.put(block)?;
+        stored.put(block.number, receipts);
+        Ok(())
+    }
+
+    fn get_by_number(&self, number: U256) -> anyhow::Result<Option<Block>> {
+        Ok(self.blocks.read().unwrap().get(&number).cloned())
+    }
+
+    fn remove(&self, number: U256) -> anyhow::Result<()> {
+        let mut receipts = self.receipts.write().unwrap();
+        if let Some(block) = self.blocks.write().unwrap().remove(&number) {
+            self.numbers_by_hash.write().unwrap().remove(&block.hash);
+        }
+        receipts.remove(number);
+        Ok(())
+    }
+
+    fn get_by_hash(&self, hash: B256) -> anyhow::Result<Option<Block>> {
+        let number = self.numbers_by_hash.read().unwrap().get(&hash).copied();
+        match number {
+            Some(number) => self.get_by_number(number),
+            None => Ok(None),
+        }
+    }
+
+    fn prune(&self, number: U256) -> anyhow::Result<bool> {
+        let mut receipts = self.receipts.write().unwrap();
+        let mut pruned = receipts.by_number.contains_key(&number);
+        receipts.remove(number);
+        if let Some(block) = self.blocks.write().unwrap().get_mut(&number) {
+            if !block.pruned {
+                *block = block.pruned_header();
+                pruned = true;
+            }
+        }
+        Ok(pruned)
+    }
+
+    fn latest_number(&self) -> anyhow::Result<Option<U256>> {
+        Ok(self.blocks.read().unwrap().keys().max().copied())
+    }
+}
+
+impl ReceiptStore for MemoryBlockStore {
+    fn put_receipts(&self, number: U256, receipts: &[StoredReceipt]) -> anyhow::Result<()> {
+        self.receipts.write().unwrap().put(number, receipts);
+        Ok(())
+    }
+
+    fn get_receipts(&self, number: U256) -> anyhow::Result<Option<Vec<StoredReceipt>>> {
+        Ok(self
+            .receipts
+            .read()
+            .unwrap()
+            .by_number
+            .get(&number)
+            .cloned())
+    }
+
+    fn get_receipt(&self, tx_hash: B256) -> anyhow::Result<Option<StoredReceipt>> {
+        let receipts = self.receipts.read().unwrap();
+        Ok(receipts
+            .numbers_by_tx
+            .get(&tx_hash)
+            .and_then(|number| receipts.by_number.get(number))
+            .and_then(|block| block.iter().find(|receipt| receipt.tx_hash == tx_hash))
+            .cloned())
+    }
+}
+
+#[cfg(feature = "sled")]
+const BLOCKS_TREE: &str = "blocks";
+#[cfg(feature = "sled")]
+const HASHES_TREE: &str = "block_hashes";
+#[cfg(feature = "sled")]
+const RECEIPTS_TREE: &str = "receipts";
+#[cfg(feature = "sled")]
+const RECEIPT_HASHES_TREE: &str = "receipt_hashes";
+// 50ms apart
+#[cfg(feature = "sled")]
+const LOCK_ATTEMPTS: u32 = 40;
+
+// blocks are JSON encoded under their big endian number, so the last key is the latest block,
+// a second tree maps hashes to numbers. Receipts are kept the same way, each block's under its
+// number and every tx hash mapping to the number of its block
+#[cfg(feature = "sled")]
+pub struct SledBlockStore {
+    db: sled::Db,
+    blocks: sled::Tree,
+    hashes: sled::Tree,
+    receipts: sled::Tree,
+    receipt_hashes: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledBlockStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        // every write is flushed right away, sled's background flusher would only hold on to the
+        // database, and its lock, for a while after the store is dropped
+        let config = sled::Config::new().path(path).flush_every_ms(None);
+        // a store dropped in this process gives up its lock once sled's background writes are
+        // done, so reopening it right away, like one command after another does, waits a moment
+        let mut attempts = 0;
+        let db = loop {
+            match config.open() {
+                Err(sled::Error::Io(e))
+                    if attempts < LOCK_ATTEMPTS
+                        && e.to_string().contains("could not acquire lock") =>
+                {
+                    attempts += 1;
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                }
+                result => break result?,
+            }
+        };
+        let blocks = db.open_tree(BLOCKS_TREE)?;
+        let hashes = db.open_tree(HASHES_TREE)?;
+        let receipts = db.open_tree(RECEIPTS_TREE)?;
+        let receipt_hashes = db.open_tree(RECEIPT_HASHES_TREE)?;
+        Ok(Self {
+            db,
+            blocks,
+            hashes,
+            receipts,
+            receipt_hashes,
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> anyhow::Result<Block> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn decode_receipts(bytes: &[u8]) -> anyhow::Result<Vec<StoredReceipt>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    // runs `f` on the four trees in one transaction, then flushes
+    fn transaction(
+        &self,
+        f: impl Fn(&SledTrees) -> ConflictableTransactionResult<(), anyhow::Error>,
+    ) -> anyhow::Result<()> {
+        use sled::Transactional;
+        (
+            &self.blocks,
+            &self.hashes,
+            &self.receipts,
+            &self.receipt_hashes,
+        )
+            .transaction(|(blocks, hashes, receipts, receipt_hashes)| {
+                f(&SledTrees {
+                    blocks,
+                    hashes,
+                    receipts,
+                    receipt_hashes,
+                })
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => e.into(),
+            })?;
+        // a block the node has announced must survive a crash
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sled")]
+struct SledTrees<'a> {
+    blocks: &'a TransactionalTree,
+    hashes: &'a TransactionalTree,
+    receipts: &'a TransactionalTree,
+    receipt_hashes: &'a TransactionalTree,
+}
+
+#[cfg(feature = "sled")]
+impl SledTrees<'_> {
+    fn put_block(
+        &self,
+        block: &Block,
+        encoded: &[u8],
+    ) -> ConflictableTransactionResult<(), anyhow::Error> {
+        let number = block.number.to_be_bytes::<32>();
+        self.blocks.insert(&number[..], encoded)?;
+        self.hashes.insert(block.hash.as_slice(), &number[..])?;
+        Ok(())
+    }
+
+    fn put_receipts(
+        &self,
+        number: U256,
+        receipts: &[StoredReceipt],
+        encoded: &[u8],
+    ) -> ConflictableTransactionResult<(), anyhow::Error> {
+        self.remove_receipts(number)?;
+        let number = number.to_be_bytes::<32>();
+        for receipt in receipts {
+            self.receipt_hashes
+                .insert(receipt.tx_hash.as_slice(), &number[..])?;
+        }
+        self.receipts.insert(&number[..], encoded)?;
+        Ok(())
+    }
+
+    fn remove_receipts(&self, number: U256) -> ConflictableTransactionResult<(), anyhow::Error> {
+        let number = number.to_be_bytes::<32>();
+        let Some(bytes) = self.receipts.remove(&number[..])? else {
+            return Ok(());
+        };
+        let receipts =
+            SledBlockStore::decode_receipts(&bytes).map_err(ConflictableTransactionError::Abort)?;
+        for receipt in receipts {
+            // an older block may hold the same tx, it keeps pointing there
+            let hash = receipt.tx_hash.as_slice();
+            if self.receipt_hashes.get(hash)?.as_deref() == Some(&number[..]) {
+                self.receipt_hashes.remove(hash)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sled")]
+impl BlockStore for SledBlockStore {
+    fn put(&self, block: &Block) -> anyhow::Result<()> {
+        let encoded = serde_json::to_vec(block)?;
+        self.transaction(|trees| trees.put_block(block, &encoded))
+    }
+
+    fn put_with_receipts(&self, block: &Block, receipts: &[StoredReceipt]) -> anyhow::Result<()> {
+        let encoded = serde_json::to_vec(block)?;
+        let encoded_receipts = serde_json::to_vec(receipts)?;
+        self.transaction(|trees| {
+            trees.put_block(block, &encoded)?;
+            trees.put_receipts(block.number, receipts, &encoded_receipts)
+        })
+    }
+
+    fn get_by_number(&self, number: U256) -> anyhow::Result<Option<Block>> {
+        match self.blocks.get(number.to_be_bytes::<32>())? {
+            Some(bytes) => Ok(Some(Self::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn remove(&self, number: U256) -> anyhow::Result<()> {
+        self.transaction(|trees| {
+            if let Some(bytes) = trees.blocks.remove(&number.to_be_bytes::<32>()[..])? {
+                let block = Self::decode(&bytes).map_err(ConflictableTransactionError::Abort)?;
+                trees.hashes.remove(block.hash.as_slice())?;
+            }
+            trees.remove_receipts(number)
+        })
+    }
+
+    fn get_by_hash(&self, hash: B256) -> anyhow::Result<Option<Block>> {
+        match self.hashes.get(hash.as_slice())? {
+            Some(number) => self.get_by_number(U256::from_be_slice(&number)),
+            None => Ok(None),
+        }
+    }
+
+    fn prune(&self, number: U256) -> anyhow::Result<bool> {
+        let key = number.to_be_bytes::<32>();
+        let header = self
+            .get_by_number(number)?
+            .filter(|block| !block.pruned)
+            .map(|block| block.pruned_header());
+        if header.is_none() && !self.receipts.contains_key(key)? {
+            return Ok(false);
+        }
+        let encoded = header.as_ref().map(serde_json::to_vec).transpose()?;
+        self.transaction(|trees| {
+            if let (Some(header), Some(encoded)) = (&header, &encoded) {
+                trees.put_block(header, encoded)?;
+            }
+            trees.remove_receipts(number)
+        })?;
+        Ok(true)
+    }
+
+    fn latest_number(&self) -> anyhow::Result<Option<U256>> {
+        Ok(self
+            .blocks
+            .last()?
+            .map(|(number, _)| U256::from_be_slice(&number)))
+    }
+}
+
+#[cfg(feature = "sled")]
+impl ReceiptStore for SledBlockStore {
+    fn put_receipts(&self, number: U256, receipts: &[StoredReceipt]) -> anyhow::Result<()> {
+        let encoded = serde_json::to_vec(receipts)?;
+        self.transaction(|trees| trees.put_receipts(number, receipts, &encoded))
+    }
+
+    fn get_receipts(&self, number: U256) -> anyhow::Result<Option<Vec<StoredReceipt>>> {
+        match self.receipts.get(number.to_be_bytes::<32>())? {
+            Some(bytes) => Ok(Some(Self::decode_receipts(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_receipt(&self, tx_hash: B256) -> anyhow::Result<Option<StoredReceipt>> {
+        let Some(number) = self.receipt_hashes.get(tx_hash.as_slice())? else {
+            return Ok(None);
+        };
+        Ok(self
+            .get_receipts(U256::from_be_slice(&number))?
+            .into_iter()
+            .flatten()
+            .find(|receipt| receipt.tx_hash == tx_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipts::block_receipts;
+    use alloy::primitives::Address;
+    use tx::tx::Tx;
+
+    #[cfg(feature = "sled")]
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "fastpay-block-store-{}-{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn block(number: u64) -> Block {
+        Block::new(
+            U256::from(number),
+            B256::repeat_byte(number as u8),
+            1_700_000_000 + number,
+            vec![Tx::new(
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                U256::from(number),
+                None,
+            )],
+            Address::ZERO,
+        )
+    }
+
+    fn check_store(store: &dyn BlockStore) {
+        assert_eq!(store.latest_number().unwrap(), None);
+
+        for number in 0..3 {
+            store.put(&block(number)).unwrap();
+        }
+
+        assert_eq!(store.latest_number().unwrap(), Some(U256::from(2)));
+        let second = store.get_by_number(U256::from(1)).unwrap().unwrap();
+        assert_eq!(second.hash, block(1).hash);
+        assert_eq!(second.transactions[0].amount(), U256::from(1));
+        assert_eq!(
+            store.get_by_hash(second.hash).unwrap().unwrap().number,
+            U256::from(1)
+        );
+        assert!(store.get_by_number(U256::from(3)).unwrap().is_none());
+        assert!(store.get_by_hash(B256::ZERO).unwrap().is_none());
+
+        store.remove(U256::from(2)).unwrap();
+        assert_eq!(store.latest_number().unwrap(), Some(U256::from(1)));
+        assert!(store.get_by_hash(block(2).hash).unwrap().is_none());
+        store.put(&block(2)).unwrap();
+
+        // Receipts are written and dropped with their block
+        let third = block(3);
+        let receipts = block_receipts(&third, &[]);
+        store.put_with_receipts(&third, &receipts).unwrap();
+        assert_eq!(
+            store.get_receipts(U256::from(3)).unwrap(),
+            Some(receipts.clone())
+        );
+        assert_eq!(
+            store.get_receipt(receipts[0].tx_hash).unwrap(),
+            Some(receipts[0].clone())
+        );
+        assert!(store.get_receipts(U256::from(2)).unwrap().is_none());
+        store.remove(U256::from(3)).unwrap();
+        assert!(store.get_receipts(U256::from(3)).unwrap().is_none());
+        assert!(store.get_receipt(receipts[0].tx_hash).unwrap().is_none());
+
+        // Pruning keeps the header, still found by its hash, and drops the txs and receipts
+        store.put_with_receipts(&third, &receipts).unwrap();
+        assert!(store.prune(U256::from(3)).unwrap());
+        let pruned = store.get_by_hash(third.hash).unwrap().unwrap();
+        assert!(pruned.pruned);
+        assert!(pruned.transactions.is_empty());
+        assert_eq!(pruned.compute_hash(), third.hash);
+        assert!(store.get_receipts(U256::from(3)).unwrap().is_none());
+        assert!(store.get_receipt(receipts[0].tx_hash).unwrap().is_none());
+        assert!(!store.prune(U256::from(3)).unwrap());
+        store.remove(U256::from(3)).unwrap();
+    }
+
+    #[test]
+    fn test_memory_block_store() {
+        check_store(&MemoryBlockStore::new());
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_block_store() {
+        let path = temp_path("sled");
+        check_store(&SledBlockStore::open(&path).unwrap());
+
+        // Blocks are still there after reopening
+        let reopened = SledBlockStore::open(&path).unwrap();
+        assert_eq!(reopened.latest_number().unwrap(), Some(U256::from(2)));
+        assert_eq!(
+            reopened.get_by_number(U256::ZERO).unwrap().unwrap().hash,
+            block(0).hash
+        );
+
+        drop(reopened);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+// deposits into the bridge contract on the primary chain, read back from its logs. The contract
+// numbers them from zero and the operator relays them with that number as its sequence, so each
+// is credited exactly once and in order
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+use client::events::RpcLog;
+use tx::tx::Tx;
+
+use crate::BridgeError;
+
+// `recipient` is indexed, `amount` and `nonce` are in the data
+pub const DEPOSIT_EVENT_SIGNATURE: &str = "Deposit(address,uint256,uint256)";
+
+pub fn deposit_topic() -> B256 {
+    keccak256(DEPOSIT_EVENT_SIGNATURE)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deposit {
+    pub recipient: Address,
+    pub amount: U256,
+    pub nonce: u64,
+    // the primary chain tx that made the deposit
+    pub tx_hash: Option<B256>,
+}
+
+impl Deposit {
+    pub fn from_log(log: &RpcLog) -> Result<Self, BridgeError> {
+        if log.topics.first() != Some(&deposit_topic()) {
+            return Err(BridgeError::InvalidDeposit("not a deposit log".to_string()));
+        }
+        if log.topics.len() != 2 {
+            return Err(BridgeError::InvalidDeposit(format!(
+                "deposit log has {} topics, expected 2",
+                log.topics.len()
+            )));
+        }
+        if log.data.len() != 64 {
+            return Err(BridgeError::InvalidDeposit(format!(
+                "deposit log has {} bytes of data, expected 64",
+                log.data.len()
+            )));
+        }
+        let nonce = U256::from_be_slice(&log.data[32..]);
+        if nonce > U256::from(u64::MAX) {
+            return Err(BridgeError::InvalidDeposit(format!(
+                "deposit nonce {} does not fit in a sequence number",
+                nonce
+            )));
+        }
+
+        Ok(Self {
+            recipient: Address::from_word(log.topics[1]),
+            amount: U256::from_be_slice(&log.data[..32]),
+            nonce: nonce.to::<u64>(),
+            tx_hash: log.transaction_hash,
+        })
+    }
+
+    // the unsigned tx crediting this deposit on behalf of `operator`
+    pub fn to_tx(&self, operator: Address) -> Tx {
+        Tx::fund_from_primary(operator, self.recipient, self.amount, self.nonce, None)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    // a deposit log as the primary chain's `eth_getLogs` returns it
+    pub(crate) fn deposit_log(recipient: Address, amount: u64, nonce: u64, block: u64) -> Value {
+        let mut data = U256::from(amount).to_be_bytes::<32>().to_vec();
+        data.extend_from_slice(&U256::from(nonce).to_be_bytes::<32>());
+        json!({
+            "address": Address::repeat_byte(0xbb),
+            "topics": [deposit_topic(), recipient.into_word()],
+            "data": alloy::primitives::Bytes::from(data),
+            "blockNumber": format!("{:#x}", block),
+            "transactionHash": B256::repeat_byte(nonce as u8),
+            "logIndex": "0x0",
+        })
+    }
+
+    #[test]
+    fn test_deposit_from_log() {
+        let recipient = Address::repeat_byte(1);
+        let log: RpcLog = serde_json::from_value(deposit_log(recipient, 50, 3, 10)).unwrap();
+        let deposit = Deposit::from_log(&log).unwrap();
+        assert_eq!(deposit.recipient, recipient);
+        assert_eq!(deposit.amount, U256::from(50));
+        assert_eq!(deposit.nonce, 3);
+        assert_eq!(deposit.tx_hash, Some(B256::repeat_byte(3)));
+
+        let operator = Address::repeat_byte(9);
+        let tx = deposit.to_tx(operator);
+        assert_eq!(tx.from(), operator);
+        assert_eq!(tx.to(), recipient);
+        assert_eq!(tx.sequence(), Some(3));
+
+        // Some other event of the contract
+        let mut other = log.clone();
+        other.topics[0] = B256::repeat_byte(1);
+        assert!(Deposit::from_log(&other).is_err());
+
+        let mut truncated = log;
+        truncated.data = truncated.data.slice(..32);
+        assert!(Deposit::from_log(&truncated).is_err());
+    }
+}
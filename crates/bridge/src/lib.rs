@@ -0,0 +1,221 @@
+// the bridge to the primary chain. The relayer watches the bridge contract there for deposits and
+// has the bridge operator credit them on fastpay, the other way round redemptions burn funds on
+// fastpay and the committee's votes for them make a certificate the contract pays out against.
+// The primary chain is reached through the same json-rpc client as fastpay nodes, it only needs
+// `eth_blockNumber` and `eth_getLogs`
+
+use alloy::primitives::{Address, B256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use client::transport::{HttpTransport, Transport};
+use client::{Client, ClientError};
+use committee::CommitteeError;
+use serde_json::json;
+use tx::tx::Tx;
+
+pub mod deposit;
+pub mod redemption;
+
+use deposit::{deposit_topic, Deposit};
+
+// blocks a deposit has to be buried under before it is credited
+const DEFAULT_CONFIRMATIONS: u64 = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BridgeError {
+    Client(ClientError),
+    InvalidDeposit(String),
+    // a deposit nonce was skipped, crediting later ones would leave it out for good
+    MissingDeposit { expected: u64, got: u64 },
+    InvalidRedemption(String),
+    Committee(CommitteeError),
+    SigningError(String),
+}
+
+impl From<ClientError> for BridgeError {
+    fn from(error: ClientError) -> Self {
+        Self::Client(error)
+    }
+}
+
+pub struct Relayer<T = HttpTransport> {
+    primary: Client<T>,
+    contract: Address,
+    operator: PrivateKeySigner,
+    confirmations: u64,
+    // the first primary chain block not scanned yet
+    next_block: u64,
+    // the nonce of the next deposit to credit, the operator's sequence number on fastpay
+    next_nonce: u64,
+}
+
+impl<T: Transport> Relayer<T> {
+    pub fn new(primary: Client<T>, contract: Address, operator: PrivateKeySigner) -> Self {
+        Self {
+            primary,
+            contract,
+            operator,
+            confirmations: DEFAULT_CONFIRMATIONS,
+            next_block: 0,
+            next_nonce: 0,
+        }
+    }
+
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    // resumes from where a previous relayer stopped, the nonce is the operator's transaction
+    // count on fastpay
+    pub fn with_start(mut self, next_block: u64, next_nonce: u64) -> Self {
+        self.next_block = next_block;
+        self.next_nonce = next_nonce;
+        self
+    }
+
+    pub fn next_nonce(&self) -> u64 {
+        self.next_nonce
+    }
+
+    // the signed txs crediting the deposits confirmed since the last poll. Nothing moves on when
+    // it fails, the same blocks are scanned again next time
+    pub async fn poll(&mut self) -> Result<Vec<Tx>, BridgeError> {
+        let latest: u64 = self.primary.block_number().await?.saturating_to();
+        let Some(confirmed) = latest.checked_sub(self.confirmations) else {
+            return Ok(Vec::new());
+        };
+        if confirmed < self.next_block {
+            return Ok(Vec::new());
+        }
+
+        let logs = self
+            .primary
+            .get_logs(json!({
+                "fromBlock": format!("{:#x}", self.next_block),
+                "toBlock": format!("{:#x}", confirmed),
+                "address": self.contract,
+                "topics": [deposit_topic()],
+            }))
+            .await?;
+        let mut deposits = logs
+            .iter()
+            .filter(|log| !log.removed)
+            .map(Deposit::from_log)
+            .collect::<Result<Vec<_>, _>>()?;
+        deposits.sort_by_key(|deposit| deposit.nonce);
+
+        let mut next_nonce = self.next_nonce;
+        let mut txs = Vec::new();
+        for deposit in deposits {
+            // already credited, the scanned ranges overlap after a restart
+            if deposit.nonce < next_nonce {
+                continue;
+            }
+            if deposit.nonce > next_nonce {
+                return Err(BridgeError::MissingDeposit {
+                    expected: next_nonce,
+                    got: deposit.nonce,
+                });
+            }
+            txs.push(self.sign(deposit.to_tx(self.operator.address()))?);
+            next_nonce += 1;
+        }
+
+        self.next_block = confirmed + 1;
+        self.next_nonce = next_nonce;
+        Ok(txs)
+    }
+
+    // polls and submits the credits to a fastpay node, returns their hashes
+    pub async fn relay<U: Transport>(
+        &mut self,
+        fastpay: &Client<U>,
+    ) -> Result<Vec<B256>, BridgeError> {
+        let mut hashes = Vec::new();
+        for tx in self.poll().await? {
+            hashes.push(fastpay.send_bridge_tx(&tx).await?);
+        }
+        Ok(hashes)
+    }
+
+    fn sign(&self, tx: Tx) -> Result<Tx, BridgeError> {
+        let signature = self
+            .operator
+            .sign_message_sync(&tx.tx_hash())
+            .map_err(|e| BridgeError::SigningError(e.to_string()))?;
+        Ok(tx.with_signature(signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use client::mock::MockNode;
+    use deposit::tests::deposit_log;
+
+    #[tokio::test]
+    async fn test_relay_deposits() {
+        let primary = MockNode::new();
+        let fastpay = MockNode::new();
+        let operator = PrivateKeySigner::random();
+        let alice = Address::repeat_byte(1);
+        let mut relayer = Relayer::new(
+            Client::with_transport(primary.clone()),
+            Address::repeat_byte(0xbb),
+            operator.clone(),
+        )
+        .with_confirmations(2);
+
+        // Fewer blocks than the confirmations asked for
+        primary.set_block_number(U256::from(1));
+        assert!(relayer.poll().await.unwrap().is_empty());
+
+        primary.push_log(deposit_log(alice, 50, 1, 3));
+        primary.push_log(deposit_log(alice, 20, 0, 3));
+
+        primary.set_block_number(U256::from(5));
+        let hashes = relayer
+            .relay(&Client::with_transport(fastpay.clone()))
+            .await
+            .unwrap();
+        assert_eq!(hashes.len(), 2);
+        let sent = fastpay.bridge_txs();
+        assert_eq!(sent[0]["depositNonce"], 0);
+        assert_eq!(sent[0]["amount"], json!(U256::from(20)));
+        assert_eq!(sent[1]["depositNonce"], 1);
+        assert_eq!(relayer.next_nonce(), 2);
+
+        // Deposits seen again are not credited twice, a gap stops the relayer
+        primary.push_log(deposit_log(alice, 5, 3, 6));
+        primary.set_block_number(U256::from(8));
+        assert_eq!(
+            relayer.poll().await.err(),
+            Some(BridgeError::MissingDeposit {
+                expected: 2,
+                got: 3
+            })
+        );
+        assert_eq!(relayer.next_nonce(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_signed_by_operator() {
+        let primary = MockNode::new();
+        let operator = PrivateKeySigner::random();
+        let mut relayer = Relayer::new(
+            Client::with_transport(primary.clone()),
+            Address::repeat_byte(0xbb),
+            operator.clone(),
+        )
+        .with_confirmations(0)
+        .with_start(0, 4);
+        primary.push_log(deposit_log(Address::repeat_byte(1), 9, 4, 0));
+
+        let txs = relayer.poll().await.unwrap();
+        assert_eq!(txs.len(), 1);
+        assert!(txs[0].is_signed_by(operator.address()));
+        assert_eq!(txs[0].sequence(), Some(4));
+    }
+}
@@ -0,0 +1,133 @@
+// redemptions out of fastpay: once the committee has voted for a `RedeemToPrimary` tx, the votes
+// make a certificate the bridge contract can check before paying the recipient on the primary
+// chain
+
+use std::collections::HashMap;
+
+use alloy::primitives::B256;
+use committee::certificate::{Certificate, Vote};
+use committee::committee::Committee;
+use tx::tx::Tx;
+
+use crate::BridgeError;
+
+#[derive(Debug)]
+pub struct RedemptionCollector {
+    committee: Committee,
+    // redemptions still short of a quorum, by tx hash
+    pending: HashMap<B256, Certificate>,
+}
+
+impl RedemptionCollector {
+    pub fn new(committee: Committee) -> Self {
+        Self {
+            committee,
+            pending: HashMap::new(),
+        }
+    }
+
+    // adds an authority's vote for a redemption, returns its certificate once the votes reach a
+    // quorum. Votes that don't check out are refused without touching what was collected
+    pub fn add_vote(&mut self, tx: &Tx, vote: Vote) -> Result<Option<Certificate>, BridgeError> {
+        if !matches!(tx, Tx::RedeemToPrimary { .. }) {
+            return Err(BridgeError::InvalidRedemption(
+                "not a redemption tx".to_string(),
+            ));
+        }
+        if !self.committee.is_member(&vote.authority) {
+            return Err(BridgeError::Committee(
+                committee::CommitteeError::UnknownAuthority(vote.authority),
+            ));
+        }
+        vote.verify(tx).map_err(BridgeError::Committee)?;
+
+        let tx_hash = B256::from_slice(&tx.tx_hash());
+        let certificate = self
+            .pending
+            .entry(tx_hash)
+            .or_insert_with(|| Certificate::new(tx.clone()));
+        if certificate
+            .votes()
+            .iter()
+            .any(|known| known.authority == vote.authority)
+        {
+            return Ok(None);
+        }
+        certificate.add_vote(vote);
+
+        if certificate.verify(&self.committee).is_err() {
+            return Ok(None);
+        }
+        Ok(self.pending.remove(&tx_hash))
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use committee::certificate::certificate_message;
+
+    fn vote(authority: &PrivateKeySigner, tx: &Tx) -> Vote {
+        Vote {
+            authority: authority.address(),
+            signature: authority
+                .sign_message_sync(&certificate_message(tx))
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_redemption_certificate() {
+        let authorities: Vec<_> = (0..4).map(|_| PrivateKeySigner::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+        let mut collector = RedemptionCollector::new(committee.clone());
+
+        let user = PrivateKeySigner::random();
+        let tx = Tx::redeem_to_primary(
+            user.address(),
+            Address::repeat_byte(7),
+            U256::from(10),
+            0,
+            None,
+        );
+        let signature = user.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = tx.with_signature(signature);
+
+        assert!(collector
+            .add_vote(&tx, vote(&authorities[0], &tx))
+            .unwrap()
+            .is_none());
+        // The same authority twice adds no stake
+        assert!(collector
+            .add_vote(&tx, vote(&authorities[0], &tx))
+            .unwrap()
+            .is_none());
+        assert!(collector
+            .add_vote(&tx, vote(&authorities[1], &tx))
+            .unwrap()
+            .is_none());
+
+        // A vote from outside the committee is refused
+        let outsider = PrivateKeySigner::random();
+        assert!(collector.add_vote(&tx, vote(&outsider, &tx)).is_err());
+
+        let certificate = collector
+            .add_vote(&tx, vote(&authorities[2], &tx))
+            .unwrap()
+            .unwrap();
+        assert!(certificate.verify(&committee).is_ok());
+        assert_eq!(collector.pending(), 0);
+
+        let transfer = Tx::new(user.address(), Address::repeat_byte(7), U256::from(1), None);
+        assert!(collector
+            .add_vote(&transfer, vote(&authorities[0], &transfer))
+            .is_err());
+    }
+}
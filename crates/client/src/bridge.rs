@@ -0,0 +1,82 @@
+// bridge txs as the node accepts them over rpc
+
+use alloy::primitives::hex;
+use serde_json::{json, Value};
+use tx::tx::Tx;
+
+use crate::ClientError;
+
+// the `fastpay_sendBridgeTx` request for a signed deposit or redemption
+pub fn bridge_tx_request(tx: &Tx) -> Result<Value, ClientError> {
+    let signature = tx.signature().ok_or_else(|| {
+        ClientError::InvalidRequest("bridge tx must be signed before sending".to_string())
+    })?;
+
+    let mut request = match tx {
+        Tx::FundFromPrimary {
+            from,
+            to,
+            amount,
+            deposit_nonce,
+            ..
+        } => json!({
+            "kind": "fund",
+            "from": from,
+            "to": to,
+            "amount": amount,
+            "depositNonce": deposit_nonce,
+        }),
+        Tx::RedeemToPrimary {
+            from,
+            recipient,
+            amount,
+            sequence,
+            ..
+        } => json!({
+            "kind": "redeem",
+            "from": from,
+            "recipient": recipient,
+            "amount": amount,
+            "sequence": sequence,
+        }),
+        _ => return Err(ClientError::InvalidRequest("not a bridge tx".to_string())),
+    };
+
+    request["signature"] = json!(hex::encode_prefixed(signature.as_bytes()));
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_bridge_tx_request() {
+        let operator = PrivateKeySigner::random();
+        let tx = Tx::fund_from_primary(
+            operator.address(),
+            Address::repeat_byte(2),
+            U256::from(7),
+            3,
+            None,
+        );
+        assert!(bridge_tx_request(&tx).is_err());
+
+        let signature = operator.sign_message_sync(&tx.tx_hash()).unwrap();
+        let request = bridge_tx_request(&tx.with_signature(signature)).unwrap();
+        assert_eq!(request["kind"], "fund");
+        assert_eq!(request["depositNonce"], 3);
+        assert_eq!(request["amount"], json!(U256::from(7)));
+
+        let transfer = Tx::new(
+            operator.address(),
+            Address::repeat_byte(2),
+            U256::from(1),
+            Some(signature),
+        );
+        assert!(bridge_tx_request(&transfer).is_err());
+    }
+}
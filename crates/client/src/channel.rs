@@ -0,0 +1,126 @@
+// payment channels as the node reports and accepts them over rpc
+
+use alloy::primitives::{hex, Address, PrimitiveSignature};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tx::tx::Tx;
+
+use crate::ClientError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelInfo {
+    pub payer: Address,
+    pub payee: Address,
+    pub deposit: u64,
+    pub challenge_period: u64,
+    // set once the payer has started the timeout
+    pub expires_at: Option<u64>,
+}
+
+fn signature_hex(signature: &PrimitiveSignature) -> String {
+    hex::encode_prefixed(signature.as_bytes())
+}
+
+// the `fastpay_sendChannelTx` request for a signed channel tx
+pub fn channel_tx_request(tx: &Tx) -> Result<Value, ClientError> {
+    let signature = tx.signature().ok_or_else(|| {
+        ClientError::InvalidRequest("channel tx must be signed before sending".to_string())
+    })?;
+
+    let mut request = match tx {
+        Tx::Transfer { .. }
+        | Tx::Settlement { .. }
+        | Tx::MultisigTransfer { .. }
+        | Tx::Mint { .. }
+        | Tx::Burn { .. }
+        | Tx::FundFromPrimary { .. }
+        | Tx::RedeemToPrimary { .. }
+        | Tx::SetPolicy { .. }
+        | Tx::Schedule { .. }
+        | Tx::CancelSchedule { .. }
+        | Tx::EscrowCreate { .. }
+        | Tx::EscrowRelease { .. }
+        | Tx::EscrowRefund { .. }
+        | Tx::HtlcLock { .. }
+        | Tx::HtlcClaim { .. }
+        | Tx::HtlcRefund { .. }
+        | Tx::IssueAsset { .. } => {
+            return Err(ClientError::InvalidRequest(
+                "not a payment channel tx".to_string(),
+            ))
+        }
+        Tx::OpenChannel {
+            from,
+            to,
+            amount,
+            challenge_period,
+            ..
+        } => json!({
+            "kind": "open",
+            "from": from,
+            "to": to,
+            "amount": amount,
+            "challengePeriod": challenge_period,
+        }),
+        Tx::CloseChannel {
+            from,
+            to,
+            channel_id,
+            amount,
+            update_signature,
+            ..
+        } => json!({
+            "kind": "close",
+            "from": from,
+            "to": to,
+            "channelId": channel_id,
+            "amount": amount,
+            "updateSignature": signature_hex(update_signature),
+        }),
+        Tx::StartChannelTimeout {
+            from,
+            to,
+            channel_id,
+            ..
+        } => json!({ "kind": "startTimeout", "from": from, "to": to, "channelId": channel_id }),
+        Tx::ClaimChannelTimeout {
+            from,
+            to,
+            channel_id,
+            ..
+        } => json!({ "kind": "claimTimeout", "from": from, "to": to, "channelId": channel_id }),
+    };
+
+    request["signature"] = json!(signature_hex(&signature));
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{B256, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_channel_tx_request() {
+        let signer = PrivateKeySigner::random();
+        let to = Address::repeat_byte(2);
+        let tx = Tx::start_channel_timeout(signer.address(), to, B256::repeat_byte(1), None);
+
+        assert!(matches!(
+            channel_tx_request(&tx),
+            Err(ClientError::InvalidRequest(_))
+        ));
+
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let request = channel_tx_request(&tx.with_signature(signature)).unwrap();
+        assert_eq!(request["kind"], "startTimeout");
+        assert_eq!(request["channelId"], json!(B256::repeat_byte(1)));
+        assert_eq!(request["signature"], json!(signature_hex(&signature)));
+
+        let transfer = Tx::new(signer.address(), to, U256::from(1), Some(signature));
+        assert!(channel_tx_request(&transfer).is_err());
+    }
+}
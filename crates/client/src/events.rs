@@ -0,0 +1,393 @@
+// typed node events, decoded from `eth_getLogs` entries and `eth_subscription` notifications
+
+use std::collections::BTreeMap;
+
+use alloy::primitives::{keccak256, Address, Bloom, BloomInput, Bytes, B256, U256, U64};
+use futures::stream::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+
+pub const TRANSFER_EVENT_SIGNATURE: &str = "Transfer(address,address,uint256)";
+
+// how many recent heads are remembered to measure the depth of a reorg
+const HEAD_HISTORY: usize = 128;
+
+pub fn transfer_topic() -> B256 {
+    keccak256(TRANSFER_EVENT_SIGNATURE)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventError {
+    Malformed(String),
+    // a well formed log that isn't a transfer
+    NotATransfer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    pub block_number: Option<U64>,
+    pub transaction_hash: Option<B256>,
+    pub log_index: Option<U64>,
+    #[serde(default)]
+    pub removed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferEvent {
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    pub block_number: Option<u64>,
+    pub tx_hash: Option<B256>,
+    // set when the transfer was dropped from the canonical chain by a reorg
+    pub removed: bool,
+}
+
+impl TransferEvent {
+    pub fn from_log(log: &RpcLog) -> Result<Self, EventError> {
+        if log.topics.first() != Some(&transfer_topic()) {
+            return Err(EventError::NotATransfer);
+        }
+        if log.topics.len() != 3 {
+            return Err(EventError::Malformed(format!(
+                "transfer log has {} topics, expected 3",
+                log.topics.len()
+            )));
+        }
+        if log.data.len() != 32 {
+            return Err(EventError::Malformed(format!(
+                "transfer log has {} bytes of data, expected 32",
+                log.data.len()
+            )));
+        }
+
+        Ok(Self {
+            from: Address::from_word(log.topics[1]),
+            to: Address::from_word(log.topics[2]),
+            amount: U256::from_be_slice(&log.data),
+            block_number: log.block_number.map(|n| n.to::<u64>()),
+            tx_hash: log.transaction_hash,
+            removed: log.removed,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, EventError> {
+        let log: RpcLog = serde_json::from_value(notification_result(value).clone())
+            .map_err(|e| EventError::Malformed(e.to_string()))?;
+        Self::from_log(&log)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockEvent {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    // only present on full headers, not every notification carries it
+    pub state_root: Option<B256>,
+    pub timestamp: u64,
+    pub transactions: Vec<B256>,
+    // the addresses the block's txs touch, None from nodes that don't serve it
+    pub address_bloom: Option<Bloom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcHeader {
+    number: U64,
+    hash: B256,
+    parent_hash: B256,
+    #[serde(default)]
+    state_root: Option<B256>,
+    timestamp: U64,
+    #[serde(default)]
+    transactions: Vec<B256>,
+    #[serde(default)]
+    address_bloom: Option<Bloom>,
+}
+
+impl BlockEvent {
+    pub fn from_json(value: &Value) -> Result<Self, EventError> {
+        let header: RpcHeader = serde_json::from_value(notification_result(value).clone())
+            .map_err(|e| EventError::Malformed(e.to_string()))?;
+
+        Ok(Self {
+            number: header.number.to::<u64>(),
+            hash: header.hash,
+            parent_hash: header.parent_hash,
+            state_root: header.state_root,
+            timestamp: header.timestamp.to::<u64>(),
+            transactions: header.transactions,
+            address_bloom: header.address_bloom,
+        })
+    }
+
+    // false only when the block certainly has no tx touching `address`, wallets syncing their
+    // history can skip fetching it
+    pub fn may_involve(&self, address: &Address) -> bool {
+        self.address_bloom
+            .is_none_or(|bloom| bloom.contains_input(BloomInput::Raw(address.as_slice())))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReorgEvent {
+    // the head that was abandoned
+    pub old_head: B256,
+    // how many blocks of the old chain were replaced, as far as the tracked history goes
+    pub depth: u64,
+    pub new_head: BlockEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadEvent {
+    NewBlock(BlockEvent),
+    Reorg(ReorgEvent),
+}
+
+// HeadTracker follows a stream of heads and notices when one doesn't build on the previous one
+#[derive(Debug, Default)]
+pub struct HeadTracker {
+    heads: BTreeMap<u64, B256>,
+}
+
+impl HeadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, block: BlockEvent) -> HeadEvent {
+        let previous = self
+            .heads
+            .last_key_value()
+            .map(|(number, hash)| (*number, *hash));
+
+        let event = match previous {
+            Some((number, hash)) if block.parent_hash != hash || block.number != number + 1 => {
+                // the new head forks off at the last block we agree on with its parent
+                let fork_point = self
+                    .heads
+                    .iter()
+                    .rev()
+                    .find(|(_, hash)| **hash == block.parent_hash)
+                    .map(|(number, _)| *number);
+                let depth = match fork_point {
+                    Some(fork_point) => number - fork_point,
+                    None => number + 1 - block.number.min(number + 1),
+                };
+
+                HeadEvent::Reorg(ReorgEvent {
+                    old_head: hash,
+                    depth,
+                    new_head: block.clone(),
+                })
+            }
+            _ => HeadEvent::NewBlock(block.clone()),
+        };
+
+        self.heads.split_off(&block.number);
+        self.heads.insert(block.number, block.hash);
+        while self.heads.len() > HEAD_HISTORY {
+            self.heads.pop_first();
+        }
+
+        event
+    }
+}
+
+// subscription notifications wrap the payload in `params.result`, log entries don't
+pub fn notification_result(value: &Value) -> &Value {
+    value
+        .get("params")
+        .and_then(|params| params.get("result"))
+        .unwrap_or(value)
+}
+
+// keeps the transfers out of a stream of logs or log notifications, anything else is skipped
+pub fn transfer_events<S>(values: S) -> impl Stream<Item = TransferEvent>
+where
+    S: Stream<Item = Value>,
+{
+    values.filter_map(|value| async move { TransferEvent::from_json(&value).ok() })
+}
+
+pub fn block_events<S>(values: S) -> impl Stream<Item = BlockEvent>
+where
+    S: Stream<Item = Value>,
+{
+    values.filter_map(|value| async move { BlockEvent::from_json(&value).ok() })
+}
+
+// like `block_events`, but reports a reorg whenever a head doesn't extend the previous one
+pub fn head_events<S>(values: S) -> impl Stream<Item = HeadEvent>
+where
+    S: Stream<Item = Value>,
+{
+    let mut tracker = HeadTracker::new();
+    block_events(values).map(move |block| tracker.observe(block))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use serde_json::json;
+
+    fn word(address: Address) -> B256 {
+        address.into_word()
+    }
+
+    fn transfer_log(from: Address, to: Address, amount: u64, removed: bool) -> Value {
+        json!({
+            "address": Address::ZERO,
+            "topics": [transfer_topic(), word(from), word(to)],
+            "data": B256::from(U256::from(amount)),
+            "blockNumber": "0x10",
+            "transactionHash": B256::repeat_byte(0xab),
+            "logIndex": "0x0",
+            "removed": removed,
+        })
+    }
+
+    fn header(number: u64, hash: u8, parent: u8) -> Value {
+        json!({
+            "number": format!("{:#x}", number),
+            "hash": B256::repeat_byte(hash),
+            "parentHash": B256::repeat_byte(parent),
+            "timestamp": "0x6553f100",
+            "transactions": [],
+        })
+    }
+
+    fn block(number: u64, hash: u8, parent: u8) -> BlockEvent {
+        BlockEvent::from_json(&header(number, hash, parent)).unwrap()
+    }
+
+    #[test]
+    fn test_decode_transfer_log() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+
+        let event = TransferEvent::from_json(&transfer_log(from, to, 500, false)).unwrap();
+        assert_eq!(event.from, from);
+        assert_eq!(event.to, to);
+        assert_eq!(event.amount, U256::from(500));
+        assert_eq!(event.block_number, Some(16));
+        assert_eq!(event.tx_hash, Some(B256::repeat_byte(0xab)));
+        assert!(!event.removed);
+    }
+
+    #[test]
+    fn test_decode_transfer_notification() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": {"subscription": "0x1", "result": transfer_log(from, to, 7, true)},
+        });
+
+        let event = TransferEvent::from_json(&notification).unwrap();
+        assert_eq!(event.amount, U256::from(7));
+        assert!(event.removed);
+    }
+
+    #[test]
+    fn test_decode_non_transfer_log() {
+        let mut log = transfer_log(Address::ZERO, Address::ZERO, 1, false);
+        log["topics"][0] = json!(B256::repeat_byte(9));
+
+        assert_eq!(
+            TransferEvent::from_json(&log).unwrap_err(),
+            EventError::NotATransfer
+        );
+        assert!(matches!(
+            TransferEvent::from_json(&json!({"foo": 1})),
+            Err(EventError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_block() {
+        let event = block(26, 2, 1);
+        assert_eq!(event.number, 26);
+        assert_eq!(event.hash, B256::repeat_byte(2));
+        assert_eq!(event.parent_hash, B256::repeat_byte(1));
+        assert_eq!(event.timestamp, 1_700_000_000);
+        // Without a bloom no block can be skipped
+        assert!(event.may_involve(&Address::ZERO));
+
+        let alice = Address::repeat_byte(1);
+        let mut bloom = Bloom::ZERO;
+        bloom.accrue(BloomInput::Raw(alice.as_slice()));
+        let mut value = header(26, 2, 1);
+        value["addressBloom"] = json!(bloom);
+        let event = BlockEvent::from_json(&value).unwrap();
+        assert!(event.may_involve(&alice));
+        assert!(!event.may_involve(&Address::repeat_byte(2)));
+    }
+
+    #[test]
+    fn test_head_tracker_detects_reorg() {
+        let mut tracker = HeadTracker::new();
+
+        assert!(matches!(
+            tracker.observe(block(1, 1, 0)),
+            HeadEvent::NewBlock(_)
+        ));
+        assert!(matches!(
+            tracker.observe(block(2, 2, 1)),
+            HeadEvent::NewBlock(_)
+        ));
+        assert!(matches!(
+            tracker.observe(block(3, 3, 2)),
+            HeadEvent::NewBlock(_)
+        ));
+
+        // A competing block 3 on top of block 2 replaces one block
+        match tracker.observe(block(3, 0x33, 2)) {
+            HeadEvent::Reorg(reorg) => {
+                assert_eq!(reorg.old_head, B256::repeat_byte(3));
+                assert_eq!(reorg.depth, 1);
+                assert_eq!(reorg.new_head.hash, B256::repeat_byte(0x33));
+            }
+            other => panic!("expected reorg, got {:?}", other),
+        }
+
+        // A new block 2 on top of block 1 replaces two blocks
+        match tracker.observe(block(2, 0x22, 1)) {
+            HeadEvent::Reorg(reorg) => assert_eq!(reorg.depth, 2),
+            other => panic!("expected reorg, got {:?}", other),
+        }
+
+        assert!(matches!(
+            tracker.observe(block(3, 0x44, 0x22)),
+            HeadEvent::NewBlock(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_adapters() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+
+        let logs = stream::iter(vec![
+            transfer_log(from, to, 1, false),
+            json!({"not": "a log"}),
+            transfer_log(from, to, 2, false),
+        ]);
+        let transfers: Vec<TransferEvent> = transfer_events(logs).collect().await;
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[1].amount, U256::from(2));
+
+        let heads = stream::iter(vec![header(1, 1, 0), header(2, 2, 1), header(2, 0x22, 1)]);
+        let events: Vec<HeadEvent> = head_events(heads).collect().await;
+        assert!(matches!(events[0], HeadEvent::NewBlock(_)));
+        assert!(matches!(events[1], HeadEvent::NewBlock(_)));
+        assert!(matches!(events[2], HeadEvent::Reorg(_)));
+    }
+}
@@ -0,0 +1,98 @@
+// fee suggestions as fastpay_estimateFee reports them
+
+use std::str::FromStr;
+
+use alloy::primitives::U256;
+use serde::Deserialize;
+
+use crate::ClientError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl FromStr for FeeLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            other => Err(format!(
+                "invalid fee level {}, expected low, medium or high",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSuggestion {
+    pub fee: u64,
+    // blocks until a transfer paying the fee is expected in one, 1 for the next block
+    pub expected_blocks: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub low: FeeSuggestion,
+    pub medium: FeeSuggestion,
+    pub high: FeeSuggestion,
+}
+
+impl FeeEstimate {
+    pub fn level(&self, level: FeeLevel) -> FeeSuggestion {
+        match level {
+            FeeLevel::Low => self.low,
+            FeeLevel::Medium => self.medium,
+            FeeLevel::High => self.high,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcFeeSuggestion {
+    fee: String,
+    expected_blocks: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RpcFeeEstimate {
+    low: RpcFeeSuggestion,
+    medium: RpcFeeSuggestion,
+    high: RpcFeeSuggestion,
+}
+
+impl TryFrom<RpcFeeSuggestion> for FeeSuggestion {
+    type Error = ClientError;
+
+    fn try_from(suggestion: RpcFeeSuggestion) -> Result<Self, Self::Error> {
+        let quantity = |value: &str| {
+            U256::from_str(value)
+                .ok()
+                .and_then(|value| u64::try_from(value).ok())
+                .ok_or_else(|| ClientError::InvalidResponse(format!("invalid quantity {}", value)))
+        };
+        Ok(Self {
+            fee: quantity(&suggestion.fee)?,
+            expected_blocks: quantity(&suggestion.expected_blocks)?,
+        })
+    }
+}
+
+impl TryFrom<RpcFeeEstimate> for FeeEstimate {
+    type Error = ClientError;
+
+    fn try_from(estimate: RpcFeeEstimate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            low: estimate.low.try_into()?,
+            medium: estimate.medium.try_into()?,
+            high: estimate.high.try_into()?,
+        })
+    }
+}
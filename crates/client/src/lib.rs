@@ -0,0 +1,297 @@
+use alloy::primitives::{hex, Address, PrimitiveSignature, B256, U256};
+use committee::{certificate::Certificate, committee::Committee};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tx::tx::Tx;
+
+pub mod bridge;
+pub mod channel;
+pub mod events;
+pub mod fee;
+pub mod middleware;
+pub mod mock;
+pub mod retry;
+pub mod transport;
+
+use channel::ChannelInfo;
+use events::{BlockEvent, RpcLog, TransferEvent};
+use fee::{FeeEstimate, RpcFeeEstimate};
+use transport::{HttpTransport, Transport, TransportConfig};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    Transport(String),
+    Timeout,
+    Http(u16),
+    Rpc { code: i64, message: String },
+    InvalidResponse(String),
+    InvalidRequest(String),
+}
+
+impl ClientError {
+    // errors that may go away on their own, a node answering with an rpc error won't change its mind
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(_) | Self::Timeout => true,
+            Self::Http(status) => *status == 429 || *status >= 500,
+            Self::Rpc { .. } | Self::InvalidResponse(_) | Self::InvalidRequest(_) => false,
+        }
+    }
+}
+
+// Client talks to a fastpay node over JSON-RPC
+pub struct Client<T = HttpTransport> {
+    transport: T,
+}
+
+impl Client<HttpTransport> {
+    pub fn new(url: impl Into<String>) -> Result<Self, ClientError> {
+        let transport = HttpTransport::new(TransportConfig::new(url))?;
+        Ok(Self { transport })
+    }
+}
+
+impl<T: Transport> Client<T> {
+    pub fn with_transport(transport: T) -> Self {
+        Self { transport }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub async fn block_number(&self) -> Result<U256, ClientError> {
+        let number: String = self.request("eth_blockNumber", json!([])).await?;
+        parse_quantity(&number)
+    }
+
+    // the chain txs sent to the node have to be signed for
+    pub async fn chain_id(&self) -> Result<u64, ClientError> {
+        let chain_id: String = self.request("eth_chainId", json!([])).await?;
+        Ok(parse_quantity(&chain_id)?.saturating_to())
+    }
+
+    pub async fn get_balance(&self, address: Address) -> Result<U256, ClientError> {
+        let balance: String = self
+            .request("eth_getBalance", json!([address, "latest"]))
+            .await?;
+        parse_quantity(&balance)
+    }
+
+    // the sequence number for the address's next transfer order, counting its orders the node
+    // holds in its mempool when `pending` is set
+    pub async fn get_transaction_count(
+        &self,
+        address: Address,
+        pending: bool,
+    ) -> Result<u64, ClientError> {
+        let tag = if pending { "pending" } else { "latest" };
+        let count: String = self
+            .request("eth_getTransactionCount", json!([address, tag]))
+            .await?;
+        Ok(parse_quantity(&count)?.saturating_to())
+    }
+
+    // submits a signed transfer, returns its hash once the node has queued it
+    pub async fn send_transfer(&self, tx: &Tx) -> Result<B256, ClientError> {
+        let signature = tx.signature().ok_or_else(|| {
+            ClientError::InvalidRequest("transfer must be signed before sending".to_string())
+        })?;
+        let mut transfer = json!({
+            "from": tx.from(),
+            "to": tx.to(),
+            "amount": tx.amount(),
+            "signature": hex::encode_prefixed(signature.as_bytes()),
+        });
+        if let Some(sequence) = tx.sequence() {
+            transfer["sequence"] = json!(sequence);
+        }
+        if tx.fee() > 0 {
+            transfer["fee"] = json!(tx.fee());
+        }
+        if let Some(chain_id) = tx.chain_id() {
+            transfer["chainId"] = json!(chain_id);
+        }
+
+        let tx_hash: String = self
+            .request("fastpay_sendTransfer", json!([transfer]))
+            .await?;
+        B256::from_str(&tx_hash).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    // takes a tx the node hasn't put in a block yet back out of its mempool, false if it is no
+    // longer there. `signature` is the sender's over tx::cancel::cancellation_message
+    pub async fn cancel_transaction(
+        &self,
+        tx_hash: B256,
+        signature: &PrimitiveSignature,
+    ) -> Result<bool, ClientError> {
+        self.request(
+            "fastpay_cancelTransaction",
+            json!([tx_hash, hex::encode_prefixed(signature.as_bytes())]),
+        )
+        .await
+    }
+
+    // submits a signed payment channel tx, returns its hash once the node has queued it
+    pub async fn send_channel_tx(&self, tx: &Tx) -> Result<B256, ClientError> {
+        let request = channel::channel_tx_request(tx)?;
+        let tx_hash: String = self
+            .request("fastpay_sendChannelTx", json!([request]))
+            .await?;
+        B256::from_str(&tx_hash).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    // submits a signed deposit or redemption, returns its hash once the node has queued it
+    pub async fn send_bridge_tx(&self, tx: &Tx) -> Result<B256, ClientError> {
+        let request = bridge::bridge_tx_request(tx)?;
+        let tx_hash: String = self
+            .request("fastpay_sendBridgeTx", json!([request]))
+            .await?;
+        B256::from_str(&tx_hash).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    // None once the channel has been closed, or if it never existed
+    pub async fn get_channel(&self, channel_id: B256) -> Result<Option<ChannelInfo>, ClientError> {
+        self.request("fastpay_getChannel", json!([channel_id]))
+            .await
+    }
+
+    // None until the transfer has settled. Nothing here is checked, see `get_verified_certificate`
+    pub async fn get_certificate(&self, tx_hash: B256) -> Result<Option<Certificate>, ClientError> {
+        self.request("fastpay_getCertificate", json!([tx_hash]))
+            .await
+    }
+
+    // the certificate of a settled transfer, checked against `committee` so a recipient doesn't
+    // have to trust the node it asked
+    pub async fn get_verified_certificate(
+        &self,
+        tx_hash: B256,
+        committee: &Committee,
+    ) -> Result<Option<Certificate>, ClientError> {
+        let Some(certificate) = self.get_certificate(tx_hash).await? else {
+            return Ok(None);
+        };
+        if certificate.tx_hash() != tx_hash {
+            return Err(ClientError::InvalidResponse(format!(
+                "asked for the certificate of {} but got one for {}",
+                tx_hash,
+                certificate.tx_hash()
+            )));
+        }
+        certificate
+            .verify(committee)
+            .map_err(|e| ClientError::InvalidResponse(format!("invalid certificate: {:?}", e)))?;
+        Ok(Some(certificate))
+    }
+
+    // what to pay for a transfer to land soon, see FeeEstimate::level
+    pub async fn estimate_fee(&self) -> Result<FeeEstimate, ClientError> {
+        let estimate: RpcFeeEstimate = self.request("fastpay_estimateFee", json!([])).await?;
+        estimate.try_into()
+    }
+
+    // the latest block when `number` is None
+    pub async fn get_block_by_number(
+        &self,
+        number: Option<u64>,
+    ) -> Result<Option<BlockEvent>, ClientError> {
+        let tag = match number {
+            Some(number) => format!("{:#x}", number),
+            None => "latest".to_string(),
+        };
+
+        let block: Value = self
+            .request("eth_getBlockByNumber", json!([tag, false]))
+            .await?;
+        if block.is_null() {
+            return Ok(None);
+        }
+        BlockEvent::from_json(&block)
+            .map(Some)
+            .map_err(|e| ClientError::InvalidResponse(format!("{:?}", e)))
+    }
+
+    pub async fn get_block_by_hash(&self, hash: B256) -> Result<Option<BlockEvent>, ClientError> {
+        let block: Value = self
+            .request("eth_getBlockByHash", json!([hash, false]))
+            .await?;
+        if block.is_null() {
+            return Ok(None);
+        }
+        BlockEvent::from_json(&block)
+            .map(Some)
+            .map_err(|e| ClientError::InvalidResponse(format!("{:?}", e)))
+    }
+
+    // transfers logged between the two blocks, optionally only those emitted by `address`
+    pub async fn get_transfer_events(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        address: Option<Address>,
+    ) -> Result<Vec<TransferEvent>, ClientError> {
+        let mut filter = json!({
+            "fromBlock": format!("{:#x}", from_block),
+            "toBlock": format!("{:#x}", to_block),
+            "topics": [events::transfer_topic()],
+        });
+        if let Some(address) = address {
+            filter["address"] = json!(address);
+        }
+
+        let logs = self.get_logs(filter).await?;
+        logs.iter()
+            .map(|log| {
+                TransferEvent::from_log(log)
+                    .map_err(|e| ClientError::InvalidResponse(format!("{:?}", e)))
+            })
+            .collect()
+    }
+
+    // the raw logs matching an `eth_getLogs` filter, for events other than transfers. Works against
+    // any node that speaks the eth api, not only fastpay ones
+    pub async fn get_logs(&self, filter: Value) -> Result<Vec<RpcLog>, ClientError> {
+        self.request("eth_getLogs", json!([filter])).await
+    }
+
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, ClientError> {
+        let value = self.transport.request_value(method, params).await?;
+        serde_json::from_value(value).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+}
+
+fn parse_quantity(value: &str) -> Result<U256, ClientError> {
+    U256::from_str(value).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_errors() {
+        assert!(ClientError::Timeout.is_retryable());
+        assert!(ClientError::Transport("connection refused".to_string()).is_retryable());
+        assert!(ClientError::Http(503).is_retryable());
+        assert!(ClientError::Http(429).is_retryable());
+        assert!(!ClientError::Http(401).is_retryable());
+        assert!(!ClientError::Rpc {
+            code: -32000,
+            message: "insufficient balance".to_string()
+        }
+        .is_retryable());
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity("0x1234").unwrap(), U256::from(0x1234));
+        assert!(parse_quantity("not a number").is_err());
+    }
+}
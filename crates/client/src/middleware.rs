@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::transport::RpcRequest;
+use crate::ClientError;
+
+// Middleware gets to look at (and change) every request the transport sends, every response it
+// gets back and every retry it makes. All hooks are optional.
+pub trait Middleware: Send + Sync {
+    fn on_request(&self, _request: &mut RpcRequest) {}
+
+    fn on_response(
+        &self,
+        _request: &RpcRequest,
+        _result: &Result<Value, ClientError>,
+        _elapsed: Duration,
+    ) {
+    }
+
+    fn on_retry(&self, _request: &RpcRequest, _attempt: u32, _error: &ClientError) {}
+}
+
+// adds a fixed header to every request, e.g. an api key
+#[derive(Debug, Clone)]
+pub struct HeaderMiddleware {
+    name: String,
+    value: String,
+}
+
+impl HeaderMiddleware {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn bearer(token: impl AsRef<str>) -> Self {
+        Self::new("Authorization", format!("Bearer {}", token.as_ref()))
+    }
+}
+
+impl Middleware for HeaderMiddleware {
+    fn on_request(&self, request: &mut RpcRequest) {
+        request.set_header(self.name.clone(), self.value.clone());
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingMiddleware;
+
+impl Middleware for LoggingMiddleware {
+    fn on_response(
+        &self,
+        request: &RpcRequest,
+        result: &Result<Value, ClientError>,
+        elapsed: Duration,
+    ) {
+        match result {
+            Ok(_) => tracing::debug!(method = request.method(), ?elapsed, "rpc ok"),
+            Err(e) => tracing::warn!(method = request.method(), ?elapsed, error = ?e, "rpc failed"),
+        }
+    }
+
+    fn on_retry(&self, request: &RpcRequest, attempt: u32, error: &ClientError) {
+        tracing::info!(method = request.method(), attempt, ?error, "rpc retry");
+    }
+}
+
+// counters are shared, so keep an `Arc<MetricsMiddleware>` around to read them
+#[derive(Debug, Default)]
+pub struct MetricsMiddleware {
+    requests: AtomicU64,
+    failures: AtomicU64,
+    retries: AtomicU64,
+    total_latency_micros: AtomicU64,
+}
+
+impl MetricsMiddleware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    pub fn average_latency(&self) -> Duration {
+        let requests = self.requests();
+        if requests == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.total_latency_micros.load(Ordering::Relaxed) / requests)
+    }
+}
+
+impl Middleware for MetricsMiddleware {
+    fn on_response(
+        &self,
+        _request: &RpcRequest,
+        result: &Result<Value, ClientError>,
+        elapsed: Duration,
+    ) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.total_latency_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        if result.is_err() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn on_retry(&self, _request: &RpcRequest, _attempt: u32, _error: &ClientError) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<M: Middleware> Middleware for std::sync::Arc<M> {
+    fn on_request(&self, request: &mut RpcRequest) {
+        (**self).on_request(request)
+    }
+
+    fn on_response(
+        &self,
+        request: &RpcRequest,
+        result: &Result<Value, ClientError>,
+        elapsed: Duration,
+    ) {
+        (**self).on_response(request, result, elapsed)
+    }
+
+    fn on_retry(&self, request: &RpcRequest, attempt: u32, error: &ClientError) {
+        (**self).on_retry(request, attempt, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_header_middleware() {
+        let mut request = RpcRequest::new(1, "eth_blockNumber", json!([]));
+        HeaderMiddleware::bearer("secret").on_request(&mut request);
+        HeaderMiddleware::new("x-api-key", "key").on_request(&mut request);
+
+        assert_eq!(request.header("Authorization"), Some("Bearer secret"));
+        assert_eq!(request.header("x-api-key"), Some("key"));
+    }
+
+    #[test]
+    fn test_metrics_middleware() {
+        let metrics = MetricsMiddleware::new();
+        let request = RpcRequest::new(1, "eth_blockNumber", json!([]));
+
+        metrics.on_response(&request, &Ok(json!("0x1")), Duration::from_millis(10));
+        metrics.on_retry(&request, 1, &ClientError::Timeout);
+        metrics.on_response(
+            &request,
+            &Err(ClientError::Timeout),
+            Duration::from_millis(30),
+        );
+
+        assert_eq!(metrics.requests(), 2);
+        assert_eq!(metrics.failures(), 1);
+        assert_eq!(metrics.retries(), 1);
+        assert_eq!(metrics.average_latency(), Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,590 @@
+// in-memory stand-in for a node, so code built on the client can be tested without running one
+//
+//     let node = MockNode::new();
+//     node.set_balance(address, U256::from(100));
+//     let client = Client::with_transport(node.clone());
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::transport::Transport;
+use crate::ClientError;
+
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+const INVALID_PARAMS_CODE: i64 = -32602;
+
+#[derive(Debug, Default)]
+struct MockState {
+    balances: HashMap<Address, U256>,
+    transaction_counts: HashMap<Address, u64>,
+    block_number: U256,
+    logs: Vec<Value>,
+    blocks: Vec<Value>,
+    transfers: Vec<Value>,
+    channels: HashMap<B256, Value>,
+    certificates: HashMap<B256, Value>,
+    channel_txs: Vec<Value>,
+    bridge_txs: Vec<Value>,
+    fee_estimate: Option<Value>,
+    results: HashMap<String, Value>,
+    delay: Duration,
+    // failures handed out to the next calls, whatever the method
+    next_failures: VecDeque<ClientError>,
+    // failures returned for every call to a method until cleared
+    method_failures: HashMap<String, ClientError>,
+    calls: Vec<(String, Value)>,
+}
+
+// clones share the same state, keep one to program the node while the client owns another
+#[derive(Debug, Clone, Default)]
+pub struct MockNode {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockNode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_balance(&self, address: Address, balance: U256) {
+        self.state.lock().unwrap().balances.insert(address, balance);
+    }
+
+    // served by `eth_getTransactionCount` whatever the block tag
+    pub fn set_transaction_count(&self, address: Address, count: u64) {
+        self.state
+            .lock()
+            .unwrap()
+            .transaction_counts
+            .insert(address, count);
+    }
+
+    pub fn set_block_number(&self, block_number: U256) {
+        self.state.lock().unwrap().block_number = block_number;
+    }
+
+    // logs handed back by every `eth_getLogs` call, the filter is ignored
+    pub fn push_log(&self, log: Value) {
+        self.state.lock().unwrap().logs.push(log);
+    }
+
+    // blocks served by `eth_getBlockByNumber`, the last one pushed is the latest
+    pub fn push_block(&self, block: Value) {
+        self.state.lock().unwrap().blocks.push(block);
+    }
+
+    // transfers received through `fastpay_sendTransfer`, in order
+    pub fn transfers(&self) -> Vec<Value> {
+        self.state.lock().unwrap().transfers.clone()
+    }
+
+    // what `fastpay_getChannel` answers for `channel_id`, None removes it
+    pub fn set_channel(&self, channel_id: B256, channel: Option<Value>) {
+        let mut state = self.state.lock().unwrap();
+        match channel {
+            Some(channel) => state.channels.insert(channel_id, channel),
+            None => state.channels.remove(&channel_id),
+        };
+    }
+
+    // what `fastpay_getCertificate` answers for `tx_hash`, None removes it
+    pub fn set_certificate(&self, tx_hash: B256, certificate: Option<Value>) {
+        let mut state = self.state.lock().unwrap();
+        match certificate {
+            Some(certificate) => state.certificates.insert(tx_hash, certificate),
+            None => state.certificates.remove(&tx_hash),
+        };
+    }
+
+    // channel txs received through `fastpay_sendChannelTx`, in order
+    pub fn channel_txs(&self) -> Vec<Value> {
+        self.state.lock().unwrap().channel_txs.clone()
+    }
+
+    // bridge txs received through `fastpay_sendBridgeTx`, in order
+    pub fn bridge_txs(&self) -> Vec<Value> {
+        self.state.lock().unwrap().bridge_txs.clone()
+    }
+
+    // what `method` answers whatever its params, for methods the mock doesn't serve otherwise
+    pub fn set_result(&self, method: &str, result: Value) {
+        self.state
+            .lock()
+            .unwrap()
+            .results
+            .insert(method.to_string(), result);
+    }
+
+    // what `fastpay_estimateFee` answers, the method isn't found until it is set
+    pub fn set_fee_estimate(&self, estimate: Value) {
+        self.state.lock().unwrap().fee_estimate = Some(estimate);
+    }
+
+    // every call waits this long before answering
+    pub fn set_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().delay = delay;
+    }
+
+    pub fn fail_next(&self, error: ClientError) {
+        self.state.lock().unwrap().next_failures.push_back(error);
+    }
+
+    pub fn fail_method(&self, method: impl Into<String>, error: ClientError) {
+        self.state
+            .lock()
+            .unwrap()
+            .method_failures
+            .insert(method.into(), error);
+    }
+
+    pub fn clear_failures(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.next_failures.clear();
+        state.method_failures.clear();
+    }
+
+    // every call received so far, in order
+    pub fn calls(&self) -> Vec<(String, Value)> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    fn handle(&self, method: &str, params: &Value) -> Result<Value, ClientError> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(error) = state.next_failures.pop_front() {
+            return Err(error);
+        }
+        if let Some(error) = state.method_failures.get(method) {
+            return Err(error.clone());
+        }
+
+        match method {
+            "eth_blockNumber" => Ok(json!(format!("{:#x}", state.block_number))),
+            "eth_chainId" => Ok(json!(format!("{:#x}", tx::eip712::CHAIN_ID))),
+            "eth_getBalance" => {
+                let address: Address = params
+                    .get(0)
+                    .cloned()
+                    .and_then(|address| serde_json::from_value(address).ok())
+                    .ok_or_else(|| ClientError::Rpc {
+                        code: INVALID_PARAMS_CODE,
+                        message: "invalid address".to_string(),
+                    })?;
+                let balance = state.balances.get(&address).copied().unwrap_or_default();
+                Ok(json!(format!("{:#x}", balance)))
+            }
+            "eth_getTransactionCount" => {
+                let address: Address = params
+                    .get(0)
+                    .cloned()
+                    .and_then(|address| serde_json::from_value(address).ok())
+                    .ok_or_else(|| ClientError::Rpc {
+                        code: INVALID_PARAMS_CODE,
+                        message: "invalid address".to_string(),
+                    })?;
+                let count = state
+                    .transaction_counts
+                    .get(&address)
+                    .copied()
+                    .unwrap_or_default();
+                Ok(json!(format!("{:#x}", count)))
+            }
+            "eth_getBlockByNumber" => {
+                let block = match params.get(0).and_then(|tag| tag.as_str()) {
+                    Some("latest") => state.blocks.last(),
+                    Some(number) => state.blocks.iter().find(|b| b["number"] == number),
+                    None => None,
+                };
+                Ok(block.cloned().unwrap_or(Value::Null))
+            }
+            "eth_getBlockByHash" => {
+                let block = params
+                    .get(0)
+                    .and_then(|hash| state.blocks.iter().find(|b| &b["hash"] == hash));
+                Ok(block.cloned().unwrap_or(Value::Null))
+            }
+            "fastpay_sendTransfer" => {
+                let transfer = params.get(0).cloned().unwrap_or_default();
+                state.transfers.push(transfer);
+                Ok(json!(format!("{:#066x}", state.transfers.len())))
+            }
+            "fastpay_sendChannelTx" => {
+                let request = params.get(0).cloned().unwrap_or_default();
+                state.channel_txs.push(request);
+                Ok(json!(format!("{:#066x}", state.channel_txs.len())))
+            }
+            "fastpay_sendBridgeTx" => {
+                let request = params.get(0).cloned().unwrap_or_default();
+                state.bridge_txs.push(request);
+                Ok(json!(format!("{:#066x}", state.bridge_txs.len())))
+            }
+            "fastpay_getChannel" => {
+                let channel_id: B256 = params
+                    .get(0)
+                    .cloned()
+                    .and_then(|id| serde_json::from_value(id).ok())
+                    .ok_or_else(|| ClientError::Rpc {
+                        code: INVALID_PARAMS_CODE,
+                        message: "invalid channel id".to_string(),
+                    })?;
+                Ok(state
+                    .channels
+                    .get(&channel_id)
+                    .cloned()
+                    .unwrap_or(Value::Null))
+            }
+            "fastpay_getCertificate" => {
+                let tx_hash: B256 = params
+                    .get(0)
+                    .cloned()
+                    .and_then(|hash| serde_json::from_value(hash).ok())
+                    .ok_or_else(|| ClientError::Rpc {
+                        code: INVALID_PARAMS_CODE,
+                        message: "invalid transaction hash".to_string(),
+                    })?;
+                Ok(state
+                    .certificates
+                    .get(&tx_hash)
+                    .cloned()
+                    .unwrap_or(Value::Null))
+            }
+            "eth_getLogs" => Ok(Value::Array(state.logs.clone())),
+            "fastpay_estimateFee" if state.fee_estimate.is_some() => {
+                Ok(state.fee_estimate.clone().unwrap_or_default())
+            }
+            method if state.results.contains_key(method) => Ok(state.results[method].clone()),
+            _ => Err(ClientError::Rpc {
+                code: METHOD_NOT_FOUND_CODE,
+                message: "Method not found".to_string(),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for MockNode {
+    async fn request_value(&self, method: &str, params: Value) -> Result<Value, ClientError> {
+        let delay = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push((method.to_string(), params.clone()));
+            state.delay
+        };
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        self.handle(method, &params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use alloy::signers::local::PrivateKeySigner;
+    use tx::tx::Tx;
+
+    #[tokio::test]
+    async fn test_programmed_balances() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+
+        let funded = PrivateKeySigner::random().address();
+        let unknown = PrivateKeySigner::random().address();
+        node.set_balance(funded, U256::from(1000));
+
+        assert_eq!(client.get_balance(funded).await.unwrap(), U256::from(1000));
+        assert_eq!(client.get_balance(unknown).await.unwrap(), U256::ZERO);
+
+        node.set_transaction_count(funded, 3);
+        assert_eq!(client.get_transaction_count(funded, true).await.unwrap(), 3);
+        assert_eq!(
+            client.get_transaction_count(unknown, false).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_number() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+
+        node.set_block_number(U256::from(42));
+        assert_eq!(client.block_number().await.unwrap(), U256::from(42));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_events() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        let from = PrivateKeySigner::random().address();
+        let to = PrivateKeySigner::random().address();
+
+        node.push_log(json!({
+            "address": Address::ZERO,
+            "topics": [crate::events::transfer_topic(), from.into_word(), to.into_word()],
+            "data": alloy::primitives::B256::from(U256::from(25)),
+            "blockNumber": "0x3",
+            "transactionHash": null,
+            "logIndex": "0x0",
+        }));
+
+        let events = client.get_transfer_events(0, 10, None).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].from, from);
+        assert_eq!(events[0].to, to);
+        assert_eq!(events[0].amount, U256::from(25));
+        assert_eq!(events[0].block_number, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+
+        assert!(client.get_block_by_number(None).await.unwrap().is_none());
+
+        for number in 0..3u8 {
+            node.push_block(json!({
+                "number": format!("{:#x}", number),
+                "hash": alloy::primitives::B256::repeat_byte(number + 1),
+                "parentHash": alloy::primitives::B256::repeat_byte(number),
+                "stateRoot": alloy::primitives::B256::ZERO,
+                "timestamp": "0x0",
+                "transactions": [],
+            }));
+        }
+
+        let latest = client.get_block_by_number(None).await.unwrap().unwrap();
+        assert_eq!(latest.number, 2);
+        assert_eq!(latest.state_root, Some(alloy::primitives::B256::ZERO));
+        let first = client.get_block_by_number(Some(1)).await.unwrap().unwrap();
+        assert_eq!(first.hash, latest.parent_hash);
+        assert!(client.get_block_by_number(Some(9)).await.unwrap().is_none());
+
+        let by_hash = client.get_block_by_hash(first.hash).await.unwrap().unwrap();
+        assert_eq!(by_hash.number, 1);
+        assert!(client
+            .get_block_by_hash(alloy::primitives::B256::ZERO)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_send_transfer() {
+        use alloy::signers::SignerSync;
+
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        let signer = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+
+        let unsigned = Tx::new(signer.address(), to, U256::from(5), None);
+        assert!(matches!(
+            client.send_transfer(&unsigned).await,
+            Err(ClientError::InvalidRequest(_))
+        ));
+
+        let unsigned = unsigned.with_chain_id(client.chain_id().await.unwrap());
+        let signature = signer.sign_message_sync(&unsigned.tx_hash()).unwrap();
+        let tx = unsigned.with_signature(signature);
+        client.send_transfer(&tx).await.unwrap();
+
+        let transfers = node.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0]["amount"], json!(U256::from(5)));
+        assert_eq!(transfers[0]["to"], json!(to));
+        assert_eq!(transfers[0]["chainId"], tx::eip712::CHAIN_ID);
+    }
+
+    #[tokio::test]
+    async fn test_channels() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        let id = B256::repeat_byte(1);
+
+        assert_eq!(client.get_channel(id).await.unwrap(), None);
+        node.set_channel(
+            id,
+            Some(json!({
+                "payer": Address::repeat_byte(2),
+                "payee": Address::repeat_byte(3),
+                "deposit": 100,
+                "challengePeriod": 10,
+                "expiresAt": 25,
+            })),
+        );
+        let channel = client.get_channel(id).await.unwrap().unwrap();
+        assert_eq!(channel.expires_at, Some(25));
+
+        use alloy::signers::SignerSync;
+        let signer = PrivateKeySigner::random();
+        let tx = Tx::claim_channel_timeout(signer.address(), channel.payee, id, None);
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        client
+            .send_channel_tx(&tx.with_signature(signature))
+            .await
+            .unwrap();
+        assert_eq!(node.channel_txs()[0]["kind"], "claimTimeout");
+    }
+
+    #[tokio::test]
+    async fn test_fail_next() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+
+        node.fail_next(ClientError::Timeout);
+        assert_eq!(
+            client.block_number().await.unwrap_err(),
+            ClientError::Timeout
+        );
+
+        // Only the next call fails
+        assert!(client.block_number().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fail_method() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        let address = PrivateKeySigner::random().address();
+
+        node.fail_method("eth_getBalance", ClientError::Http(503));
+        assert_eq!(
+            client.get_balance(address).await.unwrap_err(),
+            ClientError::Http(503)
+        );
+        assert_eq!(
+            client.get_balance(address).await.unwrap_err(),
+            ClientError::Http(503)
+        );
+        assert!(client.block_number().await.is_ok());
+
+        node.clear_failures();
+        assert!(client.get_balance(address).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delay() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+
+        node.set_delay(Duration::from_millis(20));
+        let started = std::time::Instant::now();
+        client.block_number().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_records_calls() {
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        let address = PrivateKeySigner::random().address();
+
+        client.block_number().await.unwrap();
+        client.get_balance(address).await.unwrap();
+
+        let calls = node.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].0, "eth_blockNumber");
+        assert_eq!(calls[1].0, "eth_getBalance");
+        assert_eq!(calls[1].1, json!([address, "latest"]));
+    }
+
+    #[tokio::test]
+    async fn test_certificates() {
+        use alloy::signers::SignerSync;
+        use committee::{authority::Authority, certificate::Certificate, committee::Committee};
+
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        let authorities: Vec<Authority> = (0..4).map(|_| Authority::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+
+        let signer = PrivateKeySigner::random();
+        let certify = |amount: u64, votes: usize| {
+            let tx = Tx::new(signer.address(), Address::ZERO, U256::from(amount), None);
+            let tx = tx
+                .clone()
+                .with_signature(signer.sign_message_sync(&tx.tx_hash()).unwrap());
+            let mut certificate = Certificate::new(tx.clone());
+            for authority in &authorities[..votes] {
+                certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+            }
+            certificate
+        };
+
+        let certificate = certify(10, 3);
+        let tx_hash = certificate.tx_hash();
+        assert!(client
+            .get_verified_certificate(tx_hash, &committee)
+            .await
+            .unwrap()
+            .is_none());
+
+        node.set_certificate(tx_hash, Some(serde_json::to_value(&certificate).unwrap()));
+        let verified = client
+            .get_verified_certificate(tx_hash, &committee)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(verified.tx().amount(), U256::from(10));
+
+        // A node can't pass off a certificate without a quorum, or one for another transfer
+        let weak = certify(10, 2);
+        node.set_certificate(tx_hash, Some(serde_json::to_value(&weak).unwrap()));
+        assert!(client.get_certificate(tx_hash).await.unwrap().is_some());
+        assert!(matches!(
+            client.get_verified_certificate(tx_hash, &committee).await,
+            Err(ClientError::InvalidResponse(_))
+        ));
+        let other = certify(20, 3);
+        node.set_certificate(tx_hash, Some(serde_json::to_value(&other).unwrap()));
+        assert!(matches!(
+            client.get_verified_certificate(tx_hash, &committee).await,
+            Err(ClientError::InvalidResponse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee() {
+        use crate::fee::{FeeLevel, FeeSuggestion};
+
+        let node = MockNode::new();
+        let client = Client::with_transport(node.clone());
+        assert!(client.estimate_fee().await.is_err());
+
+        let suggestion = |fee: &str, blocks: &str| json!({"fee": fee, "expectedBlocks": blocks});
+        node.set_fee_estimate(json!({
+            "low": suggestion("0x0", "0x4"),
+            "medium": suggestion("0x2", "0x3"),
+            "high": suggestion("0x9", "0x1"),
+        }));
+        let estimate = client.estimate_fee().await.unwrap();
+        assert_eq!(
+            estimate.level(FeeLevel::High),
+            FeeSuggestion {
+                fee: 9,
+                expected_blocks: 1
+            }
+        );
+        assert_eq!(estimate.level(FeeLevel::Low).expected_blocks, 4);
+
+        node.set_fee_estimate(json!({
+            "low": suggestion("lots", "0x4"),
+            "medium": suggestion("0x2", "0x3"),
+            "high": suggestion("0x9", "0x1"),
+        }));
+        assert!(matches!(
+            client.estimate_fee().await,
+            Err(ClientError::InvalidResponse(_))
+        ));
+    }
+}
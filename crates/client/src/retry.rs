@@ -0,0 +1,93 @@
+use rand::Rng;
+use std::time::Duration;
+
+// exponential backoff between attempts, with a random fraction shaved off each delay so that
+// many clients retrying against the same node don't do it in lockstep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // fraction of the delay (0.0 - 1.0) that may be randomly removed
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    // delay before retry number `attempt` (starting at 0), without jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    pub fn backoff_with_jitter(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+
+        let removed = rand::thread_rng().gen_range(0.0..jitter);
+        backoff.mul_f64(1.0 - removed)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_max() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            jitter: 0.0,
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(4), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_backoff_with_jitter_stays_in_range() {
+        let policy = RetryPolicy {
+            jitter: 0.5,
+            ..RetryPolicy::default()
+        };
+
+        for attempt in 0..5 {
+            let max = policy.backoff(attempt);
+            let delay = policy.backoff_with_jitter(attempt);
+            assert!(delay <= max);
+            assert!(delay >= max / 2);
+        }
+    }
+
+    #[test]
+    fn test_no_retries() {
+        assert_eq!(RetryPolicy::none().max_retries, 0);
+    }
+}
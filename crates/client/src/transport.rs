@@ -0,0 +1,338 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::middleware::Middleware;
+use crate::retry::RetryPolicy;
+use crate::ClientError;
+
+// Transport sends a single JSON-RPC call and hands back its `result`
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn request_value(&self, method: &str, params: Value) -> Result<Value, ClientError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    pub url: String,
+    pub request_timeout: Duration,
+    pub connect_timeout: Duration,
+    pub retry: RetryPolicy,
+}
+
+impl TransportConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            request_timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(5),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcRequest {
+    id: u64,
+    method: String,
+    params: Value,
+    headers: Vec<(String, String)>,
+}
+
+impl RpcRequest {
+    pub fn new(id: u64, method: impl Into<String>, params: Value) -> Self {
+        Self {
+            id,
+            method: method.into(),
+            params,
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn params(&self) -> &Value {
+        &self.params
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+    }
+
+    fn body(&self) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": self.id,
+            "method": self.method,
+            "params": self.params,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcResponseError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponseError {
+    code: i64,
+    message: String,
+}
+
+// JSON-RPC over HTTP, with per-request timeouts, retries and middleware
+pub struct HttpTransport {
+    http: reqwest::Client,
+    config: TransportConfig,
+    middleware: Vec<Arc<dyn Middleware>>,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    pub fn new(config: TransportConfig) -> Result<Self, ClientError> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .build()
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+
+        Ok(Self {
+            http,
+            config,
+            middleware: Vec::new(),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    // middleware runs in the order it was added
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    pub fn config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, ClientError> {
+        let value = self.request_with_retries(method, params).await?;
+        serde_json::from_value(value).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    async fn request_with_retries(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, ClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut request = RpcRequest::new(id, method, params);
+        for middleware in &self.middleware {
+            middleware.on_request(&mut request);
+        }
+
+        let mut attempt = 0;
+        loop {
+            let started = Instant::now();
+            let result = self.send(&request).await;
+            let elapsed = started.elapsed();
+
+            for middleware in &self.middleware {
+                middleware.on_response(&request, &result, elapsed);
+            }
+
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.config.retry.max_retries => {
+                    attempt += 1;
+                    for middleware in &self.middleware {
+                        middleware.on_retry(&request, attempt, &e);
+                    }
+                    tokio::time::sleep(self.config.retry.backoff_with_jitter(attempt - 1)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send(&self, request: &RpcRequest) -> Result<Value, ClientError> {
+        let mut builder = self.http.post(&self.config.url).json(&request.body());
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+
+        let response = builder.send().await.map_err(|e| {
+            if e.is_timeout() {
+                ClientError::Timeout
+            } else {
+                ClientError::Transport(e.to_string())
+            }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ClientError::Http(status.as_u16()));
+        }
+
+        let response: RpcResponse = response
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(ClientError::Rpc {
+                code: error.code,
+                message: error.message,
+            }),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn request_value(&self, method: &str, params: Value) -> Result<Value, ClientError> {
+        self.request_with_retries(method, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::MetricsMiddleware;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // answers each incoming connection with the next canned (status, body) pair
+    async fn serve(responses: Vec<(u16, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn config(url: String, max_retries: u32) -> TransportConfig {
+        TransportConfig {
+            retry: RetryPolicy {
+                max_retries,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(5),
+                jitter: 0.5,
+            },
+            ..TransportConfig::new(url)
+        }
+    }
+
+    #[test]
+    fn test_set_header_replaces_existing() {
+        let mut request = RpcRequest::new(1, "eth_blockNumber", json!([]));
+        request.set_header("Authorization", "a");
+        request.set_header("authorization", "b");
+
+        assert_eq!(request.header("Authorization"), Some("b"));
+        assert_eq!(request.headers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_success() {
+        let url = serve(vec![(200, r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#)]).await;
+        let transport = HttpTransport::new(config(url, 0)).unwrap();
+
+        let result: String = transport
+            .request("eth_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(result, "0x10");
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let url = serve(vec![
+            (503, "{}"),
+            (502, "{}"),
+            (200, r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#),
+        ])
+        .await;
+        let metrics = Arc::new(MetricsMiddleware::new());
+        let transport = HttpTransport::new(config(url, 3))
+            .unwrap()
+            .with_middleware(metrics.clone());
+
+        let result: String = transport
+            .request("eth_blockNumber", json!([]))
+            .await
+            .unwrap();
+        assert_eq!(result, "0x10");
+        assert_eq!(metrics.requests(), 3);
+        assert_eq!(metrics.failures(), 2);
+        assert_eq!(metrics.retries(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let url = serve(vec![(503, "{}"), (503, "{}")]).await;
+        let transport = HttpTransport::new(config(url, 1)).unwrap();
+
+        let result = transport.request_value("eth_blockNumber", json!([])).await;
+        assert_eq!(result.unwrap_err(), ClientError::Http(503));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_errors_are_not_retried() {
+        let url = serve(vec![(
+            200,
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#,
+        )])
+        .await;
+        let transport = HttpTransport::new(config(url, 3)).unwrap();
+
+        let result = transport.request_value("eth_foo", json!([])).await;
+        assert_eq!(
+            result.unwrap_err(),
+            ClientError::Rpc {
+                code: -32601,
+                message: "Method not found".to_string()
+            }
+        );
+    }
+}
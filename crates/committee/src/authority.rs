@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use alloy::primitives::Address;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use tx::tx::Tx;
+
+use crate::certificate::{certificate_message, Certificate, Vote};
+use crate::committee::Committee;
+use crate::CommitteeError;
+
+// Authority checks transfer orders sent by clients and votes for the valid ones
+pub struct Authority {
+    signer: PrivateKeySigner,
+    sequencing: Option<Sequencing>,
+}
+
+// the paper's sequence number rules: an authority votes for one order per account and sequence
+// number, and only moves on to the next sequence number once it sees that order certified
+struct Sequencing {
+    committee: Committee,
+    accounts: Mutex<HashMap<Address, AccountLock>>,
+}
+
+#[derive(Default)]
+struct AccountLock {
+    next_sequence: u64,
+    // the order voted for at next_sequence, any other order is refused until it is confirmed
+    pending: Option<Tx>,
+}
+
+impl Authority {
+    pub fn new(signer: PrivateKeySigner) -> Self {
+        Self {
+            signer,
+            sequencing: None,
+        }
+    }
+
+    // enforces sequence numbers on transfer orders, confirmations are checked against `committee`
+    pub fn with_sequence_numbers(mut self, committee: Committee) -> Self {
+        self.sequencing = Some(Sequencing {
+            committee,
+            accounts: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    pub fn random() -> Self {
+        Self::new(PrivateKeySigner::random())
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn handle_transfer_order(&self, tx: &Tx) -> Result<Vote, CommitteeError> {
+        if tx.signature().is_none() {
+            return Err(CommitteeError::InvalidTransferOrder(
+                "Transaction has no signature".to_string(),
+            ));
+        }
+        if !tx.is_signed_by(tx.from()) {
+            return Err(CommitteeError::InvalidTransferOrder(
+                "Transaction signature is invalid".to_string(),
+            ));
+        }
+
+        if let Some(sequencing) = &self.sequencing {
+            let sequence = tx.sequence().ok_or_else(|| {
+                CommitteeError::InvalidTransferOrder(
+                    "Transfer order has no sequence number".to_string(),
+                )
+            })?;
+
+            let mut accounts = sequencing.accounts.lock().unwrap();
+            let account = accounts.entry(tx.from()).or_default();
+            if sequence != account.next_sequence {
+                return Err(CommitteeError::UnexpectedSequence {
+                    expected: account.next_sequence,
+                    got: sequence,
+                });
+            }
+            match &account.pending {
+                // the client retrying, the same vote again is harmless
+                Some(pending) if pending.tx_hash() == tx.tx_hash() => {}
+                Some(_) => return Err(CommitteeError::ConflictingOrder(tx.from())),
+                None => account.pending = Some(tx.clone()),
+            }
+        }
+
+        let signature = self
+            .signer
+            .sign_message_sync(&certificate_message(tx))
+            .map_err(|e| CommitteeError::SigningError(e.to_string()))?;
+
+        Ok(Vote {
+            authority: self.address(),
+            signature,
+        })
+    }
+
+    // a certified order is final, the account moves on to its next sequence number. Without
+    // sequence numbers there is nothing to keep track of
+    pub fn handle_confirmation_order(
+        &self,
+        certificate: &Certificate,
+    ) -> Result<(), CommitteeError> {
+        let Some(sequencing) = &self.sequencing else {
+            return Ok(());
+        };
+        certificate.verify(&sequencing.committee)?;
+
+        let tx = certificate.tx();
+        if let Some(sequence) = tx.sequence() {
+            let mut accounts = sequencing.accounts.lock().unwrap();
+            let account = accounts.entry(tx.from()).or_default();
+            // confirmations can arrive more than once, or for orders this authority never saw
+            if sequence >= account.next_sequence {
+                account.next_sequence = sequence + 1;
+                account.pending = None;
+            }
+        }
+        Ok(())
+    }
+
+    // the sequence number the account's next transfer order has to carry
+    pub fn next_sequence(&self, address: Address) -> u64 {
+        self.sequencing
+            .as_ref()
+            .and_then(|sequencing| {
+                sequencing
+                    .accounts
+                    .lock()
+                    .unwrap()
+                    .get(&address)
+                    .map(|account| account.next_sequence)
+            })
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_vote_on_valid_order() {
+        let authority = Authority::random();
+        let sender = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+
+        let tx = Tx::new(sender.address(), to, U256::from(10), None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = Tx::new(sender.address(), to, U256::from(10), Some(signature));
+
+        let vote = authority.handle_transfer_order(&tx).unwrap();
+        assert_eq!(vote.authority, authority.address());
+
+        let recovered = vote
+            .signature
+            .recover_address_from_msg(certificate_message(&tx))
+            .unwrap();
+        assert_eq!(recovered, authority.address());
+    }
+
+    #[test]
+    fn test_reject_unsigned_order() {
+        let authority = Authority::random();
+        let tx = Tx::new(
+            PrivateKeySigner::random().address(),
+            Address::ZERO,
+            U256::from(10),
+            None,
+        );
+
+        assert!(matches!(
+            authority.handle_transfer_order(&tx),
+            Err(CommitteeError::InvalidTransferOrder(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_order_signed_by_someone_else() {
+        let authority = Authority::random();
+        let sender = PrivateKeySigner::random();
+        let impostor = PrivateKeySigner::random();
+
+        let tx = Tx::new(sender.address(), Address::ZERO, U256::from(10), None);
+        let signature = impostor.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = Tx::new(
+            sender.address(),
+            Address::ZERO,
+            U256::from(10),
+            Some(signature),
+        );
+
+        assert!(matches!(
+            authority.handle_transfer_order(&tx),
+            Err(CommitteeError::InvalidTransferOrder(_))
+        ));
+    }
+
+    #[test]
+    fn test_sequence_numbers() {
+        let authority = Authority::random();
+        let committee = Committee::new([(authority.address(), 1)]);
+        let authority = authority.with_sequence_numbers(committee);
+        let sender = PrivateKeySigner::random();
+
+        let order = |sequence: u64, amount: u64| {
+            let tx = Tx::transfer_order(
+                sender.address(),
+                Address::ZERO,
+                U256::from(amount),
+                sequence,
+                None,
+            );
+            let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        // Orders without a sequence number are refused
+        let tx = Tx::new(sender.address(), Address::ZERO, U256::from(10), None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        assert!(matches!(
+            authority.handle_transfer_order(&tx.with_signature(signature)),
+            Err(CommitteeError::InvalidTransferOrder(_))
+        ));
+
+        let first = order(0, 10);
+        let vote = authority.handle_transfer_order(&first).unwrap();
+        // Retries get the same vote, a different order for the same sequence number doesn't
+        assert!(authority.handle_transfer_order(&first).is_ok());
+        assert_eq!(
+            authority.handle_transfer_order(&order(0, 20)),
+            Err(CommitteeError::ConflictingOrder(sender.address()))
+        );
+        assert_eq!(
+            authority.handle_transfer_order(&order(1, 10)),
+            Err(CommitteeError::UnexpectedSequence {
+                expected: 0,
+                got: 1
+            })
+        );
+
+        // An uncertified order doesn't move the sequence number
+        assert!(authority
+            .handle_confirmation_order(&Certificate::new(first.clone()))
+            .is_err());
+        assert_eq!(authority.next_sequence(sender.address()), 0);
+
+        let mut certificate = Certificate::new(first.clone());
+        certificate.add_vote(vote);
+        authority.handle_confirmation_order(&certificate).unwrap();
+        authority.handle_confirmation_order(&certificate).unwrap();
+        assert_eq!(authority.next_sequence(sender.address()), 1);
+
+        assert_eq!(
+            authority.handle_transfer_order(&first),
+            Err(CommitteeError::UnexpectedSequence {
+                expected: 1,
+                got: 0
+            })
+        );
+        assert!(authority.handle_transfer_order(&order(1, 20)).is_ok());
+    }
+}
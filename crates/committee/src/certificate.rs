@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+
+use alloy::primitives::{Address, PrimitiveSignature, B256};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use tx::tx::Tx;
+
+use crate::committee::Committee;
+use crate::CommitteeError;
+
+// keeps authority votes from being mistaken for a sender's signature over the same transfer
+const CERTIFICATE_DOMAIN: &[u8] = b"fastpay-certificate";
+
+// the message an authority signs to vote for a transfer order
+pub fn certificate_message(tx: &Tx) -> Bytes {
+    let mut hasher = Keccak256::new();
+    hasher.update(CERTIFICATE_DOMAIN);
+    hasher.update(tx.tx_hash());
+
+    Bytes::from(hasher.finalize().to_vec())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vote {
+    pub authority: Address,
+    pub signature: PrimitiveSignature,
+}
+
+impl Vote {
+    // checks the signature only, not whether the authority is part of a committee
+    pub fn verify(&self, tx: &Tx) -> Result<(), CommitteeError> {
+        match self
+            .signature
+            .recover_address_from_msg(certificate_message(tx))
+        {
+            Ok(recovered) if recovered == self.authority => Ok(()),
+            _ => Err(CommitteeError::InvalidSignature(self.authority)),
+        }
+    }
+}
+
+// Certificate is a transfer order together with the votes of the authorities that accepted it,
+// once the votes carry a quorum of stake it can be settled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    tx: Tx,
+    votes: Vec<Vote>,
+}
+
+impl Certificate {
+    pub fn new(tx: Tx) -> Self {
+        Self {
+            tx,
+            votes: Vec::new(),
+        }
+    }
+
+    // the hash of the certified transfer, what certificates are looked up by
+    pub fn tx_hash(&self) -> B256 {
+        B256::from_slice(&self.tx.tx_hash())
+    }
+
+    pub fn tx(&self) -> &Tx {
+        &self.tx
+    }
+
+    pub fn votes(&self) -> &[Vote] {
+        &self.votes
+    }
+
+    pub fn add_vote(&mut self, vote: Vote) {
+        self.votes.push(vote);
+    }
+
+    // stake behind the votes from distinct committee members, signatures are not checked
+    pub fn stake(&self, committee: &Committee) -> u64 {
+        let mut seen = HashSet::new();
+        self.votes
+            .iter()
+            .filter(|vote| seen.insert(vote.authority))
+            .map(|vote| committee.stake(&vote.authority))
+            .sum()
+    }
+
+    pub fn verify(&self, committee: &Committee) -> Result<(), CommitteeError> {
+        let mut seen = HashSet::new();
+        let mut collected = 0u64;
+
+        for vote in &self.votes {
+            if !committee.is_member(&vote.authority) {
+                return Err(CommitteeError::UnknownAuthority(vote.authority));
+            }
+
+            if !seen.insert(vote.authority) {
+                return Err(CommitteeError::DuplicateSignature(vote.authority));
+            }
+
+            vote.verify(&self.tx)?;
+
+            collected += committee.stake(&vote.authority);
+        }
+
+        let required = committee.quorum_threshold();
+        if collected < required {
+            return Err(CommitteeError::InsufficientStake {
+                collected,
+                required,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::Authority;
+    use alloy::primitives::U256;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    fn setup(n: usize) -> (Vec<Authority>, Committee) {
+        let authorities: Vec<Authority> = (0..n).map(|_| Authority::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+        (authorities, committee)
+    }
+
+    fn signed_tx() -> Tx {
+        let sender = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+        let tx = Tx::new(sender.address(), to, U256::from(10), None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        Tx::new(sender.address(), to, U256::from(10), Some(signature))
+    }
+
+    #[test]
+    fn test_quorum_certificate_verifies() {
+        let (authorities, committee) = setup(4);
+        let tx = signed_tx();
+
+        let mut certificate = Certificate::new(tx.clone());
+        for authority in &authorities[..3] {
+            certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+        }
+
+        assert_eq!(certificate.stake(&committee), 3);
+        assert!(certificate.verify(&committee).is_ok());
+
+        // A certificate handed over as JSON can be checked by whoever receives it
+        let json = serde_json::to_string(&certificate).unwrap();
+        let decoded: Certificate = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.tx_hash(), certificate.tx_hash());
+        assert!(decoded.verify(&committee).is_ok());
+    }
+
+    #[test]
+    fn test_insufficient_stake() {
+        let (authorities, committee) = setup(4);
+        let tx = signed_tx();
+
+        let mut certificate = Certificate::new(tx.clone());
+        for authority in &authorities[..2] {
+            certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+        }
+
+        assert_eq!(
+            certificate.verify(&committee).unwrap_err(),
+            CommitteeError::InsufficientStake {
+                collected: 2,
+                required: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_duplicate_votes_do_not_count_twice() {
+        let (authorities, committee) = setup(4);
+        let tx = signed_tx();
+
+        let mut certificate = Certificate::new(tx.clone());
+        let vote = authorities[0].handle_transfer_order(&tx).unwrap();
+        certificate.add_vote(vote.clone());
+        certificate.add_vote(vote.clone());
+        certificate.add_vote(authorities[1].handle_transfer_order(&tx).unwrap());
+
+        assert_eq!(certificate.stake(&committee), 2);
+        assert_eq!(
+            certificate.verify(&committee).unwrap_err(),
+            CommitteeError::DuplicateSignature(vote.authority)
+        );
+    }
+
+    #[test]
+    fn test_unknown_authority() {
+        let (authorities, committee) = setup(3);
+        let outsider = Authority::random();
+        let tx = signed_tx();
+
+        let mut certificate = Certificate::new(tx.clone());
+        for authority in &authorities {
+            certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+        }
+        certificate.add_vote(outsider.handle_transfer_order(&tx).unwrap());
+
+        assert_eq!(
+            certificate.verify(&committee).unwrap_err(),
+            CommitteeError::UnknownAuthority(outsider.address())
+        );
+    }
+
+    #[test]
+    fn test_vote_for_different_tx_is_invalid() {
+        let (authorities, committee) = setup(1);
+        let tx = signed_tx();
+        let other_tx = signed_tx();
+
+        let mut certificate = Certificate::new(tx);
+        certificate.add_vote(authorities[0].handle_transfer_order(&other_tx).unwrap());
+
+        assert_eq!(
+            certificate.verify(&committee).unwrap_err(),
+            CommitteeError::InvalidSignature(authorities[0].address())
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use alloy::primitives::Address;
+
+// Committee is the set of authorities and their voting power. Like in the FastPay paper it
+// tolerates up to f faulty stake out of 3f + 1, so a quorum is anything above two thirds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Committee {
+    stakes: BTreeMap<Address, u64>,
+    total_stake: u64,
+}
+
+impl Committee {
+    pub fn new(stakes: impl IntoIterator<Item = (Address, u64)>) -> Self {
+        let stakes: BTreeMap<Address, u64> = stakes.into_iter().collect();
+        let total_stake = stakes.values().sum();
+
+        Self {
+            stakes,
+            total_stake,
+        }
+    }
+
+    pub fn stake(&self, authority: &Address) -> u64 {
+        self.stakes.get(authority).copied().unwrap_or(0)
+    }
+
+    pub fn is_member(&self, authority: &Address) -> bool {
+        self.stakes.contains_key(authority)
+    }
+
+    pub fn total_stake(&self) -> u64 {
+        self.total_stake
+    }
+
+    // stake needed to certify a transfer, 2f + 1
+    pub fn quorum_threshold(&self) -> u64 {
+        self.total_stake * 2 / 3 + 1
+    }
+
+    // stake guaranteed to contain at least one honest authority, f + 1
+    pub fn validity_threshold(&self) -> u64 {
+        self.total_stake.div_ceil(3)
+    }
+
+    pub fn authorities(&self) -> impl Iterator<Item = &Address> {
+        self.stakes.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stakes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stakes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+
+    fn addresses(n: usize) -> Vec<Address> {
+        (0..n)
+            .map(|_| PrivateKeySigner::random().address())
+            .collect()
+    }
+
+    #[test]
+    fn test_equal_stake_thresholds() {
+        let committee = Committee::new(addresses(4).into_iter().map(|a| (a, 1)));
+
+        assert_eq!(committee.len(), 4);
+        assert_eq!(committee.total_stake(), 4);
+        // 3 out of 4 authorities are needed for a quorum
+        assert_eq!(committee.quorum_threshold(), 3);
+        assert_eq!(committee.validity_threshold(), 2);
+    }
+
+    #[test]
+    fn test_weighted_stake() {
+        let members = addresses(3);
+        let committee = Committee::new(vec![(members[0], 50), (members[1], 30), (members[2], 20)]);
+
+        assert_eq!(committee.total_stake(), 100);
+        assert_eq!(committee.stake(&members[0]), 50);
+        assert_eq!(committee.quorum_threshold(), 67);
+        assert_eq!(committee.validity_threshold(), 34);
+    }
+
+    #[test]
+    fn test_non_member() {
+        let committee = Committee::new(addresses(2).into_iter().map(|a| (a, 1)));
+        let outsider = PrivateKeySigner::random().address();
+
+        assert!(!committee.is_member(&outsider));
+        assert_eq!(committee.stake(&outsider), 0);
+    }
+}
@@ -0,0 +1,20 @@
+pub mod authority;
+pub mod certificate;
+pub mod committee;
+pub mod store;
+
+use alloy::primitives::Address;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitteeError {
+    UnknownAuthority(Address),
+    DuplicateSignature(Address),
+    InvalidSignature(Address),
+    InsufficientStake { collected: u64, required: u64 },
+    InvalidTransferOrder(String),
+    // the order's sequence number is not the account's next one, stale or skipping ahead
+    UnexpectedSequence { expected: u64, got: u64 },
+    // the authority already voted for a different order at this sequence number
+    ConflictingOrder(Address),
+    SigningError(String),
+}
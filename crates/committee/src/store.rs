@@ -0,0 +1,64 @@
+// finalized certificates by the hash of the transfer they certify, kept so a recipient can fetch
+// the certificate of a payment and check the votes against the committee themselves
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use alloy::primitives::B256;
+
+use crate::certificate::Certificate;
+
+// clones share the same certificates
+#[derive(Debug, Clone, Default)]
+pub struct CertificateStore {
+    certificates: Arc<RwLock<HashMap<B256, Certificate>>>,
+}
+
+impl CertificateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, certificate: Certificate) {
+        self.certificates
+            .write()
+            .unwrap()
+            .insert(certificate.tx_hash(), certificate);
+    }
+
+    pub fn get(&self, tx_hash: &B256) -> Option<Certificate> {
+        self.certificates.read().unwrap().get(tx_hash).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.certificates.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use tx::tx::Tx;
+
+    #[test]
+    fn test_certificate_store() {
+        let store = CertificateStore::new();
+        let certificate = Certificate::new(Tx::new(
+            Address::repeat_byte(1),
+            Address::ZERO,
+            U256::from(10),
+            None,
+        ));
+        let tx_hash = certificate.tx_hash();
+
+        assert!(store.get(&tx_hash).is_none());
+        store.clone().insert(certificate);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(&tx_hash).unwrap().tx().amount(), U256::from(10));
+    }
+}
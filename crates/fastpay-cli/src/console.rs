@@ -0,0 +1,387 @@
+// an interactive console attached to a node, for poking at it while debugging: requests go over
+// the node's http endpoint and subscriptions over its websocket one
+
+use std::path::PathBuf;
+
+use alloy::primitives::{Address, U256};
+use client::{transport::Transport, Client};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio_tungstenite::tungstenite::Message;
+use wallet::Wallet;
+
+use crate::output::{CliError, ErrorKind};
+use crate::{load_key, sign_transfer};
+
+const HELP: &str = "\
+balance <address>                  balance of an account
+block [number]                     a block, the latest one without a number
+send <to> <amount> [key file]      sign and send a transfer, with the console's key by default
+txpool                             pending and queued transactions
+subscribe <kind> [count]           print newHeads or newPendingTransactions notifications
+rpc <method> [params]              any JSON-RPC call, params as a JSON array
+help                               this
+exit                               leave the console";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    Balance(Address),
+    Block(Option<u64>),
+    Send {
+        to: Address,
+        amount: U256,
+        key_file: Option<PathBuf>,
+    },
+    TxPool,
+    // stops after `count` notifications, or on ctrl-c
+    Subscribe {
+        kind: String,
+        count: Option<u64>,
+    },
+    Rpc {
+        method: String,
+        params: Value,
+    },
+    Help,
+    Exit,
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&str>, name: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    let arg = arg.ok_or_else(|| format!("missing {}", name))?;
+    arg.parse()
+        .map_err(|e| format!("invalid {} {}: {}", name, arg, e))
+}
+
+impl ConsoleCommand {
+    // None for a blank line
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Ok(None);
+        };
+
+        let command = match name {
+            "balance" => Self::Balance(parse_arg(words.next(), "address")?),
+            "block" => Self::Block(
+                words
+                    .next()
+                    .map(|number| parse_arg(Some(number), "block number"))
+                    .transpose()?,
+            ),
+            "send" => Self::Send {
+                to: parse_arg(words.next(), "recipient")?,
+                amount: parse_arg(words.next(), "amount")?,
+                key_file: words.next().map(PathBuf::from),
+            },
+            "txpool" => Self::TxPool,
+            "subscribe" => {
+                let kind: String = parse_arg(words.next(), "subscription kind")?;
+                if kind != "newHeads" && kind != "newPendingTransactions" {
+                    return Err(format!("unknown subscription {}", kind));
+                }
+                Self::Subscribe {
+                    kind,
+                    count: words
+                        .next()
+                        .map(|count| parse_arg(Some(count), "count"))
+                        .transpose()?,
+                }
+            }
+            "rpc" => {
+                let method = parse_arg(words.next(), "method")?;
+                // the params may contain spaces, they are the rest of the line
+                let rest = words.collect::<Vec<_>>().join(" ");
+                let params = if rest.is_empty() {
+                    json!([])
+                } else {
+                    serde_json::from_str(&rest).map_err(|e| format!("invalid params: {}", e))?
+                };
+                Self::Rpc { method, params }
+            }
+            "help" => Self::Help,
+            "exit" | "quit" => Self::Exit,
+            other => return Err(format!("unknown command {}, try help", other)),
+        };
+        Ok(Some(command))
+    }
+}
+
+pub struct Console<T> {
+    client: Client<T>,
+    ws_url: String,
+    // signs `send` when no key file is given
+    key_file: Option<PathBuf>,
+}
+
+impl<T: Transport> Console<T> {
+    pub fn new(client: Client<T>, ws_url: String, key_file: Option<PathBuf>) -> Self {
+        Self {
+            client,
+            ws_url,
+            key_file,
+        }
+    }
+
+    // reads commands from stdin until exit or the end of input
+    pub async fn run(&self) -> Result<(), CliError> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout = tokio::io::stdout();
+        loop {
+            stdout.write_all(b"> ").await.ok();
+            stdout.flush().await.ok();
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return Ok(()),
+                Err(e) => return Err(CliError::new(ErrorKind::Io, e.to_string())),
+            };
+
+            let command = match ConsoleCommand::parse(&line) {
+                Ok(Some(command)) => command,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
+            match command {
+                ConsoleCommand::Exit => return Ok(()),
+                ConsoleCommand::Subscribe { kind, count } => {
+                    if let Err(e) = self.subscribe(&kind, count).await {
+                        eprintln!("{}", e.message);
+                    }
+                }
+                command => match self.execute(command).await {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => eprintln!("{}", e.message),
+                },
+            }
+        }
+    }
+
+    // everything but subscriptions, which keep printing until they are done
+    pub async fn execute(&self, command: ConsoleCommand) -> Result<String, CliError> {
+        match command {
+            ConsoleCommand::Balance(address) => {
+                let balance = self
+                    .client
+                    .get_balance(address)
+                    .await
+                    .map_err(|e| CliError::from_client("failed to get balance", e))?;
+                Ok(balance.to_string())
+            }
+            ConsoleCommand::Block(number) => {
+                let tag = match number {
+                    Some(number) => format!("{:#x}", number),
+                    None => "latest".to_string(),
+                };
+                self.call("eth_getBlockByNumber", json!([tag, false])).await
+            }
+            ConsoleCommand::Send {
+                to,
+                amount,
+                key_file,
+            } => {
+                let key_file = key_file.or_else(|| self.key_file.clone()).ok_or_else(|| {
+                    CliError::new(
+                        ErrorKind::InvalidInput,
+                        "no key file, pass one or start the console with --key-file",
+                    )
+                })?;
+                let wallet = Wallet::new(load_key(&key_file)?);
+                let chain_id = self
+                    .client
+                    .chain_id()
+                    .await
+                    .map_err(|e| CliError::from_client("failed to get chain id", e))?;
+                let tx = sign_transfer(&wallet, to, amount, 0, chain_id)?;
+                let tx_hash = self
+                    .client
+                    .send_transfer(&tx)
+                    .await
+                    .map_err(|e| CliError::from_client("failed to send transfer", e))?;
+                Ok(tx_hash.to_string())
+            }
+            ConsoleCommand::TxPool => {
+                let status = self.call("txpool_status", json!([])).await?;
+                let content = self.call("txpool_content", json!([])).await?;
+                Ok(format!("{}\n{}", status, content))
+            }
+            ConsoleCommand::Rpc { method, params } => self.call(&method, params).await,
+            ConsoleCommand::Help => Ok(HELP.to_string()),
+            ConsoleCommand::Subscribe { .. } | ConsoleCommand::Exit => Err(CliError::new(
+                ErrorKind::InvalidInput,
+                "only the console loop handles this command",
+            )),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<String, CliError> {
+        let value = self
+            .client
+            .transport()
+            .request_value(method, params)
+            .await
+            .map_err(|e| CliError::from_client(&format!("{} failed", method), e))?;
+        Ok(serde_json::to_string_pretty(&value).expect("json values serialize"))
+    }
+
+    async fn subscribe(&self, kind: &str, count: Option<u64>) -> Result<(), CliError> {
+        let unavailable = |e: tokio_tungstenite::tungstenite::Error| {
+            CliError::new(
+                ErrorKind::Unavailable,
+                format!("subscription to {} failed: {}", self.ws_url, e),
+            )
+        };
+        let (mut ws, _) = tokio_tungstenite::connect_async(self.ws_url.as_str())
+            .await
+            .map_err(unavailable)?;
+        let subscribe = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_subscribe",
+            "params": [kind],
+        });
+        ws.send(Message::Text(subscribe.to_string()))
+            .await
+            .map_err(unavailable)?;
+
+        let mut received = 0;
+        loop {
+            tokio::select! {
+                message = ws.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Err(unavailable(e)),
+                        None => return Ok(()),
+                    };
+                    let Ok(value) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+                    // the first message is the subscription id
+                    if value.get("method").is_none() {
+                        continue;
+                    }
+                    println!("{}", client::events::notification_result(&value));
+                    received += 1;
+                    if count.is_some_and(|count| received >= count) {
+                        return Ok(());
+                    }
+                }
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            }
+        }
+    }
+}
+
+// the node serves websockets on the same address as http
+pub fn default_ws_url(rpc_url: &str) -> String {
+    match rpc_url.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some((_, rest)) => format!("ws://{}", rest),
+        None => format!("ws://{}", rpc_url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use client::mock::MockNode;
+
+    #[test]
+    fn test_parse() {
+        let address = Address::repeat_byte(1);
+        assert_eq!(ConsoleCommand::parse("   "), Ok(None));
+        assert_eq!(
+            ConsoleCommand::parse(&format!("balance {}", address)),
+            Ok(Some(ConsoleCommand::Balance(address)))
+        );
+        assert_eq!(
+            ConsoleCommand::parse("block 0x10"),
+            Err("invalid block number 0x10: invalid digit found in string".to_string())
+        );
+        assert_eq!(
+            ConsoleCommand::parse("block 16"),
+            Ok(Some(ConsoleCommand::Block(Some(16))))
+        );
+        assert_eq!(
+            ConsoleCommand::parse(&format!("send {} 5", address)),
+            Ok(Some(ConsoleCommand::Send {
+                to: address,
+                amount: U256::from(5),
+                key_file: None
+            }))
+        );
+        assert_eq!(
+            ConsoleCommand::parse(r#"rpc eth_getBalance ["0x01", "latest"]"#),
+            Ok(Some(ConsoleCommand::Rpc {
+                method: "eth_getBalance".to_string(),
+                params: json!(["0x01", "latest"]),
+            }))
+        );
+        assert!(ConsoleCommand::parse("subscribe logs").is_err());
+        assert!(ConsoleCommand::parse("send").is_err());
+        assert!(ConsoleCommand::parse("frobnicate").is_err());
+        assert_eq!(
+            ConsoleCommand::parse("quit"),
+            Ok(Some(ConsoleCommand::Exit))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let node = MockNode::new();
+        let address = Address::repeat_byte(1);
+        node.set_balance(address, U256::from(42));
+        node.set_block_number(U256::from(3));
+        let console = Console::new(
+            Client::with_transport(node.clone()),
+            "ws://127.0.0.1:8545".to_string(),
+            None,
+        );
+
+        assert_eq!(
+            console
+                .execute(ConsoleCommand::Balance(address))
+                .await
+                .unwrap(),
+            "42"
+        );
+        assert_eq!(
+            console
+                .execute(ConsoleCommand::Rpc {
+                    method: "eth_blockNumber".to_string(),
+                    params: json!([]),
+                })
+                .await
+                .unwrap(),
+            "\"0x3\""
+        );
+
+        // Without a key there is nothing to sign with
+        let send = ConsoleCommand::Send {
+            to: address,
+            amount: U256::from(1),
+            key_file: None,
+        };
+        assert_eq!(
+            console.execute(send).await.unwrap_err().kind,
+            ErrorKind::InvalidInput
+        );
+        assert!(node.transfers().is_empty());
+    }
+
+    #[test]
+    fn test_default_ws_url() {
+        assert_eq!(
+            default_ws_url("http://127.0.0.1:8545"),
+            "ws://127.0.0.1:8545"
+        );
+        assert_eq!(default_ws_url("https://node.example"), "wss://node.example");
+    }
+}
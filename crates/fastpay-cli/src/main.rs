@@ -0,0 +1,404 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use alloy::primitives::{hex, Address, U256};
+use alloy::signers::local::PrivateKeySigner;
+use clap::{Args, Parser, Subcommand};
+use client::{fee::FeeLevel, Client};
+use console::Console;
+use output::{BalanceOutput, CliError, ErrorKind, KeyOutput, Output, TransferOutput};
+use tx::tx::Tx;
+use wallet::Wallet;
+
+mod console;
+mod output;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "fastpay-cli",
+    version,
+    about = "Manage keys and send transfers"
+)]
+struct Cli {
+    #[arg(long, global = true, default_value = "http://127.0.0.1:8545")]
+    rpc_url: String,
+    #[arg(
+        long,
+        global = true,
+        help = "Print the result, or the error, as a JSON object on stdout"
+    )]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(subcommand, about = "Create or import a key")]
+    Wallet(WalletCommand),
+    #[command(about = "Show the balance of an account")]
+    Balance { address: Address },
+    #[command(about = "Sign and send a transfer")]
+    Transfer(TransferArgs),
+    #[command(about = "Open an interactive console attached to the node")]
+    Console(ConsoleArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum WalletCommand {
+    #[command(about = "Generate a new key")]
+    New {
+        #[arg(long, help = "Save the key to this file instead of printing it")]
+        out: Option<PathBuf>,
+    },
+    #[command(about = "Save an existing private key to a key file")]
+    Import {
+        #[arg(long)]
+        private_key: String,
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Debug, Args)]
+struct TransferArgs {
+    #[arg(long)]
+    to: Address,
+    #[arg(long)]
+    amount: U256,
+    #[arg(long, help = "File holding the sender's private key")]
+    key_file: PathBuf,
+    #[arg(
+        long,
+        help = "Fee to pay, an amount or low, medium or high to go by the node's estimate. \
+                Nothing when not set"
+    )]
+    fee: Option<Fee>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fee {
+    Amount(u64),
+    Level(FeeLevel),
+}
+
+impl FromStr for Fee {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(amount) => Ok(Self::Amount(amount)),
+            Err(_) => s.parse().map(Self::Level),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct ConsoleArgs {
+    #[arg(
+        long,
+        help = "Websocket url for subscriptions, defaults to the rpc url's host"
+    )]
+    ws_url: Option<String>,
+    #[arg(long, help = "File holding the key `send` signs with")]
+    key_file: Option<PathBuf>,
+}
+
+fn parse_private_key(private_key: &str) -> Result<PrivateKeySigner, CliError> {
+    PrivateKeySigner::from_str(private_key.trim()).map_err(|e| {
+        CliError::new(
+            ErrorKind::InvalidInput,
+            format!("invalid private key: {}", e),
+        )
+    })
+}
+
+// key files hold the hex encoded private key, readable only by their owner
+fn save_key(path: &Path, signer: &PrivateKeySigner) -> Result<(), CliError> {
+    if path.exists() {
+        return Err(CliError::new(
+            ErrorKind::InvalidInput,
+            format!("{} already exists", path.display()),
+        ));
+    }
+    let io_error = |e: std::io::Error| {
+        CliError::new(
+            ErrorKind::Io,
+            format!("failed to write {}: {}", path.display(), e),
+        )
+    };
+    std::fs::write(path, hex::encode_prefixed(signer.to_bytes())).map_err(io_error)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(io_error)?;
+    }
+    Ok(())
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeySigner, CliError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        CliError::new(
+            ErrorKind::Io,
+            format!("failed to read {}: {}", path.display(), e),
+        )
+    })?;
+    parse_private_key(&contents)
+}
+
+fn wallet(command: WalletCommand) -> Result<Output, CliError> {
+    let (signer, out) = match command {
+        WalletCommand::New { out } => (PrivateKeySigner::random(), out),
+        WalletCommand::Import { private_key, out } => (parse_private_key(&private_key)?, Some(out)),
+    };
+    let private_key = match &out {
+        Some(out) => {
+            save_key(out, &signer)?;
+            None
+        }
+        None => Some(hex::encode_prefixed(signer.to_bytes())),
+    };
+    Ok(Output::Key(KeyOutput {
+        address: signer.address(),
+        private_key,
+        key_file: out,
+    }))
+}
+
+fn sign_transfer(
+    wallet: &Wallet,
+    to: Address,
+    amount: U256,
+    fee: u64,
+    chain_id: u64,
+) -> Result<Tx, CliError> {
+    let tx = Tx::new(wallet.address(), to, amount, None)
+        .with_fee(fee)
+        .with_chain_id(chain_id);
+    let signature = wallet.sign_transaction_sync(tx.clone()).map_err(|e| {
+        CliError::new(
+            ErrorKind::InvalidInput,
+            format!("failed to sign transfer: {:?}", e),
+        )
+    })?;
+    Ok(tx.with_signature(signature))
+}
+
+// None for the console, which prints as it goes
+async fn run(cli: Cli) -> Result<Option<Output>, CliError> {
+    let client = || {
+        Client::new(cli.rpc_url.clone()).map_err(|e| CliError::from_client("invalid rpc url", e))
+    };
+
+    match cli.command {
+        Command::Wallet(command) => wallet(command).map(Some),
+        Command::Balance { address } => {
+            let balance = client()?
+                .get_balance(address)
+                .await
+                .map_err(|e| CliError::from_client("failed to get balance", e))?;
+            Ok(Some(Output::Balance(BalanceOutput::new(address, balance))))
+        }
+        Command::Transfer(args) => {
+            let wallet = Wallet::new(load_key(&args.key_file)?);
+            let client = client()?;
+            let chain_id = client
+                .chain_id()
+                .await
+                .map_err(|e| CliError::from_client("failed to get chain id", e))?;
+            let fee = match args.fee {
+                None => 0,
+                Some(Fee::Amount(fee)) => fee,
+                Some(Fee::Level(level)) => {
+                    client
+                        .estimate_fee()
+                        .await
+                        .map_err(|e| CliError::from_client("failed to estimate the fee", e))?
+                        .level(level)
+                        .fee
+                }
+            };
+            let tx = sign_transfer(&wallet, args.to, args.amount, fee, chain_id)?;
+            let tx_hash = client
+                .send_transfer(&tx)
+                .await
+                .map_err(|e| CliError::from_client("failed to send transfer", e))?;
+            Ok(Some(Output::Transfer(TransferOutput {
+                tx_hash,
+                from: wallet.address(),
+                to: args.to,
+                amount: args.amount.to_string(),
+                fee,
+                chain_id,
+            })))
+        }
+        Command::Console(args) => {
+            let ws_url = args
+                .ws_url
+                .unwrap_or_else(|| console::default_ws_url(&cli.rpc_url));
+            Console::new(client()?, ws_url, args.key_file).run().await?;
+            Ok(None)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.json;
+    match run(cli).await {
+        Ok(output) => {
+            if let Some(output) = output {
+                output.print(json);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => e.report(json),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fastpay-cli-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "fastpay-cli",
+            "transfer",
+            "--to",
+            "0x0101010101010101010101010101010101010101",
+            "--amount",
+            "5",
+            "--key-file",
+            "key",
+            "--rpc-url",
+            "http://node:8545",
+        ])
+        .unwrap();
+        assert_eq!(cli.rpc_url, "http://node:8545");
+        assert!(!cli.json);
+        assert!(matches!(
+            cli.command,
+            Command::Transfer(TransferArgs {
+                amount,
+                fee: None,
+                ..
+            }) if amount == U256::from(5)
+        ));
+
+        for (fee, expected) in [
+            ("3", Fee::Amount(3)),
+            ("medium", Fee::Level(FeeLevel::Medium)),
+        ] {
+            let cli = Cli::try_parse_from([
+                "fastpay-cli",
+                "transfer",
+                "--to",
+                "0x0101010101010101010101010101010101010101",
+                "--amount",
+                "5",
+                "--key-file",
+                "key",
+                "--fee",
+                fee,
+            ])
+            .unwrap();
+            assert!(matches!(
+                cli.command,
+                Command::Transfer(TransferArgs { fee: Some(parsed), .. }) if parsed == expected
+            ));
+        }
+        assert!("cheap".parse::<Fee>().is_err());
+
+        let cli = Cli::try_parse_from(["fastpay-cli", "console", "--key-file", "key"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Console(ConsoleArgs { ws_url: None, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wallet_output() {
+        let path = temp_path("wallet-output");
+        let cli = Cli::try_parse_from([
+            "fastpay-cli",
+            "wallet",
+            "new",
+            "--out",
+            path.to_str().unwrap(),
+            "--json",
+        ])
+        .unwrap();
+        assert!(cli.json);
+
+        let Some(Output::Key(key)) = run(cli).await.unwrap() else {
+            panic!("expected a key");
+        };
+        assert_eq!(key.private_key, None);
+        assert_eq!(load_key(&path).unwrap().address(), key.address);
+
+        // The key file is taken now
+        let cli = Cli::try_parse_from([
+            "fastpay-cli",
+            "wallet",
+            "new",
+            "--out",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let error = run(cli).await.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_load_key() {
+        let path = temp_path("key");
+        let signer = PrivateKeySigner::random();
+
+        save_key(&path, &signer).unwrap();
+        assert_eq!(load_key(&path).unwrap().address(), signer.address());
+
+        // Existing keys are never overwritten
+        assert!(save_key(&path, &PrivateKeySigner::random()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_private_key() {
+        let signer = PrivateKeySigner::random();
+        let encoded = hex::encode(signer.to_bytes());
+
+        assert_eq!(
+            parse_private_key(&encoded).unwrap().address(),
+            signer.address()
+        );
+        assert!(parse_private_key("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_sign_transfer() {
+        let wallet = Wallet::random();
+        let to = Address::repeat_byte(1);
+
+        let tx = sign_transfer(&wallet, to, U256::from(10), 2, 7).unwrap();
+        assert_eq!(tx.chain_id(), Some(7));
+        assert_eq!(tx.fee(), 2);
+        let signer = tx
+            .signature()
+            .unwrap()
+            .recover_address_from_msg(tx.tx_hash())
+            .unwrap();
+        assert_eq!(signer, wallet.address());
+    }
+}
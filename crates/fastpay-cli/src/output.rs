@@ -0,0 +1,202 @@
+// what the commands print: a line or two for people, or with --json one JSON object on stdout
+// whose fields only ever get added to, so scripts can rely on them. Failures exit with a code
+// per class of error
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use alloy::primitives::{Address, B256, U256};
+use client::ClientError;
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Output {
+    Key(KeyOutput),
+    Balance(BalanceOutput),
+    Transfer(TransferOutput),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyOutput {
+    pub address: Address,
+    // only printed when the key isn't saved to a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceOutput {
+    pub address: Address,
+    // decimal, balances can outgrow what JSON numbers hold exactly
+    pub balance: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferOutput {
+    pub tx_hash: B256,
+    pub from: Address,
+    pub to: Address,
+    // decimal, like balances
+    pub amount: String,
+    pub fee: u64,
+    pub chain_id: u64,
+}
+
+impl BalanceOutput {
+    pub fn new(address: Address, balance: U256) -> Self {
+        Self {
+            address,
+            balance: balance.to_string(),
+        }
+    }
+}
+
+impl Output {
+    pub fn print(&self, json: bool) {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string(self).expect("output serializes")
+            );
+            return;
+        }
+        match self {
+            Self::Key(key) => {
+                println!("address: {}", key.address);
+                if let Some(private_key) = &key.private_key {
+                    println!("private key: {}", private_key);
+                }
+                if let Some(key_file) = &key.key_file {
+                    println!("key saved to {}", key_file.display());
+                }
+            }
+            Self::Balance(balance) => println!("{}", balance.balance),
+            Self::Transfer(transfer) => println!("{}", transfer.tx_hash),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    // bad arguments or a bad key
+    InvalidInput,
+    // reading or writing a file failed
+    Io,
+    // the node couldn't be reached or answered with garbage, trying again may help
+    Unavailable,
+    // the node refused the request
+    Rejected,
+}
+
+impl ErrorKind {
+    // 1 is left to panics and 2 to clap's usage errors
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidInput => 3,
+            Self::Io => 4,
+            Self::Unavailable => 5,
+            Self::Rejected => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    // `context` says what was being done, e.g. "failed to send transfer"
+    pub fn from_client(context: &str, error: ClientError) -> Self {
+        let kind = match &error {
+            ClientError::Rpc { .. } => ErrorKind::Rejected,
+            ClientError::InvalidRequest(_) => ErrorKind::InvalidInput,
+            ClientError::Transport(_)
+            | ClientError::Timeout
+            | ClientError::Http(_)
+            | ClientError::InvalidResponse(_) => ErrorKind::Unavailable,
+        };
+        Self::new(kind, format!("{}: {:?}", context, error))
+    }
+
+    pub fn report(&self, json: bool) -> ExitCode {
+        if json {
+            println!(
+                "{}",
+                json!({ "error": { "kind": self.kind, "message": self.message } })
+            );
+        } else {
+            eprintln!("Error: {}", self.message);
+        }
+        ExitCode::from(self.kind.exit_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema() {
+        let key = Output::Key(KeyOutput {
+            address: Address::repeat_byte(1),
+            private_key: None,
+            key_file: Some(PathBuf::from("key")),
+        });
+        assert_eq!(
+            serde_json::to_value(&key).unwrap(),
+            json!({ "address": Address::repeat_byte(1), "keyFile": "key" })
+        );
+
+        let balance = Output::Balance(BalanceOutput::new(Address::ZERO, U256::MAX));
+        assert_eq!(
+            serde_json::to_value(&balance).unwrap()["balance"],
+            U256::MAX.to_string()
+        );
+
+        let transfer = Output::Transfer(TransferOutput {
+            tx_hash: B256::repeat_byte(2),
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(3),
+            amount: U256::from(5).to_string(),
+            fee: 0,
+            chain_id: 1337,
+        });
+        let value = serde_json::to_value(&transfer).unwrap();
+        assert_eq!(value["txHash"], json!(B256::repeat_byte(2)));
+        assert_eq!(value["amount"], "5");
+        assert_eq!(value["chainId"], 1337);
+    }
+
+    #[test]
+    fn test_client_errors() {
+        let rejected = ClientError::Rpc {
+            code: -32602,
+            message: "transaction rejected".to_string(),
+        };
+        let error = CliError::from_client("failed to send transfer", rejected);
+        assert_eq!(error.kind, ErrorKind::Rejected);
+        assert!(error.message.starts_with("failed to send transfer"));
+
+        assert_eq!(
+            CliError::from_client("", ClientError::Timeout).kind,
+            ErrorKind::Unavailable
+        );
+        assert_eq!(ErrorKind::Unavailable.exit_code(), 5);
+    }
+}
@@ -0,0 +1,315 @@
+// fastpay-monitor polls a set of nodes and alerts when their chains drift apart:
+// either one falls too far behind, or two of them disagree on a block they both have. It can
+// also watch the balances of some accounts, see watch.rs
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use clap::Parser;
+use client::{events::BlockEvent, Client};
+use futures::future::join_all;
+use serde::Serialize;
+
+use watch::{BalanceWatcher, Threshold, Watch};
+
+mod watch;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "fastpay-monitor",
+    version,
+    about = "Compare the chain heads of several fastpay nodes"
+)]
+struct Cli {
+    #[arg(
+        long = "endpoint",
+        required = true,
+        help = "RPC endpoint to watch, repeatable"
+    )]
+    endpoints: Vec<String>,
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "How many blocks a node may lag behind the highest head"
+    )]
+    max_lag: u64,
+    #[arg(
+        long,
+        help = "Keep polling every this many seconds instead of checking once"
+    )]
+    interval: Option<u64>,
+    #[arg(long, help = "URL that receives alerts as a JSON POST")]
+    webhook: Option<String>,
+    #[arg(
+        long = "watch",
+        help = "Alert when a balance crosses a threshold, as <address>:below|above:<amount>, repeatable"
+    )]
+    watches: Vec<Watch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Alert {
+    Unreachable {
+        endpoint: String,
+        error: String,
+    },
+    Lagging {
+        endpoint: String,
+        head: u64,
+        highest: u64,
+    },
+    // two nodes have different blocks, or different state, at the same height
+    Diverged {
+        height: u64,
+        endpoints: Vec<String>,
+        hashes: Vec<B256>,
+        state_roots: Vec<Option<B256>>,
+    },
+    Balance {
+        address: Address,
+        balance: U256,
+        threshold: Threshold,
+    },
+}
+
+async fn fetch_block(endpoint: &str, number: Option<u64>) -> Result<BlockEvent, String> {
+    let client = Client::new(endpoint).map_err(|e| format!("{:?}", e))?;
+    match client.get_block_by_number(number).await {
+        Ok(Some(block)) => Ok(block),
+        Ok(None) => Err("block not found".to_string()),
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+fn lag_alerts(heads: &[(String, BlockEvent)], max_lag: u64) -> Vec<Alert> {
+    let highest = heads.iter().map(|(_, head)| head.number).max().unwrap_or(0);
+
+    heads
+        .iter()
+        .filter(|(_, head)| highest - head.number > max_lag)
+        .map(|(endpoint, head)| Alert::Lagging {
+            endpoint: endpoint.clone(),
+            head: head.number,
+            highest,
+        })
+        .collect()
+}
+
+// `blocks` are the blocks every node has at the same height
+fn divergence_alert(height: u64, blocks: &[(String, BlockEvent)]) -> Option<Alert> {
+    let (_, first) = blocks.first()?;
+    let agree = blocks
+        .iter()
+        .all(|(_, block)| block.hash == first.hash && block.state_root == first.state_root);
+    if agree {
+        return None;
+    }
+
+    Some(Alert::Diverged {
+        height,
+        endpoints: blocks
+            .iter()
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect(),
+        hashes: blocks.iter().map(|(_, block)| block.hash).collect(),
+        state_roots: blocks.iter().map(|(_, block)| block.state_root).collect(),
+    })
+}
+
+// None where the balance couldn't be fetched
+async fn fetch_balances(endpoint: &str, watches: &[Watch]) -> Vec<Option<U256>> {
+    let Ok(client) = Client::new(endpoint) else {
+        return vec![None; watches.len()];
+    };
+    let results = join_all(watches.iter().map(|w| client.get_balance(w.address))).await;
+    results
+        .into_iter()
+        .zip(watches)
+        .map(|(result, watch)| match result {
+            Ok(balance) => Some(balance),
+            Err(e) => {
+                eprintln!("failed to get the balance of {}: {:?}", watch.address, e);
+                None
+            }
+        })
+        .collect()
+}
+
+async fn check(endpoints: &[String], max_lag: u64, watcher: &mut BalanceWatcher) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    let results = join_all(endpoints.iter().map(|e| fetch_block(e, None))).await;
+    let mut heads = Vec::new();
+    for (endpoint, result) in endpoints.iter().zip(results) {
+        match result {
+            Ok(head) => heads.push((endpoint.clone(), head)),
+            Err(error) => alerts.push(Alert::Unreachable {
+                endpoint: endpoint.clone(),
+                error,
+            }),
+        }
+    }
+    alerts.extend(lag_alerts(&heads, max_lag));
+
+    // balances are read from the first node that answered
+    if let (Some((endpoint, _)), false) = (heads.first(), watcher.watches().is_empty()) {
+        let balances = fetch_balances(endpoint, watcher.watches()).await;
+        alerts.extend(watcher.update(&balances));
+    }
+
+    // compare everyone at the lowest head, the highest block all of them should have
+    let Some(height) = heads.iter().map(|(_, head)| head.number).min() else {
+        return alerts;
+    };
+    let results = join_all(heads.iter().map(|(e, _)| fetch_block(e, Some(height)))).await;
+    let mut blocks = Vec::new();
+    for ((endpoint, _), result) in heads.iter().zip(results) {
+        match result {
+            Ok(block) => blocks.push((endpoint.clone(), block)),
+            Err(error) => alerts.push(Alert::Unreachable {
+                endpoint: endpoint.clone(),
+                error,
+            }),
+        }
+    }
+    alerts.extend(divergence_alert(height, &blocks));
+
+    alerts
+}
+
+async fn notify(webhook: &str, alerts: &[Alert]) -> anyhow::Result<()> {
+    reqwest::Client::new()
+        .post(webhook)
+        .json(&serde_json::json!({ "alerts": alerts }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn report(cli: &Cli, alerts: &[Alert]) {
+    for alert in alerts {
+        eprintln!("{}", serde_json::to_string(alert).unwrap());
+    }
+    if let (Some(webhook), false) = (&cli.webhook, alerts.is_empty()) {
+        if let Err(e) = notify(webhook, alerts).await {
+            eprintln!("failed to call webhook: {}", e);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut watcher = BalanceWatcher::new(cli.watches.clone());
+
+    let Some(interval) = cli.interval else {
+        let alerts = check(&cli.endpoints, cli.max_lag, &mut watcher).await;
+        report(&cli, &alerts).await;
+        return if alerts.is_empty() {
+            println!("{} nodes in agreement", cli.endpoints.len());
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    };
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        ticker.tick().await;
+        let alerts = check(&cli.endpoints, cli.max_lag, &mut watcher).await;
+        report(&cli, &alerts).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn block(number: u64, hash: u8, state_root: u8) -> BlockEvent {
+        BlockEvent {
+            number,
+            hash: B256::repeat_byte(hash),
+            parent_hash: B256::ZERO,
+            state_root: Some(B256::repeat_byte(state_root)),
+            timestamp: 0,
+            transactions: Vec::new(),
+            address_bloom: None,
+        }
+    }
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        assert!(Cli::try_parse_from(["fastpay-monitor"]).is_err());
+
+        let watch = format!("{}:below:100", Address::repeat_byte(1));
+        let cli =
+            Cli::try_parse_from(["fastpay-monitor", "--endpoint", "a", "--watch", &watch]).unwrap();
+        assert_eq!(cli.watches.len(), 1);
+        assert!(
+            Cli::try_parse_from(["fastpay-monitor", "--endpoint", "a", "--watch", "x"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_lag_alerts() {
+        let heads = vec![
+            ("a".to_string(), block(100, 1, 1)),
+            ("b".to_string(), block(97, 1, 1)),
+            ("c".to_string(), block(90, 1, 1)),
+        ];
+
+        assert_eq!(
+            lag_alerts(&heads, 5),
+            vec![Alert::Lagging {
+                endpoint: "c".to_string(),
+                head: 90,
+                highest: 100
+            }]
+        );
+        assert!(lag_alerts(&heads, 10).is_empty());
+    }
+
+    #[test]
+    fn test_divergence_alert() {
+        let agreeing = vec![
+            ("a".to_string(), block(10, 1, 1)),
+            ("b".to_string(), block(10, 1, 1)),
+        ];
+        assert!(divergence_alert(10, &agreeing).is_none());
+
+        // Same block, different state
+        let split_state = vec![
+            ("a".to_string(), block(10, 1, 1)),
+            ("b".to_string(), block(10, 1, 2)),
+        ];
+        assert!(divergence_alert(10, &split_state).is_some());
+
+        let forked = vec![
+            ("a".to_string(), block(10, 1, 1)),
+            ("b".to_string(), block(10, 2, 1)),
+        ];
+        match divergence_alert(10, &forked).unwrap() {
+            Alert::Diverged { height, hashes, .. } => {
+                assert_eq!(height, 10);
+                assert_eq!(hashes, vec![B256::repeat_byte(1), B256::repeat_byte(2)]);
+            }
+            other => panic!("unexpected alert {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_unreachable_nodes() {
+        let watch = Watch {
+            address: Address::ZERO,
+            threshold: Threshold::Below(U256::from(1)),
+        };
+        let mut watcher = BalanceWatcher::new(vec![watch]);
+        let alerts = check(&["http://127.0.0.1:1".to_string()], 5, &mut watcher).await;
+        assert!(matches!(alerts[..], [Alert::Unreachable { .. }]));
+    }
+}
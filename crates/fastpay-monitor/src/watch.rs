@@ -0,0 +1,159 @@
+// balance watches: an address and a level its balance shouldn't go past, e.g. a hot wallet that
+// needs a refill below some amount or a fee pool to sweep above another. A watch alerts when the
+// balance crosses its threshold, and not again until it has come back
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use alloy::primitives::{Address, U256};
+use serde::Serialize;
+
+use crate::Alert;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Threshold {
+    Below(U256),
+    Above(U256),
+}
+
+impl Threshold {
+    pub fn is_crossed_by(&self, balance: U256) -> bool {
+        match self {
+            Self::Below(level) => balance < *level,
+            Self::Above(level) => balance > *level,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    pub address: Address,
+    pub threshold: Threshold,
+}
+
+// <address>:below:<amount> or <address>:above:<amount>
+impl FromStr for Watch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [address, direction, amount] = s.split(':').collect::<Vec<_>>()[..] else {
+            return Err(format!(
+                "invalid watch {}, expected <address>:below|above:<amount>",
+                s
+            ));
+        };
+        let address = address
+            .parse()
+            .map_err(|e| format!("invalid address {}: {}", address, e))?;
+        let amount = amount
+            .parse()
+            .map_err(|e| format!("invalid amount {}: {}", amount, e))?;
+        let threshold = match direction {
+            "below" => Threshold::Below(amount),
+            "above" => Threshold::Above(amount),
+            other => {
+                return Err(format!(
+                    "invalid direction {}, expected below or above",
+                    other
+                ))
+            }
+        };
+        Ok(Self { address, threshold })
+    }
+}
+
+pub struct BalanceWatcher {
+    watches: Vec<Watch>,
+    // indices of the watches whose balance is past the threshold and already alerted on
+    crossed: HashSet<usize>,
+}
+
+impl BalanceWatcher {
+    pub fn new(watches: Vec<Watch>) -> Self {
+        Self {
+            watches,
+            crossed: HashSet::new(),
+        }
+    }
+
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    // `balances` line up with the watches, None where the balance couldn't be fetched, which
+    // leaves the watch as it was
+    pub fn update(&mut self, balances: &[Option<U256>]) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for (index, (watch, balance)) in self.watches.iter().zip(balances).enumerate() {
+            let Some(balance) = *balance else {
+                continue;
+            };
+            if !watch.threshold.is_crossed_by(balance) {
+                self.crossed.remove(&index);
+            } else if self.crossed.insert(index) {
+                alerts.push(Alert::Balance {
+                    address: watch.address,
+                    balance,
+                    threshold: watch.threshold,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let address = Address::repeat_byte(1);
+        assert_eq!(
+            format!("{}:below:1000", address).parse(),
+            Ok(Watch {
+                address,
+                threshold: Threshold::Below(U256::from(1000))
+            })
+        );
+        assert!(format!("{}:above:0x10", address).parse::<Watch>().is_ok());
+        assert!(format!("{}:sideways:10", address).parse::<Watch>().is_err());
+        assert!(format!("{}:below", address).parse::<Watch>().is_err());
+        assert!("nope:below:10".parse::<Watch>().is_err());
+    }
+
+    #[test]
+    fn test_update() {
+        let hot_wallet = Watch {
+            address: Address::repeat_byte(1),
+            threshold: Threshold::Below(U256::from(100)),
+        };
+        let fee_pool = Watch {
+            address: Address::repeat_byte(2),
+            threshold: Threshold::Above(U256::from(500)),
+        };
+        let mut watcher = BalanceWatcher::new(vec![hot_wallet, fee_pool]);
+        let balances = |hot: u64, fees: u64| [Some(U256::from(hot)), Some(U256::from(fees))];
+
+        assert!(watcher.update(&balances(150, 100)).is_empty());
+        assert_eq!(
+            watcher.update(&balances(99, 100)),
+            vec![Alert::Balance {
+                address: hot_wallet.address,
+                balance: U256::from(99),
+                threshold: hot_wallet.threshold,
+            }]
+        );
+        // Still low, already alerted. The fee pool crossing alerts on its own
+        let alerts = watcher.update(&balances(50, 501));
+        assert_eq!(alerts.len(), 1);
+        assert!(matches!(alerts[0], Alert::Balance { address, .. } if address == fee_pool.address));
+        // A failed fetch changes nothing
+        assert!(watcher.update(&[None, None]).is_empty());
+
+        // Once refilled, the hot wallet alerts again the next time it runs low
+        assert!(watcher.update(&balances(100, 501)).is_empty());
+        assert_eq!(watcher.update(&balances(10, 501)).len(), 1);
+    }
+}
@@ -0,0 +1,213 @@
+// fixture chains for explorer and indexer development: the same arguments always produce the
+// same genesis and the same blocks, timestamps included
+
+use std::collections::BTreeMap;
+
+use alloy::primitives::{Address, B256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use block_builder::Block;
+use node::{
+    genesis::{Genesis, GenesisAccount},
+    Node,
+};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use state::{memory::MemoryState, policy::DailySpending};
+use tx::tx::Tx;
+
+pub const GENESIS_BALANCE: u64 = 1_000_000_000;
+// the first block's timestamp, 2023-11-14
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DevchainConfig {
+    pub blocks: u64,
+    pub tps: u64,
+    pub accounts: usize,
+    // seconds between blocks
+    pub block_time: u64,
+    pub seed: u64,
+}
+
+pub struct Devchain {
+    pub genesis: Genesis,
+    pub blocks: Vec<Block>,
+}
+
+// the first few accounts act as merchants, everybody pays them and they pay out in bursts
+fn merchants(accounts: &[PrivateKeySigner]) -> usize {
+    (accounts.len() / 20).max(1)
+}
+
+// one transfer, picked from a mix of patterns so the chain doesn't look uniform
+fn random_transfer(rng: &mut ChaCha8Rng, accounts: &[PrivateKeySigner]) -> (usize, usize, u64) {
+    let merchants = merchants(accounts);
+    let any = |rng: &mut ChaCha8Rng| rng.gen_range(0..accounts.len());
+
+    let (from, to, amount) = match rng.gen_range(0..100) {
+        // small peer to peer payments
+        0..=49 => (any(rng), any(rng), rng.gen_range(1..=1_000)),
+        // customers paying a merchant
+        50..=84 => (
+            any(rng),
+            rng.gen_range(0..merchants),
+            rng.gen_range(100..=10_000),
+        ),
+        // merchants paying out
+        85..=94 => (
+            rng.gen_range(0..merchants),
+            any(rng),
+            rng.gen_range(10_000..=100_000),
+        ),
+        // rare large round amounts
+        _ => (any(rng), any(rng), rng.gen_range(1..=10) * 1_000_000),
+    };
+    (from, to, amount)
+}
+
+pub fn generate(config: DevchainConfig) -> anyhow::Result<Devchain> {
+    if config.accounts < 2 {
+        anyhow::bail!("a devchain needs at least 2 accounts");
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+
+    let accounts: Vec<_> = (0..config.accounts)
+        .map(|_| PrivateKeySigner::from_bytes(&B256::from(rng.gen::<[u8; 32]>())))
+        .collect::<Result<_, _>>()?;
+    let genesis = Genesis::new(
+        accounts
+            .iter()
+            .map(|signer| GenesisAccount {
+                address: signer.address(),
+                balance: U256::from(GENESIS_BALANCE),
+                sequence: 0,
+                multisig: None,
+                module: None,
+                label: None,
+                created_at: 0,
+                policy: None,
+                spending: DailySpending::default(),
+                schedules: Vec::new(),
+                escrows: Vec::new(),
+                htlcs: Vec::new(),
+                assets: BTreeMap::new(),
+            })
+            .collect(),
+    );
+
+    let mut state = MemoryState::new();
+    genesis.apply(&mut state)?;
+    let mut node = Node::new(Box::new(state));
+
+    let mut blocks = Vec::new();
+    let txs_per_block = config.tps * config.block_time;
+    let mut parent_hash = B256::ZERO;
+    for number in 0..config.blocks {
+        // some blocks are quiet, some busy
+        let count = match txs_per_block {
+            0 => 0,
+            n => rng.gen_range(n / 2..=n + n / 2),
+        };
+
+        let mut txs = Vec::new();
+        for _ in 0..count {
+            let (from, to, amount) = random_transfer(&mut rng, &accounts);
+            let signer = &accounts[from];
+            let tx = Tx::new(
+                signer.address(),
+                accounts[to].address(),
+                U256::from(amount),
+                None,
+            );
+            let signature = signer.sign_message_sync(&tx.tx_hash())?;
+            txs.push(tx.with_signature(signature));
+        }
+        txs.shuffle(&mut rng);
+
+        node.set_current_block(number);
+        let included: Vec<_> = node
+            .execute_batch(&txs)
+            .into_iter()
+            .zip(txs)
+            .filter_map(|(result, tx)| result.ok().map(|_| tx))
+            .collect();
+
+        // built by hand rather than by a BlockBuilder so timestamps don't depend on the clock
+        let block = Block::new(
+            U256::from(number),
+            parent_hash,
+            GENESIS_TIMESTAMP + number * config.block_time,
+            included,
+            Address::ZERO,
+        )
+        .with_state_root(node.state().state_root());
+        parent_hash = block.hash;
+        blocks.push(block);
+    }
+    Ok(Devchain { genesis, blocks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(seed: u64) -> DevchainConfig {
+        DevchainConfig {
+            blocks: 5,
+            tps: 4,
+            accounts: 10,
+            block_time: 2,
+            seed,
+        }
+    }
+
+    #[test]
+    fn test_generate_is_reproducible() {
+        let first = generate(config(7)).unwrap();
+        let second = generate(config(7)).unwrap();
+
+        assert_eq!(first.genesis.accounts.len(), 10);
+        assert_eq!(first.blocks.len(), 5);
+        assert_eq!(
+            first.blocks.last().unwrap().hash,
+            second.blocks.last().unwrap().hash
+        );
+        assert_eq!(first.blocks[1].timestamp, GENESIS_TIMESTAMP + 2);
+        assert!(first
+            .blocks
+            .iter()
+            .any(|block| !block.transactions.is_empty()));
+
+        let other = generate(config(8)).unwrap();
+        assert_ne!(
+            first.blocks.last().unwrap().hash,
+            other.blocks.last().unwrap().hash
+        );
+    }
+
+    #[test]
+    fn test_generated_chain_replays() {
+        let devchain = generate(config(1)).unwrap();
+
+        let mut state = MemoryState::new();
+        devchain.genesis.apply(&mut state).unwrap();
+        let mut node = Node::new(Box::new(state));
+        for block in &devchain.blocks {
+            assert!(node
+                .execute_batch(&block.transactions)
+                .iter()
+                .all(|result| result.is_ok()));
+            assert_eq!(node.state().state_root(), block.state_root);
+        }
+    }
+
+    #[test]
+    fn test_generate_needs_accounts() {
+        assert!(generate(DevchainConfig {
+            accounts: 1,
+            ..config(0)
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,1700 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use block_builder::{
+    limits::BlockLimits,
+    prune::PruneConfig,
+    schedule::Rotation,
+    store::{BlockStore, SledBlockStore},
+    BlockBuilder,
+};
+use clap::{Args, Parser, Subcommand};
+use mempool::{Mempool, MempoolConfig};
+use netting::NettingEngine;
+use node::{
+    actor::NodeHandle,
+    archive::StateArchive,
+    export::StateExport,
+    genesis::{Genesis, GenesisAuthority, GenesisProducers, GenesisSchedule},
+    snapshot::Snapshot,
+    Node,
+};
+use rpc::{
+    auth::{AuthConfig, AuthSecret},
+    health::HealthThresholds,
+    limits::{MethodTimeouts, RpcLimits},
+    namespace::Namespace,
+    personal::KeyManager,
+    preconf::Preconfirmer,
+    rate_limit::{RateLimit, RateLimits},
+    sync::SyncStatus,
+    RpcConfig,
+};
+use state::{memory::MemoryState, pending::PendingState, sharded::ShardedState, state::State};
+use sync::{RpcPeer, Syncer};
+use tx::signature_cache::SignatureCache;
+
+mod devchain;
+mod prune;
+mod replay;
+mod sync;
+
+const GENESIS_FILE: &str = "genesis.json";
+const BLOCKS_DIR: &str = "blocks";
+const AUTH_SECRET_FILE: &str = "auth-secret";
+// the state a data directory restored from an archive starts from instead of the genesis'
+const STATE_ARCHIVE_FILE: &str = "state-archive";
+const STATE_SHARDS: usize = 16;
+
+#[derive(Debug, Parser)]
+#[command(name = "fastpay-node", version, about = "Run a fastpay node")]
+struct Cli {
+    #[arg(
+        long,
+        global = true,
+        default_value = "pretty",
+        help = "How traces are written to stderr, pretty or json. RUST_LOG picks what is traced, \
+                info when not set"
+    )]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "invalid log format {}, expected pretty or json",
+                other
+            )),
+        }
+    }
+}
+
+fn init_tracing(format: LogFormat) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Pretty => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(about = "Write a genesis file to the data directory")]
+    Init(InitArgs),
+    #[command(about = "Start the RPC server and produce blocks")]
+    Run(Box<RunArgs>),
+    #[command(subcommand, about = "Manage accounts")]
+    Account(AccountCommand),
+    #[command(subcommand, about = "Generate local development chains")]
+    Devchain(DevchainCommand),
+    #[command(subcommand, about = "Export and compare state snapshots")]
+    Snapshot(SnapshotCommand),
+    #[command(
+        subcommand,
+        about = "Back up the whole state or start a chain from a backup"
+    )]
+    State(StateCommand),
+    #[command(
+        about = "Execute the chain in the data directory again from its genesis and check that \
+                 every block leads to its state root and stored receipts, the node must not be \
+                 running"
+    )]
+    Replay(ReplayArgs),
+    #[command(
+        about = "Print Prometheus alerting rules for the health thresholds a node runs with"
+    )]
+    AlertRules(AlertRulesArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum StateCommand {
+    #[command(
+        about = "Replay the chain in the data directory and write its whole state to a file, \
+                 the node must not be running"
+    )]
+    Export(ExportArgs),
+    #[command(
+        about = "Write an exported state as the genesis of the data directory, the new chain \
+                 starts from it"
+    )]
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum SnapshotCommand {
+    #[command(
+        about = "Replay the chain in the data directory and write the resulting accounts to a file, \
+                 the node must not be running"
+    )]
+    Export(ExportArgs),
+    #[command(
+        about = "List the accounts whose balance or sequence number differ between two snapshots"
+    )]
+    Diff(DiffArgs),
+    #[command(
+        about = "Replay the chain in the data directory and write its state at the head to a \
+                 compressed archive new nodes can start from, the node must not be running"
+    )]
+    Archive(ExportArgs),
+    #[command(
+        about = "Start a new data directory from a state archive instead of replaying the chain \
+                 from the genesis, run init with the chain's genesis first"
+    )]
+    Restore(RestoreArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum DevchainCommand {
+    #[command(about = "Write a reproducible chain of random transfers to the data directory")]
+    Generate(GenerateArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum AccountCommand {
+    #[command(about = "Credit an account in the genesis of a chain that hasn't started yet")]
+    Fund(FundArgs),
+}
+
+#[derive(Debug, Args)]
+struct DataDirArgs {
+    #[arg(long, default_value = ".fastpay")]
+    datadir: PathBuf,
+}
+
+impl DataDirArgs {
+    fn genesis_path(&self) -> PathBuf {
+        self.datadir.join(GENESIS_FILE)
+    }
+
+    fn blocks_path(&self) -> PathBuf {
+        self.datadir.join(BLOCKS_DIR)
+    }
+
+    fn state_archive_path(&self) -> PathBuf {
+        self.datadir.join(STATE_ARCHIVE_FILE)
+    }
+
+    fn auth_secret_path(&self) -> PathBuf {
+        self.datadir.join(AUTH_SECRET_FILE)
+    }
+}
+
+#[derive(Debug, Args)]
+struct InitArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    #[arg(
+        long = "alloc",
+        value_parser = parse_alloc::<U256>,
+        help = "Initial balance, as <address>=<balance>"
+    )]
+    allocs: Vec<(Address, U256)>,
+    #[arg(
+        long,
+        default_value_t = U256::ZERO,
+        help = "Smallest balance a transfer can leave an account with, 0 for no minimum"
+    )]
+    min_balance: U256,
+    #[arg(
+        long,
+        help = "Send the dust a transfer would leave to its sender along to the recipient \
+                instead of rejecting the transfer"
+    )]
+    sweep_dust: bool,
+    #[arg(long, default_value_t = 0, help = "Smallest fee a transfer can pay")]
+    base_fee: u64,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Fee a transfer pays on top of the base fee for every byte it takes, signatures \
+                included"
+    )]
+    fee_per_byte: u64,
+    #[arg(
+        long,
+        help = "Chain id transactions have to be signed for, 1337 when not set"
+    )]
+    chain_id: Option<u64>,
+    #[arg(
+        long,
+        help = "Address allowed to mint and burn. Without one, the supply is what the allocs hold"
+    )]
+    issuer: Option<Address>,
+    #[arg(
+        long,
+        help = "Address allowed to credit deposits made to the bridge contract on the primary chain"
+    )]
+    bridge_operator: Option<Address>,
+    #[arg(
+        long = "block-producer",
+        help = "Address allowed to sign blocks, repeatable. Without any, blocks aren't signed"
+    )]
+    block_producers: Vec<Address>,
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "How many of the block producers have to sign every block"
+    )]
+    producer_threshold: usize,
+    #[arg(
+        long = "authority",
+        value_parser = parse_alloc::<u64>,
+        help = "Committee member and its stake, as <address>=<stake>, repeatable. With any, the \
+                committee produces blocks in turns"
+    )]
+    authorities: Vec<(Address, u64)>,
+    #[arg(
+        long,
+        default_value = "round-robin",
+        help = "How blocks are handed out to the committee, round-robin or stake-weighted"
+    )]
+    rotation: Rotation,
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Seconds a scheduled producer has before the next one in line may take over"
+    )]
+    producer_timeout: u64,
+    #[arg(long, help = "Replace an existing genesis")]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    #[arg(long, default_value = "127.0.0.1:8545")]
+    rpc_addr: SocketAddr,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_values_t = Namespace::DEFAULT,
+        help = "Namespaces served over JSON-RPC, among eth, fastpay, txpool, admin and debug"
+    )]
+    rpc_namespaces: Vec<Namespace>,
+    #[arg(
+        long,
+        help = "Serve the admin, debug and personal namespaces on this address, to callers with \
+                the secret in the datadir's auth-secret file or a JWT signed with it. Without it \
+                they aren't served"
+    )]
+    auth_rpc_addr: Option<SocketAddr>,
+    #[arg(
+        long = "rpc-cors-origin",
+        help = "A web origin browsers may call the RPC address from, * for any, can be repeated"
+    )]
+    rpc_cors_origins: Vec<String>,
+    #[arg(
+        long,
+        help = "Serve the REST gateway for explorers and merchants on this address"
+    )]
+    api_addr: Option<SocketAddr>,
+    #[cfg(feature = "grpc")]
+    #[arg(long, help = "Serve the gRPC interface on this address too")]
+    grpc_addr: Option<SocketAddr>,
+    #[arg(long, default_value_t = 1000, help = "Milliseconds between blocks")]
+    block_time: u64,
+    #[arg(long, default_value_t = 1000)]
+    max_block_txs: usize,
+    #[arg(
+        long,
+        default_value_t = BlockLimits::default().gas_limit,
+        help = "Gas the transactions of a block may use, see block_builder::limits"
+    )]
+    block_gas_limit: u64,
+    #[arg(
+        long,
+        default_value_t = BlockLimits::default().max_bytes,
+        help = "Bytes the encoded transactions of a block may take"
+    )]
+    max_block_bytes: usize,
+    #[arg(long, default_value_t = Address::ZERO)]
+    miner: Address,
+    #[arg(
+        long,
+        help = "Private key used to sign blocks and preconfirmations, preconfirmations are \
+                disabled without one"
+    )]
+    producer_key: Option<String>,
+    #[arg(
+        long,
+        help = "Private key of the netting authority, intents are not collected without one"
+    )]
+    netting_key: Option<String>,
+    #[arg(long, default_value_t = 10, help = "Blocks in a netting window")]
+    netting_window: u64,
+    #[arg(
+        long = "peer",
+        help = "JSON-RPC url of a node to sync from, can be repeated. A node with peers follows \
+                their chain instead of producing blocks, unless the chain has a producer \
+                schedule"
+    )]
+    peers: Vec<String>,
+    #[arg(
+        long,
+        default_value_t = MempoolConfig::default().max_txs,
+        help = "Transactions the mempool holds before it refuses new ones"
+    )]
+    mempool_max_txs: usize,
+    #[arg(
+        long,
+        default_value_t = MempoolConfig::default().max_queued_per_sender,
+        help = "Transfer orders a sender can have waiting behind a sequence gap"
+    )]
+    mempool_max_queued_per_sender: usize,
+    #[arg(
+        long,
+        default_value_t = 100_000,
+        help = "Transactions whose recovered signers are kept, so the mempool and the vm don't \
+                recover them twice"
+    )]
+    signature_cache_size: usize,
+    #[arg(
+        long,
+        help = "Serve the personal namespace on --auth-rpc-addr, the node holds keys and signs \
+                with them. For development only, the keys are lost when the node stops"
+    )]
+    personal: bool,
+    #[arg(
+        long,
+        default_value_t = rpc::personal::DEFAULT_MAX_UNLOCK_DURATION.as_secs(),
+        help = "Longest a personal key can be unlocked for, in seconds"
+    )]
+    max_unlock_secs: u64,
+    #[arg(
+        long,
+        help = "Check the total supply against every account and channel after each block, and \
+                stop if they don't add up. For testing, it walks the whole state every block"
+    )]
+    check_supply: bool,
+    #[arg(
+        long,
+        help = "Prune the txs and receipts of blocks more than this many below the head, at least \
+                64 are kept. The state is checkpointed in the data directory so a restart doesn't \
+                replay the pruned blocks"
+    )]
+    keep_last_blocks: Option<u64>,
+    #[command(flatten)]
+    health: HealthArgs,
+    #[command(flatten)]
+    rpc_limits: RpcLimitArgs,
+}
+
+#[derive(Debug, Args)]
+struct RpcLimitArgs {
+    #[arg(
+        long,
+        default_value_t = RpcLimits::default().max_batch_size,
+        help = "Calls in one JSON-RPC batch, 0 turns batches off"
+    )]
+    rpc_max_batch_size: u32,
+    #[arg(long, default_value_t = RpcLimits::default().max_request_body_size)]
+    rpc_max_request_size: u32,
+    #[arg(long, default_value_t = RpcLimits::default().max_response_body_size)]
+    rpc_max_response_size: u32,
+    #[arg(
+        long,
+        default_value_t = 30_000,
+        help = "Milliseconds the calls reading many blocks may take, 0 for no limit"
+    )]
+    rpc_timeout: u64,
+    #[arg(
+        long = "rpc-method-timeout",
+        value_parser = rpc::limits::parse_method_timeout,
+        help = "A timeout for one of the calls reading many blocks, as method=milliseconds, can be \
+                repeated"
+    )]
+    rpc_method_timeouts: Vec<(String, Duration)>,
+    #[arg(
+        long,
+        help = "Requests a second the RPC address takes from everyone together"
+    )]
+    rpc_rate_limit: Option<u32>,
+    #[arg(
+        long,
+        help = "Requests a second the RPC address takes from each client, told apart by their \
+                IP address, or the X-Forwarded-For of a trusted proxy"
+    )]
+    rpc_client_rate_limit: Option<u32>,
+    #[arg(
+        long = "rpc-trusted-proxy",
+        help = "A proxy whose X-Forwarded-For and X-Real-IP say who the client is, can be repeated"
+    )]
+    rpc_trusted_proxies: Vec<IpAddr>,
+}
+
+impl RpcLimitArgs {
+    fn limits(&self) -> RpcLimits {
+        let default = (self.rpc_timeout > 0).then(|| Duration::from_millis(self.rpc_timeout));
+        let timeouts = self.rpc_method_timeouts.iter().fold(
+            MethodTimeouts::new(default),
+            |timeouts, (method, timeout)| timeouts.with_timeout(method.clone(), *timeout),
+        );
+        RpcLimits {
+            max_batch_size: self.rpc_max_batch_size,
+            max_request_body_size: self.rpc_max_request_size,
+            max_response_body_size: self.rpc_max_response_size,
+            timeouts,
+        }
+    }
+
+    fn rate_limits(&self) -> RateLimits {
+        RateLimits {
+            global: self.rpc_rate_limit.map(RateLimit::per_second),
+            per_client: self.rpc_client_rate_limit.map(RateLimit::per_second),
+            trusted_proxies: self.rpc_trusted_proxies.clone(),
+        }
+    }
+}
+
+// reported by admin_healthThresholds, alert-rules takes the same ones
+#[derive(Debug, Args)]
+struct HealthArgs {
+    #[arg(
+        long,
+        default_value_t = HealthThresholds::default().max_block_lag,
+        help = "Blocks the node may fall behind its peers before it counts as unhealthy"
+    )]
+    max_block_lag: u64,
+    #[arg(
+        long,
+        default_value_t = HealthThresholds::default().max_mempool_saturation,
+        value_parser = clap::value_parser!(u64).range(0..=100),
+        help = "How full the mempool may get, in percent of its capacity"
+    )]
+    max_mempool_saturation: u64,
+    #[arg(
+        long,
+        default_value_t = HealthThresholds::default().min_peers,
+        help = "Peers the node needs, 0 for a node running on its own"
+    )]
+    min_peers: u64,
+}
+
+impl HealthArgs {
+    fn thresholds(&self) -> HealthThresholds {
+        HealthThresholds {
+            max_block_lag: self.max_block_lag,
+            max_mempool_saturation: self.max_mempool_saturation,
+            min_peers: self.min_peers,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct AlertRulesArgs {
+    #[command(flatten)]
+    health: HealthArgs,
+    #[arg(long, help = "Write the rules to this file instead of stdout")]
+    out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    #[arg(long)]
+    blocks: u64,
+    #[arg(long, default_value_t = 10, help = "Average transfers per second")]
+    tps: u64,
+    #[arg(long, default_value_t = 100)]
+    accounts: usize,
+    #[arg(long, default_value_t = 1, help = "Seconds between blocks")]
+    block_time: u64,
+    #[arg(long, default_value_t = 0, help = "Same seed, same chain")]
+    seed: u64,
+    #[arg(long, help = "Replace an existing chain")]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct ExportArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    #[arg(long)]
+    out: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct RestoreArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ReplayArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+}
+
+#[derive(Debug, Args)]
+struct ImportArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    file: PathBuf,
+    #[arg(long, help = "Replace an existing genesis")]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct DiffArgs {
+    before: PathBuf,
+    after: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct FundArgs {
+    #[command(flatten)]
+    datadir: DataDirArgs,
+    #[arg(long)]
+    address: Address,
+    #[arg(long)]
+    amount: U256,
+}
+
+fn parse_alloc<T: FromStr>(alloc: &str) -> Result<(Address, T), String>
+where
+    T::Err: std::fmt::Display,
+{
+    let (address, balance) = alloc
+        .split_once('=')
+        .ok_or_else(|| format!("expected <address>=<balance>, got {}", alloc))?;
+    let address = address
+        .parse::<Address>()
+        .map_err(|e| format!("invalid address {}: {}", address, e))?;
+    let balance = balance
+        .parse::<T>()
+        .map_err(|e| format!("invalid balance {}: {}", balance, e))?;
+    Ok((address, balance))
+}
+
+fn load_genesis(path: &Path) -> anyhow::Result<Genesis> {
+    if !path.exists() {
+        anyhow::bail!(
+            "no genesis at {}, run `fastpay-node init` first",
+            path.display()
+        );
+    }
+    Genesis::load(path)
+}
+
+fn init(args: InitArgs) -> anyhow::Result<()> {
+    let path = args.datadir.genesis_path();
+    if path.exists() && !args.force {
+        anyhow::bail!(
+            "genesis already exists at {}, pass --force to replace it",
+            path.display()
+        );
+    }
+
+    let mut genesis = Genesis {
+        min_balance: args.min_balance,
+        sweep_dust: args.sweep_dust,
+        base_fee: args.base_fee,
+        fee_per_byte: args.fee_per_byte,
+        chain_id: args.chain_id,
+        issuer: args.issuer,
+        bridge_operator: args.bridge_operator,
+        producers: (!args.block_producers.is_empty()).then(|| GenesisProducers {
+            producers: args.block_producers.clone(),
+            threshold: args.producer_threshold,
+        }),
+        schedule: (!args.authorities.is_empty()).then(|| GenesisSchedule {
+            committee: args
+                .authorities
+                .iter()
+                .map(|&(address, stake)| GenesisAuthority { address, stake })
+                .collect(),
+            rotation: args.rotation,
+            timeout: args.producer_timeout,
+        }),
+        ..Genesis::default()
+    };
+    genesis.producer_set()?;
+    genesis.producer_schedule()?;
+    for (address, balance) in args.allocs {
+        genesis.fund(address, balance);
+    }
+
+    std::fs::create_dir_all(&args.datadir.datadir)?;
+    genesis.save(&path)?;
+    println!(
+        "wrote genesis with {} accounts to {}",
+        genesis.accounts.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn fund(args: FundArgs) -> anyhow::Result<()> {
+    let path = args.datadir.genesis_path();
+    let mut genesis = load_genesis(&path)?;
+    genesis.fund(args.address, args.amount);
+    genesis.save(&path)?;
+    println!("funded {} with {}", args.address, args.amount);
+    Ok(())
+}
+
+fn generate_devchain(args: GenerateArgs) -> anyhow::Result<()> {
+    let genesis_path = args.datadir.genesis_path();
+    let blocks_path = args.datadir.blocks_path();
+    if (genesis_path.exists() || blocks_path.exists()) && !args.force {
+        anyhow::bail!(
+            "a chain already exists in {}, pass --force to replace it",
+            args.datadir.datadir.display()
+        );
+    }
+
+    let devchain = devchain::generate(devchain::DevchainConfig {
+        blocks: args.blocks,
+        tps: args.tps,
+        accounts: args.accounts,
+        block_time: args.block_time,
+        seed: args.seed,
+    })?;
+
+    std::fs::create_dir_all(&args.datadir.datadir)?;
+    if blocks_path.exists() {
+        std::fs::remove_dir_all(&blocks_path)?;
+    }
+    devchain.genesis.save(&genesis_path)?;
+    let store = SledBlockStore::open(&blocks_path)?;
+    let mut transactions = 0;
+    for block in &devchain.blocks {
+        store.put(block)?;
+        transactions += block.transactions.len();
+    }
+
+    println!(
+        "wrote {} blocks with {} transactions to {}",
+        devchain.blocks.len(),
+        transactions,
+        args.datadir.datadir.display()
+    );
+    Ok(())
+}
+
+async fn export_snapshot(args: ExportArgs) -> anyhow::Result<()> {
+    let genesis = load_genesis(&args.datadir.genesis_path())?;
+    let (mut node, first) = start_node(&args.datadir, &genesis, Box::new(MemoryState::new()))?;
+
+    let block_builder =
+        BlockBuilder::with_store(SledBlockStore::open(args.datadir.blocks_path())?)?;
+    let blocks = replay_blocks(&mut node, &block_builder, first).await?;
+
+    let snapshot = Snapshot::capture(node.state(), blocks.checked_sub(1));
+    snapshot.save(&args.out)?;
+    println!(
+        "wrote {} accounts after {} blocks to {}",
+        snapshot.accounts.len(),
+        blocks,
+        args.out.display()
+    );
+    Ok(())
+}
+
+async fn export_state(args: ExportArgs) -> anyhow::Result<()> {
+    let genesis = load_genesis(&args.datadir.genesis_path())?;
+    let (mut node, first) = start_node(&args.datadir, &genesis, Box::new(MemoryState::new()))?;
+
+    let block_builder =
+        BlockBuilder::with_store(SledBlockStore::open(args.datadir.blocks_path())?)?;
+    let blocks = replay_blocks(&mut node, &block_builder, first).await?;
+
+    let export = StateExport::capture(node.state(), blocks.checked_sub(1), &genesis);
+    export.save(&args.out)?;
+    println!(
+        "wrote {} accounts and {} channels after {} blocks to {}",
+        export.genesis.accounts.len(),
+        export.genesis.channels.len(),
+        blocks,
+        args.out.display()
+    );
+    Ok(())
+}
+
+async fn archive_state(args: ExportArgs) -> anyhow::Result<()> {
+    let genesis = load_genesis(&args.datadir.genesis_path())?;
+    let (mut node, first) = start_node(&args.datadir, &genesis, Box::new(MemoryState::new()))?;
+
+    let block_builder =
+        BlockBuilder::with_store(SledBlockStore::open(args.datadir.blocks_path())?)?;
+    let blocks = replay_blocks(&mut node, &block_builder, first).await?;
+    let head = blocks
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("the chain has no blocks to archive the state at"))?;
+
+    let archive = node
+        .export_snapshot(&block_builder, head, &args.out)
+        .await?;
+    println!(
+        "wrote {} accounts at block {} ({}) to {}",
+        archive.accounts.len(),
+        head,
+        archive.header.hash,
+        args.out.display()
+    );
+    Ok(())
+}
+
+// the archive's block becomes the first one in the data directory's store, the node follows the
+// chain from there
+fn restore_state(args: RestoreArgs) -> anyhow::Result<()> {
+    // the rules of the chain still come from its genesis
+    load_genesis(&args.datadir.genesis_path())?;
+    if args.datadir.blocks_path().exists() {
+        anyhow::bail!(
+            "{} already has blocks, restore into a new data directory",
+            args.datadir.datadir.display()
+        );
+    }
+
+    let archive = StateArchive::load(&args.file)?;
+    archive.verify()?;
+    std::fs::copy(&args.file, args.datadir.state_archive_path())?;
+    SledBlockStore::open(args.datadir.blocks_path())?.put(&archive.header)?;
+    println!(
+        "restored {} accounts at block {} ({}) into {}",
+        archive.accounts.len(),
+        archive.block_number(),
+        archive.header.hash,
+        args.datadir.datadir.display()
+    );
+    Ok(())
+}
+
+async fn replay(args: ReplayArgs) -> anyhow::Result<()> {
+    if args.datadir.state_archive_path().exists() {
+        anyhow::bail!(
+            "{} starts from a state archive, it has no chain from the genesis to replay",
+            args.datadir.datadir.display()
+        );
+    }
+    let genesis = load_genesis(&args.datadir.genesis_path())?;
+    let mut state = MemoryState::new();
+    genesis.apply(&mut state)?;
+    let mut node = Node::from_genesis(Box::new(state), &genesis);
+
+    let block_builder =
+        BlockBuilder::with_store(SledBlockStore::open(args.datadir.blocks_path())?)?;
+    let report = replay::verify_chain(&mut node, &block_builder).await?;
+    println!(
+        "replayed {} blocks with {} transactions, {} of them with stored receipts",
+        report.blocks, report.transactions, report.blocks_with_receipts
+    );
+    Ok(())
+}
+
+// the blocks of the exported chain stay behind, the data directory must not have any
+fn import_state(args: ImportArgs) -> anyhow::Result<()> {
+    let path = args.datadir.genesis_path();
+    if path.exists() && !args.force {
+        anyhow::bail!(
+            "genesis already exists at {}, pass --force to replace it",
+            path.display()
+        );
+    }
+    if args.datadir.blocks_path().exists() {
+        anyhow::bail!(
+            "{} already has blocks, import into a new data directory",
+            args.datadir.datadir.display()
+        );
+    }
+
+    let export = StateExport::load(&args.file)?;
+    export.verify()?;
+    std::fs::create_dir_all(&args.datadir.datadir)?;
+    export.genesis.save(&path)?;
+    println!(
+        "wrote genesis with state root {} to {}",
+        export.state_root,
+        path.display()
+    );
+    Ok(())
+}
+
+// prints every differing account and fails if there are any, so scripts can check an upgrade
+fn diff_snapshots(args: DiffArgs) -> anyhow::Result<()> {
+    let before = Snapshot::load(&args.before)?;
+    let after = Snapshot::load(&args.after)?;
+    if before.block != after.block {
+        println!(
+            "snapshots are at different blocks: {:?} and {:?}",
+            before.block, after.block
+        );
+    }
+
+    let diff = before.diff(&after);
+    for account in &diff {
+        let describe = |account: Option<node::snapshot::SnapshotAccount>| match account {
+            Some(account) => format!("balance {} sequence {}", account.balance, account.sequence),
+            None => "missing".to_string(),
+        };
+        println!(
+            "{}: {} -> {}",
+            account.address,
+            describe(account.before),
+            describe(account.after)
+        );
+    }
+
+    if !diff.is_empty() {
+        anyhow::bail!("{} accounts differ", diff.len());
+    }
+    // both list every account, so the roots only differ if the channels do
+    if before.state_root != after.state_root {
+        anyhow::bail!(
+            "state roots differ: {} and {}",
+            before.state_root,
+            after.state_root
+        );
+    }
+    println!("snapshots match, state root {}", before.state_root);
+    Ok(())
+}
+
+// executes what's waiting in the mempool and seals the successful transfers in a block, what
+// doesn't fit within `limits` goes back to the mempool for the next one
+async fn produce_block(
+    node: &NodeHandle,
+    mempool: &Mempool,
+    limits: &BlockLimits,
+    miner: Address,
+) -> anyhow::Result<()> {
+    let (txs, leftovers) = limits.fill(mempool.take_batch(limits.max_txs).await);
+    mempool.requeue(leftovers).await;
+    let block = node.produce_block(txs, miner).await?;
+    println!(
+        "produced block {} with {} transactions",
+        block.number,
+        block.transactions.len()
+    );
+    Ok(())
+}
+
+// queues the settlement of the window that just ended, it lands in the next block
+async fn close_netting_window(netting: &NettingEngine, mempool: &Mempool) -> anyhow::Result<()> {
+    let window = netting.window().await;
+    let Some(settlement) = netting
+        .close_window()
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to settle netting window {}: {:?}", window, e))?
+    else {
+        return Ok(());
+    };
+
+    mempool
+        .add_tx(settlement)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to queue settlement: {:?}", e))?;
+    println!("settling netting window {}", window);
+    Ok(())
+}
+
+// the node a data directory starts from, on top of the genesis or of the state archive it was
+// restored from. Also returns the number of the first block to replay on top
+fn start_node(
+    datadir: &DataDirArgs,
+    genesis: &Genesis,
+    mut state: Box<dyn State>,
+) -> anyhow::Result<(Node, u64)> {
+    let path = datadir.state_archive_path();
+    if !path.exists() {
+        genesis.apply(state.as_mut())?;
+        return Ok((Node::from_genesis(state, genesis), 0));
+    }
+    let mut node = Node::from_genesis(state, genesis);
+    let archive = node.import_snapshot(&path)?;
+    Ok((node, archive.block_number() + 1))
+}
+
+// blocks survive a restart but the state doesn't, it is rebuilt by executing them again on top
+// of the genesis, or from block `from` on top of a state archive
+async fn replay_blocks(
+    node: &mut Node,
+    block_builder: &BlockBuilder,
+    from: u64,
+) -> anyhow::Result<u64> {
+    let latest: u64 = block_builder
+        .get_latest_block_number()
+        .await
+        .saturating_to();
+
+    for number in from..latest {
+        let block = block_builder
+            .get_block(U256::from(number))
+            .await
+            .ok_or_else(|| anyhow::anyhow!("block {} is missing from the store", number))?;
+        // only blocks below the state archive are pruned, it has to be gone
+        if block.pruned {
+            anyhow::bail!("block {} was pruned, it can't be replayed", number);
+        }
+
+        node.set_current_block(number);
+        let mut logs = Vec::new();
+        for result in node.execute_batch(&block.transactions) {
+            match result {
+                Ok(receipt) => logs.push(receipt.logs().to_vec()),
+                Err(error) => {
+                    anyhow::bail!("block {} doesn't replay: {}", number, error.reason())
+                }
+            }
+        }
+        // blocks sealed before state roots were recorded carry a zero one
+        if block.state_root != B256::ZERO && block.state_root != node.state().state_root() {
+            anyhow::bail!("block {} doesn't replay to its state root", number);
+        }
+        // stored as the block's receipts, blocks from before receipts were stored get them here
+        block_builder.record_logs(block.hash, logs).await;
+    }
+    Ok(latest)
+}
+
+async fn run(args: RunArgs) -> anyhow::Result<()> {
+    let genesis = load_genesis(&args.datadir.genesis_path())?;
+
+    // the node executes against the state while the rpc reads balances from it
+    let state = Arc::new(ShardedState::in_memory(STATE_SHARDS));
+    let (node, first) = start_node(&args.datadir, &genesis, Box::new(state.clone()))?;
+    let signature_cache = Arc::new(SignatureCache::new(args.signature_cache_size));
+    let mut node = node.with_signature_cache(signature_cache.clone());
+    if args.check_supply {
+        node = node.with_supply_check();
+    }
+    // the mempool on top of the state, refreshed every tick for "pending" queries
+    let pending = PendingState::new(state.clone());
+
+    let producer = args
+        .producer_key
+        .as_deref()
+        .map(PrivateKeySigner::from_str)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("invalid producer key: {}", e))?;
+    let limits = BlockLimits {
+        gas_limit: args.block_gas_limit,
+        max_txs: args.max_block_txs,
+        max_bytes: args.max_block_bytes,
+    };
+    let mut block_builder =
+        BlockBuilder::with_store(SledBlockStore::open(args.datadir.blocks_path())?)?
+            .with_limits(limits);
+    if let Some(producers) = genesis.producer_set()? {
+        if !producer
+            .as_ref()
+            .is_some_and(|signer| producers.contains(&signer.address()))
+        {
+            eprintln!("the producer key isn't one of the chain's block producers");
+        }
+        block_builder = block_builder.with_producers(producers);
+    }
+    if let Some(schedule) = genesis.producer_schedule()? {
+        if !producer
+            .as_ref()
+            .is_some_and(|signer| schedule.contains(&signer.address()))
+        {
+            eprintln!("the producer key isn't scheduled to produce blocks, only following");
+        }
+        block_builder = block_builder.with_schedule(schedule);
+    }
+    if let Some(signer) = &producer {
+        block_builder = block_builder.with_signer(signer.clone());
+    }
+    let producer_address = producer.as_ref().map(|signer| signer.address());
+    let replayed = replay_blocks(&mut node, &block_builder, first).await? - first;
+    if replayed > 0 {
+        println!("replayed {} blocks", replayed);
+    }
+
+    let mempool = Mempool::with_config(MempoolConfig {
+        max_txs: args.mempool_max_txs,
+        max_queued_per_sender: args.mempool_max_queued_per_sender,
+        fee_schedule: genesis.fee_schedule(),
+    })
+    .with_sequences(state.clone())
+    .with_signature_cache(signature_cache.clone());
+    let preconfirmer = match producer {
+        Some(signer) => {
+            println!(
+                "signing blocks and preconfirmations as {}",
+                signer.address()
+            );
+            Some(Preconfirmer::new(signer, args.max_block_txs))
+        }
+        None => None,
+    };
+    let netting = match &args.netting_key {
+        Some(key) => {
+            let signer = PrivateKeySigner::from_str(key)
+                .map_err(|e| anyhow::anyhow!("invalid netting key: {}", e))?;
+            println!("netting intents as {}", signer.address());
+            Some(NettingEngine::new(signer))
+        }
+        None => None,
+    };
+    let peers = args
+        .peers
+        .iter()
+        .map(RpcPeer::new)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let sync_status = SyncStatus::new();
+    let syncer = Syncer::new(peers, sync_status.clone());
+    let auth = match args.auth_rpc_addr {
+        Some(addr) => Some(AuthConfig {
+            addr,
+            secret: AuthSecret::load_or_create(args.datadir.auth_secret_path())?,
+        }),
+        None => None,
+    };
+    if let Some(auth) = &auth {
+        println!("authenticated rpc listening on {}", auth.addr);
+    }
+    let config = RpcConfig {
+        addr: args.rpc_addr,
+        preconfirmer,
+        netting: netting.clone(),
+        certificates: node.certificates().clone(),
+        sync: sync_status,
+        chain_id: genesis.chain_id(),
+        keys: args
+            .personal
+            .then(|| KeyManager::new(Duration::from_secs(args.max_unlock_secs))),
+        pending: Some(pending.clone()),
+        block_capacity: args.max_block_txs,
+        health: args.health.thresholds(),
+        limits: args.rpc_limits.limits(),
+        namespaces: args.rpc_namespaces.clone(),
+        auth,
+        cors_origins: args.rpc_cors_origins.clone(),
+        rate_limits: args.rpc_limits.rate_limits(),
+        simulator: Some(node.simulator(pending.clone())),
+        scheduled_payments: Some(node.events().scheduled_payment_channel()),
+        ..RpcConfig::default()
+    };
+    // from here on the node runs on a task of its own, block production and syncing message it
+    let node = NodeHandle::spawn(node, block_builder.clone());
+    if let Some(keep_last_blocks) = args.keep_last_blocks {
+        prune::spawn(
+            node.clone(),
+            block_builder.clone(),
+            PruneConfig::new(keep_last_blocks),
+            args.datadir.state_archive_path(),
+        );
+    }
+
+    if let Some(addr) = args.api_addr {
+        let rpc = rpc::EthRpcImpl::from_config(
+            &config,
+            block_builder.clone(),
+            mempool.clone(),
+            state.clone(),
+        );
+        println!("api listening on {}", addr);
+        tokio::spawn(async move {
+            if let Err(e) = api::start_api_server(addr, rpc).await {
+                eprintln!("api server failed: {}", e);
+            }
+        });
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = args.grpc_addr {
+        let rpc = rpc::EthRpcImpl::from_config(
+            &config,
+            block_builder.clone(),
+            mempool.clone(),
+            state.clone(),
+        );
+        println!("grpc listening on {}", addr);
+        tokio::spawn(async move {
+            if let Err(e) = grpc::start_grpc_server(addr, rpc).await {
+                eprintln!("grpc server failed: {}", e);
+            }
+        });
+    }
+    println!("rpc listening on {}", args.rpc_addr);
+    // spawned so eth_syncing answers while a long sync holds up the loop below
+    let mut server = tokio::spawn(rpc::start_rpc_server(
+        config,
+        block_builder.clone(),
+        mempool.clone(),
+        state,
+    ));
+
+    let netting_window = args.netting_window.max(1);
+    let mut produced = 0u64;
+    let mut ticker = tokio::time::interval(Duration::from_millis(args.block_time));
+    loop {
+        tokio::select! {
+            result = &mut server => return result?,
+            _ = ticker.tick() => {
+                if syncer.has_peers() {
+                    match syncer.sync(&node, &block_builder).await {
+                        Ok(0) => {}
+                        Ok(imported) => println!("imported {} blocks from peers", imported),
+                        Err(e) => eprintln!("sync failed: {}", e),
+                    }
+                }
+                // with a schedule every producer follows the others and takes its own turns
+                let due = match (block_builder.schedule(), &producer_address) {
+                    (Some(_), Some(address)) => block_builder.is_due(address).await?,
+                    (Some(_), None) => false,
+                    (None, _) => !syncer.has_peers(),
+                };
+                if due {
+                    produce_block(&node, &mempool, &limits, args.miner)
+                        .await?;
+                    produced += 1;
+                    if let (Some(netting), 0) = (&netting, produced % netting_window) {
+                        close_netting_window(netting, &mempool).await?;
+                    }
+                }
+                node.execute_pending(&pending, mempool.pending().await).await;
+            }
+            _ = tokio::signal::ctrl_c() => {
+                println!("shutting down");
+                println!(
+                    "signature cache hit rate {:.1}% ({} hits, {} misses)",
+                    signature_cache.hit_rate() * 100.0,
+                    signature_cache.hits(),
+                    signature_cache.misses()
+                );
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn alert_rules(args: AlertRulesArgs) -> anyhow::Result<()> {
+    let rules = args.health.thresholds().alerting_rules();
+    match args.out {
+        Some(path) => std::fs::write(path, rules)?,
+        None => print!("{}", rules),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    init_tracing(cli.log_format);
+    match cli.command {
+        Command::Init(args) => init(args),
+        Command::Run(args) => run(*args).await,
+        Command::Account(AccountCommand::Fund(args)) => fund(args),
+        Command::Devchain(DevchainCommand::Generate(args)) => generate_devchain(args),
+        Command::Snapshot(SnapshotCommand::Export(args)) => export_snapshot(args).await,
+        Command::Snapshot(SnapshotCommand::Diff(args)) => diff_snapshots(args),
+        Command::Snapshot(SnapshotCommand::Archive(args)) => archive_state(args).await,
+        Command::Snapshot(SnapshotCommand::Restore(args)) => restore_state(args),
+        Command::State(StateCommand::Export(args)) => export_state(args).await,
+        Command::State(StateCommand::Import(args)) => import_state(args),
+        Command::Replay(args) => replay(args).await,
+        Command::AlertRules(args) => alert_rules(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_alloc() {
+        let (address, balance) =
+            parse_alloc::<U256>("0x0101010101010101010101010101010101010101=100").unwrap();
+        assert_eq!(address, Address::repeat_byte(1));
+        assert_eq!(balance, U256::from(100));
+
+        assert!(parse_alloc::<U256>("0x01").is_err());
+        assert!(parse_alloc::<U256>("nope=100").is_err());
+        assert!(parse_alloc::<U256>("0x0101010101010101010101010101010101010101=-1").is_err());
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::try_parse_from([
+            "fastpay-node",
+            "account",
+            "fund",
+            "--datadir",
+            "/tmp/chain",
+            "--address",
+            "0x0101010101010101010101010101010101010101",
+            "--amount",
+            "5",
+        ])
+        .unwrap();
+        assert_eq!(cli.log_format, LogFormat::Pretty);
+
+        match cli.command {
+            Command::Account(AccountCommand::Fund(args)) => {
+                assert_eq!(args.datadir.datadir, PathBuf::from("/tmp/chain"));
+                assert_eq!(args.amount, U256::from(5));
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        let cli = Cli::try_parse_from([
+            "fastpay-node",
+            "devchain",
+            "generate",
+            "--blocks",
+            "100",
+            "--seed",
+            "3",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Devchain(DevchainCommand::Generate(args)) => {
+                assert_eq!(args.blocks, 100);
+                assert_eq!(args.seed, 3);
+                assert_eq!(args.accounts, 100);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        let cli =
+            Cli::try_parse_from(["fastpay-node", "run", "--personal", "--log-format", "json"])
+                .unwrap();
+        assert_eq!(cli.log_format, LogFormat::Json);
+        match cli.command {
+            Command::Run(args) => {
+                assert!(args.personal);
+                assert_eq!(args.max_unlock_secs, 3600);
+                assert_eq!(args.keep_last_blocks, None);
+                assert_eq!(args.rpc_namespaces, Namespace::DEFAULT.to_vec());
+                assert_eq!(args.health.thresholds(), HealthThresholds::default());
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        let cli = Cli::try_parse_from([
+            "fastpay-node",
+            "run",
+            "--rpc-max-batch-size",
+            "0",
+            "--rpc-method-timeout",
+            "eth_getLogs=500",
+            "--rpc-namespaces",
+            "eth,debug",
+            "--rpc-cors-origin",
+            "https://explorer.example",
+            "--rpc-client-rate-limit",
+            "20",
+            "--rpc-trusted-proxy",
+            "10.0.0.9",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Run(args) => {
+                assert_eq!(args.rpc_namespaces, vec![Namespace::Eth, Namespace::Debug]);
+                assert_eq!(args.rpc_cors_origins, vec!["https://explorer.example"]);
+                assert_eq!(
+                    args.rpc_limits.rate_limits(),
+                    RateLimits {
+                        global: None,
+                        per_client: Some(RateLimit::per_second(20)),
+                        trusted_proxies: vec![IpAddr::from([10, 0, 0, 9])],
+                    }
+                );
+                let limits = args.rpc_limits.limits();
+                assert_eq!(limits.max_batch_size, 0);
+                assert_eq!(
+                    limits.timeouts.get("eth_getLogs"),
+                    Some(Duration::from_millis(500))
+                );
+                assert_eq!(
+                    limits.timeouts.get("fastpay_getBlocks"),
+                    RpcLimits::default().timeouts.default
+                );
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+        assert!(Cli::try_parse_from([
+            "fastpay-node",
+            "run",
+            "--rpc-method-timeout",
+            "eth_chainId=500",
+        ])
+        .is_err());
+
+        let cli = Cli::try_parse_from([
+            "fastpay-node",
+            "alert-rules",
+            "--max-block-lag",
+            "20",
+            "--min-peers",
+            "2",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::AlertRules(args) => {
+                assert_eq!(args.health.max_block_lag, 20);
+                assert_eq!(args.health.max_mempool_saturation, 90);
+                assert_eq!(args.health.min_peers, 2);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+        assert!(Cli::try_parse_from([
+            "fastpay-node",
+            "alert-rules",
+            "--max-mempool-saturation",
+            "101"
+        ])
+        .is_err());
+
+        let cli = Cli::try_parse_from([
+            "fastpay-node",
+            "init",
+            "--block-producer",
+            "0x0101010101010101010101010101010101010101",
+            "--block-producer",
+            "0x0202020202020202020202020202020202020202",
+            "--producer-threshold",
+            "2",
+            "--authority",
+            "0x0101010101010101010101010101010101010101=3",
+            "--rotation",
+            "stake-weighted",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Init(args) => {
+                assert_eq!(args.block_producers.len(), 2);
+                assert_eq!(args.producer_threshold, 2);
+                assert_eq!(args.authorities, vec![(Address::repeat_byte(1), 3)]);
+                assert_eq!(args.rotation, Rotation::StakeWeighted);
+                assert_eq!(args.producer_timeout, 10);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_blocks() {
+        use alloy::signers::SignerSync;
+
+        let sender = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let genesis = Genesis::new(vec![node::genesis::GenesisAccount {
+            address: sender.address(),
+            balance: U256::from(100),
+            sequence: 0,
+            multisig: None,
+            module: None,
+            label: None,
+            created_at: 0,
+            policy: None,
+            spending: state::policy::DailySpending::default(),
+            schedules: Vec::new(),
+            escrows: Vec::new(),
+            htlcs: Vec::new(),
+            assets: std::collections::BTreeMap::new(),
+        }]);
+        let funded_node = || {
+            let mut state = MemoryState::new();
+            genesis.apply(&mut state).unwrap();
+            Node::new(Box::new(state))
+        };
+
+        let tx = tx::tx::Tx::new(sender.address(), to, U256::from(30), None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        let block_builder = BlockBuilder::new();
+        block_builder
+            .create_block(vec![tx.with_signature(signature)], Address::ZERO)
+            .await
+            .unwrap();
+
+        let mut node = funded_node();
+        assert_eq!(
+            replay_blocks(&mut node, &block_builder, 0).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            node.state().get_account(&to).unwrap().balance(),
+            U256::from(30)
+        );
+
+        // A chain that doesn't match the genesis is refused
+        let mut empty = Node::new(Box::new(MemoryState::new()));
+        assert!(replay_blocks(&mut empty, &block_builder, 0).await.is_err());
+
+        // So is one that ends up somewhere else than its blocks say
+        let tampered = BlockBuilder::new();
+        tampered
+            .create_block_with_state_root(Vec::new(), Address::ZERO, B256::repeat_byte(1))
+            .await
+            .unwrap();
+        assert!(replay_blocks(&mut funded_node(), &tampered, 0)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_and_diff_snapshots() {
+        let dir = std::env::temp_dir().join(format!("fastpay-snapshot-{}", std::process::id()));
+        generate_devchain(GenerateArgs {
+            datadir: DataDirArgs {
+                datadir: dir.clone(),
+            },
+            blocks: 3,
+            tps: 2,
+            accounts: 5,
+            block_time: 1,
+            seed: 1,
+            force: true,
+        })
+        .unwrap();
+
+        let first = dir.join("first.json");
+        let second = dir.join("second.json");
+        for out in [&first, &second] {
+            export_snapshot(ExportArgs {
+                datadir: DataDirArgs {
+                    datadir: dir.clone(),
+                },
+                out: out.clone(),
+            })
+            .await
+            .unwrap();
+        }
+        // The exports stored the receipts of the blocks they replayed, the check compares them
+        replay(ReplayArgs {
+            datadir: DataDirArgs {
+                datadir: dir.clone(),
+            },
+        })
+        .await
+        .unwrap();
+        let snapshot = Snapshot::load(&first).unwrap();
+        assert_eq!(snapshot.block, Some(2));
+        assert_eq!(snapshot.accounts.len(), 5);
+        diff_snapshots(DiffArgs {
+            before: first.clone(),
+            after: second.clone(),
+        })
+        .unwrap();
+
+        let mut changed = snapshot.clone();
+        changed.accounts[0].balance += U256::from(1);
+        changed.save(&second).unwrap();
+        assert!(diff_snapshots(DiffArgs {
+            before: first,
+            after: second,
+        })
+        .is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_state() {
+        let dir = std::env::temp_dir().join(format!("fastpay-state-{}", std::process::id()));
+        let datadir = |name: &str| DataDirArgs {
+            datadir: dir.join(name),
+        };
+        generate_devchain(GenerateArgs {
+            datadir: datadir("old"),
+            blocks: 3,
+            tps: 2,
+            accounts: 5,
+            block_time: 1,
+            seed: 1,
+            force: true,
+        })
+        .unwrap();
+
+        let file = dir.join("state.json");
+        export_state(ExportArgs {
+            datadir: datadir("old"),
+            out: file.clone(),
+        })
+        .await
+        .unwrap();
+        let export = StateExport::load(&file).unwrap();
+        assert_eq!(export.block, Some(2));
+
+        // The old chain's data directory has blocks, it can't take the import
+        let import = |name: &str| ImportArgs {
+            datadir: datadir(name),
+            file: file.clone(),
+            force: true,
+        };
+        assert!(import_state(import("old")).is_err());
+        import_state(import("new")).unwrap();
+        let genesis = load_genesis(&datadir("new").genesis_path()).unwrap();
+        let mut state = MemoryState::new();
+        genesis.apply(&mut state).unwrap();
+        assert_eq!(state::state::State::state_root(&state), export.state_root);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_restore_state() {
+        let dir = std::env::temp_dir().join(format!("fastpay-archive-{}", std::process::id()));
+        let datadir = |name: &str| DataDirArgs {
+            datadir: dir.join(name),
+        };
+        generate_devchain(GenerateArgs {
+            datadir: datadir("old"),
+            blocks: 3,
+            tps: 2,
+            accounts: 5,
+            block_time: 1,
+            seed: 1,
+            force: true,
+        })
+        .unwrap();
+        let file = dir.join("state-archive");
+        archive_state(ExportArgs {
+            datadir: datadir("old"),
+            out: file.clone(),
+        })
+        .await
+        .unwrap();
+
+        // The new node needs the chain's genesis first
+        let restore = |name: &str| RestoreArgs {
+            datadir: datadir(name),
+            file: file.clone(),
+        };
+        assert!(restore_state(restore("new")).is_err());
+        std::fs::create_dir_all(dir.join("new")).unwrap();
+        std::fs::copy(datadir("old").genesis_path(), datadir("new").genesis_path()).unwrap();
+        restore_state(restore("new")).unwrap();
+        assert!(restore_state(restore("new")).is_err());
+
+        // It starts at the old chain's state without the blocks that led there
+        for name in ["old", "new"] {
+            export_snapshot(ExportArgs {
+                datadir: datadir(name),
+                out: dir.join(format!("{}.json", name)),
+            })
+            .await
+            .unwrap();
+        }
+        let old = Snapshot::load(&dir.join("old.json")).unwrap();
+        let new = Snapshot::load(&dir.join("new.json")).unwrap();
+        assert_eq!(new.block, Some(2));
+        assert_eq!(new.state_root, old.state_root);
+        assert!(replay(ReplayArgs {
+            datadir: datadir("new"),
+        })
+        .await
+        .is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_close_netting_window() {
+        use alloy::signers::SignerSync;
+        use tx::netting::{Intent, SignedIntent};
+
+        let netting = NettingEngine::new(PrivateKeySigner::random());
+        let mempool = Mempool::new();
+
+        // Nothing is queued for an empty window
+        close_netting_window(&netting, &mempool).await.unwrap();
+        assert!(mempool.is_empty().await);
+
+        let signer = PrivateKeySigner::random();
+        let intent = Intent {
+            from: signer.address(),
+            to: Address::repeat_byte(1),
+            amount: 5,
+            window: 1,
+        };
+        netting
+            .submit(SignedIntent {
+                intent,
+                signature: signer.sign_message_sync(intent.hash().as_slice()).unwrap(),
+            })
+            .await
+            .unwrap();
+
+        close_netting_window(&netting, &mempool).await.unwrap();
+        assert_eq!(mempool.len().await, 1);
+        assert_eq!(netting.window().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_produce_block_skips_failed_transfers() {
+        let mempool = Mempool::new();
+        let block_builder = BlockBuilder::new();
+        let node = NodeHandle::spawn(
+            Node::new(Box::new(MemoryState::new())),
+            block_builder.clone(),
+        );
+
+        // unsigned, so the vm rejects it
+        mempool
+            .add_tx(tx::tx::Tx::new(
+                Address::ZERO,
+                Address::repeat_byte(1),
+                U256::from(1),
+                None,
+            ))
+            .await
+            .unwrap();
+
+        produce_block(&node, &mempool, &BlockLimits::default(), Address::ZERO)
+            .await
+            .unwrap();
+
+        let block = block_builder.get_latest_block().await.unwrap();
+        assert!(block.transactions.is_empty());
+        assert!(mempool.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_produce_signed_block() {
+        // The key a producer is started with signs its blocks
+        let producer = PrivateKeySigner::random();
+        let block_builder = BlockBuilder::new().with_signer(producer.clone());
+        let node = NodeHandle::spawn(
+            Node::new(Box::new(MemoryState::new())),
+            block_builder.clone(),
+        );
+
+        produce_block(
+            &node,
+            &Mempool::new(),
+            &BlockLimits::default(),
+            producer.address(),
+        )
+        .await
+        .unwrap();
+
+        let block = block_builder.get_latest_block().await.unwrap();
+        assert_eq!(block.signers(), vec![producer.address()]);
+    }
+}
@@ -0,0 +1,122 @@
+// what a node run with --keep-last-blocks does in the background: every so often it archives the
+// state at the head into the data directory, the checkpoint a restart replays from instead of the
+// genesis, then prunes the blocks below what the checkpoint and the retention keep. See
+// block_builder::prune for what is never pruned
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use alloy::primitives::U256;
+use block_builder::{prune::PruneConfig, BlockBuilder};
+use node::actor::NodeHandle;
+
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+
+// returns how many blocks it pruned
+pub async fn prune_once(
+    node: &NodeHandle,
+    block_builder: &BlockBuilder,
+    config: PruneConfig,
+    archive_path: &Path,
+) -> anyhow::Result<u64> {
+    let archive = node.capture_snapshot().await?;
+    // written aside and moved over the old one, a crash midway leaves the old checkpoint, whose
+    // blocks haven't been pruned yet
+    let written = archive_path.with_extension("tmp");
+    archive.save(&written)?;
+    std::fs::rename(&written, archive_path)?;
+
+    let checkpoint = archive.block_number();
+    let below = config.prune_below(checkpoint + 1, Some(checkpoint));
+    block_builder.prune(U256::from(below)).await
+}
+
+pub fn spawn(
+    node: NodeHandle,
+    block_builder: BlockBuilder,
+    config: PruneConfig,
+    archive_path: PathBuf,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PRUNE_INTERVAL);
+        // the first tick is right away, the node just replayed what it has
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            match prune_once(&node, &block_builder, config, &archive_path).await {
+                Ok(0) => {}
+                Ok(pruned) => println!("pruned {} blocks", pruned),
+                Err(e) => eprintln!("pruning failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use block_builder::prune::FINALITY_DEPTH;
+    use node::{archive::StateArchive, Node};
+    use state::{account::Account, memory::MemoryState, state::State};
+    use tx::tx::Tx;
+
+    #[tokio::test]
+    async fn test_prune_once() {
+        let sender = PrivateKeySigner::random();
+        let mut state = MemoryState::new();
+        state
+            .update_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(10_000)),
+            )
+            .unwrap();
+        let block_builder = BlockBuilder::new();
+        let node = NodeHandle::spawn(Node::new(Box::new(state)), block_builder.clone());
+        let path = std::env::temp_dir().join(format!("fastpay-prune-{}", std::process::id()));
+        let config = PruneConfig::new(10);
+
+        // Nothing to checkpoint yet
+        assert!(prune_once(&node, &block_builder, config, &path)
+            .await
+            .is_err());
+        assert!(!path.exists());
+
+        let blocks = FINALITY_DEPTH + 5;
+        for amount in 0..blocks {
+            let tx = Tx::new(
+                sender.address(),
+                Address::repeat_byte(1),
+                U256::from(amount + 1),
+                None,
+            );
+            let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+            node.produce_block(vec![tx.with_signature(signature)], Address::ZERO)
+                .await
+                .unwrap();
+        }
+        // The retention is shorter than the finality depth, which is kept instead
+        assert_eq!(
+            prune_once(&node, &block_builder, config, &path)
+                .await
+                .unwrap(),
+            5
+        );
+        let archive = StateArchive::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(archive.block_number(), blocks - 1);
+        let pruned = block_builder.get_block(U256::from(4)).await.unwrap();
+        assert!(pruned.pruned);
+        assert!(pruned.transactions.is_empty());
+        assert_eq!(
+            block_builder
+                .get_block(U256::from(5))
+                .await
+                .unwrap()
+                .transactions
+                .len(),
+            1
+        );
+    }
+}
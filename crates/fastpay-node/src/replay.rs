@@ -0,0 +1,188 @@
+// checking a chain by executing it again from its genesis on a fresh state: every block has to
+// lead to the state root and receipts root it claims, and to the receipts stored with it. Unlike
+// the replay a node does when it starts, nothing is written back, so the stored chain is what
+// gets checked. Run after moving to another state backend or changing the VM, a block that comes
+// out differently means the change doesn't reproduce the chain
+
+use alloy::primitives::{B256, U256};
+use block_builder::{merkle::receipts_root, receipts::block_receipts, BlockBuilder};
+use node::Node;
+use tx::log::logs_bloom;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub blocks: u64,
+    pub transactions: usize,
+    // blocks whose receipts were compared, the others have none stored
+    pub blocks_with_receipts: u64,
+}
+
+// stops at the first block that comes out differently, its error says how
+pub async fn verify_chain(
+    node: &mut Node,
+    block_builder: &BlockBuilder,
+) -> anyhow::Result<ReplayReport> {
+    let latest: u64 = block_builder
+        .get_latest_block_number()
+        .await
+        .saturating_to();
+
+    let mut report = ReplayReport::default();
+    for number in 0..latest {
+        let block = block_builder
+            .get_block(U256::from(number))
+            .await
+            .ok_or_else(|| anyhow::anyhow!("block {} is missing from the store", number))?;
+        if block.pruned {
+            anyhow::bail!("block {} was pruned, the chain can't be checked", number);
+        }
+
+        node.set_current_block(number);
+        let mut logs = Vec::new();
+        for (index, result) in node.execute_batch(&block.transactions).iter().enumerate() {
+            match result {
+                Ok(receipt) => logs.push(receipt.logs().to_vec()),
+                Err(error) => anyhow::bail!(
+                    "transaction {} of block {} fails: {}",
+                    index,
+                    number,
+                    error.reason()
+                ),
+            }
+        }
+
+        // blocks sealed before state roots, logs blooms or receipts roots were recorded carry
+        // none to check
+        let state_root = node.state().state_root();
+        if block.state_root != B256::ZERO && block.state_root != state_root {
+            anyhow::bail!(
+                "block {} claims state root {} but executing it gives {}",
+                number,
+                block.state_root,
+                state_root
+            );
+        }
+        if let Some(bloom) = block.logs_bloom_filter() {
+            if logs_bloom(logs.iter().flatten()) != bloom {
+                anyhow::bail!("block {} has the wrong logs bloom", number);
+            }
+        }
+        if block.receipts_root != B256::ZERO {
+            let root = receipts_root(&block.tx_hashes(), &logs);
+            if root != block.receipts_root {
+                anyhow::bail!(
+                    "block {} claims receipts root {} but executing it gives {}",
+                    number,
+                    block.receipts_root,
+                    root
+                );
+            }
+        }
+        if let Some(stored) = block_builder.get_block_receipts(block.hash).await {
+            let receipts = block_receipts(&block, &logs);
+            if stored.len() != receipts.len() {
+                anyhow::bail!(
+                    "block {} has {} receipts stored but {} transactions",
+                    number,
+                    stored.len(),
+                    receipts.len()
+                );
+            }
+            if let Some(index) = (0..receipts.len()).find(|&i| stored[i] != receipts[i]) {
+                anyhow::bail!(
+                    "the stored receipt of transaction {} of block {} differs from executing it",
+                    index,
+                    number
+                );
+            }
+            report.blocks_with_receipts += 1;
+        }
+
+        report.blocks += 1;
+        report.transactions += block.transactions.len();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use block_builder::{receipts::ReceiptStore, store::MemoryBlockStore};
+    use node::genesis::{Genesis, GenesisAccount};
+    use state::{memory::MemoryState, policy::DailySpending};
+    use std::{collections::BTreeMap, sync::Arc};
+    use tx::tx::Tx;
+
+    #[tokio::test]
+    async fn test_verify_chain() {
+        let sender = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let genesis = Genesis::new(vec![GenesisAccount {
+            address: sender.address(),
+            balance: U256::from(100),
+            sequence: 0,
+            multisig: None,
+            module: None,
+            label: None,
+            created_at: 0,
+            policy: None,
+            spending: DailySpending::default(),
+            schedules: Vec::new(),
+            escrows: Vec::new(),
+            htlcs: Vec::new(),
+            assets: BTreeMap::new(),
+        }]);
+        let funded_node = || {
+            let mut state = MemoryState::new();
+            genesis.apply(&mut state).unwrap();
+            Node::from_genesis(Box::new(state), &genesis)
+        };
+
+        // The chain as a node produces it, executed and with its receipts stored
+        let store = Arc::new(MemoryBlockStore::new());
+        let block_builder = BlockBuilder::with_store(store.clone()).unwrap();
+        let mut producer = funded_node();
+        for amount in [30, 20] {
+            let tx = Tx::new(sender.address(), to, U256::from(amount), None);
+            let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+            let included = vec![tx.with_signature(signature)];
+            let logs = producer
+                .execute_batch(&included)
+                .into_iter()
+                .map(|result| result.ok().unwrap().logs().to_vec())
+                .collect();
+            let state_root = producer.state().state_root();
+            block_builder
+                .create_block_with_logs(included, Address::ZERO, state_root, logs)
+                .await
+                .unwrap();
+        }
+
+        let report = verify_chain(&mut funded_node(), &block_builder)
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ReplayReport {
+                blocks: 2,
+                transactions: 2,
+                blocks_with_receipts: 2,
+            }
+        );
+
+        // A fresh state that doesn't hold the genesis can't execute it
+        let mut empty = Node::new(Box::new(MemoryState::new()));
+        assert!(verify_chain(&mut empty, &block_builder).await.is_err());
+
+        // Nor does a chain whose stored receipts were changed check out
+        let mut receipts = store.get_receipts(U256::from(1)).unwrap().unwrap();
+        receipts[0].gas_used += 1;
+        store.put_receipts(U256::from(1), &receipts).unwrap();
+        let error = verify_chain(&mut funded_node(), &block_builder)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("block 1"));
+    }
+}
@@ -0,0 +1,294 @@
+// catching up with the chain: a node that is behind asks its peers for the blocks it is missing,
+// a batch at a time, and imports them in order so each one is validated and executed before it
+// reaches the store. Peers are other nodes' JSON-RPC endpoints
+
+use alloy::primitives::U256;
+use block_builder::{Block, BlockBuilder};
+use client::transport::{HttpTransport, TransportConfig};
+use node::actor::NodeHandle;
+use rpc::{sync::SyncStatus, MAX_BLOCKS_PER_REQUEST};
+use serde_json::{json, Value};
+
+pub trait BlockSource {
+    // where the peer's canonical chain ends, None when it has no blocks yet
+    async fn head(&self) -> anyhow::Result<Option<u64>>;
+
+    async fn get_blocks(&self, from: u64, count: u64) -> anyhow::Result<Vec<Block>>;
+
+    fn name(&self) -> &str;
+}
+
+pub struct RpcPeer {
+    url: String,
+    transport: HttpTransport,
+}
+
+impl RpcPeer {
+    pub fn new(url: impl Into<String>) -> anyhow::Result<Self> {
+        let url = url.into();
+        let transport = HttpTransport::new(TransportConfig::new(url.clone()))
+            .map_err(|e| anyhow::anyhow!("invalid peer {}: {:?}", url, e))?;
+        Ok(Self { url, transport })
+    }
+}
+
+impl BlockSource for RpcPeer {
+    async fn head(&self) -> anyhow::Result<Option<u64>> {
+        // eth_blockNumber can't tell an empty chain from one with a single block
+        let latest: Value = self
+            .transport
+            .request("eth_getBlockByNumber", json!(["latest", false]))
+            .await
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        if latest.is_null() {
+            return Ok(None);
+        }
+        let number = latest["number"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("block without a number"))?;
+        Ok(Some(
+            U256::from_str_radix(number.trim_start_matches("0x"), 16)?.saturating_to(),
+        ))
+    }
+
+    async fn get_blocks(&self, from: u64, count: u64) -> anyhow::Result<Vec<Block>> {
+        self.transport
+            .request("fastpay_getBlocks", json!([from, count]))
+            .await
+            .map_err(|e| anyhow::anyhow!("{:?}", e))
+    }
+
+    fn name(&self) -> &str {
+        &self.url
+    }
+}
+
+pub struct Syncer<P> {
+    peers: Vec<P>,
+    status: SyncStatus,
+}
+
+impl<P: BlockSource> Syncer<P> {
+    pub fn new(peers: Vec<P>, status: SyncStatus) -> Self {
+        Self { peers, status }
+    }
+
+    pub fn has_peers(&self) -> bool {
+        !self.peers.is_empty()
+    }
+
+    // imports blocks until the node reaches the highest head its peers announce, trying the next
+    // peer when one can't be reached or serves a block that doesn't check out. Returns how many
+    // blocks were imported
+    pub async fn sync(
+        &self,
+        node: &NodeHandle,
+        block_builder: &BlockBuilder,
+    ) -> anyhow::Result<u64> {
+        let mut heads = Vec::new();
+        for peer in &self.peers {
+            match peer.head().await {
+                Ok(Some(head)) => heads.push((head, peer)),
+                Ok(None) => {}
+                Err(e) => eprintln!("peer {} is unreachable: {}", peer.name(), e),
+            }
+        }
+        heads.sort_by_key(|(head, _)| std::cmp::Reverse(*head));
+
+        let Some(&(highest, _)) = heads.first() else {
+            return Ok(0);
+        };
+        let start = next_number(block_builder).await;
+        if start > highest {
+            return Ok(0);
+        }
+        self.status.start(start.saturating_sub(1), highest);
+
+        let mut imported = 0;
+        for (head, peer) in heads {
+            if let Err(e) = self
+                .sync_from(peer, head, node, block_builder, &mut imported)
+                .await
+            {
+                eprintln!("syncing from {} failed: {}", peer.name(), e);
+            }
+        }
+        self.status.finish();
+
+        let next = next_number(block_builder).await;
+        if next <= highest {
+            anyhow::bail!(
+                "stuck at block {}, peers are at {}",
+                next.saturating_sub(1),
+                highest
+            );
+        }
+        Ok(imported)
+    }
+
+    async fn sync_from(
+        &self,
+        peer: &P,
+        head: u64,
+        node: &NodeHandle,
+        block_builder: &BlockBuilder,
+        imported: &mut u64,
+    ) -> anyhow::Result<()> {
+        loop {
+            let next = next_number(block_builder).await;
+            if next > head {
+                return Ok(());
+            }
+
+            let count = (head - next + 1).min(MAX_BLOCKS_PER_REQUEST);
+            let blocks = peer.get_blocks(next, count).await?;
+            if blocks.is_empty() {
+                anyhow::bail!("no blocks from {} although its head is {}", next, head);
+            }
+            for block in blocks {
+                let number = block.number.saturating_to();
+                node.import_block(block).await?;
+                self.status.advance(number);
+                *imported += 1;
+            }
+        }
+    }
+}
+
+async fn next_number(block_builder: &BlockBuilder) -> u64 {
+    match block_builder.get_latest_block().await {
+        Some(block) => block.number.saturating_to::<u64>() + 1,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devchain::{generate, DevchainConfig};
+    use node::Node;
+    use state::memory::MemoryState;
+
+    // a peer serving a fixed chain
+    struct ChainPeer {
+        name: String,
+        blocks: Vec<Block>,
+    }
+
+    impl BlockSource for ChainPeer {
+        async fn head(&self) -> anyhow::Result<Option<u64>> {
+            Ok(self.blocks.last().map(|block| block.number.saturating_to()))
+        }
+
+        async fn get_blocks(&self, from: u64, count: u64) -> anyhow::Result<Vec<Block>> {
+            Ok(self
+                .blocks
+                .iter()
+                .skip(from as usize)
+                .take(count as usize)
+                .cloned()
+                .collect())
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+    }
+
+    fn devchain(blocks: u64) -> crate::devchain::Devchain {
+        generate(DevchainConfig {
+            blocks,
+            tps: 2,
+            accounts: 10,
+            block_time: 1,
+            seed: 3,
+        })
+        .unwrap()
+    }
+
+    fn lagging_node(
+        devchain: &crate::devchain::Devchain,
+        block_builder: &BlockBuilder,
+    ) -> NodeHandle {
+        let mut state = MemoryState::new();
+        devchain.genesis.apply(&mut state).unwrap();
+        NodeHandle::spawn(Node::new(Box::new(state)), block_builder.clone())
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let chain = devchain(150);
+        let block_builder = BlockBuilder::new();
+        let node = lagging_node(&chain, &block_builder);
+        // The node already has the first blocks
+        for block in &chain.blocks[..10] {
+            node.import_block(block.clone()).await.unwrap();
+        }
+
+        let status = SyncStatus::new();
+        let syncer = Syncer::new(
+            vec![ChainPeer {
+                name: "peer".to_string(),
+                blocks: chain.blocks.clone(),
+            }],
+            status.clone(),
+        );
+        // Takes more than one request
+        assert_eq!(syncer.sync(&node, &block_builder).await.unwrap(), 140);
+        assert_eq!(status.progress(), None);
+
+        let head = block_builder.get_latest_block().await.unwrap();
+        assert_eq!(head.hash, chain.blocks.last().unwrap().hash);
+        assert_eq!(
+            node.inspect(|node| node.state().state_root()).await,
+            head.state_root
+        );
+
+        // Nothing to do once caught up
+        assert_eq!(syncer.sync(&node, &block_builder).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_skips_bad_peers() {
+        let chain = devchain(5);
+        let mut tampered = chain.blocks.clone();
+        tampered[2].timestamp += 1;
+
+        let syncer = Syncer::new(
+            vec![
+                ChainPeer {
+                    name: "tampered".to_string(),
+                    blocks: tampered,
+                },
+                ChainPeer {
+                    name: "honest".to_string(),
+                    blocks: chain.blocks.clone(),
+                },
+            ],
+            SyncStatus::new(),
+        );
+        let block_builder = BlockBuilder::new();
+        let node = lagging_node(&chain, &block_builder);
+        assert_eq!(syncer.sync(&node, &block_builder).await.unwrap(), 5);
+        assert_eq!(
+            block_builder.get_latest_block().await.unwrap().hash,
+            chain.blocks[4].hash
+        );
+
+        // With only the tampered peer the node gets stuck before the bad block
+        let syncer = Syncer::new(
+            vec![ChainPeer {
+                name: "tampered".to_string(),
+                blocks: syncer.peers[0].blocks.clone(),
+            }],
+            SyncStatus::new(),
+        );
+        let block_builder = BlockBuilder::new();
+        let node = lagging_node(&chain, &block_builder);
+        assert!(syncer.sync(&node, &block_builder).await.is_err());
+        assert_eq!(
+            block_builder.get_latest_block().await.unwrap().number,
+            U256::from(1)
+        );
+    }
+}
@@ -0,0 +1,316 @@
+// fastpay-watchtower closes payment channels on behalf of payees who may be offline. payees hand it
+// a close signed with the payer's latest balance update, and whenever a payer starts a timeout on
+// one of those channels the tower settles it with that update before the challenge period ends
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use alloy::primitives::{hex, Address, PrimitiveSignature, B256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use clap::{Args, Parser, Subcommand};
+use client::{channel::ChannelInfo, events::block_events, transport::Transport, Client};
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+use tx::tx::Tx;
+
+mod store;
+
+use store::{SignedClose, Store};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "fastpay-watchtower",
+    version,
+    about = "Settle payment channels when their payer tries to time them out"
+)]
+struct Cli {
+    #[arg(
+        long,
+        default_value = "watchtower.json",
+        help = "File holding the watched closes"
+    )]
+    store: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(about = "Hand the tower the latest balance update of a channel")]
+    Add(AddArgs),
+    #[command(about = "Follow new blocks and challenge timeouts")]
+    Run(RunArgs),
+}
+
+#[derive(Debug, Args)]
+struct AddArgs {
+    #[arg(long)]
+    channel_id: B256,
+    #[arg(long)]
+    payer: Address,
+    #[arg(long, help = "Total the payer owes the payee")]
+    amount: U256,
+    #[arg(long, help = "The payer's signature of the balance update")]
+    update_signature: String,
+    #[arg(
+        long,
+        help = "File holding the payee's private key, used once to sign the close"
+    )]
+    key_file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    #[arg(long, default_value = "http://127.0.0.1:8545")]
+    rpc_url: String,
+    #[arg(long, default_value = "ws://127.0.0.1:8545")]
+    ws_url: String,
+}
+
+fn load_key(path: &Path) -> anyhow::Result<PrivateKeySigner> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))?;
+    PrivateKeySigner::from_str(contents.trim())
+        .map_err(|e| anyhow::anyhow!("invalid private key: {}", e))
+}
+
+fn add(store_path: &Path, args: AddArgs) -> anyhow::Result<()> {
+    let payee = load_key(&args.key_file)?;
+    let update_signature = hex::decode(args.update_signature.trim())
+        .map_err(|e| anyhow::anyhow!("invalid update signature: {}", e))
+        .and_then(|bytes| {
+            PrimitiveSignature::try_from(bytes.as_slice())
+                .map_err(|e| anyhow::anyhow!("invalid update signature: {}", e))
+        })?;
+
+    let tx = Tx::close_channel(
+        payee.address(),
+        args.payer,
+        args.channel_id,
+        args.amount,
+        update_signature,
+        None,
+    );
+    let signature = payee
+        .sign_message_sync(&tx.tx_hash())
+        .map_err(|e| anyhow::anyhow!("failed to sign close: {}", e))?;
+    let close = SignedClose {
+        channel_id: args.channel_id,
+        payer: args.payer,
+        payee: payee.address(),
+        amount: args.amount,
+        update_signature,
+        signature,
+    };
+
+    let mut store = Store::load(store_path)?;
+    if store.insert(close).map_err(|e| anyhow::anyhow!(e))? {
+        store.save()?;
+        println!("watching {} at {}", args.channel_id, args.amount);
+    } else {
+        println!("already holding a later update for {}", args.channel_id);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Wait,
+    Challenge,
+    Forget,
+}
+
+// `challenged` is whether the tower already sent the close for this channel
+fn decide(channel: Option<&ChannelInfo>, challenged: bool) -> Action {
+    match channel {
+        // closed, by the tower, the payee or the payer's timeout
+        None => Action::Forget,
+        Some(channel) if channel.expires_at.is_some() && !challenged => Action::Challenge,
+        Some(_) => Action::Wait,
+    }
+}
+
+struct Watchtower<T> {
+    client: Client<T>,
+    store_path: PathBuf,
+    challenged: HashSet<B256>,
+}
+
+impl<T: Transport> Watchtower<T> {
+    fn new(client: Client<T>, store_path: PathBuf) -> Self {
+        Self {
+            client,
+            store_path,
+            challenged: HashSet::new(),
+        }
+    }
+
+    // the store is read again every time so closes added while running are picked up
+    async fn check(&mut self) -> anyhow::Result<()> {
+        let mut store = Store::load(&self.store_path)?;
+        let closes: Vec<SignedClose> = store.closes().cloned().collect();
+
+        let mut forgotten = false;
+        for close in closes {
+            let channel = match self.client.get_channel(close.channel_id).await {
+                Ok(channel) => channel,
+                Err(e) => {
+                    eprintln!("failed to get channel {}: {:?}", close.channel_id, e);
+                    continue;
+                }
+            };
+
+            match decide(
+                channel.as_ref(),
+                self.challenged.contains(&close.channel_id),
+            ) {
+                Action::Wait => {}
+                Action::Challenge => match self.client.send_channel_tx(&close.to_tx()).await {
+                    Ok(tx_hash) => {
+                        println!(
+                            "timeout started on {}, closed at {} with {}",
+                            close.channel_id, close.amount, tx_hash
+                        );
+                        self.challenged.insert(close.channel_id);
+                    }
+                    Err(e) => eprintln!("failed to close {}: {:?}", close.channel_id, e),
+                },
+                Action::Forget => {
+                    store.remove(&close.channel_id);
+                    self.challenged.remove(&close.channel_id);
+                    forgotten = true;
+                }
+            }
+        }
+
+        if forgotten {
+            store.save()?;
+        }
+        Ok(())
+    }
+}
+
+async fn run(store_path: PathBuf, args: RunArgs) -> anyhow::Result<()> {
+    let client = Client::new(args.rpc_url).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let mut tower = Watchtower::new(client, store_path);
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(args.ws_url.as_str()).await?;
+    let subscribe = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["newHeads"],
+    });
+    ws.send(Message::Text(subscribe.to_string())).await?;
+
+    let values = ws.filter_map(|message| async move {
+        match message {
+            Ok(Message::Text(text)) => serde_json::from_str(&text).ok(),
+            _ => None,
+        }
+    });
+    let mut heads = Box::pin(block_events(values));
+
+    // a timeout may have started while the tower was down
+    tower.check().await?;
+    while let Some(head) = heads.next().await {
+        if let Err(e) = tower.check().await {
+            eprintln!("check at block {} failed: {}", head.number, e);
+        }
+    }
+    anyhow::bail!("newHeads subscription closed")
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Add(args) => add(&cli.store, args),
+        Command::Run(args) => run(cli.store, args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use client::mock::MockNode;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "fastpay-watchtower-main-{}-{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn channel(expires_at: Option<u64>) -> ChannelInfo {
+        ChannelInfo {
+            payer: Address::repeat_byte(1),
+            payee: Address::repeat_byte(2),
+            deposit: 100,
+            challenge_period: 10,
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_decide() {
+        assert_eq!(decide(None, false), Action::Forget);
+        assert_eq!(decide(Some(&channel(None)), false), Action::Wait);
+        assert_eq!(decide(Some(&channel(Some(20))), false), Action::Challenge);
+        assert_eq!(decide(Some(&channel(Some(20))), true), Action::Wait);
+    }
+
+    #[tokio::test]
+    async fn test_check_challenges_timeouts() {
+        let path = temp_path("store.json");
+        let (payer, payee) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let id = B256::repeat_byte(7);
+
+        let mut store = Store::load(&path).unwrap();
+        store
+            .insert(store::tests::signed_close(&payer, &payee, id, 40))
+            .unwrap();
+        store.save().unwrap();
+
+        let node = MockNode::new();
+        let mut tower = Watchtower::new(Client::with_transport(node.clone()), path.clone());
+        let info = |expires_at: Option<u64>| {
+            json!({
+                "payer": payer.address(),
+                "payee": payee.address(),
+                "deposit": 100,
+                "challengePeriod": 10,
+                "expiresAt": expires_at,
+            })
+        };
+
+        node.set_channel(id, Some(info(None)));
+        tower.check().await.unwrap();
+        assert!(node.channel_txs().is_empty());
+
+        // The payer starts a timeout, the tower answers with the latest update once
+        node.set_channel(id, Some(info(Some(15))));
+        tower.check().await.unwrap();
+        tower.check().await.unwrap();
+        let sent = node.channel_txs();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0]["kind"], "close");
+        assert_eq!(sent[0]["amount"], json!(U256::from(40)));
+
+        // Once the channel is gone the tower stops watching it
+        node.set_channel(id, None);
+        tower.check().await.unwrap();
+        assert_eq!(Store::load(&path).unwrap().closes().count(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+}
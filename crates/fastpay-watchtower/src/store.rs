@@ -0,0 +1,193 @@
+// the closes the watchtower holds on behalf of payees, one per channel, kept in a JSON file
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::{Address, PrimitiveSignature, B256, U256};
+use serde::{Deserialize, Serialize};
+use tx::channel::ChannelUpdate;
+use tx::tx::Tx;
+
+// a close tx already signed by the payee, carrying the payer's latest balance update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedClose {
+    pub channel_id: B256,
+    pub payer: Address,
+    pub payee: Address,
+    pub amount: U256,
+    pub update_signature: PrimitiveSignature,
+    pub signature: PrimitiveSignature,
+}
+
+impl SignedClose {
+    pub fn to_tx(&self) -> Tx {
+        Tx::close_channel(
+            self.payee,
+            self.payer,
+            self.channel_id,
+            self.amount,
+            self.update_signature,
+            Some(self.signature),
+        )
+    }
+
+    // a close the node would reject is no use when the payer tries to time out
+    pub fn verify(&self) -> Result<(), String> {
+        let update = ChannelUpdate::new(self.channel_id, self.amount);
+        if update.signer(&self.update_signature) != Some(self.payer) {
+            return Err("balance update is not signed by the payer".to_string());
+        }
+
+        let signer = self
+            .signature
+            .recover_address_from_msg(self.to_tx().tx_hash())
+            .map_err(|e| format!("invalid close signature: {}", e))?;
+        if signer != self.payee {
+            return Err("close is not signed by the payee".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct Store {
+    path: PathBuf,
+    closes: BTreeMap<B256, SignedClose>,
+}
+
+impl Store {
+    // a missing file is an empty store
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let closes: Vec<SignedClose> = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            closes: closes
+                .into_iter()
+                .map(|close| (close.channel_id, close))
+                .collect(),
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let closes: Vec<&SignedClose> = self.closes.values().collect();
+        std::fs::write(&self.path, serde_json::to_string_pretty(&closes)?)?;
+        Ok(())
+    }
+
+    // keeps the close paying the payee the most, returns false if `close` isn't newer than the
+    // one already held
+    pub fn insert(&mut self, close: SignedClose) -> Result<bool, String> {
+        close.verify()?;
+
+        if let Some(held) = self.closes.get(&close.channel_id) {
+            if held.payer != close.payer || held.payee != close.payee {
+                return Err("close doesn't match the channel's parties".to_string());
+            }
+            if held.amount >= close.amount {
+                return Ok(false);
+            }
+        }
+        self.closes.insert(close.channel_id, close);
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, channel_id: &B256) -> Option<SignedClose> {
+        self.closes.remove(channel_id)
+    }
+
+    pub fn closes(&self) -> impl Iterator<Item = &SignedClose> {
+        self.closes.values()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    pub(crate) fn signed_close(
+        payer: &PrivateKeySigner,
+        payee: &PrivateKeySigner,
+        channel_id: B256,
+        amount: u64,
+    ) -> SignedClose {
+        let amount = U256::from(amount);
+        let update = ChannelUpdate::new(channel_id, amount);
+        let update_signature = payer.sign_message_sync(update.hash().as_slice()).unwrap();
+        let tx = Tx::close_channel(
+            payee.address(),
+            payer.address(),
+            channel_id,
+            amount,
+            update_signature,
+            None,
+        );
+
+        SignedClose {
+            channel_id,
+            payer: payer.address(),
+            payee: payee.address(),
+            amount,
+            update_signature,
+            signature: payee.sign_message_sync(&tx.tx_hash()).unwrap(),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "fastpay-watchtower-{}-{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn test_insert_keeps_latest_update() {
+        let (payer, payee) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let id = B256::repeat_byte(1);
+        let mut store = Store::load(&temp_path("unused")).unwrap();
+
+        assert!(store.insert(signed_close(&payer, &payee, id, 10)).unwrap());
+        assert!(store.insert(signed_close(&payer, &payee, id, 30)).unwrap());
+        assert!(!store.insert(signed_close(&payer, &payee, id, 20)).unwrap());
+        assert_eq!(store.closes().next().unwrap().amount, U256::from(30));
+    }
+
+    #[test]
+    fn test_insert_rejects_invalid_closes() {
+        let (payer, payee) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let id = B256::repeat_byte(1);
+        let mut store = Store::load(&temp_path("unused")).unwrap();
+
+        // The payee can't sign their own balance update
+        let mut forged = signed_close(&payee, &payee, id, 10);
+        forged.payer = payer.address();
+        assert!(store.insert(forged).is_err());
+
+        let mut tampered = signed_close(&payer, &payee, id, 10);
+        tampered.amount = U256::from(50);
+        assert!(store.insert(tampered).is_err());
+        assert_eq!(store.closes().count(), 0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("store.json");
+        let (payer, payee) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let close = signed_close(&payer, &payee, B256::repeat_byte(1), 10);
+
+        let mut store = Store::load(&path).unwrap();
+        store.insert(close.clone()).unwrap();
+        store.save().unwrap();
+
+        let loaded = Store::load(&path).unwrap();
+        assert_eq!(loaded.closes().collect::<Vec<_>>(), vec![&close]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+// the faucet's HTTP endpoint:
+//
+//     POST /drip {"address": "0x..", "amount": 100}
+//
+// answers {"txHash": "0x..", "amount": 100}, the amount may be left out to get the most a drip
+// sends. GET / tells what the faucet hands out
+
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256, U256};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use client::transport::Transport;
+use serde::{Deserialize, Serialize};
+
+use crate::{Faucet, FaucetError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DripRequest {
+    pub address: Address,
+    #[serde(default)]
+    pub amount: Option<U256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DripResponse {
+    pub tx_hash: B256,
+    pub amount: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaucetInfo {
+    pub address: Address,
+    pub max_drip: U256,
+    pub cooldown_secs: u64,
+}
+
+impl IntoResponse for FaucetError {
+    fn into_response(self) -> Response {
+        let (status, message) = match &self {
+            Self::InvalidAmount { max, got } => (
+                StatusCode::BAD_REQUEST,
+                format!("amount {} is not between 1 and {}", got, max),
+            ),
+            Self::RateLimited { retry_after } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "address was funded recently, retry in {}s",
+                    retry_after.as_secs()
+                ),
+            ),
+            Self::Signing(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to sign transfer: {}", e),
+            ),
+            Self::Client(e) => (
+                StatusCode::BAD_GATEWAY,
+                format!("failed to send transfer: {:?}", e),
+            ),
+        };
+        let body = Json(serde_json::json!({ "error": message }));
+        match self {
+            // rounded up, retrying a second early would be refused again
+            Self::RateLimited { retry_after } => {
+                let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                (status, [(header::RETRY_AFTER, secs.to_string())], body).into_response()
+            }
+            _ => (status, body).into_response(),
+        }
+    }
+}
+
+pub fn router<T: Transport + 'static>(faucet: Arc<Faucet<T>>) -> Router {
+    Router::new()
+        .route("/", get(info::<T>))
+        .route("/drip", post(drip::<T>))
+        .with_state(faucet)
+}
+
+async fn info<T: Transport>(State(faucet): State<Arc<Faucet<T>>>) -> Json<FaucetInfo> {
+    let config = faucet.config();
+    Json(FaucetInfo {
+        address: faucet.address(),
+        max_drip: config.max_drip,
+        cooldown_secs: config.cooldown.as_secs(),
+    })
+}
+
+async fn drip<T: Transport>(
+    State(faucet): State<Arc<Faucet<T>>>,
+    Json(request): Json<DripRequest>,
+) -> Result<Json<DripResponse>, FaucetError> {
+    let amount = request.amount.unwrap_or(faucet.config().max_drip);
+    let tx_hash = faucet.drip(request.address, Some(amount)).await?;
+    Ok(Json(DripResponse { tx_hash, amount }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FaucetConfig;
+    use axum::body::Body;
+    use axum::http::Request;
+    use client::mock::MockNode;
+    use tower::ServiceExt;
+
+    async fn call(router: &Router, method: &str, uri: &str, body: &str) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let node = MockNode::new();
+        let faucet = Arc::new(crate::tests::faucet(&node, FaucetConfig::default()));
+        let router = router(faucet.clone());
+
+        let info = json(call(&router, "GET", "/", "").await).await;
+        assert_eq!(info["address"], serde_json::json!(faucet.address()));
+        assert_eq!(info["maxDrip"], serde_json::json!(U256::from(1_000)));
+
+        let address = Address::repeat_byte(1);
+        let body = format!(r#"{{"address": "{}", "amount": 40}}"#, address);
+        let response = call(&router, "POST", "/drip", &body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let drip: DripResponse = serde_json::from_value(json(response).await).unwrap();
+        assert_eq!(drip.amount, U256::from(40));
+        assert_eq!(
+            node.transfers()[0]["amount"],
+            serde_json::json!(U256::from(40))
+        );
+
+        let response = call(&router, "POST", "/drip", &body).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "86400");
+
+        let body = format!(r#"{{"address": "{}", "amount": 5000}}"#, Address::ZERO);
+        let response = call(&router, "POST", "/drip", &body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(node.transfers().len(), 1);
+    }
+}
@@ -0,0 +1,217 @@
+// a faucet for test networks: anyone can ask it to fund an address, and it sends a transfer from
+// its own wallet. Each address gets at most `max_drip` at a time and has to wait `cooldown`
+// before asking again
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use client::{transport::Transport, Client, ClientError};
+use tokio::time::Instant;
+use tx::tx::Tx;
+use wallet::Wallet;
+
+pub mod http;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaucetConfig {
+    // the most one drip sends, and what it sends when no amount is asked for
+    pub max_drip: U256,
+    // how long an address waits between drips
+    pub cooldown: Duration,
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        Self {
+            max_drip: U256::from(1_000),
+            cooldown: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaucetError {
+    InvalidAmount { max: U256, got: U256 },
+    RateLimited { retry_after: Duration },
+    Signing(String),
+    // the node failed to answer or refused the transfer
+    Client(ClientError),
+}
+
+pub struct Faucet<T> {
+    client: Client<T>,
+    wallet: Wallet,
+    config: FaucetConfig,
+    // when each address was last funded
+    drips: Mutex<HashMap<Address, Instant>>,
+    // held from reading the wallet's sequence number until the transfer is sent, so two drips
+    // don't take the same one
+    sending: tokio::sync::Mutex<()>,
+}
+
+impl<T: Transport> Faucet<T> {
+    pub fn new(client: Client<T>, wallet: Wallet, config: FaucetConfig) -> Self {
+        Self {
+            client,
+            wallet,
+            config,
+            drips: Mutex::new(HashMap::new()),
+            sending: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub fn config(&self) -> FaucetConfig {
+        self.config
+    }
+
+    // sends `amount`, or the most a drip may send, to `to` and returns the transfer's hash
+    pub async fn drip(&self, to: Address, amount: Option<U256>) -> Result<B256, FaucetError> {
+        let amount = amount.unwrap_or(self.config.max_drip);
+        if amount.is_zero() || amount > self.config.max_drip {
+            return Err(FaucetError::InvalidAmount {
+                max: self.config.max_drip,
+                got: amount,
+            });
+        }
+
+        let previous = self.reserve(to)?;
+        let result = self.send(to, amount).await;
+        if result.is_err() {
+            // nothing was sent, the address may ask again right away
+            let mut drips = self.drips.lock().unwrap();
+            match previous {
+                Some(previous) => drips.insert(to, previous),
+                None => drips.remove(&to),
+            };
+        }
+        result
+    }
+
+    // marks `to` as funded now, returns when it was funded before
+    fn reserve(&self, to: Address) -> Result<Option<Instant>, FaucetError> {
+        let now = Instant::now();
+        let mut drips = self.drips.lock().unwrap();
+        if let Some(last) = drips.get(&to) {
+            let ready_at = *last + self.config.cooldown;
+            if ready_at > now {
+                return Err(FaucetError::RateLimited {
+                    retry_after: ready_at - now,
+                });
+            }
+        }
+        Ok(drips.insert(to, now))
+    }
+
+    async fn send(&self, to: Address, amount: U256) -> Result<B256, FaucetError> {
+        let _sending = self.sending.lock().await;
+        let chain_id = self.client.chain_id().await.map_err(FaucetError::Client)?;
+        let sequence = self
+            .client
+            .get_transaction_count(self.address(), true)
+            .await
+            .map_err(FaucetError::Client)?;
+        let fee = self
+            .client
+            .estimate_fee()
+            .await
+            .map_err(FaucetError::Client)?
+            .low
+            .fee;
+
+        let tx = Tx::transfer_order(self.address(), to, amount, sequence, None)
+            .with_fee(fee)
+            .with_chain_id(chain_id);
+        let signature = self
+            .wallet
+            .sign_transaction_sync(tx.clone())
+            .map_err(|e| FaucetError::Signing(format!("{:?}", e)))?;
+        self.client
+            .send_transfer(&tx.with_signature(signature))
+            .await
+            .map_err(FaucetError::Client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::mock::MockNode;
+    use serde_json::json;
+
+    pub(crate) fn faucet(node: &MockNode, config: FaucetConfig) -> Faucet<MockNode> {
+        let suggestion = |fee: &str| json!({"fee": fee, "expectedBlocks": "0x1"});
+        node.set_fee_estimate(json!({
+            "low": suggestion("0x2"),
+            "medium": suggestion("0x3"),
+            "high": suggestion("0x4"),
+        }));
+        Faucet::new(
+            Client::with_transport(node.clone()),
+            Wallet::random(),
+            config,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drip() {
+        let node = MockNode::new();
+        let faucet = faucet(&node, FaucetConfig::default());
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        node.set_transaction_count(faucet.address(), 7);
+
+        faucet.drip(alice, Some(U256::from(250))).await.unwrap();
+        let transfer = &node.transfers()[0];
+        assert_eq!(transfer["from"], json!(faucet.address()));
+        assert_eq!(transfer["to"], json!(alice));
+        assert_eq!(transfer["amount"], json!(U256::from(250)));
+        assert_eq!(transfer["sequence"], 7);
+        assert_eq!(transfer["fee"], 2);
+
+        // Once per cooldown, whatever the amount
+        assert_eq!(
+            faucet.drip(alice, Some(U256::from(1))).await,
+            Err(FaucetError::RateLimited {
+                retry_after: FaucetConfig::default().cooldown
+            })
+        );
+        faucet.drip(bob, None).await.unwrap();
+        assert_eq!(node.transfers()[1]["amount"], json!(U256::from(1_000)));
+
+        tokio::time::advance(FaucetConfig::default().cooldown).await;
+        faucet.drip(alice, None).await.unwrap();
+        assert_eq!(node.transfers().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_drip_limits() {
+        let node = MockNode::new();
+        let faucet = faucet(&node, FaucetConfig::default());
+        let alice = Address::repeat_byte(1);
+
+        for amount in [U256::ZERO, U256::from(1_001)] {
+            assert_eq!(
+                faucet.drip(alice, Some(amount)).await,
+                Err(FaucetError::InvalidAmount {
+                    max: U256::from(1_000),
+                    got: amount
+                })
+            );
+        }
+
+        // A drip the node refuses doesn't count against the address
+        node.fail_method("fastpay_sendTransfer", ClientError::Http(503));
+        assert_eq!(
+            faucet.drip(alice, None).await,
+            Err(FaucetError::Client(ClientError::Http(503)))
+        );
+        node.clear_failures();
+        faucet.drip(alice, None).await.unwrap();
+        assert_eq!(node.transfers().len(), 1);
+    }
+}
@@ -0,0 +1,82 @@
+// fastpay-faucet funds addresses on a test network from a key it is given, over HTTP, see
+// src/http.rs for the endpoint
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::U256;
+use alloy::signers::local::PrivateKeySigner;
+use clap::Parser;
+use client::Client;
+use faucet::{http, Faucet, FaucetConfig};
+use wallet::Wallet;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "fastpay-faucet",
+    version,
+    about = "Hand out test funds over HTTP"
+)]
+struct Cli {
+    #[arg(long, default_value = "http://127.0.0.1:8545")]
+    rpc_url: String,
+    #[arg(long, default_value = "127.0.0.1:8080", help = "Address to serve on")]
+    listen: SocketAddr,
+    #[arg(long, help = "File holding the private key of the faucet's funds")]
+    key_file: PathBuf,
+    #[arg(
+        long,
+        default_value_t = FaucetConfig::default().max_drip,
+        help = "The most one request is sent"
+    )]
+    max_drip: U256,
+    #[arg(
+        long,
+        default_value_t = FaucetConfig::default().cooldown.as_secs(),
+        help = "Seconds an address waits before it can be funded again"
+    )]
+    cooldown_secs: u64,
+}
+
+fn load_key(path: &Path) -> anyhow::Result<PrivateKeySigner> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))?;
+    PrivateKeySigner::from_str(contents.trim())
+        .map_err(|e| anyhow::anyhow!("invalid private key: {}", e))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let client =
+        Client::new(cli.rpc_url).map_err(|e| anyhow::anyhow!("invalid rpc url: {:?}", e))?;
+    let config = FaucetConfig {
+        max_drip: cli.max_drip,
+        cooldown: Duration::from_secs(cli.cooldown_secs),
+    };
+    let faucet = Faucet::new(client, Wallet::new(load_key(&cli.key_file)?), config);
+
+    println!("funding from {} on {}", faucet.address(), cli.listen);
+    axum::Server::bind(&cli.listen)
+        .serve(http::router(Arc::new(faucet)).into_make_service())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from(["fastpay-faucet", "--key-file", "faucet.key"]);
+        assert_eq!(cli.max_drip, U256::from(1_000));
+        assert_eq!(cli.cooldown_secs, 86_400);
+        assert_eq!(cli.listen, "127.0.0.1:8080".parse().unwrap());
+    }
+}
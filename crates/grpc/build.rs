@@ -0,0 +1,6 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // a protoc of our own, so building doesn't depend on one being installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::compile_protos("proto/fastpay.proto")?;
+    Ok(())
+}
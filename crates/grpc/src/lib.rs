@@ -0,0 +1,190 @@
+// the gRPC interface, for payment workloads where JSON-RPC's encoding costs more than the
+// transfers themselves. It serves a JSON-RPC server's EthRpcImpl, so txs are admitted with the
+// same checks and reads see the same node. See proto/fastpay.proto for the messages
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use jsonrpsee::types::{error::INVALID_PARAMS_CODE, ErrorObjectOwned};
+use rpc::{EthRpcImpl, FastpayRpcServer, TransferRequest};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("fastpay.v1");
+}
+
+use proto::fastpay_server::{Fastpay, FastpayServer};
+
+#[derive(Clone)]
+pub struct FastpayGrpc {
+    rpc: EthRpcImpl,
+}
+
+impl FastpayGrpc {
+    pub fn new(rpc: EthRpcImpl) -> Self {
+        Self { rpc }
+    }
+}
+
+// a rejected tx is the caller's to fix, anything else is the node's
+fn to_status(error: ErrorObjectOwned) -> Status {
+    match error.code() {
+        INVALID_PARAMS_CODE => Status::invalid_argument(error.message()),
+        _ => Status::internal(error.message()),
+    }
+}
+
+// tonic's status is large, but it is what every handler returns
+#[allow(clippy::result_large_err)]
+fn address(bytes: &[u8], field: &str) -> Result<Address, Status> {
+    (bytes.len() == 20)
+        .then(|| Address::from_slice(bytes))
+        .ok_or_else(|| Status::invalid_argument(format!("{} is not a 20 byte address", field)))
+}
+
+#[allow(clippy::result_large_err)]
+fn amount(bytes: &[u8]) -> Result<U256, Status> {
+    U256::try_from_be_slice(bytes)
+        .ok_or_else(|| Status::invalid_argument("amount is longer than 32 bytes"))
+}
+
+impl TryFrom<proto::Transfer> for TransferRequest {
+    type Error = Status;
+
+    fn try_from(transfer: proto::Transfer) -> Result<Self, Self::Error> {
+        Ok(TransferRequest {
+            from: address(&transfer.from, "from")?,
+            to: address(&transfer.to, "to")?,
+            amount: amount(&transfer.amount)?,
+            signature: Bytes::from(transfer.signature),
+            sequence: transfer.sequence,
+            fee: transfer.fee,
+            chain_id: transfer.chain_id,
+        })
+    }
+}
+
+impl From<&block_builder::Block> for proto::Block {
+    fn from(block: &block_builder::Block) -> Self {
+        Self {
+            number: block.number.saturating_to(),
+            hash: block.hash.to_vec(),
+            parent_hash: block.parent_hash.to_vec(),
+            timestamp: block.timestamp,
+            tx_hashes: block
+                .transactions
+                .iter()
+                .map(|tx| tx.tx_hash().to_vec())
+                .collect(),
+            gas_used: block.gas_used.saturating_to(),
+        }
+    }
+}
+
+type BlockStream = Pin<Box<dyn Stream<Item = Result<proto::Block, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Fastpay for FastpayGrpc {
+    async fn submit_transfer(
+        &self,
+        request: Request<proto::Transfer>,
+    ) -> Result<Response<proto::SubmitTransferResponse>, Status> {
+        let transfer = TransferRequest::try_from(request.into_inner())?;
+        let tx_hash = FastpayRpcServer::send_transfer(&self.rpc, transfer)
+            .await
+            .map_err(to_status)?;
+        let tx_hash: B256 = tx_hash
+            .parse()
+            .map_err(|_| Status::internal("invalid tx hash"))?;
+        Ok(Response::new(proto::SubmitTransferResponse {
+            tx_hash: tx_hash.to_vec(),
+        }))
+    }
+
+    async fn get_account(
+        &self,
+        request: Request<proto::GetAccountRequest>,
+    ) -> Result<Response<proto::Account>, Status> {
+        let address = address(&request.into_inner().address, "address")?;
+        let (balance, sequence) = match self.rpc.accounts().get_account(&address) {
+            Some(account) => (account.balance(), account.sequence()),
+            None => (U256::ZERO, 0),
+        };
+        Ok(Response::new(proto::Account {
+            address: address.to_vec(),
+            balance: balance.to_be_bytes::<32>().to_vec(),
+            sequence,
+        }))
+    }
+
+    type SubscribeBlocksStream = BlockStream;
+
+    async fn subscribe_blocks(
+        &self,
+        _request: Request<proto::SubscribeBlocksRequest>,
+    ) -> Result<Response<Self::SubscribeBlocksStream>, Status> {
+        let receiver = self.rpc.block_builder().subscribe_new_heads();
+        // a subscriber too slow to keep up misses blocks, like newHeads subscribers do
+        let blocks = BroadcastStream::new(receiver).filter_map(|block| match block {
+            Ok(block) => Some(Ok(proto::Block::from(&block))),
+            Err(e) => {
+                tracing::debug!(reason = %e, "grpc block subscriber fell behind");
+                None
+            }
+        });
+        Ok(Response::new(Box::pin(blocks)))
+    }
+}
+
+pub async fn start_grpc_server(addr: SocketAddr, rpc: EthRpcImpl) -> anyhow::Result<()> {
+    tonic::transport::Server::builder()
+        .add_service(FastpayServer::new(FastpayGrpc::new(rpc)))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_request() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let transfer = proto::Transfer {
+            from: from.to_vec(),
+            to: to.to_vec(),
+            amount: U256::from(1000).to_be_bytes::<32>().to_vec(),
+            signature: vec![0; 65],
+            sequence: Some(3),
+            fee: 1,
+            chain_id: None,
+        };
+        let request = TransferRequest::try_from(transfer.clone()).unwrap();
+        assert_eq!(request.from, from);
+        assert_eq!(request.to, to);
+        assert_eq!(request.amount, U256::from(1000));
+        assert_eq!(request.sequence, Some(3));
+
+        // Short amounts are big-endian too, addresses have to be whole
+        let short = proto::Transfer {
+            amount: vec![0x03, 0xe8],
+            ..transfer.clone()
+        };
+        assert_eq!(
+            TransferRequest::try_from(short).unwrap().amount,
+            U256::from(1000)
+        );
+        let truncated = proto::Transfer {
+            from: vec![1; 19],
+            ..transfer
+        };
+        assert_eq!(
+            TransferRequest::try_from(truncated).unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// the header chain a light client follows: every header has to hash to what it claims and build
+// on the one before, starting from a checkpoint the client trusts. With producers set it also
+// needs enough of their signatures, otherwise whoever serves the headers is trusted to pick the
+// canonical ones
+
+use alloy::primitives::{B256, U256};
+use block_builder::merkle::TxProof;
+use block_builder::producers::ProducerSet;
+use block_builder::Block;
+
+use crate::LightClientError;
+
+#[derive(Debug, Clone)]
+pub struct HeaderChain {
+    // contiguous, from the checkpoint to the tip
+    headers: Vec<Block>,
+    producers: Option<ProducerSet>,
+}
+
+impl HeaderChain {
+    pub fn new(checkpoint: Block) -> Result<Self, LightClientError> {
+        check_hash(&checkpoint)?;
+        Ok(Self {
+            headers: vec![checkpoint.header()],
+            producers: None,
+        })
+    }
+
+    pub fn with_producers(mut self, producers: ProducerSet) -> Self {
+        self.producers = Some(producers);
+        self
+    }
+
+    pub fn tip(&self) -> &Block {
+        self.headers
+            .last()
+            .expect("the checkpoint is never removed")
+    }
+
+    // None before the checkpoint or past the tip
+    pub fn get(&self, number: u64) -> Option<&Block> {
+        let checkpoint: u64 = self.headers[0].number.saturating_to();
+        let offset = number.checked_sub(checkpoint)?;
+        self.headers.get(usize::try_from(offset).ok()?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    // appends the header after the tip, txs it still carries are dropped
+    pub fn push(&mut self, header: Block) -> Result<(), LightClientError> {
+        check_hash(&header)?;
+        let tip = self.tip();
+        if header.number != tip.number + U256::from(1) || header.parent_hash != tip.hash {
+            return Err(LightClientError::NotLinked(header.hash));
+        }
+        if let Some(producers) = &self.producers {
+            if !producers.is_met_by(&header.signers()) {
+                return Err(LightClientError::NotSigned(header.hash));
+            }
+        }
+        self.headers.push(header.header());
+        Ok(())
+    }
+
+    // checks a proof served by a full node, its header has to be the one this chain holds at
+    // that height
+    pub fn verify(&self, proof: &TxProof) -> Result<(), LightClientError> {
+        let number: u64 = proof.header.number.saturating_to();
+        let known = self
+            .get(number)
+            .ok_or(LightClientError::UnknownHeader(number))?;
+        if known.hash != proof.header.hash || proof.header.compute_hash() != known.hash {
+            return Err(LightClientError::InvalidProof(format!(
+                "proof is against block {}, the chain has {} at {}",
+                proof.header.hash, known.hash, number
+            )));
+        }
+        if !proof.verify() {
+            return Err(LightClientError::InvalidProof(format!(
+                "proof doesn't hold against block {}",
+                known.hash
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn check_hash(header: &Block) -> Result<(), LightClientError> {
+    let expected: B256 = header.compute_hash();
+    if expected != header.hash {
+        return Err(LightClientError::InvalidHash {
+            claimed: header.hash,
+            expected,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use alloy::signers::local::PrivateKeySigner;
+    use tx::tx::Tx;
+
+    fn chain(length: u64) -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        for number in 0..length {
+            let parent = blocks.last().map_or(B256::ZERO, |block| block.hash);
+            let tx = Tx::new(
+                Address::ZERO,
+                Address::repeat_byte(number as u8 + 1),
+                U256::from(1),
+                None,
+            );
+            blocks.push(Block::new(
+                U256::from(number),
+                parent,
+                number,
+                vec![tx],
+                Address::ZERO,
+            ));
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_header_chain() {
+        let blocks = chain(4);
+        let mut headers = HeaderChain::new(blocks[0].clone()).unwrap();
+        headers.push(blocks[1].clone()).unwrap();
+        assert_eq!(headers.tip().hash, blocks[1].hash);
+        assert!(headers.tip().transactions.is_empty());
+
+        // Skipping a header, or one that lies about its hash, is refused
+        assert_eq!(
+            headers.push(blocks[3].clone()),
+            Err(LightClientError::NotLinked(blocks[3].hash))
+        );
+        let mut forged = blocks[2].clone();
+        forged.state_root = B256::repeat_byte(1);
+        assert!(matches!(
+            headers.push(forged),
+            Err(LightClientError::InvalidHash { .. })
+        ));
+
+        headers.push(blocks[2].clone()).unwrap();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.get(2).unwrap().hash, blocks[2].hash);
+        assert!(headers.get(3).is_none());
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let blocks = chain(2);
+        let mut headers = HeaderChain::new(blocks[0].clone()).unwrap();
+        headers.push(blocks[1].clone()).unwrap();
+
+        let proof = TxProof {
+            header: blocks[1].header(),
+            tx_proof: blocks[1].proof_for_tx(0).unwrap(),
+            receipt_proof: None,
+        };
+        assert_eq!(headers.verify(&proof), Ok(()));
+
+        // A proof against a block the chain doesn't have
+        let mut elsewhere = proof.clone();
+        elsewhere.header = blocks[0].header();
+        elsewhere.header.number = U256::from(1);
+        assert!(headers.verify(&elsewhere).is_err());
+
+        let mut wrong_tx = proof.clone();
+        wrong_tx.tx_proof = blocks[0].proof_for_tx(0).unwrap();
+        assert!(matches!(
+            headers.verify(&wrong_tx),
+            Err(LightClientError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_producer_signatures() {
+        let producer = PrivateKeySigner::random();
+        let blocks = chain(2);
+        let mut headers = HeaderChain::new(blocks[0].clone())
+            .unwrap()
+            .with_producers(ProducerSet::new(vec![producer.address()], 1).unwrap());
+
+        assert_eq!(
+            headers.push(blocks[1].clone()),
+            Err(LightClientError::NotSigned(blocks[1].hash))
+        );
+        let signature = blocks[1].sign(&producer).unwrap();
+        headers
+            .push(blocks[1].clone().with_signature(signature))
+            .unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+// a light client for mobile and embedded payment checks: it follows block headers only and checks
+// that a payment made it into a block, and what it logged, with the proofs a full node serves
+// over `fastpay_getProof`. The node isn't trusted, only the checkpoint the client starts from
+
+use alloy::primitives::{Address, B256};
+use block_builder::merkle::TxProof;
+use block_builder::Block;
+use client::transport::{HttpTransport, Transport, TransportConfig};
+use client::ClientError;
+use serde::Deserialize;
+use serde_json::json;
+use state::root::{verify_account_proof, AccountProof};
+
+pub mod headers;
+
+use headers::HeaderChain;
+
+// headers asked for at once while syncing, the most a node hands out per request
+const HEADERS_PER_REQUEST: u64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LightClientError {
+    Client(ClientError),
+    InvalidHash { claimed: B256, expected: B256 },
+    // the header doesn't build on the tip
+    NotLinked(B256),
+    // not signed by enough of the producers
+    NotSigned(B256),
+    // a proof for a block the chain doesn't have yet
+    UnknownHeader(u64),
+    InvalidProof(String),
+}
+
+impl From<ClientError> for LightClientError {
+    fn from(error: ClientError) -> Self {
+        Self::Client(error)
+    }
+}
+
+pub struct LightClient<T = HttpTransport> {
+    transport: T,
+    headers: HeaderChain,
+}
+
+impl LightClient<HttpTransport> {
+    pub fn new(url: impl Into<String>, headers: HeaderChain) -> Result<Self, LightClientError> {
+        let transport = HttpTransport::new(TransportConfig::new(url))?;
+        Ok(Self { transport, headers })
+    }
+}
+
+impl<T: Transport> LightClient<T> {
+    pub fn with_transport(transport: T, headers: HeaderChain) -> Self {
+        Self { transport, headers }
+    }
+
+    pub fn headers(&self) -> &HeaderChain {
+        &self.headers
+    }
+
+    // follows the node's chain up to its head, returns how many headers were added. A header that
+    // doesn't check out stops the sync with the ones before it kept
+    pub async fn sync(&mut self) -> Result<usize, LightClientError> {
+        let mut added = 0;
+        loop {
+            let from: u64 = self.headers.tip().number.saturating_to::<u64>() + 1;
+            let value = self
+                .transport
+                .request_value("fastpay_getHeaders", json!([from, HEADERS_PER_REQUEST]))
+                .await?;
+            let headers: Vec<Block> = serde_json::from_value(value)
+                .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+            let fetched = headers.len() as u64;
+            for header in headers {
+                self.headers.push(header)?;
+                added += 1;
+            }
+            if fetched < HEADERS_PER_REQUEST {
+                return Ok(added);
+            }
+        }
+    }
+
+    // the checked proof that the tx is in a block of the chain, None when the node doesn't know
+    // it. Sync first for recent txs
+    pub async fn verify_tx(&self, tx_hash: B256) -> Result<Option<TxProof>, LightClientError> {
+        let value = self
+            .transport
+            .request_value("fastpay_getProof", json!([tx_hash]))
+            .await?;
+        let proof: Option<TxProof> = serde_json::from_value(value)
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        let Some(proof) = proof else {
+            return Ok(None);
+        };
+        if proof.tx_proof.leaf != tx_hash {
+            return Err(LightClientError::InvalidProof(format!(
+                "asked for {} and got a proof for {}",
+                tx_hash, proof.tx_proof.leaf
+            )));
+        }
+        self.headers.verify(&proof)?;
+        Ok(Some(proof))
+    }
+
+    // the account as of the node's head checked against its state root, None when the node says
+    // it doesn't exist. The head has to be synced already
+    pub async fn verify_account(
+        &self,
+        address: Address,
+    ) -> Result<Option<AccountProof>, LightClientError> {
+        let value = self
+            .transport
+            .request_value("eth_getProof", json!([address, [], "latest"]))
+            .await?;
+        let response: AccountProofResponse = serde_json::from_value(value)
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        if self.headers.tip().hash != response.block_hash {
+            return Err(LightClientError::UnknownHeader(
+                self.headers.tip().number.saturating_to::<u64>() + 1,
+            ));
+        }
+        let Some(proof) = response.proof else {
+            return Ok(None);
+        };
+        if proof.address != address || !verify_account_proof(self.headers.tip().state_root, &proof)
+        {
+            return Err(LightClientError::InvalidProof(format!(
+                "account proof for {} doesn't hold against block {}",
+                address, response.block_hash
+            )));
+        }
+        Ok(Some(proof))
+    }
+}
+
+// the part of an `eth_getProof` answer the light client reads
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountProofResponse {
+    block_hash: B256,
+    proof: Option<AccountProof>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use block_builder::BlockBuilder;
+    use client::mock::MockNode;
+    use state::account::Account;
+    use tx::{log::Log, tx::Tx};
+
+    #[tokio::test]
+    async fn test_sync_and_verify() {
+        let full_node = BlockBuilder::new();
+        let genesis = full_node
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+        let tx = Tx::new(Address::ZERO, Address::repeat_byte(1), U256::from(5), None);
+        let logs = vec![vec![Log::transfer(
+            Address::ZERO,
+            Address::repeat_byte(1),
+            U256::from(5),
+        )]];
+        let block = full_node
+            .create_block_with_logs(vec![tx.clone()], Address::ZERO, B256::ZERO, logs.clone())
+            .await
+            .unwrap();
+
+        let node = MockNode::new();
+        node.set_result("fastpay_getHeaders", json!([block.header()]));
+        let mut light =
+            LightClient::with_transport(node.clone(), HeaderChain::new(genesis).unwrap());
+        assert_eq!(light.sync().await, Ok(1));
+        assert_eq!(light.headers().tip().hash, block.hash);
+
+        let tx_hash = B256::from_slice(&tx.tx_hash());
+        let proof = TxProof {
+            header: block.header(),
+            tx_proof: block.proof_for_tx(0).unwrap(),
+            receipt_proof: block.receipt_proof(&logs, 0),
+        };
+        node.set_result("fastpay_getProof", json!(proof));
+        let verified = light.verify_tx(tx_hash).await.unwrap().unwrap();
+        assert_eq!(verified.receipt_proof.unwrap().logs, logs[0]);
+
+        // A node passing off another tx's proof
+        assert!(matches!(
+            light.verify_tx(B256::repeat_byte(1)).await,
+            Err(LightClientError::InvalidProof(_))
+        ));
+
+        node.set_result("fastpay_getProof", serde_json::Value::Null);
+        assert!(matches!(light.verify_tx(tx_hash).await, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_account() {
+        let alice = Account::new(Address::repeat_byte(1), U256::from(40));
+        let bob = Account::new(Address::repeat_byte(2), U256::from(2));
+        let accounts = vec![alice.clone(), bob];
+        let state_root = state::root::state_root(&accounts, &[]);
+        let head = Block::new(U256::ZERO, B256::ZERO, 0, Vec::new(), Address::ZERO)
+            .with_state_root(state_root);
+        let node = MockNode::new();
+        let light =
+            LightClient::with_transport(node.clone(), HeaderChain::new(head.clone()).unwrap());
+
+        let mut proof = state::root::account_proof(&accounts, &[], &alice.get_address()).unwrap();
+        node.set_result(
+            "eth_getProof",
+            json!({ "blockHash": head.hash, "proof": proof }),
+        );
+        let verified = light.verify_account(alice.get_address()).await.unwrap();
+        assert_eq!(verified.unwrap().balance, U256::from(40));
+
+        // A node claiming more than the account holds
+        proof.balance = U256::from(400);
+        node.set_result(
+            "eth_getProof",
+            json!({ "blockHash": head.hash, "proof": proof }),
+        );
+        assert!(matches!(
+            light.verify_account(alice.get_address()).await,
+            Err(LightClientError::InvalidProof(_))
+        ));
+
+        // Or answering from a block the client doesn't follow
+        node.set_result(
+            "eth_getProof",
+            json!({ "blockHash": B256::repeat_byte(1), "proof": null }),
+        );
+        assert!(light.verify_account(alice.get_address()).await.is_err());
+    }
+}
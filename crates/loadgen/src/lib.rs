@@ -0,0 +1,352 @@
+// load for stress testing a node: wallets derived from a seed, so a run can be repeated with the
+// wallets an earlier one funded, get funds from a faucet account and then send each other signed
+// transfers at a target rate. A transfer's latency is how long the node took to accept it,
+// failures are counted by what went wrong
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::{keccak256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use client::{transport::Transport, Client, ClientError};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tx::tx::Tx;
+use wallet::Wallet;
+
+// how often funding is checked on while waiting for it to land
+const FUNDING_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadConfig {
+    pub tps: u64,
+    pub duration: Duration,
+    // what every transfer sends
+    pub amount: U256,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    pub sent: u64,
+    // of the transfers the node accepted, sorted
+    pub latencies: Vec<Duration>,
+    // the transfers that failed, by what went wrong
+    pub failures: BTreeMap<String, u64>,
+    pub elapsed: Duration,
+}
+
+impl LoadReport {
+    pub fn accepted(&self) -> u64 {
+        self.latencies.len() as u64
+    }
+
+    pub fn failed(&self) -> u64 {
+        self.failures.values().sum()
+    }
+
+    // the latency `percentile` percent of the accepted transfers came back within, None when
+    // there were none
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let rank = (percentile / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies
+            .get(rank.clamp(1, self.latencies.len().max(1)) - 1)
+            .copied()
+    }
+
+    // accepted transfers per second
+    pub fn tps(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.accepted() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn record(&mut self, latency: Duration, error: Option<ClientError>) {
+        match error {
+            Some(error) => *self.failures.entry(failure_kind(&error)).or_default() += 1,
+            None => self.latencies.push(latency),
+        }
+    }
+}
+
+// what a failure is counted as. Rpc errors keep their message, it says why the node refused
+pub fn failure_kind(error: &ClientError) -> String {
+    match error {
+        ClientError::Transport(_) => "transport".to_string(),
+        ClientError::Timeout => "timeout".to_string(),
+        ClientError::Http(status) => format!("http {}", status),
+        ClientError::Rpc { code, message } => format!("rpc {}: {}", code, message),
+        ClientError::InvalidResponse(_) => "invalid response".to_string(),
+        ClientError::InvalidRequest(_) => "invalid request".to_string(),
+    }
+}
+
+fn client_error(error: ClientError) -> anyhow::Error {
+    anyhow::anyhow!("{:?}", error)
+}
+
+// the `index`th wallet of `seed` has the hash of both as its key
+pub fn derive_wallets(seed: &str, count: usize) -> anyhow::Result<Vec<Wallet>> {
+    (0..count as u64)
+        .map(|index| {
+            let key = keccak256([seed.as_bytes(), &index.to_be_bytes()].concat());
+            let signer = PrivateKeySigner::from_bytes(&key)
+                .map_err(|e| anyhow::anyhow!("invalid key for wallet {}: {}", index, e))?;
+            Ok(Wallet::new(signer))
+        })
+        .collect()
+}
+
+fn sign(wallet: &Wallet, tx: Tx) -> anyhow::Result<Tx> {
+    let signature = wallet
+        .sign_transaction_sync(tx.clone())
+        .map_err(|e| anyhow::anyhow!("failed to sign: {:?}", e))?;
+    Ok(tx.with_signature(signature))
+}
+
+// sends `amount` from `faucet` to each wallet holding less, then waits up to `timeout` for all of
+// them to have it. Returns how many it funded
+pub async fn fund<T: Transport>(
+    client: &Client<T>,
+    faucet: &Wallet,
+    wallets: &[Wallet],
+    amount: U256,
+    timeout: Duration,
+) -> anyhow::Result<usize> {
+    let chain_id = client.chain_id().await.map_err(client_error)?;
+    let fee = client.estimate_fee().await.map_err(client_error)?.low.fee;
+    let mut sequence = client
+        .get_transaction_count(faucet.address(), true)
+        .await
+        .map_err(client_error)?;
+
+    let mut funded = Vec::new();
+    for wallet in wallets {
+        let address = wallet.address();
+        if client.get_balance(address).await.map_err(client_error)? >= amount {
+            continue;
+        }
+        let tx = Tx::transfer_order(faucet.address(), address, amount, sequence, None)
+            .with_fee(fee)
+            .with_chain_id(chain_id);
+        client
+            .send_transfer(&sign(faucet, tx)?)
+            .await
+            .map_err(client_error)?;
+        sequence += 1;
+        funded.push(address);
+    }
+
+    let deadline = Instant::now() + timeout;
+    for address in &funded {
+        while client.get_balance(*address).await.map_err(client_error)? < amount {
+            if Instant::now() >= deadline {
+                anyhow::bail!("{} wasn't funded within {:?}", address, timeout);
+            }
+            tokio::time::sleep(FUNDING_POLL_INTERVAL).await;
+        }
+    }
+    Ok(funded.len())
+}
+
+struct Sender {
+    wallet: Wallet,
+    next_sequence: u64,
+    // set when one of its transfers failed, the ones after it wait for a sequence number that
+    // was never taken, so the next one asks the node again
+    resync: Arc<AtomicBool>,
+}
+
+// each wallet in turn pays the next one, `config.tps` transfers a second for `config.duration`.
+// Transfers are sent without waiting for the ones before them to be accepted
+pub async fn run<T: Transport + 'static>(
+    client: Arc<Client<T>>,
+    wallets: Vec<Wallet>,
+    config: LoadConfig,
+) -> anyhow::Result<LoadReport> {
+    if wallets.len() < 2 {
+        anyhow::bail!("load needs at least 2 wallets");
+    }
+    if config.tps == 0 {
+        anyhow::bail!("the target tps must be above 0");
+    }
+    let chain_id = client.chain_id().await.map_err(client_error)?;
+    let fee = client.estimate_fee().await.map_err(client_error)?.low.fee;
+    let mut senders = Vec::new();
+    for wallet in wallets {
+        let next_sequence = client
+            .get_transaction_count(wallet.address(), true)
+            .await
+            .map_err(client_error)?;
+        senders.push(Sender {
+            wallet,
+            next_sequence,
+            resync: Arc::new(AtomicBool::new(false)),
+        });
+    }
+
+    let mut report = LoadReport::default();
+    let mut sending = JoinSet::new();
+    let total = (config.tps as f64 * config.duration.as_secs_f64()) as u64;
+    let mut ticker = tokio::time::interval(Duration::from_secs(1) / config.tps as u32);
+    let started = Instant::now();
+    for n in 0..total {
+        ticker.tick().await;
+        let index = n as usize % senders.len();
+        let to = senders[(index + 1) % senders.len()].wallet.address();
+        let sender = &mut senders[index];
+        report.sent += 1;
+
+        if sender.resync.swap(false, Ordering::Relaxed) {
+            match client
+                .get_transaction_count(sender.wallet.address(), true)
+                .await
+            {
+                Ok(sequence) => sender.next_sequence = sequence,
+                Err(error) => {
+                    sender.resync.store(true, Ordering::Relaxed);
+                    report.record(Duration::ZERO, Some(error));
+                    continue;
+                }
+            }
+        }
+        let tx = Tx::transfer_order(
+            sender.wallet.address(),
+            to,
+            config.amount,
+            sender.next_sequence,
+            None,
+        )
+        .with_fee(fee)
+        .with_chain_id(chain_id);
+        let tx = sign(&sender.wallet, tx)?;
+        sender.next_sequence += 1;
+
+        let client = client.clone();
+        let resync = sender.resync.clone();
+        sending.spawn(async move {
+            let sent_at = Instant::now();
+            let result = client.send_transfer(&tx).await;
+            if result.is_err() {
+                resync.store(true, Ordering::Relaxed);
+            }
+            (sent_at.elapsed(), result.err())
+        });
+    }
+
+    while let Some(result) = sending.join_next().await {
+        let (latency, error) = result?;
+        report.record(latency, error);
+    }
+    report.elapsed = started.elapsed();
+    report.latencies.sort();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::mock::MockNode;
+    use serde_json::json;
+
+    fn mock_node() -> MockNode {
+        let node = MockNode::new();
+        let suggestion = json!({"fee": "0x1", "expectedBlocks": "0x1"});
+        node.set_fee_estimate(json!({
+            "low": suggestion,
+            "medium": suggestion,
+            "high": suggestion,
+        }));
+        node
+    }
+
+    #[test]
+    fn test_derive_wallets() {
+        let wallets = derive_wallets("bench", 3).unwrap();
+        let again = derive_wallets("bench", 2).unwrap();
+        assert_eq!(wallets[1].address(), again[1].address());
+        assert_ne!(wallets[0].address(), wallets[1].address());
+        assert_ne!(
+            derive_wallets("other", 1).unwrap()[0].address(),
+            wallets[0].address()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fund() {
+        let node = mock_node();
+        let client = Client::with_transport(node.clone());
+        let faucet = Wallet::random();
+        let wallets = derive_wallets("bench", 3).unwrap();
+        node.set_transaction_count(faucet.address(), 4);
+        // Already funded by an earlier run
+        node.set_balance(wallets[0].address(), U256::from(100));
+
+        // Nothing lands until a block includes the transfers
+        let timeout = Duration::from_secs(2);
+        assert!(fund(&client, &faucet, &wallets, U256::from(100), timeout)
+            .await
+            .is_err());
+        let transfers = node.transfers();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0]["to"], json!(wallets[1].address()));
+        assert_eq!(transfers[1]["sequence"], 5);
+
+        for wallet in &wallets {
+            node.set_balance(wallet.address(), U256::from(100));
+        }
+        assert_eq!(
+            fund(&client, &faucet, &wallets, U256::from(100), timeout)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run() {
+        let node = mock_node();
+        let client = Arc::new(Client::with_transport(node.clone()));
+        let wallets = derive_wallets("bench", 2).unwrap();
+        let (alice, bob) = (wallets[0].address(), wallets[1].address());
+        node.set_transaction_count(bob, 9);
+        let config = LoadConfig {
+            tps: 10,
+            duration: Duration::from_secs(1),
+            amount: U256::from(1),
+        };
+
+        let report = run(client.clone(), wallets, config).await.unwrap();
+        assert_eq!(report.sent, 10);
+        assert_eq!(report.accepted(), 10);
+        assert!(report.percentile(99.0).is_some());
+        let transfers = node.transfers();
+        // The wallets take turns paying each other, each with its own sequence numbers
+        assert_eq!(transfers[0]["from"], json!(alice));
+        assert_eq!(transfers[0]["to"], json!(bob));
+        assert_eq!(transfers[1]["from"], json!(bob));
+        assert_eq!(transfers[1]["sequence"], 9);
+        assert_eq!(transfers[3]["sequence"], 10);
+
+        node.fail_method("fastpay_sendTransfer", ClientError::Http(503));
+        let wallets = derive_wallets("bench", 2).unwrap();
+        let report = run(client, wallets, config).await.unwrap();
+        assert_eq!(report.accepted(), 0);
+        assert_eq!(report.failures.get("http 503"), Some(&10));
+        assert_eq!(report.percentile(50.0), None);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let report = LoadReport {
+            latencies: (1..=100).map(Duration::from_millis).collect(),
+            ..LoadReport::default()
+        };
+        assert_eq!(report.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(report.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(report.percentile(100.0), Some(Duration::from_millis(100)));
+        assert_eq!(report.percentile(0.0), Some(Duration::from_millis(1)));
+    }
+}
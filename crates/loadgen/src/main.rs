@@ -0,0 +1,142 @@
+// fastpay-loadgen stress tests a node over its rpc, see src/lib.rs for how the load is made
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::U256;
+use alloy::signers::local::PrivateKeySigner;
+use clap::Parser;
+use client::Client;
+use loadgen::{derive_wallets, fund, run, LoadConfig, LoadReport};
+use wallet::Wallet;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "fastpay-loadgen",
+    version,
+    about = "Send signed transfers to a node at a target rate and report how it kept up"
+)]
+struct Cli {
+    #[arg(long, default_value = "http://127.0.0.1:8545")]
+    rpc_url: String,
+    #[arg(
+        long,
+        default_value = "fastpay-loadgen",
+        help = "Seed the wallets are derived from, the same seed gives the same wallets"
+    )]
+    seed: String,
+    #[arg(long, default_value_t = 10)]
+    wallets: usize,
+    #[arg(
+        long,
+        help = "File holding the private key of the account funding the wallets, they aren't \
+                funded without it"
+    )]
+    faucet_key_file: Option<PathBuf>,
+    #[arg(long, default_value_t = U256::from(1_000_000), help = "What each wallet is funded with")]
+    fund_amount: U256,
+    #[arg(
+        long,
+        default_value_t = 60,
+        help = "Seconds to wait for the funding to land"
+    )]
+    fund_timeout_secs: u64,
+    #[arg(long, default_value_t = 100, help = "Transfers to send per second")]
+    tps: u64,
+    #[arg(long, default_value_t = 30, help = "Seconds to send them for")]
+    duration_secs: u64,
+    #[arg(long, default_value_t = U256::from(1), help = "What each transfer sends")]
+    amount: U256,
+}
+
+fn load_key(path: &Path) -> anyhow::Result<PrivateKeySigner> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))?;
+    PrivateKeySigner::from_str(contents.trim())
+        .map_err(|e| anyhow::anyhow!("invalid private key: {}", e))
+}
+
+fn print_report(report: &LoadReport) {
+    println!(
+        "sent {} transfers in {:.1}s, {} accepted ({:.1} tps), {} failed",
+        report.sent,
+        report.elapsed.as_secs_f64(),
+        report.accepted(),
+        report.tps(),
+        report.failed()
+    );
+    if let (Some(p50), Some(p90), Some(p99), Some(max)) = (
+        report.percentile(50.0),
+        report.percentile(90.0),
+        report.percentile(99.0),
+        report.percentile(100.0),
+    ) {
+        println!(
+            "latency p50 {:?} p90 {:?} p99 {:?} max {:?}",
+            p50, p90, p99, max
+        );
+    }
+    for (kind, count) in &report.failures {
+        println!("{:>8} {}", count, kind);
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let client = Arc::new(
+        Client::new(cli.rpc_url).map_err(|e| anyhow::anyhow!("invalid rpc url: {:?}", e))?,
+    );
+    let wallets = derive_wallets(&cli.seed, cli.wallets)?;
+
+    if let Some(path) = &cli.faucet_key_file {
+        let faucet = Wallet::new(load_key(path)?);
+        let funded = fund(
+            &client,
+            &faucet,
+            &wallets,
+            cli.fund_amount,
+            Duration::from_secs(cli.fund_timeout_secs),
+        )
+        .await?;
+        println!(
+            "funded {} of {} wallets from {}",
+            funded,
+            wallets.len(),
+            faucet.address()
+        );
+    }
+
+    let config = LoadConfig {
+        tps: cli.tps,
+        duration: Duration::from_secs(cli.duration_secs),
+        amount: cli.amount,
+    };
+    println!(
+        "sending {} transfers a second from {} wallets for {}s",
+        cli.tps,
+        wallets.len(),
+        cli.duration_secs
+    );
+    let report = run(client, wallets, config).await?;
+    print_report(&report);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from(["fastpay-loadgen", "--tps", "500", "--wallets", "50"]);
+        assert_eq!(cli.tps, 500);
+        assert_eq!(cli.wallets, 50);
+        assert_eq!(cli.duration_secs, 30);
+        assert!(cli.faucet_key_file.is_none());
+    }
+}
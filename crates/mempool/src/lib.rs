@@ -0,0 +1,915 @@
+use alloy::primitives::{Address, B256};
+use bytes::Bytes;
+use state::{
+    sharded::ShardedState,
+    state::{State, SEEN_FOR_GOOD},
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tx::{fee::FeeSchedule, signature_cache::SignatureCache, tx::Tx};
+
+// how many pending transactions a slow subscriber can fall behind before it starts missing them
+const PENDING_TXS_CHANNEL_CAPACITY: usize = 1024;
+// how much more a tx has to pay to take the place of the sender's tx with the same sequence number
+const REPLACEMENT_FEE_BUMP_PERCENT: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MempoolError {
+    AlreadyKnown,
+    // the chain executed the exact same tx in this block, the vm refuses it again
+    AlreadyExecuted { block: u64 },
+    // the tx pays less than the fee schedule asks for its size
+    Underpriced { required: u64, got: u64 },
+    // the sender already used this sequence number
+    StaleSequence { expected: u64, got: u64 },
+    // the sender has a tx with this sequence number in the pool and the new one doesn't pay
+    // enough more to replace it
+    ReplacementUnderpriced { required: u64, got: u64 },
+    // the sender has too many transactions waiting on a gap
+    SenderQueueFull,
+    // the pool is at capacity and the tx is no better than anything it could evict
+    Full,
+    // only checked with a signature cache, see Mempool::with_signature_cache
+    InvalidSignature(String),
+}
+
+// where the chain is at for each sender, so the mempool can tell a gap from a stale tx, and which
+// txs it already executed
+pub trait SequenceReader: Send + Sync {
+    fn next_sequence(&self, address: &Address) -> u64;
+
+    fn executed_in(&self, tx_hash: &B256) -> Option<u64>;
+}
+
+impl<S: State + Send + Sync> SequenceReader for ShardedState<S> {
+    fn next_sequence(&self, address: &Address) -> u64 {
+        self.read_account(address)
+            .map_or(0, |account| account.sequence())
+    }
+
+    fn executed_in(&self, tx_hash: &B256) -> Option<u64> {
+        self.read_seen_tx(tx_hash).map(|seen| seen & !SEEN_FOR_GOOD)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MempoolConfig {
+    // pending and queued transactions together
+    pub max_txs: usize,
+    // queued transactions per sender
+    pub max_queued_per_sender: usize,
+    // the chain's, a tx the vm would reject for its fee doesn't get in
+    pub fee_schedule: FeeSchedule,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            max_txs: 65_536,
+            max_queued_per_sender: 64,
+            fee_schedule: FeeSchedule::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Pool {
+    // ready for a block, in the order they became ready
+    pending: VecDeque<Tx>,
+    // transfer orders waiting for an earlier sequence number of their sender, by sequence number
+    queued: HashMap<Address, BTreeMap<u64, Tx>>,
+    hashes: HashSet<Bytes>,
+    // the sequence number following the sender's last pending tx, the chain catches up once
+    // the pending ones are executed
+    next_sequences: HashMap<Address, u64>,
+}
+
+impl Pool {
+    fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    // the sender's tx with `sequence` if it is still in the pool, and whether it is queued
+    fn find(&mut self, sender: &Address, sequence: u64) -> Option<(&mut Tx, bool)> {
+        if let Some(tx) = self
+            .pending
+            .iter_mut()
+            .find(|tx| tx.from() == *sender && tx.sequence() == Some(sequence))
+        {
+            return Some((tx, false));
+        }
+        self.queued
+            .get_mut(sender)
+            .and_then(|queue| queue.get_mut(&sequence))
+            .map(|tx| (tx, true))
+    }
+}
+
+// the smallest fee that replaces a tx paying `fee`, always more than it
+fn replacement_fee(fee: u64) -> u64 {
+    let bump = (fee as u128 * REPLACEMENT_FEE_BUMP_PERCENT as u128).div_ceil(100) as u64;
+    fee.saturating_add(bump.max(1))
+}
+
+// the indices of `pending` in the order they go in a block: highest fee first, ties in the order
+// they became pending. A sender's transfer orders then trade places so they still come out by
+// sequence number, the ones with the lowest take the best spots
+fn batch_order(pending: &VecDeque<Tx>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..pending.len()).collect();
+    order.sort_by_key(|&i| Reverse(pending[i].fee()));
+
+    let mut spots: HashMap<Address, Vec<usize>> = HashMap::new();
+    for (spot, &i) in order.iter().enumerate() {
+        if pending[i].sequence().is_some() {
+            spots.entry(pending[i].from()).or_default().push(spot);
+        }
+    }
+    for spots in spots.into_values() {
+        let mut orders: Vec<usize> = spots.iter().map(|&spot| order[spot]).collect();
+        orders.sort_by_key(|&i| pending[i].sequence());
+        for (spot, i) in spots.into_iter().zip(orders) {
+            order[spot] = i;
+        }
+    }
+    order
+}
+
+// Mempool holds transactions that have been accepted by the node but not yet included in a block.
+// Transfers without a sequence number are pending right away, transfer orders are queued until
+// every earlier sequence number of their sender is pending or executed
+#[derive(Clone)]
+pub struct Mempool {
+    pool: Arc<RwLock<Pool>>,
+    config: MempoolConfig,
+    sequences: Option<Arc<dyn SequenceReader>>,
+    signature_cache: Option<Arc<SignatureCache>>,
+    pending_txs: broadcast::Sender<Tx>,
+}
+
+impl std::fmt::Debug for Mempool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mempool")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::with_config(MempoolConfig::default())
+    }
+
+    pub fn with_config(config: MempoolConfig) -> Self {
+        let (pending_txs, _) = broadcast::channel(PENDING_TXS_CHANNEL_CAPACITY);
+
+        Self {
+            pool: Arc::new(RwLock::new(Pool::default())),
+            config,
+            sequences: None,
+            signature_cache: None,
+            pending_txs,
+        }
+    }
+
+    // without it every sender is assumed to start at sequence number 0
+    pub fn with_sequences(mut self, sequences: Arc<dyn SequenceReader>) -> Self {
+        self.sequences = Some(sequences);
+        self
+    }
+
+    // checks the signature of every tx it admits through `signature_cache`, shared with the vm so
+    // executing the tx later finds its signers already recovered
+    pub fn with_signature_cache(mut self, signature_cache: Arc<SignatureCache>) -> Self {
+        self.signature_cache = Some(signature_cache);
+        self
+    }
+
+    pub fn signature_cache(&self) -> Option<&Arc<SignatureCache>> {
+        self.signature_cache.as_ref()
+    }
+
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        self.config.fee_schedule
+    }
+
+    pub async fn add_tx(&self, tx: Tx) -> Result<(), MempoolError> {
+        // before taking the lock, recovering is the slow part of admission
+        if let Some(signature_cache) = &self.signature_cache {
+            signature_cache
+                .verify_signature(&tx)
+                .map_err(MempoolError::InvalidSignature)?;
+        }
+
+        let mut pool = self.pool.write().await;
+
+        if pool.hashes.contains(&tx.tx_hash()) {
+            return Err(MempoolError::AlreadyKnown);
+        }
+        if let Some(block) = self
+            .sequences
+            .as_ref()
+            .and_then(|sequences| sequences.executed_in(&B256::from_slice(&tx.tx_hash())))
+        {
+            return Err(MempoolError::AlreadyExecuted { block });
+        }
+
+        let required = self.config.fee_schedule.min_fee(&tx);
+        if tx.fee() < required {
+            return Err(MempoolError::Underpriced {
+                required,
+                got: tx.fee(),
+            });
+        }
+
+        let Some(sequence) = tx.sequence() else {
+            self.make_room(&mut pool, None)?;
+            self.push_pending(&mut pool, tx);
+            return Ok(());
+        };
+
+        let from = tx.from();
+        if let Some((existing, queued)) = pool.find(&from, sequence) {
+            let required = replacement_fee(existing.fee());
+            if tx.fee() < required {
+                return Err(MempoolError::ReplacementUnderpriced {
+                    required,
+                    got: tx.fee(),
+                });
+            }
+            let replaced = std::mem::replace(existing, tx.clone());
+            pool.hashes.remove(&replaced.tx_hash());
+            pool.hashes.insert(tx.tx_hash());
+            if !queued {
+                let _ = self.pending_txs.send(tx);
+            }
+            return Ok(());
+        }
+
+        let expected = self.expected_sequence(&pool, &from);
+        if sequence < expected {
+            return Err(MempoolError::StaleSequence {
+                expected,
+                got: sequence,
+            });
+        }
+
+        if sequence == expected {
+            self.make_room(&mut pool, None)?;
+            self.push_pending(&mut pool, tx);
+            pool.next_sequences.insert(from, sequence + 1);
+            self.promote(&mut pool, &from);
+            return Ok(());
+        }
+
+        let queued = pool.queued.get(&from).map_or(0, |queue| queue.len());
+        if queued >= self.config.max_queued_per_sender {
+            return Err(MempoolError::SenderQueueFull);
+        }
+        self.make_room(&mut pool, Some(sequence - expected))?;
+        pool.hashes.insert(tx.tx_hash());
+        pool.queued.entry(from).or_default().insert(sequence, tx);
+        Ok(())
+    }
+
+    // removes up to `max` pending transactions, highest fee first, see batch_order
+    pub async fn take_batch(&self, max: usize) -> Vec<Tx> {
+        let mut pool = self.pool.write().await;
+
+        // the chain may have moved on through txs that never went through this pool
+        let senders: Vec<Address> = pool.queued.keys().copied().collect();
+        for sender in senders {
+            self.promote(&mut pool, &sender);
+        }
+        if let Some(sequences) = &self.sequences {
+            pool.next_sequences
+                .retain(|sender, next| sequences.next_sequence(sender) < *next);
+        }
+
+        let mut order = batch_order(&pool.pending);
+        order.truncate(max);
+        let mut pending: Vec<Option<Tx>> = pool.pending.drain(..).map(Some).collect();
+        let batch: Vec<Tx> = order.iter().filter_map(|&i| pending[i].take()).collect();
+        // what's left keeps the order it became pending in
+        pool.pending = pending.into_iter().flatten().collect();
+        for tx in &batch {
+            pool.hashes.remove(&tx.tx_hash());
+        }
+
+        batch
+    }
+
+    // puts back txs taken by take_batch that didn't make it into the block, ahead of the rest
+    pub async fn requeue(&self, txs: Vec<Tx>) {
+        let mut pool = self.pool.write().await;
+        for tx in txs.into_iter().rev() {
+            if pool.hashes.insert(tx.tx_hash()) {
+                pool.pending.push_front(tx);
+            }
+        }
+    }
+
+    // the transactions waiting for a block, oldest first, without removing them
+    pub async fn pending(&self) -> Vec<Tx> {
+        self.pool.read().await.pending.iter().cloned().collect()
+    }
+
+    // the transfer orders waiting on a gap, by sender and sequence number
+    pub async fn queued(&self) -> Vec<Tx> {
+        let pool = self.pool.read().await;
+        let mut senders: Vec<_> = pool.queued.iter().collect();
+        senders.sort_by_key(|(sender, _)| **sender);
+        senders
+            .into_iter()
+            .flat_map(|(_, queue)| queue.values().cloned())
+            .collect()
+    }
+
+    // the tx with `tx_hash`, pending or queued
+    pub async fn get(&self, tx_hash: &Bytes) -> Option<Tx> {
+        let pool = self.pool.read().await;
+        if !pool.hashes.contains(tx_hash) {
+            return None;
+        }
+        pool.pending
+            .iter()
+            .chain(pool.queued.values().flat_map(|queue| queue.values()))
+            .find(|tx| tx.tx_hash() == *tx_hash)
+            .cloned()
+    }
+
+    // takes the tx out of the pool, for a sender cancelling it before it is in a block. The
+    // sender's pending transfer orders after it go back to waiting for its sequence number,
+    // which a new tx can then take
+    pub async fn remove(&self, tx_hash: &Bytes) -> Option<Tx> {
+        let mut pool = self.pool.write().await;
+        if !pool.hashes.remove(tx_hash) {
+            return None;
+        }
+
+        if let Some(index) = pool.pending.iter().position(|tx| tx.tx_hash() == *tx_hash) {
+            let tx = pool.pending.remove(index)?;
+            if let Some(sequence) = tx.sequence() {
+                let from = tx.from();
+                let (later, pending): (VecDeque<Tx>, VecDeque<Tx>) =
+                    pool.pending.drain(..).partition(|pending| {
+                        pending.from() == from
+                            && pending.sequence().is_some_and(|later| later > sequence)
+                    });
+                pool.pending = pending;
+                for later in later {
+                    if let Some(later_sequence) = later.sequence() {
+                        pool.queued
+                            .entry(from)
+                            .or_default()
+                            .insert(later_sequence, later);
+                    }
+                }
+                pool.next_sequences.insert(from, sequence);
+            }
+            return Some(tx);
+        }
+
+        let (sender, sequence) = pool.queued.iter().find_map(|(sender, queue)| {
+            queue
+                .iter()
+                .find(|(_, tx)| tx.tx_hash() == *tx_hash)
+                .map(|(sequence, _)| (*sender, *sequence))
+        })?;
+        let queue = pool.queued.get_mut(&sender)?;
+        let tx = queue.remove(&sequence);
+        if queue.is_empty() {
+            pool.queued.remove(&sender);
+        }
+        tx
+    }
+
+    pub async fn contains(&self, tx_hash: &Bytes) -> bool {
+        self.pool.read().await.hashes.contains(tx_hash)
+    }
+
+    // pending transactions only, queued ones can't go in a block yet
+    pub async fn len(&self) -> usize {
+        self.pool.read().await.pending.len()
+    }
+
+    pub async fn queued_len(&self) -> usize {
+        let pool = self.pool.read().await;
+        pool.queued.values().map(|queue| queue.len()).sum()
+    }
+
+    // the sequence number the sender's next transfer order takes, past its pending ones. Queued
+    // ones don't count, they wait on a gap the next order has to fill
+    pub async fn next_sequence(&self, sender: &Address) -> u64 {
+        let pool = self.pool.read().await;
+        self.expected_sequence(&pool, sender)
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.pool.read().await.pending.is_empty()
+    }
+
+    pub fn subscribe_pending_txs(&self) -> broadcast::Receiver<Tx> {
+        self.pending_txs.subscribe()
+    }
+
+    fn expected_sequence(&self, pool: &Pool, sender: &Address) -> u64 {
+        let chain = self
+            .sequences
+            .as_ref()
+            .map_or(0, |sequences| sequences.next_sequence(sender));
+        pool.next_sequences
+            .get(sender)
+            .map_or(chain, |next| chain.max(*next))
+    }
+
+    fn push_pending(&self, pool: &mut Pool, tx: Tx) {
+        pool.hashes.insert(tx.tx_hash());
+        pool.pending.push_back(tx.clone());
+
+        // nobody listening for pending transactions is not an error
+        let _ = self.pending_txs.send(tx);
+    }
+
+    // moves the sender's queued txs that no longer wait on a gap to pending, and drops the ones
+    // whose sequence number has been used in the meantime
+    fn promote(&self, pool: &mut Pool, sender: &Address) {
+        let mut next = self.expected_sequence(pool, sender);
+        let Some(mut queue) = pool.queued.remove(sender) else {
+            return;
+        };
+
+        while let Some(entry) = queue.first_entry() {
+            if *entry.key() > next {
+                break;
+            }
+            let stale = *entry.key() < next;
+            let tx = entry.remove();
+            if stale {
+                pool.hashes.remove(&tx.tx_hash());
+            } else {
+                self.push_pending(pool, tx);
+                next += 1;
+            }
+        }
+
+        if next > 0 {
+            pool.next_sequences.insert(*sender, next);
+        }
+        if !queue.is_empty() {
+            pool.queued.insert(*sender, queue);
+        }
+    }
+
+    // frees a slot for a new tx when the pool is full. What goes is the queued tx furthest from
+    // executing, the one with the largest gap before its sequence number. `gap` is the incoming tx's, None when it is ready to execute,
+    // it is refused if it wouldn't be better than the one it would evict
+    fn make_room(&self, pool: &mut Pool, gap: Option<u64>) -> Result<(), MempoolError> {
+        if pool.len() < self.config.max_txs {
+            return Ok(());
+        }
+
+        let victim = pool
+            .queued
+            .iter()
+            .filter_map(|(sender, queue)| {
+                let (sequence, _) = queue.last_key_value()?;
+                let gap = sequence.saturating_sub(self.expected_sequence(pool, sender));
+                Some((gap, *sender, *sequence))
+            })
+            .max();
+        let Some((victim_gap, sender, sequence)) = victim else {
+            return Err(MempoolError::Full);
+        };
+        if gap.is_some_and(|gap| gap >= victim_gap) {
+            return Err(MempoolError::Full);
+        }
+
+        if let Some(queue) = pool.queued.get_mut(&sender) {
+            if let Some(tx) = queue.remove(&sequence) {
+                pool.hashes.remove(&tx.tx_hash());
+            }
+            if queue.is_empty() {
+                pool.queued.remove(&sender);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use wallet::Wallet;
+
+    fn signed_tx(wallet: &Wallet, amount: u64) -> Tx {
+        let (to, amount) = (Wallet::random().address(), U256::from(amount));
+        let tx = Tx::new(wallet.address(), to, amount, None);
+        let signature = wallet.sign_transaction_sync(tx.clone()).unwrap();
+        Tx::new(wallet.address(), to, amount, Some(signature))
+    }
+
+    #[tokio::test]
+    async fn test_add_and_take_batch() {
+        let mempool = Mempool::new();
+        let wallet = Wallet::random();
+
+        let tx1 = signed_tx(&wallet, 10);
+        let tx2 = signed_tx(&wallet, 20);
+        let tx3 = signed_tx(&wallet, 30);
+
+        mempool.add_tx(tx1.clone()).await.unwrap();
+        mempool.add_tx(tx2.clone()).await.unwrap();
+        mempool.add_tx(tx3.clone()).await.unwrap();
+        assert_eq!(mempool.len().await, 3);
+        assert!(mempool.contains(&tx1.tx_hash()).await);
+
+        // Transactions come out in insertion order
+        let batch = mempool.take_batch(2).await;
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].tx_hash(), tx1.tx_hash());
+        assert_eq!(batch[1].tx_hash(), tx2.tx_hash());
+        assert!(!mempool.contains(&tx1.tx_hash()).await);
+
+        let pending = mempool.pending().await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].tx_hash(), tx3.tx_hash());
+
+        let batch = mempool.take_batch(10).await;
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].tx_hash(), tx3.tx_hash());
+        assert!(mempool.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_requeue() {
+        let mempool = Mempool::new();
+        let wallet = Wallet::random();
+        let txs: Vec<Tx> = (1..=3).map(|i| signed_tx(&wallet, i * 10)).collect();
+        for tx in &txs {
+            mempool.add_tx(tx.clone()).await.unwrap();
+        }
+
+        // The block only had room for the first, the other two go back ahead of newer txs
+        let mut batch = mempool.take_batch(3).await;
+        let leftovers = batch.split_off(1);
+        let newer = signed_tx(&wallet, 40);
+        mempool.add_tx(newer.clone()).await.unwrap();
+        mempool.requeue(leftovers).await;
+        assert!(mempool.contains(&txs[1].tx_hash()).await);
+
+        let hashes: Vec<Bytes> = mempool
+            .take_batch(10)
+            .await
+            .iter()
+            .map(Tx::tx_hash)
+            .collect();
+        assert_eq!(
+            hashes,
+            vec![txs[1].tx_hash(), txs[2].tx_hash(), newer.tx_hash()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signature_cache() {
+        let signature_cache = Arc::new(SignatureCache::new(16));
+        let mempool = Mempool::new().with_signature_cache(signature_cache.clone());
+        let wallet = Wallet::random();
+
+        let unsigned = Tx::new(wallet.address(), Address::ZERO, U256::from(10), None);
+        assert!(matches!(
+            mempool.add_tx(unsigned).await,
+            Err(MempoolError::InvalidSignature(_))
+        ));
+
+        let tx = signed_tx(&wallet, 10);
+        mempool.add_tx(tx.clone()).await.unwrap();
+        assert_eq!(signature_cache.misses(), 1);
+
+        // Executing it later finds the signer recovered
+        let batch = mempool.take_batch(1).await;
+        assert_eq!(signature_cache.verify_signature(&batch[0]), Ok(()));
+        assert_eq!(signature_cache.hits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_tx_rejected() {
+        let mempool = Mempool::new();
+        let wallet = Wallet::random();
+        let tx = signed_tx(&wallet, 10);
+
+        mempool.add_tx(tx.clone()).await.unwrap();
+        assert_eq!(
+            mempool.add_tx(tx).await.unwrap_err(),
+            MempoolError::AlreadyKnown
+        );
+        assert_eq!(mempool.len().await, 1);
+    }
+
+    fn order(wallet: &Wallet, sequence: u64) -> Tx {
+        let tx = Tx::transfer_order(
+            wallet.address(),
+            Address::ZERO,
+            U256::from(10),
+            sequence,
+            None,
+        );
+        let signature = wallet.sign_transaction_sync(tx.clone()).unwrap();
+        tx.with_signature(signature)
+    }
+
+    fn sequences(pending: &[Tx]) -> Vec<Option<u64>> {
+        pending.iter().map(|tx| tx.sequence()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_orders_wait_for_the_gap() {
+        let mempool = Mempool::new();
+        let wallet = Wallet::random();
+
+        mempool.add_tx(order(&wallet, 2)).await.unwrap();
+        mempool.add_tx(order(&wallet, 1)).await.unwrap();
+        assert!(mempool.is_empty().await);
+        assert_eq!(mempool.queued_len().await, 2);
+        assert_eq!(sequences(&mempool.queued().await), vec![Some(1), Some(2)]);
+        assert_eq!(mempool.next_sequence(&wallet.address()).await, 0);
+
+        // Filling the gap releases everything behind it, in order
+        mempool.add_tx(order(&wallet, 0)).await.unwrap();
+        assert_eq!(mempool.queued_len().await, 0);
+        assert_eq!(mempool.next_sequence(&wallet.address()).await, 3);
+        assert_eq!(
+            sequences(&mempool.take_batch(10).await),
+            vec![Some(0), Some(1), Some(2)]
+        );
+
+        // Sequence numbers already handed out are stale
+        assert_eq!(
+            mempool.add_tx(order(&wallet, 1)).await.unwrap_err(),
+            MempoolError::StaleSequence {
+                expected: 3,
+                got: 1
+            }
+        );
+        mempool.add_tx(order(&wallet, 3)).await.unwrap();
+        assert_eq!(mempool.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sequences_follow_the_chain() {
+        let chain = Arc::new(ShardedState::in_memory(2));
+        let mempool = Mempool::new().with_sequences(chain.clone());
+        let wallet = Wallet::random();
+        let mut account = state::account::Account::new(wallet.address(), U256::from(100));
+        account.set_sequence(5);
+        chain
+            .write_account(&wallet.address(), account.clone())
+            .unwrap();
+
+        assert!(matches!(
+            mempool.add_tx(order(&wallet, 4)).await,
+            Err(MempoolError::StaleSequence { expected: 5, .. })
+        ));
+        mempool.add_tx(order(&wallet, 7)).await.unwrap();
+        mempool.add_tx(order(&wallet, 8)).await.unwrap();
+        assert!(mempool.is_empty().await);
+
+        // The chain catching up through some other route releases the queued orders, those it
+        // overtook are dropped
+        account.set_sequence(8);
+        chain.write_account(&wallet.address(), account).unwrap();
+        assert_eq!(sequences(&mempool.take_batch(10).await), vec![Some(8)]);
+        assert_eq!(mempool.queued_len().await, 0);
+        assert!(!mempool.contains(&order(&wallet, 7).tx_hash()).await);
+    }
+
+    #[tokio::test]
+    async fn test_executed_txs_are_refused() {
+        let chain = Arc::new(ShardedState::in_memory(2));
+        let mempool = Mempool::new().with_sequences(chain.clone());
+        let wallet = Wallet::random();
+        let tx = signed_tx(&wallet, 10);
+        chain
+            .write_seen_tx(&B256::from_slice(&tx.tx_hash()), Some(4))
+            .unwrap();
+
+        assert_eq!(
+            mempool.add_tx(tx).await.unwrap_err(),
+            MempoolError::AlreadyExecuted { block: 4 }
+        );
+        mempool.add_tx(signed_tx(&wallet, 11)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_capacity_limits() {
+        let mempool = Mempool::with_config(MempoolConfig {
+            max_txs: 4,
+            max_queued_per_sender: 2,
+            ..Default::default()
+        });
+        let alice = Wallet::random();
+        let bob = Wallet::random();
+
+        mempool.add_tx(order(&alice, 3)).await.unwrap();
+        mempool.add_tx(order(&alice, 5)).await.unwrap();
+        assert_eq!(
+            mempool.add_tx(order(&alice, 4)).await.unwrap_err(),
+            MempoolError::SenderQueueFull
+        );
+        mempool.add_tx(order(&bob, 1)).await.unwrap();
+        mempool.add_tx(signed_tx(&bob, 10)).await.unwrap();
+
+        // Full: a tx further from executing than everything queued is refused
+        assert_eq!(
+            mempool.add_tx(order(&bob, 9)).await.unwrap_err(),
+            MempoolError::Full
+        );
+        // a closer one takes the place of alice's furthest order
+        mempool.add_tx(order(&bob, 2)).await.unwrap();
+        assert!(!mempool.contains(&order(&alice, 5).tx_hash()).await);
+        // and a ready tx always finds room while something is queued
+        mempool.add_tx(signed_tx(&alice, 10)).await.unwrap();
+        assert!(!mempool.contains(&order(&alice, 3).tx_hash()).await);
+        assert_eq!(mempool.len().await, 2);
+        assert_eq!(mempool.queued_len().await, 2);
+
+        // Filling bob's gap releases the order behind it, at the cost of the one after
+        mempool.add_tx(order(&bob, 0)).await.unwrap();
+        assert_eq!(mempool.queued_len().await, 0);
+        assert!(!mempool.contains(&order(&bob, 2).tx_hash()).await);
+
+        // Once only pending txs are left there is nothing to evict
+        assert_eq!(
+            mempool.add_tx(signed_tx(&alice, 20)).await.unwrap_err(),
+            MempoolError::Full
+        );
+        assert_eq!(
+            sequences(&mempool.take_batch(10).await),
+            vec![None, None, Some(0), Some(1)]
+        );
+    }
+
+    fn paying(wallet: &Wallet, sequence: Option<u64>, fee: u64) -> Tx {
+        let tx = match sequence {
+            Some(sequence) => Tx::transfer_order(
+                wallet.address(),
+                Address::ZERO,
+                U256::from(10),
+                sequence,
+                None,
+            ),
+            None => Tx::new(wallet.address(), Address::ZERO, U256::from(10), None),
+        }
+        .with_fee(fee);
+        let signature = wallet.sign_transaction_sync(tx.clone()).unwrap();
+        tx.with_signature(signature)
+    }
+
+    #[tokio::test]
+    async fn test_highest_fee_first() {
+        let mempool = Mempool::new();
+        let alice = Wallet::random();
+        let bob = Wallet::random();
+
+        mempool.add_tx(paying(&alice, None, 1)).await.unwrap();
+        mempool.add_tx(paying(&bob, None, 5)).await.unwrap();
+        mempool.add_tx(paying(&alice, None, 0)).await.unwrap();
+        mempool.add_tx(paying(&alice, None, 5)).await.unwrap();
+        // bob's second order pays the most but can't go before his first
+        mempool.add_tx(paying(&bob, Some(0), 2)).await.unwrap();
+        mempool.add_tx(paying(&bob, Some(1), 9)).await.unwrap();
+
+        let batch = mempool.take_batch(4).await;
+        let fees: Vec<_> = batch.iter().map(|tx| (tx.fee(), tx.sequence())).collect();
+        assert_eq!(fees, vec![(2, Some(0)), (5, None), (5, None), (9, Some(1))]);
+        assert_eq!(batch[1].from(), bob.address());
+
+        // The rest is still in the order it arrived
+        let fees: Vec<_> = mempool.pending().await.iter().map(|tx| tx.fee()).collect();
+        assert_eq!(fees, vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_fee_schedule() {
+        let mempool = Mempool::with_config(MempoolConfig {
+            fee_schedule: FeeSchedule::new(100, 1),
+            ..Default::default()
+        });
+        let alice = Wallet::random();
+
+        // 81 bytes of transfer and 65 of signature
+        assert_eq!(
+            mempool.add_tx(paying(&alice, None, 245)).await.unwrap_err(),
+            MempoolError::Underpriced {
+                required: 246,
+                got: 245
+            }
+        );
+        mempool.add_tx(paying(&alice, None, 246)).await.unwrap();
+        // A sequence number takes 8 bytes more
+        assert!(mempool.add_tx(paying(&alice, Some(0), 246)).await.is_err());
+        mempool.add_tx(paying(&alice, Some(0), 254)).await.unwrap();
+        assert_eq!(mempool.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_replace_by_fee() {
+        let mempool = Mempool::new();
+        let wallet = Wallet::random();
+
+        mempool.add_tx(paying(&wallet, Some(0), 100)).await.unwrap();
+        mempool.add_tx(paying(&wallet, Some(2), 0)).await.unwrap();
+
+        // Less than 10% more is refused
+        assert_eq!(
+            mempool
+                .add_tx(paying(&wallet, Some(0), 109))
+                .await
+                .unwrap_err(),
+            MempoolError::ReplacementUnderpriced {
+                required: 110,
+                got: 109
+            }
+        );
+
+        // Pending and queued txs can both be replaced
+        let mut pending = mempool.subscribe_pending_txs();
+        mempool.add_tx(paying(&wallet, Some(0), 110)).await.unwrap();
+        assert_eq!(pending.recv().await.unwrap().fee(), 110);
+        mempool.add_tx(paying(&wallet, Some(2), 1)).await.unwrap();
+        assert!(
+            !mempool
+                .contains(&paying(&wallet, Some(0), 100).tx_hash())
+                .await
+        );
+        assert!(
+            !mempool
+                .contains(&paying(&wallet, Some(2), 0).tx_hash())
+                .await
+        );
+        assert_eq!(mempool.len().await, 1);
+        assert_eq!(mempool.queued().await[0].fee(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_remove() {
+        let mempool = Mempool::new();
+        let wallet = Wallet::random();
+        let orders: Vec<Tx> = (0..3).map(|i| paying(&wallet, Some(i), 0)).collect();
+        for order in &orders {
+            mempool.add_tx(order.clone()).await.unwrap();
+        }
+        let queued = paying(&wallet, Some(5), 0);
+        mempool.add_tx(queued.clone()).await.unwrap();
+        assert_eq!(mempool.len().await, 3);
+
+        assert_eq!(
+            mempool.get(&queued.tx_hash()).await.unwrap().sequence(),
+            Some(5)
+        );
+        assert!(mempool.remove(&queued.tx_hash()).await.is_some());
+        assert!(mempool.get(&queued.tx_hash()).await.is_none());
+        assert!(mempool.remove(&queued.tx_hash()).await.is_none());
+
+        // The orders after a cancelled one wait for its sequence number again
+        let removed = mempool.remove(&orders[1].tx_hash()).await.unwrap();
+        assert_eq!(removed.sequence(), Some(1));
+        assert_eq!(mempool.len().await, 1);
+        assert_eq!(mempool.queued_len().await, 1);
+        assert_eq!(mempool.next_sequence(&wallet.address()).await, 1);
+
+        mempool.add_tx(paying(&wallet, Some(1), 7)).await.unwrap();
+        assert_eq!(mempool.len().await, 3);
+        assert_eq!(mempool.queued_len().await, 0);
+    }
+
+    #[test]
+    fn test_replacement_fee() {
+        assert_eq!(replacement_fee(0), 1);
+        assert_eq!(replacement_fee(5), 6);
+        assert_eq!(replacement_fee(100), 110);
+        assert_eq!(replacement_fee(101), 112);
+        assert_eq!(replacement_fee(u64::MAX), u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_pending_txs() {
+        let mempool = Mempool::new();
+        let mut pending = mempool.subscribe_pending_txs();
+
+        let wallet = Wallet::random();
+        let tx = signed_tx(&wallet, 10);
+        mempool.add_tx(tx.clone()).await.unwrap();
+
+        let received = pending.recv().await.unwrap();
+        assert_eq!(received.tx_hash(), tx.tx_hash());
+    }
+}
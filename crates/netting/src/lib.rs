@@ -0,0 +1,188 @@
+// netting authority: collects the intents of a window and, when the window closes, turns them into
+// one settlement tx carrying the net obligations. nodes only run it when configured with a key
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use tokio::sync::RwLock;
+use tx::netting::{net_obligations, SignedIntent};
+use tx::tx::Tx;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NettingError {
+    WrongWindow { current: u64, intent: u64 },
+    InvalidIntent(String),
+    AlreadyKnown,
+    Settlement(String),
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    number: u64,
+    intents: Vec<SignedIntent>,
+    hashes: HashSet<B256>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NettingEngine {
+    authority: Arc<PrivateKeySigner>,
+    window: Arc<RwLock<Window>>,
+}
+
+impl NettingEngine {
+    pub fn new(authority: PrivateKeySigner) -> Self {
+        Self {
+            authority: Arc::new(authority),
+            window: Arc::new(RwLock::new(Window::default())),
+        }
+    }
+
+    pub fn authority(&self) -> Address {
+        self.authority.address()
+    }
+
+    // the window intents are currently accepted for
+    pub async fn window(&self) -> u64 {
+        self.window.read().await.number
+    }
+
+    pub async fn submit(&self, signed: SignedIntent) -> Result<(), NettingError> {
+        signed.verify().map_err(NettingError::InvalidIntent)?;
+
+        let mut window = self.window.write().await;
+        if signed.intent.window != window.number {
+            return Err(NettingError::WrongWindow {
+                current: window.number,
+                intent: signed.intent.window,
+            });
+        }
+        if !window.hashes.insert(signed.intent.hash()) {
+            return Err(NettingError::AlreadyKnown);
+        }
+        window.intents.push(signed);
+        Ok(())
+    }
+
+    // ends the current window and returns its settlement, None when nobody submitted anything
+    pub async fn close_window(&self) -> Result<Option<Tx>, NettingError> {
+        let mut window = self.window.write().await;
+        let next = Window {
+            number: window.number + 1,
+            ..Window::default()
+        };
+        let closed = std::mem::replace(&mut *window, next);
+        if closed.intents.is_empty() {
+            return Ok(None);
+        }
+
+        let intents: Vec<_> = closed.intents.iter().map(|signed| signed.intent).collect();
+        let obligations = net_obligations(&intents).map_err(NettingError::Settlement)?;
+        let tx = Tx::settlement(
+            self.authority(),
+            closed.number,
+            closed.intents,
+            obligations,
+            None,
+        );
+        let signature = self
+            .authority
+            .sign_message_sync(&tx.tx_hash())
+            .map_err(|e| NettingError::Settlement(e.to_string()))?;
+        Ok(Some(tx.with_signature(signature)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tx::netting::Intent;
+
+    fn signed_intent(
+        signer: &PrivateKeySigner,
+        to: Address,
+        amount: u64,
+        window: u64,
+    ) -> SignedIntent {
+        let intent = Intent {
+            from: signer.address(),
+            to,
+            amount,
+            window,
+        };
+        SignedIntent {
+            intent,
+            signature: signer.sign_message_sync(intent.hash().as_slice()).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_window_nets_intents() {
+        let engine = NettingEngine::new(PrivateKeySigner::random());
+        let (a, b) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+
+        assert_eq!(engine.close_window().await.unwrap().map(|_| ()), None);
+        assert_eq!(engine.window().await, 1);
+
+        engine
+            .submit(signed_intent(&a, b.address(), 50, 1))
+            .await
+            .unwrap();
+        engine
+            .submit(signed_intent(&b, a.address(), 20, 1))
+            .await
+            .unwrap();
+
+        let tx = engine.close_window().await.unwrap().unwrap();
+        assert_eq!(engine.window().await, 2);
+        match &tx {
+            Tx::Settlement {
+                window,
+                intents,
+                obligations,
+                ..
+            } => {
+                assert_eq!(*window, 1);
+                assert_eq!(intents.len(), 2);
+                assert_eq!(obligations.len(), 1);
+                assert_eq!(obligations[0].from, a.address());
+                assert_eq!(obligations[0].amount, 30);
+            }
+            other => panic!("unexpected tx {:?}", other),
+        }
+        let signer = tx
+            .signature()
+            .unwrap()
+            .recover_address_from_msg(tx.tx_hash())
+            .unwrap();
+        assert_eq!(signer, engine.authority());
+    }
+
+    #[tokio::test]
+    async fn test_submit_rejects() {
+        let engine = NettingEngine::new(PrivateKeySigner::random());
+        let a = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+
+        assert_eq!(
+            engine.submit(signed_intent(&a, to, 5, 3)).await,
+            Err(NettingError::WrongWindow {
+                current: 0,
+                intent: 3
+            })
+        );
+
+        let signed = signed_intent(&a, to, 5, 0);
+        engine.submit(signed).await.unwrap();
+        assert_eq!(engine.submit(signed).await, Err(NettingError::AlreadyKnown));
+
+        let mut forged = signed;
+        forged.intent.amount = 6;
+        assert!(matches!(
+            engine.submit(forged).await,
+            Err(NettingError::InvalidIntent(_))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+// the node as an actor: it runs on a task of its own and is only reached through messages sent by
+// a NodeHandle, so nothing locks it and txs are executed in the order they arrive. The mempool,
+// block builder and rpc already are handles of their own, this makes the node one too. The task
+// stops once every handle is dropped
+
+use alloy::primitives::Address;
+use block_builder::{fork::ImportOutcome, Block, BlockBuilder};
+use committee::{certificate::Certificate, committee::Committee, store::CertificateStore};
+use state::{pending::PendingState, supply::TotalSupply};
+use tokio::sync::{mpsc, oneshot};
+use tx::tx::Tx;
+use vm::VMError;
+
+use crate::{archive::StateArchive, events::EventBus, Node};
+
+// how many messages can wait for the node before senders have to wait too
+const NODE_CHANNEL_CAPACITY: usize = 1024;
+
+type Reply<T> = oneshot::Sender<T>;
+
+enum NodeMessage {
+    SubmitTx(Tx, Reply<Result<(), VMError>>),
+    SettleCertificate(Certificate, Committee, Reply<Result<(), VMError>>),
+    ProduceBlock(Vec<Tx>, Address, Reply<anyhow::Result<Block>>),
+    ImportBlock(Box<Block>, Reply<anyhow::Result<ImportOutcome>>),
+    ExecutePending(PendingState, Vec<Tx>, Reply<()>),
+    TotalSupply(Reply<TotalSupply>),
+    CaptureSnapshot(Reply<anyhow::Result<StateArchive>>),
+    // anything else, run on the node's task
+    Inspect(Box<dyn FnOnce(&Node) + Send>),
+}
+
+#[derive(Clone)]
+pub struct NodeHandle {
+    sender: mpsc::Sender<NodeMessage>,
+    events: EventBus,
+    certificates: CertificateStore,
+}
+
+impl NodeHandle {
+    // moves `node` onto a task of its own, blocks are built and imported through `block_builder`
+    pub fn spawn(node: Node, block_builder: BlockBuilder) -> Self {
+        let (sender, receiver) = mpsc::channel(NODE_CHANNEL_CAPACITY);
+        let handle = Self {
+            sender,
+            events: node.events().clone(),
+            certificates: node.certificates().clone(),
+        };
+        tokio::spawn(run(node, block_builder, receiver));
+        handle
+    }
+
+    pub async fn submit_tx(&self, tx: Tx) -> Result<(), VMError> {
+        self.request(|reply| NodeMessage::SubmitTx(tx, reply)).await
+    }
+
+    pub async fn settle_certificate(
+        &self,
+        certificate: Certificate,
+        committee: Committee,
+    ) -> Result<(), VMError> {
+        self.request(|reply| NodeMessage::SettleCertificate(certificate, committee, reply))
+            .await
+    }
+
+    pub async fn produce_block(&self, txs: Vec<Tx>, miner: Address) -> anyhow::Result<Block> {
+        self.request(|reply| NodeMessage::ProduceBlock(txs, miner, reply))
+            .await
+    }
+
+    pub async fn import_block(&self, block: Block) -> anyhow::Result<ImportOutcome> {
+        self.request(|reply| NodeMessage::ImportBlock(Box::new(block), reply))
+            .await
+    }
+
+    pub async fn execute_pending(&self, pending: &PendingState, txs: Vec<Tx>) {
+        self.request(|reply| NodeMessage::ExecutePending(pending.clone(), txs, reply))
+            .await
+    }
+
+    pub async fn total_supply(&self) -> TotalSupply {
+        self.request(NodeMessage::TotalSupply).await
+    }
+
+    // the state at the head as an archive, captured between two blocks
+    pub async fn capture_snapshot(&self) -> anyhow::Result<StateArchive> {
+        self.request(NodeMessage::CaptureSnapshot).await
+    }
+
+    // runs `f` on the node's task, between two messages
+    pub async fn inspect<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Node) -> R + Send + 'static,
+    ) -> R {
+        self.request(|reply| {
+            NodeMessage::Inspect(Box::new(move |node| {
+                let _ = reply.send(f(node));
+            }))
+        })
+        .await
+    }
+
+    // the bus and the store are shared, they don't need a round trip through the node
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub fn certificates(&self) -> &CertificateStore {
+        &self.certificates
+    }
+
+    // the node only stops once every handle is gone, so a missing reply means it panicked
+    async fn request<T>(&self, message: impl FnOnce(Reply<T>) -> NodeMessage) -> T {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(message(reply))
+            .await
+            .expect("the node task stopped");
+        response.await.expect("the node task stopped")
+    }
+}
+
+// a requester that went away doesn't care about the reply, so failed sends are ignored
+async fn run(
+    mut node: Node,
+    block_builder: BlockBuilder,
+    mut receiver: mpsc::Receiver<NodeMessage>,
+) {
+    while let Some(message) = receiver.recv().await {
+        match message {
+            NodeMessage::SubmitTx(tx, reply) => {
+                let _ = reply.send(node.execute_tx(&tx));
+            }
+            NodeMessage::SettleCertificate(certificate, committee, reply) => {
+                let _ = reply.send(node.settle_certificate(&certificate, &committee));
+            }
+            NodeMessage::ProduceBlock(txs, miner, reply) => {
+                let _ = reply.send(node.produce_block(&block_builder, txs, miner).await);
+            }
+            NodeMessage::ImportBlock(block, reply) => {
+                let _ = reply.send(node.import_block(&block_builder, *block).await);
+            }
+            NodeMessage::ExecutePending(pending, txs, reply) => {
+                node.execute_pending(&pending, &txs);
+                let _ = reply.send(());
+            }
+            NodeMessage::TotalSupply(reply) => {
+                let _ = reply.send(node.total_supply());
+            }
+            NodeMessage::CaptureSnapshot(reply) => {
+                let next_number: u64 = block_builder
+                    .get_latest_block_number()
+                    .await
+                    .saturating_to();
+                let archive = match next_number.checked_sub(1) {
+                    Some(head) => node.capture_snapshot(&block_builder, head).await,
+                    None => Err(anyhow::anyhow!(
+                        "there are no blocks to capture the state at"
+                    )),
+                };
+                let _ = reply.send(archive);
+            }
+            NodeMessage::Inspect(f) => f(&node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use futures::StreamExt;
+    use state::{account::Account, memory::MemoryState, state::State};
+    use wallet::Wallet;
+
+    #[tokio::test]
+    async fn test_node_actor() {
+        let sender = Wallet::random();
+        let mut state = MemoryState::new();
+        state
+            .update_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(100)),
+            )
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+        let block_builder = BlockBuilder::new();
+        let node = NodeHandle::spawn(Node::new(Box::new(state)), block_builder.clone());
+        let mut blocks = Box::pin(node.events().blocks());
+
+        let recipient = Address::repeat_byte(9);
+        let tx = Tx::new(sender.address(), recipient, U256::from(30), None);
+        let signature = sender.sign_transaction_sync(tx.clone()).unwrap();
+        let tx = tx.with_signature(signature);
+
+        // Submitted from another task, through a clone of the handle
+        let submitter = node.clone();
+        let submitted = tokio::spawn(async move { submitter.submit_tx(tx).await.is_ok() });
+        assert!(submitted.await.unwrap());
+        // Unsigned, so the vm rejects it
+        let unsigned = Tx::new(sender.address(), recipient, U256::from(1), None);
+        assert!(node.submit_tx(unsigned).await.is_err());
+
+        let block = node.produce_block(Vec::new(), Address::ZERO).await.unwrap();
+        assert_eq!(blocks.next().await.unwrap().unwrap().hash, block.hash);
+        assert_eq!(
+            block_builder.get_latest_block().await.unwrap().hash,
+            block.hash
+        );
+
+        let balance = node
+            .inspect(move |node| node.state().get_account(&recipient).map(|a| a.balance()))
+            .await;
+        assert_eq!(balance, Some(U256::from(30)));
+        assert!(node.total_supply().await.is_balanced());
+    }
+}
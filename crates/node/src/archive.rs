@@ -0,0 +1,209 @@
+// the state at a block, for a new node to join the chain from instead of executing it from the
+// genesis. The archive carries the block's header, so the state is bound to the block's hash and
+// root, and the new node follows the chain from the block on. It is written as
+//
+//     magic | keccak256 of the body | body, gzipped JSON
+//
+// so a truncated or corrupted download is refused before anything is decompressed. Only the state
+// is in it, the txs the chain executed lately among it so the new node refuses their replays too.
+// The rules of the chain come from the genesis the new node already has
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use alloy::primitives::{keccak256, B256};
+use block_builder::Block;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use state::{memory::MemoryState, state::State};
+
+use crate::export::StateExport;
+use crate::genesis::{Genesis, GenesisAccount, GenesisChannel};
+
+const MAGIC: &[u8; 8] = b"fpstate1";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateArchive {
+    // without its txs, the state is the one after them
+    pub header: Block,
+    pub accounts: Vec<GenesisAccount>,
+    pub channels: Vec<GenesisChannel>,
+    // what State::seen_txs holds, sorted. Not under the state root, so only the checksum covers it
+    #[serde(default)]
+    pub seen_txs: Vec<(B256, u64)>,
+}
+
+impl StateArchive {
+    // `state` has to be the one `header` leads to
+    pub fn capture(state: &dyn State, header: Block) -> anyhow::Result<Self> {
+        let state_root = state.state_root();
+        // blocks sealed before state roots were recorded can't vouch for one
+        if header.state_root == B256::ZERO {
+            anyhow::bail!(
+                "block {} has no state root to bind the state to",
+                header.hash
+            );
+        }
+        if header.state_root != state_root {
+            anyhow::bail!(
+                "block {} has state root {} but the state is at {}",
+                header.hash,
+                header.state_root,
+                state_root
+            );
+        }
+        let export = StateExport::capture(state, None, &Genesis::default());
+        let mut seen_txs = state.seen_txs();
+        seen_txs.sort();
+        Ok(Self {
+            header: header.header(),
+            accounts: export.genesis.accounts,
+            channels: export.genesis.channels,
+            seen_txs,
+        })
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.header.number.saturating_to()
+    }
+
+    // writes the accounts and channels into `state`, which has to be empty
+    pub fn apply(&self, state: &mut dyn State) -> anyhow::Result<()> {
+        if !state.accounts().is_empty()
+            || !state.channels().is_empty()
+            || !state.seen_txs().is_empty()
+        {
+            anyhow::bail!("the state to restore into isn't empty");
+        }
+        self.genesis().apply(state)?;
+        for (hash, block) in &self.seen_txs {
+            state
+                .update_seen_tx(hash, Some(*block))
+                .map_err(|e| anyhow::anyhow!("failed to restore seen tx {}: {:?}", hash, e))?;
+        }
+        let state_root = state.state_root();
+        if state_root != self.header.state_root {
+            anyhow::bail!(
+                "archive of block {} restores to state root {} instead of {}",
+                self.header.hash,
+                state_root,
+                self.header.state_root
+            );
+        }
+        Ok(())
+    }
+
+    // the header hashes to what it claims and the state comes out at its root
+    pub fn verify(&self) -> anyhow::Result<()> {
+        if self.header.compute_hash() != self.header.hash {
+            anyhow::bail!("archive header doesn't hash to {}", self.header.hash);
+        }
+        self.apply(&mut MemoryState::new())
+    }
+
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        let body = encoder.finish()?;
+
+        let mut encoded = Vec::with_capacity(MAGIC.len() + 32 + body.len());
+        encoded.extend_from_slice(MAGIC);
+        encoded.extend_from_slice(keccak256(&body).as_slice());
+        encoded.extend_from_slice(&body);
+        Ok(encoded)
+    }
+
+    pub fn decode(encoded: &[u8]) -> anyhow::Result<Self> {
+        let rest = encoded
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| anyhow::anyhow!("not a state archive"))?;
+        if rest.len() < 32 {
+            anyhow::bail!("state archive is truncated");
+        }
+        let (checksum, body) = rest.split_at(32);
+        if keccak256(body).as_slice() != checksum {
+            anyhow::bail!("state archive doesn't match its checksum");
+        }
+        let mut json = Vec::new();
+        GzDecoder::new(body).read_to_end(&mut json)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::decode(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::File::create(path)?.write_all(&self.encode()?)?;
+        Ok(())
+    }
+
+    fn genesis(&self) -> Genesis {
+        Genesis {
+            accounts: self.accounts.clone(),
+            channels: self.channels.clone(),
+            ..Genesis::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use state::{account::Account, channel::Channel};
+
+    #[test]
+    fn test_encode_and_decode() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let mut state = MemoryState::new();
+        state
+            .update_account(&alice, Account::new(alice, U256::from(100)))
+            .unwrap();
+        state
+            .update_channel(
+                &B256::repeat_byte(9),
+                Some(Channel::new(alice, bob, U256::from(30), 10)),
+            )
+            .unwrap();
+        state
+            .update_seen_tx(&B256::repeat_byte(5), Some(2))
+            .unwrap();
+        let block = Block::new(U256::from(4), B256::repeat_byte(3), 0, Vec::new(), bob)
+            .with_state_root(state.state_root());
+
+        let archive = StateArchive::capture(&state, block.clone()).unwrap();
+        let encoded = archive.encode().unwrap();
+        let decoded = StateArchive::decode(&encoded).unwrap();
+        decoded.verify().unwrap();
+        assert_eq!(decoded.header.hash, block.hash);
+        assert_eq!(decoded.block_number(), 4);
+
+        let mut restored = MemoryState::new();
+        decoded.apply(&mut restored).unwrap();
+        assert_eq!(
+            restored.get_account(&alice).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_eq!(restored.seen_tx(&B256::repeat_byte(5)), Some(2));
+        // Only into an empty state
+        assert!(decoded.apply(&mut restored).is_err());
+
+        // A flipped byte or a cut off download is caught by the checksum
+        let mut corrupted = encoded.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(StateArchive::decode(&corrupted).is_err());
+        assert!(StateArchive::decode(&encoded[..encoded.len() - 1]).is_err());
+
+        // The state has to be the one the block says
+        let other = Block::new(U256::from(4), B256::ZERO, 0, Vec::new(), bob)
+            .with_state_root(B256::repeat_byte(1));
+        assert!(StateArchive::capture(&state, other).is_err());
+
+        let mut tampered = decoded;
+        tampered.accounts[0].balance += U256::from(1);
+        assert!(tampered.verify().is_err());
+    }
+}
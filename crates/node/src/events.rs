@@ -0,0 +1,144 @@
+// the node's event bus: blocks it accepts, transactions it executes and the scheduled payments it
+// makes, for Rust embedders that want to follow chain activity as streams. the node never waits on
+// a slow subscriber, one that falls more than EVENT_CHANNEL_CAPACITY events behind is told how
+// many it missed instead
+
+use block_builder::Block;
+use futures::{stream, Stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tx::tx::Tx;
+use vm::schedule::ScheduledPayment;
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lagged(pub u64);
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    blocks: broadcast::Sender<Block>,
+    transactions: broadcast::Sender<Tx>,
+    scheduled_payments: broadcast::Sender<ScheduledPayment>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (blocks, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (transactions, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (scheduled_payments, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            blocks,
+            transactions,
+            scheduled_payments,
+        }
+    }
+
+    // nobody listening is not an error
+    pub fn publish_block(&self, block: &Block) {
+        let _ = self.blocks.send(block.clone());
+    }
+
+    pub fn publish_transaction(&self, tx: &Tx) {
+        let _ = self.transactions.send(tx.clone());
+    }
+
+    pub fn publish_scheduled_payment(&self, payment: &ScheduledPayment) {
+        let _ = self.scheduled_payments.send(payment.clone());
+    }
+
+    // only sees what is published after the call
+    pub fn blocks(&self) -> impl Stream<Item = Result<Block, Lagged>> {
+        into_stream(self.blocks.subscribe())
+    }
+
+    pub fn transactions(&self) -> impl Stream<Item = Result<Tx, Lagged>> {
+        into_stream(self.transactions.subscribe())
+    }
+
+    // missed payments too, with why they were missed
+    pub fn scheduled_payments(&self) -> impl Stream<Item = Result<ScheduledPayment, Lagged>> {
+        into_stream(self.scheduled_payments.subscribe())
+    }
+
+    // the channel itself, for subscribers that read a broadcast receiver, like the rpc's payment
+    // subscriptions
+    pub fn scheduled_payment_channel(&self) -> broadcast::Sender<ScheduledPayment> {
+        self.scheduled_payments.clone()
+    }
+}
+
+fn into_stream<T: Clone + Send + 'static>(
+    receiver: broadcast::Receiver<T>,
+) -> impl Stream<Item = Result<T, Lagged>> {
+    stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(item) => Some((Ok(item), receiver)),
+            Err(RecvError::Lagged(missed)) => Some((Err(Lagged(missed)), receiver)),
+            Err(RecvError::Closed) => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_streams() {
+        let bus = EventBus::new();
+        let blocks = bus.blocks();
+        let transactions = bus.transactions();
+        futures::pin_mut!(blocks, transactions);
+
+        let tx = Tx::new(Address::ZERO, Address::repeat_byte(1), U256::from(5), None);
+        let block = Block::new(
+            U256::ZERO,
+            Default::default(),
+            0,
+            vec![tx.clone()],
+            Address::ZERO,
+        );
+        bus.publish_transaction(&tx);
+        bus.publish_block(&block);
+
+        assert_eq!(
+            transactions.next().await.unwrap().unwrap().tx_hash(),
+            tx.tx_hash()
+        );
+        assert_eq!(blocks.next().await.unwrap().unwrap().hash, block.hash);
+
+        // Closed once the bus is gone
+        drop(bus);
+        assert!(blocks.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscribers_are_told_what_they_missed() {
+        let bus = EventBus::new();
+        let transactions = bus.transactions();
+        futures::pin_mut!(transactions);
+
+        for amount in 0..EVENT_CHANNEL_CAPACITY as u64 + 3 {
+            bus.publish_transaction(&Tx::new(
+                Address::ZERO,
+                Address::ZERO,
+                U256::from(amount),
+                None,
+            ));
+        }
+
+        assert_eq!(transactions.next().await.unwrap().unwrap_err(), Lagged(3));
+        assert_eq!(
+            transactions.next().await.unwrap().unwrap().amount(),
+            U256::from(3)
+        );
+    }
+}
@@ -0,0 +1,161 @@
+// a dump of the whole state, for backups and for migrating a ledger to a new chain. Unlike a
+// snapshot it keeps everything the state root commits to, multisigs and channels included, and
+// it is written as a genesis: importing it starts a chain whose first state is the exported one
+
+use std::path::Path;
+
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+use state::{memory::MemoryState, state::State};
+
+use crate::genesis::{Genesis, GenesisAccount, GenesisChannel, GenesisMultisig};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateExport {
+    // the number of the last block executed, None before the first one
+    pub block: Option<u64>,
+    pub state_root: B256,
+    #[serde(flatten)]
+    pub genesis: Genesis,
+}
+
+impl StateExport {
+    // the accounts and channels come from `state`, the rules of the chain from `genesis`
+    pub fn capture(state: &dyn State, block: Option<u64>, genesis: &Genesis) -> Self {
+        let mut accounts: Vec<_> = state
+            .accounts()
+            .into_iter()
+            .filter(|account| !account.is_empty())
+            .map(|account| GenesisAccount {
+                address: account.get_address(),
+                balance: account.balance(),
+                sequence: account.sequence(),
+                multisig: account.multisig().map(|multisig| GenesisMultisig {
+                    owners: multisig.owners().to_vec(),
+                    threshold: multisig.threshold(),
+                }),
+                module: account.module().map(str::to_string),
+                label: account.label().map(str::to_string),
+                created_at: account.created_at(),
+                policy: account.policy().cloned(),
+                spending: account.spending(),
+                schedules: account.schedules().to_vec(),
+                escrows: account.escrows().to_vec(),
+                htlcs: account.htlcs().to_vec(),
+                assets: account.assets().clone(),
+            })
+            .collect();
+        accounts.sort_by_key(|account| account.address);
+        let mut channels: Vec<_> = state
+            .channels()
+            .into_iter()
+            .map(|(id, channel)| GenesisChannel {
+                id,
+                payer: channel.payer(),
+                payee: channel.payee(),
+                deposit: channel.deposit(),
+                challenge_period: channel.challenge_period(),
+                expires_at: channel.expires_at(),
+            })
+            .collect();
+        channels.sort_by_key(|channel| channel.id);
+
+        Self {
+            block,
+            state_root: state.state_root(),
+            genesis: Genesis {
+                accounts,
+                channels,
+                ..genesis.clone()
+            },
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // rebuilds the state from the export, it has to come out at the root the export claims
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let mut state = MemoryState::new();
+        self.genesis.apply(&mut state)?;
+        let state_root = state.state_root();
+        if state_root != self.state_root {
+            anyhow::bail!(
+                "export claims state root {} but importing it gives {}",
+                self.state_root,
+                state_root
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use state::{
+        account::{Account, Multisig},
+        channel::Channel,
+        policy::{DailySpending, SpendingPolicy},
+    };
+
+    #[test]
+    fn test_export_and_import() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let mut state = MemoryState::new();
+        let mut account = Account::new(alice, U256::from(100)).with_policy(SpendingPolicy {
+            daily_limit: Some(U256::from(50)),
+            ..SpendingPolicy::default()
+        });
+        account.set_sequence(4);
+        account.set_spending(DailySpending::default().add(2, U256::from(20)));
+        state.update_account(&alice, account).unwrap();
+        let multisig = Multisig::new(vec![alice, bob], 2).unwrap();
+        state
+            .update_account(
+                &multisig.address(),
+                Account::new(multisig.address(), U256::ZERO).with_multisig(multisig),
+            )
+            .unwrap();
+        let mut channel = Channel::new(alice, bob, U256::from(30), 10);
+        channel.start_timeout(5);
+        state
+            .update_channel(&B256::repeat_byte(9), Some(channel))
+            .unwrap();
+
+        let genesis = Genesis {
+            chain_id: Some(7),
+            ..Genesis::default()
+        };
+        let export = StateExport::capture(&state, Some(12), &genesis);
+        assert_eq!(export.genesis.chain_id(), 7);
+        assert_eq!(export.genesis.accounts.len(), 2);
+
+        let path = std::env::temp_dir().join(format!("state-export-{}.json", std::process::id()));
+        export.save(&path).unwrap();
+        let loaded = StateExport::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded, export);
+        loaded.verify().unwrap();
+
+        // Starting a chain from it gives back the exact state
+        let mut imported = MemoryState::new();
+        loaded.genesis.apply(&mut imported).unwrap();
+        assert_eq!(imported.state_root(), state.state_root());
+        assert_eq!(imported.get_account(&alice).unwrap().sequence(), 4);
+
+        let mut tampered = export;
+        tampered.genesis.accounts[0].balance += U256::from(1);
+        assert!(tampered.verify().is_err());
+    }
+}
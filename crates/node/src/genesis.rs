@@ -0,0 +1,496 @@
+// genesis describes the accounts a chain starts with
+
+use std::{collections::BTreeMap, path::Path};
+
+use alloy::primitives::{Address, B256, U256};
+use block_builder::{
+    producers::ProducerSet,
+    schedule::{ProducerSchedule, Rotation},
+};
+use committee::committee::Committee;
+use serde::{Deserialize, Serialize};
+use state::{
+    account::{Account, AccountKind, Multisig},
+    channel::Channel,
+    escrow::Escrow,
+    htlc::Htlc,
+    policy::{DailySpending, SpendingPolicy},
+    schedule::Schedule,
+    state::State,
+};
+use tx::{asset::AssetId, eip712::CHAIN_ID, fee::FeeSchedule};
+use vm::dust::{DustMode, DustPolicy};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAccount {
+    pub address: Address,
+    pub balance: U256,
+    // only set for chains started from a state export, so old transfer orders can't be replayed
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub sequence: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<GenesisMultisig>,
+    // the module an account is reserved for, it can't also be a multisig
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    // the block the account was created in, kept by state exports
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<SpendingPolicy>,
+    // what was spent under the policy, kept by state exports
+    #[serde(default, skip_serializing_if = "DailySpending::is_empty")]
+    pub spending: DailySpending,
+    // recurring payments set up before the export, see state::schedule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<Schedule>,
+    // funds locked in escrows before the export, they count towards the supply
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub escrows: Vec<Escrow>,
+    // funds locked for the account under a hashlock, they count towards the supply too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub htlcs: Vec<Htlc>,
+    // balances of issued assets, by asset id. They don't count towards the native supply
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<AssetId, U256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisMultisig {
+    pub owners: Vec<Address>,
+    pub threshold: u64,
+}
+
+// a channel open from the start, only chains started from a state export have them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisChannel {
+    pub id: B256,
+    pub payer: Address,
+    pub payee: Address,
+    pub deposit: U256,
+    pub challenge_period: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisProducers {
+    pub producers: Vec<Address>,
+    pub threshold: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAuthority {
+    pub address: Address,
+    pub stake: u64,
+}
+
+// the committee producing blocks in turns
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisSchedule {
+    pub committee: Vec<GenesisAuthority>,
+    #[serde(default)]
+    pub rotation: Rotation,
+    // seconds before the next producer in line may take over a block
+    pub timeout: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Genesis {
+    pub accounts: Vec<GenesisAccount>,
+    // transfers can't leave an account with less than this, 0 for no minimum
+    #[serde(default, skip_serializing_if = "U256::is_zero")]
+    pub min_balance: U256,
+    // with a minimum, dust a transfer would leave to its sender goes along to the recipient
+    // instead of failing the transfer
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sweep_dust: bool,
+    // what every transfer pays at least, and what it pays more per byte, see fee_schedule()
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub base_fee: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub fee_per_byte: u64,
+    // the chain txs have to be signed for, see chain_id()
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    // the only account allowed to mint and burn, None when the supply is fixed at genesis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<Address>,
+    // credits deposits made to the bridge contract on the primary chain, None without a bridge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge_operator: Option<Address>,
+    // blocks have to be signed by enough of these, see producer_set()
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producers: Option<GenesisProducers>,
+    // who has to sign each block, see producer_schedule()
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<GenesisSchedule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<GenesisChannel>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl Genesis {
+    pub fn new(accounts: Vec<GenesisAccount>) -> Self {
+        Self {
+            accounts,
+            ..Self::default()
+        }
+    }
+
+    pub fn dust_policy(&self) -> DustPolicy {
+        let mode = if self.sweep_dust {
+            DustMode::Sweep
+        } else {
+            DustMode::Reject
+        };
+        DustPolicy::new(self.min_balance, mode)
+    }
+
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        FeeSchedule::new(self.base_fee, self.fee_per_byte)
+    }
+
+    // chains from before chain ids get the default one
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id.unwrap_or(CHAIN_ID)
+    }
+
+    // None when any block is accepted unsigned
+    pub fn producer_set(&self) -> anyhow::Result<Option<ProducerSet>> {
+        self.producers
+            .as_ref()
+            .map(|p| {
+                ProducerSet::new(p.producers.clone(), p.threshold)
+                    .map_err(|e| anyhow::anyhow!("invalid genesis producers: {}", e))
+            })
+            .transpose()
+    }
+
+    // None when blocks aren't produced in turns
+    pub fn producer_schedule(&self) -> anyhow::Result<Option<ProducerSchedule>> {
+        self.schedule
+            .as_ref()
+            .map(|schedule| {
+                let committee = Committee::new(
+                    schedule
+                        .committee
+                        .iter()
+                        .map(|authority| (authority.address, authority.stake)),
+                );
+                ProducerSchedule::from_committee(&committee, schedule.rotation, schedule.timeout)
+                    .map_err(|e| anyhow::anyhow!("invalid genesis schedule: {}", e))
+            })
+            .transpose()
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // adds `amount` to the account, creating it if needed
+    pub fn fund(&mut self, address: Address, amount: U256) {
+        match self.accounts.iter_mut().find(|a| a.address == address) {
+            Some(account) => account.balance = account.balance.saturating_add(amount),
+            None => self.accounts.push(GenesisAccount {
+                address,
+                balance: amount,
+                sequence: 0,
+                multisig: None,
+                module: None,
+                label: None,
+                created_at: 0,
+                policy: None,
+                spending: DailySpending::default(),
+                schedules: Vec::new(),
+                escrows: Vec::new(),
+                htlcs: Vec::new(),
+                assets: BTreeMap::new(),
+            }),
+        }
+    }
+
+    // adds a multisig account at the address derived from its owners, and returns that address
+    pub fn add_multisig(
+        &mut self,
+        owners: Vec<Address>,
+        threshold: u64,
+        balance: U256,
+    ) -> anyhow::Result<Address> {
+        let address = Multisig::new(owners.clone(), threshold)
+            .map_err(|e| anyhow::anyhow!(e))?
+            .address();
+        self.accounts.push(GenesisAccount {
+            address,
+            balance,
+            sequence: 0,
+            multisig: Some(GenesisMultisig { owners, threshold }),
+            module: None,
+            label: None,
+            created_at: 0,
+            policy: None,
+            spending: DailySpending::default(),
+            schedules: Vec::new(),
+            escrows: Vec::new(),
+            htlcs: Vec::new(),
+            assets: BTreeMap::new(),
+        });
+        Ok(address)
+    }
+
+    // the total supply starts as everything the accounts hold and the channels, escrows and htlcs
+    // have locked
+    pub fn apply(&self, state: &mut dyn State) -> anyhow::Result<()> {
+        let supply = self
+            .accounts
+            .iter()
+            .flat_map(|account| {
+                std::iter::once(account.balance)
+                    .chain(account.escrows.iter().map(|escrow| escrow.amount))
+                    .chain(account.htlcs.iter().map(|htlc| htlc.amount))
+            })
+            .chain(self.channels.iter().map(|channel| channel.deposit))
+            .try_fold(U256::ZERO, |supply, amount| supply.checked_add(amount))
+            .ok_or_else(|| anyhow::anyhow!("genesis holds more than U256::MAX in total"))?;
+        for account in &self.accounts {
+            let mut state_account =
+                Account::new(account.address, account.balance).with_created_at(account.created_at);
+            state_account.set_sequence(account.sequence);
+            state_account.set_label(account.label.clone());
+            state_account.set_policy(account.policy.clone());
+            if state_account.policy().is_some() {
+                state_account.set_spending(account.spending);
+            }
+            state_account.set_schedules(account.schedules.clone());
+            state_account.set_escrows(account.escrows.clone());
+            state_account.set_htlcs(account.htlcs.clone());
+            for (asset, balance) in &account.assets {
+                state_account.set_asset_balance(*asset, *balance);
+            }
+            if account.multisig.is_some() && account.module.is_some() {
+                anyhow::bail!(
+                    "invalid genesis account {}: it can't be both a multisig and a module",
+                    account.address
+                );
+            }
+            if let Some(module) = &account.module {
+                state_account = state_account.with_kind(AccountKind::Module(module.clone()));
+            }
+            if let Some(multisig) = &account.multisig {
+                let multisig =
+                    Multisig::new(multisig.owners.clone(), multisig.threshold).map_err(|e| {
+                        anyhow::anyhow!("invalid genesis account {}: {}", account.address, e)
+                    })?;
+                state_account = state_account.with_multisig(multisig);
+            }
+            state
+                .update_account(&account.address, state_account)
+                .map_err(|e| anyhow::anyhow!("failed to apply genesis: {:?}", e))?;
+        }
+        for channel in &self.channels {
+            let mut state_channel = Channel::new(
+                channel.payer,
+                channel.payee,
+                channel.deposit,
+                channel.challenge_period,
+            );
+            if let Some(expires_at) = channel.expires_at {
+                let started_at = expires_at
+                    .checked_sub(channel.challenge_period)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("genesis channel {} expires before it started", channel.id)
+                    })?;
+                state_channel.start_timeout(started_at);
+            }
+            state
+                .update_channel(&channel.id, Some(state_channel))
+                .map_err(|e| anyhow::anyhow!("failed to apply genesis: {:?}", e))?;
+        }
+        state
+            .set_total_supply(supply)
+            .map_err(|e| anyhow::anyhow!("failed to apply genesis: {:?}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::memory::MemoryState;
+
+    #[test]
+    fn test_fund_and_apply() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+
+        let mut genesis = Genesis::default();
+        genesis.fund(alice, U256::from(100));
+        genesis.fund(bob, U256::from(5));
+        genesis.fund(alice, U256::from(50));
+        assert_eq!(genesis.accounts.len(), 2);
+
+        let mut state = MemoryState::new();
+        genesis.apply(&mut state).unwrap();
+        assert_eq!(
+            state.get_account(&alice).unwrap().balance(),
+            U256::from(150)
+        );
+        assert_eq!(state.get_account(&bob).unwrap().balance(), U256::from(5));
+        assert_eq!(state.total_supply(), U256::from(155));
+
+        // Escrowed funds are part of the supply too
+        genesis.accounts[1].escrows.push(Escrow {
+            id: B256::repeat_byte(1),
+            to: alice,
+            arbiter: Address::repeat_byte(3),
+            amount: U256::from(10),
+            expires_at: 20,
+        });
+        let mut state = MemoryState::new();
+        genesis.apply(&mut state).unwrap();
+        assert_eq!(state.total_supply(), U256::from(165));
+        assert_eq!(state.get_account(&bob).unwrap().escrowed(), U256::from(10));
+
+        // A supply that doesn't fit is refused
+        genesis.fund(bob, U256::MAX);
+        assert!(genesis.apply(&mut MemoryState::new()).is_err());
+    }
+
+    #[test]
+    fn test_multisig() {
+        let owners = vec![Address::repeat_byte(1), Address::repeat_byte(2)];
+        let mut genesis = Genesis::default();
+        assert!(genesis
+            .add_multisig(owners.clone(), 3, U256::from(100))
+            .is_err());
+        let address = genesis
+            .add_multisig(owners.clone(), 2, U256::from(100))
+            .unwrap();
+
+        let mut state = MemoryState::new();
+        genesis.apply(&mut state).unwrap();
+        let account = state.get_account(&address).unwrap();
+        assert_eq!(account.balance(), U256::from(100));
+        assert_eq!(account.multisig().unwrap().owners(), owners.as_slice());
+
+        let json = serde_json::to_string(&genesis).unwrap();
+        assert_eq!(serde_json::from_str::<Genesis>(&json).unwrap(), genesis);
+        // Plain accounts don't mention multisigs
+        genesis.fund(Address::repeat_byte(3), U256::from(1));
+        assert!(!serde_json::to_string(&genesis.accounts[1])
+            .unwrap()
+            .contains("multisig"));
+    }
+
+    #[test]
+    fn test_module_and_label() {
+        let address = Address::repeat_byte(1);
+        let mut genesis = Genesis::default();
+        genesis.fund(address, U256::from(100));
+        genesis.accounts[0].module = Some("bridge".to_string());
+        genesis.accounts[0].label = Some("bridge escrow".to_string());
+        genesis.accounts[0].created_at = 7;
+
+        let mut state = MemoryState::new();
+        genesis.apply(&mut state).unwrap();
+        let account = state.get_account(&address).unwrap();
+        assert_eq!(account.module(), Some("bridge"));
+        assert_eq!(account.label(), Some("bridge escrow"));
+        assert_eq!(account.created_at(), 7);
+
+        let json = serde_json::to_string(&genesis).unwrap();
+        assert_eq!(serde_json::from_str::<Genesis>(&json).unwrap(), genesis);
+
+        // An account is either a multisig or a module
+        genesis.accounts[0].multisig = Some(GenesisMultisig {
+            owners: vec![Address::repeat_byte(2)],
+            threshold: 1,
+        });
+        assert!(genesis.apply(&mut MemoryState::new()).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("genesis-{}.json", std::process::id()));
+
+        let mut genesis = Genesis::default();
+        genesis.fund(Address::repeat_byte(1), U256::from(100));
+        genesis.save(&path).unwrap();
+
+        assert_eq!(Genesis::load(&path).unwrap(), genesis);
+
+        genesis.min_balance = U256::from(10);
+        genesis.sweep_dust = true;
+        genesis.chain_id = Some(7);
+        genesis.fee_per_byte = 2;
+        genesis.issuer = Some(Address::repeat_byte(9));
+        genesis.save(&path).unwrap();
+        assert_eq!(
+            Genesis::load(&path).unwrap().issuer,
+            Some(Address::repeat_byte(9))
+        );
+        assert_eq!(Genesis::load(&path).unwrap().chain_id(), 7);
+        assert_eq!(
+            Genesis::load(&path).unwrap().fee_schedule(),
+            FeeSchedule::new(0, 2)
+        );
+        assert_eq!(
+            Genesis::load(&path).unwrap().dust_policy(),
+            DustPolicy::new(U256::from(10), DustMode::Sweep)
+        );
+        std::fs::remove_file(path).unwrap();
+
+        // Genesis files written before the dust policy have none
+        let genesis: Genesis = serde_json::from_str(r#"{"accounts":[]}"#).unwrap();
+        assert_eq!(genesis.dust_policy(), DustPolicy::default());
+        assert_eq!(genesis.fee_schedule(), FeeSchedule::default());
+        assert_eq!(genesis.chain_id(), CHAIN_ID);
+        assert_eq!(genesis.producer_set().unwrap(), None);
+        assert_eq!(genesis.producer_schedule().unwrap(), None);
+    }
+
+    #[test]
+    fn test_producers() {
+        let mut genesis = Genesis {
+            producers: Some(GenesisProducers {
+                producers: vec![Address::repeat_byte(1), Address::repeat_byte(2)],
+                threshold: 2,
+            }),
+            ..Genesis::default()
+        };
+        assert_eq!(genesis.producer_set().unwrap().unwrap().threshold(), 2);
+
+        genesis.producers.as_mut().unwrap().threshold = 3;
+        assert!(genesis.producer_set().is_err());
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut genesis: Genesis = serde_json::from_str(
+            r#"{"accounts":[],"schedule":{"committee":[
+                {"address":"0x0101010101010101010101010101010101010101","stake":3},
+                {"address":"0x0202020202020202020202020202020202020202","stake":1}
+            ],"rotation":"stakeWeighted","timeout":10}}"#,
+        )
+        .unwrap();
+        let schedule = genesis.producer_schedule().unwrap().unwrap();
+        assert_eq!(schedule.producer_for(2, 0), Address::repeat_byte(1));
+        assert_eq!(schedule.producer_for(3, 0), Address::repeat_byte(2));
+        assert_eq!(schedule.timeout(), 10);
+
+        genesis.schedule.as_mut().unwrap().timeout = 0;
+        assert!(genesis.producer_schedule().is_err());
+    }
+}
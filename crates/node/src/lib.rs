@@ -1,29 +1,428 @@
-use state::{memory::MemoryState, state::State};
-use tx::tx::Tx;
-use vm::{VMError, VM};
+use std::path::Path;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256, U256};
+use archive::StateArchive;
+use block_builder::{fork::ImportOutcome, merkle::receipts_root, Block, BlockBuilder};
+use committee::{certificate::Certificate, committee::Committee, store::CertificateStore};
+use events::{EventBus, Lagged};
+use futures::Stream;
+use genesis::Genesis;
+use state::{pending::PendingState, state::State, supply::TotalSupply};
+use tx::{fee::FeeSchedule, log::logs_bloom, signature_cache::SignatureCache, tx::Tx};
+use vm::{
+    dust::DustPolicy, schedule::ScheduledPayment, simulator::Simulator, validator::TxValidator,
+    Receipt, VMError, VM,
+};
+
+pub mod actor;
+pub mod archive;
+pub mod events;
+pub mod export;
+pub mod genesis;
+pub mod snapshot;
 
 pub struct Node {
     vm: VM,
+    events: EventBus,
+    certificates: CertificateStore,
+    // audits the total supply after every block and panics if it's off, for testing
+    check_supply: bool,
 }
 
 impl Node {
     pub fn new(state: Box<dyn State>) -> Self {
         let vm = VM::new(state);
-        Self { vm }
+        Self {
+            vm,
+            events: EventBus::new(),
+            certificates: CertificateStore::new(),
+            check_supply: false,
+        }
+    }
+
+    // a node executing under the rules `genesis` sets, on a state the genesis was applied to
+    pub fn from_genesis(state: Box<dyn State>, genesis: &Genesis) -> Self {
+        let mut node = Self::new(state)
+            .with_dust_policy(genesis.dust_policy())
+            .with_fee_schedule(genesis.fee_schedule())
+            .with_chain_id(genesis.chain_id());
+        if let Some(issuer) = genesis.issuer {
+            node = node.with_issuer(issuer);
+        }
+        if let Some(operator) = genesis.bridge_operator {
+            node = node.with_bridge_operator(operator);
+        }
+        node
+    }
+
+    // part of the rules of the chain, every node executing it needs the same one
+    pub fn with_dust_policy(mut self, dust_policy: DustPolicy) -> Self {
+        self.vm = self.vm.with_dust_policy(dust_policy);
+        self
+    }
+
+    // also part of the rules of the chain, the mempool should refuse txs with the same one
+    pub fn with_fee_schedule(mut self, fee_schedule: FeeSchedule) -> Self {
+        self.vm = self.vm.with_fee_schedule(fee_schedule);
+        self
+    }
+
+    // txs signed for another chain are rejected
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.vm = self.vm.with_chain_id(chain_id);
+        self
+    }
+
+    // the only account allowed to mint and burn
+    pub fn with_issuer(mut self, issuer: Address) -> Self {
+        self.vm = self.vm.with_issuer(issuer);
+        self
+    }
+
+    // the only account allowed to credit deposits made on the primary chain
+    pub fn with_bridge_operator(mut self, operator: Address) -> Self {
+        self.vm = self.vm.with_bridge_operator(operator);
+        self
+    }
+
+    // shared with the mempool, so txs it admitted aren't recovered again to be executed
+    pub fn with_signature_cache(mut self, signature_cache: Arc<SignatureCache>) -> Self {
+        self.vm = self.vm.with_signature_cache(signature_cache);
+        self
+    }
+
+    // walks the whole state after every block, too slow for a node under load
+    pub fn with_supply_check(mut self) -> Self {
+        self.check_supply = true;
+        self
+    }
+
+    // extra checks on every tx, blocks whose txs fail them are not imported either
+    pub fn with_validator(mut self, validator: impl TxValidator + 'static) -> Self {
+        self.vm = self.vm.with_validator(validator);
+        self
     }
 
     pub fn execute_tx(&mut self, tx: &Tx) -> Result<(), VMError> {
-        self.vm.execute(tx)
+        self.vm.execute(tx)?;
+        self.events.publish_transaction(tx);
+        Ok(())
+    }
+
+    // the number of the block the next txs are executed in. The scheduled payments due in it are
+    // made first, see vm::schedule
+    pub fn set_current_block(&mut self, block: u64) {
+        self.vm.set_current_block(block);
+        for payment in self.vm.execute_due_payments() {
+            self.events.publish_scheduled_payment(&payment);
+        }
+    }
+
+    pub fn execute_batch(&mut self, txs: &[Tx]) -> Vec<Result<Receipt, VMError>> {
+        let results = self.vm.execute_batch(txs);
+        for (tx, result) in txs.iter().zip(&results) {
+            if result.is_ok() {
+                self.events.publish_transaction(tx);
+            }
+        }
+        results
+    }
+
+    // executes `txs` as if they made it into the next block, on a fresh overlay over `pending`'s
+    // committed state, and swaps the outcome into `pending`. Nothing of the node's state changes
+    // and no events are published
+    pub fn execute_pending(&self, pending: &PendingState, txs: &[Tx]) {
+        let overlay = pending.fresh();
+        let mut vm = VM::new(Box::new(overlay.clone()))
+            .with_dust_policy(self.vm.dust_policy())
+            .with_fee_schedule(self.vm.fee_schedule())
+            .with_validators(self.vm.validators().to_vec());
+        if let Some(chain_id) = self.vm.chain_id() {
+            vm = vm.with_chain_id(chain_id);
+        }
+        if let Some(issuer) = self.vm.issuer() {
+            vm = vm.with_issuer(issuer);
+        }
+        if let Some(operator) = self.vm.bridge_operator() {
+            vm = vm.with_bridge_operator(operator);
+        }
+        vm.set_current_block(self.vm.current_block() + 1);
+        vm.execute_due_payments();
+        vm.execute_batch(txs);
+        pending.replace_with(&overlay);
+    }
+
+    // dry runs on `pending` under the rules of this node, for eth_call
+    pub fn simulator(&self, pending: PendingState) -> Simulator {
+        self.vm.simulator(pending)
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    // blocks the node accepted, whether it built them or imported them
+    pub fn blocks(&self) -> impl Stream<Item = Result<Block, Lagged>> {
+        self.events.blocks()
+    }
+
+    // transactions the node executed successfully
+    pub fn transactions(&self) -> impl Stream<Item = Result<Tx, Lagged>> {
+        self.events.transactions()
+    }
+
+    // payments made, or missed, for schedules as their blocks are executed
+    pub fn scheduled_payments(&self) -> impl Stream<Item = Result<ScheduledPayment, Lagged>> {
+        self.events.scheduled_payments()
+    }
+
+    pub fn state(&self) -> &dyn State {
+        self.vm.state()
+    }
+
+    // executes `txs` in the block after the builder's latest and seals the ones that succeed,
+    // with their logs and the state root they lead to
+    pub async fn produce_block(
+        &mut self,
+        block_builder: &BlockBuilder,
+        txs: Vec<Tx>,
+        miner: Address,
+    ) -> anyhow::Result<Block> {
+        self.set_current_block(
+            block_builder
+                .get_latest_block_number()
+                .await
+                .saturating_to(),
+        );
+        let results = self.execute_batch(&txs);
+
+        let (included, logs): (Vec<_>, Vec<_>) = txs
+            .into_iter()
+            .zip(results)
+            .filter_map(|(tx, result)| result.ok().map(|receipt| (tx, receipt.logs().to_vec())))
+            .unzip();
+
+        let block = block_builder
+            .create_block_with_logs(included, miner, self.state().state_root(), logs)
+            .await?;
+        self.assert_supply(&block);
+        self.events.publish_block(&block);
+        Ok(block)
+    }
+
+    // checks a block built by someone else before accepting it: the header through the block
+    // builder, then its transactions all have to succeed and lead to the state root the block
+    // claims
+    pub async fn import_block(
+        &mut self,
+        block_builder: &BlockBuilder,
+        block: Block,
+    ) -> anyhow::Result<ImportOutcome> {
+        if block_builder.get_block_by_hash(block.hash).await.is_some() {
+            return Ok(ImportOutcome::Known);
+        }
+        block_builder.validate_header(&block).await?;
+
+        // only the state at the head is kept, so only blocks building on it can be executed
+        let head = block_builder
+            .get_latest_block()
+            .await
+            .map_or(B256::ZERO, |head| head.hash);
+        if block.parent_hash != head {
+            anyhow::bail!("block {} doesn't build on the head {}", block.hash, head);
+        }
+
+        // the block is executed on the live state, which is rolled back to the snapshot unless
+        // the block turns out valid and becomes the new head
+        let number = block.number.saturating_to();
+        let previous_block = self.vm.current_block();
+        let snapshot = self.vm.state_mut().snapshot();
+        self.vm.set_current_block(number);
+        let payments = self.vm.execute_due_payments();
+        let results = self.vm.execute_batch(&block.transactions);
+        let outcome = match check_execution(&block, &results, self.state()) {
+            Ok(()) => {
+                // kept even if the block ends up on a side branch, a reorg may bring it back
+                let logs = results
+                    .iter()
+                    .flatten()
+                    .map(|receipt| receipt.logs().to_vec())
+                    .collect();
+                block_builder.record_logs(block.hash, logs).await;
+                block_builder.import_block(block.clone()).await
+            }
+            Err(e) => Err(e),
+        };
+
+        match outcome {
+            Ok(ImportOutcome::Canonical(change)) => {
+                self.vm.state_mut().commit(snapshot).map_err(|e| {
+                    anyhow::anyhow!("failed to commit block {}: {:?}", block.hash, e)
+                })?;
+                self.assert_supply(&block);
+                for payment in &payments {
+                    self.events.publish_scheduled_payment(payment);
+                }
+                for (tx, result) in block.transactions.iter().zip(&results) {
+                    if result.is_ok() {
+                        self.events.publish_transaction(tx);
+                    }
+                }
+                self.events.publish_block(&block);
+                Ok(ImportOutcome::Canonical(change))
+            }
+            outcome => {
+                self.vm.state_mut().revert_to(snapshot).map_err(|e| {
+                    anyhow::anyhow!("failed to roll back block {}: {:?}", block.hash, e)
+                })?;
+                self.vm.set_current_block(previous_block);
+                outcome
+            }
+        }
+    }
+
+    // settled certificates are kept so recipients can fetch them later
+    pub fn settle_certificate(
+        &mut self,
+        certificate: &Certificate,
+        committee: &Committee,
+    ) -> Result<(), VMError> {
+        self.vm.execute_certificate(certificate, committee)?;
+        self.certificates.insert(certificate.clone());
+        self.events.publish_transaction(certificate.tx());
+        Ok(())
+    }
+
+    pub fn certificates(&self) -> &CertificateStore {
+        &self.certificates
+    }
+
+    // the total supply and where it is, see state::supply
+    pub fn total_supply(&self) -> TotalSupply {
+        self.vm.total_supply()
+    }
+
+    // the state after block `block_number` as an archive other nodes can start from. Only the
+    // state at the head is kept, so it has to be the last block the node executed
+    pub async fn capture_snapshot(
+        &self,
+        block_builder: &BlockBuilder,
+        block_number: u64,
+    ) -> anyhow::Result<StateArchive> {
+        if self.vm.current_block() != block_number {
+            anyhow::bail!(
+                "the state is at block {}, not {}",
+                self.vm.current_block(),
+                block_number
+            );
+        }
+        let block = block_builder
+            .get_block(U256::from(block_number))
+            .await
+            .ok_or_else(|| anyhow::anyhow!("block {} is missing from the store", block_number))?;
+        StateArchive::capture(self.state(), block)
+    }
+
+    // writes the state after block `block_number` to `path`, see capture_snapshot
+    pub async fn export_snapshot(
+        &self,
+        block_builder: &BlockBuilder,
+        block_number: u64,
+        path: &Path,
+    ) -> anyhow::Result<StateArchive> {
+        let archive = self.capture_snapshot(block_builder, block_number).await?;
+        archive.save(path)?;
+        Ok(archive)
+    }
+
+    // restores the state of an archive into the node's empty state. The next block the node
+    // executes is the one after the archive's, whose header the block builder needs as its head
+    pub fn import_snapshot(&mut self, path: &Path) -> anyhow::Result<StateArchive> {
+        let archive = StateArchive::load(path)?;
+        if archive.header.compute_hash() != archive.header.hash {
+            anyhow::bail!("archive header doesn't hash to {}", archive.header.hash);
+        }
+        archive.apply(self.vm.state_mut().as_mut())?;
+        self.vm.set_current_block(archive.block_number());
+        Ok(archive)
+    }
+
+    // a supply that doesn't add up is a bug in the vm, no block can cause it
+    fn assert_supply(&self, block: &Block) {
+        if !self.check_supply {
+            return;
+        }
+        let supply = self.total_supply();
+        assert!(
+            supply.is_balanced(),
+            "total supply {} is not the {} held plus the {} locked after block {}",
+            supply.total,
+            supply.held,
+            supply.locked,
+            block.hash
+        );
+    }
+}
+
+// every transaction of an imported block has to apply, and leave the state at the root the block
+// claims
+fn check_execution(
+    block: &Block,
+    results: &[Result<Receipt, VMError>],
+    state: &dyn State,
+) -> anyhow::Result<()> {
+    for (index, result) in results.iter().enumerate() {
+        if let Err(error) = result {
+            anyhow::bail!(
+                "transaction {} of block {} fails: {}",
+                index,
+                block.hash,
+                error.reason()
+            );
+        }
+    }
+    // blocks from before logs don't have a bloom to check
+    if let Some(bloom) = block.logs_bloom_filter() {
+        let logs = results.iter().flatten().flat_map(|receipt| receipt.logs());
+        if logs_bloom(logs) != bloom {
+            anyhow::bail!("block {} has the wrong logs bloom", block.hash);
+        }
     }
+    // nor a receipts root
+    if block.receipts_root != B256::ZERO {
+        let logs: Vec<Vec<_>> = results
+            .iter()
+            .flatten()
+            .map(|receipt| receipt.logs().to_vec())
+            .collect();
+        if receipts_root(&block.tx_hashes(), &logs) != block.receipts_root {
+            anyhow::bail!("block {} has the wrong receipts root", block.hash);
+        }
+    }
+    let state_root = state.state_root();
+    if state_root != block.state_root {
+        anyhow::bail!(
+            "block {} claims state root {} but executing it gives {}",
+            block.hash,
+            block.state_root,
+            state_root
+        );
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy::primitives::Address;
+    use alloy::primitives::{Address, U256};
     use alloy::signers::local::PrivateKeySigner;
     use alloy::signers::SignerSync;
-    use state::account::Account;
+    use block_builder::store::{BlockStore, MemoryBlockStore};
+    use committee::authority::Authority;
+    use futures::StreamExt;
+    use state::{account::Account, memory::MemoryState, sharded::ShardedState};
+    use std::sync::Arc;
     use wallet::Wallet;
 
     #[test]
@@ -36,7 +435,7 @@ mod tests {
         let sender_wallet = Wallet::random();
         let sender_address = sender_wallet.address();
         let initial_balance = 1000;
-        let sender_account = Account::new(sender_address, initial_balance);
+        let sender_account = Account::new(sender_address, U256::from(initial_balance));
         node.vm
             .state_mut()
             .update_account(&sender_address, sender_account)
@@ -51,9 +450,14 @@ mod tests {
         let recipient3_address = recipient3_wallet.address();
 
         // First transaction: 100 to recipient1
-        let tx1 = Tx::new(sender_address, recipient1_address, 100, None);
-        let signature1 = sender_wallet.sign_transaction(tx1.clone()).unwrap();
-        let tx1 = Tx::new(sender_address, recipient1_address, 100, Some(signature1));
+        let tx1 = Tx::new(sender_address, recipient1_address, U256::from(100), None);
+        let signature1 = sender_wallet.sign_transaction_sync(tx1.clone()).unwrap();
+        let tx1 = Tx::new(
+            sender_address,
+            recipient1_address,
+            U256::from(100),
+            Some(signature1),
+        );
 
         // Execute first transaction
         let result = node.execute_tx(&tx1);
@@ -65,20 +469,27 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance - 100);
         let recipient1_balance = node
             .vm
             .state()
             .get_account(&recipient1_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient1_balance, 100);
 
         // Second transaction: 200 to recipient2
-        let tx2 = Tx::new(sender_address, recipient2_address, 200, None);
-        let signature2 = sender_wallet.sign_transaction(tx2.clone()).unwrap();
-        let tx2 = Tx::new(sender_address, recipient2_address, 200, Some(signature2));
+        let tx2 = Tx::new(sender_address, recipient2_address, U256::from(200), None);
+        let signature2 = sender_wallet.sign_transaction_sync(tx2.clone()).unwrap();
+        let tx2 = Tx::new(
+            sender_address,
+            recipient2_address,
+            U256::from(200),
+            Some(signature2),
+        );
 
         // Execute second transaction
         let result = node.execute_tx(&tx2);
@@ -90,20 +501,27 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance - 100 - 200);
         let recipient2_balance = node
             .vm
             .state()
             .get_account(&recipient2_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient2_balance, 200);
 
         // Third transaction: 300 to recipient3
-        let tx3 = Tx::new(sender_address, recipient3_address, 300, None);
-        let signature3 = sender_wallet.sign_transaction(tx3.clone()).unwrap();
-        let tx3 = Tx::new(sender_address, recipient3_address, 300, Some(signature3));
+        let tx3 = Tx::new(sender_address, recipient3_address, U256::from(300), None);
+        let signature3 = sender_wallet.sign_transaction_sync(tx3.clone()).unwrap();
+        let tx3 = Tx::new(
+            sender_address,
+            recipient3_address,
+            U256::from(300),
+            Some(signature3),
+        );
 
         // Execute third transaction
         let result = node.execute_tx(&tx3);
@@ -115,14 +533,16 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance - 100 - 200 - 300);
         let recipient3_balance = node
             .vm
             .state()
             .get_account(&recipient3_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient3_balance, 300);
 
         // Verify all recipient balances
@@ -132,7 +552,7 @@ mod tests {
                 .get_account(&recipient1_address)
                 .unwrap()
                 .balance(),
-            100
+            U256::from(100)
         );
         assert_eq!(
             node.vm
@@ -140,7 +560,7 @@ mod tests {
                 .get_account(&recipient2_address)
                 .unwrap()
                 .balance(),
-            200
+            U256::from(200)
         );
         assert_eq!(
             node.vm
@@ -148,7 +568,7 @@ mod tests {
                 .get_account(&recipient3_address)
                 .unwrap()
                 .balance(),
-            300
+            U256::from(300)
         );
     }
 
@@ -162,7 +582,7 @@ mod tests {
         let sender_wallet = Wallet::random();
         let sender_address = sender_wallet.address();
         let initial_balance = 100;
-        let sender_account = Account::new(sender_address, initial_balance);
+        let sender_account = Account::new(sender_address, U256::from(initial_balance));
         node.vm
             .state_mut()
             .update_account(&sender_address, sender_account)
@@ -173,9 +593,14 @@ mod tests {
         let recipient_address = recipient_wallet.address();
 
         // First transaction: 50 to recipient
-        let tx1 = Tx::new(sender_address, recipient_address, 50, None);
-        let signature1 = sender_wallet.sign_transaction(tx1.clone()).unwrap();
-        let tx1 = Tx::new(sender_address, recipient_address, 50, Some(signature1));
+        let tx1 = Tx::new(sender_address, recipient_address, U256::from(50), None);
+        let signature1 = sender_wallet.sign_transaction_sync(tx1.clone()).unwrap();
+        let tx1 = Tx::new(
+            sender_address,
+            recipient_address,
+            U256::from(50),
+            Some(signature1),
+        );
 
         // Execute first transaction
         let result = node.execute_tx(&tx1);
@@ -187,29 +612,33 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance - 50);
         let recipient_balance = node
             .vm
             .state()
             .get_account(&recipient_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient_balance, 50);
 
         // Second transaction: 60 to recipient (should fail due to insufficient balance)
-        let tx2 = Tx::new(sender_address, recipient_address, 60, None);
-        let signature2 = sender_wallet.sign_transaction(tx2.clone()).unwrap();
-        let tx2 = Tx::new(sender_address, recipient_address, 60, Some(signature2));
+        let tx2 = Tx::new(sender_address, recipient_address, U256::from(60), None);
+        let signature2 = sender_wallet.sign_transaction_sync(tx2.clone()).unwrap();
+        let tx2 = Tx::new(
+            sender_address,
+            recipient_address,
+            U256::from(60),
+            Some(signature2),
+        );
 
         // Execute second transaction
         let result = node.execute_tx(&tx2);
         assert!(result.is_err());
-        match result.unwrap_err() {
-            VMError::InvalidTransaction(msg) => {
-                assert!(msg.contains("does not have enough balance"));
-            }
-        }
+        let msg = result.unwrap_err().reason();
+        assert!(msg.contains("does not have enough balance"));
 
         // Verify balances remain unchanged after failed transaction
         let sender_balance = node
@@ -217,14 +646,16 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance - 50);
         let recipient_balance = node
             .vm
             .state()
             .get_account(&recipient_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient_balance, 50);
     }
 
@@ -238,7 +669,7 @@ mod tests {
         let sender_wallet = Wallet::random();
         let sender_address = sender_wallet.address();
         let initial_balance = 100;
-        let sender_account = Account::new(sender_address, initial_balance);
+        let sender_account = Account::new(sender_address, U256::from(initial_balance));
         node.vm
             .state_mut()
             .update_account(&sender_address, sender_account)
@@ -249,19 +680,21 @@ mod tests {
         let recipient_address = recipient_wallet.address();
 
         // Create transaction with signature from wrong wallet
-        let tx = Tx::new(sender_address, recipient_address, 50, None);
+        let tx = Tx::new(sender_address, recipient_address, U256::from(50), None);
         let wrong_wallet = Wallet::random();
-        let signature = wrong_wallet.sign_transaction(tx.clone()).unwrap();
-        let tx = Tx::new(sender_address, recipient_address, 50, Some(signature));
+        let signature = wrong_wallet.sign_transaction_sync(tx.clone()).unwrap();
+        let tx = Tx::new(
+            sender_address,
+            recipient_address,
+            U256::from(50),
+            Some(signature),
+        );
 
         // Execute transaction
         let result = node.execute_tx(&tx);
         assert!(result.is_err());
-        match result.unwrap_err() {
-            VMError::InvalidTransaction(msg) => {
-                assert!(msg.contains("signature is invalid"));
-            }
-        }
+        let msg = result.unwrap_err().reason();
+        assert!(msg.contains("signature is invalid"));
 
         // Verify balances remain unchanged
         let sender_balance = node
@@ -269,7 +702,8 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance);
         assert!(node.vm.state().get_account(&recipient_address).is_none());
     }
@@ -284,7 +718,7 @@ mod tests {
         let sender_wallet = Wallet::random();
         let sender_address = sender_wallet.address();
         let initial_balance = 100;
-        let sender_account = Account::new(sender_address, initial_balance);
+        let sender_account = Account::new(sender_address, U256::from(initial_balance));
         node.vm
             .state_mut()
             .update_account(&sender_address, sender_account)
@@ -295,9 +729,14 @@ mod tests {
         let recipient_address = recipient_wallet.address();
 
         // Create and sign transaction
-        let tx = Tx::new(sender_address, recipient_address, 50, None);
-        let signature = sender_wallet.sign_transaction(tx.clone()).unwrap();
-        let tx = Tx::new(sender_address, recipient_address, 50, Some(signature));
+        let tx = Tx::new(sender_address, recipient_address, U256::from(50), None);
+        let signature = sender_wallet.sign_transaction_sync(tx.clone()).unwrap();
+        let tx = Tx::new(
+            sender_address,
+            recipient_address,
+            U256::from(50),
+            Some(signature),
+        );
 
         // Execute transaction
         let result = node.execute_tx(&tx);
@@ -309,14 +748,16 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance - 50);
         let recipient_balance = node
             .vm
             .state()
             .get_account(&recipient_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient_balance, 50);
     }
 
@@ -330,7 +771,7 @@ mod tests {
         let sender_wallet = Wallet::random();
         let sender_address = sender_wallet.address();
         let initial_balance = 100;
-        let sender_account = Account::new(sender_address, initial_balance);
+        let sender_account = Account::new(sender_address, U256::from(initial_balance));
         node.vm
             .state_mut()
             .update_account(&sender_address, sender_account)
@@ -341,9 +782,14 @@ mod tests {
         let recipient_address = recipient_wallet.address();
 
         // Create and sign transaction with zero amount
-        let tx = Tx::new(sender_address, recipient_address, 0, None);
-        let signature = sender_wallet.sign_transaction(tx.clone()).unwrap();
-        let tx = Tx::new(sender_address, recipient_address, 0, Some(signature));
+        let tx = Tx::new(sender_address, recipient_address, U256::ZERO, None);
+        let signature = sender_wallet.sign_transaction_sync(tx.clone()).unwrap();
+        let tx = Tx::new(
+            sender_address,
+            recipient_address,
+            U256::ZERO,
+            Some(signature),
+        );
 
         // Execute transaction
         let result = node.execute_tx(&tx);
@@ -355,14 +801,348 @@ mod tests {
             .state()
             .get_account(&sender_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(sender_balance, initial_balance);
         let recipient_balance = node
             .vm
             .state()
             .get_account(&recipient_address)
             .unwrap()
-            .balance();
+            .balance()
+            .to::<u64>();
         assert_eq!(recipient_balance, 0);
     }
+
+    #[test]
+    fn test_settle_certificate() {
+        let mut node = Node::new(Box::new(MemoryState::new()));
+        let sender = Wallet::random();
+        node.vm
+            .state_mut()
+            .update_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(100)),
+            )
+            .unwrap();
+
+        let authorities: Vec<Authority> = (0..4).map(|_| Authority::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+        let tx = Tx::new(sender.address(), Address::ZERO, U256::from(40), None);
+        let signature = sender.sign_transaction_sync(tx.clone()).unwrap();
+        let tx = tx.with_signature(signature);
+
+        // Without a quorum nothing is settled or kept
+        let mut certificate = Certificate::new(tx.clone());
+        for authority in &authorities[..2] {
+            certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+        }
+        assert!(node.settle_certificate(&certificate, &committee).is_err());
+        assert!(node.certificates().is_empty());
+
+        certificate.add_vote(authorities[2].handle_transfer_order(&tx).unwrap());
+        assert!(node.settle_certificate(&certificate, &committee).is_ok());
+        let stored = node.certificates().get(&certificate.tx_hash()).unwrap();
+        assert!(stored.verify(&committee).is_ok());
+        assert_eq!(
+            node.state().get_account(&Address::ZERO).unwrap().balance(),
+            U256::from(40)
+        );
+    }
+
+    #[test]
+    fn test_execute_pending() {
+        let sender = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let state = Arc::new(ShardedState::in_memory(2));
+        state
+            .write_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(100)),
+            )
+            .unwrap();
+        let node = Node::new(Box::new(state.clone()));
+        let pending = PendingState::new(state.clone());
+
+        let tx = Tx::new(sender.address(), to, U256::from(30), None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        let transactions = node.transactions();
+        node.execute_pending(&pending, &[tx.with_signature(signature)]);
+
+        assert_eq!(pending.get_account(&to).unwrap().balance(), U256::from(30));
+        assert!(state.read_account(&to).is_none());
+        assert_eq!(
+            state.read_account(&sender.address()).unwrap().balance(),
+            U256::from(100)
+        );
+        futures::pin_mut!(transactions);
+        assert!(futures::FutureExt::now_or_never(transactions.next()).is_none());
+
+        // Executing again starts over from the committed state
+        node.execute_pending(&pending, &[]);
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_block() {
+        let sender = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let funded_node = || {
+            let mut state = MemoryState::new();
+            state
+                .update_account(
+                    &sender.address(),
+                    Account::new(sender.address(), U256::from(100)),
+                )
+                .unwrap();
+            state.set_total_supply(U256::from(100)).unwrap();
+            Node::new(Box::new(state)).with_supply_check()
+        };
+
+        // The producer executes a transfer and seals the resulting state root
+        let mut producer = funded_node();
+        let produced = BlockBuilder::new();
+        let tx = Tx::new(sender.address(), to, U256::from(30), None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        // an unsigned tx fails and is left out of the block
+        let txs = vec![tx.clone().with_signature(signature), tx];
+        let block = producer
+            .produce_block(&produced, txs, Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(block.transactions.len(), 1);
+
+        let mut importer = funded_node();
+        let imported = BlockBuilder::new();
+        let blocks = importer.blocks();
+        futures::pin_mut!(blocks);
+
+        // A block lying about the state it leads to is refused and changes nothing
+        let lying = block.clone().with_state_root(B256::repeat_byte(1));
+        assert!(importer.import_block(&imported, lying).await.is_err());
+        let wrong_bloom = block
+            .clone()
+            .with_logs_bloom(alloy::primitives::Bloom::ZERO);
+        assert!(importer.import_block(&imported, wrong_bloom).await.is_err());
+        let wrong_receipts = block.clone().with_receipts_root(B256::repeat_byte(1));
+        assert!(importer
+            .import_block(&imported, wrong_receipts)
+            .await
+            .is_err());
+        assert!(importer.state().get_account(&to).is_none());
+        assert_eq!(
+            importer
+                .state()
+                .get_account(&sender.address())
+                .unwrap()
+                .balance(),
+            U256::from(100)
+        );
+        assert!(imported.get_latest_block().await.is_none());
+
+        assert!(matches!(
+            importer
+                .import_block(&imported, block.clone())
+                .await
+                .unwrap(),
+            ImportOutcome::Canonical(_)
+        ));
+        assert_eq!(
+            importer.state().get_account(&to).unwrap().balance(),
+            U256::from(30)
+        );
+        assert_eq!(importer.state().state_root(), block.state_root);
+        let imported_block = blocks.next().await.unwrap().unwrap();
+        assert_eq!(imported_block.hash, block.hash);
+        assert_eq!(
+            imported.get_logs(block.hash).await,
+            vec![vec![tx::log::Log::transfer(
+                sender.address(),
+                to,
+                U256::from(30)
+            )]]
+        );
+        assert_eq!(
+            importer.import_block(&imported, block).await.unwrap(),
+            ImportOutcome::Known
+        );
+
+        // Blocks have to build on the head
+        let stray = Block::new(
+            alloy::primitives::U256::from(1),
+            B256::repeat_byte(2),
+            0,
+            Vec::new(),
+            Address::ZERO,
+        );
+        assert!(importer.import_block(&imported, stray).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_payments_in_blocks() {
+        let sender = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let funded_node = || {
+            let mut state = MemoryState::new();
+            state
+                .update_account(
+                    &sender.address(),
+                    Account::new(sender.address(), U256::from(100)),
+                )
+                .unwrap();
+            state.set_total_supply(U256::from(100)).unwrap();
+            Node::new(Box::new(state)).with_supply_check()
+        };
+
+        let mut producer = funded_node();
+        let produced = BlockBuilder::new();
+        let tx = Tx::schedule(sender.address(), to, U256::from(10), 1, None, 0, None);
+        let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+        let first = producer
+            .produce_block(&produced, vec![tx.with_signature(signature)], Address::ZERO)
+            .await
+            .unwrap();
+        // The first payment is made in the next block, without a tx of its own
+        let second = producer
+            .produce_block(&produced, Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+        assert!(second.transactions.is_empty());
+        assert_eq!(
+            producer.state().get_account(&to).unwrap().balance(),
+            U256::from(10)
+        );
+
+        // An importer makes the same payment and reaches the same state
+        let mut importer = funded_node();
+        let imported = BlockBuilder::new();
+        let payments = importer.scheduled_payments();
+        futures::pin_mut!(payments);
+        importer.import_block(&imported, first).await.unwrap();
+        importer
+            .import_block(&imported, second.clone())
+            .await
+            .unwrap();
+        assert_eq!(importer.state().state_root(), second.state_root);
+        let payment = payments.next().await.unwrap().unwrap();
+        assert_eq!(payment.block, 1);
+        assert_eq!(payment.to, to);
+        assert_eq!(payment.error, None);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_snapshot() {
+        let sender = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let mut state = MemoryState::new();
+        state
+            .update_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(100)),
+            )
+            .unwrap();
+        let mut producer = Node::new(Box::new(state));
+        let produced = BlockBuilder::new();
+        let sign = |tx: Tx| {
+            let signature = sender.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+        for amount in [10, 20] {
+            let tx = sign(Tx::new(sender.address(), to, U256::from(amount), None));
+            producer
+                .produce_block(&produced, vec![tx], Address::ZERO)
+                .await
+                .unwrap();
+        }
+
+        let path = std::env::temp_dir().join(format!("node-snapshot-{}", std::process::id()));
+        // Only the state at the head is there to export
+        assert!(producer.export_snapshot(&produced, 0, &path).await.is_err());
+        let archive = producer.export_snapshot(&produced, 1, &path).await.unwrap();
+
+        // A new node starts from the archive and follows the chain from there
+        let mut joined = Node::new(Box::new(MemoryState::new()));
+        assert_eq!(
+            joined.import_snapshot(&path).unwrap().header.hash,
+            archive.header.hash
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(joined.state().state_root(), producer.state().state_root());
+        // Its block store begins with the archive's header
+        let store = MemoryBlockStore::new();
+        store.put(&archive.header).unwrap();
+        let following = BlockBuilder::with_store(store).unwrap();
+
+        let tx = sign(Tx::new(sender.address(), to, U256::from(30), None));
+        let block = producer
+            .produce_block(&produced, vec![tx], Address::ZERO)
+            .await
+            .unwrap();
+        assert!(matches!(
+            joined.import_block(&following, block).await.unwrap(),
+            ImportOutcome::Canonical(_)
+        ));
+        assert_eq!(
+            joined.state().get_account(&to).unwrap().balance(),
+            U256::from(60)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_supply_check() {
+        let issuer = PrivateKeySigner::random();
+        let sender = PrivateKeySigner::random();
+        let mut state = MemoryState::new();
+        state
+            .update_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(100)),
+            )
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+        let mut node = Node::new(Box::new(state))
+            .with_issuer(issuer.address())
+            .with_supply_check();
+        let sign = |signer: &PrivateKeySigner, tx: Tx| {
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        // Mints add to the supply, burns and fees take from it
+        let txs = vec![
+            sign(
+                &issuer,
+                Tx::mint(issuer.address(), issuer.address(), U256::from(50), 0, None),
+            ),
+            sign(&issuer, Tx::burn(issuer.address(), U256::from(20), 1, None)),
+            sign(
+                &sender,
+                Tx::new(sender.address(), issuer.address(), U256::from(10), None).with_fee(5),
+            ),
+        ];
+        let block_builder = BlockBuilder::new();
+        let block = node
+            .produce_block(&block_builder, txs, Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(block.transactions.len(), 3);
+        let supply = node.total_supply();
+        assert_eq!(supply.total, U256::from(125));
+        assert_eq!(supply.held, U256::from(125));
+
+        // Funds that appear out of nowhere stop the node at the next block
+        node.vm
+            .state_mut()
+            .update_account(
+                &sender.address(),
+                Account::new(sender.address(), U256::from(1_000)),
+            )
+            .unwrap();
+        let produced = std::panic::AssertUnwindSafe(node.produce_block(
+            &block_builder,
+            Vec::new(),
+            Address::ZERO,
+        ));
+        assert!(futures::FutureExt::catch_unwind(produced).await.is_err());
+    }
 }
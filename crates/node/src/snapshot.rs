@@ -0,0 +1,151 @@
+// snapshots of the accounts at a block, written by one node and compared against another node's
+// or the same node's after an upgrade, to check both ended up in the same state
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use alloy::primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use state::state::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotAccount {
+    pub address: Address,
+    pub balance: U256,
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    // the number of the last block executed, None before the first one
+    pub block: Option<u64>,
+    pub state_root: B256,
+    // sorted by address
+    pub accounts: Vec<SnapshotAccount>,
+}
+
+// an account that differs between two snapshots, None where a snapshot doesn't have it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub before: Option<SnapshotAccount>,
+    pub after: Option<SnapshotAccount>,
+}
+
+impl Snapshot {
+    // empty accounts are left out, like they are from the state root
+    pub fn capture(state: &dyn State, block: Option<u64>) -> Self {
+        let mut accounts: Vec<_> = state
+            .accounts()
+            .into_iter()
+            .filter(|account| !account.is_empty())
+            .map(|account| SnapshotAccount {
+                address: account.get_address(),
+                balance: account.balance(),
+                sequence: account.sequence(),
+            })
+            .collect();
+        accounts.sort_by_key(|account| account.address);
+
+        Self {
+            block,
+            state_root: state.state_root(),
+            accounts,
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // the accounts whose balance or sequence number changed from `self` to `other`, by address
+    pub fn diff(&self, other: &Snapshot) -> Vec<AccountDiff> {
+        let mut accounts: BTreeMap<Address, AccountDiff> = BTreeMap::new();
+        for account in &self.accounts {
+            accounts.entry(account.address).or_insert(AccountDiff {
+                address: account.address,
+                before: Some(*account),
+                after: None,
+            });
+        }
+        for account in &other.accounts {
+            accounts
+                .entry(account.address)
+                .or_insert(AccountDiff {
+                    address: account.address,
+                    before: None,
+                    after: None,
+                })
+                .after = Some(*account);
+        }
+
+        accounts
+            .into_values()
+            .filter(|diff| diff.before != diff.after)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::{account::Account, memory::MemoryState};
+
+    fn state(accounts: &[(Address, u64, u64)]) -> MemoryState {
+        let mut state = MemoryState::new();
+        for &(address, balance, sequence) in accounts {
+            let mut account = Account::new(address, U256::from(balance));
+            account.set_sequence(sequence);
+            state.update_account(&address, account).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn test_diff() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let carol = Address::repeat_byte(3);
+        let dave = Address::repeat_byte(4);
+
+        let before = Snapshot::capture(
+            &state(&[(alice, 10, 0), (bob, 5, 1), (carol, 7, 0), (dave, 0, 0)]),
+            Some(3),
+        );
+        assert_eq!(before.accounts.len(), 3);
+        assert!(before.diff(&before).is_empty());
+
+        let after = Snapshot::capture(
+            &state(&[(alice, 10, 0), (bob, 5, 2), (dave, 1, 0)]),
+            Some(3),
+        );
+        assert_ne!(before.state_root, after.state_root);
+
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.iter().map(|diff| diff.address).collect::<Vec<_>>(),
+            vec![bob, carol, dave]
+        );
+        assert_eq!(diff[0].before.unwrap().sequence, 1);
+        assert_eq!(diff[0].after.unwrap().sequence, 2);
+        assert_eq!(diff[1].after, None);
+        assert_eq!(diff[2].before, None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", std::process::id()));
+
+        let snapshot = Snapshot::capture(&state(&[(Address::repeat_byte(1), 100, 2)]), None);
+        snapshot.save(&path).unwrap();
+
+        assert_eq!(Snapshot::load(&path).unwrap(), snapshot);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+// issued assets over rpc: an issuer issues its asset to an account, holders transfer it as they
+// would the native token
+
+use alloy::primitives::{Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+use tx::{asset::AssetId, tx::Tx};
+
+use crate::channel::parse_signature;
+
+// a signed asset tx, the signature is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AssetTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Transfer {
+        from: Address,
+        to: Address,
+        asset: AssetId,
+        amount: U256,
+        sequence: u64,
+        // in the native token
+        #[serde(default)]
+        fee: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Issue {
+        from: Address,
+        symbol: String,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Bytes,
+    },
+}
+
+impl TryFrom<AssetTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: AssetTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            AssetTxRequest::Transfer {
+                from,
+                to,
+                asset,
+                amount,
+                sequence,
+                fee,
+                signature,
+            } => Tx::transfer_order(
+                from,
+                to,
+                amount,
+                sequence,
+                Some(parse_signature(&signature)?),
+            )
+            .with_asset(asset)
+            .with_fee(fee),
+            AssetTxRequest::Issue {
+                from,
+                symbol,
+                to,
+                amount,
+                sequence,
+                signature,
+            } => Tx::issue_asset(
+                from,
+                symbol,
+                to,
+                amount,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use tx::asset::asset_id;
+
+    #[test]
+    fn test_request_from_json() {
+        let holder = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let asset = asset_id(Address::repeat_byte(2), "USD");
+        let tx = Tx::transfer_order(holder.address(), to, U256::from(5), 3, None).with_asset(asset);
+        let signature = holder.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: AssetTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "transfer",
+            "from": holder.address(),
+            "to": to,
+            "asset": asset,
+            "amount": U256::from(5),
+            "sequence": 3,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(Tx::try_from(request).unwrap().tx_hash(), tx.tx_hash());
+
+        // Signed for another asset than the one sent
+        let forged = AssetTxRequest::Transfer {
+            from: holder.address(),
+            to,
+            asset: asset_id(Address::repeat_byte(2), "EUR"),
+            amount: U256::from(5),
+            sequence: 3,
+            fee: 0,
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+// the namespaces that shouldn't be public, admin_, debug_ and personal_, are served on an address
+// of their own, the way execution clients serve the engine API. Every request there has to carry
+// `Authorization: Bearer <token>`, the token being either the shared secret in hex or a JWT
+// signed with it (HS256) and issued within the last minute
+
+use std::error::Error as StdError;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::hex;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use hyper::{header::AUTHORIZATION, Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use sha2::Sha256;
+use tower::{Layer, Service};
+
+pub const AUTH_SECRET_LEN: usize = 32;
+// how far a JWT's issue time may be from the server's clock, either way
+pub const JWT_IAT_WINDOW_SECS: u64 = 60;
+
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub addr: SocketAddr,
+    pub secret: AuthSecret,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthSecret([u8; AUTH_SECRET_LEN]);
+
+// the secret stays out of logs
+impl std::fmt::Debug for AuthSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AuthSecret(..)")
+    }
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+#[cfg(unix)]
+fn check_private(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path)?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        anyhow::bail!(
+            "{} can be read by other users (mode {:o}), restrict it to 600",
+            path.display(),
+            mode
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_private(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    iat: u64,
+}
+
+impl AuthSecret {
+    pub fn new(secret: [u8; AUTH_SECRET_LEN]) -> Self {
+        Self(secret)
+    }
+
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, String> {
+        let bytes = hex::decode(s.trim()).map_err(|e| format!("invalid secret: {}", e))?;
+        let secret = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            format!(
+                "the secret is {} bytes, {} expected",
+                bytes.len(),
+                AUTH_SECRET_LEN
+            )
+        })?;
+        Ok(Self(secret))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode_prefixed(self.0)
+    }
+
+    // the secret in `path`, a new one is written there if the file doesn't exist yet. Only the
+    // node's user may read it, a secret others can read is refused
+    pub fn load_or_create(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            check_private(path)?;
+            let secret = std::fs::read_to_string(path)?;
+            return Self::from_hex(&secret)
+                .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e));
+        }
+        let secret = Self::random();
+        create_private(path)?.write_all(secret.to_hex().as_bytes())?;
+        Ok(secret)
+    }
+
+    // a JWT for calling the authenticated endpoint, issued at `iat` seconds
+    pub fn jwt(&self, iat: u64) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"iat":{}}}"#, iat));
+        let signed = format!("{}.{}", header, claims);
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&signed).finalize().into_bytes());
+        format!("{}.{}", signed, signature)
+    }
+
+    // whether `token` is the secret itself or a JWT it signed, issued close enough to `now`
+    pub fn verify(&self, token: &str, now: u64) -> bool {
+        if let Ok(secret) = Self::from_hex(token) {
+            // compared through their macs, so the time taken tells nothing about the secret
+            return self.tag(&secret.0) == self.tag(&self.0);
+        }
+        self.verify_jwt(token, now).is_some()
+    }
+
+    fn verify_jwt(&self, token: &str, now: u64) -> Option<()> {
+        let (signed, signature) = token.rsplit_once('.')?;
+        let (header, claims) = signed.split_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(signed).verify_slice(&signature).ok()?;
+
+        let header: JwtHeader =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+        let claims: JwtClaims =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).ok()?).ok()?;
+        (header.alg == "HS256" && claims.iat.abs_diff(now) <= JWT_IAT_WINDOW_SECS).then_some(())
+    }
+
+    fn mac(&self, message: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("hmac takes keys of any size");
+        mac.update(message.as_bytes());
+        mac
+    }
+
+    fn tag(&self, bytes: &[u8]) -> Vec<u8> {
+        self.mac("")
+            .chain_update(bytes)
+            .finalize()
+            .into_bytes()
+            .to_vec()
+    }
+}
+
+// turns away requests without a valid token before jsonrpsee sees them, WebSocket connections
+// are checked when they are opened
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    secret: AuthSecret,
+}
+
+impl AuthLayer {
+    pub fn new(secret: AuthSecret) -> Self {
+        Self { secret }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            secret: self.secret.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    secret: AuthSecret,
+}
+
+type BoxError = Box<dyn StdError + Send + Sync + 'static>;
+
+impl<S> Service<Request<Body>> for AuthService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| self.secret.verify(token, now));
+        if !authorized {
+            let response = Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::from("missing or invalid bearer token"))
+                .expect("a static response is valid");
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let secret = AuthSecret::random();
+        let now = 1_700_000_000;
+        assert!(secret.verify(&secret.to_hex(), now));
+        assert!(secret.verify(&secret.jwt(now - 30), now));
+        assert!(secret.verify(&secret.jwt(now + 30), now));
+
+        // Too old, signed with another secret, or tampered with
+        assert!(!secret.verify(&secret.jwt(now - 120), now));
+        assert!(!secret.verify(&AuthSecret::random().jwt(now), now));
+        assert!(!secret.verify(&AuthSecret::random().to_hex(), now));
+        let jwt = secret.jwt(now);
+        let (signed, _) = jwt.rsplit_once('.').unwrap();
+        let forged = AuthSecret::random().jwt(now);
+        let (_, signature) = forged.rsplit_once('.').unwrap();
+        assert!(!secret.verify(&format!("{}.{}", signed, signature), now));
+        assert!(!secret.verify("", now));
+    }
+
+    struct Ok200;
+
+    impl Service<Request<Body>> for Ok200 {
+        type Response = Response<Body>;
+        type Error = BoxError;
+        type Future = std::future::Ready<Result<Response<Body>, BoxError>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<Body>) -> Self::Future {
+            std::future::ready(Ok(Response::new(Body::empty())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_layer() {
+        let secret = AuthSecret::random();
+        let mut service = AuthLayer::new(secret.clone()).layer(Ok200);
+        let request = |authorization: Option<String>| {
+            let mut request = Request::builder();
+            if let Some(authorization) = authorization {
+                request = request.header(AUTHORIZATION, authorization);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let mut status = |authorization| {
+            let response = service.call(request(authorization));
+            async move { response.await.unwrap().status() }
+        };
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(Some(secret.jwt(now))).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(Some(format!("Bearer {}", secret.jwt(now)))).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(Some(format!("Bearer {}", secret.to_hex()))).await,
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_load_or_create() {
+        let path = std::env::temp_dir().join(format!("fastpay-auth-secret-{}", std::process::id()));
+        let created = AuthSecret::load_or_create(&path).unwrap();
+        assert_eq!(AuthSecret::load_or_create(&path).unwrap(), created);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            // A secret others can read is refused
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(AuthSecret::load_or_create(&path).is_err());
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        std::fs::write(&path, "0x1234").unwrap();
+        assert!(AuthSecret::load_or_create(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
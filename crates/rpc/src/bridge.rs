@@ -0,0 +1,104 @@
+// bridge txs over rpc: deposits credited by the bridge operator and redemptions sent by users,
+// see the bridge crate for the other side
+
+use alloy::primitives::{Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+use crate::channel::parse_signature;
+
+// a signed bridge tx, the signature is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BridgeTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Fund {
+        from: Address,
+        to: Address,
+        amount: U256,
+        deposit_nonce: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Redeem {
+        from: Address,
+        recipient: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Bytes,
+    },
+}
+
+impl TryFrom<BridgeTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: BridgeTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            BridgeTxRequest::Fund {
+                from,
+                to,
+                amount,
+                deposit_nonce,
+                signature,
+            } => Tx::fund_from_primary(
+                from,
+                to,
+                amount,
+                deposit_nonce,
+                Some(parse_signature(&signature)?),
+            ),
+            BridgeTxRequest::Redeem {
+                from,
+                recipient,
+                amount,
+                sequence,
+                signature,
+            } => Tx::redeem_to_primary(
+                from,
+                recipient,
+                amount,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_request_from_json() {
+        let user = PrivateKeySigner::random();
+        let recipient = Address::repeat_byte(9);
+        let tx = Tx::redeem_to_primary(user.address(), recipient, U256::from(5), 0, None);
+        let signature = user.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: BridgeTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "redeem",
+            "from": user.address(),
+            "recipient": recipient,
+            "amount": U256::from(5),
+            "sequence": 0,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(Tx::try_from(request).unwrap().tx_hash(), tx.tx_hash());
+
+        // Signed by someone else than the sender it claims
+        let forged = BridgeTxRequest::Fund {
+            from: Address::repeat_byte(1),
+            to: user.address(),
+            amount: U256::from(5),
+            deposit_nonce: 0,
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
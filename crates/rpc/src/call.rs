@@ -0,0 +1,111 @@
+// eth_call and eth_estimateGas: a transfer tried out before it is signed and sent, to find out
+// whether the sender can afford it and its sequence number and fee are right. The call comes as
+// an Ethereum call object, with the fee as a fastpay extension. Calls of balanceOf on an issued
+// asset's token address are answered on their own, see token.rs
+
+use alloy::primitives::{Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallRequest {
+    // token balance reads come without one, a transfer is refused without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    pub to: Address,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    // a transfer order's sequence number, a plain transfer without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<U256>,
+    // there are no contracts to call, transfers only accept empty input
+    #[serde(default, alias = "data", skip_serializing_if = "Option::is_none")]
+    pub input: Option<Bytes>,
+}
+
+impl TryFrom<CallRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: CallRequest) -> Result<Self, Self::Error> {
+        if request
+            .input
+            .as_ref()
+            .is_some_and(|input| !input.is_empty())
+        {
+            return Err("only transfers can be called, input has to be empty".to_string());
+        }
+        let quantity = |name: &str, value: Option<U256>| {
+            u64::try_from(value.unwrap_or_default()).map_err(|_| format!("{} is too large", name))
+        };
+        let from = request
+            .from
+            .ok_or_else(|| "from is required for a transfer".to_string())?;
+        let amount = request.value.unwrap_or_default();
+        let mut tx = match request.nonce {
+            Some(nonce) => Tx::transfer_order(
+                from,
+                request.to,
+                amount,
+                quantity("nonce", Some(nonce))?,
+                None,
+            ),
+            None => Tx::new(from, request.to, amount, None),
+        }
+        .with_fee(quantity("fee", request.fee)?);
+        if let Some(chain_id) = request.chain_id {
+            tx = tx.with_chain_id(quantity("chainId", Some(chain_id))?);
+        }
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_call_request() {
+        let request: CallRequest = serde_json::from_value(json!({
+            "from": Address::repeat_byte(1),
+            "to": Address::repeat_byte(2),
+            "value": "0x64",
+            "nonce": "0x3",
+            "fee": "0x2",
+            "data": "0x",
+        }))
+        .unwrap();
+        let tx = Tx::try_from(request.clone()).unwrap();
+        assert_eq!(tx.amount(), U256::from(100));
+        assert_eq!(tx.sequence(), Some(3));
+        assert_eq!(tx.fee(), 2);
+        assert_eq!(tx.chain_id(), None);
+
+        let calldata = CallRequest {
+            input: Some(Bytes::from_static(&[0xa9, 0x05, 0x9c, 0xbb])),
+            ..request.clone()
+        };
+        assert!(Tx::try_from(calldata).is_err());
+        let too_much = CallRequest {
+            nonce: Some(U256::MAX),
+            ..request.clone()
+        };
+        assert!(Tx::try_from(too_much).is_err());
+        let everything = CallRequest {
+            value: Some(U256::MAX),
+            ..request.clone()
+        };
+        assert_eq!(Tx::try_from(everything).unwrap().amount(), U256::MAX);
+        // Not simulated from the zero address
+        let anonymous = CallRequest {
+            from: None,
+            ..request
+        };
+        assert!(Tx::try_from(anonymous).is_err());
+    }
+}
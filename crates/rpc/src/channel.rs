@@ -0,0 +1,181 @@
+// payment channel txs over rpc, see state::channel for how channels work
+
+use alloy::primitives::{Address, Bytes, PrimitiveSignature, B256, U256};
+use serde::{Deserialize, Serialize};
+use state::channel::Channel;
+use tx::tx::Tx;
+
+// a signed channel tx, signatures are the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ChannelTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Open {
+        from: Address,
+        to: Address,
+        amount: U256,
+        challenge_period: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Close {
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        amount: U256,
+        update_signature: Bytes,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    StartTimeout {
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    ClaimTimeout {
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        signature: Bytes,
+    },
+}
+
+pub(crate) fn parse_signature(signature: &Bytes) -> Result<PrimitiveSignature, String> {
+    PrimitiveSignature::try_from(signature.as_ref())
+        .map_err(|e| format!("invalid signature: {}", e))
+}
+
+impl TryFrom<ChannelTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: ChannelTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            ChannelTxRequest::Open {
+                from,
+                to,
+                amount,
+                challenge_period,
+                signature,
+            } => Tx::open_channel(
+                from,
+                to,
+                amount,
+                challenge_period,
+                Some(parse_signature(&signature)?),
+            ),
+            ChannelTxRequest::Close {
+                from,
+                to,
+                channel_id,
+                amount,
+                update_signature,
+                signature,
+            } => Tx::close_channel(
+                from,
+                to,
+                channel_id,
+                amount,
+                parse_signature(&update_signature)?,
+                Some(parse_signature(&signature)?),
+            ),
+            ChannelTxRequest::StartTimeout {
+                from,
+                to,
+                channel_id,
+                signature,
+            } => {
+                Tx::start_channel_timeout(from, to, channel_id, Some(parse_signature(&signature)?))
+            }
+            ChannelTxRequest::ClaimTimeout {
+                from,
+                to,
+                channel_id,
+                signature,
+            } => {
+                Tx::claim_channel_timeout(from, to, channel_id, Some(parse_signature(&signature)?))
+            }
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelInfo {
+    pub payer: Address,
+    pub payee: Address,
+    pub deposit: U256,
+    pub challenge_period: u64,
+    // set once the payer has started the timeout
+    pub expires_at: Option<u64>,
+}
+
+impl From<&Channel> for ChannelInfo {
+    fn from(channel: &Channel) -> Self {
+        Self {
+            payer: channel.payer(),
+            payee: channel.payee(),
+            deposit: channel.deposit(),
+            challenge_period: channel.challenge_period(),
+            expires_at: channel.expires_at(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    fn signature_bytes(signer: &PrivateKeySigner, tx: &Tx) -> Bytes {
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        Bytes::from(signature.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_request_from_json() {
+        let request: ChannelTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "startTimeout",
+            "from": Address::repeat_byte(1),
+            "to": Address::repeat_byte(2),
+            "channelId": B256::repeat_byte(3),
+            "signature": "0x00",
+        }))
+        .unwrap();
+        assert!(matches!(request, ChannelTxRequest::StartTimeout { .. }));
+
+        // A one byte signature doesn't parse
+        assert!(Tx::try_from(request).is_err());
+    }
+
+    #[test]
+    fn test_request_checks_sender() {
+        let payer = PrivateKeySigner::random();
+        let payee = PrivateKeySigner::random().address();
+        let tx = Tx::open_channel(payer.address(), payee, U256::from(100), 10, None);
+
+        let request = ChannelTxRequest::Open {
+            from: payer.address(),
+            to: payee,
+            amount: U256::from(100),
+            challenge_period: 10,
+            signature: signature_bytes(&payer, &tx),
+        };
+        let parsed = Tx::try_from(request).unwrap();
+        assert_eq!(parsed.tx_hash(), tx.tx_hash());
+
+        let forged = ChannelTxRequest::Open {
+            from: payer.address(),
+            to: payee,
+            amount: U256::from(100),
+            challenge_period: 10,
+            signature: signature_bytes(&PrivateKeySigner::random(), &tx),
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
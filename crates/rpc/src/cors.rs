@@ -0,0 +1,216 @@
+// lets the web pages of the origins given call the node from a browser. Their preflight requests
+// are answered here and their responses get the headers browsers look for. Requests from other
+// origins are turned away, which covers WebSocket connections too since browsers don't check
+// those themselves. Without origins the server doesn't do CORS at all, as before
+
+use std::error::Error as StdError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::{
+    header::{
+        HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+        ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+    },
+    Body, Method, Request, Response, StatusCode,
+};
+use tower::{Layer, Service};
+
+// how long browsers may cache a preflight's answer
+const PREFLIGHT_MAX_AGE_SECS: u32 = 86400;
+
+#[derive(Debug, Clone, Default)]
+pub struct CorsLayer {
+    // "*" lets any origin in
+    origins: Arc<Vec<String>>,
+}
+
+impl CorsLayer {
+    pub fn new(origins: Vec<String>) -> Self {
+        Self {
+            origins: Arc::new(origins),
+        }
+    }
+
+    // what Access-Control-Allow-Origin says to `origin`, None if it isn't allowed
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        if self.origins.iter().any(|allowed| allowed == "*") {
+            return Some(HeaderValue::from_static("*"));
+        }
+        let origin_str = origin.to_str().ok()?;
+        self.origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/') == origin_str)
+            .then(|| origin.clone())
+    }
+}
+
+impl<S> Layer<S> for CorsLayer {
+    type Service = CorsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CorsService {
+            inner,
+            cors: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CorsService<S> {
+    inner: S,
+    cors: CorsLayer,
+}
+
+type BoxError = Box<dyn StdError + Send + Sync + 'static>;
+
+fn response(status: StatusCode, body: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(body))
+        .expect("a static response is valid")
+}
+
+impl<S> Service<Request<Body>> for CorsService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let origin = match request.headers().get(ORIGIN) {
+            Some(origin) if !self.cors.origins.is_empty() => origin,
+            _ => return Box::pin(self.inner.call(request)),
+        };
+        let Some(allow_origin) = self.cors.allow_origin(origin) else {
+            let response = response(StatusCode::FORBIDDEN, "origin not allowed");
+            return Box::pin(async move { Ok(response) });
+        };
+
+        if request.method() == Method::OPTIONS
+            && request
+                .headers()
+                .contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let mut response = response(StatusCode::NO_CONTENT, "");
+            let headers = response.headers_mut();
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static("GET, POST, OPTIONS"),
+            );
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                request
+                    .headers()
+                    .get(ACCESS_CONTROL_REQUEST_HEADERS)
+                    .cloned()
+                    .unwrap_or(HeaderValue::from_static("content-type")),
+            );
+            headers.insert(ACCESS_CONTROL_MAX_AGE, PREFLIGHT_MAX_AGE_SECS.into());
+            headers.insert(VARY, HeaderValue::from_static("origin"));
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = response.await?;
+            let headers = response.headers_mut();
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+            headers.insert(VARY, HeaderValue::from_static("origin"));
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ok200;
+
+    impl Service<Request<Body>> for Ok200 {
+        type Response = Response<Body>;
+        type Error = BoxError;
+        type Future = std::future::Ready<Result<Response<Body>, BoxError>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<Body>) -> Self::Future {
+            std::future::ready(Ok(Response::new(Body::empty())))
+        }
+    }
+
+    fn request(method: Method, origin: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().method(method);
+        if let Some(origin) = origin {
+            request = request.header(ORIGIN, origin);
+        }
+        request
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cors_layer() {
+        let mut service =
+            CorsLayer::new(vec!["https://explorer.example/".to_string()]).layer(Ok200);
+        let allowed = Some("https://explorer.example");
+
+        let preflight = service
+            .call(request(Method::OPTIONS, allowed))
+            .await
+            .unwrap();
+        assert_eq!(preflight.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            preflight.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://explorer.example"
+        );
+        assert_eq!(
+            preflight.headers()[ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+
+        let response = service.call(request(Method::POST, allowed)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://explorer.example"
+        );
+
+        // Other origins are turned away, requests without one pass untouched
+        let response = service
+            .call(request(Method::POST, Some("https://evil.example")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = service.call(request(Method::POST, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let mut any = CorsLayer::new(vec!["*".to_string()]).layer(Ok200);
+        let response = any
+            .call(request(Method::POST, Some("https://evil.example")))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        // Without origins the server doesn't do CORS
+        let mut none = CorsLayer::default().layer(Ok200);
+        let response = none.call(request(Method::OPTIONS, allowed)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
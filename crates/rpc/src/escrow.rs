@@ -0,0 +1,144 @@
+// escrows over rpc, the payer locks the funds and the arbiter, or one of the parties, settles them
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+use crate::channel::parse_signature;
+
+// a signed escrow tx, the signature is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum EscrowTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Create {
+        from: Address,
+        to: Address,
+        arbiter: Address,
+        amount: U256,
+        timeout: u64,
+        sequence: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Release {
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Refund {
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        signature: Bytes,
+    },
+}
+
+impl TryFrom<EscrowTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: EscrowTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            EscrowTxRequest::Create {
+                from,
+                to,
+                arbiter,
+                amount,
+                timeout,
+                sequence,
+                signature,
+            } => Tx::escrow_create(
+                from,
+                to,
+                arbiter,
+                amount,
+                timeout,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+            EscrowTxRequest::Release {
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                signature,
+            } => Tx::escrow_release(
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+            EscrowTxRequest::Refund {
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                signature,
+            } => Tx::escrow_refund(
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_request_from_json() {
+        let arbiter = PrivateKeySigner::random();
+        let payer = Address::repeat_byte(1);
+        let escrow_id = B256::repeat_byte(2);
+        let tx = Tx::escrow_release(arbiter.address(), payer, escrow_id, 4, None);
+        let signature = arbiter.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: EscrowTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "release",
+            "from": arbiter.address(),
+            "payer": payer,
+            "escrowId": escrow_id,
+            "sequence": 4,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(
+            Tx::try_from(request.clone()).unwrap().tx_hash(),
+            tx.tx_hash()
+        );
+
+        // A release signature doesn't make a refund
+        let EscrowTxRequest::Release {
+            from,
+            payer,
+            escrow_id,
+            sequence,
+            signature,
+        } = request
+        else {
+            unreachable!()
+        };
+        let forged = EscrowTxRequest::Refund {
+            from,
+            payer,
+            escrow_id,
+            sequence,
+            signature,
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
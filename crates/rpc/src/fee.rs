@@ -0,0 +1,103 @@
+// fastpay_estimateFee: the fee to offer for a transfer to land within a number of blocks. Blocks
+// take the highest fees in the mempool first, so a fee is as fast as the number of pending txs
+// paying at least as much. Full recent blocks show how much was paid to get in when blocks were
+// contested, that is the floor for each level, new txs keep coming after all
+
+use serde::{Deserialize, Serialize};
+
+// the blocks each level aims to land within
+const HIGH_TARGET_BLOCKS: u64 = 1;
+const MEDIUM_TARGET_BLOCKS: u64 = 3;
+const LOW_TARGET_BLOCKS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSuggestion {
+    pub fee: String,
+    // blocks until a transfer paying the fee is expected in one, 1 for the next block
+    pub expected_blocks: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    pub low: FeeSuggestion,
+    pub medium: FeeSuggestion,
+    pub high: FeeSuggestion,
+}
+
+impl FeeEstimate {
+    // `pending` are the fees waiting in the mempool, `recent` those paid in recent blocks that
+    // were full. A block holds `block_capacity` txs, and a transfer has to pay at least `min_fee`
+    pub fn new(pending: &[u64], recent: &[u64], block_capacity: usize, min_fee: u64) -> Self {
+        let block_capacity = block_capacity.max(1) as u64;
+        let mut pending = pending.to_vec();
+        pending.sort_unstable_by(|a, b| b.cmp(a));
+        let mut recent = recent.to_vec();
+        recent.sort_unstable();
+
+        let suggest = |target: u64, percentile: usize| {
+            // a tx goes behind the ones paying as much, beating the last that fits is enough
+            let queue_fee = pending
+                .get((target * block_capacity - 1) as usize)
+                .map_or(0, |fee| fee.saturating_add(1));
+            let floor = match recent.len() {
+                0 => 0,
+                len => recent[(len - 1) * percentile / 100],
+            };
+            let fee = queue_fee.max(floor).max(min_fee);
+            let ahead = pending
+                .iter()
+                .take_while(|pending| **pending >= fee)
+                .count() as u64;
+            FeeSuggestion {
+                fee: format!("{:#x}", fee),
+                expected_blocks: format!("{:#x}", ahead / block_capacity + 1),
+            }
+        };
+
+        Self {
+            low: suggest(LOW_TARGET_BLOCKS, 25),
+            medium: suggest(MEDIUM_TARGET_BLOCKS, 50),
+            high: suggest(HIGH_TARGET_BLOCKS, 90),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(fee: u64, expected_blocks: u64) -> FeeSuggestion {
+        FeeSuggestion {
+            fee: format!("{:#x}", fee),
+            expected_blocks: format!("{:#x}", expected_blocks),
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        // Nothing waiting and no contested blocks, everything gets in for free
+        let estimate = FeeEstimate::new(&[], &[], 2, 0);
+        assert_eq!(estimate.high, suggestion(0, 1));
+        assert_eq!(estimate.low, suggestion(0, 1));
+
+        // Three blocks' worth of txs, the next block needs 9 to beat the second highest
+        let pending = [1, 5, 9, 8, 5, 5];
+        let estimate = FeeEstimate::new(&pending, &[], 2, 0);
+        assert_eq!(estimate.high, suggestion(9, 1));
+        assert_eq!(estimate.medium, suggestion(2, 3));
+        assert_eq!(estimate.low, suggestion(0, 4));
+
+        // Contested recent blocks raise the floor of every level
+        let recent = [2, 3, 4, 10, 20];
+        let estimate = FeeEstimate::new(&pending, &recent, 2, 0);
+        assert_eq!(estimate.high, suggestion(10, 1));
+        assert_eq!(estimate.medium, suggestion(4, 3));
+        assert_eq!(estimate.low, suggestion(3, 3));
+
+        // Nothing below what the fee schedule asks for
+        let estimate = FeeEstimate::new(&pending, &recent, 2, 6);
+        assert_eq!(estimate.high, suggestion(10, 1));
+        assert_eq!(estimate.medium, suggestion(6, 2));
+    }
+}
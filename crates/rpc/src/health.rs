@@ -0,0 +1,107 @@
+// the limits past which a node counts as unhealthy, set by the operator when the node starts.
+// admin_healthThresholds reports them, and alerting_rules() turns them into Prometheus alerting
+// rules, so alerts fire on the limits the node runs with instead of ones copied by hand
+
+use serde::{Deserialize, Serialize};
+
+// the metrics the rules are written against
+pub const BLOCK_LAG_METRIC: &str = "fastpay_sync_lag_blocks";
+pub const MEMPOOL_TXS_METRIC: &str = "fastpay_mempool_txs";
+pub const MEMPOOL_CAPACITY_METRIC: &str = "fastpay_mempool_capacity";
+pub const PEERS_METRIC: &str = "fastpay_peers";
+
+// how long a threshold has to stay crossed before its alert fires
+const ALERT_FOR: &str = "2m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthThresholds {
+    // blocks the node may be behind the highest head its peers announced
+    pub max_block_lag: u64,
+    // how full the mempool may get, in percent of its capacity
+    pub max_mempool_saturation: u64,
+    // peers the node needs, 0 for a node that runs on its own
+    pub min_peers: u64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_block_lag: 5,
+            max_mempool_saturation: 90,
+            min_peers: 0,
+        }
+    }
+}
+
+impl HealthThresholds {
+    // a Prometheus rule file, one alert per threshold that is set
+    pub fn alerting_rules(&self) -> String {
+        let mut rules = vec![
+            alert(
+                "FastpayBlockLag",
+                &format!("{} > {}", BLOCK_LAG_METRIC, self.max_block_lag),
+                &format!(
+                    "{{{{ $labels.instance }}}} is more than {} blocks behind its peers",
+                    self.max_block_lag
+                ),
+            ),
+            alert(
+                "FastpayMempoolSaturated",
+                &format!(
+                    "100 * {} / {} > {}",
+                    MEMPOOL_TXS_METRIC, MEMPOOL_CAPACITY_METRIC, self.max_mempool_saturation
+                ),
+                &format!(
+                    "{{{{ $labels.instance }}}} mempool is more than {}% full",
+                    self.max_mempool_saturation
+                ),
+            ),
+        ];
+        if self.min_peers > 0 {
+            rules.push(alert(
+                "FastpayTooFewPeers",
+                &format!("{} < {}", PEERS_METRIC, self.min_peers),
+                &format!(
+                    "{{{{ $labels.instance }}}} has fewer than {} peers",
+                    self.min_peers
+                ),
+            ));
+        }
+        format!("groups:\n  - name: fastpay\n    rules:\n{}", rules.concat())
+    }
+}
+
+fn alert(name: &str, expr: &str, summary: &str) -> String {
+    format!(
+        "      - alert: {}\n        expr: {}\n        for: {}\n        labels:\n          \
+         severity: warning\n        annotations:\n          summary: \"{}\"\n",
+        name, expr, ALERT_FOR, summary
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerting_rules() {
+        let rules = HealthThresholds::default().alerting_rules();
+        assert!(rules.starts_with("groups:\n  - name: fastpay\n    rules:\n"));
+        assert!(rules.contains("        expr: fastpay_sync_lag_blocks > 5\n"));
+        assert!(rules
+            .contains("        expr: 100 * fastpay_mempool_txs / fastpay_mempool_capacity > 90\n"));
+        assert!(rules.contains("summary: \"{{ $labels.instance }} mempool is more than 90% full\""));
+        // No peers needed, no alert for them
+        assert_eq!(rules.matches("- alert:").count(), 2);
+
+        let rules = HealthThresholds {
+            min_peers: 3,
+            ..Default::default()
+        }
+        .alerting_rules();
+        assert!(
+            rules.contains("      - alert: FastpayTooFewPeers\n        expr: fastpay_peers < 3\n")
+        );
+    }
+}
@@ -0,0 +1,133 @@
+// htlcs over rpc, for atomic swaps: the sender locks the funds, the recipient claims them with the
+// secret and the sender refunds them once the timelock is reached
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+use crate::channel::parse_signature;
+
+// a signed htlc tx, the signature is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum HtlcTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Lock {
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        timelock: u64,
+        amount: U256,
+        sequence: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Claim {
+        from: Address,
+        // who locked it
+        sender: Address,
+        preimage: B256,
+        sequence: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Refund {
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        sequence: u64,
+        signature: Bytes,
+    },
+}
+
+impl TryFrom<HtlcTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: HtlcTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            HtlcTxRequest::Lock {
+                from,
+                recipient,
+                hashlock,
+                timelock,
+                amount,
+                sequence,
+                signature,
+            } => Tx::htlc_lock(
+                from,
+                recipient,
+                hashlock,
+                timelock,
+                amount,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+            HtlcTxRequest::Claim {
+                from,
+                sender,
+                preimage,
+                sequence,
+                signature,
+            } => Tx::htlc_claim(
+                from,
+                sender,
+                preimage,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+            HtlcTxRequest::Refund {
+                from,
+                recipient,
+                hashlock,
+                sequence,
+                signature,
+            } => Tx::htlc_refund(
+                from,
+                recipient,
+                hashlock,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_request_from_json() {
+        let recipient = PrivateKeySigner::random();
+        let sender = Address::repeat_byte(1);
+        let preimage = B256::repeat_byte(7);
+        let tx = Tx::htlc_claim(recipient.address(), sender, preimage, 2, None);
+        let signature = recipient.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: HtlcTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "claim",
+            "from": recipient.address(),
+            "sender": sender,
+            "preimage": preimage,
+            "sequence": 2,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(Tx::try_from(request).unwrap().tx_hash(), tx.tx_hash());
+
+        // Signed for another secret than the one revealed
+        let forged = HtlcTxRequest::Claim {
+            from: recipient.address(),
+            sender,
+            preimage: B256::repeat_byte(8),
+            sequence: 2,
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
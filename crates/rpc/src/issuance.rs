@@ -0,0 +1,93 @@
+// mints and burns over rpc, only the chain's issuer can send them and the vm is what checks it
+
+use alloy::primitives::{Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+use crate::channel::parse_signature;
+
+// a signed mint or burn, the signature is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum IssuanceTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Mint {
+        from: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Burn {
+        from: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Bytes,
+    },
+}
+
+impl TryFrom<IssuanceTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: IssuanceTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            IssuanceTxRequest::Mint {
+                from,
+                to,
+                amount,
+                sequence,
+                signature,
+            } => Tx::mint(
+                from,
+                to,
+                amount,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+            IssuanceTxRequest::Burn {
+                from,
+                amount,
+                sequence,
+                signature,
+            } => Tx::burn(from, amount, sequence, Some(parse_signature(&signature)?)),
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_request_from_json() {
+        let issuer = PrivateKeySigner::random();
+        let tx = Tx::burn(issuer.address(), U256::from(5), 2, None);
+        let signature = issuer.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: IssuanceTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "burn",
+            "from": issuer.address(),
+            "amount": U256::from(5),
+            "sequence": 2,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(Tx::try_from(request).unwrap().tx_hash(), tx.tx_hash());
+
+        // Signed by someone else than the sender it claims
+        let forged = IssuanceTxRequest::Mint {
+            from: issuer.address(),
+            to: Address::repeat_byte(1),
+            amount: U256::from(5),
+            sequence: 2,
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
@@ -1,25 +1,486 @@
+use alloy::primitives::{hex, Address, Bytes, PrimitiveSignature, B256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use asset::AssetTxRequest;
+use auth::{AuthConfig, AuthLayer};
+use block_builder::{
+    index::{AddressTx, Direction},
+    merkle::TxProof,
+    receipts::block_receipts,
+    BlockBuilder,
+};
+use bridge::BridgeTxRequest;
+use call::CallRequest;
+use channel::{ChannelInfo, ChannelTxRequest};
+use committee::{certificate::Certificate, store::CertificateStore};
+use cors::CorsLayer;
+use escrow::EscrowTxRequest;
+use fee::FeeEstimate;
+use health::HealthThresholds;
+use htlc::HtlcTxRequest;
+use issuance::IssuanceTxRequest;
 use jsonrpsee::{
-    core::{async_trait, RpcResult},
+    core::{async_trait, RpcResult, SubscriptionResult},
     proc_macros::rpc,
     server::ServerBuilder,
+    types::{
+        error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE, METHOD_NOT_FOUND_CODE},
+        ErrorObject,
+    },
+    PendingSubscriptionSink, RpcModule,
 };
+use limits::{MethodTimeouts, RpcLimits};
+use logger::RpcLogger;
+use logs::{LogFilter, RpcLog, MAX_LOG_BLOCK_RANGE};
+use mempool::Mempool;
+use namespace::Namespace;
+use netting::{NettingEngine, NettingError};
+use pagination::{Page, PageRequest, Position};
+use payments::PaymentNotification;
+use personal::{AuditEntry, KeyManager, PersonalTransferRequest};
+use policy::PolicyTxRequest;
+use preconf::{Preconfirmation, Preconfirmer};
+use proof::AccountProofResponse;
+use rate_limit::{RateLimitLayer, RateLimits};
+use schedule::ScheduleTxRequest;
 use serde::{Deserialize, Serialize};
+use state::{
+    account::Account,
+    channel::Channel,
+    escrow::Escrow,
+    htlc::Htlc,
+    pending::PendingState,
+    policy::SpendingPolicy,
+    root::{verify_account_proof, AccountProof},
+    schedule::Schedule,
+    sharded::ShardedState,
+    state::State,
+    supply::TotalSupply,
+};
 use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use subscription::{SubscriptionConfig, SubscriptionMetrics};
+use sync::{SyncStatus, Syncing};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+use transaction::{Transaction, TransactionReceipt, CHAIN_ID};
+use tx::asset::{token_address, AssetId};
+use tx::cancel::cancelled_by;
+use tx::log::logs_bloom;
+use tx::netting::SignedIntent;
+use tx::tx::Tx;
+use txpool::{TxPoolContent, TxPoolStatus};
+use vm::{schedule::ScheduledPayment, simulator::Simulator};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub mod asset;
+pub mod auth;
+pub mod bridge;
+pub mod call;
+pub mod channel;
+pub mod cors;
+pub mod escrow;
+pub mod fee;
+pub mod health;
+pub mod htlc;
+pub mod issuance;
+pub mod limits;
+pub mod logger;
+pub mod logs;
+pub mod namespace;
+pub mod pagination;
+pub mod payments;
+pub mod personal;
+pub mod policy;
+pub mod preconf;
+pub mod proof;
+pub mod rate_limit;
+pub mod schedule;
+pub mod subscription;
+pub mod sync;
+pub mod token;
+pub mod transaction;
+pub mod txpool;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     number: String,
     hash: String,
-    parentHash: String,
+    #[serde(rename = "parentHash")]
+    parent_hash: String,
+    #[serde(rename = "stateRoot")]
+    state_root: String,
+    // see block_builder::merkle
+    #[serde(rename = "transactionsRoot")]
+    transactions_root: String,
+    // see block_builder::Block::address_bloom
+    #[serde(rename = "addressBloom")]
+    address_bloom: String,
+    // zero for blocks sealed before logs
+    #[serde(rename = "logsBloom")]
+    logs_bloom: String,
     timestamp: String,
-    transactions: Vec<String>,
+    transactions: BlockTransactions,
+}
+
+// hashes unless the full transactions were asked for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BlockTransactions {
+    Hashes(Vec<String>),
+    Full(Vec<Transaction>),
+}
+
+impl BlockTransactions {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Hashes(hashes) => hashes.len(),
+            Self::Full(txs) => txs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Block {
+    pub fn new(block: &block_builder::Block, full_tx: bool) -> Self {
+        let transactions = if full_tx {
+            BlockTransactions::Full(
+                block
+                    .transactions
+                    .iter()
+                    .enumerate()
+                    .map(|(index, tx)| Transaction::new(tx, block, index))
+                    .collect(),
+            )
+        } else {
+            BlockTransactions::Hashes(block.transactions.iter().map(tx_hash_hex).collect())
+        };
+        Self {
+            number: format!("{:#x}", block.number),
+            hash: block.hash.to_string(),
+            parent_hash: block.parent_hash.to_string(),
+            state_root: block.state_root.to_string(),
+            transactions_root: block.tx_root.to_string(),
+            address_bloom: block.address_bloom.to_string(),
+            logs_bloom: block.logs_bloom_filter().unwrap_or_default().to_string(),
+            timestamp: format!("{:#x}", block.timestamp),
+            transactions,
+        }
+    }
+}
+
+impl From<&block_builder::Block> for Block {
+    fn from(block: &block_builder::Block) -> Self {
+        Self::new(block, false)
+    }
+}
+
+fn tx_hash_hex(tx: &Tx) -> String {
+    hex::encode_prefixed(tx.tx_hash())
+}
+
+// a transfer sent or received by an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    block_number: String,
+    transaction_index: String,
+    tx_hash: String,
+    from: Address,
+    to: Address,
+    // the token address of the asset moved, the zero address for the native token
+    token: Address,
+    amount: String,
+}
+
+// a tx an address is on either side of, for listing deposits and payments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferEntry {
+    block_number: String,
+    transaction_index: String,
+    tx_hash: String,
+    direction: Direction,
+    from: Address,
+    to: Address,
+    // as in HistoryEntry
+    token: Address,
+    amount: String,
+}
+
+// the total supply and where it is, for audits. `balanced` is whether the accounts and channels
+// add up to the total
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotalSupplyInfo {
+    pub total_supply: U256,
+    pub held: U256,
+    pub locked: U256,
+    pub balanced: bool,
+}
+
+impl From<TotalSupply> for TotalSupplyInfo {
+    fn from(supply: TotalSupply) -> Self {
+        Self {
+            total_supply: supply.total,
+            held: supply.held,
+            locked: supply.locked,
+            balanced: supply.is_balanced(),
+        }
+    }
+}
+
+// a signed transfer submitted to the node, `signature` is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferRequest {
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    pub signature: Bytes,
+    // set for a transfer order, which waits in the mempool until the sender's earlier ones are in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    // burned on top of the amount, the mempool hands out block space highest fee first
+    #[serde(default)]
+    pub fee: u64,
+    // the chain the transfer was signed for, None for transfers signed before chain ids
+    #[serde(default, rename = "chainId", skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+}
+
+impl TryFrom<TransferRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: TransferRequest) -> Result<Self, Self::Error> {
+        let signature = PrimitiveSignature::try_from(request.signature.as_ref())
+            .map_err(|e| format!("invalid signature: {}", e))?;
+        let mut tx = match request.sequence {
+            Some(sequence) => Tx::transfer_order(
+                request.from,
+                request.to,
+                request.amount,
+                sequence,
+                Some(signature),
+            ),
+            None => Tx::new(request.from, request.to, request.amount, Some(signature)),
+        }
+        .with_fee(request.fee);
+        if let Some(chain_id) = request.chain_id {
+            tx = tx.with_chain_id(chain_id);
+        }
+
+        Ok(tx)
+    }
+}
+
+// submitted txs must be signed by their sender, the vm checks again but the mempool shouldn't
+// hold txs that can never execute
+fn check_sender(tx: &Tx) -> Result<(), String> {
+    if tx.signature().is_none() {
+        return Err("missing signature".to_string());
+    }
+    if !tx.is_signed_by(tx.from()) {
+        return Err("signature does not match sender".to_string());
+    }
+    Ok(())
+}
+
+// AccountReader gives the rpc read access to the state the node is executing against
+pub trait AccountReader: Send + Sync {
+    fn get_account(&self, address: &Address) -> Option<Account>;
+
+    fn get_channel(&self, id: &B256) -> Option<Channel>;
+
+    // walks every account and channel, see state::supply
+    fn total_supply(&self) -> TotalSupply;
+
+    // both walk every account and channel, see state::root
+    fn state_root(&self) -> B256;
+
+    fn account_proof(&self, address: &Address) -> Option<AccountProof>;
+
+    // the asset at token address `token`, walks every account for one holding it
+    fn find_asset(&self, token: Address) -> Option<AssetId>;
+}
+
+impl<S: State + Send + Sync> AccountReader for ShardedState<S> {
+    fn get_account(&self, address: &Address) -> Option<Account> {
+        self.read_account(address)
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        self.read_channel(id)
+    }
+
+    fn total_supply(&self) -> TotalSupply {
+        TotalSupply::of(self)
+    }
+
+    fn state_root(&self) -> B256 {
+        State::state_root(self)
+    }
+
+    fn account_proof(&self, address: &Address) -> Option<AccountProof> {
+        State::account_proof(self, address)
+    }
+
+    fn find_asset(&self, token: Address) -> Option<AssetId> {
+        self.all_accounts()
+            .iter()
+            .flat_map(|account| account.assets().keys())
+            .find(|asset| token_address(asset) == token)
+            .copied()
+    }
+}
+
+fn invalid_params(message: String) -> ErrorObject<'static> {
+    ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>)
+}
+
+// whether eth_call and eth_estimateGas run on the pending state rather than the latest, only the
+// latest state is kept
+fn call_on_pending(block: Option<&str>) -> RpcResult<bool> {
+    match block.unwrap_or("latest") {
+        "pending" => Ok(true),
+        "latest" | "safe" | "finalized" => Ok(false),
+        other => Err(invalid_params(format!(
+            "calls only run on the latest or pending state, not {}",
+            other
+        ))),
+    }
+}
+
+// what Ethereum nodes answer a call that fails to execute with
+const EXECUTION_ERROR_CODE: i32 = -32000;
+
+// what nodes that drop old history, as in EIP-4444, answer a request for it with
+const PRUNED_CODE: i32 = 4444;
+
+// the block as a whole, refused when only its header is left, see block_builder::prune
+fn unpruned(block: block_builder::Block) -> RpcResult<block_builder::Block> {
+    if block.pruned {
+        return Err(ErrorObject::owned(
+            PRUNED_CODE,
+            format!("block {} was pruned, only its header is kept", block.number),
+            None::<()>,
+        ));
+    }
+    Ok(block)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    NewHeads,
+    NewPendingTransactions,
+}
+
+impl FromStr for SubscriptionKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "newHeads" => Ok(Self::NewHeads),
+            "newPendingTransactions" => Ok(Self::NewPendingTransactions),
+            _ => Err(format!("unsupported subscription kind: {}", kind)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NettingWindow {
+    pub window: u64,
+    pub authority: Address,
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    pub addr: SocketAddr,
+    pub subscriptions: SubscriptionConfig,
+    // preconfirmations are only offered when the producer's key is set
+    pub preconfirmer: Option<Preconfirmer>,
+    // intents are only collected when the node is a netting authority
+    pub netting: Option<NettingEngine>,
+    // shared with the node, which adds certificates as it settles them
+    pub certificates: CertificateStore,
+    // updated by the sync service while the node catches up with its peers
+    pub sync: SyncStatus,
+    // served by eth_chainId, submitted txs for another chain are turned away
+    pub chain_id: u64,
+    // the personal_ namespace is only served with node-managed keys, for development, and only on
+    // the authenticated address
+    pub keys: Option<KeyManager>,
+    // the mempool executed on top of the state, kept up to date by the node. Without it
+    // "pending" queries are answered from the latest state
+    pub pending: Option<PendingState>,
+    // the most txs the node puts in a block, fee estimates count how many blocks a fee waits
+    pub block_capacity: usize,
+    // served by admin_healthThresholds
+    pub health: HealthThresholds,
+    // eth_call and eth_estimateGas are only served with one, see vm::simulator
+    pub simulator: Option<Simulator>,
+    // batch and body sizes, and how long the calls reading many blocks may take
+    pub limits: RpcLimits,
+    pub namespaces: Vec<Namespace>,
+    // where the private namespaces and personal_ are served, see auth.rs. Without it they aren't
+    // served at all
+    pub auth: Option<AuthConfig>,
+    // the web origins browsers may call the public address from, see cors.rs
+    pub cors_origins: Vec<String>,
+    // requests the public address takes, from everyone and from each client
+    pub rate_limits: RateLimits,
+    // the node's scheduled payments as it makes them, for payment subscriptions. Without it they
+    // only see what txs pay
+    pub scheduled_payments: Option<broadcast::Sender<ScheduledPayment>>,
 }
 
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8545)),
+            subscriptions: SubscriptionConfig::default(),
+            preconfirmer: None,
+            netting: None,
+            certificates: CertificateStore::new(),
+            sync: SyncStatus::new(),
+            chain_id: CHAIN_ID,
+            keys: None,
+            pending: None,
+            block_capacity: DEFAULT_BLOCK_CAPACITY,
+            health: HealthThresholds::default(),
+            simulator: None,
+            limits: RpcLimits::default(),
+            namespaces: Namespace::DEFAULT.to_vec(),
+            auth: None,
+            cors_origins: Vec::new(),
+            rate_limits: RateLimits::default(),
+            scheduled_payments: None,
+        }
+    }
+}
+
+pub const DEFAULT_BLOCK_CAPACITY: usize = 1000;
+// how far back fastpay_estimateFee looks for contested blocks
+const FEE_HISTORY_BLOCKS: u64 = 20;
+
+// the most blocks a peer gets back from one fastpay_getBlocks call
+pub const MAX_BLOCKS_PER_REQUEST: u64 = 100;
+
 #[rpc(server)]
 pub trait EthRpc {
     #[method(name = "eth_getBalance")]
     async fn get_balance(&self, address: String, block: String) -> RpcResult<String>;
 
+    // the sender's next sequence number, "pending" counts its orders waiting in the mempool
+    #[method(name = "eth_getTransactionCount")]
+    async fn get_transaction_count(
+        &self,
+        address: Address,
+        block: Option<String>,
+    ) -> RpcResult<String>;
+
     #[method(name = "eth_getBlockByNumber")]
     async fn get_block_by_number(
         &self,
@@ -27,47 +488,2745 @@ pub trait EthRpc {
         full_tx: bool,
     ) -> RpcResult<Option<Block>>;
 
+    #[method(name = "eth_getBlockByHash")]
+    async fn get_block_by_hash(&self, hash: B256, full_tx: bool) -> RpcResult<Option<Block>>;
+
     #[method(name = "eth_blockNumber")]
     async fn block_number(&self) -> RpcResult<String>;
+
+    #[method(name = "eth_chainId")]
+    async fn chain_id(&self) -> RpcResult<String>;
+
+    #[method(name = "eth_getTransactionByHash")]
+    async fn get_transaction_by_hash(&self, hash: B256) -> RpcResult<Option<Transaction>>;
+
+    #[method(name = "eth_getTransactionReceipt")]
+    async fn get_transaction_receipt(&self, hash: B256) -> RpcResult<Option<TransactionReceipt>>;
+
+    // the receipts of every tx of a block, by number, tag or hash
+    #[method(name = "eth_getBlockReceipts")]
+    async fn get_block_receipts(&self, block: String)
+        -> RpcResult<Option<Vec<TransactionReceipt>>>;
+
+    #[method(name = "eth_syncing")]
+    async fn syncing(&self) -> RpcResult<Syncing>;
+
+    #[method(name = "eth_getLogs")]
+    async fn get_logs(&self, filter: LogFilter) -> RpcResult<Vec<RpcLog>>;
+
+    // empty output when the transfer would go through, the reason it wouldn't otherwise. A
+    // balanceOf call on a token address returns the holder's balance of that asset
+    #[method(name = "eth_call")]
+    async fn call(&self, request: CallRequest, block: Option<String>) -> RpcResult<Bytes>;
+
+    // there is no gas, a transfer that would go through needs none
+    #[method(name = "eth_estimateGas")]
+    async fn estimate_gas(&self, request: CallRequest, block: Option<String>) -> RpcResult<String>;
+
+    // a proof of the account against the head's state root. Only the latest state is kept, so
+    // `block` can only be "latest" or "pending". Accounts have no storage, `storage_keys` has to
+    // be empty
+    #[method(name = "eth_getProof")]
+    async fn get_account_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: Option<String>,
+    ) -> RpcResult<AccountProofResponse>;
+
+    #[subscription(name = "eth_subscribe" => "eth_subscription", unsubscribe = "eth_unsubscribe", item = serde_json::Value)]
+    async fn subscribe(&self, kind: String) -> SubscriptionResult;
+}
+
+#[rpc(server)]
+pub trait FastpayRpc {
+    // "payments" to an address, see payments.rs
+    #[subscription(name = "fastpay_subscribe" => "fastpay_subscription", unsubscribe = "fastpay_unsubscribe", item = PaymentNotification)]
+    async fn subscribe_payments(&self, kind: String, address: Address) -> SubscriptionResult;
+
+    #[method(name = "fastpay_getAccountHistory")]
+    async fn get_account_history(
+        &self,
+        address: Address,
+        page: Option<PageRequest>,
+    ) -> RpcResult<Page<HistoryEntry>>;
+
+    // the txs `address` sent or received in blocks `from_block` to `to_block`, both included and
+    // defaulting to the whole chain, oldest first
+    #[method(name = "fastpay_getTransfersByAddress")]
+    async fn get_transfers_by_address(
+        &self,
+        address: Address,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        page: Option<PageRequest>,
+    ) -> RpcResult<Page<TransferEntry>>;
+
+    // queues a signed transfer and returns its hash
+    #[method(name = "fastpay_sendTransfer")]
+    async fn send_transfer(&self, transfer: TransferRequest) -> RpcResult<String>;
+
+    // takes the sender's tx back out of the mempool, false once it has left it. `signature` is
+    // the sender's over tx::cancel::cancellation_message
+    #[method(name = "fastpay_cancelTransaction")]
+    async fn cancel_transaction(&self, hash: B256, signature: Bytes) -> RpcResult<bool>;
+
+    // queues a signed payment channel tx and returns its hash
+    #[method(name = "fastpay_sendChannelTx")]
+    async fn send_channel_tx(&self, request: ChannelTxRequest) -> RpcResult<String>;
+
+    // queues a signed mint or burn and returns its hash
+    #[method(name = "fastpay_sendIssuanceTx")]
+    async fn send_issuance_tx(&self, request: IssuanceTxRequest) -> RpcResult<String>;
+
+    // queues a signed deposit or redemption and returns its hash
+    #[method(name = "fastpay_sendBridgeTx")]
+    async fn send_bridge_tx(&self, request: BridgeTxRequest) -> RpcResult<String>;
+
+    // queues a signed SetPolicy tx and returns its hash
+    #[method(name = "fastpay_sendPolicyTx")]
+    async fn send_policy_tx(&self, request: PolicyTxRequest) -> RpcResult<String>;
+
+    // the spending policy the account is held to, None without one
+    #[method(name = "fastpay_getPolicy")]
+    async fn get_policy(&self, address: Address) -> RpcResult<Option<SpendingPolicy>>;
+
+    // queues a signed Schedule or CancelSchedule tx and returns its hash
+    #[method(name = "fastpay_sendScheduleTx")]
+    async fn send_schedule_tx(&self, request: ScheduleTxRequest) -> RpcResult<String>;
+
+    // the account's scheduled payments, in the order they were registered
+    #[method(name = "fastpay_getSchedules")]
+    async fn get_schedules(&self, address: Address) -> RpcResult<Vec<Schedule>>;
+
+    // queues a signed EscrowCreate, EscrowRelease or EscrowRefund tx and returns its hash
+    #[method(name = "fastpay_sendEscrowTx")]
+    async fn send_escrow_tx(&self, request: EscrowTxRequest) -> RpcResult<String>;
+
+    // the escrows the account has locked and that aren't settled yet
+    #[method(name = "fastpay_getEscrows")]
+    async fn get_escrows(&self, address: Address) -> RpcResult<Vec<Escrow>>;
+
+    // queues a signed HtlcLock, HtlcClaim or HtlcRefund tx and returns its hash
+    #[method(name = "fastpay_sendHtlcTx")]
+    async fn send_htlc_tx(&self, request: HtlcTxRequest) -> RpcResult<String>;
+
+    // the htlcs the account has locked and that aren't claimed or refunded yet
+    #[method(name = "fastpay_getHtlcs")]
+    async fn get_htlcs(&self, address: Address) -> RpcResult<Vec<Htlc>>;
+
+    // queues a signed asset Transfer or IssueAsset tx and returns its hash
+    #[method(name = "fastpay_sendAssetTx")]
+    async fn send_asset_tx(&self, request: AssetTxRequest) -> RpcResult<String>;
+
+    // how much of the asset the account holds, the native asset's id gives its balance
+    #[method(name = "fastpay_getAssetBalance")]
+    async fn get_asset_balance(&self, address: Address, asset: AssetId) -> RpcResult<U256>;
+
+    #[method(name = "fastpay_getChannel")]
+    async fn get_channel(&self, channel_id: B256) -> RpcResult<Option<ChannelInfo>>;
+
+    // like fastpay_sendTransfer, but answers with the producer's promise to include the transfer
+    #[method(name = "fastpay_sendTransferWithPreconf")]
+    async fn send_transfer_with_preconf(
+        &self,
+        transfer: TransferRequest,
+    ) -> RpcResult<Preconfirmation>;
+
+    // queues a signed intent for the current netting window
+    #[method(name = "fastpay_submitIntent")]
+    async fn submit_intent(&self, intent: SignedIntent) -> RpcResult<B256>;
+
+    #[method(name = "fastpay_nettingWindow")]
+    async fn netting_window(&self) -> RpcResult<NettingWindow>;
+
+    // the certificate a settled transfer was finalized with, the votes are returned as they were
+    // signed so the caller can check them against the committee instead of trusting this node
+    #[method(name = "fastpay_getCertificate")]
+    async fn get_certificate(&self, tx_hash: B256) -> RpcResult<Option<Certificate>>;
+
+    // up to `count` canonical blocks starting at `from`, with everything needed to re-execute
+    // them, so lagging nodes can catch up. Stops early at the head
+    #[method(name = "fastpay_getBlocks")]
+    async fn get_blocks(&self, from: u64, count: u64) -> RpcResult<Vec<block_builder::Block>>;
+
+    // fees to offer for a transfer to land in the next block, within a few or eventually
+    #[method(name = "fastpay_estimateFee")]
+    async fn estimate_fee(&self) -> RpcResult<FeeEstimate>;
+
+    // for debugging, walks the whole state on every call
+    #[method(name = "fastpay_totalSupply")]
+    async fn total_supply(&self) -> RpcResult<TotalSupplyInfo>;
+
+    // the header of the block holding a tx with proofs of the tx and its receipt under it, for
+    // light clients. None for txs not in a block
+    #[method(name = "fastpay_getProof")]
+    async fn get_proof(&self, tx_hash: B256) -> RpcResult<Option<TxProof>>;
+
+    // up to `count` canonical headers starting at `from`, blocks without their txs. Stops early
+    // at the head
+    #[method(name = "fastpay_getHeaders")]
+    async fn get_headers(&self, from: u64, count: u64) -> RpcResult<Vec<block_builder::Block>>;
+}
+
+#[rpc(server)]
+pub trait TxPoolRpc {
+    #[method(name = "txpool_content")]
+    async fn content(&self) -> RpcResult<TxPoolContent>;
+
+    #[method(name = "txpool_status")]
+    async fn status(&self) -> RpcResult<TxPoolStatus>;
+}
+
+// for operators, see health.rs
+#[rpc(server)]
+pub trait AdminRpc {
+    #[method(name = "admin_healthThresholds")]
+    async fn health_thresholds(&self) -> RpcResult<HealthThresholds>;
+}
+
+// raw encodings, for checking other implementations against this one
+#[rpc(server)]
+pub trait DebugRpc {
+    // the header a block's hash is taken over
+    #[method(name = "debug_getRawHeader")]
+    async fn get_raw_header(&self, block: String) -> RpcResult<Option<Bytes>>;
+
+    // the encoding a tx's hash is taken over, without its signatures
+    #[method(name = "debug_getRawTransaction")]
+    async fn get_raw_transaction(&self, hash: B256) -> RpcResult<Option<Bytes>>;
 }
 
-pub struct EthRpcServer;
+// keys the node holds and signs with, see personal.rs
+#[rpc(server)]
+pub trait PersonalRpc {
+    // creates a locked key and returns its address
+    #[method(name = "personal_newAccount")]
+    async fn new_account(&self, password: String) -> RpcResult<Address>;
+
+    #[method(name = "personal_listAccounts")]
+    async fn list_accounts(&self) -> RpcResult<Vec<Address>>;
+
+    // `duration` in seconds, the default unlock when not given
+    #[method(name = "personal_unlockAccount")]
+    async fn unlock_account(
+        &self,
+        address: Address,
+        password: String,
+        duration: Option<u64>,
+    ) -> RpcResult<bool>;
+
+    #[method(name = "personal_lockAccount")]
+    async fn lock_account(&self, address: Address) -> RpcResult<bool>;
+
+    // signs the transfer with the sender's unlocked key and queues it, returns its hash
+    #[method(name = "personal_sendTransfer")]
+    async fn send_personal_transfer(&self, transfer: PersonalTransferRequest) -> RpcResult<String>;
+
+    #[method(name = "personal_auditLog")]
+    async fn audit_log(&self) -> RpcResult<Vec<AuditEntry>>;
+}
+
+#[derive(Clone)]
+pub struct EthRpcImpl {
+    block_builder: BlockBuilder,
+    mempool: Mempool,
+    accounts: Arc<dyn AccountReader>,
+    subscriptions: SubscriptionConfig,
+    subscription_metrics: Arc<SubscriptionMetrics>,
+    preconfirmer: Option<Arc<Preconfirmer>>,
+    netting: Option<NettingEngine>,
+    certificates: CertificateStore,
+    sync: SyncStatus,
+    chain_id: u64,
+    keys: Option<KeyManager>,
+    pending: Option<PendingState>,
+    block_capacity: usize,
+    health: HealthThresholds,
+    simulator: Option<Simulator>,
+    timeouts: MethodTimeouts,
+    scheduled_payments: Option<broadcast::Sender<ScheduledPayment>>,
+}
+
+impl EthRpcImpl {
+    pub fn new(
+        block_builder: BlockBuilder,
+        mempool: Mempool,
+        accounts: Arc<dyn AccountReader>,
+        subscriptions: SubscriptionConfig,
+    ) -> Self {
+        Self {
+            block_builder,
+            mempool,
+            accounts,
+            subscriptions,
+            subscription_metrics: Arc::new(SubscriptionMetrics::new()),
+            preconfirmer: None,
+            netting: None,
+            certificates: CertificateStore::new(),
+            sync: SyncStatus::new(),
+            chain_id: CHAIN_ID,
+            keys: None,
+            pending: None,
+            block_capacity: DEFAULT_BLOCK_CAPACITY,
+            health: HealthThresholds::default(),
+            simulator: None,
+            timeouts: MethodTimeouts::default(),
+            scheduled_payments: None,
+        }
+    }
+
+    pub fn with_preconfirmer(mut self, preconfirmer: Preconfirmer) -> Self {
+        self.preconfirmer = Some(Arc::new(preconfirmer));
+        self
+    }
+
+    pub fn with_netting(mut self, netting: NettingEngine) -> Self {
+        self.netting = Some(netting);
+        self
+    }
+
+    pub fn with_certificates(mut self, certificates: CertificateStore) -> Self {
+        self.certificates = certificates;
+        self
+    }
+
+    pub fn with_sync_status(mut self, sync: SyncStatus) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_keys(mut self, keys: KeyManager) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    pub fn with_pending_state(mut self, pending: PendingState) -> Self {
+        self.pending = Some(pending);
+        self
+    }
+
+    pub fn with_block_capacity(mut self, block_capacity: usize) -> Self {
+        self.block_capacity = block_capacity;
+        self
+    }
+
+    pub fn with_health_thresholds(mut self, health: HealthThresholds) -> Self {
+        self.health = health;
+        self
+    }
+
+    pub fn with_method_timeouts(mut self, timeouts: MethodTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn with_scheduled_payments(
+        mut self,
+        scheduled_payments: broadcast::Sender<ScheduledPayment>,
+    ) -> Self {
+        self.scheduled_payments = Some(scheduled_payments);
+        self
+    }
+
+    pub fn with_simulator(mut self, simulator: Simulator) -> Self {
+        self.simulator = Some(simulator);
+        self
+    }
+
+    // the rpc `config` describes, over the node's handles. Other servers of the node, like the
+    // grpc one, build theirs the same way so they admit txs as the JSON-RPC server does
+    pub fn from_config(
+        config: &RpcConfig,
+        block_builder: BlockBuilder,
+        mempool: Mempool,
+        accounts: Arc<dyn AccountReader>,
+    ) -> Self {
+        let mut rpc = Self::new(block_builder, mempool, accounts, config.subscriptions)
+            .with_certificates(config.certificates.clone())
+            .with_sync_status(config.sync.clone())
+            .with_chain_id(config.chain_id)
+            .with_block_capacity(config.block_capacity)
+            .with_health_thresholds(config.health)
+            .with_method_timeouts(config.limits.timeouts.clone());
+        if let Some(simulator) = &config.simulator {
+            rpc = rpc.with_simulator(simulator.clone());
+        }
+        if let Some(preconfirmer) = &config.preconfirmer {
+            rpc = rpc.with_preconfirmer(preconfirmer.clone());
+        }
+        if let Some(netting) = &config.netting {
+            rpc = rpc.with_netting(netting.clone());
+        }
+        if let Some(keys) = &config.keys {
+            rpc = rpc.with_keys(keys.clone());
+        }
+        if let Some(pending) = &config.pending {
+            rpc = rpc.with_pending_state(pending.clone());
+        }
+        if let Some(scheduled_payments) = &config.scheduled_payments {
+            rpc = rpc.with_scheduled_payments(scheduled_payments.clone());
+        }
+        rpc
+    }
+
+    pub fn block_builder(&self) -> &BlockBuilder {
+        &self.block_builder
+    }
+
+    pub fn accounts(&self) -> &Arc<dyn AccountReader> {
+        &self.accounts
+    }
+
+    // the tx of the call run on the latest state, or with the mempool applied for "pending"
+    async fn simulate(&self, request: CallRequest, block: Option<String>) -> RpcResult<()> {
+        let simulator = self.simulator.as_ref().ok_or_else(|| {
+            ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                "calls are not enabled on this node",
+                None::<()>,
+            )
+        })?;
+        let pending = call_on_pending(block.as_deref())?;
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let next_block = self
+            .block_builder
+            .get_latest_block_number()
+            .await
+            .to::<u64>();
+        match simulator.simulate(&tx, next_block, pending) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(ErrorObject::owned(
+                EXECUTION_ERROR_CODE,
+                error.reason(),
+                None::<()>,
+            )),
+        }
+    }
+
+    fn keys(&self) -> RpcResult<&KeyManager> {
+        self.keys.as_ref().ok_or_else(|| {
+            ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                "node-managed keys are not enabled on this node",
+                None::<()>,
+            )
+        })
+    }
+
+    // a mempool with a signature cache checks signatures itself, recovering through the cache
+    // the vm uses
+    fn check_signature(&self, tx: &Tx) -> RpcResult<()> {
+        if self.mempool.signature_cache().is_some() {
+            return Ok(());
+        }
+        check_sender(tx).map_err(invalid_params)
+    }
+
+    // the vm would reject it too, but only once it left the mempool
+    fn check_chain_id(&self, tx: &Tx) -> RpcResult<()> {
+        match tx.chain_id() {
+            Some(chain_id) if chain_id != self.chain_id => Err(invalid_params(format!(
+                "transaction is for chain {}, this is chain {}",
+                chain_id, self.chain_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    async fn admit(&self, tx: Tx) -> RpcResult<()> {
+        let span = tracing::debug_span!("admit_tx", tx_hash = %tx_hash_hex(&tx), from = %tx.from());
+        async {
+            let result = self.mempool.add_tx(tx).await;
+            if let Err(e) = &result {
+                tracing::debug!(reason = ?e, "tx rejected by the mempool");
+            }
+            result.map_err(|e| invalid_params(format!("transaction rejected: {:?}", e)))
+        }
+        .instrument(span)
+        .await
+    }
+
+    fn netting(&self) -> RpcResult<&NettingEngine> {
+        self.netting.as_ref().ok_or_else(|| {
+            ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                "netting is not enabled on this node",
+                None::<()>,
+            )
+        })
+    }
+
+    pub fn subscription_metrics(&self) -> Arc<SubscriptionMetrics> {
+        self.subscription_metrics.clone()
+    }
+
+    // the canonical block holding the tx and its index there
+    async fn find_transaction(&self, hash: B256) -> Option<(block_builder::Block, usize)> {
+        self.block_builder.get_transaction(hash).await
+    }
+
+    // the canonical block `block` names, a number, a tag or a hash
+    async fn find_block(&self, block: &str) -> RpcResult<Option<block_builder::Block>> {
+        let number = match block {
+            "latest" | "pending" | "safe" | "finalized" => {
+                return Ok(self.block_builder.get_latest_block().await);
+            }
+            "earliest" => U256::ZERO,
+            hash if hash.len() == 66 => {
+                let hash = B256::from_str(hash)
+                    .map_err(|_| invalid_params(format!("invalid block hash: {}", hash)))?;
+                return Ok(self.block_builder.get_block_by_hash(hash).await);
+            }
+            number => U256::from_str(number)
+                .map_err(|_| invalid_params(format!("invalid block number: {}", number)))?,
+        };
+        Ok(self.block_builder.get_block(number).await)
+    }
+
+    // the receipts of `block`'s txs as stored with it. A block that wasn't executed here has
+    // none, its txs still went through but their logs are unknown
+    async fn block_receipts(&self, block: &block_builder::Block) -> Vec<TransactionReceipt> {
+        let stored = match self.block_builder.get_block_receipts(block.hash).await {
+            Some(receipts) => receipts,
+            None => block_receipts(block, &[]),
+        };
+        let logs: Vec<_> = stored.iter().map(|receipt| receipt.logs.clone()).collect();
+        stored
+            .iter()
+            .zip(RpcLog::from_block(block, &logs))
+            .zip(&block.transactions)
+            .map(|((receipt, rpc_logs), tx)| {
+                TransactionReceipt::new(tx, block, receipt.index)
+                    .with_gas(receipt)
+                    .with_logs(rpc_logs, logs_bloom(&receipt.logs))
+            })
+            .collect()
+    }
+
+    // one page of the txs `address` is on either side of in `blocks`, from the block builder's
+    // index, with the txs themselves
+    async fn address_page(
+        &self,
+        address: &Address,
+        blocks: std::ops::Range<u64>,
+        page: &PageRequest,
+    ) -> RpcResult<Page<(AddressTx, Tx)>> {
+        let txs = self.block_builder.get_address_txs(address, blocks).await;
+        let positions = txs.into_iter().map(|tx| {
+            let position = Position::new(tx.location.block_number, tx.location.index as u64);
+            (position, tx)
+        });
+        let page = pagination::paginate(positions, page).map_err(invalid_params)?;
+
+        let mut items = Vec::with_capacity(page.items.len());
+        let mut block: Option<block_builder::Block> = None;
+        for tx in page.items {
+            let number = U256::from(tx.location.block_number);
+            if block.as_ref().map(|block| block.number) != Some(number) {
+                block = self.block_builder.get_block(number).await;
+            }
+            // a reorg between reading the index and the block leaves the tx out
+            if let Some(block_tx) = block
+                .as_ref()
+                .and_then(|block| block.transactions.get(tx.location.index))
+            {
+                items.push((tx, block_tx.clone()));
+            }
+        }
+        Ok(Page {
+            items,
+            next_cursor: page.next_cursor,
+            has_more: page.has_more,
+        })
+    }
+}
 
 #[async_trait]
-impl EthRpc for EthRpcServer {
-    async fn get_balance(&self, _address: String, _block: String) -> RpcResult<String> {
-        // Return a dummy balance of 1 ETH
-        Ok("0xde0b6b3a7640000".to_string()) // 1 ETH in wei
+impl EthRpcServer for EthRpcImpl {
+    // only the latest state is kept, so any block but "pending" reads it
+    async fn get_balance(&self, address: String, block: String) -> RpcResult<String> {
+        let address = Address::from_str(&address)
+            .map_err(|_| invalid_params(format!("invalid address: {}", address)))?;
+        let account = match (&self.pending, block.as_str()) {
+            (Some(pending), "pending") => pending.get_account(&address),
+            _ => self.accounts.get_account(&address),
+        };
+        let balance = account.map(|account| account.balance()).unwrap_or_default();
+        Ok(format!("{:#x}", balance))
+    }
+
+    // as for balances, any block but "pending" reads the latest state
+    async fn get_transaction_count(
+        &self,
+        address: Address,
+        block: Option<String>,
+    ) -> RpcResult<String> {
+        let latest = self
+            .accounts
+            .get_account(&address)
+            .map_or(0, |account| account.sequence());
+        if block.as_deref() != Some("pending") {
+            return Ok(format!("{:#x}", latest));
+        }
+        let executed = self
+            .pending
+            .as_ref()
+            .and_then(|pending| pending.get_account(&address))
+            .map_or(latest, |account| account.sequence().max(latest));
+        let next = self.mempool.next_sequence(&address).await.max(executed);
+        Ok(format!("{:#x}", next))
     }
 
     async fn get_block_by_number(
         &self,
         block_number: String,
-        _full_tx: bool,
+        full_tx: bool,
     ) -> RpcResult<Option<Block>> {
-        // Return a dummy block
-        Ok(Some(Block {
-            number: block_number,
-            hash: "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef".to_string(),
-            parentHash: "0xabcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890"
-                .to_string(),
-            timestamp: "0x5f5e100".to_string(), // Current timestamp
-            transactions: vec![],
-        }))
+        self.timeouts
+            .run("eth_getBlockByNumber", async {
+                Ok(self
+                    .find_block(&block_number)
+                    .await?
+                    .map(unpruned)
+                    .transpose()?
+                    .map(|block| Block::new(&block, full_tx)))
+            })
+            .await
+    }
+
+    async fn get_block_by_hash(&self, hash: B256, full_tx: bool) -> RpcResult<Option<Block>> {
+        self.timeouts
+            .run("eth_getBlockByHash", async {
+                Ok(self
+                    .block_builder
+                    .get_block_by_hash(hash)
+                    .await
+                    .map(unpruned)
+                    .transpose()?
+                    .map(|block| Block::new(&block, full_tx)))
+            })
+            .await
     }
 
     async fn block_number(&self) -> RpcResult<String> {
-        // Return a dummy block number
-        Ok("0x1234".to_string())
+        let head = match self.block_builder.get_latest_block().await {
+            Some(block) => block.number,
+            None => U256::ZERO,
+        };
+        Ok(format!("{:#x}", head))
+    }
+
+    async fn chain_id(&self) -> RpcResult<String> {
+        Ok(format!("{:#x}", self.chain_id))
+    }
+
+    async fn get_transaction_by_hash(&self, hash: B256) -> RpcResult<Option<Transaction>> {
+        Ok(self
+            .find_transaction(hash)
+            .await
+            .map(|(block, index)| Transaction::new(&block.transactions[index], &block, index)))
+    }
+
+    async fn get_transaction_receipt(&self, hash: B256) -> RpcResult<Option<TransactionReceipt>> {
+        let Some((block, index)) = self.find_transaction(hash).await else {
+            return Ok(None);
+        };
+        Ok(self.block_receipts(&block).await.into_iter().nth(index))
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block: String,
+    ) -> RpcResult<Option<Vec<TransactionReceipt>>> {
+        self.timeouts
+            .run("eth_getBlockReceipts", async {
+                match self.find_block(&block).await? {
+                    Some(block) => Ok(Some(self.block_receipts(&unpruned(block)?).await)),
+                    None => Ok(None),
+                }
+            })
+            .await
+    }
+
+    async fn syncing(&self) -> RpcResult<Syncing> {
+        Ok(Syncing::from(self.sync.progress()))
+    }
+
+    async fn get_logs(&self, filter: LogFilter) -> RpcResult<Vec<RpcLog>> {
+        self.timeouts
+            .run("eth_getLogs", async {
+                let blocks = match filter.block_hash {
+                    Some(hash) => {
+                        let block = self
+                            .block_builder
+                            .get_block_by_hash(hash)
+                            .await
+                            .ok_or_else(|| invalid_params(format!("unknown block: {}", hash)))?;
+                        vec![block]
+                    }
+                    None => {
+                        let latest = match self.block_builder.get_latest_block().await {
+                            Some(block) => block.number,
+                            None => return Ok(Vec::new()),
+                        };
+                        let from = logs::block_number(filter.from_block.as_deref(), latest)
+                            .map_err(invalid_params)?;
+                        let to = logs::block_number(filter.to_block.as_deref(), latest)
+                            .map_err(invalid_params)?
+                            .min(latest);
+                        if from > to {
+                            return Ok(Vec::new());
+                        }
+                        if to - from >= U256::from(MAX_LOG_BLOCK_RANGE) {
+                            return Err(invalid_params(format!(
+                                "block range is limited to {} blocks",
+                                MAX_LOG_BLOCK_RANGE
+                            )));
+                        }
+                        let mut blocks = Vec::new();
+                        let mut number = from;
+                        while number <= to {
+                            if let Some(block) = self.block_builder.get_block(number).await {
+                                blocks.push(block);
+                            }
+                            number += U256::from(1);
+                        }
+                        blocks
+                    }
+                };
+
+                let mut matching = Vec::new();
+                for block in blocks {
+                    if let Some(bloom) = block.logs_bloom_filter() {
+                        if !filter.may_match(&bloom) {
+                            continue;
+                        }
+                    }
+                    let block = unpruned(block)?;
+                    let logs = self.block_builder.get_logs(block.hash).await;
+                    matching.extend(
+                        RpcLog::from_block(&block, &logs)
+                            .into_iter()
+                            .flatten()
+                            .zip(logs.iter().flatten())
+                            .filter(|(_, log)| filter.matches(log))
+                            .map(|(rpc_log, _)| rpc_log),
+                    );
+                }
+                Ok(matching)
+            })
+            .await
+    }
+
+    async fn call(&self, request: CallRequest, block: Option<String>) -> RpcResult<Bytes> {
+        // a state read, it needs no simulator
+        if let Some(holder) = token::balance_of(&request).map_err(invalid_params)? {
+            let account = match (&self.pending, call_on_pending(block.as_deref())?) {
+                (Some(pending), true) => pending.get_account(&holder),
+                _ => self.accounts.get_account(&holder),
+            };
+            let balance = match token::token_balance(account.as_ref(), request.to) {
+                Some(balance) => balance,
+                None if self.accounts.find_asset(request.to).is_some() => U256::ZERO,
+                None => {
+                    return Err(invalid_params(format!(
+                        "no asset has the token address {}",
+                        request.to
+                    )))
+                }
+            };
+            return Ok(token::encode_balance(balance));
+        }
+        self.simulate(request, block).await?;
+        Ok(Bytes::new())
+    }
+
+    async fn estimate_gas(&self, request: CallRequest, block: Option<String>) -> RpcResult<String> {
+        self.simulate(request, block).await?;
+        Ok("0x0".to_string())
+    }
+
+    async fn get_account_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: Option<String>,
+    ) -> RpcResult<AccountProofResponse> {
+        if !storage_keys.is_empty() {
+            return Err(invalid_params("accounts have no storage".to_string()));
+        }
+        // a proof against the head wouldn't verify against an older block's root
+        if let Some(other) = block
+            .as_deref()
+            .filter(|block| !matches!(*block, "latest" | "pending"))
+        {
+            return Err(invalid_params(format!(
+                "proofs are only taken against the latest state, not {}",
+                other
+            )));
+        }
+        let Some(head) = self.block_builder.get_latest_block().await else {
+            return Err(invalid_params("no block to prove against yet".to_string()));
+        };
+        let proof = self.accounts.account_proof(&address);
+        // a block produced while the proof was taken leaves it against a state no block has
+        let state_root = self.accounts.state_root();
+        let consistent = proof
+            .as_ref()
+            .is_none_or(|proof| verify_account_proof(state_root, proof));
+        if state_root != head.state_root || !consistent {
+            return Err(ErrorObject::owned(
+                INTERNAL_ERROR_CODE,
+                format!("the state has moved past block {}, try again", head.hash),
+                None::<()>,
+            ));
+        }
+        Ok(AccountProofResponse::new(
+            address, head.hash, state_root, proof,
+        ))
+    }
+
+    async fn subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: String,
+    ) -> SubscriptionResult {
+        let kind = match SubscriptionKind::from_str(&kind) {
+            Ok(kind) => kind,
+            Err(message) => {
+                pending.reject(invalid_params(message)).await;
+                return Ok(());
+            }
+        };
+
+        let sink = pending.accept().await?;
+        let config = self.subscriptions;
+        let metrics = self.subscription_metrics.clone();
+
+        match kind {
+            SubscriptionKind::NewHeads => {
+                let receiver = self.block_builder.subscribe_new_heads();
+                tokio::spawn(subscription::forward(
+                    receiver,
+                    sink,
+                    config,
+                    metrics,
+                    |block: &block_builder::Block| Block::from(block),
+                ));
+            }
+            SubscriptionKind::NewPendingTransactions => {
+                let receiver = self.mempool.subscribe_pending_txs();
+                tokio::spawn(subscription::forward(
+                    receiver,
+                    sink,
+                    config,
+                    metrics,
+                    tx_hash_hex,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FastpayRpcServer for EthRpcImpl {
+    async fn subscribe_payments(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: String,
+        address: Address,
+    ) -> SubscriptionResult {
+        if kind != "payments" {
+            pending
+                .reject(invalid_params(format!(
+                    "unsupported subscription kind: {}",
+                    kind
+                )))
+                .await;
+            return Ok(());
+        }
+
+        let sink = pending.accept().await?;
+        let receiver = payments::watch(
+            self.block_builder.clone(),
+            self.scheduled_payments
+                .as_ref()
+                .map(broadcast::Sender::subscribe),
+            address,
+            self.subscriptions.buffer_size,
+        );
+        tokio::spawn(subscription::forward(
+            receiver,
+            sink,
+            self.subscriptions,
+            self.subscription_metrics.clone(),
+            PaymentNotification::clone,
+        ));
+        Ok(())
+    }
+
+    async fn get_account_history(
+        &self,
+        address: Address,
+        page: Option<PageRequest>,
+    ) -> RpcResult<Page<HistoryEntry>> {
+        self.timeouts
+            .run("fastpay_getAccountHistory", async {
+                let page = page.unwrap_or_default();
+                // blocks before the cursor can't be on this page
+                let after = page.after().map_err(invalid_params)?;
+                let from_block = after.map_or(0, |after| after.block);
+
+                let page = self
+                    .address_page(&address, from_block..u64::MAX, &page)
+                    .await?;
+                Ok(page.map(|(tx, block_tx)| HistoryEntry {
+                    block_number: format!("{:#x}", tx.location.block_number),
+                    transaction_index: format!("{:#x}", tx.location.index),
+                    tx_hash: tx_hash_hex(&block_tx),
+                    from: block_tx.from(),
+                    to: block_tx.to(),
+                    token: token_address(&block_tx.asset()),
+                    amount: format!("{:#x}", block_tx.amount()),
+                }))
+            })
+            .await
+    }
+
+    async fn get_transfers_by_address(
+        &self,
+        address: Address,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        page: Option<PageRequest>,
+    ) -> RpcResult<Page<TransferEntry>> {
+        self.timeouts
+            .run("fastpay_getTransfersByAddress", async {
+                let blocks =
+                    from_block.unwrap_or(0)..to_block.map_or(u64::MAX, |to| to.saturating_add(1));
+                let page = self
+                    .address_page(&address, blocks, &page.unwrap_or_default())
+                    .await?;
+                Ok(page.map(|(tx, block_tx)| TransferEntry {
+                    block_number: format!("{:#x}", tx.location.block_number),
+                    transaction_index: format!("{:#x}", tx.location.index),
+                    tx_hash: hex::encode_prefixed(tx.tx_hash),
+                    direction: tx.direction,
+                    from: block_tx.from(),
+                    to: block_tx.to(),
+                    token: token_address(&block_tx.asset()),
+                    amount: format!("{:#x}", block_tx.amount()),
+                }))
+            })
+            .await
+    }
+
+    async fn send_transfer(&self, transfer: TransferRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(transfer).map_err(invalid_params)?;
+        self.check_signature(&tx)?;
+        self.check_chain_id(&tx)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn cancel_transaction(&self, hash: B256, signature: Bytes) -> RpcResult<bool> {
+        let signature = PrimitiveSignature::try_from(signature.as_ref())
+            .map_err(|e| invalid_params(format!("invalid signature: {}", e)))?;
+        let tx_hash = hash.to_vec().into();
+        let Some(tx) = self.mempool.get(&tx_hash).await else {
+            return Ok(false);
+        };
+        if cancelled_by(hash.as_slice(), &signature) != Some(tx.from()) {
+            return Err(invalid_params(
+                "the cancellation isn't signed by the sender".to_string(),
+            ));
+        }
+        Ok(self.mempool.remove(&tx_hash).await.is_some())
+    }
+
+    async fn send_channel_tx(&self, request: ChannelTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn send_issuance_tx(&self, request: IssuanceTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn send_bridge_tx(&self, request: BridgeTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn send_policy_tx(&self, request: PolicyTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn get_policy(&self, address: Address) -> RpcResult<Option<SpendingPolicy>> {
+        Ok(self
+            .accounts
+            .get_account(&address)
+            .and_then(|account| account.policy().cloned()))
+    }
+
+    async fn send_schedule_tx(&self, request: ScheduleTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn get_schedules(&self, address: Address) -> RpcResult<Vec<Schedule>> {
+        Ok(self
+            .accounts
+            .get_account(&address)
+            .map(|account| account.schedules().to_vec())
+            .unwrap_or_default())
+    }
+
+    async fn send_escrow_tx(&self, request: EscrowTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn get_escrows(&self, address: Address) -> RpcResult<Vec<Escrow>> {
+        Ok(self
+            .accounts
+            .get_account(&address)
+            .map(|account| account.escrows().to_vec())
+            .unwrap_or_default())
+    }
+
+    async fn send_htlc_tx(&self, request: HtlcTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn get_htlcs(&self, address: Address) -> RpcResult<Vec<Htlc>> {
+        Ok(self
+            .accounts
+            .get_account(&address)
+            .map(|account| account.htlcs().to_vec())
+            .unwrap_or_default())
+    }
+
+    async fn send_asset_tx(&self, request: AssetTxRequest) -> RpcResult<String> {
+        let tx = Tx::try_from(request).map_err(invalid_params)?;
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn get_asset_balance(&self, address: Address, asset: AssetId) -> RpcResult<U256> {
+        Ok(self
+            .accounts
+            .get_account(&address)
+            .map(|account| account.asset_balance(&asset))
+            .unwrap_or_default())
+    }
+
+    async fn get_channel(&self, channel_id: B256) -> RpcResult<Option<ChannelInfo>> {
+        Ok(self
+            .accounts
+            .get_channel(&channel_id)
+            .map(|channel| ChannelInfo::from(&channel)))
+    }
+
+    async fn send_transfer_with_preconf(
+        &self,
+        transfer: TransferRequest,
+    ) -> RpcResult<Preconfirmation> {
+        let Some(preconfirmer) = &self.preconfirmer else {
+            return Err(ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                "preconfirmations are not enabled on this node",
+                None::<()>,
+            ));
+        };
+
+        let tx = Tx::try_from(transfer).map_err(invalid_params)?;
+        self.check_signature(&tx)?;
+        self.check_chain_id(&tx)?;
+        let tx_hash = B256::from_slice(&tx.tx_hash());
+
+        // read before admission so the promise doesn't count blocks sealed in between against us
+        let next_block = self
+            .block_builder
+            .get_latest_block_number()
+            .await
+            .to::<u64>();
+        let queue_position = self.mempool.len().await;
+
+        self.admit(tx).await?;
+
+        preconfirmer
+            .issue(tx_hash, next_block, queue_position)
+            .map_err(|e| ErrorObject::owned(INTERNAL_ERROR_CODE, e, None::<()>))
+    }
+
+    async fn submit_intent(&self, intent: SignedIntent) -> RpcResult<B256> {
+        let hash = intent.intent.hash();
+        self.netting()?.submit(intent).await.map_err(|e| match e {
+            NettingError::WrongWindow { current, intent } => invalid_params(format!(
+                "intent is for window {}, the current one is {}",
+                intent, current
+            )),
+            NettingError::AlreadyKnown => invalid_params("intent already submitted".to_string()),
+            NettingError::InvalidIntent(e) | NettingError::Settlement(e) => invalid_params(e),
+        })?;
+        Ok(hash)
+    }
+
+    async fn netting_window(&self) -> RpcResult<NettingWindow> {
+        let netting = self.netting()?;
+        Ok(NettingWindow {
+            window: netting.window().await,
+            authority: netting.authority(),
+        })
+    }
+
+    async fn get_certificate(&self, tx_hash: B256) -> RpcResult<Option<Certificate>> {
+        Ok(self.certificates.get(&tx_hash))
+    }
+
+    async fn get_blocks(&self, from: u64, count: u64) -> RpcResult<Vec<block_builder::Block>> {
+        self.timeouts
+            .run("fastpay_getBlocks", async {
+                let mut blocks = Vec::new();
+                for number in from..from.saturating_add(count.min(MAX_BLOCKS_PER_REQUEST)) {
+                    // a peer can't import a header, it has to sync them from someone else
+                    match self.block_builder.get_block(U256::from(number)).await {
+                        Some(block) => blocks.push(unpruned(block)?),
+                        None => break,
+                    }
+                }
+                Ok(blocks)
+            })
+            .await
+    }
+
+    async fn estimate_fee(&self) -> RpcResult<FeeEstimate> {
+        let pending: Vec<u64> = self.mempool.pending().await.iter().map(Tx::fee).collect();
+        let head = self.block_builder.get_latest_block_number().await;
+        let mut recent = Vec::new();
+        let mut number = head;
+        while number > U256::ZERO && head - number < U256::from(FEE_HISTORY_BLOCKS) {
+            number -= U256::from(1);
+            let Some(block) = self.block_builder.get_block(number).await else {
+                continue;
+            };
+            // a block with room left took every tx it was offered, whatever they paid
+            if block.transactions.len() >= self.block_capacity {
+                recent.extend(block.transactions.iter().map(Tx::fee));
+            }
+        }
+        // a transfer order paying a fee is as big as transfers get
+        let transfer =
+            Tx::transfer_order(Address::ZERO, Address::ZERO, U256::ZERO, 0, None).with_fee(1);
+        let min_fee = self.mempool.fee_schedule().min_fee(&transfer);
+        Ok(FeeEstimate::new(
+            &pending,
+            &recent,
+            self.block_capacity,
+            min_fee,
+        ))
+    }
+
+    async fn total_supply(&self) -> RpcResult<TotalSupplyInfo> {
+        Ok(self.accounts.total_supply().into())
+    }
+
+    async fn get_proof(&self, tx_hash: B256) -> RpcResult<Option<TxProof>> {
+        let Some((block, index)) = self.find_transaction(tx_hash).await else {
+            return Ok(None);
+        };
+        let Some(tx_proof) = block.proof_for_tx(index) else {
+            return Ok(None);
+        };
+        let receipt_proof = if block.receipts_root == B256::ZERO {
+            None
+        } else {
+            let logs = self.block_builder.get_logs(block.hash).await;
+            block.receipt_proof(&logs, index)
+        };
+        Ok(Some(TxProof {
+            header: block.header(),
+            tx_proof,
+            receipt_proof,
+        }))
+    }
+
+    async fn get_headers(&self, from: u64, count: u64) -> RpcResult<Vec<block_builder::Block>> {
+        self.timeouts
+            .run("fastpay_getHeaders", async {
+                let mut headers = Vec::new();
+                for number in from..from.saturating_add(count.min(MAX_BLOCKS_PER_REQUEST)) {
+                    match self.block_builder.get_block(U256::from(number)).await {
+                        Some(block) => headers.push(block.header()),
+                        None => break,
+                    }
+                }
+                Ok(headers)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl TxPoolRpcServer for EthRpcImpl {
+    async fn content(&self) -> RpcResult<TxPoolContent> {
+        Ok(TxPoolContent::new(
+            &self.mempool.pending().await,
+            &self.mempool.queued().await,
+        ))
+    }
+
+    async fn status(&self) -> RpcResult<TxPoolStatus> {
+        Ok(TxPoolStatus::new(
+            self.mempool.len().await,
+            self.mempool.queued_len().await,
+        ))
+    }
+}
+
+#[async_trait]
+impl AdminRpcServer for EthRpcImpl {
+    async fn health_thresholds(&self) -> RpcResult<HealthThresholds> {
+        Ok(self.health)
     }
 }
 
-pub async fn start_rpc_server(addr: SocketAddr) -> anyhow::Result<()> {
-    let server = ServerBuilder::default().build(addr).await?;
+#[async_trait]
+impl DebugRpcServer for EthRpcImpl {
+    async fn get_raw_header(&self, block: String) -> RpcResult<Option<Bytes>> {
+        Ok(self
+            .find_block(&block)
+            .await?
+            .map(|block| Bytes::from(block.encode_header())))
+    }
+
+    async fn get_raw_transaction(&self, hash: B256) -> RpcResult<Option<Bytes>> {
+        Ok(self
+            .find_transaction(hash)
+            .await
+            .map(|(block, index)| Bytes::from(block.transactions[index].to_bytes().to_vec())))
+    }
+}
+
+#[async_trait]
+impl PersonalRpcServer for EthRpcImpl {
+    async fn new_account(&self, password: String) -> RpcResult<Address> {
+        Ok(self.keys()?.add_key(PrivateKeySigner::random(), &password))
+    }
 
-    let rpc = EthRpcServer;
-    let handle = server.start(rpc.into_rpc())?;
+    async fn list_accounts(&self) -> RpcResult<Vec<Address>> {
+        Ok(self.keys()?.accounts())
+    }
+
+    async fn unlock_account(
+        &self,
+        address: Address,
+        password: String,
+        duration: Option<u64>,
+    ) -> RpcResult<bool> {
+        self.keys()?
+            .unlock(&address, &password, duration.map(Duration::from_secs))
+            .map_err(invalid_params)?;
+        Ok(true)
+    }
 
-    handle.stopped().await;
+    async fn lock_account(&self, address: Address) -> RpcResult<bool> {
+        Ok(self.keys()?.lock(&address))
+    }
+
+    async fn send_personal_transfer(&self, transfer: PersonalTransferRequest) -> RpcResult<String> {
+        let keys = self.keys()?;
+        let tx = match transfer.sequence {
+            Some(sequence) => {
+                Tx::transfer_order(transfer.from, transfer.to, transfer.amount, sequence, None)
+            }
+            None => Tx::new(transfer.from, transfer.to, transfer.amount, None),
+        }
+        .with_fee(transfer.fee)
+        .with_chain_id(self.chain_id);
+        let signature = keys
+            .sign_message(&transfer.from, &tx.tx_hash())
+            .map_err(invalid_params)?;
+        let tx = tx.with_signature(signature);
+        let tx_hash = tx_hash_hex(&tx);
+
+        self.admit(tx).await?;
+        Ok(tx_hash)
+    }
+
+    async fn audit_log(&self) -> RpcResult<Vec<AuditEntry>> {
+        Ok(self.keys()?.audit_log())
+    }
+}
+
+// the methods of `namespaces`, with personal_ when the node holds keys
+fn rpc_module(
+    rpc: &EthRpcImpl,
+    namespaces: &[Namespace],
+    personal: bool,
+) -> anyhow::Result<RpcModule<()>> {
+    let mut module = RpcModule::new(());
+    for namespace in namespaces {
+        match namespace {
+            Namespace::Eth => module.merge(EthRpcServer::into_rpc(rpc.clone()))?,
+            Namespace::Fastpay => module.merge(FastpayRpcServer::into_rpc(rpc.clone()))?,
+            Namespace::Txpool => module.merge(TxPoolRpcServer::into_rpc(rpc.clone()))?,
+            Namespace::Admin => module.merge(AdminRpcServer::into_rpc(rpc.clone()))?,
+            Namespace::Debug => module.merge(DebugRpcServer::into_rpc(rpc.clone()))?,
+        }
+    }
+    if personal {
+        module.merge(PersonalRpcServer::into_rpc(rpc.clone()))?;
+    }
+    Ok(module)
+}
+
+// the module served on the public address and, with an authenticated address, the one served
+// there. The private namespaces and personal_ are only ever served on the authenticated address,
+// so without one they are refused: personal_ keeps the node from starting, the node's keys being
+// asked for, the private namespaces are left out
+fn served_modules(
+    rpc: &EthRpcImpl,
+    config: &RpcConfig,
+) -> anyhow::Result<(RpcModule<()>, Option<RpcModule<()>>)> {
+    let (private, public): (Vec<Namespace>, Vec<Namespace>) = config
+        .namespaces
+        .iter()
+        .copied()
+        .partition(|namespace| namespace.is_private());
+    let personal = config.keys.is_some();
+    if config.auth.is_some() {
+        return Ok((
+            rpc_module(rpc, &public, false)?,
+            Some(rpc_module(rpc, &config.namespaces, personal)?),
+        ));
+    }
+
+    if personal {
+        anyhow::bail!("personal_ is only served on an authenticated address and none is set");
+    }
+    if !private.is_empty() {
+        let private: Vec<&str> = private.iter().map(|namespace| namespace.as_str()).collect();
+        tracing::warn!(
+            namespaces = %private.join(","),
+            "not serving private namespaces without an authenticated address"
+        );
+    }
+    Ok((rpc_module(rpc, &public, false)?, None))
+}
+
+// serves both HTTP and WebSocket on the same address. With an authenticated address, the private
+// namespaces are only served there, along with every other one. CORS and rate limits only apply to
+// the public address
+pub async fn start_rpc_server(
+    config: RpcConfig,
+    block_builder: BlockBuilder,
+    mempool: Mempool,
+    accounts: Arc<dyn AccountReader>,
+) -> anyhow::Result<()> {
+    let rpc = EthRpcImpl::from_config(&config, block_builder, mempool, accounts);
+    let (public, private) = served_modules(&rpc, &config)?;
+    let limits = &config.limits;
+    let server = ServerBuilder::default()
+        .set_logger(RpcLogger)
+        .set_middleware(
+            tower::ServiceBuilder::new()
+                .layer(CorsLayer::new(config.cors_origins.clone()))
+                .layer(RateLimitLayer::new(config.rate_limits.clone())),
+        )
+        .set_batch_request_config(limits.batch_config())
+        .max_request_body_size(limits.max_request_body_size)
+        .max_response_body_size(limits.max_response_body_size)
+        .build(config.addr)
+        .await?;
+    let auth_server = match &config.auth {
+        Some(auth) => Some(
+            ServerBuilder::default()
+                .set_logger(RpcLogger)
+                .set_middleware(
+                    tower::ServiceBuilder::new().layer(AuthLayer::new(auth.secret.clone())),
+                )
+                .set_batch_request_config(limits.batch_config())
+                .max_request_body_size(limits.max_request_body_size)
+                .max_response_body_size(limits.max_response_body_size)
+                .build(auth.addr)
+                .await?,
+        ),
+        None => None,
+    };
+
+    let handle = server.start(public);
+
+    match (auth_server, private) {
+        (Some(auth_server), Some(private)) => {
+            let auth_handle = auth_server.start(private);
+            tokio::join!(handle.stopped(), auth_handle.stopped());
+        }
+        _ => handle.stopped().await,
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use auth::AuthSecret;
+
+    #[test]
+    fn test_subscription_kind_from_str() {
+        assert_eq!(
+            SubscriptionKind::from_str("newHeads").unwrap(),
+            SubscriptionKind::NewHeads
+        );
+        assert_eq!(
+            SubscriptionKind::from_str("newPendingTransactions").unwrap(),
+            SubscriptionKind::NewPendingTransactions
+        );
+        assert!(SubscriptionKind::from_str("logs").is_err());
+    }
+
+    #[test]
+    fn test_block_from_built_block() {
+        let block = block_builder::Block::new(
+            U256::from(26),
+            Default::default(),
+            1_700_000_000,
+            Vec::new(),
+            Address::ZERO,
+        );
+
+        let rpc_block = Block::from(&block);
+        assert_eq!(rpc_block.number, "0x1a");
+        assert_eq!(rpc_block.hash, block.hash.to_string());
+        assert_eq!(rpc_block.timestamp, "0x6553f100");
+        assert_eq!(rpc_block.address_bloom, format!("0x{}", "0".repeat(512)));
+        assert_eq!(rpc_block.logs_bloom, format!("0x{}", "0".repeat(512)));
+        assert!(rpc_block.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_number_and_get_block_by_number() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+
+        assert_eq!(rpc.block_number().await.unwrap(), "0x0");
+        assert!(rpc
+            .get_block_by_number("latest".to_string(), false)
+            .await
+            .unwrap()
+            .is_none());
+
+        for _ in 0..3 {
+            block_builder
+                .create_block(Vec::new(), Address::ZERO)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(rpc.block_number().await.unwrap(), "0x2");
+        let latest = rpc
+            .get_block_by_number("latest".to_string(), false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.number, "0x2");
+        let first = rpc
+            .get_block_by_number("0x1".to_string(), false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.hash, latest.parent_hash);
+        let by_hash = rpc
+            .get_block_by_hash(B256::from_str(&first.hash).unwrap(), false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_hash.number, "0x1");
+        assert!(rpc
+            .get_block_by_hash(B256::ZERO, false)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(rpc
+            .get_block_by_number("0x10".to_string(), false)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(rpc
+            .get_block_by_number("bogus".to_string(), false)
+            .await
+            .is_err());
+    }
+
+    fn signed_transfer(signer: &PrivateKeySigner, to: Address, amount: u64) -> TransferRequest {
+        let amount = U256::from(amount);
+        let tx = Tx::new(signer.address(), to, amount, None);
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        TransferRequest {
+            from: signer.address(),
+            to,
+            amount,
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+            sequence: None,
+            fee: 0,
+            chain_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_balance() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let address = PrivateKeySigner::random().address();
+        accounts
+            .write_account(&address, Account::new(address, U256::from(255)))
+            .unwrap();
+
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            accounts,
+            SubscriptionConfig::default(),
+        );
+
+        let balance = rpc
+            .get_balance(address.to_string(), "latest".to_string())
+            .await
+            .unwrap();
+        assert_eq!(balance, "0xff");
+        let unknown = PrivateKeySigner::random().address();
+        assert_eq!(
+            rpc.get_balance(unknown.to_string(), "latest".to_string())
+                .await
+                .unwrap(),
+            "0x0"
+        );
+        assert!(rpc
+            .get_balance("nope".to_string(), "latest".to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_pending_balance() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let address = Address::repeat_byte(1);
+        accounts
+            .write_account(&address, Account::new(address, U256::from(255)))
+            .unwrap();
+        let mut pending = PendingState::new(accounts.clone());
+        pending
+            .update_account(&address, Account::new(address, U256::from(15)))
+            .unwrap();
+
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            accounts,
+            SubscriptionConfig::default(),
+        )
+        .with_pending_state(pending);
+        let balance = |block: &str| rpc.get_balance(address.to_string(), block.to_string());
+        assert_eq!(balance("pending").await.unwrap(), "0xf");
+        assert_eq!(balance("latest").await.unwrap(), "0xff");
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_count() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let signer = PrivateKeySigner::random();
+        let mut account = Account::new(signer.address(), U256::from(255));
+        account.set_sequence(2);
+        accounts.write_account(&signer.address(), account).unwrap();
+        let mempool = Mempool::new().with_sequences(accounts.clone());
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            accounts,
+            SubscriptionConfig::default(),
+        );
+        let order = |sequence: u64| {
+            let tx = Tx::transfer_order(
+                signer.address(),
+                Address::ZERO,
+                U256::from(1),
+                sequence,
+                None,
+            );
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+        let count = |block: &str| rpc.get_transaction_count(signer.address(), Some(block.into()));
+
+        assert_eq!(count("latest").await.unwrap(), "0x2");
+        assert_eq!(count("pending").await.unwrap(), "0x2");
+
+        // Orders in the mempool count for "pending", one waiting on a gap doesn't
+        for sequence in [2, 3, 5] {
+            mempool.add_tx(order(sequence)).await.unwrap();
+        }
+        assert_eq!(count("pending").await.unwrap(), "0x4");
+        assert_eq!(count("latest").await.unwrap(), "0x2");
+        assert_eq!(
+            rpc.get_transaction_count(Address::ZERO, None)
+                .await
+                .unwrap(),
+            "0x0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_transfer() {
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+
+        let transfer = signed_transfer(&signer, to, 10);
+        let tx_hash = rpc.send_transfer(transfer.clone()).await.unwrap();
+        assert_eq!(mempool.len().await, 1);
+        assert_eq!(
+            tx_hash,
+            tx_hash_hex(&Tx::try_from(transfer.clone()).unwrap())
+        );
+
+        // Submitting the same transfer twice is rejected
+        assert!(rpc.send_transfer(transfer).await.is_err());
+
+        // So is a transfer signed by someone else than the sender
+        let mut forged = signed_transfer(&PrivateKeySigner::random(), to, 10);
+        forged.from = signer.address();
+        assert!(rpc.send_transfer(forged).await.is_err());
+        // The fee is signed too, nobody else can raise it
+        let mut bumped = signed_transfer(&signer, to, 20);
+        bumped.fee = 5;
+        assert!(rpc.send_transfer(bumped).await.is_err());
+        assert_eq!(mempool.len().await, 1);
+
+        // A transfer signed over its typed data is accepted as well
+        let mut typed = signed_transfer(&signer, to, 30);
+        let typed_hash = Tx::try_from(typed.clone()).unwrap().typed_hash().unwrap();
+        let signature = signer.sign_hash_sync(&typed_hash).unwrap();
+        typed.signature = Bytes::from(signature.as_bytes().to_vec());
+        assert!(rpc.send_transfer(typed).await.is_ok());
+        assert_eq!(mempool.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_transaction() {
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let transfer = signed_transfer(&signer, Address::repeat_byte(1), 10);
+        let hash = B256::from_str(&rpc.send_transfer(transfer).await.unwrap()).unwrap();
+        let cancellation = |signer: &PrivateKeySigner| {
+            let message = tx::cancel::cancellation_message(hash.as_slice());
+            Bytes::from(
+                signer
+                    .sign_message_sync(&message)
+                    .unwrap()
+                    .as_bytes()
+                    .to_vec(),
+            )
+        };
+
+        // Only the sender can cancel, and not with the tx's own signature
+        let other = cancellation(&PrivateKeySigner::random());
+        assert!(rpc.cancel_transaction(hash, other).await.is_err());
+        let own = Bytes::from(
+            signer
+                .sign_message_sync(hash.as_slice())
+                .unwrap()
+                .as_bytes()
+                .to_vec(),
+        );
+        assert!(rpc.cancel_transaction(hash, own).await.is_err());
+        assert_eq!(mempool.len().await, 1);
+
+        assert!(rpc
+            .cancel_transaction(hash, cancellation(&signer))
+            .await
+            .unwrap());
+        assert!(mempool.is_empty().await);
+        assert!(!rpc
+            .cancel_transaction(hash, cancellation(&signer))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_send_transfer_chain_id() {
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        )
+        .with_chain_id(7);
+        assert_eq!(rpc.chain_id().await.unwrap(), "0x7");
+
+        let signer = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+        let transfer = |chain_id: u64| {
+            let tx = Tx::new(signer.address(), to, U256::from(10), None).with_chain_id(chain_id);
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            TransferRequest {
+                chain_id: Some(chain_id),
+                signature: Bytes::from(signature.as_bytes().to_vec()),
+                ..signed_transfer(&signer, to, 10)
+            }
+        };
+
+        assert!(rpc.send_transfer(transfer(8)).await.is_err());
+        assert!(rpc.send_transfer(transfer(7)).await.is_ok());
+        assert_eq!(mempool.pending().await[0].chain_id(), Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_personal_send_transfer() {
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        )
+        .with_chain_id(7);
+        // Disabled unless the node manages keys
+        assert!(rpc.new_account("secret".to_string()).await.is_err());
+
+        let rpc = rpc.with_keys(KeyManager::default());
+        let from = rpc.new_account("secret".to_string()).await.unwrap();
+        assert_eq!(rpc.list_accounts().await.unwrap(), vec![from]);
+        let transfer = PersonalTransferRequest {
+            from,
+            to: Address::repeat_byte(1),
+            amount: U256::from(10),
+            sequence: None,
+            fee: 0,
+        };
+
+        assert!(rpc.send_personal_transfer(transfer.clone()).await.is_err());
+        assert!(rpc
+            .unlock_account(from, "secret".to_string(), Some(0))
+            .await
+            .is_err());
+        assert!(rpc
+            .unlock_account(from, "secret".to_string(), Some(60))
+            .await
+            .unwrap());
+        let tx_hash = rpc.send_personal_transfer(transfer.clone()).await.unwrap();
+        let pending = mempool.pending().await;
+        assert_eq!(tx_hash_hex(&pending[0]), tx_hash);
+        assert_eq!(pending[0].chain_id(), Some(7));
+        assert!(pending[0].is_signed_by(from));
+
+        assert!(rpc.lock_account(from).await.unwrap());
+        assert!(rpc.send_personal_transfer(transfer).await.is_err());
+        assert_eq!(rpc.audit_log().await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_send_transfer_with_preconf() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+
+        // Disabled unless the node has a producer key
+        assert!(rpc
+            .send_transfer_with_preconf(signed_transfer(&signer, to, 1))
+            .await
+            .is_err());
+
+        let producer = PrivateKeySigner::random();
+        let rpc = rpc.with_preconfirmer(Preconfirmer::new(producer.clone(), 2));
+        block_builder
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+
+        let mut promised = Vec::new();
+        for amount in 1..=3 {
+            let preconfirmation = rpc
+                .send_transfer_with_preconf(signed_transfer(&signer, to, amount))
+                .await
+                .unwrap();
+            preconfirmation.verify(producer.address()).unwrap();
+            promised.push(preconfirmation.block_number);
+        }
+
+        // Two transfers fit in the next block, the third has to wait for the one after
+        assert_eq!(promised, vec![1, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_sync_rpc() {
+        let block_builder = BlockBuilder::new();
+        let sync = SyncStatus::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        )
+        .with_sync_status(sync.clone());
+
+        assert_eq!(rpc.syncing().await.unwrap(), Syncing::NotSyncing(false));
+        sync.start(0, 5);
+        match rpc.syncing().await.unwrap() {
+            Syncing::Syncing(info) => assert_eq!(info.highest_block, "0x5"),
+            other => panic!("expected sync progress, got {:?}", other),
+        }
+
+        for _ in 0..3 {
+            block_builder
+                .create_block(Vec::new(), Address::ZERO)
+                .await
+                .unwrap();
+        }
+        let blocks = rpc.get_blocks(1, 10).await.unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].number, U256::from(1));
+        assert_eq!(blocks[1].parent_hash, blocks[0].hash);
+        assert!(rpc.get_blocks(3, 10).await.unwrap().is_empty());
+        assert_eq!(rpc.get_blocks(0, 0).await.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pruned_blocks() {
+        let block_builder = BlockBuilder::new();
+        let mut blocks = Vec::new();
+        for amount in 1..=3u64 {
+            let tx = Tx::new(
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                U256::from(amount),
+                None,
+            );
+            blocks.push(
+                block_builder
+                    .create_block(vec![tx], Address::ZERO)
+                    .await
+                    .unwrap(),
+            );
+        }
+        block_builder.prune(U256::from(2)).await.unwrap();
+        let rpc = EthRpcImpl::new(
+            block_builder,
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+
+        // Only the header is left, the block isn't passed off as an empty one
+        let error = rpc
+            .get_block_by_number("0x0".to_string(), true)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), PRUNED_CODE);
+        assert!(rpc.get_block_by_hash(blocks[1].hash, false).await.is_err());
+        assert!(rpc.get_block_receipts("0x1".to_string()).await.is_err());
+        assert!(rpc
+            .get_block_by_number("0x2".to_string(), true)
+            .await
+            .unwrap()
+            .is_some());
+
+        // Nor is it served to peers syncing, though its header is
+        assert!(rpc.get_blocks(0, 10).await.is_err());
+        assert_eq!(rpc.get_blocks(2, 10).await.unwrap().len(), 1);
+        let headers = rpc.get_headers(0, 10).await.unwrap();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers[0].hash, blocks[0].hash);
+    }
+
+    #[tokio::test]
+    async fn test_get_certificate() {
+        let certificates = CertificateStore::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        )
+        .with_certificates(certificates.clone());
+
+        let authorities: Vec<_> = (0..4)
+            .map(|_| committee::authority::Authority::random())
+            .collect();
+        let committee = committee::committee::Committee::new(
+            authorities.iter().map(|authority| (authority.address(), 1)),
+        );
+        let signer = PrivateKeySigner::random();
+        let tx = Tx::new(signer.address(), Address::ZERO, U256::from(10), None);
+        let tx = tx
+            .clone()
+            .with_signature(signer.sign_message_sync(&tx.tx_hash()).unwrap());
+        let mut certificate = Certificate::new(tx.clone());
+        for authority in &authorities[..3] {
+            certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+        }
+
+        let tx_hash = certificate.tx_hash();
+        assert!(rpc.get_certificate(tx_hash).await.unwrap().is_none());
+        certificates.insert(certificate);
+
+        // What goes over the wire is enough to check the votes against the committee
+        let served = rpc.get_certificate(tx_hash).await.unwrap().unwrap();
+        let json = serde_json::to_value(&served).unwrap();
+        let decoded: Certificate = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.tx_hash(), tx_hash);
+        assert!(decoded.verify(&committee).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_netting_rpc() {
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let intent = tx::netting::Intent {
+            from: signer.address(),
+            to: Address::repeat_byte(1),
+            amount: 10,
+            window: 0,
+        };
+        let signed = SignedIntent {
+            intent,
+            signature: signer.sign_message_sync(intent.hash().as_slice()).unwrap(),
+        };
+
+        // Disabled unless the node is a netting authority
+        assert!(rpc.netting_window().await.is_err());
+        assert!(rpc.submit_intent(signed).await.is_err());
+
+        let engine = NettingEngine::new(PrivateKeySigner::random());
+        let rpc = rpc.with_netting(engine.clone());
+        assert_eq!(
+            rpc.netting_window().await.unwrap(),
+            NettingWindow {
+                window: 0,
+                authority: engine.authority()
+            }
+        );
+        assert_eq!(rpc.submit_intent(signed).await.unwrap(), intent.hash());
+        assert!(rpc.submit_intent(signed).await.is_err());
+
+        engine.close_window().await.unwrap().unwrap();
+        assert_eq!(rpc.netting_window().await.unwrap().window, 1);
+    }
+
+    #[tokio::test]
+    async fn test_channel_rpc() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            accounts.clone(),
+            SubscriptionConfig::default(),
+        );
+        let payer = PrivateKeySigner::random();
+        let payee = PrivateKeySigner::random().address();
+        let id = B256::repeat_byte(9);
+
+        assert_eq!(rpc.get_channel(id).await.unwrap(), None);
+        accounts
+            .write_channel(
+                &id,
+                Some(Channel::new(payer.address(), payee, U256::from(50), 10)),
+            )
+            .unwrap();
+        let channel = rpc.get_channel(id).await.unwrap().unwrap();
+        assert_eq!(channel.deposit, U256::from(50));
+        assert_eq!(channel.expires_at, None);
+
+        let tx = Tx::start_channel_timeout(payer.address(), payee, id, None);
+        let signature = payer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let request = ChannelTxRequest::StartTimeout {
+            from: payer.address(),
+            to: payee,
+            channel_id: id,
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+        };
+        assert_eq!(
+            rpc.send_channel_tx(request).await.unwrap(),
+            tx_hash_hex(&tx)
+        );
+        assert_eq!(mempool.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_total_supply() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            accounts.clone(),
+            SubscriptionConfig::default(),
+        );
+        let alice = Address::repeat_byte(1);
+        accounts
+            .write_account(&alice, Account::new(alice, U256::from(70)))
+            .unwrap();
+        accounts
+            .write_channel(
+                &B256::repeat_byte(9),
+                Some(Channel::new(alice, Address::ZERO, U256::from(30), 10)),
+            )
+            .unwrap();
+        accounts.write_total_supply(U256::from(100)).unwrap();
+
+        let supply = rpc.total_supply().await.unwrap();
+        assert_eq!(supply.held, U256::from(70));
+        assert_eq!(supply.locked, U256::from(30));
+        assert!(supply.balanced);
+        assert_eq!(
+            serde_json::to_value(&supply).unwrap()["totalSupply"],
+            serde_json::json!(U256::from(100))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        )
+        .with_block_capacity(1);
+        let estimate = rpc.estimate_fee().await.unwrap();
+        assert_eq!(estimate.high.fee, "0x0");
+        assert_eq!(estimate.high.expected_blocks, "0x1");
+
+        // A full block paid 4 to get in, one tx is already waiting
+        let paid = Tx::new(
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            U256::from(1),
+            None,
+        )
+        .with_fee(4);
+        block_builder
+            .create_block(vec![paid], Address::ZERO)
+            .await
+            .unwrap();
+        let signer = PrivateKeySigner::random();
+        rpc.send_transfer(signed_transfer(&signer, Address::repeat_byte(2), 1))
+            .await
+            .unwrap();
+
+        let estimate = rpc.estimate_fee().await.unwrap();
+        assert_eq!(estimate.high.fee, "0x4");
+        assert_eq!(estimate.high.expected_blocks, "0x1");
+        assert_eq!(estimate.low.fee, "0x4");
+    }
+
+    #[tokio::test]
+    async fn test_txpool() {
+        let mempool = Mempool::new();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            mempool.clone(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+
+        assert_eq!(rpc.status().await.unwrap(), TxPoolStatus::new(0, 0));
+
+        for amount in 1..=2 {
+            rpc.send_transfer(signed_transfer(&signer, to, amount))
+                .await
+                .unwrap();
+        }
+
+        let status = rpc.status().await.unwrap();
+        assert_eq!(status.pending, "0x2");
+        assert_eq!(status.queued, "0x0");
+
+        let content = rpc.content().await.unwrap();
+        assert_eq!(content.pending[&signer.address()].len(), 2);
+
+        // A transfer order ahead of the sender's sequence number waits in the queue
+        let order = Tx::transfer_order(signer.address(), to, U256::from(5), 1, None);
+        let signature = signer.sign_message_sync(&order.tx_hash()).unwrap();
+        rpc.send_transfer(TransferRequest {
+            from: signer.address(),
+            to,
+            amount: U256::from(5),
+            signature: Bytes::from(signature.as_bytes().to_vec()),
+            sequence: Some(1),
+            fee: 0,
+            chain_id: None,
+        })
+        .await
+        .unwrap();
+        let status = rpc.status().await.unwrap();
+        assert_eq!(status.pending, "0x2");
+        assert_eq!(status.queued, "0x1");
+        assert_eq!(
+            rpc.content().await.unwrap().queued[&signer.address()][0].nonce,
+            Some("0x1".to_string())
+        );
+
+        // Txs leave the pool once they are taken for a block
+        mempool.take_batch(10).await;
+        assert!(rpc.content().await.unwrap().pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_health_thresholds() {
+        let thresholds = HealthThresholds {
+            max_block_lag: 3,
+            max_mempool_saturation: 80,
+            min_peers: 2,
+        };
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        )
+        .with_health_thresholds(thresholds);
+
+        let served = rpc.health_thresholds().await.unwrap();
+        assert_eq!(
+            serde_json::to_value(served).unwrap(),
+            serde_json::json!({ "maxBlockLag": 3, "maxMempoolSaturation": 80, "minPeers": 2 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_namespaces() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let methods = |module: &RpcModule<()>| -> Vec<String> {
+            module.method_names().map(str::to_string).collect()
+        };
+        let private = |method: &String| {
+            ["admin_", "debug_", "personal_"]
+                .iter()
+                .any(|prefix| method.starts_with(prefix))
+        };
+        let mut config = RpcConfig {
+            namespaces: vec![Namespace::Eth, Namespace::Txpool, Namespace::Admin],
+            ..RpcConfig::default()
+        };
+
+        // Without an authenticated address the private namespaces aren't served
+        let (public, auth) = served_modules(&rpc, &config).unwrap();
+        let public = methods(&public);
+        assert!(public.contains(&"eth_chainId".to_string()));
+        assert!(public.contains(&"txpool_status".to_string()));
+        assert!(!public.iter().any(private));
+        assert!(auth.is_none());
+        // And the node's keys aren't served at all
+        config.keys = Some(KeyManager::new(Duration::from_secs(60)));
+        assert!(served_modules(&rpc, &config).is_err());
+
+        // With one they are served there only
+        config.auth = Some(AuthConfig {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8551)),
+            secret: AuthSecret::random(),
+        });
+        let (public, auth) = served_modules(&rpc, &config).unwrap();
+        assert!(!methods(&public).iter().any(private));
+        let auth = methods(&auth.unwrap());
+        assert!(auth.contains(&"admin_healthThresholds".to_string()));
+        assert!(auth.contains(&"personal_listAccounts".to_string()));
+        assert!(auth.contains(&"eth_chainId".to_string()));
+
+        let block = block_builder
+            .create_block(
+                vec![Tx::new(
+                    Address::ZERO,
+                    Address::repeat_byte(1),
+                    U256::from(1),
+                    None,
+                )],
+                Address::ZERO,
+            )
+            .await
+            .unwrap();
+        let header = rpc.get_raw_header("latest".to_string()).await.unwrap();
+        assert_eq!(header, Some(Bytes::from(block.encode_header())));
+        let tx = rpc
+            .get_raw_transaction(block.tx_hashes()[0])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx.to_vec(), block.transactions[0].to_bytes().to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_and_receipt() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let txs: Vec<Tx> = [
+            signed_transfer(&signer, to, 1),
+            signed_transfer(&signer, to, 2),
+        ]
+        .into_iter()
+        .map(|request| Tx::try_from(request).unwrap())
+        .collect();
+        block_builder
+            .create_block(Vec::new(), Address::ZERO)
+            .await
+            .unwrap();
+        let block = block_builder
+            .create_block(txs.clone(), Address::ZERO)
+            .await
+            .unwrap();
+
+        let hash = B256::from_slice(&txs[1].tx_hash());
+        let transaction = rpc.get_transaction_by_hash(hash).await.unwrap().unwrap();
+        assert_eq!(transaction.block_hash, block.hash);
+        assert_eq!(transaction.block_number, "0x1");
+        assert_eq!(transaction.transaction_index, "0x1");
+        assert_eq!(transaction.value, "0x2");
+
+        let receipt = rpc.get_transaction_receipt(hash).await.unwrap().unwrap();
+        assert_eq!(receipt.transaction_hash, hash);
+        assert_eq!(receipt.status, "0x1");
+
+        assert!(rpc
+            .get_transaction_receipt(B256::repeat_byte(9))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(rpc.chain_id().await.unwrap(), "0x539");
+
+        // Full blocks carry the same objects
+        let full = rpc
+            .get_block_by_hash(block.hash, true)
+            .await
+            .unwrap()
+            .unwrap();
+        match full.transactions {
+            BlockTransactions::Full(transactions) => assert_eq!(transactions[1], transaction),
+            other => panic!("expected full transactions, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_and_headers() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let tx = Tx::try_from(signed_transfer(&signer, to, 3)).unwrap();
+        let logs = vec![vec![tx::log::Log::transfer(
+            signer.address(),
+            to,
+            U256::from(3),
+        )]];
+        let block = block_builder
+            .create_block_with_logs(vec![tx.clone()], Address::ZERO, B256::ZERO, logs.clone())
+            .await
+            .unwrap();
+
+        let hash = B256::from_slice(&tx.tx_hash());
+        let proof = rpc.get_proof(hash).await.unwrap().unwrap();
+        // What a light client gets back over the wire
+        let proof: TxProof = serde_json::from_value(serde_json::to_value(&proof).unwrap()).unwrap();
+        assert!(proof.verify());
+        assert_eq!(proof.header.hash, block.hash);
+        assert_eq!(proof.header.compute_hash(), block.hash);
+        assert_eq!(proof.receipt_proof.unwrap().logs, logs[0]);
+        assert!(rpc.get_proof(B256::repeat_byte(9)).await.unwrap().is_none());
+
+        let headers = rpc.get_headers(0, 10).await.unwrap();
+        assert_eq!(headers.len(), 1);
+        assert!(headers[0].transactions.is_empty());
+        assert_eq!(headers[0].compute_hash(), block.hash);
+    }
+
+    #[tokio::test]
+    async fn test_get_account_proof() {
+        let block_builder = BlockBuilder::new();
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            accounts.clone(),
+            SubscriptionConfig::default(),
+        );
+        let alice = Address::repeat_byte(1);
+        assert!(rpc
+            .get_account_proof(alice, Vec::new(), None)
+            .await
+            .is_err());
+
+        for i in 1..=3 {
+            let address = Address::repeat_byte(i);
+            accounts
+                .write_account(&address, Account::new(address, U256::from(i * 10)))
+                .unwrap();
+        }
+        let block = block_builder
+            .create_block_with_state_root(Vec::new(), Address::ZERO, accounts.state_root())
+            .await
+            .unwrap();
+
+        let response = rpc
+            .get_account_proof(alice, Vec::new(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.balance, U256::from(10));
+        assert_eq!(response.block_hash, block.hash);
+        assert!(verify_account_proof(
+            block.state_root,
+            &response.proof.unwrap()
+        ));
+
+        let missing = rpc
+            .get_account_proof(Address::repeat_byte(9), Vec::new(), None)
+            .await
+            .unwrap();
+        assert_eq!(missing.balance, U256::ZERO);
+        assert!(missing.proof.is_none());
+        assert!(rpc
+            .get_account_proof(alice, vec![B256::ZERO], None)
+            .await
+            .is_err());
+        assert!(rpc
+            .get_account_proof(alice, Vec::new(), Some("latest".to_string()))
+            .await
+            .is_ok());
+        assert!(rpc
+            .get_account_proof(alice, Vec::new(), Some("0x0".to_string()))
+            .await
+            .is_err());
+
+        // Once the state moves past the head there is nothing to prove against
+        accounts
+            .write_account(&alice, Account::new(alice, U256::from(11)))
+            .unwrap();
+        assert!(rpc
+            .get_account_proof(alice, Vec::new(), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_logs() {
+        let block_builder = BlockBuilder::new();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+        let signer = PrivateKeySigner::random();
+        let (to, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut hashes = Vec::new();
+        for recipient in [to, other, to] {
+            let tx = Tx::try_from(signed_transfer(&signer, recipient, 5)).unwrap();
+            hashes.push(B256::from_slice(&tx.tx_hash()));
+            let logs = vec![vec![tx::log::Log::transfer(
+                signer.address(),
+                recipient,
+                U256::from(5),
+            )]];
+            block_builder
+                .create_block_with_logs(vec![tx], Address::ZERO, B256::ZERO, logs)
+                .await
+                .unwrap();
+        }
+
+        let to_filter =
+            |filter: serde_json::Value| -> LogFilter { serde_json::from_value(filter).unwrap() };
+        let all = rpc
+            .get_logs(to_filter(serde_json::json!({ "fromBlock": "earliest" })))
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1].block_number, "0x1");
+        assert_eq!(all[1].transaction_hash, hashes[1]);
+        assert_eq!(all[1].log_index, "0x0");
+
+        // Only the latest block by default
+        let latest = rpc.get_logs(LogFilter::default()).await.unwrap();
+        assert_eq!(latest, vec![all[2].clone()]);
+
+        let to_topic = serde_json::json!({
+            "fromBlock": "0x0",
+            "toBlock": "0x2",
+            "topics": [null, null, to.into_word()],
+        });
+        let received = rpc.get_logs(to_filter(to_topic)).await.unwrap();
+        assert_eq!(received, vec![all[0].clone(), all[2].clone()]);
+        let by_hash = rpc
+            .get_logs(LogFilter {
+                block_hash: Some(block_builder.get_block(U256::from(1)).await.unwrap().hash),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(by_hash, vec![all[1].clone()]);
+        assert!(rpc
+            .get_logs(to_filter(serde_json::json!({ "address": to })))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Receipts carry their tx's logs
+        let receipt = rpc
+            .get_transaction_receipt(hashes[2])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.logs, vec![all[2].clone()]);
+        assert_ne!(receipt.logs_bloom, alloy::primitives::Bloom::ZERO);
+
+        // And are read from the store, with their block's
+        let stored = block_builder.get_receipt(hashes[2]).await.unwrap();
+        assert_eq!(receipt.gas_used, format!("{:#x}", stored.gas_used));
+        let block_hash = format!("{}", receipt.block_hash);
+        let by_block = rpc.get_block_receipts(block_hash).await.unwrap().unwrap();
+        assert_eq!(by_block, vec![receipt.clone()]);
+        assert_eq!(
+            rpc.get_block_receipts("0x2".to_string()).await.unwrap(),
+            Some(vec![receipt])
+        );
+        assert_eq!(
+            rpc.get_block_receipts("0x9".to_string()).await.unwrap(),
+            None
+        );
+
+        // A range past the head stops there
+        let past_head = LogFilter {
+            from_block: Some("earliest".to_string()),
+            to_block: Some(format!("{:#x}", MAX_LOG_BLOCK_RANGE * 2)),
+            ..Default::default()
+        };
+        assert_eq!(rpc.get_logs(past_head).await.unwrap(), all);
+        assert!(rpc
+            .get_logs(LogFilter {
+                block_hash: Some(B256::repeat_byte(9)),
+                ..Default::default()
+            })
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let sender = PrivateKeySigner::random().address();
+        accounts
+            .write_account(&sender, Account::new(sender, U256::from(100)))
+            .unwrap();
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            accounts.clone(),
+            SubscriptionConfig::default(),
+        );
+        let request = CallRequest {
+            from: Some(sender),
+            to: Address::repeat_byte(1),
+            value: Some(U256::from(60)),
+            ..Default::default()
+        };
+        assert!(rpc.call(request.clone(), None).await.is_err());
+
+        let pending = PendingState::new(accounts.clone());
+        let rpc = rpc.with_simulator(vm::VM::new(Box::new(pending.fresh())).simulator(pending));
+        assert_eq!(rpc.call(request.clone(), None).await.unwrap(), Bytes::new());
+        assert_eq!(
+            rpc.estimate_gas(request.clone(), Some("pending".to_string()))
+                .await
+                .unwrap(),
+            "0x0"
+        );
+        // Nothing was applied
+        assert_eq!(
+            accounts.read_account(&sender).unwrap().balance(),
+            U256::from(100)
+        );
+
+        let too_much = CallRequest {
+            value: Some(U256::from(101)),
+            ..request.clone()
+        };
+        let error = rpc.call(too_much, None).await.unwrap_err();
+        assert_eq!(error.code(), EXECUTION_ERROR_CODE);
+        assert!(error.message().contains("enough balance"));
+        assert!(rpc.call(request, Some("0x1".to_string())).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call_balance_of() {
+        let accounts = Arc::new(ShardedState::in_memory(2));
+        let holder = PrivateKeySigner::random().address();
+        let asset = tx::asset::asset_id(Address::repeat_byte(2), "USD");
+        let mut account = Account::new(holder, U256::from(1));
+        account.set_asset_balance(asset, U256::from(300));
+        accounts.write_account(&holder, account).unwrap();
+        // Without a simulator, token reads don't run anything
+        let rpc = EthRpcImpl::new(
+            BlockBuilder::new(),
+            Mempool::new(),
+            accounts,
+            SubscriptionConfig::default(),
+        );
+
+        let mut input = token::BALANCE_OF_SELECTOR.to_vec();
+        input.extend_from_slice(holder.into_word().as_slice());
+        let request: CallRequest = serde_json::from_value(serde_json::json!({
+            "to": token_address(&asset),
+            "data": Bytes::from(input),
+        }))
+        .unwrap();
+        assert_eq!(
+            rpc.call(request.clone(), Some("latest".to_string()))
+                .await
+                .unwrap(),
+            token::encode_balance(U256::from(300))
+        );
+        // Someone holds the asset, just not this holder
+        let mut input = token::BALANCE_OF_SELECTOR.to_vec();
+        input.extend_from_slice(Address::repeat_byte(4).into_word().as_slice());
+        let none_held = CallRequest {
+            input: Some(Bytes::from(input)),
+            ..request.clone()
+        };
+        assert_eq!(
+            rpc.call(none_held, None).await.unwrap(),
+            token::encode_balance(U256::ZERO)
+        );
+        // No asset is at the address, or the block isn't one calls run on
+        let other = CallRequest {
+            to: token_address(&tx::asset::asset_id(Address::repeat_byte(2), "EUR")),
+            ..request.clone()
+        };
+        assert!(rpc.call(other, None).await.is_err());
+        assert!(rpc.call(request, Some("0x1".to_string())).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_account_history() {
+        let block_builder = BlockBuilder::new();
+        let alice = PrivateKeySigner::random().address();
+        let bob = PrivateKeySigner::random().address();
+        let carol = PrivateKeySigner::random().address();
+
+        for _ in 0..3 {
+            block_builder
+                .create_block(
+                    vec![
+                        Tx::new(alice, bob, U256::from(1), None),
+                        Tx::new(bob, carol, U256::from(2), None),
+                        Tx::new(carol, alice, U256::from(3), None),
+                    ],
+                    Address::ZERO,
+                )
+                .await
+                .unwrap();
+        }
+
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+
+        let request = |cursor| {
+            Some(PageRequest {
+                cursor,
+                limit: Some(4),
+            })
+        };
+
+        let first = rpc.get_account_history(alice, request(None)).await.unwrap();
+        assert_eq!(first.items.len(), 4);
+        assert!(first.has_more);
+        assert_eq!(first.items[0].to, bob);
+        assert_eq!(first.items[1].from, carol);
+        assert_eq!(first.items[1].transaction_index, "0x2");
+
+        let second = rpc
+            .get_account_history(alice, request(first.next_cursor))
+            .await
+            .unwrap();
+        assert_eq!(second.items.len(), 2);
+        assert!(!second.has_more);
+        assert_eq!(second.items[0].block_number, "0x2");
+
+        assert!(rpc
+            .get_account_history(alice, request(Some("bogus".to_string())))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_transfers_by_address() {
+        let block_builder = BlockBuilder::new();
+        let exchange = Address::repeat_byte(1);
+        let user = Address::repeat_byte(2);
+        for amount in 1..=3u64 {
+            block_builder
+                .create_block(
+                    vec![
+                        Tx::new(user, exchange, U256::from(amount), None),
+                        Tx::new(Address::repeat_byte(3), user, U256::from(9), None),
+                    ],
+                    Address::ZERO,
+                )
+                .await
+                .unwrap();
+        }
+        block_builder
+            .create_block(
+                vec![Tx::new(exchange, user, U256::from(4), None)
+                    .with_asset(tx::asset::asset_id(exchange, "USD"))],
+                Address::ZERO,
+            )
+            .await
+            .unwrap();
+        let rpc = EthRpcImpl::new(
+            block_builder.clone(),
+            Mempool::new(),
+            Arc::new(ShardedState::in_memory(1)),
+            SubscriptionConfig::default(),
+        );
+
+        let all = rpc
+            .get_transfers_by_address(exchange, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            all.items
+                .iter()
+                .map(|entry| entry.direction)
+                .collect::<Vec<_>>(),
+            vec![
+                Direction::Incoming,
+                Direction::Incoming,
+                Direction::Incoming,
+                Direction::Outgoing
+            ]
+        );
+        assert_eq!(all.items[0].token, Address::ZERO);
+        // The withdrawal is of an asset, not the native token
+        assert_eq!(
+            all.items[3].token,
+            token_address(&tx::asset::asset_id(exchange, "USD"))
+        );
+        assert_eq!(all.items[3].amount, "0x4");
+
+        // Deposits in blocks 1 and 2 only, a page at a time
+        let page = |cursor| {
+            Some(PageRequest {
+                cursor,
+                limit: Some(1),
+            })
+        };
+        let first = rpc
+            .get_transfers_by_address(exchange, Some(1), Some(2), page(None))
+            .await
+            .unwrap();
+        assert_eq!(first.items[0].block_number, "0x1");
+        assert_eq!(first.items[0].from, user);
+        assert!(first.has_more);
+        let second = rpc
+            .get_transfers_by_address(exchange, Some(1), Some(2), page(first.next_cursor))
+            .await
+            .unwrap();
+        assert_eq!(second.items[0].amount, "0x3");
+        assert!(!second.has_more);
+
+        assert!(rpc
+            .get_transfers_by_address(Address::repeat_byte(9), None, None, None)
+            .await
+            .unwrap()
+            .items
+            .is_empty());
+    }
+}
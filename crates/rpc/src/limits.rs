@@ -0,0 +1,179 @@
+// how much a client can ask of the server at once. Explorers fetch blocks in big batches, the
+// limits let them while keeping one client from tying the node up: batches and request bodies
+// are capped by jsonrpsee, and the calls that read many blocks give up after their timeout
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use jsonrpsee::{core::RpcResult, server::BatchRequestConfig, types::ErrorObject};
+
+// what a call that ran out of time fails with
+pub const TIMEOUT_ERROR_CODE: i32 = -32002;
+
+// the calls that run under a timeout, a timeout for any other method would never be applied
+pub const TIMED_METHODS: [&str; 8] = [
+    "eth_getBlockByNumber",
+    "eth_getBlockByHash",
+    "eth_getBlockReceipts",
+    "eth_getLogs",
+    "fastpay_getAccountHistory",
+    "fastpay_getTransfersByAddress",
+    "fastpay_getBlocks",
+    "fastpay_getHeaders",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcLimits {
+    // calls in one batch, 0 turns batches off
+    pub max_batch_size: u32,
+    pub max_request_body_size: u32,
+    pub max_response_body_size: u32,
+    pub timeouts: MethodTimeouts,
+}
+
+impl Default for RpcLimits {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 100,
+            max_request_body_size: 10 * 1024 * 1024,
+            max_response_body_size: 10 * 1024 * 1024,
+            timeouts: MethodTimeouts::default(),
+        }
+    }
+}
+
+impl RpcLimits {
+    pub fn batch_config(&self) -> BatchRequestConfig {
+        match self.max_batch_size {
+            0 => BatchRequestConfig::Disabled,
+            max => BatchRequestConfig::Limit(max),
+        }
+    }
+}
+
+// how long the calls that read many blocks may take, by method name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodTimeouts {
+    // for the methods without one of their own, None lets them take as long as they need
+    pub default: Option<Duration>,
+    by_method: HashMap<String, Duration>,
+}
+
+impl Default for MethodTimeouts {
+    fn default() -> Self {
+        Self::new(Some(Duration::from_secs(30)))
+    }
+}
+
+impl MethodTimeouts {
+    pub fn new(default: Option<Duration>) -> Self {
+        Self {
+            default,
+            by_method: HashMap::new(),
+        }
+    }
+
+    pub fn with_timeout(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.by_method.insert(method.into(), timeout);
+        self
+    }
+
+    pub fn get(&self, method: &str) -> Option<Duration> {
+        self.by_method.get(method).copied().or(self.default)
+    }
+
+    // runs `call`, failing it once `method`'s timeout has passed
+    pub async fn run<T>(
+        &self,
+        method: &str,
+        call: impl Future<Output = RpcResult<T>>,
+    ) -> RpcResult<T> {
+        debug_assert!(TIMED_METHODS.contains(&method), "{} is not timed", method);
+        let Some(timeout) = self.get(method) else {
+            return call.await;
+        };
+        tokio::time::timeout(timeout, call).await.map_err(|_| {
+            ErrorObject::owned(
+                TIMEOUT_ERROR_CODE,
+                format!("{} timed out after {}ms", method, timeout.as_millis()),
+                None::<()>,
+            )
+        })?
+    }
+}
+
+// a timeout given as "method=milliseconds", the way the node takes them on its command line
+pub fn parse_method_timeout(s: &str) -> Result<(String, Duration), String> {
+    let (method, millis) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected method=milliseconds, got {}", s))?;
+    if !TIMED_METHODS.contains(&method) {
+        return Err(format!(
+            "{} has no timeout, only {} do",
+            method,
+            TIMED_METHODS.join(", ")
+        ));
+    }
+    let millis: u64 = millis
+        .parse()
+        .map_err(|_| format!("invalid timeout for {}: {}", method, millis))?;
+    Ok((method.to_string(), Duration::from_millis(millis)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_method_timeouts() {
+        let (method, timeout) = parse_method_timeout("eth_getLogs=10").unwrap();
+        let timeouts =
+            MethodTimeouts::new(Some(Duration::from_secs(60))).with_timeout(method, timeout);
+        assert_eq!(timeouts.get("eth_getLogs"), Some(Duration::from_millis(10)));
+        assert_eq!(
+            timeouts.get("eth_getBlockByNumber"),
+            Some(Duration::from_secs(60))
+        );
+        assert!(parse_method_timeout("eth_getLogs").is_err());
+        assert!(parse_method_timeout("eth_getLogs=soon").is_err());
+        // A method that never runs under a timeout is refused rather than ignored
+        assert!(parse_method_timeout("eth_chainId=10").is_err());
+        for method in TIMED_METHODS {
+            assert!(parse_method_timeout(&format!("{}=10", method)).is_ok());
+        }
+
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        };
+        let error = timeouts.run("eth_getLogs", slow).await.unwrap_err();
+        assert_eq!(error.code(), TIMEOUT_ERROR_CODE);
+        assert_eq!(
+            timeouts
+                .run("eth_getBlockByNumber", async { Ok(1) })
+                .await
+                .unwrap(),
+            1
+        );
+
+        // Without a default, only the methods named are timed
+        assert_eq!(MethodTimeouts::new(None).get("eth_getLogs"), None);
+    }
+
+    #[test]
+    fn test_batch_config() {
+        let limits = RpcLimits {
+            max_batch_size: 0,
+            ..RpcLimits::default()
+        };
+        assert!(matches!(
+            limits.batch_config(),
+            BatchRequestConfig::Disabled
+        ));
+        assert!(matches!(
+            RpcLimits::default().batch_config(),
+            BatchRequestConfig::Limit(100)
+        ));
+    }
+}
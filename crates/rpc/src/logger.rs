@@ -0,0 +1,88 @@
+// traces every JSON-RPC call: the method and its params when it comes in, how it went and how
+// long it took when it's done. Batches trace each of their calls
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use jsonrpsee::server::logger::{
+    HttpRequest, Logger, MethodKind, Params, SuccessOrError, TransportProtocol,
+};
+
+use crate::rate_limit::Peer;
+
+// personal_ calls carry the passwords of the node's keys, their params are never logged
+const REDACTED_PREFIXES: [&str; 1] = ["personal_"];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcLogger;
+
+// params that don't parse are logged as null, the call itself reports why
+fn logged_params(method: &str, params: Params) -> serde_json::Value {
+    if REDACTED_PREFIXES
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+    {
+        return serde_json::Value::String("redacted".to_string());
+    }
+    params.parse().unwrap_or_default()
+}
+
+impl Logger for RpcLogger {
+    type Instant = Instant;
+
+    // also where the rate limiter learns who the client is, see rate_limit.rs
+    fn on_connect(&self, remote_addr: SocketAddr, request: &HttpRequest, _t: TransportProtocol) {
+        Peer::record(request, remote_addr);
+        tracing::trace!(%remote_addr, "rpc client connected");
+    }
+
+    fn on_request(&self, _transport: TransportProtocol) -> Self::Instant {
+        Instant::now()
+    }
+
+    fn on_call(&self, method: &str, params: Params, _kind: MethodKind, _t: TransportProtocol) {
+        let params = logged_params(method, params);
+        tracing::debug!(method, %params, "rpc call");
+    }
+
+    fn on_result(
+        &self,
+        method: &str,
+        result: SuccessOrError,
+        started_at: Self::Instant,
+        _transport: TransportProtocol,
+    ) {
+        let elapsed_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+        match result.as_error_code() {
+            None => tracing::debug!(method, elapsed_ms, "rpc call succeeded"),
+            Some(code) => tracing::info!(method, elapsed_ms, code, "rpc call failed"),
+        }
+    }
+
+    fn on_response(&self, _result: &str, _started_at: Self::Instant, _t: TransportProtocol) {}
+
+    fn on_disconnect(&self, remote_addr: SocketAddr, _transport: TransportProtocol) {
+        tracing::trace!(%remote_addr, "rpc client disconnected");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logged_params() {
+        let params = Params::new(Some(r#"["0x01", "hunter2"]"#));
+        assert_eq!(
+            logged_params("personal_unlockAccount", params.clone()),
+            serde_json::json!("redacted")
+        );
+        assert_eq!(
+            logged_params("eth_getBalance", params),
+            serde_json::json!(["0x01", "hunter2"])
+        );
+        assert_eq!(
+            logged_params("eth_getBalance", Params::new(Some("not json"))),
+            serde_json::Value::Null
+        );
+    }
+}
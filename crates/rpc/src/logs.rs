@@ -0,0 +1,193 @@
+// eth_getLogs: the logs of canonical blocks matching a filter. The filter and the logs come in the
+// shape Ethereum tooling sends and decodes. A block's logs bloom lets blocks without a match be
+// skipped before their logs are read
+
+use alloy::primitives::{Address, Bloom, BloomInput, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tx::log::Log;
+
+// the most blocks one eth_getLogs call searches
+pub const MAX_LOG_BLOCK_RANGE: u64 = 1000;
+
+// a single value or a list of them, a list matches any of its values
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: PartialEq> OneOrMany<T> {
+    fn contains(&self, value: &T) -> bool {
+        match self {
+            Self::One(one) => one == value,
+            Self::Many(many) => many.contains(value),
+        }
+    }
+
+    fn values(&self) -> &[T] {
+        match self {
+            Self::One(one) => std::slice::from_ref(one),
+            Self::Many(many) => many,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    pub from_block: Option<String>,
+    pub to_block: Option<String>,
+    // a single block, instead of a range
+    pub block_hash: Option<B256>,
+    pub address: Option<OneOrMany<Address>>,
+    // by position, null matches any topic there
+    #[serde(default)]
+    pub topics: Vec<Option<OneOrMany<B256>>>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if let Some(address) = &self.address {
+            if !address.contains(&log.address) {
+                return false;
+            }
+        }
+        self.topics
+            .iter()
+            .enumerate()
+            .all(|(position, topics)| match topics {
+                None => true,
+                Some(topics) => log
+                    .topics
+                    .get(position)
+                    .is_some_and(|topic| topics.contains(topic)),
+            })
+    }
+
+    // false when a block with `bloom` can't hold a matching log
+    pub fn may_match(&self, bloom: &Bloom) -> bool {
+        let address = self.address.as_ref().is_none_or(|address| {
+            address
+                .values()
+                .iter()
+                .any(|address| bloom.contains_input(BloomInput::Raw(address.as_slice())))
+        });
+        address
+            && self.topics.iter().flatten().all(|topics| {
+                topics
+                    .values()
+                    .iter()
+                    .any(|topic| bloom.contains_input(BloomInput::Raw(topic.as_slice())))
+            })
+    }
+}
+
+// a block number or one of the tags, `latest` being the head
+pub fn block_number(tag: Option<&str>, latest: U256) -> Result<U256, String> {
+    match tag.unwrap_or("latest") {
+        "latest" | "pending" | "safe" | "finalized" => Ok(latest),
+        "earliest" => Ok(U256::ZERO),
+        number => U256::from_str(number).map_err(|_| format!("invalid block number: {}", number)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    pub block_number: String,
+    pub block_hash: B256,
+    pub transaction_hash: B256,
+    pub transaction_index: String,
+    // the position of the log among all of its block's
+    pub log_index: String,
+    // logs are only served for canonical blocks
+    pub removed: bool,
+}
+
+impl RpcLog {
+    pub fn new(log: &Log, block: &block_builder::Block, tx_index: usize, log_index: usize) -> Self {
+        Self {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            block_number: format!("{:#x}", block.number),
+            block_hash: block.hash,
+            transaction_hash: B256::from_slice(&block.transactions[tx_index].tx_hash()),
+            transaction_index: format!("{:#x}", tx_index),
+            log_index: format!("{:#x}", log_index),
+            removed: false,
+        }
+    }
+
+    // the logs of every tx of `block`, `logs` holding them by tx
+    pub fn from_block(block: &block_builder::Block, logs: &[Vec<Log>]) -> Vec<Vec<Self>> {
+        let mut log_index = 0;
+        logs.iter()
+            .enumerate()
+            .map(|(tx_index, tx_logs)| {
+                tx_logs
+                    .iter()
+                    .map(|log| {
+                        log_index += 1;
+                        Self::new(log, block, tx_index, log_index - 1)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter() {
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let log = Log::transfer(a, b, U256::from(10));
+        let bloom = tx::log::logs_bloom([&log]);
+
+        let filter: LogFilter = serde_json::from_value(json!({
+            "address": Address::ZERO,
+            "topics": [tx::log::transfer_topic(), null, [c.into_word(), b.into_word()]],
+        }))
+        .unwrap();
+        assert!(filter.matches(&log));
+        assert!(filter.may_match(&bloom));
+        assert!(LogFilter::default().matches(&log));
+
+        // From c, or from any other contract, doesn't match
+        let filter: LogFilter =
+            serde_json::from_value(json!({ "topics": [null, c.into_word()] })).unwrap();
+        assert!(!filter.matches(&log));
+        assert!(!filter.may_match(&bloom));
+        let filter: LogFilter = serde_json::from_value(json!({ "address": [a, c] })).unwrap();
+        assert!(!filter.matches(&log));
+
+        // More topics than the log has
+        let filter = LogFilter {
+            topics: vec![None, None, None, Some(OneOrMany::One(B256::ZERO))],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&log));
+    }
+
+    #[test]
+    fn test_block_number() {
+        let latest = U256::from(9);
+        assert_eq!(block_number(None, latest), Ok(latest));
+        assert_eq!(block_number(Some("earliest"), latest), Ok(U256::ZERO));
+        assert_eq!(block_number(Some("0x3"), latest), Ok(U256::from(3)));
+        assert!(block_number(Some("next"), latest).is_err());
+    }
+}
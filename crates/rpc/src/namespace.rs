@@ -0,0 +1,81 @@
+// the groups of methods the server can serve, named after the prefix of their methods. An
+// operator picks the ones to serve, the private ones are only served on the authenticated address,
+// see auth.rs, and not at all without one. personal_ isn't among them, it is served there
+// whenever the node holds keys
+
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    Eth,
+    Fastpay,
+    Txpool,
+    Admin,
+    Debug,
+}
+
+impl Namespace {
+    pub const ALL: [Namespace; 5] = [
+        Namespace::Eth,
+        Namespace::Fastpay,
+        Namespace::Txpool,
+        Namespace::Admin,
+        Namespace::Debug,
+    ];
+
+    // what a node served before namespaces could be picked, admin_ now only with an authenticated
+    // address
+    pub const DEFAULT: [Namespace; 4] = [
+        Namespace::Eth,
+        Namespace::Fastpay,
+        Namespace::Txpool,
+        Namespace::Admin,
+    ];
+
+    // they change what the node does or show what it holds
+    pub fn is_private(self) -> bool {
+        matches!(self, Namespace::Admin | Namespace::Debug)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Namespace::Eth => "eth",
+            Namespace::Fastpay => "fastpay",
+            Namespace::Txpool => "txpool",
+            Namespace::Admin => "admin",
+            Namespace::Debug => "debug",
+        }
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|namespace| namespace.as_str() == s)
+            .ok_or_else(|| format!("unknown namespace: {}", s))
+    }
+}
+
+impl std::fmt::Display for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaces() {
+        for namespace in Namespace::ALL {
+            assert_eq!(namespace.to_string().parse(), Ok(namespace));
+        }
+        assert!("personal".parse::<Namespace>().is_err());
+        assert!(Namespace::Debug.is_private());
+        assert!(!Namespace::Eth.is_private());
+    }
+}
@@ -0,0 +1,193 @@
+// cursor based pagination shared by the history and log endpoints
+//
+// items are ordered by their position in the chain, (block number, index in block), and a cursor
+// is the position of the last item handed out, so new blocks never shift a page that was
+// already served and finalized blocks always page the same way
+
+use alloy::primitives::hex;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub block: u64,
+    pub index: u64,
+}
+
+impl Position {
+    pub fn new(block: u64, index: u64) -> Self {
+        Self { block, index }
+    }
+}
+
+// Cursor is handed to clients as an opaque string, they are only meant to pass it back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor(Position);
+
+impl Cursor {
+    pub fn position(&self) -> Position {
+        self.0
+    }
+
+    pub fn encode(&self) -> String {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.0.block.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.0.index.to_be_bytes());
+        hex::encode(bytes)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        let bytes = hex::decode(cursor).map_err(|_| format!("invalid cursor: {}", cursor))?;
+        if bytes.len() != 16 {
+            return Err(format!("invalid cursor: {}", cursor));
+        }
+
+        let block = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+        let index = u64::from_be_bytes(bytes[8..].try_into().unwrap());
+        Ok(Self(Position::new(block, index)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageRequest {
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl PageRequest {
+    pub fn limit(&self) -> Result<usize, String> {
+        match self.limit {
+            None => Ok(DEFAULT_PAGE_LIMIT),
+            Some(0) => Err("limit must be greater than 0".to_string()),
+            Some(limit) if limit > MAX_PAGE_LIMIT => Err(format!(
+                "limit must be at most {}, got {}",
+                MAX_PAGE_LIMIT, limit
+            )),
+            Some(limit) => Ok(limit),
+        }
+    }
+
+    pub fn after(&self) -> Result<Option<Position>, String> {
+        self.cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(cursor).map(|c| c.position()))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    // the same page with every item turned into another
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            has_more: self.has_more,
+        }
+    }
+}
+
+// cuts one page out of `items`, which must come in ascending position order
+pub fn paginate<T>(
+    items: impl IntoIterator<Item = (Position, T)>,
+    request: &PageRequest,
+) -> Result<Page<T>, String> {
+    let limit = request.limit()?;
+    let after = request.after()?;
+
+    let mut remaining = items
+        .into_iter()
+        .skip_while(|(position, _)| after.is_some_and(|after| *position <= after));
+
+    let page: Vec<(Position, T)> = remaining.by_ref().take(limit).collect();
+    let has_more = remaining.next().is_some();
+    let next_cursor = if has_more {
+        page.last().map(|(position, _)| Cursor(*position).encode())
+    } else {
+        None
+    };
+
+    Ok(Page {
+        items: page.into_iter().map(|(_, item)| item).collect(),
+        next_cursor,
+        has_more,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(n: u64) -> Vec<(Position, u64)> {
+        (0..n).map(|i| (Position::new(i / 3, i % 3), i)).collect()
+    }
+
+    fn request(cursor: Option<String>, limit: usize) -> PageRequest {
+        PageRequest {
+            cursor,
+            limit: Some(limit),
+        }
+    }
+
+    #[test]
+    fn test_cursor_roundtrip() {
+        let cursor = Cursor(Position::new(26, 3));
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+        assert!(Cursor::decode("abcd").is_err());
+    }
+
+    #[test]
+    fn test_paginate_walks_all_items() {
+        let mut cursor = None;
+        let mut seen = Vec::new();
+
+        loop {
+            let page = paginate(items(10), &request(cursor, 4)).unwrap();
+            seen.extend(page.items);
+            if !page.has_more {
+                assert!(page.next_cursor.is_none());
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_paginate_is_stable_when_items_are_appended() {
+        let first = paginate(items(6), &request(None, 4)).unwrap();
+        assert_eq!(first.items, vec![0, 1, 2, 3]);
+
+        // More blocks were produced between the two calls
+        let second = paginate(items(20), &request(first.next_cursor, 4)).unwrap();
+        assert_eq!(second.items, vec![4, 5, 6, 7]);
+        assert!(second.has_more);
+    }
+
+    #[test]
+    fn test_paginate_exact_fit_has_no_more() {
+        let page = paginate(items(4), &request(None, 4)).unwrap();
+        assert_eq!(page.items.len(), 4);
+        assert!(!page.has_more);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(PageRequest::default().limit().unwrap(), DEFAULT_PAGE_LIMIT);
+        assert!(request(None, 0).limit().is_err());
+        assert!(request(None, MAX_PAGE_LIMIT + 1).limit().is_err());
+        assert!(paginate(items(1), &request(Some("zz".to_string()), 1)).is_err());
+    }
+}
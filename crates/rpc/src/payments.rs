@@ -0,0 +1,232 @@
+// the "payments" kind of fastpay_subscribe: a notification for every credit to the watched
+// address in the blocks that become the head. Credits are read from the transfer logs of the
+// block's receipts, so they cover whatever moved funds to the address, HTLC claims and escrow
+// releases included, and from the scheduled payments the node makes as it executes a block. A
+// subscription reads them through a task of its own that picks out the address's payments, so it
+// buffers them under the same policy as any other subscription, see subscription.rs
+
+use alloy::primitives::{hex, Address, U256};
+use block_builder::{Block, BlockBuilder};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tx::log::{transfer_topic, Log};
+use vm::schedule::ScheduledPayment;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentNotification {
+    // the tx that paid, None for a scheduled payment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    // the schedule a scheduled payment was made for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_id: Option<String>,
+    pub block_number: String,
+    pub from: Address,
+    pub to: Address,
+    // the token address of the asset paid, the zero address for the native token, see
+    // tx::asset::token_address
+    pub token: Address,
+    pub amount: String,
+}
+
+// a Transfer log's move of funds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credit {
+    pub token: Address,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+}
+
+// the credit `log` records, None for any other log
+pub fn credit(log: &Log) -> Option<Credit> {
+    if log.topics.len() != 3 || log.topics[0] != transfer_topic() || log.data.len() != 32 {
+        return None;
+    }
+    Some(Credit {
+        token: log.address,
+        from: Address::from_word(log.topics[1]),
+        to: Address::from_word(log.topics[2]),
+        amount: U256::from_be_slice(&log.data),
+    })
+}
+
+// the credits to `address` in `logs`, a transfer to oneself isn't one
+fn credits_to(logs: &[Log], address: Address) -> impl Iterator<Item = Credit> + '_ {
+    logs.iter().filter_map(credit).filter(move |credit| {
+        credit.to == address && credit.from != address && !credit.amount.is_zero()
+    })
+}
+
+fn notification(credit: Credit, block_number: String) -> PaymentNotification {
+    PaymentNotification {
+        tx_hash: None,
+        schedule_id: None,
+        block_number,
+        from: credit.from,
+        to: credit.to,
+        token: credit.token,
+        amount: format!("{:#x}", credit.amount),
+    }
+}
+
+// the payments `block` makes to `address`, `logs` lining up with its txs
+pub fn payments_to(block: &Block, logs: &[Vec<Log>], address: Address) -> Vec<PaymentNotification> {
+    let block_number = format!("{:#x}", block.number);
+    block
+        .transactions
+        .iter()
+        .zip(logs)
+        .flat_map(|(tx, logs)| {
+            let tx_hash = hex::encode_prefixed(tx.tx_hash());
+            let block_number = block_number.clone();
+            credits_to(logs, address).map(move |credit| PaymentNotification {
+                tx_hash: Some(tx_hash.clone()),
+                ..notification(credit, block_number.clone())
+            })
+        })
+        .collect()
+}
+
+// what `payment` pays `address`, nothing when it was missed
+pub fn scheduled_payments_to(
+    payment: &ScheduledPayment,
+    address: Address,
+) -> Vec<PaymentNotification> {
+    if payment.error.is_some() {
+        return Vec::new();
+    }
+    credits_to(&payment.logs, address)
+        .map(|credit| PaymentNotification {
+            schedule_id: Some(hex::encode_prefixed(payment.schedule_id)),
+            ..notification(credit, format!("{:#x}", payment.block))
+        })
+        .collect()
+}
+
+async fn next_scheduled(
+    scheduled: &mut Option<broadcast::Receiver<ScheduledPayment>>,
+) -> Result<ScheduledPayment, RecvError> {
+    match scheduled {
+        Some(scheduled) => scheduled.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+// the payments to `address` in the blocks that become the head and in the scheduled payments
+// `scheduled` yields, until the returned receiver is dropped and the next payment shows it
+pub fn watch(
+    block_builder: BlockBuilder,
+    mut scheduled: Option<broadcast::Receiver<ScheduledPayment>>,
+    address: Address,
+    capacity: usize,
+) -> broadcast::Receiver<PaymentNotification> {
+    let mut heads = block_builder.subscribe_new_heads();
+    let (sender, receiver) = broadcast::channel(capacity.max(1));
+    tokio::spawn(async move {
+        loop {
+            // the subscription's own buffer counts what it drops, blocks and payments skipped
+            // here are simply missed
+            let payments = tokio::select! {
+                head = heads.recv() => match head {
+                    Ok(block) => {
+                        let logs = block_builder.get_logs(block.hash).await;
+                        payments_to(&block, &logs, address)
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+                payment = next_scheduled(&mut scheduled) => match payment {
+                    Ok(payment) => scheduled_payments_to(&payment, address),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => {
+                        scheduled = None;
+                        continue;
+                    }
+                },
+            };
+            for payment in payments {
+                if sender.send(payment).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+    use tx::{asset::asset_id, asset::token_address, tx::Tx};
+
+    #[tokio::test]
+    async fn test_watch() {
+        let merchant = Address::repeat_byte(1);
+        let customer = Address::repeat_byte(2);
+        let usd = asset_id(customer, "USD");
+        let block_builder = BlockBuilder::new();
+        let (schedules, _) = broadcast::channel(16);
+        let mut payments = watch(
+            block_builder.clone(),
+            Some(schedules.subscribe()),
+            merchant,
+            16,
+        );
+
+        let paid = Tx::new(customer, merchant, U256::from(30), None);
+        // like an HTLC claim or an escrow release, the tx doesn't name the merchant, only its log
+        // says who was paid
+        let claim = Tx::new(Address::repeat_byte(3), customer, U256::ZERO, None);
+        let txs = vec![
+            paid.clone(),
+            Tx::new(merchant, customer, U256::from(5), None),
+            Tx::new(merchant, merchant, U256::from(5), None),
+            claim.clone(),
+        ];
+        let logs = vec![
+            vec![Log::transfer(customer, merchant, U256::from(30))],
+            vec![Log::transfer(merchant, customer, U256::from(5))],
+            vec![Log::transfer(merchant, merchant, U256::from(5))],
+            vec![Log::asset_transfer(&usd, customer, merchant, U256::from(7))],
+        ];
+        block_builder
+            .create_block_with_logs(txs, Address::ZERO, B256::ZERO, logs)
+            .await
+            .unwrap();
+
+        let payment = payments.recv().await.unwrap();
+        assert_eq!(payment.tx_hash, Some(hex::encode_prefixed(paid.tx_hash())));
+        assert_eq!(payment.block_number, "0x0");
+        assert_eq!(payment.from, customer);
+        assert_eq!(payment.token, Address::ZERO);
+        assert_eq!(payment.amount, "0x1e");
+        let payment = payments.recv().await.unwrap();
+        assert_eq!(payment.tx_hash, Some(hex::encode_prefixed(claim.tx_hash())));
+        assert_eq!(payment.token, token_address(&usd));
+        assert_eq!(payment.amount, "0x7");
+
+        schedules
+            .send(ScheduledPayment {
+                schedule_id: B256::repeat_byte(9),
+                from: customer,
+                to: merchant,
+                amount: U256::from(10),
+                block: 1,
+                error: None,
+                logs: vec![Log::transfer(customer, merchant, U256::from(10))],
+            })
+            .unwrap();
+        let payment = payments.recv().await.unwrap();
+        assert_eq!(payment.tx_hash, None);
+        assert_eq!(
+            payment.schedule_id,
+            Some(hex::encode_prefixed(B256::repeat_byte(9)))
+        );
+        assert_eq!(payment.block_number, "0x1");
+        assert_eq!(payment.amount, "0xa");
+        assert!(payments.try_recv().is_err());
+    }
+}
@@ -0,0 +1,323 @@
+// keys held by the node for development and personal use, served under the personal_ namespace.
+// A key has to be unlocked with its password before the node signs with it, and an unlock always
+// runs out: once it has, the key is locked again and signing is refused until it is unlocked
+// anew. Unlocks, locks and every signature made or refused are kept in an audit log. Keys only
+// live in memory, they are gone when the node stops
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::{Address, PrimitiveSignature, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_UNLOCK_DURATION: Duration = Duration::from_secs(300);
+pub const DEFAULT_MAX_UNLOCK_DURATION: Duration = Duration::from_secs(3600);
+// the oldest entries are dropped past this
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum AuditEvent {
+    Unlocked { seconds: u64 },
+    Locked,
+    // the unlock ran out
+    Expired,
+    Signed,
+    SignRejected { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub address: Address,
+    // unix seconds
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+struct ManagedKey {
+    signer: PrivateKeySigner,
+    password: String,
+    unlocked_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Keys {
+    keys: HashMap<Address, ManagedKey>,
+    audit: VecDeque<AuditEntry>,
+}
+
+impl Keys {
+    fn audit(&mut self, address: Address, event: AuditEvent) {
+        if let AuditEvent::SignRejected { reason } = &event {
+            tracing::warn!(%address, %reason, "refused to sign");
+        }
+        if self.audit.len() == MAX_AUDIT_ENTRIES {
+            self.audit.pop_front();
+        }
+        self.audit.push_back(AuditEntry {
+            address,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            event,
+        });
+    }
+
+    // locks every key whose unlock ran out by `now`
+    fn relock_expired(&mut self, now: Instant) {
+        let expired: Vec<Address> = self
+            .keys
+            .iter_mut()
+            .filter(|(_, key)| key.unlocked_until.is_some_and(|until| now >= until))
+            .map(|(address, key)| {
+                key.unlocked_until = None;
+                *address
+            })
+            .collect();
+        for address in expired {
+            self.audit(address, AuditEvent::Expired);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyManager {
+    keys: Arc<Mutex<Keys>>,
+    max_unlock_duration: Duration,
+}
+
+// never prints the keys or their passwords
+impl std::fmt::Debug for KeyManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyManager")
+            .field("accounts", &self.accounts())
+            .field("max_unlock_duration", &self.max_unlock_duration)
+            .finish()
+    }
+}
+
+impl Default for KeyManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_UNLOCK_DURATION)
+    }
+}
+
+impl KeyManager {
+    pub fn new(max_unlock_duration: Duration) -> Self {
+        Self {
+            keys: Arc::new(Mutex::new(Keys::default())),
+            max_unlock_duration,
+        }
+    }
+
+    // keys are added locked
+    pub fn add_key(&self, signer: PrivateKeySigner, password: &str) -> Address {
+        let address = signer.address();
+        self.keys.lock().unwrap().keys.insert(
+            address,
+            ManagedKey {
+                signer,
+                password: password.to_string(),
+                unlocked_until: None,
+            },
+        );
+        address
+    }
+
+    pub fn accounts(&self) -> Vec<Address> {
+        let mut accounts: Vec<_> = self.keys.lock().unwrap().keys.keys().copied().collect();
+        accounts.sort();
+        accounts
+    }
+
+    pub fn is_unlocked(&self, address: &Address) -> bool {
+        self.is_unlocked_at(address, Instant::now())
+    }
+
+    fn is_unlocked_at(&self, address: &Address, now: Instant) -> bool {
+        let mut keys = self.keys.lock().unwrap();
+        keys.relock_expired(now);
+        keys.keys
+            .get(address)
+            .is_some_and(|key| key.unlocked_until.is_some())
+    }
+
+    // None unlocks for DEFAULT_UNLOCK_DURATION. There is no unlocking for good, 0 and anything
+    // over the maximum are refused
+    pub fn unlock(
+        &self,
+        address: &Address,
+        password: &str,
+        duration: Option<Duration>,
+    ) -> Result<(), String> {
+        self.unlock_at(address, password, duration, Instant::now())
+    }
+
+    fn unlock_at(
+        &self,
+        address: &Address,
+        password: &str,
+        duration: Option<Duration>,
+        now: Instant,
+    ) -> Result<(), String> {
+        let duration = duration.unwrap_or(DEFAULT_UNLOCK_DURATION);
+        if duration.is_zero() || duration > self.max_unlock_duration {
+            return Err(format!(
+                "unlock duration must be between 1 and {} seconds",
+                self.max_unlock_duration.as_secs()
+            ));
+        }
+
+        let mut keys = self.keys.lock().unwrap();
+        let key = keys
+            .keys
+            .get_mut(address)
+            .ok_or_else(|| format!("no key for {}", address))?;
+        if key.password != password {
+            return Err("wrong password".to_string());
+        }
+        key.unlocked_until = Some(now + duration);
+        keys.audit(
+            *address,
+            AuditEvent::Unlocked {
+                seconds: duration.as_secs(),
+            },
+        );
+        Ok(())
+    }
+
+    // false when there is no such key
+    pub fn lock(&self, address: &Address) -> bool {
+        let mut keys = self.keys.lock().unwrap();
+        let Some(key) = keys.keys.get_mut(address) else {
+            return false;
+        };
+        key.unlocked_until = None;
+        keys.audit(*address, AuditEvent::Locked);
+        true
+    }
+
+    // signs `message` like a wallet does (EIP-191) if the key is unlocked
+    pub fn sign_message(
+        &self,
+        address: &Address,
+        message: &[u8],
+    ) -> Result<PrimitiveSignature, String> {
+        self.sign_message_at(address, message, Instant::now())
+    }
+
+    fn sign_message_at(
+        &self,
+        address: &Address,
+        message: &[u8],
+        now: Instant,
+    ) -> Result<PrimitiveSignature, String> {
+        let mut keys = self.keys.lock().unwrap();
+        keys.relock_expired(now);
+        let result = match keys.keys.get(address) {
+            None => Err(format!("no key for {}", address)),
+            Some(key) if key.unlocked_until.is_none() => Err(format!("{} is locked", address)),
+            Some(key) => key
+                .signer
+                .sign_message_sync(message)
+                .map_err(|e| format!("failed to sign: {}", e)),
+        };
+        let event = match &result {
+            Ok(_) => AuditEvent::Signed,
+            Err(reason) => AuditEvent::SignRejected {
+                reason: reason.clone(),
+            },
+        };
+        keys.audit(*address, event);
+        result
+    }
+
+    // oldest first
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        self.keys.lock().unwrap().audit.iter().cloned().collect()
+    }
+}
+
+// a transfer for the node to sign with one of its keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersonalTransferRequest {
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    #[serde(default)]
+    pub fee: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlock_expiry() {
+        let keys = KeyManager::new(Duration::from_secs(60));
+        let address = keys.add_key(PrivateKeySigner::random(), "secret");
+        let now = Instant::now();
+        assert!(keys.sign_message_at(&address, b"hi", now).is_err());
+
+        assert!(keys.unlock_at(&address, "wrong", None, now).is_err());
+        // No unlocking forever, nor for longer than the maximum
+        for duration in [0, 61] {
+            let duration = Some(Duration::from_secs(duration));
+            assert!(keys.unlock_at(&address, "secret", duration, now).is_err());
+        }
+
+        let duration = Some(Duration::from_secs(30));
+        keys.unlock_at(&address, "secret", duration, now).unwrap();
+        let signature = keys.sign_message_at(&address, b"hi", now).unwrap();
+        assert_eq!(signature.recover_address_from_msg(b"hi").unwrap(), address);
+        assert!(keys.is_unlocked_at(&address, now + Duration::from_secs(29)));
+
+        // The key relocks by itself, signing is refused from then on
+        let later = now + Duration::from_secs(30);
+        assert!(keys.sign_message_at(&address, b"hi", later).is_err());
+        assert!(!keys.is_unlocked_at(&address, now));
+
+        let events: Vec<_> = keys
+            .audit_log()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                AuditEvent::SignRejected {
+                    reason: format!("{} is locked", address)
+                },
+                AuditEvent::Unlocked { seconds: 30 },
+                AuditEvent::Signed,
+                AuditEvent::Expired,
+                AuditEvent::SignRejected {
+                    reason: format!("{} is locked", address)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lock() {
+        let keys = KeyManager::default();
+        let address = keys.add_key(PrivateKeySigner::random(), "");
+        assert_eq!(keys.accounts(), vec![address]);
+
+        keys.unlock(&address, "", None).unwrap();
+        assert!(keys.is_unlocked(&address));
+        assert!(keys.lock(&address));
+        assert!(keys.sign_message(&address, b"hi").is_err());
+        assert!(!keys.lock(&Address::ZERO));
+
+        let entry = serde_json::to_value(&keys.audit_log()[1]).unwrap();
+        assert_eq!(entry["event"], "locked");
+        assert_eq!(entry["address"], serde_json::json!(address));
+    }
+}
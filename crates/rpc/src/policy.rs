@@ -0,0 +1,81 @@
+// spending policies over rpc, an account's owner sets the limits on what leaves it
+
+use alloy::primitives::{Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+use crate::channel::parse_signature;
+
+// a signed SetPolicy tx, the signature is the 65 byte r || s || v encoding. Limits left out are
+// lifted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyTxRequest {
+    pub from: Address,
+    #[serde(default)]
+    pub max_per_tx: Option<U256>,
+    #[serde(default)]
+    pub daily_limit: Option<U256>,
+    #[serde(default)]
+    pub allowlist: Vec<Address>,
+    pub sequence: u64,
+    pub signature: Bytes,
+}
+
+impl TryFrom<PolicyTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: PolicyTxRequest) -> Result<Self, Self::Error> {
+        let tx = Tx::set_policy(
+            request.from,
+            request.max_per_tx,
+            request.daily_limit,
+            request.allowlist,
+            request.sequence,
+            Some(parse_signature(&request.signature)?),
+        );
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_request_from_json() {
+        let owner = PrivateKeySigner::random();
+        let tx = Tx::set_policy(
+            owner.address(),
+            None,
+            Some(U256::from(100)),
+            vec![],
+            1,
+            None,
+        );
+        let signature = owner.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: PolicyTxRequest = serde_json::from_value(serde_json::json!({
+            "from": owner.address(),
+            "dailyLimit": U256::from(100),
+            "sequence": 1,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(
+            Tx::try_from(request.clone()).unwrap().tx_hash(),
+            tx.tx_hash()
+        );
+
+        // Signed for other limits than the ones sent
+        let forged = PolicyTxRequest {
+            daily_limit: None,
+            ..request
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+// soft confirmations: when a transfer is admitted to the mempool the block producer signs a
+// promise to include it no later than a given block, final settlement still comes with the block
+
+use alloy::primitives::{keccak256, Address, PrimitiveSignature, B256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use serde::{Deserialize, Serialize};
+
+const PRECONFIRMATION_DOMAIN: &[u8] = b"fastpay-preconfirmation";
+
+pub fn preconfirmation_message(tx_hash: B256, block_number: u64) -> B256 {
+    let mut message = PRECONFIRMATION_DOMAIN.to_vec();
+    message.extend_from_slice(tx_hash.as_slice());
+    message.extend_from_slice(&block_number.to_be_bytes());
+    keccak256(message)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preconfirmation {
+    pub tx_hash: B256,
+    // the transfer will be included in this block or an earlier one
+    pub block_number: u64,
+    pub producer: Address,
+    pub signature: PrimitiveSignature,
+}
+
+impl Preconfirmation {
+    pub fn verify(&self, producer: Address) -> Result<(), String> {
+        if self.producer != producer {
+            return Err(format!(
+                "preconfirmation issued by {}, expected {}",
+                self.producer, producer
+            ));
+        }
+
+        let message = preconfirmation_message(self.tx_hash, self.block_number);
+        let signer = self
+            .signature
+            .recover_address_from_msg(message)
+            .map_err(|e| format!("invalid preconfirmation signature: {}", e))?;
+        if signer != producer {
+            return Err("preconfirmation is not signed by the producer".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Preconfirmer {
+    signer: PrivateKeySigner,
+    // how many transfers the producer puts in a block, to tell which block a queued one lands in
+    max_block_txs: usize,
+}
+
+impl Preconfirmer {
+    pub fn new(signer: PrivateKeySigner, max_block_txs: usize) -> Self {
+        Self {
+            signer,
+            max_block_txs: max_block_txs.max(1),
+        }
+    }
+
+    pub fn producer(&self) -> Address {
+        self.signer.address()
+    }
+
+    // `queue_position` is how many transfers are ahead of this one in the mempool
+    pub fn issue(
+        &self,
+        tx_hash: B256,
+        next_block: u64,
+        queue_position: usize,
+    ) -> Result<Preconfirmation, String> {
+        let block_number = next_block + (queue_position / self.max_block_txs) as u64;
+        let signature = self
+            .signer
+            .sign_message_sync(preconfirmation_message(tx_hash, block_number).as_slice())
+            .map_err(|e| format!("failed to sign preconfirmation: {}", e))?;
+
+        Ok(Preconfirmation {
+            tx_hash,
+            block_number,
+            producer: self.producer(),
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_verify() {
+        let preconfirmer = Preconfirmer::new(PrivateKeySigner::random(), 10);
+        let tx_hash = B256::repeat_byte(1);
+
+        let preconfirmation = preconfirmer.issue(tx_hash, 5, 0).unwrap();
+        assert_eq!(preconfirmation.block_number, 5);
+        assert!(preconfirmation.verify(preconfirmer.producer()).is_ok());
+
+        // Transfers queued behind a full block are promised a later one
+        assert_eq!(preconfirmer.issue(tx_hash, 5, 25).unwrap().block_number, 7);
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering() {
+        let preconfirmer = Preconfirmer::new(PrivateKeySigner::random(), 10);
+        let preconfirmation = preconfirmer.issue(B256::repeat_byte(1), 5, 0).unwrap();
+
+        let mut later = preconfirmation.clone();
+        later.block_number = 9;
+        assert!(later.verify(preconfirmer.producer()).is_err());
+
+        let other = PrivateKeySigner::random().address();
+        assert!(preconfirmation.verify(other).is_err());
+
+        let mut impersonated = preconfirmation;
+        impersonated.producer = other;
+        assert!(impersonated.verify(other).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+// what eth_getProof answers, shaped like EIP-1186 where it can be. Accounts have no code or
+// storage, and the proof is one of the state root's own merkle tree rather than of a trie, see
+// state::root. Check it with state::root::verify_account_proof against the block's state root
+
+use alloy::primitives::{Address, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+use state::root::AccountProof;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProofResponse {
+    pub address: Address,
+    pub balance: U256,
+    pub nonce: U64,
+    // the block whose state root the proof is against, always the head
+    pub block_hash: B256,
+    pub state_root: B256,
+    // None for an account that doesn't exist, its absence isn't proven
+    pub proof: Option<AccountProof>,
+    // always empty, kept for clients reading EIP-1186 responses
+    pub storage_proof: Vec<()>,
+}
+
+impl AccountProofResponse {
+    pub fn new(
+        address: Address,
+        block_hash: B256,
+        state_root: B256,
+        proof: Option<AccountProof>,
+    ) -> Self {
+        Self {
+            address,
+            balance: proof.as_ref().map_or(U256::ZERO, |proof| proof.balance),
+            nonce: U64::from(proof.as_ref().map_or(0, |proof| proof.sequence)),
+            block_hash,
+            state_root,
+            proof,
+            storage_proof: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+// token buckets in front of the server, so a public endpoint can't be flooded: one shared by
+// every client and one per client. A client is the IP address the request came from, whatever
+// connection it opens. Behind a trusted proxy it is the address the proxy put in X-Forwarded-For
+// or X-Real-IP, those headers are ignored from anyone else. WebSocket connections are counted
+// when they are opened, not per message.
+// jsonrpsee doesn't hand middleware the peer's address, only its logger, see RpcLogger. The
+// request is handed on first and the logger records where it came from as the server takes it.
+// Nothing runs until the returned future is polled, so a request over the limit is dropped there
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use hyper::{header::RETRY_AFTER, Body, Request, Response, StatusCode};
+use tower::{Layer, Service};
+
+// idle clients' buckets are dropped once there are this many
+const MAX_CLIENT_BUCKETS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    // how many requests can come at once after a quiet spell
+    pub burst: u32,
+}
+
+impl RateLimit {
+    // `requests` a second, all of which may come at once
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            per_second: requests as f64,
+            burst: requests.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimits {
+    pub global: Option<RateLimit>,
+    pub per_client: Option<RateLimit>,
+    // the proxies whose X-Forwarded-For and X-Real-IP say who the client is
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.updated = now;
+    }
+
+    fn has_token(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        self.tokens >= 1.0
+    }
+
+    fn is_full(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        self.tokens >= limit.burst as f64
+    }
+
+    // seconds until the next token
+    fn wait(&self, limit: &RateLimit) -> u64 {
+        if limit.per_second <= 0.0 {
+            return u64::MAX;
+        }
+        ((1.0 - self.tokens).max(0.0) / limit.per_second).ceil() as u64
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    global: Option<TokenBucket>,
+    clients: HashMap<IpAddr, TokenBucket>,
+}
+
+// a token from the global bucket and the client's, or the seconds to wait for one
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: Mutex::new(Buckets {
+                global: limits.global.map(|limit| TokenBucket::full(&limit, now)),
+                clients: HashMap::new(),
+            }),
+            limits,
+        }
+    }
+
+    // the client a request from `peer` is counted against
+    fn client(&self, peer: IpAddr, forwarded: Option<IpAddr>) -> IpAddr {
+        match forwarded {
+            Some(forwarded) if self.limits.trusted_proxies.contains(&peer) => forwarded,
+            _ => peer,
+        }
+    }
+
+    fn take(&self, client: IpAddr, now: Instant) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets { global, clients } = &mut *buckets;
+        let mut global = global.as_mut().zip(self.limits.global);
+        let mut client = self.limits.per_client.map(|limit| {
+            if clients.len() >= MAX_CLIENT_BUCKETS {
+                clients.retain(|_, bucket| !bucket.is_full(&limit, now));
+            }
+            let bucket = clients
+                .entry(client)
+                .or_insert_with(|| TokenBucket::full(&limit, now));
+            (bucket, limit)
+        });
+
+        // nothing is taken from either bucket unless both have a token
+        let mut wait = None;
+        for (bucket, limit) in global.iter_mut().chain(client.iter_mut()) {
+            if !bucket.has_token(limit, now) {
+                wait = wait.max(Some(bucket.wait(limit)));
+            }
+        }
+        if let Some(wait) = wait {
+            return Err(wait.max(1));
+        }
+        for (bucket, _) in global.iter_mut().chain(client.iter_mut()) {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+// where a request came from, put in its extensions by the rate limiter and filled in by the
+// logger once the server takes the request
+#[derive(Debug, Clone, Default)]
+pub struct Peer(Arc<Mutex<Option<SocketAddr>>>);
+
+impl Peer {
+    // a no-op for requests that didn't come through the rate limiter
+    pub fn record(request: &Request<Body>, remote_addr: SocketAddr) {
+        if let Some(peer) = request.extensions().get::<Peer>() {
+            *peer.0.lock().unwrap() = Some(remote_addr);
+        }
+    }
+
+    fn get(&self) -> Option<SocketAddr> {
+        *self.0.lock().unwrap()
+    }
+}
+
+// the rate limiter as jsonrpsee middleware, for a server logging with RpcLogger
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limiter: Arc::new(RateLimiter::new(limits)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+type BoxError = Box<dyn StdError + Send + Sync + 'static>;
+
+// the address a proxy says the request came from, the first one being the client's. Only
+// believed from a trusted proxy
+fn forwarded_ip(request: &Request<Body>) -> Option<IpAddr> {
+    ["x-forwarded-for", "x-real-ip"]
+        .into_iter()
+        .find_map(|name| {
+            request
+                .headers()
+                .get(name)?
+                .to_str()
+                .ok()?
+                .split(',')
+                .next()?
+                .trim()
+                .parse()
+                .ok()
+        })
+}
+
+impl<S> Service<Request<Body>> for RateLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let peer = Peer::default();
+        request.extensions_mut().insert(peer.clone());
+        let forwarded = forwarded_ip(&request);
+        let response = self.inner.call(request);
+        // the server turned the request away before taking it, there's nothing to count
+        let Some(peer) = peer.get() else {
+            return Box::pin(response);
+        };
+        let client = self.limiter.client(peer.ip(), forwarded);
+        if let Err(wait) = self.limiter.take(client, Instant::now()) {
+            drop(response);
+            let response = Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(RETRY_AFTER, wait)
+                .body(Body::from("rate limit exceeded"))
+                .expect("a static response is valid");
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(RateLimits {
+            global: Some(RateLimit::per_second(3)),
+            per_client: Some(RateLimit::per_second(2)),
+            ..RateLimits::default()
+        });
+        let now = Instant::now();
+        let alice = IpAddr::from([10, 0, 0, 1]);
+        let bob = IpAddr::from([10, 0, 0, 2]);
+        assert_eq!(limiter.take(alice, now), Ok(()));
+        assert_eq!(limiter.take(alice, now), Ok(()));
+        assert_eq!(limiter.take(alice, now), Err(1));
+
+        // Bob has a bucket of his own, until the global one runs dry
+        assert_eq!(limiter.take(bob, now), Ok(()));
+        assert_eq!(limiter.take(bob, now), Err(1));
+
+        // Both refill over time
+        let later = now + Duration::from_millis(500);
+        assert_eq!(limiter.take(alice, later), Ok(()));
+        assert_eq!(
+            limiter.take(bob, later + Duration::from_millis(500)),
+            Ok(())
+        );
+        assert!(limiter.take(alice, later).is_err());
+    }
+
+    // stands in for jsonrpsee and its logger, taking every request from `peer`
+    struct Ok200 {
+        peer: SocketAddr,
+    }
+
+    impl Service<Request<Body>> for Ok200 {
+        type Response = Response<Body>;
+        type Error = BoxError;
+        type Future = std::future::Ready<Result<Response<Body>, BoxError>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<Body>) -> Self::Future {
+            Peer::record(&request, self.peer);
+            std::future::ready(Ok(Response::new(Body::empty())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_layer() {
+        let proxy = IpAddr::from([10, 0, 0, 9]);
+        let layer = RateLimitLayer::new(RateLimits {
+            global: None,
+            per_client: Some(RateLimit::per_second(1)),
+            trusted_proxies: vec![proxy],
+        });
+        let connection = |ip: [u8; 4], port| {
+            layer.layer(Ok200 {
+                peer: SocketAddr::from((ip, port)),
+            })
+        };
+        let request = |forwarded: Option<&str>| {
+            let mut request = Request::builder();
+            if let Some(forwarded) = forwarded {
+                request = request.header("x-forwarded-for", forwarded);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        // A client is its address, a new connection doesn't get it a new bucket
+        let mut first = connection([10, 0, 0, 1], 1000);
+        assert_eq!(
+            first.call(request(None)).await.unwrap().status(),
+            StatusCode::OK
+        );
+        let limited = first.call(request(None)).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[RETRY_AFTER], "1");
+        let mut reconnected = connection([10, 0, 0, 1], 1001);
+        assert_eq!(
+            reconnected.call(request(None)).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // Made up addresses are ignored from anyone but the proxy
+        assert_eq!(
+            reconnected
+                .call(request(Some("10.0.0.5")))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        let mut proxied = connection([10, 0, 0, 9], 2000);
+        let forwarded = Some("10.0.0.5, 10.0.0.9");
+        assert_eq!(
+            proxied.call(request(forwarded)).await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            proxied.call(request(forwarded)).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            proxied
+                .call(request(Some("10.0.0.6")))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// scheduled payments over rpc, the payer registers or cancels them and the vm makes the payments
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+use crate::channel::parse_signature;
+
+// a signed Schedule or CancelSchedule tx, the signature is the 65 byte r || s || v encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ScheduleTxRequest {
+    #[serde(rename_all = "camelCase")]
+    Schedule {
+        from: Address,
+        to: Address,
+        amount: U256,
+        interval: u64,
+        #[serde(default)]
+        end: Option<u64>,
+        sequence: u64,
+        signature: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Cancel {
+        from: Address,
+        schedule_id: B256,
+        sequence: u64,
+        signature: Bytes,
+    },
+}
+
+impl TryFrom<ScheduleTxRequest> for Tx {
+    type Error = String;
+
+    fn try_from(request: ScheduleTxRequest) -> Result<Self, Self::Error> {
+        let tx = match request {
+            ScheduleTxRequest::Schedule {
+                from,
+                to,
+                amount,
+                interval,
+                end,
+                sequence,
+                signature,
+            } => Tx::schedule(
+                from,
+                to,
+                amount,
+                interval,
+                end,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+            ScheduleTxRequest::Cancel {
+                from,
+                schedule_id,
+                sequence,
+                signature,
+            } => Tx::cancel_schedule(
+                from,
+                schedule_id,
+                sequence,
+                Some(parse_signature(&signature)?),
+            ),
+        };
+
+        super::check_sender(&tx)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_request_from_json() {
+        let payer = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let tx = Tx::schedule(payer.address(), to, U256::from(5), 10, None, 3, None);
+        let signature = payer.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let request: ScheduleTxRequest = serde_json::from_value(serde_json::json!({
+            "kind": "schedule",
+            "from": payer.address(),
+            "to": to,
+            "amount": U256::from(5),
+            "interval": 10,
+            "sequence": 3,
+            "signature": Bytes::from(signature.as_bytes().to_vec()),
+        }))
+        .unwrap();
+        assert_eq!(
+            Tx::try_from(request.clone()).unwrap().tx_hash(),
+            tx.tx_hash()
+        );
+
+        // Signed without the end it is sent with
+        let ScheduleTxRequest::Schedule {
+            from,
+            to,
+            amount,
+            interval,
+            sequence,
+            signature,
+            ..
+        } = request
+        else {
+            unreachable!()
+        };
+        let forged = ScheduleTxRequest::Schedule {
+            from,
+            to,
+            amount,
+            interval,
+            end: Some(100),
+            sequence,
+            signature,
+        };
+        assert!(Tx::try_from(forged).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+// per-subscription buffering, so a slow subscriber can't make the node hold an unbounded backlog
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use jsonrpsee::core::server::{SubscriptionMessage, SubscriptionSink};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    // drop the oldest buffered notification to make room for the new one
+    DropOldest,
+    // close the subscription once its buffer is full
+    DropConnection,
+    // stop reading new notifications until the subscriber drains its buffer,
+    // anything the source channel overwrites in the meantime is counted as dropped
+    Backpressure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionConfig {
+    pub buffer_size: usize,
+    pub policy: SlowConsumerPolicy,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 1024,
+            policy: SlowConsumerPolicy::DropOldest,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SubscriptionMetrics {
+    dropped_notifications: AtomicU64,
+    dropped_subscriptions: AtomicU64,
+}
+
+impl SubscriptionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dropped_notifications(&self) -> u64 {
+        self.dropped_notifications.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped_subscriptions(&self) -> u64 {
+        self.dropped_subscriptions.load(Ordering::Relaxed)
+    }
+
+    fn record_dropped_notifications(&self, count: u64) {
+        self.dropped_notifications
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn record_dropped_subscription(&self) {
+        self.dropped_subscriptions.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    DroppedOldest,
+    // the buffer is full and the policy doesn't allow dropping, the item was not queued
+    Overflow,
+}
+
+#[derive(Debug)]
+pub struct SubscriptionBuffer<T> {
+    queue: VecDeque<T>,
+    config: SubscriptionConfig,
+}
+
+impl<T> SubscriptionBuffer<T> {
+    pub fn new(config: SubscriptionConfig) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            config,
+        }
+    }
+
+    pub fn push(&mut self, item: T) -> PushOutcome {
+        if !self.is_full() {
+            self.queue.push_back(item);
+            return PushOutcome::Queued;
+        }
+
+        match self.config.policy {
+            SlowConsumerPolicy::DropOldest => {
+                self.queue.pop_front();
+                self.queue.push_back(item);
+                PushOutcome::DroppedOldest
+            }
+            SlowConsumerPolicy::DropConnection | SlowConsumerPolicy::Backpressure => {
+                PushOutcome::Overflow
+            }
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.queue.front()
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= self.config.buffer_size.max(1)
+    }
+}
+
+// pipes items from a broadcast channel into a subscription, applying the configured policy
+// whenever the connection can't keep up, returns once either side goes away
+pub async fn forward<T, N, F>(
+    mut receiver: broadcast::Receiver<T>,
+    sink: SubscriptionSink,
+    config: SubscriptionConfig,
+    metrics: Arc<SubscriptionMetrics>,
+    notification: F,
+) where
+    T: Clone,
+    N: Serialize,
+    F: Fn(&T) -> N,
+{
+    let mut buffer = SubscriptionBuffer::new(config);
+
+    loop {
+        if buffer.is_full() && config.policy == SlowConsumerPolicy::Backpressure {
+            let item = buffer.pop().map(|item| notification(&item));
+            if !send_item(&sink, item).await {
+                return;
+            }
+            continue;
+        }
+
+        let next = buffer.front().map(&notification);
+
+        tokio::select! {
+            received = receiver.recv() => match received {
+                Ok(item) => match buffer.push(item) {
+                    PushOutcome::Queued => {}
+                    PushOutcome::DroppedOldest => metrics.record_dropped_notifications(1),
+                    PushOutcome::Overflow => {
+                        metrics.record_dropped_notifications(buffer.len() as u64 + 1);
+                        metrics.record_dropped_subscription();
+                        return;
+                    }
+                },
+                Err(RecvError::Lagged(skipped)) => metrics.record_dropped_notifications(skipped),
+                Err(RecvError::Closed) => return,
+            },
+            sent = send_item(&sink, next), if !buffer.is_empty() => {
+                if !sent {
+                    return;
+                }
+                buffer.pop();
+            }
+            _ = sink.closed() => return,
+        }
+    }
+}
+
+async fn send_item<T: Serialize>(sink: &SubscriptionSink, item: Option<T>) -> bool {
+    let Some(item) = item else {
+        return true;
+    };
+
+    match SubscriptionMessage::from_json(&item) {
+        Ok(message) => sink.send(message).await.is_ok(),
+        // an item we can't serialize is skipped rather than tearing down the subscription
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(buffer_size: usize, policy: SlowConsumerPolicy) -> SubscriptionConfig {
+        SubscriptionConfig {
+            buffer_size,
+            policy,
+        }
+    }
+
+    #[test]
+    fn test_buffer_queues_until_full() {
+        let mut buffer = SubscriptionBuffer::new(config(2, SlowConsumerPolicy::DropOldest));
+
+        assert_eq!(buffer.push(1), PushOutcome::Queued);
+        assert_eq!(buffer.push(2), PushOutcome::Queued);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn test_drop_oldest_policy() {
+        let mut buffer = SubscriptionBuffer::new(config(2, SlowConsumerPolicy::DropOldest));
+
+        buffer.push(1);
+        buffer.push(2);
+        assert_eq!(buffer.push(3), PushOutcome::DroppedOldest);
+
+        // Oldest notification is gone, newest is kept
+        assert_eq!(buffer.pop(), Some(2));
+        assert_eq!(buffer.pop(), Some(3));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_drop_connection_policy() {
+        let mut buffer = SubscriptionBuffer::new(config(1, SlowConsumerPolicy::DropConnection));
+
+        buffer.push(1);
+        assert_eq!(buffer.push(2), PushOutcome::Overflow);
+
+        // Buffered notification is untouched
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.front(), Some(&1));
+    }
+
+    #[test]
+    fn test_backpressure_policy_does_not_drop() {
+        let mut buffer = SubscriptionBuffer::new(config(1, SlowConsumerPolicy::Backpressure));
+
+        buffer.push(1);
+        assert_eq!(buffer.push(2), PushOutcome::Overflow);
+        assert_eq!(buffer.pop(), Some(1));
+        assert_eq!(buffer.push(2), PushOutcome::Queued);
+    }
+
+    #[test]
+    fn test_zero_buffer_size_holds_one_item() {
+        let mut buffer = SubscriptionBuffer::new(config(0, SlowConsumerPolicy::DropOldest));
+
+        assert_eq!(buffer.push(1), PushOutcome::Queued);
+        assert_eq!(buffer.push(2), PushOutcome::DroppedOldest);
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = SubscriptionMetrics::new();
+        metrics.record_dropped_notifications(3);
+        metrics.record_dropped_notifications(2);
+        metrics.record_dropped_subscription();
+
+        assert_eq!(metrics.dropped_notifications(), 5);
+        assert_eq!(metrics.dropped_subscriptions(), 1);
+    }
+}
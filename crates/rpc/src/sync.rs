@@ -0,0 +1,108 @@
+// how far along a lagging node is in catching up with its peers, written by the sync service and
+// read by eth_syncing
+
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncProgress {
+    // the head when this round of syncing started
+    pub starting_block: u64,
+    pub current_block: u64,
+    // the highest head announced by a peer
+    pub highest_block: u64,
+}
+
+// clones share the same progress, None while the node is caught up
+#[derive(Debug, Clone, Default)]
+pub struct SyncStatus {
+    progress: Arc<RwLock<Option<SyncProgress>>>,
+}
+
+impl SyncStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&self, starting_block: u64, highest_block: u64) {
+        *self.progress.write().unwrap() = Some(SyncProgress {
+            starting_block,
+            current_block: starting_block,
+            highest_block,
+        });
+    }
+
+    pub fn advance(&self, current_block: u64) {
+        if let Some(progress) = self.progress.write().unwrap().as_mut() {
+            progress.current_block = current_block;
+            progress.highest_block = progress.highest_block.max(current_block);
+        }
+    }
+
+    pub fn finish(&self) {
+        *self.progress.write().unwrap() = None;
+    }
+
+    pub fn progress(&self) -> Option<SyncProgress> {
+        *self.progress.read().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncInfo {
+    pub starting_block: String,
+    pub current_block: String,
+    pub highest_block: String,
+}
+
+// eth_syncing answers `false` once the node is caught up, the progress otherwise
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Syncing {
+    Syncing(SyncInfo),
+    NotSyncing(bool),
+}
+
+impl From<Option<SyncProgress>> for Syncing {
+    fn from(progress: Option<SyncProgress>) -> Self {
+        match progress {
+            Some(progress) => Self::Syncing(SyncInfo {
+                starting_block: format!("{:#x}", progress.starting_block),
+                current_block: format!("{:#x}", progress.current_block),
+                highest_block: format!("{:#x}", progress.highest_block),
+            }),
+            None => Self::NotSyncing(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sync_status() {
+        let status = SyncStatus::new();
+        assert_eq!(
+            serde_json::to_value(Syncing::from(status.progress())).unwrap(),
+            json!(false)
+        );
+
+        status.clone().start(10, 40);
+        status.advance(26);
+        assert_eq!(
+            serde_json::to_value(Syncing::from(status.progress())).unwrap(),
+            json!({
+                "startingBlock": "0xa",
+                "currentBlock": "0x1a",
+                "highestBlock": "0x28",
+            })
+        );
+
+        status.finish();
+        assert_eq!(status.progress(), None);
+    }
+}
@@ -0,0 +1,94 @@
+// issued assets as ERC-20 tokens, for wallets: an asset sits at the synthetic address
+// tx::asset::token_address gives it, and an eth_call of balanceOf(address) on that address is
+// answered from the holder's account without running any contract. A holder's account lists the
+// assets it holds, so the address is matched against those. A holder with none of it gets 0 as
+// long as some account holds the asset, an address no asset has is refused
+
+use alloy::primitives::{Address, Bytes, U256};
+use state::account::Account;
+use tx::asset::token_address;
+
+use crate::call::CallRequest;
+
+// keccak256("balanceOf(address)")[..4]
+pub const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+// the holder a balanceOf call asks about, None for calls that aren't one
+pub fn balance_of(request: &CallRequest) -> Result<Option<Address>, String> {
+    let Some(input) = request.input.as_ref() else {
+        return Ok(None);
+    };
+    // the native token has no contract address
+    if request.to == Address::ZERO || !input.starts_with(&BALANCE_OF_SELECTOR) {
+        return Ok(None);
+    }
+    // the address is abi encoded as a word, left padded with zeros
+    let word = &input[BALANCE_OF_SELECTOR.len()..];
+    if word.len() != 32 || word[..12].iter().any(|byte| *byte != 0) {
+        return Err("balanceOf takes a single abi encoded address".to_string());
+    }
+    Ok(Some(Address::from_slice(&word[12..])))
+}
+
+// how much of the asset at `token` the account holds, None when it holds none
+pub fn token_balance(account: Option<&Account>, token: Address) -> Option<U256> {
+    account?
+        .assets()
+        .iter()
+        .find(|(asset, _)| token_address(asset) == token)
+        .map(|(_, balance)| *balance)
+}
+
+// the uint256 balanceOf returns
+pub fn encode_balance(balance: U256) -> Bytes {
+    Bytes::from(balance.to_be_bytes::<32>().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tx::asset::asset_id;
+
+    #[test]
+    fn test_balance_of() {
+        let holder = Address::repeat_byte(1);
+        let asset = asset_id(Address::repeat_byte(2), "USD");
+        let mut input = BALANCE_OF_SELECTOR.to_vec();
+        input.extend_from_slice(holder.into_word().as_slice());
+        let request = CallRequest {
+            to: token_address(&asset),
+            input: Some(Bytes::from(input.clone())),
+            ..Default::default()
+        };
+        assert_eq!(balance_of(&request), Ok(Some(holder)));
+
+        let mut account = Account::new(holder, U256::from(7));
+        assert_eq!(token_balance(Some(&account), request.to), None);
+        account.set_asset_balance(asset, U256::from(300));
+        assert_eq!(
+            token_balance(Some(&account), request.to),
+            Some(U256::from(300))
+        );
+        assert_eq!(
+            encode_balance(U256::from(300)).to_string(),
+            format!("0x{:064x}", 300)
+        );
+
+        // Transfers and the native token aren't token calls
+        let transfer = CallRequest {
+            input: None,
+            ..request.clone()
+        };
+        assert_eq!(balance_of(&transfer), Ok(None));
+        let native = CallRequest {
+            to: Address::ZERO,
+            ..request.clone()
+        };
+        assert_eq!(balance_of(&native), Ok(None));
+        let truncated = CallRequest {
+            input: Some(Bytes::from(input[..20].to_vec())),
+            ..request
+        };
+        assert!(balance_of(&truncated).is_err());
+    }
+}
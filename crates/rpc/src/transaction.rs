@@ -0,0 +1,273 @@
+// transactions and receipts in the shape Ethereum tooling decodes: every fastpay tx is presented
+// as a signed EIP-2930 (type 0x1) transaction with an empty access list. There is no gas, so gas
+// quantities are zero, and a block only holds txs that executed, so every receipt succeeded
+
+use alloy::eips::eip2930::AccessList;
+use alloy::primitives::{Address, Bloom, Bytes, B256, U256};
+use block_builder::receipts::StoredReceipt;
+use serde::{Deserialize, Serialize};
+use tx::{asset::is_native, tx::Tx};
+
+use crate::logs::RpcLog;
+
+// the chain id typed signatures commit to
+pub use tx::eip712::CHAIN_ID;
+pub const EIP2930_TX_TYPE: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    #[serde(rename = "type")]
+    pub tx_type: String,
+    pub chain_id: String,
+    pub hash: B256,
+    pub nonce: String,
+    pub block_hash: B256,
+    pub block_number: String,
+    pub transaction_index: String,
+    pub from: Address,
+    pub to: Address,
+    pub value: String,
+    pub gas: String,
+    pub gas_price: String,
+    // native transfers carry nothing, other txs their fastpay encoding
+    pub input: Bytes,
+    pub access_list: AccessList,
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+// the r, s and parity of the sender's signature, v repeats the parity as typed txs do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Signature {
+    pub r: U256,
+    pub s: U256,
+    pub v: String,
+    pub y_parity: String,
+}
+
+impl Transaction {
+    // the tx at `index` in `block`
+    pub fn new(tx: &Tx, block: &block_builder::Block, index: usize) -> Self {
+        let native = is_native(&tx.asset());
+        let input = if tx.is_transfer() && native {
+            Bytes::new()
+        } else {
+            Bytes::from(tx.to_bytes().to_vec())
+        };
+        let signature = tx.signature().map(|signature| {
+            let parity = format!("{:#x}", signature.v() as u8);
+            Signature {
+                r: signature.r(),
+                s: signature.s(),
+                v: parity.clone(),
+                y_parity: parity,
+            }
+        });
+
+        Self {
+            tx_type: format!("{:#x}", EIP2930_TX_TYPE),
+            chain_id: format!("{:#x}", CHAIN_ID),
+            hash: B256::from_slice(&tx.tx_hash()),
+            nonce: format!("{:#x}", tx.sequence().unwrap_or(0)),
+            block_hash: block.hash,
+            block_number: format!("{:#x}", block.number),
+            transaction_index: format!("{:#x}", index),
+            from: tx.from(),
+            to: tx.to(),
+            // other assets don't move any of the native token
+            value: format!("{:#x}", if native { tx.amount() } else { U256::ZERO }),
+            gas: "0x0".to_string(),
+            gas_price: "0x0".to_string(),
+            input,
+            access_list: AccessList::default(),
+            signature,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    #[serde(rename = "type")]
+    pub tx_type: String,
+    pub transaction_hash: B256,
+    pub transaction_index: String,
+    pub block_hash: B256,
+    pub block_number: String,
+    pub from: Address,
+    pub to: Address,
+    pub cumulative_gas_used: String,
+    pub gas_used: String,
+    pub effective_gas_price: String,
+    // fastpay has no contracts, always null
+    pub contract_address: Option<Address>,
+    pub logs: Vec<RpcLog>,
+    pub logs_bloom: Bloom,
+    pub status: String,
+}
+
+impl TransactionReceipt {
+    pub fn new(tx: &Tx, block: &block_builder::Block, index: usize) -> Self {
+        Self {
+            tx_type: format!("{:#x}", EIP2930_TX_TYPE),
+            transaction_hash: B256::from_slice(&tx.tx_hash()),
+            transaction_index: format!("{:#x}", index),
+            block_hash: block.hash,
+            block_number: format!("{:#x}", block.number),
+            from: tx.from(),
+            to: tx.to(),
+            cumulative_gas_used: "0x0".to_string(),
+            gas_used: "0x0".to_string(),
+            effective_gas_price: "0x0".to_string(),
+            contract_address: None,
+            logs: Vec::new(),
+            logs_bloom: Bloom::ZERO,
+            status: "0x1".to_string(),
+        }
+    }
+
+    // the gas the block's txs used up to this one, as stored with the block
+    pub fn with_gas(mut self, receipt: &StoredReceipt) -> Self {
+        self.gas_used = format!("{:#x}", receipt.gas_used);
+        self.cumulative_gas_used = format!("{:#x}", receipt.cumulative_gas_used);
+        self
+    }
+
+    pub fn with_logs(mut self, logs: Vec<RpcLog>, logs_bloom: Bloom) -> Self {
+        self.logs = logs;
+        self.logs_bloom = logs_bloom;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::Transaction as _;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use serde_json::json;
+
+    fn included_transfer() -> (Tx, block_builder::Block) {
+        let signer = PrivateKeySigner::random();
+        let tx = Tx::transfer_order(
+            signer.address(),
+            Address::repeat_byte(2),
+            U256::from(500),
+            3,
+            None,
+        );
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = tx.with_signature(signature);
+        let block = block_builder::Block::new(
+            U256::from(7),
+            B256::repeat_byte(1),
+            1_700_000_000,
+            vec![tx.clone()],
+            Address::ZERO,
+        );
+        (tx, block)
+    }
+
+    #[test]
+    fn test_transaction_decodes_as_eip2930() {
+        let (tx, block) = included_transfer();
+        let transaction = Transaction::new(&tx, &block, 0);
+        let json = serde_json::to_value(&transaction).unwrap();
+
+        let decoded: alloy::rpc::types::Transaction = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(decoded.inner.tx_type() as u8, EIP2930_TX_TYPE as u8);
+        assert_eq!(decoded.from, tx.from());
+        assert_eq!(decoded.to(), Some(tx.to()));
+        assert_eq!(decoded.value(), U256::from(500));
+        assert_eq!(decoded.nonce(), 3);
+        assert_eq!(decoded.chain_id(), Some(CHAIN_ID));
+        assert_eq!(decoded.block_hash, Some(block.hash));
+        assert_eq!(decoded.block_number, Some(7));
+        assert_eq!(decoded.transaction_index, Some(0));
+        assert_eq!(decoded.inner.signature().r(), tx.signature().unwrap().r());
+
+        // Nothing is lost on the way back
+        let encoded = serde_json::to_value(&decoded).unwrap();
+        for field in json.as_object().unwrap().keys() {
+            assert_eq!(encoded[field], json[field], "{} differs", field);
+        }
+    }
+
+    #[test]
+    fn test_receipt_decodes() {
+        let (tx, block) = included_transfer();
+        let log = tx::log::Log::transfer(tx.from(), tx.to(), U256::from(500));
+        let receipt = TransactionReceipt::new(&tx, &block, 0).with_logs(
+            vec![RpcLog::new(&log, &block, 0, 0)],
+            tx::log::logs_bloom([&log]),
+        );
+        let json = serde_json::to_value(&receipt).unwrap();
+
+        let decoded: alloy::rpc::types::TransactionReceipt =
+            serde_json::from_value(json.clone()).unwrap();
+        assert!(decoded.status());
+        assert_eq!(decoded.transaction_hash, B256::from_slice(&tx.tx_hash()));
+        assert_eq!(decoded.block_number, Some(7));
+        assert_eq!(decoded.from, tx.from());
+        assert_eq!(decoded.to, Some(tx.to()));
+        assert_eq!(decoded.contract_address, None);
+        assert_eq!(decoded.inner.logs()[0].topics(), log.topics.as_slice());
+        assert_eq!(decoded.inner.logs()[0].log_index, Some(0));
+
+        let encoded = serde_json::to_value(&decoded).unwrap();
+        for field in json.as_object().unwrap().keys() {
+            assert_eq!(encoded[field], json[field], "{} differs", field);
+        }
+    }
+
+    // an EIP-2930 transaction and its receipt as a node answers them, in the layout ethers and
+    // viem fixtures use
+    #[test]
+    fn test_fixtures_round_trip() {
+        let transaction = json!({
+            "type": "0x1",
+            "chainId": "0x539",
+            "hash": "0x4e1a5f30c3a1b0b1aa2c0b2e2ab0d0fb9e2e2f8a8bbad3c7e0b1c6f8c6a9d3e1",
+            "nonce": "0x2",
+            "blockHash": "0x0b9e1e6f5a8d9c7b1a2f3e4d5c6b7a8f9e0d1c2b3a4f5e6d7c8b9a0f1e2d3c4b",
+            "blockNumber": "0x1b4",
+            "transactionIndex": "0x0",
+            "from": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+            "to": "0x095e7baea6a6c7c4c2dfeb977efac326af552d87",
+            "value": "0xa",
+            "gas": "0x0",
+            "gasPrice": "0x0",
+            "input": "0x",
+            "accessList": [],
+            "r": "0x5d9fa7b7e5f3e3c1b6d7d0e0bd5d4b5c06b3c8f3a5a5d1e4b0d1e2c7a2f6b9e3",
+            "s": "0x3b1a6fb0a0a09f5d3c1cb2e5f6d2a7e8c9b0a1f2e3d4c5b6a7f8e9d0c1b2a3f4",
+            "v": "0x1",
+            "yParity": "0x1",
+        });
+        let decoded: Transaction = serde_json::from_value(transaction.clone()).unwrap();
+        assert_eq!(decoded.signature.as_ref().unwrap().y_parity, "0x1");
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), transaction);
+
+        let receipt = json!({
+            "type": "0x1",
+            "transactionHash": "0x4e1a5f30c3a1b0b1aa2c0b2e2ab0d0fb9e2e2f8a8bbad3c7e0b1c6f8c6a9d3e1",
+            "transactionIndex": "0x0",
+            "blockHash": "0x0b9e1e6f5a8d9c7b1a2f3e4d5c6b7a8f9e0d1c2b3a4f5e6d7c8b9a0f1e2d3c4b",
+            "blockNumber": "0x1b4",
+            "from": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+            "to": "0x095e7baea6a6c7c4c2dfeb977efac326af552d87",
+            "cumulativeGasUsed": "0x0",
+            "gasUsed": "0x0",
+            "effectiveGasPrice": "0x0",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "status": "0x1",
+        });
+        let decoded: TransactionReceipt = serde_json::from_value(receipt.clone()).unwrap();
+        assert_eq!(decoded.contract_address, None);
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), receipt);
+    }
+}
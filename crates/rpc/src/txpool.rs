@@ -0,0 +1,101 @@
+// txpool_* views of the mempool. Only transfer orders carry a sequence number, the nonce of the
+// usual txpool layout, so only they can be queued behind a gap, everything else is pending
+
+use std::collections::BTreeMap;
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tx::tx::Tx;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPoolTransaction {
+    pub hash: String,
+    pub from: Address,
+    pub to: Address,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+impl From<&Tx> for TxPoolTransaction {
+    fn from(tx: &Tx) -> Self {
+        Self {
+            hash: super::tx_hash_hex(tx),
+            from: tx.from(),
+            to: tx.to(),
+            value: format!("{:#x}", tx.amount()),
+            nonce: tx.sequence().map(|sequence| format!("{:#x}", sequence)),
+        }
+    }
+}
+
+// transactions per sender, in the order the mempool will execute them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxPoolContent {
+    pub pending: BTreeMap<Address, Vec<TxPoolTransaction>>,
+    pub queued: BTreeMap<Address, Vec<TxPoolTransaction>>,
+}
+
+impl TxPoolContent {
+    pub fn new(pending: &[Tx], queued: &[Tx]) -> Self {
+        let mut content = Self::default();
+        for (txs, by_sender) in [
+            (pending, &mut content.pending),
+            (queued, &mut content.queued),
+        ] {
+            for tx in txs {
+                by_sender
+                    .entry(tx.from())
+                    .or_default()
+                    .push(TxPoolTransaction::from(tx));
+            }
+        }
+        content
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxPoolStatus {
+    pub pending: String,
+    pub queued: String,
+}
+
+impl TxPoolStatus {
+    pub fn new(pending: usize, queued: usize) -> Self {
+        Self {
+            pending: format!("{:#x}", pending),
+            queued: format!("{:#x}", queued),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_content_groups_by_sender() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let txs = vec![
+            Tx::new(alice, bob, U256::from(1), None),
+            Tx::new(bob, alice, U256::from(2), None),
+            Tx::new(alice, bob, U256::from(3), None),
+        ];
+
+        let queued = vec![Tx::transfer_order(bob, alice, U256::from(4), 2, None)];
+        let content = TxPoolContent::new(&txs, &queued);
+        assert_eq!(content.queued[&bob][0].nonce.as_deref(), Some("0x2"));
+        assert_eq!(content.pending.len(), 2);
+
+        let values: Vec<&str> = content.pending[&alice]
+            .iter()
+            .map(|tx| tx.value.as_str())
+            .collect();
+        assert_eq!(values, vec!["0x1", "0x3"]);
+        assert_eq!(content.pending[&bob][0].to, alice);
+        assert_eq!(content.pending[&bob][0].nonce, None);
+    }
+}
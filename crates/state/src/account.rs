@@ -1,25 +1,382 @@
-use alloy::primitives::Address;
+use std::collections::BTreeMap;
+
+use alloy::primitives::{keccak256, Address, U256};
+use tx::asset::{is_native, AssetId};
+
+use crate::escrow::Escrow;
+use crate::htlc::Htlc;
+use crate::policy::{DailySpending, SpendingPolicy};
+use crate::schedule::Schedule;
+
+const MULTISIG_ADDRESS_DOMAIN: &[u8] = b"fastpay-multisig";
+
+// an M-of-N multisig: funds only leave the account with signatures of `threshold` of its owners
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multisig {
+    // sorted and deduplicated
+    owners: Vec<Address>,
+    threshold: u64,
+}
+
+impl Multisig {
+    pub fn new(mut owners: Vec<Address>, threshold: u64) -> Result<Self, String> {
+        owners.sort();
+        owners.dedup();
+        if threshold == 0 || threshold > owners.len() as u64 {
+            return Err(format!(
+                "Multisig threshold {} is not between 1 and its {} owners",
+                threshold,
+                owners.len()
+            ));
+        }
+        Ok(Self { owners, threshold })
+    }
+
+    pub fn owners(&self) -> &[Address] {
+        &self.owners
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    // whether `signers` has enough distinct owners among them
+    pub fn is_met_by(&self, signers: &[Address]) -> bool {
+        let mut owners: Vec<_> = signers
+            .iter()
+            .filter(|signer| self.owners.binary_search(signer).is_ok())
+            .collect();
+        owners.sort();
+        owners.dedup();
+        owners.len() as u64 >= self.threshold
+    }
+
+    // an address nobody holds the key of, derived from the owners and the threshold
+    pub fn address(&self) -> Address {
+        let mut encoded = MULTISIG_ADDRESS_DOMAIN.to_vec();
+        encoded.extend_from_slice(&self.threshold.to_be_bytes());
+        for owner in &self.owners {
+            encoded.extend_from_slice(owner.as_slice());
+        }
+        Address::from_word(keccak256(encoded))
+    }
+}
+
+// who controls an account's funds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AccountKind {
+    // whoever holds the key the address is derived from
+    #[default]
+    Eoa,
+    Multisig(Multisig),
+    // a part of the chain itself, e.g. the bridge's escrow, named by the module. Nobody signs for
+    // it, only the module's own txs move its funds
+    Module(String),
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Account {
     address: Address,
-    balance: u64,
+    balance: U256,
+    // the sequence number the account's next transfer order has to carry
+    sequence: u64,
+    kind: AccountKind,
+    // a human readable name, e.g. "treasury", only for display
+    label: Option<String>,
+    // the block the account was created in, an account dropped once empty is created again the
+    // next time it is funded. 0 for the genesis
+    created_at: u64,
+    // limits on what leaves the account, set by its owner
+    policy: Option<SpendingPolicy>,
+    // only kept while there is a policy
+    spending: DailySpending,
+    // recurring payments out of the account, in the order they were registered
+    schedules: Vec<Schedule>,
+    // funds the account locked in escrows it hasn't seen settled yet, not part of its balance
+    escrows: Vec<Escrow>,
+    // funds the account locked for others under a hashlock, not part of its balance until claimed
+    // by the recipient or refunded
+    htlcs: Vec<Htlc>,
+    // balances of assets other than the native token, an asset the account holds none of has no
+    // entry
+    assets: BTreeMap<AssetId, U256>,
 }
 
 impl Account {
-    pub fn new(address: Address, balance: u64) -> Self {
-        Self { address, balance }
+    pub fn new(address: Address, balance: U256) -> Self {
+        Self {
+            address,
+            balance,
+            sequence: 0,
+            kind: AccountKind::Eoa,
+            label: None,
+            created_at: 0,
+            policy: None,
+            spending: DailySpending::default(),
+            schedules: Vec::new(),
+            escrows: Vec::new(),
+            htlcs: Vec::new(),
+            assets: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_multisig(self, multisig: Multisig) -> Self {
+        self.with_kind(AccountKind::Multisig(multisig))
+    }
+
+    pub fn with_kind(mut self, kind: AccountKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_created_at(mut self, block: u64) -> Self {
+        self.created_at = block;
+        self
+    }
+
+    pub fn kind(&self) -> &AccountKind {
+        &self.kind
+    }
+
+    pub fn multisig(&self) -> Option<&Multisig> {
+        match &self.kind {
+            AccountKind::Multisig(multisig) => Some(multisig),
+            _ => None,
+        }
+    }
+
+    // the module holding the account, None unless it is module owned
+    pub fn module(&self) -> Option<&str> {
+        match &self.kind {
+            AccountKind::Module(module) => Some(module),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn with_policy(mut self, policy: SpendingPolicy) -> Self {
+        self.set_policy(Some(policy));
+        self
+    }
+
+    pub fn policy(&self) -> Option<&SpendingPolicy> {
+        self.policy.as_ref()
+    }
+
+    // an unrestricted policy removes it, and with it what was spent under it
+    pub fn set_policy(&mut self, policy: Option<SpendingPolicy>) {
+        self.policy = policy.filter(|policy| !policy.is_unrestricted());
+        if self.policy.is_none() {
+            self.spending = DailySpending::default();
+        }
+    }
+
+    pub fn spending(&self) -> DailySpending {
+        self.spending
     }
 
-    pub fn balance(&self) -> u64 {
+    pub fn set_spending(&mut self, spending: DailySpending) {
+        self.spending = spending;
+    }
+
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    pub fn set_schedules(&mut self, schedules: Vec<Schedule>) {
+        self.schedules = schedules;
+    }
+
+    pub fn escrows(&self) -> &[Escrow] {
+        &self.escrows
+    }
+
+    pub fn set_escrows(&mut self, escrows: Vec<Escrow>) {
+        self.escrows = escrows;
+    }
+
+    // everything locked in the account's escrows
+    pub fn escrowed(&self) -> U256 {
+        self.escrows.iter().fold(U256::ZERO, |escrowed, escrow| {
+            escrowed.saturating_add(escrow.amount)
+        })
+    }
+
+    pub fn htlcs(&self) -> &[Htlc] {
+        &self.htlcs
+    }
+
+    pub fn set_htlcs(&mut self, htlcs: Vec<Htlc>) {
+        self.htlcs = htlcs;
+    }
+
+    // funds kept with the account that aren't part of its balance, its escrows and htlcs
+    pub fn locked(&self) -> U256 {
+        self.htlcs.iter().fold(self.escrowed(), |locked, htlc| {
+            locked.saturating_add(htlc.amount)
+        })
+    }
+
+    // the native token's balance for the native asset
+    pub fn asset_balance(&self, asset: &AssetId) -> U256 {
+        if is_native(asset) {
+            return self.balance;
+        }
+        self.assets.get(asset).copied().unwrap_or_default()
+    }
+
+    pub fn set_asset_balance(&mut self, asset: AssetId, balance: U256) {
+        if is_native(&asset) {
+            self.balance = balance;
+        } else if balance.is_zero() {
+            self.assets.remove(&asset);
+        } else {
+            self.assets.insert(asset, balance);
+        }
+    }
+
+    // the assets other than the native token the account holds, by id
+    pub fn assets(&self) -> &BTreeMap<AssetId, U256> {
+        &self.assets
+    }
+
+    // an empty account is the same as one that never existed. A multisig or module account is
+    // never empty, who controls it has to be remembered for funds sent to it later, nor is one
+    // with a policy, scheduled payments, escrows, htlcs or other assets. A label alone doesn't keep
+    // an account
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_zero()
+            && self.sequence == 0
+            && self.kind == AccountKind::Eoa
+            && self.policy.is_none()
+            && self.schedules.is_empty()
+            && self.escrows.is_empty()
+            && self.htlcs.is_empty()
+            && self.assets.is_empty()
+    }
+
+    pub fn balance(&self) -> U256 {
         self.balance
     }
 
-    pub fn set_balance(&mut self, balance: u64) {
+    pub fn set_balance(&mut self, balance: U256) {
         self.balance = balance;
     }
 
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn set_sequence(&mut self, sequence: u64) {
+        self.sequence = sequence;
+    }
+
     pub fn get_address(&self) -> Address {
-        self.address.clone()
+        self.address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multisig() {
+        let owners: Vec<_> = (1..=3).map(Address::repeat_byte).collect();
+        assert!(Multisig::new(owners.clone(), 0).is_err());
+        assert!(Multisig::new(owners.clone(), 4).is_err());
+        // Owners listed twice only count once
+        assert!(Multisig::new(vec![owners[0], owners[0]], 2).is_err());
+
+        let multisig = Multisig::new(owners.iter().rev().copied().collect(), 2).unwrap();
+        assert_eq!(multisig.owners(), owners.as_slice());
+        assert!(multisig.is_met_by(&[owners[2], owners[0]]));
+        assert!(!multisig.is_met_by(&[owners[1], owners[1]]));
+        assert!(!multisig.is_met_by(&[owners[1], Address::repeat_byte(9)]));
+
+        assert_eq!(
+            multisig.address(),
+            Multisig::new(owners.clone(), 2).unwrap().address()
+        );
+        assert_ne!(
+            multisig.address(),
+            Multisig::new(owners.clone(), 3).unwrap().address()
+        );
+
+        let account = Account::new(multisig.address(), U256::ZERO);
+        assert!(account.is_empty());
+        assert!(!account.with_multisig(multisig).is_empty());
+    }
+
+    #[test]
+    fn test_account_kinds() {
+        let address = Address::repeat_byte(1);
+        let account = Account::new(address, U256::ZERO);
+        assert_eq!(account.kind(), &AccountKind::Eoa);
+        assert_eq!((account.label(), account.created_at()), (None, 0));
+
+        // Labels are only for display, they don't keep an account alive
+        let labeled = account.clone().with_label("treasury").with_created_at(7);
+        assert_eq!(labeled.label(), Some("treasury"));
+        assert_eq!(labeled.created_at(), 7);
+        assert!(labeled.is_empty());
+
+        let escrow = account.with_kind(AccountKind::Module("bridge".to_string()));
+        assert_eq!(escrow.module(), Some("bridge"));
+        assert_eq!(escrow.multisig(), None);
+        assert!(!escrow.is_empty());
+    }
+
+    #[test]
+    fn test_asset_balances() {
+        let mut account = Account::new(Address::repeat_byte(1), U256::from(5));
+        let usd = AssetId::repeat_byte(1);
+        assert_eq!(
+            account.asset_balance(&tx::asset::NATIVE_ASSET),
+            U256::from(5)
+        );
+        assert_eq!(account.asset_balance(&usd), U256::ZERO);
+
+        account.set_balance(U256::ZERO);
+        account.set_asset_balance(usd, U256::from(7));
+        assert_eq!(account.asset_balance(&usd), U256::from(7));
+        assert!(!account.is_empty());
+        // An asset spent down to nothing leaves no entry behind
+        account.set_asset_balance(usd, U256::ZERO);
+        assert!(account.assets().is_empty());
+        assert!(account.is_empty());
+    }
+
+    #[test]
+    fn test_policy() {
+        let mut account =
+            Account::new(Address::repeat_byte(1), U256::ZERO).with_policy(SpendingPolicy {
+                daily_limit: Some(U256::from(10)),
+                ..SpendingPolicy::default()
+            });
+        account.set_spending(DailySpending::default().add(1, U256::from(4)));
+        assert!(!account.is_empty());
+
+        // Lifting every limit drops the policy and what was spent under it
+        account.set_policy(Some(SpendingPolicy::default()));
+        assert_eq!(account.policy(), None);
+        assert_eq!(account.spending(), DailySpending::default());
+        assert!(account.is_empty());
     }
 }
@@ -0,0 +1,78 @@
+// a unidirectional payment channel: the payer locks a deposit up front and pays the payee off-chain
+// with signed balance updates, the payee settles the latest one on-chain to close the channel.
+// if the payee goes away the payer can start a timeout and take the deposit back once the
+// challenge period has passed without the payee closing
+
+use alloy::primitives::{Address, U256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    payer: Address,
+    payee: Address,
+    deposit: U256,
+    // number of blocks the payee has to close once the payer starts the timeout
+    challenge_period: u64,
+    // block from which the payer can take the deposit back
+    expires_at: Option<u64>,
+}
+
+impl Channel {
+    pub fn new(payer: Address, payee: Address, deposit: U256, challenge_period: u64) -> Self {
+        Self {
+            payer,
+            payee,
+            deposit,
+            challenge_period,
+            expires_at: None,
+        }
+    }
+
+    pub fn payer(&self) -> Address {
+        self.payer
+    }
+
+    pub fn payee(&self) -> Address {
+        self.payee
+    }
+
+    pub fn deposit(&self) -> U256 {
+        self.deposit
+    }
+
+    pub fn challenge_period(&self) -> u64 {
+        self.challenge_period
+    }
+
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at
+    }
+
+    pub fn start_timeout(&mut self, current_block: u64) {
+        self.expires_at = Some(current_block.saturating_add(self.challenge_period));
+    }
+
+    pub fn is_expired(&self, current_block: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if current_block >= expires_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout() {
+        let mut channel = Channel::new(
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            U256::from(100),
+            10,
+        );
+        assert!(!channel.is_expired(u64::MAX));
+
+        channel.start_timeout(5);
+        assert_eq!(channel.expires_at(), Some(15));
+        assert!(!channel.is_expired(14));
+        assert!(channel.is_expired(15));
+    }
+}
@@ -0,0 +1,523 @@
+// concurrent state: every account sits behind a lock of its own, so transfers between unrelated
+// accounts run in parallel where the sharded state makes them wait whenever their accounts share a
+// shard. Channels, the supply and the seen txs keep one lock each
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use alloy::primitives::{Address, B256, U256};
+
+use crate::account::Account;
+use crate::channel::Channel;
+use crate::state::{SnapshotId, State, StateError};
+
+// None once the account is removed, slots are never dropped
+type Slot = Arc<Mutex<Option<Account>>>;
+
+// what a write overwrote, like the memory state's journal
+enum Change {
+    Account(Address, Option<Box<Account>>),
+    Channel(B256, Option<Channel>),
+    TotalSupply(U256),
+    SeenTx(B256, Option<u64>),
+}
+
+#[derive(Default)]
+struct Journal {
+    changes: Vec<Change>,
+    // the length of the journal when each open snapshot was taken
+    snapshots: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct ConcurrentState {
+    // a slot for every address ever written. Writes hold the map's read lock for as long as they
+    // run, it is only write-locked to add slots and to take or close snapshots, so those never
+    // see a write half done
+    accounts: RwLock<HashMap<Address, Slot>>,
+    channels: RwLock<HashMap<B256, Channel>>,
+    total_supply: Mutex<U256>,
+    seen_txs: RwLock<HashMap<B256, u64>>,
+    journal: Mutex<Journal>,
+    // whether a snapshot is open, so writes don't take the journal's lock when none is
+    journaling: AtomicBool,
+}
+
+impl ConcurrentState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read_account(&self, address: &Address) -> Option<Account> {
+        let accounts = self.accounts.read().unwrap();
+        let account = accounts.get(address)?.lock().unwrap().clone();
+        account
+    }
+
+    // `None` removes the account
+    pub fn write_account(&self, address: &Address, account: Option<Account>) {
+        self.add_slots(&[*address]);
+        let accounts = self.accounts.read().unwrap();
+        let previous = std::mem::replace(&mut *accounts[address].lock().unwrap(), account);
+        self.record(Change::Account(*address, previous.map(Box::new)));
+    }
+
+    pub fn read_channel(&self, id: &B256) -> Option<Channel> {
+        self.channels.read().unwrap().get(id).cloned()
+    }
+
+    pub fn write_channel(&self, id: &B256, channel: Option<Channel>) {
+        let _accounts = self.accounts.read().unwrap();
+        let mut channels = self.channels.write().unwrap();
+        let previous = match channel {
+            Some(channel) => channels.insert(*id, channel),
+            None => channels.remove(id),
+        };
+        self.record(Change::Channel(*id, previous));
+    }
+
+    pub fn read_total_supply(&self) -> U256 {
+        *self.total_supply.lock().unwrap()
+    }
+
+    pub fn write_total_supply(&self, supply: U256) {
+        let _accounts = self.accounts.read().unwrap();
+        let previous = std::mem::replace(&mut *self.total_supply.lock().unwrap(), supply);
+        self.record(Change::TotalSupply(previous));
+    }
+
+    pub fn read_seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.seen_txs.read().unwrap().get(hash).copied()
+    }
+
+    pub fn write_seen_tx(&self, hash: &B256, block: Option<u64>) {
+        let _accounts = self.accounts.read().unwrap();
+        let mut seen_txs = self.seen_txs.write().unwrap();
+        let previous = match block {
+            Some(block) => seen_txs.insert(*hash, block),
+            None => seen_txs.remove(hash),
+        };
+        self.record(Change::SeenTx(*hash, previous));
+    }
+
+    pub fn all_seen_txs(&self) -> Vec<(B256, u64)> {
+        self.seen_txs
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(hash, block)| (*hash, *block))
+            .collect()
+    }
+
+    pub fn all_accounts(&self) -> Vec<Account> {
+        self.accounts
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|slot| slot.lock().unwrap().clone())
+            .collect()
+    }
+
+    pub fn all_channels(&self) -> Vec<(B256, Channel)> {
+        self.channels
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, channel)| (*id, channel.clone()))
+            .collect()
+    }
+
+    // moves `amount` between two accounts holding only their locks, signature checks are expected
+    // to have happened before. Same rules as the sharded state's apply_transfer
+    pub fn apply_transfer(
+        &self,
+        from: &Address,
+        to: &Address,
+        amount: U256,
+    ) -> Result<(), StateError> {
+        self.add_slots(&[*from, *to]);
+        let accounts = self.accounts.read().unwrap();
+
+        if from == to {
+            let from_account = accounts[from].lock().unwrap();
+            let from_account = from_account.as_ref().ok_or(StateError::AccountNotFound)?;
+            if from_account.balance() < amount {
+                return Err(StateError::AccountBalanceTooLow);
+            }
+            return Ok(());
+        }
+
+        // accounts are always locked in ascending order so two transfers can't deadlock
+        let (mut from_slot, mut to_slot) = if from < to {
+            let from_slot = accounts[from].lock().unwrap();
+            (from_slot, accounts[to].lock().unwrap())
+        } else {
+            let to_slot = accounts[to].lock().unwrap();
+            (accounts[from].lock().unwrap(), to_slot)
+        };
+
+        let mut from_account = from_slot.clone().ok_or(StateError::AccountNotFound)?;
+        if from_account.balance() < amount {
+            return Err(StateError::AccountBalanceTooLow);
+        }
+        let mut to_account = to_slot
+            .clone()
+            .unwrap_or_else(|| Account::new(*to, U256::ZERO));
+        let credited = to_account
+            .balance()
+            .checked_add(amount)
+            .ok_or(StateError::BalanceOverflow)?;
+        to_account.set_balance(credited);
+        from_account.set_balance(from_account.balance() - amount);
+
+        // an account left with nothing is dropped, like the vm does
+        let from_account = (!from_account.is_empty()).then_some(from_account);
+        let previous_from = std::mem::replace(&mut *from_slot, from_account);
+        let previous_to = to_slot.replace(to_account);
+        self.record(Change::Account(*from, previous_from.map(Box::new)));
+        self.record(Change::Account(*to, previous_to.map(Box::new)));
+        Ok(())
+    }
+
+    // waits for the writes in flight, the ones after it are journaled
+    pub fn take_snapshot(&self) -> SnapshotId {
+        let _accounts = self.accounts.write().unwrap();
+        let mut journal = self.journal.lock().unwrap();
+        let length = journal.changes.len();
+        journal.snapshots.push(length);
+        self.journaling.store(true, Ordering::Relaxed);
+        SnapshotId(journal.snapshots.len() - 1)
+    }
+
+    pub fn revert_snapshot(&self, id: SnapshotId) -> Result<(), StateError> {
+        let mut accounts = self.accounts.write().unwrap();
+        let mut journal = self.journal.lock().unwrap();
+        let length = *journal
+            .snapshots
+            .get(id.0)
+            .ok_or(StateError::UnknownSnapshot)?;
+        // undone newest first, so an entry changed twice ends up as it was at the snapshot
+        for change in journal.changes.drain(length..).rev() {
+            match change {
+                Change::Account(address, account) => {
+                    *accounts.entry(address).or_default().lock().unwrap() =
+                        account.map(|account| *account);
+                }
+                Change::Channel(id, Some(channel)) => {
+                    self.channels.write().unwrap().insert(id, channel);
+                }
+                Change::Channel(id, None) => {
+                    self.channels.write().unwrap().remove(&id);
+                }
+                Change::TotalSupply(supply) => *self.total_supply.lock().unwrap() = supply,
+                Change::SeenTx(hash, Some(block)) => {
+                    self.seen_txs.write().unwrap().insert(hash, block);
+                }
+                Change::SeenTx(hash, None) => {
+                    self.seen_txs.write().unwrap().remove(&hash);
+                }
+            }
+        }
+        Self::close_snapshots(&mut journal, id, &self.journaling);
+        Ok(())
+    }
+
+    pub fn commit_snapshot(&self, id: SnapshotId) -> Result<(), StateError> {
+        let _accounts = self.accounts.write().unwrap();
+        let mut journal = self.journal.lock().unwrap();
+        if id.0 >= journal.snapshots.len() {
+            return Err(StateError::UnknownSnapshot);
+        }
+        Self::close_snapshots(&mut journal, id, &self.journaling);
+        Ok(())
+    }
+
+    // drops `id` and every snapshot taken after it, and the journal with the last one
+    fn close_snapshots(journal: &mut Journal, id: SnapshotId, journaling: &AtomicBool) {
+        journal.snapshots.truncate(id.0);
+        if journal.snapshots.is_empty() {
+            journal.changes.clear();
+            journaling.store(false, Ordering::Relaxed);
+        }
+    }
+
+    // the map is only write-locked when one of the addresses is new
+    fn add_slots(&self, addresses: &[Address]) {
+        let accounts = self.accounts.read().unwrap();
+        if addresses
+            .iter()
+            .all(|address| accounts.contains_key(address))
+        {
+            return;
+        }
+        drop(accounts);
+        let mut accounts = self.accounts.write().unwrap();
+        for address in addresses {
+            accounts.entry(*address).or_default();
+        }
+    }
+
+    // called with the accounts' read lock held, so a snapshot can't be taken or closed meanwhile
+    fn record(&self, change: Change) {
+        if self.journaling.load(Ordering::Relaxed) {
+            self.journal.lock().unwrap().changes.push(change);
+        }
+    }
+}
+
+impl State for ConcurrentState {
+    fn get_account(&self, address: &Address) -> Option<Account> {
+        self.read_account(address)
+    }
+
+    fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError> {
+        self.write_account(address, Some(account));
+        Ok(())
+    }
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError> {
+        self.write_account(address, None);
+        Ok(())
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        self.read_channel(id)
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        self.write_channel(id, channel);
+        Ok(())
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        self.all_accounts()
+    }
+
+    fn channels(&self) -> Vec<(B256, Channel)> {
+        self.all_channels()
+    }
+
+    fn total_supply(&self) -> U256 {
+        self.read_total_supply()
+    }
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError> {
+        self.write_total_supply(supply);
+        Ok(())
+    }
+
+    fn seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.read_seen_tx(hash)
+    }
+
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        self.write_seen_tx(hash, block);
+        Ok(())
+    }
+
+    fn seen_txs(&self) -> Vec<(B256, u64)> {
+        self.all_seen_txs()
+    }
+
+    fn snapshot(&mut self) -> SnapshotId {
+        self.take_snapshot()
+    }
+
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.revert_snapshot(id)
+    }
+
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.commit_snapshot(id)
+    }
+}
+
+// lets a node execute against the state while other components transfer and read concurrently
+impl State for Arc<ConcurrentState> {
+    fn get_account(&self, address: &Address) -> Option<Account> {
+        self.read_account(address)
+    }
+
+    fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError> {
+        self.write_account(address, Some(account));
+        Ok(())
+    }
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError> {
+        self.write_account(address, None);
+        Ok(())
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        self.read_channel(id)
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        self.write_channel(id, channel);
+        Ok(())
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        self.all_accounts()
+    }
+
+    fn channels(&self) -> Vec<(B256, Channel)> {
+        self.all_channels()
+    }
+
+    fn total_supply(&self) -> U256 {
+        self.read_total_supply()
+    }
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError> {
+        self.write_total_supply(supply);
+        Ok(())
+    }
+
+    fn seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.read_seen_tx(hash)
+    }
+
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        self.write_seen_tx(hash, block);
+        Ok(())
+    }
+
+    fn seen_txs(&self) -> Vec<(B256, u64)> {
+        self.all_seen_txs()
+    }
+
+    fn snapshot(&mut self) -> SnapshotId {
+        self.take_snapshot()
+    }
+
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.revert_snapshot(id)
+    }
+
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.commit_snapshot(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryState;
+    use alloy::signers::local::PrivateKeySigner;
+
+    fn random_address() -> Address {
+        PrivateKeySigner::random().address()
+    }
+
+    #[test]
+    fn test_apply_transfer() {
+        let state = ConcurrentState::new();
+        let from = random_address();
+        let to = random_address();
+        state.write_account(&from, Some(Account::new(from, U256::from(50))));
+
+        state.apply_transfer(&from, &to, U256::from(20)).unwrap();
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(30));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::from(20));
+
+        assert_eq!(
+            state.apply_transfer(&from, &to, U256::from(31)),
+            Err(StateError::AccountBalanceTooLow)
+        );
+        let nobody = random_address();
+        assert_eq!(
+            state.apply_transfer(&nobody, &to, U256::from(1)),
+            Err(StateError::AccountNotFound)
+        );
+        // Slots made for unknown addresses don't show up as accounts
+        assert_eq!(state.all_accounts().len(), 2);
+
+        // Draining an account removes it
+        state.apply_transfer(&from, &to, U256::from(30)).unwrap();
+        assert!(state.read_account(&from).is_none());
+    }
+
+    #[test]
+    fn test_concurrent_transfers() {
+        let state = ConcurrentState::new();
+        let hub = random_address();
+        let pairs: Vec<(Address, Address)> = (0..32)
+            .map(|_| (random_address(), random_address()))
+            .collect();
+        for (from, _) in &pairs {
+            state.write_account(from, Some(Account::new(*from, U256::from(1000))));
+        }
+
+        std::thread::scope(|scope| {
+            for (from, to) in &pairs {
+                let state = &state;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        state.apply_transfer(from, to, U256::from(10)).unwrap();
+                        // And all of them pay into the same account, in both lock orders
+                        state.apply_transfer(from, &hub, U256::from(1)).unwrap();
+                    }
+                });
+            }
+        });
+
+        for (from, to) in &pairs {
+            assert_eq!(state.read_account(from).unwrap().balance(), U256::from(890));
+            assert_eq!(state.read_account(to).unwrap().balance(), U256::from(100));
+        }
+        assert_eq!(state.read_account(&hub).unwrap().balance(), U256::from(320));
+    }
+
+    #[test]
+    fn test_snapshot_and_revert() {
+        let state = Arc::new(ConcurrentState::new());
+        let mut writer: Box<dyn State> = Box::new(state.clone());
+        let from = random_address();
+        let to = random_address();
+        writer
+            .update_account(&from, Account::new(from, U256::from(50)))
+            .unwrap();
+
+        let snapshot = writer.snapshot();
+        state.apply_transfer(&from, &to, U256::from(50)).unwrap();
+        writer.set_total_supply(U256::from(7)).unwrap();
+        writer
+            .update_channel(
+                &B256::repeat_byte(1),
+                Some(Channel::new(from, to, U256::from(1), 10)),
+            )
+            .unwrap();
+        writer.revert_to(snapshot).unwrap();
+
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(50));
+        assert!(state.read_account(&to).is_none());
+        assert_eq!(state.read_total_supply(), U256::ZERO);
+        assert!(state.all_channels().is_empty());
+        assert_eq!(writer.revert_to(snapshot), Err(StateError::UnknownSnapshot));
+
+        // Committed changes stay
+        let snapshot = writer.snapshot();
+        state.apply_transfer(&from, &to, U256::from(5)).unwrap();
+        writer.commit(snapshot).unwrap();
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::from(5));
+    }
+
+    #[test]
+    fn test_state_root_matches_memory_state() {
+        let mut concurrent = ConcurrentState::new();
+        let mut memory = MemoryState::new();
+        for i in 1..=5u8 {
+            let address = Address::repeat_byte(i);
+            let account = Account::new(address, U256::from(i));
+            concurrent
+                .update_account(&address, account.clone())
+                .unwrap();
+            memory.update_account(&address, account).unwrap();
+        }
+        concurrent.remove_account(&Address::repeat_byte(3)).unwrap();
+        memory.remove_account(&Address::repeat_byte(3)).unwrap();
+        assert_eq!(concurrent.state_root(), memory.state_root());
+    }
+}
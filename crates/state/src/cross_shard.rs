@@ -0,0 +1,134 @@
+// cross-shard transfers in two phases: the sender's shard debits and leaves a message in the
+// recipient shard's inbox, the recipient's shard credits it when it drains its inbox. Neither
+// phase holds more than one shard's lock. A credited message is recorded among the recipient
+// shard's seen txs, under delivery_key, so one sent again after a crash, by a sender that can't
+// tell whether it arrived, is credited only once, and the record is pruned and persisted like
+// the seen txs are. Only a message the sender's shard has in its outbox, debited and not
+// delivered yet, can be sent again. A message that can't be credited is dead-lettered and
+// refunded to its sender
+
+use std::collections::{HashMap, VecDeque};
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossShardMessage {
+    // the hash of the transfer that sent it, what makes delivery idempotent
+    pub id: B256,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    // the shard holding `to`
+    pub to_shard: usize,
+}
+
+impl CrossShardMessage {
+    // where the message's delivery is recorded among the seen txs. Not the id itself, the vm
+    // records that once the transfer executes. It starts with the recipient's first two bytes,
+    // so it is kept in the recipient's shard whatever the number of shards
+    pub fn delivery_key(&self) -> B256 {
+        let mut key = keccak256([b"fastpay-cross-shard".as_slice(), self.id.as_slice()].concat());
+        key[..2].copy_from_slice(&self.to[..2]);
+        key
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Inbox {
+    pending: VecDeque<CrossShardMessage>,
+    // debited but neither credited nor refunded yet, see ShardedState::refund_dead_letters
+    dead_letters: Vec<CrossShardMessage>,
+}
+
+impl Inbox {
+    pub fn contains(&self, id: &B256) -> bool {
+        self.pending.iter().any(|pending| pending.id == *id)
+    }
+
+    // queues a message unless it is already queued, returns whether it was queued
+    pub fn push(&mut self, message: CrossShardMessage) -> bool {
+        if self.contains(&message.id) {
+            return false;
+        }
+        self.pending.push_back(message);
+        true
+    }
+
+    pub fn front(&self) -> Option<&CrossShardMessage> {
+        self.pending.front()
+    }
+
+    // drops the front message, once it was credited or dead-lettered
+    pub fn pop(&mut self) -> Option<CrossShardMessage> {
+        self.pending.pop_front()
+    }
+
+    pub fn dead_letter(&mut self, message: CrossShardMessage) {
+        self.dead_letters.push(message);
+    }
+
+    pub fn take_dead_letter(&mut self) -> Option<CrossShardMessage> {
+        self.dead_letters.pop()
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &CrossShardMessage> {
+        self.pending.iter()
+    }
+
+    pub fn dead_letters(&self) -> &[CrossShardMessage] {
+        &self.dead_letters
+    }
+}
+
+// the messages a shard's accounts were debited for that haven't been delivered yet
+#[derive(Debug, Default)]
+pub struct Outbox {
+    sent: HashMap<B256, CrossShardMessage>,
+}
+
+impl Outbox {
+    pub fn contains(&self, id: &B256) -> bool {
+        self.sent.contains_key(id)
+    }
+
+    pub fn record(&mut self, message: CrossShardMessage) {
+        self.sent.insert(message.id, message);
+    }
+
+    pub fn get(&self, id: &B256) -> Option<&CrossShardMessage> {
+        self.sent.get(id)
+    }
+
+    // drops the message once it was credited or dead-lettered
+    pub fn settle(&mut self, id: &B256) -> Option<CrossShardMessage> {
+        self.sent.remove(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inbox_drops_duplicates() {
+        let mut inbox = Inbox::default();
+        let message = CrossShardMessage {
+            id: B256::repeat_byte(1),
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            amount: U256::from(5),
+            to_shard: 0,
+        };
+
+        assert!(inbox.push(message.clone()));
+        assert!(!inbox.push(message.clone()));
+        assert!(inbox.contains(&message.id));
+        assert_eq!(inbox.pop(), Some(message.clone()));
+        assert!(inbox.front().is_none());
+
+        // Kept in the recipient's shard, apart from the transfer's own seen tx
+        let key = message.delivery_key();
+        assert_eq!(key[..2], message.to[..2]);
+        assert_ne!(key, message.id);
+    }
+}
@@ -0,0 +1,66 @@
+// funds held between a payer and a payee until an arbiter settles them, e.g. a marketplace holding
+// a buyer's payment until the goods arrive. The payer locks them with an EscrowCreate tx, a release
+// pays them to the payee and a refund returns them to the payer. The arbiter can do either, the
+// payer can release and the payee can refund, and once the timeout has passed the payer can take
+// them back without the arbiter
+
+use alloy::primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Escrow {
+    // the hash of the EscrowCreate tx that locked it
+    pub id: B256,
+    pub to: Address,
+    pub arbiter: Address,
+    pub amount: U256,
+    // the block from which the payer can refund it alone
+    pub expires_at: u64,
+}
+
+impl Escrow {
+    pub fn is_expired(&self, block: u64) -> bool {
+        block >= self.expires_at
+    }
+
+    // whether `sender` may pay the escrow `payer` locked out to the payee
+    pub fn can_release(&self, payer: Address, sender: Address) -> bool {
+        sender == self.arbiter || sender == payer
+    }
+
+    // or back to the payer in `block`
+    pub fn can_refund(&self, payer: Address, sender: Address, block: u64) -> bool {
+        sender == self.arbiter || sender == self.to || (sender == payer && self.is_expired(block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_who_settles() {
+        let payer = Address::repeat_byte(1);
+        let escrow = Escrow {
+            id: B256::ZERO,
+            to: Address::repeat_byte(2),
+            arbiter: Address::repeat_byte(3),
+            amount: U256::from(10),
+            expires_at: 20,
+        };
+        let stranger = Address::repeat_byte(4);
+
+        assert!(escrow.can_release(payer, escrow.arbiter));
+        assert!(escrow.can_release(payer, payer));
+        assert!(!escrow.can_release(payer, escrow.to));
+        assert!(!escrow.can_release(payer, stranger));
+
+        assert!(escrow.can_refund(payer, escrow.arbiter, 0));
+        assert!(escrow.can_refund(payer, escrow.to, 0));
+        // The payer only once the timeout has passed
+        assert!(!escrow.can_refund(payer, payer, 19));
+        assert!(escrow.can_refund(payer, payer, 20));
+        assert!(!escrow.can_refund(payer, stranger, 20));
+    }
+}
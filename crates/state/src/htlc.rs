@@ -0,0 +1,26 @@
+// hash time-locked contracts, for atomic swaps with other chains. The sender locks funds for a
+// recipient under the hash of a secret, the recipient takes them by revealing the secret before
+// the timelock, and from the timelock on the sender can take them back. The counterparty on the
+// other chain learns the secret from the claim and uses it there. Kept on the sender's account,
+// like an escrow, so what it can hold bounds only the sender's own locks; a claim names the sender
+// it claims from
+
+use alloy::primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Htlc {
+    pub recipient: Address,
+    // the sha256 of the secret, the hash Bitcoin and Ethereum swap contracts use
+    pub hashlock: B256,
+    pub amount: U256,
+    // the block from which the recipient can no longer claim and the sender can refund
+    pub timelock: u64,
+}
+
+impl Htlc {
+    pub fn is_expired(&self, block: u64) -> bool {
+        block >= self.timelock
+    }
+}
@@ -1,3 +1,14 @@
 pub mod account;
+pub mod channel;
+pub mod concurrent;
+pub mod cross_shard;
+pub mod escrow;
+pub mod htlc;
 pub mod memory;
+pub mod pending;
+pub mod policy;
+pub mod root;
+pub mod schedule;
+pub mod sharded;
 pub mod state;
+pub mod supply;
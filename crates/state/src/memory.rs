@@ -2,19 +2,68 @@
 
 use std::collections::HashMap;
 
-use alloy::primitives::Address;
+use alloy::primitives::{Address, B256, U256};
 
 use crate::account::Account;
-use crate::state::{State, StateError};
+use crate::channel::Channel;
+use crate::state::{SnapshotId, State, StateError};
+
+// what a write overwrote, None where there was nothing
+enum Change {
+    Account(Address, Option<Box<Account>>),
+    Channel(B256, Option<Channel>),
+    TotalSupply(U256),
+    SeenTx(B256, Option<u64>),
+}
 
 pub struct MemoryState {
     accounts: HashMap<Address, Account>,
+    channels: HashMap<B256, Channel>,
+    total_supply: U256,
+    seen_txs: HashMap<B256, u64>,
+    // the changes made since the oldest open snapshot, only kept while there is one
+    journal: Vec<Change>,
+    // the length of the journal when each open snapshot was taken
+    snapshots: Vec<usize>,
+}
+
+impl Default for MemoryState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemoryState {
     pub fn new() -> Self {
         Self {
             accounts: HashMap::new(),
+            channels: HashMap::new(),
+            total_supply: U256::ZERO,
+            seen_txs: HashMap::new(),
+            journal: Vec::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    // a detached copy of another state, to execute against without touching the original
+    pub fn copy_of(state: &dyn State) -> Self {
+        Self {
+            accounts: state
+                .accounts()
+                .into_iter()
+                .map(|account| (account.get_address(), account))
+                .collect(),
+            channels: state.channels().into_iter().collect(),
+            total_supply: state.total_supply(),
+            seen_txs: state.seen_txs().into_iter().collect(),
+            journal: Vec::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        if !self.snapshots.is_empty() {
+            self.journal.push(change);
         }
     }
 }
@@ -25,7 +74,117 @@ impl State for MemoryState {
     }
 
     fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError> {
-        self.accounts.insert(address.clone(), account);
+        let previous = self.accounts.insert(*address, account);
+        self.record(Change::Account(*address, previous.map(Box::new)));
+        Ok(())
+    }
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError> {
+        let previous = self.accounts.remove(address);
+        self.record(Change::Account(*address, previous.map(Box::new)));
+        Ok(())
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        self.channels.get(id).cloned()
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        let previous = match channel {
+            Some(channel) => self.channels.insert(*id, channel),
+            None => self.channels.remove(id),
+        };
+        self.record(Change::Channel(*id, previous));
+        Ok(())
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        self.accounts.values().cloned().collect()
+    }
+
+    fn channels(&self) -> Vec<(B256, Channel)> {
+        self.channels
+            .iter()
+            .map(|(id, channel)| (*id, channel.clone()))
+            .collect()
+    }
+
+    fn total_supply(&self) -> U256 {
+        self.total_supply
+    }
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError> {
+        let previous = std::mem::replace(&mut self.total_supply, supply);
+        self.record(Change::TotalSupply(previous));
+        Ok(())
+    }
+
+    fn seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.seen_txs.get(hash).copied()
+    }
+
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        let previous = match block {
+            Some(block) => self.seen_txs.insert(*hash, block),
+            None => self.seen_txs.remove(hash),
+        };
+        self.record(Change::SeenTx(*hash, previous));
+        Ok(())
+    }
+
+    fn seen_txs(&self) -> Vec<(B256, u64)> {
+        self.seen_txs
+            .iter()
+            .map(|(hash, block)| (*hash, *block))
+            .collect()
+    }
+
+    fn snapshot(&mut self) -> SnapshotId {
+        self.snapshots.push(self.journal.len());
+        SnapshotId(self.snapshots.len() - 1)
+    }
+
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        let length = *self
+            .snapshots
+            .get(id.0)
+            .ok_or(StateError::UnknownSnapshot)?;
+        // undone newest first, so an entry changed twice ends up as it was at the snapshot
+        for change in self.journal.drain(length..).rev() {
+            match change {
+                Change::Account(address, Some(account)) => {
+                    self.accounts.insert(address, *account);
+                }
+                Change::Account(address, None) => {
+                    self.accounts.remove(&address);
+                }
+                Change::Channel(id, Some(channel)) => {
+                    self.channels.insert(id, channel);
+                }
+                Change::Channel(id, None) => {
+                    self.channels.remove(&id);
+                }
+                Change::TotalSupply(supply) => self.total_supply = supply,
+                Change::SeenTx(hash, Some(block)) => {
+                    self.seen_txs.insert(hash, block);
+                }
+                Change::SeenTx(hash, None) => {
+                    self.seen_txs.remove(&hash);
+                }
+            }
+        }
+        self.snapshots.truncate(id.0);
+        Ok(())
+    }
+
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        if id.0 >= self.snapshots.len() {
+            return Err(StateError::UnknownSnapshot);
+        }
+        self.snapshots.truncate(id.0);
+        if self.snapshots.is_empty() {
+            self.journal.clear();
+        }
         Ok(())
     }
 }
@@ -33,6 +192,7 @@ impl State for MemoryState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy::primitives::U256;
     use alloy::signers::local::PrivateKeySigner;
 
     #[test]
@@ -55,14 +215,14 @@ mod tests {
 
         let signer = PrivateKeySigner::random();
         let address = signer.address();
-        let account = Account::new(address.clone(), 100);
+        let account = Account::new(address, U256::from(100));
 
         // Update account
         state.update_account(&address, account.clone()).unwrap();
 
         // Get account and verify
         let retrieved = state.get_account(&address).unwrap();
-        assert_eq!(retrieved.balance(), 100);
+        assert_eq!(retrieved.balance(), U256::from(100));
         assert_eq!(retrieved.get_address(), address);
     }
 
@@ -73,16 +233,16 @@ mod tests {
         let address = signer.address();
 
         // First update
-        let account1 = Account::new(address.clone(), 100);
+        let account1 = Account::new(address, U256::from(100));
         state.update_account(&address, account1).unwrap();
 
         // Second update
-        let account2 = Account::new(address.clone(), 200);
+        let account2 = Account::new(address, U256::from(200));
         state.update_account(&address, account2.clone()).unwrap();
 
         // Verify latest update
         let retrieved = state.get_account(&address).unwrap();
-        assert_eq!(retrieved.balance(), 200);
+        assert_eq!(retrieved.balance(), U256::from(200));
     }
 
     #[test]
@@ -92,17 +252,111 @@ mod tests {
         // Add first account
         let signer1 = PrivateKeySigner::random();
         let address1 = signer1.address();
-        let account1 = Account::new(address1.clone(), 100);
+        let account1 = Account::new(address1, U256::from(100));
         state.update_account(&address1, account1).unwrap();
 
         // Add second account
         let signer2 = PrivateKeySigner::random();
         let address2 = signer2.address();
-        let account2 = Account::new(address2.clone(), 200);
+        let account2 = Account::new(address2, U256::from(200));
         state.update_account(&address2, account2).unwrap();
 
         // Verify both accounts
-        assert_eq!(state.get_account(&address1).unwrap().balance(), 100);
-        assert_eq!(state.get_account(&address2).unwrap().balance(), 200);
+        assert_eq!(
+            state.get_account(&address1).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_eq!(
+            state.get_account(&address2).unwrap().balance(),
+            U256::from(200)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_and_revert() {
+        let mut state = MemoryState::new();
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let channel_id = B256::repeat_byte(1);
+        state
+            .update_account(&alice, Account::new(alice, U256::from(100)))
+            .unwrap();
+        let root = state.state_root();
+
+        let snapshot = state.snapshot();
+        state
+            .update_account(&alice, Account::new(alice, U256::from(50)))
+            .unwrap();
+        state
+            .update_account(&alice, Account::new(alice, U256::from(20)))
+            .unwrap();
+        state
+            .update_account(&bob, Account::new(bob, U256::from(80)))
+            .unwrap();
+        state
+            .update_channel(
+                &channel_id,
+                Some(Channel::new(alice, bob, U256::from(10), 5)),
+            )
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+        state
+            .update_seen_tx(&B256::repeat_byte(7), Some(3))
+            .unwrap();
+
+        // A nested snapshot goes away with the one it was taken in
+        let nested = state.snapshot();
+        state.remove_account(&bob).unwrap();
+        state.revert_to(nested).unwrap();
+        assert_eq!(state.get_account(&bob).unwrap().balance(), U256::from(80));
+
+        state.revert_to(snapshot).unwrap();
+        assert_eq!(
+            state.get_account(&alice).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_eq!(state.get_account(&bob), None);
+        assert_eq!(state.get_channel(&channel_id), None);
+        assert_eq!(state.total_supply(), U256::ZERO);
+        assert_eq!(state.seen_tx(&B256::repeat_byte(7)), None);
+        assert_eq!(state.state_root(), root);
+        assert_eq!(state.revert_to(snapshot), Err(StateError::UnknownSnapshot));
+        assert_eq!(state.revert_to(nested), Err(StateError::UnknownSnapshot));
+
+        // Committed changes stay, and nothing is journaled without a snapshot
+        let snapshot = state.snapshot();
+        state
+            .update_account(&bob, Account::new(bob, U256::from(1)))
+            .unwrap();
+        state.commit(snapshot).unwrap();
+        assert!(state.journal.is_empty());
+        assert_eq!(state.get_account(&bob).unwrap().balance(), U256::from(1));
+        assert_eq!(state.commit(snapshot), Err(StateError::UnknownSnapshot));
+    }
+
+    #[test]
+    fn test_copy_of() {
+        let mut state = MemoryState::new();
+        let address = PrivateKeySigner::random().address();
+        state
+            .update_account(&address, Account::new(address, U256::from(100)))
+            .unwrap();
+        state
+            .update_channel(
+                &B256::repeat_byte(1),
+                Some(Channel::new(address, Address::ZERO, U256::from(10), 5)),
+            )
+            .unwrap();
+
+        let mut copy = MemoryState::copy_of(&state);
+        assert_eq!(copy.state_root(), state.state_root());
+
+        copy.update_account(&address, Account::new(address, U256::from(1)))
+            .unwrap();
+        assert_eq!(
+            state.get_account(&address).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_ne!(copy.state_root(), state.state_root());
     }
 }
@@ -0,0 +1,321 @@
+// a copy-on-write overlay over the committed state: reads fall through to the committed state
+// unless the overlay changed the entry, writes only ever land in the overlay. The node executes
+// what waits in the mempool on one so that "pending" queries see in-flight txs, while the
+// committed state is only changed by blocks.
+// Clones share the overlay, which lets the node hand one to the rpc and refresh it in place
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use alloy::primitives::{Address, B256, U256};
+
+use crate::account::Account;
+use crate::channel::Channel;
+use crate::state::{SnapshotId, State, StateError};
+
+// None where the overlay removed the entry
+#[derive(Clone, Default)]
+struct Overlay {
+    accounts: HashMap<Address, Option<Account>>,
+    channels: HashMap<B256, Option<Channel>>,
+    total_supply: Option<U256>,
+    seen_txs: HashMap<B256, Option<u64>>,
+}
+
+#[derive(Default)]
+struct Changes {
+    overlay: Overlay,
+    // the overlay as it was when each open snapshot was taken
+    snapshots: Vec<Overlay>,
+}
+
+#[derive(Clone)]
+pub struct PendingState {
+    base: Arc<dyn State + Send + Sync>,
+    changes: Arc<RwLock<Changes>>,
+}
+
+impl std::fmt::Debug for PendingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let changes = self.changes.read().unwrap();
+        f.debug_struct("PendingState")
+            .field("accounts", &changes.overlay.accounts.len())
+            .field("channels", &changes.overlay.channels.len())
+            .finish()
+    }
+}
+
+impl PendingState {
+    pub fn new(base: Arc<dyn State + Send + Sync>) -> Self {
+        Self {
+            base,
+            changes: Arc::new(RwLock::new(Changes::default())),
+        }
+    }
+
+    // an empty overlay over the same committed state, not shared with this one
+    pub fn fresh(&self) -> Self {
+        Self::new(self.base.clone())
+    }
+
+    // a copy of the overlay over the same committed state, changes to either don't show in the
+    // other
+    pub fn fork(&self) -> Self {
+        let overlay = self.changes.read().unwrap().overlay.clone();
+        Self {
+            base: self.base.clone(),
+            changes: Arc::new(RwLock::new(Changes {
+                overlay,
+                snapshots: Vec::new(),
+            })),
+        }
+    }
+
+    // swaps in the changes of `other` at once, readers never see a half executed overlay
+    pub fn replace_with(&self, other: &PendingState) {
+        let overlay = other.changes.read().unwrap().overlay.clone();
+        *self.changes.write().unwrap() = Changes {
+            overlay,
+            snapshots: Vec::new(),
+        };
+    }
+
+    // drops every change, e.g. once they made it into a block
+    pub fn clear(&self) {
+        *self.changes.write().unwrap() = Changes::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let changes = self.changes.read().unwrap();
+        changes.overlay.accounts.is_empty()
+            && changes.overlay.channels.is_empty()
+            && changes.overlay.total_supply.is_none()
+            && changes.overlay.seen_txs.is_empty()
+    }
+}
+
+impl State for PendingState {
+    fn get_account(&self, address: &Address) -> Option<Account> {
+        match self.changes.read().unwrap().overlay.accounts.get(address) {
+            Some(account) => account.clone(),
+            None => self.base.get_account(address),
+        }
+    }
+
+    fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError> {
+        let mut changes = self.changes.write().unwrap();
+        changes.overlay.accounts.insert(*address, Some(account));
+        Ok(())
+    }
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError> {
+        let mut changes = self.changes.write().unwrap();
+        changes.overlay.accounts.insert(*address, None);
+        Ok(())
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        match self.changes.read().unwrap().overlay.channels.get(id) {
+            Some(channel) => channel.clone(),
+            None => self.base.get_channel(id),
+        }
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        let mut changes = self.changes.write().unwrap();
+        changes.overlay.channels.insert(*id, channel);
+        Ok(())
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        let changes = self.changes.read().unwrap();
+        let accounts = &changes.overlay.accounts;
+        let mut merged: Vec<Account> = self
+            .base
+            .accounts()
+            .into_iter()
+            .filter(|account| !accounts.contains_key(&account.get_address()))
+            .collect();
+        merged.extend(accounts.values().flatten().cloned());
+        merged
+    }
+
+    fn channels(&self) -> Vec<(B256, Channel)> {
+        let changes = self.changes.read().unwrap();
+        let channels = &changes.overlay.channels;
+        let mut merged: Vec<(B256, Channel)> = self
+            .base
+            .channels()
+            .into_iter()
+            .filter(|(id, _)| !channels.contains_key(id))
+            .collect();
+        merged.extend(
+            channels
+                .iter()
+                .filter_map(|(id, channel)| Some((*id, channel.clone()?))),
+        );
+        merged
+    }
+
+    fn total_supply(&self) -> U256 {
+        match self.changes.read().unwrap().overlay.total_supply {
+            Some(supply) => supply,
+            None => self.base.total_supply(),
+        }
+    }
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError> {
+        self.changes.write().unwrap().overlay.total_supply = Some(supply);
+        Ok(())
+    }
+
+    fn seen_tx(&self, hash: &B256) -> Option<u64> {
+        match self.changes.read().unwrap().overlay.seen_txs.get(hash) {
+            Some(block) => *block,
+            None => self.base.seen_tx(hash),
+        }
+    }
+
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        let mut changes = self.changes.write().unwrap();
+        changes.overlay.seen_txs.insert(*hash, block);
+        Ok(())
+    }
+
+    // messages are only sent and delivered on the base
+    fn in_flight(&self) -> U256 {
+        self.base.in_flight()
+    }
+
+    fn seen_txs(&self) -> Vec<(B256, u64)> {
+        let changes = self.changes.read().unwrap();
+        let seen_txs = &changes.overlay.seen_txs;
+        let mut merged: Vec<(B256, u64)> = self
+            .base
+            .seen_txs()
+            .into_iter()
+            .filter(|(hash, _)| !seen_txs.contains_key(hash))
+            .collect();
+        merged.extend(
+            seen_txs
+                .iter()
+                .filter_map(|(hash, block)| Some((*hash, (*block)?))),
+        );
+        merged
+    }
+
+    fn snapshot(&mut self) -> SnapshotId {
+        let mut changes = self.changes.write().unwrap();
+        let overlay = changes.overlay.clone();
+        changes.snapshots.push(overlay);
+        SnapshotId(changes.snapshots.len() - 1)
+    }
+
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        let mut changes = self.changes.write().unwrap();
+        if id.0 >= changes.snapshots.len() {
+            return Err(StateError::UnknownSnapshot);
+        }
+        changes.overlay = changes.snapshots.swap_remove(id.0);
+        changes.snapshots.truncate(id.0);
+        Ok(())
+    }
+
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        let mut changes = self.changes.write().unwrap();
+        if id.0 >= changes.snapshots.len() {
+            return Err(StateError::UnknownSnapshot);
+        }
+        changes.snapshots.truncate(id.0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sharded::ShardedState;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_overlay() {
+        let committed = Arc::new(ShardedState::in_memory(2));
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        committed
+            .write_account(&alice, Account::new(alice, U256::from(100)))
+            .unwrap();
+        committed
+            .write_account(&bob, Account::new(bob, U256::from(5)))
+            .unwrap();
+        let root = committed.state_root();
+
+        let mut pending = PendingState::new(committed.clone());
+        let reader = pending.clone();
+        pending
+            .update_account(&alice, Account::new(alice, U256::from(60)))
+            .unwrap();
+        pending.remove_account(&bob).unwrap();
+
+        // The overlay is seen through every clone, the committed state is untouched
+        assert_eq!(
+            reader.get_account(&alice).unwrap().balance(),
+            U256::from(60)
+        );
+        assert!(reader.get_account(&bob).is_none());
+        assert_eq!(reader.accounts().len(), 1);
+        assert_eq!(
+            committed.read_account(&bob).unwrap().balance(),
+            U256::from(5)
+        );
+        assert_eq!(committed.state_root(), root);
+
+        // A fresh overlay starts over, and replaces the shared one at once
+        let mut next = pending.fresh();
+        assert_eq!(next.get_account(&alice).unwrap().balance(), U256::from(100));
+        next.update_account(&bob, Account::new(bob, U256::from(7)))
+            .unwrap();
+        pending.replace_with(&next);
+        assert_eq!(
+            reader.get_account(&alice).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_eq!(reader.get_account(&bob).unwrap().balance(), U256::from(7));
+
+        // A fork starts from the overlay and goes its own way
+        let mut fork = pending.fork();
+        assert_eq!(fork.get_account(&bob).unwrap().balance(), U256::from(7));
+        fork.update_account(&bob, Account::new(bob, U256::from(8)))
+            .unwrap();
+        assert_eq!(reader.get_account(&bob).unwrap().balance(), U256::from(7));
+
+        reader.clear();
+        assert!(pending.is_empty());
+        assert_eq!(fork.get_account(&bob).unwrap().balance(), U256::from(8));
+        assert_eq!(pending.state_root(), root);
+    }
+
+    #[test]
+    fn test_snapshot_and_revert() {
+        let committed = Arc::new(ShardedState::in_memory(2));
+        let alice = Address::repeat_byte(1);
+        let mut pending = PendingState::new(committed);
+        pending
+            .update_account(&alice, Account::new(alice, U256::from(1)))
+            .unwrap();
+
+        let snapshot = pending.snapshot();
+        pending
+            .update_account(&alice, Account::new(alice, U256::from(2)))
+            .unwrap();
+        pending.set_total_supply(U256::from(2)).unwrap();
+        assert!(!pending.is_empty());
+        pending.revert_to(snapshot).unwrap();
+        assert_eq!(pending.total_supply(), U256::ZERO);
+        assert_eq!(
+            pending.get_account(&alice).unwrap().balance(),
+            U256::from(1)
+        );
+        assert_eq!(pending.commit(snapshot), Err(StateError::UnknownSnapshot));
+    }
+}
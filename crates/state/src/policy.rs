@@ -0,0 +1,124 @@
+// spending limits an account puts on itself, e.g. a custodian capping what a single tx or a day's
+// worth of them can take out of a hot account. The owner sets them with a SetPolicy tx and the vm
+// enforces them on everything that leaves the account
+
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendingPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_tx: Option<U256>,
+    // what may leave the account in one day, see DailySpending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_limit: Option<U256>,
+    // the only recipients funds may go to, any when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowlist: Vec<Address>,
+}
+
+impl SpendingPolicy {
+    // a policy without limits is the same as none
+    pub fn is_unrestricted(&self) -> bool {
+        self.max_per_tx.is_none() && self.daily_limit.is_none() && self.allowlist.is_empty()
+    }
+
+    pub fn allows_recipient(&self, to: Address) -> bool {
+        self.allowlist.is_empty() || self.allowlist.contains(&to)
+    }
+
+    // why `amount` can't go to `to` when `spent_today` has already left, None when it can
+    pub fn check(&self, to: Address, amount: U256, spent_today: U256) -> Option<String> {
+        if let Some(max) = self.max_per_tx.filter(|max| amount > *max) {
+            return Some(format!(
+                "{} is over the limit of {} per transaction",
+                amount, max
+            ));
+        }
+        if let Some(limit) = self.daily_limit {
+            if spent_today.saturating_add(amount) > limit {
+                return Some(format!(
+                    "{} would take today's spending past the daily limit of {}, {} is spent",
+                    amount, limit, spent_today
+                ));
+            }
+        }
+        if !self.allows_recipient(to) {
+            return Some(format!("{} is not an allowed recipient", to));
+        }
+        None
+    }
+}
+
+// what an account with a policy has spent in the current day. Days are counted in blocks, so every
+// node starts a new one at the same block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailySpending {
+    pub day: u64,
+    pub spent: U256,
+}
+
+impl DailySpending {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn spent_on(&self, day: u64) -> U256 {
+        if self.day == day {
+            self.spent
+        } else {
+            U256::ZERO
+        }
+    }
+
+    pub fn add(self, day: u64, amount: U256) -> Self {
+        Self {
+            day,
+            spent: self.spent_on(day).saturating_add(amount),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let allowed = Address::repeat_byte(1);
+        let policy = SpendingPolicy {
+            max_per_tx: Some(U256::from(50)),
+            daily_limit: Some(U256::from(80)),
+            allowlist: vec![allowed],
+        };
+        assert_eq!(policy.check(allowed, U256::from(50), U256::from(30)), None);
+        assert!(policy
+            .check(allowed, U256::from(51), U256::ZERO)
+            .unwrap()
+            .contains("per transaction"));
+        assert!(policy
+            .check(allowed, U256::from(50), U256::from(31))
+            .unwrap()
+            .contains("daily limit"));
+        assert!(policy
+            .check(Address::repeat_byte(2), U256::from(1), U256::ZERO)
+            .is_some());
+
+        assert!(SpendingPolicy::default().is_unrestricted());
+        assert_eq!(
+            SpendingPolicy::default().check(allowed, U256::MAX, U256::MAX),
+            None
+        );
+    }
+
+    #[test]
+    fn test_daily_spending() {
+        let spending = DailySpending::default().add(3, U256::from(10));
+        let spending = spending.add(3, U256::from(5));
+        assert_eq!(spending.spent_on(3), U256::from(15));
+        // A new day starts from nothing
+        assert_eq!(spending.spent_on(4), U256::ZERO);
+        assert_eq!(spending.add(4, U256::from(1)).spent, U256::from(1));
+    }
+}
@@ -0,0 +1,472 @@
+// the state root commits to every funded account and open channel, sorted so that two nodes
+// holding the same state agree on it whatever order they got there in. Accounts are the leaves of
+// a merkle tree so a light client or a bridge can check a balance against a block's state root,
+// see AccountProof, of its native balance. Channels, multisigs, module accounts, spending policies,
+// scheduled payments, escrows, htlcs and the balances of other assets are hashed next to it in one
+// piece. Labels and creation blocks are bookkeeping and not
+// committed to
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use tx::merkle::{merkle_root, InclusionProof};
+
+use crate::account::Account;
+use crate::channel::Channel;
+
+const STATE_ROOT_DOMAIN: &[u8] = b"fastpay-state-root";
+const MODULES_TAG: &[u8] = b"modules";
+const POLICIES_TAG: &[u8] = b"policies";
+const SCHEDULES_TAG: &[u8] = b"schedules";
+const ESCROWS_TAG: &[u8] = b"escrows";
+const HTLCS_TAG: &[u8] = b"htlcs";
+const ASSETS_TAG: &[u8] = b"assets";
+
+pub fn state_root(accounts: &[Account], channels: &[(B256, Channel)]) -> B256 {
+    let accounts = sorted_accounts(accounts);
+    let leaves: Vec<B256> = accounts
+        .iter()
+        .map(|account| account_leaf(account))
+        .collect();
+    combine(merkle_root(&leaves), channels_hash(&accounts, channels))
+}
+
+// an account drained to zero is the same as one that never existed, unless it has sent transfer
+// orders and so has a sequence number to remember, or is a multisig or module account
+fn sorted_accounts(accounts: &[Account]) -> Vec<&Account> {
+    let mut accounts: Vec<_> = accounts.iter().filter(|a| !a.is_empty()).collect();
+    accounts.sort_by_key(|account| account.get_address());
+    accounts
+}
+
+fn account_leaf(account: &Account) -> B256 {
+    leaf(account.get_address(), account.balance(), account.sequence())
+}
+
+fn leaf(address: Address, balance: U256, sequence: u64) -> B256 {
+    let mut encoded = address.to_vec();
+    encoded.extend_from_slice(&balance.to_be_bytes::<32>());
+    encoded.extend_from_slice(&sequence.to_be_bytes());
+    keccak256(encoded)
+}
+
+fn combine(accounts_root: B256, channels_hash: B256) -> B256 {
+    let mut encoded = STATE_ROOT_DOMAIN.to_vec();
+    encoded.extend_from_slice(accounts_root.as_slice());
+    encoded.extend_from_slice(channels_hash.as_slice());
+    keccak256(encoded)
+}
+
+// the channels, then the multisigs, the module accounts, the policies, the schedules, the escrows,
+// the htlcs and the asset balances in sections of their own so states without any keep the root they had before them
+fn channels_hash(accounts: &[&Account], channels: &[(B256, Channel)]) -> B256 {
+    let mut channels: Vec<_> = channels.iter().collect();
+    channels.sort_by_key(|(id, _)| *id);
+    let multisigs: Vec<_> = accounts
+        .iter()
+        .filter_map(|account| Some((account.get_address(), account.multisig()?)))
+        .collect();
+    let modules: Vec<_> = accounts
+        .iter()
+        .filter_map(|account| Some((account.get_address(), account.module()?)))
+        .collect();
+    let policies: Vec<_> = accounts
+        .iter()
+        .filter_map(|account| Some((account, account.policy()?)))
+        .collect();
+    let schedules: Vec<_> = accounts
+        .iter()
+        .filter(|account| !account.schedules().is_empty())
+        .collect();
+    let escrows: Vec<_> = accounts
+        .iter()
+        .filter(|account| !account.escrows().is_empty())
+        .collect();
+    let htlcs: Vec<_> = accounts
+        .iter()
+        .filter(|account| !account.htlcs().is_empty())
+        .collect();
+    let holders: Vec<_> = accounts
+        .iter()
+        .filter(|account| !account.assets().is_empty())
+        .collect();
+
+    let mut encoded = (channels.len() as u64).to_be_bytes().to_vec();
+    for (id, channel) in channels {
+        encoded.extend_from_slice(id.as_slice());
+        encoded.extend_from_slice(channel.payer().as_slice());
+        encoded.extend_from_slice(channel.payee().as_slice());
+        encoded.extend_from_slice(&channel.deposit().to_be_bytes::<32>());
+        encoded.extend_from_slice(&channel.challenge_period().to_be_bytes());
+        match channel.expires_at() {
+            Some(block) => {
+                encoded.push(1);
+                encoded.extend_from_slice(&block.to_be_bytes());
+            }
+            None => encoded.push(0),
+        }
+    }
+    if !multisigs.is_empty() {
+        encoded.extend_from_slice(&(multisigs.len() as u64).to_be_bytes());
+        for (address, multisig) in multisigs {
+            encoded.extend_from_slice(address.as_slice());
+            encoded.extend_from_slice(&multisig.threshold().to_be_bytes());
+            encoded.extend_from_slice(&(multisig.owners().len() as u64).to_be_bytes());
+            for owner in multisig.owners() {
+                encoded.extend_from_slice(owner.as_slice());
+            }
+        }
+    }
+    if !modules.is_empty() {
+        // tagged, a state with modules but no multisigs can't hash like one with multisigs
+        encoded.extend_from_slice(MODULES_TAG);
+        encoded.extend_from_slice(&(modules.len() as u64).to_be_bytes());
+        for (address, module) in modules {
+            encoded.extend_from_slice(address.as_slice());
+            encoded.extend_from_slice(&(module.len() as u64).to_be_bytes());
+            encoded.extend_from_slice(module.as_bytes());
+        }
+    }
+    if !policies.is_empty() {
+        encoded.extend_from_slice(POLICIES_TAG);
+        encoded.extend_from_slice(&(policies.len() as u64).to_be_bytes());
+        for (account, policy) in policies {
+            encoded.extend_from_slice(account.get_address().as_slice());
+            for limit in [policy.max_per_tx, policy.daily_limit] {
+                match limit {
+                    Some(limit) => {
+                        encoded.push(1);
+                        encoded.extend_from_slice(&limit.to_be_bytes::<32>());
+                    }
+                    None => encoded.push(0),
+                }
+            }
+            encoded.extend_from_slice(&(policy.allowlist.len() as u64).to_be_bytes());
+            for recipient in &policy.allowlist {
+                encoded.extend_from_slice(recipient.as_slice());
+            }
+            let spending = account.spending();
+            encoded.extend_from_slice(&spending.day.to_be_bytes());
+            encoded.extend_from_slice(&spending.spent.to_be_bytes::<32>());
+        }
+    }
+    if !schedules.is_empty() {
+        encoded.extend_from_slice(SCHEDULES_TAG);
+        encoded.extend_from_slice(&(schedules.len() as u64).to_be_bytes());
+        for account in schedules {
+            encoded.extend_from_slice(account.get_address().as_slice());
+            encoded.extend_from_slice(&(account.schedules().len() as u64).to_be_bytes());
+            for schedule in account.schedules() {
+                encoded.extend_from_slice(schedule.id.as_slice());
+                encoded.extend_from_slice(schedule.to.as_slice());
+                encoded.extend_from_slice(&schedule.amount.to_be_bytes::<32>());
+                encoded.extend_from_slice(&schedule.interval.to_be_bytes());
+                encoded.extend_from_slice(&schedule.next_due.to_be_bytes());
+                match schedule.end {
+                    Some(end) => {
+                        encoded.push(1);
+                        encoded.extend_from_slice(&end.to_be_bytes());
+                    }
+                    None => encoded.push(0),
+                }
+            }
+        }
+    }
+    if !escrows.is_empty() {
+        encoded.extend_from_slice(ESCROWS_TAG);
+        encoded.extend_from_slice(&(escrows.len() as u64).to_be_bytes());
+        for account in escrows {
+            encoded.extend_from_slice(account.get_address().as_slice());
+            encoded.extend_from_slice(&(account.escrows().len() as u64).to_be_bytes());
+            for escrow in account.escrows() {
+                encoded.extend_from_slice(escrow.id.as_slice());
+                encoded.extend_from_slice(escrow.to.as_slice());
+                encoded.extend_from_slice(escrow.arbiter.as_slice());
+                encoded.extend_from_slice(&escrow.amount.to_be_bytes::<32>());
+                encoded.extend_from_slice(&escrow.expires_at.to_be_bytes());
+            }
+        }
+    }
+    if !htlcs.is_empty() {
+        encoded.extend_from_slice(HTLCS_TAG);
+        encoded.extend_from_slice(&(htlcs.len() as u64).to_be_bytes());
+        for account in htlcs {
+            encoded.extend_from_slice(account.get_address().as_slice());
+            encoded.extend_from_slice(&(account.htlcs().len() as u64).to_be_bytes());
+            for htlc in account.htlcs() {
+                encoded.extend_from_slice(htlc.recipient.as_slice());
+                encoded.extend_from_slice(htlc.hashlock.as_slice());
+                encoded.extend_from_slice(&htlc.amount.to_be_bytes::<32>());
+                encoded.extend_from_slice(&htlc.timelock.to_be_bytes());
+            }
+        }
+    }
+    if !holders.is_empty() {
+        encoded.extend_from_slice(ASSETS_TAG);
+        encoded.extend_from_slice(&(holders.len() as u64).to_be_bytes());
+        for account in holders {
+            encoded.extend_from_slice(account.get_address().as_slice());
+            encoded.extend_from_slice(&(account.assets().len() as u64).to_be_bytes());
+            // sorted by asset, it is a BTreeMap
+            for (asset, balance) in account.assets() {
+                encoded.extend_from_slice(asset.as_slice());
+                encoded.extend_from_slice(&balance.to_be_bytes::<32>());
+            }
+        }
+    }
+    keccak256(encoded)
+}
+
+// that an account held `balance` with `sequence` in the state a root was taken of. Only accounts
+// that exist can be proven, a missing one has no proof rather than a proof of absence
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: Address,
+    pub balance: U256,
+    pub sequence: u64,
+    // the account's place among the accounts sorted by address
+    pub proof: InclusionProof,
+    // the rest of the state, the channels, multisigs and module accounts
+    pub channels_hash: B256,
+}
+
+// None when the account doesn't exist, or is empty
+pub fn account_proof(
+    accounts: &[Account],
+    channels: &[(B256, Channel)],
+    address: &Address,
+) -> Option<AccountProof> {
+    let accounts = sorted_accounts(accounts);
+    let index = accounts
+        .binary_search_by_key(address, |account| account.get_address())
+        .ok()?;
+    let leaves: Vec<B256> = accounts
+        .iter()
+        .map(|account| account_leaf(account))
+        .collect();
+
+    Some(AccountProof {
+        address: *address,
+        balance: accounts[index].balance(),
+        sequence: accounts[index].sequence(),
+        proof: InclusionProof::new(&leaves, index)?,
+        channels_hash: channels_hash(&accounts, channels),
+    })
+}
+
+// whether the proof holds against `state_root`, needs nothing but the proof
+pub fn verify_account_proof(state_root: B256, proof: &AccountProof) -> bool {
+    if leaf(proof.address, proof.balance, proof.sequence) != proof.proof.leaf {
+        return false;
+    }
+    proof
+        .proof
+        .root()
+        .is_some_and(|accounts_root| combine(accounts_root, proof.channels_hash) == state_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escrow::Escrow;
+    use crate::htlc::Htlc;
+    use crate::policy::{DailySpending, SpendingPolicy};
+    use crate::schedule::Schedule;
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn test_state_root() {
+        let a = Account::new(Address::repeat_byte(1), U256::from(10));
+        let b = Account::new(Address::repeat_byte(2), U256::from(20));
+        let empty = Account::new(Address::repeat_byte(3), U256::ZERO);
+        let channel = (
+            B256::repeat_byte(9),
+            Channel::new(
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                U256::from(5),
+                10,
+            ),
+        );
+
+        let root = state_root(&[a.clone(), b.clone()], std::slice::from_ref(&channel));
+        // Order and drained accounts don't matter
+        assert_eq!(
+            state_root(
+                &[b.clone(), empty, a.clone()],
+                std::slice::from_ref(&channel)
+            ),
+            root
+        );
+
+        assert_ne!(state_root(&[a.clone(), b.clone()], &[]), root);
+        let mut expiring = channel.clone();
+        expiring.1.start_timeout(3);
+        assert_ne!(state_root(&[a.clone(), b.clone()], &[expiring]), root);
+
+        let mut sequenced = a.clone();
+        sequenced.set_sequence(1);
+        assert_ne!(
+            state_root(&[sequenced, b.clone()], std::slice::from_ref(&channel)),
+            root
+        );
+        // A drained account that has sent orders still counts
+        let mut drained = Account::new(Address::repeat_byte(3), U256::ZERO);
+        drained.set_sequence(1);
+        assert_ne!(
+            state_root(
+                &[a.clone(), b.clone(), drained],
+                std::slice::from_ref(&channel)
+            ),
+            root
+        );
+
+        // So does an empty multisig, and its owners are part of the root
+        let multisig = |threshold| {
+            let owners = vec![Address::repeat_byte(1), Address::repeat_byte(2)];
+            Account::new(Address::repeat_byte(4), U256::ZERO)
+                .with_multisig(crate::account::Multisig::new(owners, threshold).unwrap())
+        };
+        let with_multisig = state_root(
+            &[a.clone(), b.clone(), multisig(1)],
+            std::slice::from_ref(&channel),
+        );
+        assert_ne!(with_multisig, root);
+        assert_ne!(
+            state_root(
+                &[a.clone(), b.clone(), multisig(2)],
+                std::slice::from_ref(&channel)
+            ),
+            with_multisig
+        );
+
+        // Module accounts are part of it as well, labels and creation blocks aren't
+        let module = |name: &str| {
+            Account::new(Address::repeat_byte(5), U256::ZERO)
+                .with_kind(crate::account::AccountKind::Module(name.to_string()))
+        };
+        let with_module = state_root(
+            &[a.clone(), b.clone(), module("bridge")],
+            std::slice::from_ref(&channel),
+        );
+        assert_ne!(with_module, root);
+        assert_ne!(
+            state_root(
+                &[a.clone(), b.clone(), module("escrow")],
+                std::slice::from_ref(&channel)
+            ),
+            with_module
+        );
+        let labeled = a.clone().with_label("alice").with_created_at(3);
+        assert_eq!(
+            state_root(&[labeled, b.clone()], std::slice::from_ref(&channel)),
+            root
+        );
+
+        // So are policies and what was spent under them
+        let limited = a.clone().with_policy(SpendingPolicy {
+            daily_limit: Some(U256::from(10)),
+            ..SpendingPolicy::default()
+        });
+        let with_policy = state_root(
+            &[limited.clone(), b.clone()],
+            std::slice::from_ref(&channel),
+        );
+        assert_ne!(with_policy, root);
+        let mut spent = limited;
+        spent.set_spending(DailySpending::default().add(1, U256::from(4)));
+        assert_ne!(
+            state_root(&[spent, b.clone()], std::slice::from_ref(&channel)),
+            with_policy
+        );
+
+        // And scheduled payments, down to when the next one is due
+        let mut schedule = Schedule {
+            id: B256::repeat_byte(7),
+            to: Address::repeat_byte(2),
+            amount: U256::from(5),
+            interval: 10,
+            next_due: 10,
+            end: None,
+        };
+        let mut scheduled = a.clone();
+        scheduled.set_schedules(vec![schedule.clone()]);
+        let with_schedule = state_root(
+            &[scheduled.clone(), b.clone()],
+            std::slice::from_ref(&channel),
+        );
+        assert_ne!(with_schedule, root);
+        schedule.advance();
+        scheduled.set_schedules(vec![schedule]);
+        assert_ne!(
+            state_root(&[scheduled, b.clone()], std::slice::from_ref(&channel)),
+            with_schedule
+        );
+
+        // And escrows
+        let mut escrowing = a.clone();
+        escrowing.set_escrows(vec![Escrow {
+            id: B256::repeat_byte(8),
+            to: Address::repeat_byte(2),
+            arbiter: Address::repeat_byte(3),
+            amount: U256::from(5),
+            expires_at: 10,
+        }]);
+        assert_ne!(
+            state_root(&[escrowing, b.clone()], std::slice::from_ref(&channel)),
+            root
+        );
+
+        // And htlcs
+        let mut locking = a.clone();
+        locking.set_htlcs(vec![Htlc {
+            recipient: Address::repeat_byte(2),
+            hashlock: B256::repeat_byte(9),
+            amount: U256::from(5),
+            timelock: 10,
+        }]);
+        assert_ne!(
+            state_root(&[locking, b.clone()], std::slice::from_ref(&channel)),
+            root
+        );
+
+        // And balances of other assets
+        let mut holder = a.clone();
+        holder.set_asset_balance(B256::repeat_byte(1), U256::from(5));
+        assert_ne!(state_root(&[holder, b], &[channel]), root);
+    }
+
+    #[test]
+    fn test_account_proof() {
+        let accounts: Vec<Account> = (1..=5)
+            .map(|i| Account::new(Address::repeat_byte(i), U256::from(i)))
+            .collect();
+        let channels = vec![(
+            B256::repeat_byte(9),
+            Channel::new(
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                U256::from(5),
+                10,
+            ),
+        )];
+        let root = state_root(&accounts, &channels);
+
+        for account in &accounts {
+            let proof = account_proof(&accounts, &channels, &account.get_address()).unwrap();
+            assert_eq!(proof.balance, account.balance());
+            assert!(verify_account_proof(root, &proof));
+        }
+
+        // A balance the account doesn't have doesn't check out
+        let mut inflated = account_proof(&accounts, &channels, &Address::repeat_byte(3)).unwrap();
+        inflated.balance = U256::from(1000);
+        assert!(!verify_account_proof(root, &inflated));
+
+        // Nor does a proof against another state
+        let proof = account_proof(&accounts, &channels, &Address::repeat_byte(3)).unwrap();
+        assert!(!verify_account_proof(state_root(&accounts, &[]), &proof));
+
+        assert!(account_proof(&accounts, &channels, &Address::repeat_byte(7)).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+// recurring payments an account has set up, e.g. a salary or a subscription. The payer registers
+// one with a Schedule tx and the vm pays it every `interval` blocks until its end or until the
+// payer cancels it
+
+use alloy::primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    // the hash of the Schedule tx that registered it
+    pub id: B256,
+    pub to: Address,
+    pub amount: U256,
+    // in blocks
+    pub interval: u64,
+    // the block the next payment is made in
+    pub next_due: u64,
+    // the last block a payment can be made in, None runs until cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<u64>,
+}
+
+impl Schedule {
+    pub fn is_due(&self, block: u64) -> bool {
+        self.next_due <= block
+    }
+
+    // moves on to the next payment, false once there is none left before the end
+    pub fn advance(&mut self) -> bool {
+        self.next_due = self.next_due.saturating_add(self.interval);
+        self.end.is_none_or(|end| self.next_due <= end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let mut schedule = Schedule {
+            id: B256::ZERO,
+            to: Address::repeat_byte(1),
+            amount: U256::from(10),
+            interval: 5,
+            next_due: 5,
+            end: Some(12),
+        };
+        assert!(!schedule.is_due(4));
+        assert!(schedule.is_due(5));
+
+        assert!(schedule.advance());
+        assert_eq!(schedule.next_due, 10);
+        // 15 is past the end
+        assert!(!schedule.advance());
+
+        schedule.end = None;
+        assert!(schedule.advance());
+    }
+}
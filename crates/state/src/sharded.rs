@@ -0,0 +1,853 @@
+// sharded state: accounts are split across N shards by address prefix, each behind its own lock,
+// so transfers touching different shards don't wait on each other
+
+use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+
+use alloy::primitives::{Address, B256, U256};
+
+use crate::account::Account;
+use crate::channel::Channel;
+use crate::cross_shard::{CrossShardMessage, Inbox, Outbox};
+use crate::memory::MemoryState;
+use crate::state::{SnapshotId, State, StateError};
+
+pub struct ShardedState<S> {
+    shards: Vec<RwLock<S>>,
+    // for each open snapshot, the snapshot every shard took for it
+    snapshots: Mutex<Vec<Vec<SnapshotId>>>,
+    // cross-shard credits waiting for each shard, see cross_shard.rs. They are not part of
+    // snapshots, a message is only sent once its debit is final
+    inboxes: Vec<Mutex<Inbox>>,
+    // the messages each shard's accounts sent that are still in flight, what a resend is taken
+    // from
+    outboxes: Vec<Mutex<Outbox>>,
+}
+
+impl ShardedState<MemoryState> {
+    pub fn in_memory(num_shards: usize) -> Self {
+        Self::new(num_shards, MemoryState::new)
+    }
+}
+
+impl<S: State> ShardedState<S> {
+    pub fn new(num_shards: usize, make_shard: impl Fn() -> S) -> Self {
+        assert!(num_shards > 0, "sharded state needs at least one shard");
+
+        Self {
+            shards: (0..num_shards).map(|_| RwLock::new(make_shard())).collect(),
+            snapshots: Mutex::new(Vec::new()),
+            inboxes: (0..num_shards)
+                .map(|_| Mutex::new(Inbox::default()))
+                .collect(),
+            outboxes: (0..num_shards)
+                .map(|_| Mutex::new(Outbox::default()))
+                .collect(),
+        }
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    // the first two bytes of the address decide the shard
+    pub fn shard_for(&self, address: &Address) -> usize {
+        let prefix = u16::from_be_bytes([address[0], address[1]]) as usize;
+        prefix % self.shards.len()
+    }
+
+    // channels are spread the same way, by the first two bytes of their id
+    pub fn shard_for_channel(&self, id: &B256) -> usize {
+        let prefix = u16::from_be_bytes([id[0], id[1]]) as usize;
+        prefix % self.shards.len()
+    }
+
+    pub fn read_account(&self, address: &Address) -> Option<Account> {
+        let shard = self.shards[self.shard_for(address)].read().unwrap();
+        shard.get_account(address)
+    }
+
+    pub fn write_account(&self, address: &Address, account: Account) -> Result<(), StateError> {
+        let mut shard = self.shards[self.shard_for(address)].write().unwrap();
+        shard.update_account(address, account)
+    }
+
+    pub fn delete_account(&self, address: &Address) -> Result<(), StateError> {
+        let mut shard = self.shards[self.shard_for(address)].write().unwrap();
+        shard.remove_account(address)
+    }
+
+    pub fn read_channel(&self, id: &B256) -> Option<Channel> {
+        let shard = self.shards[self.shard_for_channel(id)].read().unwrap();
+        shard.get_channel(id)
+    }
+
+    pub fn write_channel(&self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        let mut shard = self.shards[self.shard_for_channel(id)].write().unwrap();
+        shard.update_channel(id, channel)
+    }
+
+    pub fn all_accounts(&self) -> Vec<Account> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.read().unwrap().accounts())
+            .collect()
+    }
+
+    pub fn all_channels(&self) -> Vec<(B256, Channel)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.read().unwrap().channels())
+            .collect()
+    }
+
+    // the supply lives in the first shard, it isn't tied to any address
+    pub fn read_total_supply(&self) -> U256 {
+        self.shards[0].read().unwrap().total_supply()
+    }
+
+    pub fn write_total_supply(&self, supply: U256) -> Result<(), StateError> {
+        self.shards[0].write().unwrap().set_total_supply(supply)
+    }
+
+    // seen txs are spread like channels, by their hash
+    pub fn read_seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.shards[self.shard_for_channel(hash)]
+            .read()
+            .unwrap()
+            .seen_tx(hash)
+    }
+
+    pub fn write_seen_tx(&self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        let mut shard = self.shards[self.shard_for_channel(hash)].write().unwrap();
+        shard.update_seen_tx(hash, block)
+    }
+
+    pub fn all_seen_txs(&self) -> Vec<(B256, u64)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.read().unwrap().seen_txs())
+            .collect()
+    }
+
+    // every shard is locked while the snapshot is taken, in ascending order like apply_transfer,
+    // so no transfer is half in it
+    pub fn take_snapshot(&self) -> SnapshotId {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let mut guards: Vec<_> = self.shards.iter().map(|s| s.write().unwrap()).collect();
+        snapshots.push(guards.iter_mut().map(|shard| shard.snapshot()).collect());
+        SnapshotId(snapshots.len() - 1)
+    }
+
+    pub fn revert_snapshot(&self, id: SnapshotId) -> Result<(), StateError> {
+        self.close_snapshot(id, |shard, id| shard.revert_to(id))
+    }
+
+    pub fn commit_snapshot(&self, id: SnapshotId) -> Result<(), StateError> {
+        self.close_snapshot(id, |shard, id| shard.commit(id))
+    }
+
+    fn close_snapshot(
+        &self,
+        id: SnapshotId,
+        close: impl Fn(&mut S, SnapshotId) -> Result<(), StateError>,
+    ) -> Result<(), StateError> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let shard_ids = snapshots
+            .get(id.0)
+            .cloned()
+            .ok_or(StateError::UnknownSnapshot)?;
+        let mut guards: Vec<_> = self.shards.iter().map(|s| s.write().unwrap()).collect();
+        for (shard, shard_id) in guards.iter_mut().zip(shard_ids) {
+            close(shard, shard_id)?;
+        }
+        snapshots.truncate(id.0);
+        Ok(())
+    }
+
+    // moves `amount` between two accounts holding only the locks of the shards involved,
+    // signature checks are expected to have happened before
+    pub fn apply_transfer(
+        &self,
+        from: &Address,
+        to: &Address,
+        amount: U256,
+    ) -> Result<(), StateError> {
+        let from_shard = self.shard_for(from);
+        let to_shard = self.shard_for(to);
+
+        // shards are always locked in ascending order so two transfers can't deadlock
+        let (first, second) = if from_shard <= to_shard {
+            (from_shard, to_shard)
+        } else {
+            (to_shard, from_shard)
+        };
+        let mut first_guard = self.shards[first].write().unwrap();
+        let mut second_guard: Option<RwLockWriteGuard<S>> = if first != second {
+            Some(self.shards[second].write().unwrap())
+        } else {
+            None
+        };
+
+        let from_account = {
+            let shard: &S = if from_shard == first {
+                &first_guard
+            } else {
+                second_guard.as_deref().unwrap()
+            };
+            shard.get_account(from).ok_or(StateError::AccountNotFound)?
+        };
+
+        if from_account.balance() < amount {
+            return Err(StateError::AccountBalanceTooLow);
+        }
+        if from == to {
+            return Ok(());
+        }
+
+        // credited before anything is written, so a recipient that can't hold the amount leaves
+        // both accounts as they were
+        let mut to_account = {
+            let shard: &S = if to_shard == first {
+                &first_guard
+            } else {
+                second_guard.as_deref().unwrap()
+            };
+            shard
+                .get_account(to)
+                .unwrap_or_else(|| Account::new(*to, U256::ZERO))
+        };
+        let credited = to_account
+            .balance()
+            .checked_add(amount)
+            .ok_or(StateError::BalanceOverflow)?;
+        to_account.set_balance(credited);
+
+        {
+            let shard: &mut S = if from_shard == first {
+                &mut first_guard
+            } else {
+                second_guard.as_deref_mut().unwrap()
+            };
+            let mut updated_from = from_account.clone();
+            updated_from.set_balance(from_account.balance() - amount);
+            // an account left with nothing is dropped, like the vm does
+            if updated_from.is_empty() {
+                shard.remove_account(from)?;
+            } else {
+                shard.update_account(from, updated_from)?;
+            }
+        }
+
+        let shard: &mut S = if to_shard == first {
+            &mut first_guard
+        } else {
+            second_guard.as_deref_mut().unwrap()
+        };
+        shard.update_account(to, to_account)
+    }
+
+    // first phase of a cross-shard transfer: debits `from` holding only its shard's lock and
+    // queues the credit for the recipient's shard. `id` has to be unique per transfer, the tx
+    // hash, one already queued or credited is refused before anything is debited
+    pub fn send_cross_shard(
+        &self,
+        id: B256,
+        from: &Address,
+        to: &Address,
+        amount: U256,
+    ) -> Result<CrossShardMessage, StateError> {
+        let from_shard = self.shard_for(from);
+        let to_shard = self.shard_for(to);
+        let message = CrossShardMessage {
+            id,
+            from: *from,
+            to: *to,
+            amount,
+            to_shard,
+        };
+        // looked up before taking the sender's shard, only one shard is ever held. One credited
+        // in between is refunded when it is delivered again, rather than credited twice
+        if self.read_seen_tx(&message.delivery_key()).is_some() {
+            return Err(StateError::DuplicateMessage);
+        }
+
+        // the shard, then the inbox, then the outbox, the order deliver_cross_shard takes them in
+        let mut shard = self.shards[from_shard].write().unwrap();
+        let mut inbox = self.inboxes[to_shard].lock().unwrap();
+        let mut outbox = self.outboxes[from_shard].lock().unwrap();
+        if inbox.contains(&id) || outbox.contains(&id) {
+            return Err(StateError::DuplicateMessage);
+        }
+        let mut from_account = shard.get_account(from).ok_or(StateError::AccountNotFound)?;
+        if from_account.balance() < amount {
+            return Err(StateError::AccountBalanceTooLow);
+        }
+        from_account.set_balance(from_account.balance() - amount);
+        if from_account.is_empty() {
+            shard.remove_account(from)?;
+        } else {
+            shard.update_account(from, from_account)?;
+        }
+
+        // queued while the debit is still locked, so no one sees the funds in neither place
+        inbox.push(message.clone());
+        outbox.record(message.clone());
+        Ok(message)
+    }
+
+    // hands the message `from` sent as `id` to its shard again, for a sender that crashed before
+    // knowing it was queued. It is taken from the sender's outbox, nothing that wasn't debited can
+    // be sent. Returns false when it was never sent, or is already queued or settled
+    pub fn resend_cross_shard(&self, from: &Address, id: &B256) -> bool {
+        let outbox = self.outboxes[self.shard_for(from)].lock().unwrap();
+        let Some(message) = outbox.get(id).cloned() else {
+            return false;
+        };
+        // let go of before the recipient's shard is taken. A message settled in between is left
+        // out by its delivery record
+        drop(outbox);
+        // the recipient's shard is held so the message can't be credited in between
+        let shard = self.shards[message.to_shard].read().unwrap();
+        if shard.seen_tx(&message.delivery_key()).is_some() {
+            return false;
+        }
+        self.inboxes[message.to_shard].lock().unwrap().push(message)
+    }
+
+    // second phase: credits every message queued for `shard` and records their delivery as seen
+    // in `block`, returns how many were credited. A message that was already credited, or whose
+    // recipient can't hold the amount, is dead-lettered and refunded to its sender instead
+    pub fn deliver_cross_shard(&self, shard: usize, block: u64) -> Result<usize, StateError> {
+        let mut delivered = 0;
+        {
+            // the shard before its inbox, the order send_cross_shard takes them in
+            let mut state = self.shards[shard].write().unwrap();
+            let mut inbox = self.inboxes[shard].lock().unwrap();
+
+            while let Some(message) = inbox.front().cloned() {
+                let key = message.delivery_key();
+                // no longer in flight for a resend, credited or not
+                self.outboxes[self.shard_for(&message.from)]
+                    .lock()
+                    .unwrap()
+                    .settle(&message.id);
+                if state.seen_tx(&key).is_some() {
+                    inbox.pop();
+                    inbox.dead_letter(message);
+                    continue;
+                }
+                let mut to_account = state
+                    .get_account(&message.to)
+                    .unwrap_or_else(|| Account::new(message.to, U256::ZERO));
+                match to_account.balance().checked_add(message.amount) {
+                    Some(credited) => {
+                        to_account.set_balance(credited);
+                        state.update_account(&message.to, to_account)?;
+                        delivered += 1;
+                    }
+                    None => inbox.dead_letter(message.clone()),
+                }
+                // settled either way, a resend is refused from now on
+                state.update_seen_tx(&key, Some(block))?;
+                inbox.pop();
+            }
+        }
+        self.refund_dead_letters(shard)?;
+        Ok(delivered)
+    }
+
+    // pays the messages dead-lettered in `shard`'s inbox back to their senders, one sender's shard
+    // at a time. One whose sender can't hold it stays dead-lettered, still in flight
+    pub fn refund_dead_letters(&self, shard: usize) -> Result<(), StateError> {
+        let mut stuck = Vec::new();
+        let result = loop {
+            let Some(message) = self.inboxes[shard].lock().unwrap().take_dead_letter() else {
+                break Ok(());
+            };
+            let mut state = self.shards[self.shard_for(&message.from)].write().unwrap();
+            let mut from_account = state
+                .get_account(&message.from)
+                .unwrap_or_else(|| Account::new(message.from, U256::ZERO));
+            let Some(refunded) = from_account.balance().checked_add(message.amount) else {
+                stuck.push(message);
+                continue;
+            };
+            from_account.set_balance(refunded);
+            if let Err(e) = state.update_account(&message.from, from_account) {
+                stuck.push(message);
+                break Err(e);
+            }
+        };
+
+        let mut inbox = self.inboxes[shard].lock().unwrap();
+        for message in stuck {
+            inbox.dead_letter(message);
+        }
+        result
+    }
+
+    // debited but not credited or refunded yet, what a supply audit finds in neither accounts
+    // nor channels
+    pub fn cross_shard_in_flight(&self) -> U256 {
+        self.inboxes.iter().fold(U256::ZERO, |total, inbox| {
+            let inbox = inbox.lock().unwrap();
+            inbox
+                .pending()
+                .chain(inbox.dead_letters())
+                .fold(total, |total, message| total.saturating_add(message.amount))
+        })
+    }
+}
+
+impl<S: State> State for ShardedState<S> {
+    fn get_account(&self, address: &Address) -> Option<Account> {
+        self.read_account(address)
+    }
+
+    fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError> {
+        let shard = self.shard_for(address);
+        self.shards[shard]
+            .get_mut()
+            .unwrap()
+            .update_account(address, account)
+    }
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError> {
+        let shard = self.shard_for(address);
+        self.shards[shard]
+            .get_mut()
+            .unwrap()
+            .remove_account(address)
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        self.read_channel(id)
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        let shard = self.shard_for_channel(id);
+        self.shards[shard]
+            .get_mut()
+            .unwrap()
+            .update_channel(id, channel)
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        self.all_accounts()
+    }
+
+    fn channels(&self) -> Vec<(B256, Channel)> {
+        self.all_channels()
+    }
+
+    fn total_supply(&self) -> U256 {
+        self.read_total_supply()
+    }
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError> {
+        self.shards[0].get_mut().unwrap().set_total_supply(supply)
+    }
+
+    fn seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.read_seen_tx(hash)
+    }
+
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        let shard = self.shard_for_channel(hash);
+        self.shards[shard]
+            .get_mut()
+            .unwrap()
+            .update_seen_tx(hash, block)
+    }
+
+    fn seen_txs(&self) -> Vec<(B256, u64)> {
+        self.all_seen_txs()
+    }
+
+    fn in_flight(&self) -> U256 {
+        self.cross_shard_in_flight()
+    }
+
+    fn snapshot(&mut self) -> SnapshotId {
+        self.take_snapshot()
+    }
+
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.revert_snapshot(id)
+    }
+
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.commit_snapshot(id)
+    }
+}
+
+// lets a node execute against the state while other components read it concurrently
+impl<S: State> State for Arc<ShardedState<S>> {
+    fn get_account(&self, address: &Address) -> Option<Account> {
+        self.read_account(address)
+    }
+
+    fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError> {
+        self.write_account(address, account)
+    }
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError> {
+        self.delete_account(address)
+    }
+
+    fn get_channel(&self, id: &B256) -> Option<Channel> {
+        self.read_channel(id)
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError> {
+        self.write_channel(id, channel)
+    }
+
+    fn accounts(&self) -> Vec<Account> {
+        self.all_accounts()
+    }
+
+    fn channels(&self) -> Vec<(B256, Channel)> {
+        self.all_channels()
+    }
+
+    fn total_supply(&self) -> U256 {
+        self.read_total_supply()
+    }
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError> {
+        self.write_total_supply(supply)
+    }
+
+    fn seen_tx(&self, hash: &B256) -> Option<u64> {
+        self.read_seen_tx(hash)
+    }
+
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError> {
+        self.write_seen_tx(hash, block)
+    }
+
+    fn seen_txs(&self) -> Vec<(B256, u64)> {
+        self.all_seen_txs()
+    }
+
+    fn in_flight(&self) -> U256 {
+        self.cross_shard_in_flight()
+    }
+
+    fn snapshot(&mut self) -> SnapshotId {
+        self.take_snapshot()
+    }
+
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.revert_snapshot(id)
+    }
+
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError> {
+        self.commit_snapshot(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supply::TotalSupply;
+    use alloy::signers::local::PrivateKeySigner;
+
+    fn random_address() -> Address {
+        PrivateKeySigner::random().address()
+    }
+
+    #[test]
+    fn test_shard_for_is_stable_and_in_range() {
+        let state = ShardedState::in_memory(8);
+
+        for _ in 0..100 {
+            let address = random_address();
+            let shard = state.shard_for(&address);
+            assert!(shard < 8);
+            assert_eq!(shard, state.shard_for(&address));
+        }
+
+        // Addresses sharing a prefix land on the same shard
+        let mut a = [0u8; 20];
+        let mut b = [0xffu8; 20];
+        a[0] = 0x12;
+        a[1] = 0x34;
+        b[0] = 0x12;
+        b[1] = 0x34;
+        assert_eq!(
+            state.shard_for(&Address::from(a)),
+            state.shard_for(&Address::from(b))
+        );
+    }
+
+    #[test]
+    fn test_update_and_get_account() {
+        let mut state = ShardedState::in_memory(4);
+        let address = random_address();
+
+        state
+            .update_account(&address, Account::new(address, U256::from(100)))
+            .unwrap();
+        assert_eq!(
+            state.get_account(&address).unwrap().balance(),
+            U256::from(100)
+        );
+        assert!(state.get_account(&random_address()).is_none());
+    }
+
+    #[test]
+    fn test_update_and_remove_channel() {
+        let mut state = ShardedState::in_memory(4);
+        let id = B256::repeat_byte(7);
+        let channel = Channel::new(random_address(), random_address(), U256::from(100), 10);
+
+        state.update_channel(&id, Some(channel.clone())).unwrap();
+        assert_eq!(state.read_channel(&id), Some(channel));
+
+        state.update_channel(&id, None).unwrap();
+        assert!(state.get_channel(&id).is_none());
+    }
+
+    #[test]
+    fn test_apply_transfer() {
+        let state = ShardedState::in_memory(4);
+        let from = random_address();
+        let to = random_address();
+
+        state
+            .write_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        state.apply_transfer(&from, &to, U256::from(30)).unwrap();
+
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(70));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::from(30));
+    }
+
+    #[test]
+    fn test_apply_transfer_errors() {
+        let state = ShardedState::in_memory(4);
+        let from = random_address();
+        let to = random_address();
+
+        assert_eq!(
+            state.apply_transfer(&from, &to, U256::from(1)),
+            Err(StateError::AccountNotFound)
+        );
+
+        state
+            .write_account(&from, Account::new(from, U256::from(10)))
+            .unwrap();
+        assert_eq!(
+            state.apply_transfer(&from, &to, U256::from(11)),
+            Err(StateError::AccountBalanceTooLow)
+        );
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(10));
+        assert!(state.read_account(&to).is_none());
+
+        // Neither side is written when the recipient can't hold the amount
+        state
+            .write_account(&to, Account::new(to, U256::MAX))
+            .unwrap();
+        assert_eq!(
+            state.apply_transfer(&from, &to, U256::from(1)),
+            Err(StateError::BalanceOverflow)
+        );
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(10));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::MAX);
+    }
+
+    #[test]
+    fn test_shared_state() {
+        let shared = Arc::new(ShardedState::in_memory(4));
+        let mut writer: Box<dyn State> = Box::new(shared.clone());
+        let address = random_address();
+
+        writer
+            .update_account(&address, Account::new(address, U256::from(42)))
+            .unwrap();
+        assert_eq!(
+            shared.read_account(&address).unwrap().balance(),
+            U256::from(42)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_and_revert() {
+        let state = Arc::new(ShardedState::in_memory(4));
+        let mut writer: Box<dyn State> = Box::new(state.clone());
+        let from = random_address();
+        let to = random_address();
+        state
+            .write_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        let root = state.state_root();
+
+        let snapshot = writer.snapshot();
+        state.apply_transfer(&from, &to, U256::from(30)).unwrap();
+        writer
+            .update_channel(
+                &B256::repeat_byte(1),
+                Some(Channel::new(from, to, U256::from(5), 1)),
+            )
+            .unwrap();
+        writer.set_total_supply(U256::from(100)).unwrap();
+        assert_eq!(state.read_total_supply(), U256::from(100));
+        writer.revert_to(snapshot).unwrap();
+
+        assert_eq!(
+            state.read_account(&from).unwrap().balance(),
+            U256::from(100)
+        );
+        assert!(state.read_account(&to).is_none());
+        assert_eq!(state.read_total_supply(), U256::ZERO);
+        assert_eq!(state.state_root(), root);
+        assert_eq!(writer.commit(snapshot), Err(StateError::UnknownSnapshot));
+
+        let snapshot = writer.snapshot();
+        state.apply_transfer(&from, &to, U256::from(30)).unwrap();
+        writer.commit(snapshot).unwrap();
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::from(30));
+    }
+
+    #[test]
+    fn test_concurrent_transfers() {
+        let state = ShardedState::in_memory(16);
+        let pairs: Vec<(Address, Address)> = (0..32)
+            .map(|_| (random_address(), random_address()))
+            .collect();
+
+        for (from, _) in &pairs {
+            state
+                .write_account(from, Account::new(*from, U256::from(1000)))
+                .unwrap();
+        }
+
+        std::thread::scope(|scope| {
+            for (from, to) in &pairs {
+                let state = &state;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        state.apply_transfer(from, to, U256::from(10)).unwrap();
+                    }
+                });
+            }
+        });
+
+        for (from, to) in &pairs {
+            assert_eq!(state.read_account(from).unwrap().balance(), U256::from(900));
+            assert_eq!(state.read_account(to).unwrap().balance(), U256::from(100));
+        }
+    }
+
+    #[test]
+    fn test_cross_shard_transfer() {
+        let mut state = ShardedState::in_memory(4);
+        let from = Address::repeat_byte(0);
+        let to = Address::repeat_byte(1);
+        assert_ne!(state.shard_for(&from), state.shard_for(&to));
+        state
+            .write_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+
+        let message = state
+            .send_cross_shard(B256::repeat_byte(1), &from, &to, U256::from(30))
+            .unwrap();
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(70));
+        assert!(state.read_account(&to).is_none());
+        assert_eq!(state.in_flight(), U256::from(30));
+        // In flight, the supply still adds up
+        assert!(TotalSupply::of(&state).is_balanced());
+        // Sent twice before it arrived, nothing more is debited
+        assert_eq!(
+            state.send_cross_shard(message.id, &from, &to, U256::from(30)),
+            Err(StateError::DuplicateMessage)
+        );
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(70));
+        // Still queued, and only what the sender was debited for can be sent again
+        assert!(!state.resend_cross_shard(&from, &message.id));
+        assert!(!state.resend_cross_shard(&from, &B256::repeat_byte(9)));
+        assert!(!state.resend_cross_shard(&to, &message.id));
+        assert_eq!(state.in_flight(), U256::from(30));
+
+        assert_eq!(state.deliver_cross_shard(message.to_shard, 1), Ok(1));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::from(30));
+        assert_eq!(state.in_flight(), U256::ZERO);
+
+        // The sender crashed and sends the same message again, it isn't credited twice
+        assert!(!state.resend_cross_shard(&from, &message.id));
+        assert_eq!(state.deliver_cross_shard(message.to_shard, 2), Ok(0));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::from(30));
+        assert_eq!(
+            state.send_cross_shard(message.id, &from, &to, U256::from(30)),
+            Err(StateError::DuplicateMessage)
+        );
+
+        assert_eq!(
+            state.send_cross_shard(B256::repeat_byte(2), &from, &to, U256::from(71)),
+            Err(StateError::AccountBalanceTooLow)
+        );
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(70));
+        assert!(TotalSupply::of(&state).is_balanced());
+    }
+
+    #[test]
+    fn test_cross_shard_refunds() {
+        let state = ShardedState::in_memory(4);
+        let from = Address::repeat_byte(0);
+        let to = Address::repeat_byte(1);
+        state
+            .write_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        state
+            .write_account(&to, Account::new(to, U256::MAX - U256::from(5)))
+            .unwrap();
+
+        // The recipient can't hold it, it goes back to the sender and the inbox isn't blocked
+        let overflow = state
+            .send_cross_shard(B256::repeat_byte(1), &from, &to, U256::from(10))
+            .unwrap();
+        let fits = state
+            .send_cross_shard(B256::repeat_byte(2), &from, &to, U256::from(5))
+            .unwrap();
+        assert_eq!(state.deliver_cross_shard(overflow.to_shard, 1), Ok(1));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::MAX);
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(95));
+        assert_eq!(state.cross_shard_in_flight(), U256::ZERO);
+        // Settled, so it can't be resent
+        assert!(!state.resend_cross_shard(&from, &overflow.id));
+
+        // A duplicate sent as the first one was credited got past the sender's check, what it
+        // debited is refunded rather than credited again
+        state
+            .write_account(&from, Account::new(from, U256::from(90)))
+            .unwrap();
+        state.inboxes[fits.to_shard]
+            .lock()
+            .unwrap()
+            .push(fits.clone());
+        assert_eq!(state.deliver_cross_shard(fits.to_shard, 2), Ok(0));
+        assert_eq!(state.read_account(&to).unwrap().balance(), U256::MAX);
+        assert_eq!(state.read_account(&from).unwrap().balance(), U256::from(95));
+    }
+
+    #[test]
+    fn test_state_root_matches_unsharded_state() {
+        let mut sharded = ShardedState::in_memory(4);
+        let mut memory = MemoryState::new();
+        for _ in 0..20 {
+            let address = random_address();
+            sharded
+                .update_account(&address, Account::new(address, U256::from(7)))
+                .unwrap();
+            memory
+                .update_account(&address, Account::new(address, U256::from(7)))
+                .unwrap();
+        }
+
+        assert_eq!(sharded.accounts().len(), 20);
+        assert_eq!(sharded.state_root(), memory.state_root());
+    }
+}
@@ -1,15 +1,85 @@
 use crate::account::Account;
-use alloy::primitives::Address;
+use crate::channel::Channel;
+use alloy::primitives::{Address, B256, U256};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StateError {
     AccountNotFound,
     AccountBalanceTooLow,
+    // the recipient's balance would go past U256::MAX
+    BalanceOverflow,
+    // reverted or committed already, or never taken
+    UnknownSnapshot,
+    // a cross-shard message with the same id was already sent
+    DuplicateMessage,
 }
 
-// State in fastpay is simple, it allows you to read & update accounts based on their address
-pub trait State {
+// set on the block a seen tx is recorded with when the record has to be kept for good, see
+// vm::REPLAY_WINDOW. The block it was executed in is what's left without it
+pub const SEEN_FOR_GOOD: u64 = 1 << 63;
+
+// a point the state can be reverted to, snapshots nest: reverting to or committing one drops
+// every snapshot taken after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SnapshotId(pub usize);
+
+// State in fastpay is simple, it allows you to read & update accounts based on their address,
+// and payment channels based on their id. Send + Sync so a node holding one can be shared between
+// tasks
+pub trait State: Send + Sync {
     fn get_account(&self, address: &Address) -> Option<Account>;
 
     fn update_account(&mut self, address: &Address, account: Account) -> Result<(), StateError>;
+
+    fn remove_account(&mut self, address: &Address) -> Result<(), StateError>;
+
+    fn get_channel(&self, id: &B256) -> Option<Channel>;
+
+    // `None` removes the channel
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), StateError>;
+
+    // every account and channel, in no particular order
+    fn accounts(&self) -> Vec<Account>;
+
+    fn channels(&self) -> Vec<(B256, Channel)>;
+
+    // everything minted and not burned or paid in fees yet, kept next to the accounts rather than
+    // summed from them. See supply.rs for checking the two agree
+    fn total_supply(&self) -> U256;
+
+    fn set_total_supply(&mut self, supply: U256) -> Result<(), StateError>;
+
+    // the block a tx with this hash was executed in, remembered so the vm never executes the same
+    // tx twice. Not part of the state root, a replay of the chain rebuilds it
+    fn seen_tx(&self, hash: &B256) -> Option<u64>;
+
+    // `None` forgets the tx
+    fn update_seen_tx(&mut self, hash: &B256, block: Option<u64>) -> Result<(), StateError>;
+
+    // every tx remembered, in no particular order
+    fn seen_txs(&self) -> Vec<(B256, u64)>;
+
+    // debited from an account but not credited anywhere yet, only sharded state has any, see
+    // cross_shard.rs
+    fn in_flight(&self) -> U256 {
+        U256::ZERO
+    }
+
+    // changes made from here on can be undone with revert_to, until the snapshot is committed
+    fn snapshot(&mut self) -> SnapshotId;
+
+    // undoes every change made since the snapshot was taken
+    fn revert_to(&mut self, id: SnapshotId) -> Result<(), StateError>;
+
+    // keeps the changes made since the snapshot was taken and forgets it
+    fn commit(&mut self, id: SnapshotId) -> Result<(), StateError>;
+
+    fn state_root(&self) -> B256 {
+        crate::root::state_root(&self.accounts(), &self.channels())
+    }
+
+    // a proof of the account against state_root, walks the whole state like it
+    fn account_proof(&self, address: &Address) -> Option<crate::root::AccountProof> {
+        crate::root::account_proof(&self.accounts(), &self.channels(), address)
+    }
 }
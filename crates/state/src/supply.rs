@@ -0,0 +1,97 @@
+// an audit of the total supply: the state keeps a running total that only mints, burns and fees
+// change, this is where it should all be found. Accounts hold part of it and channels, escrows,
+// htlcs and cross-shard transfers in flight lock the rest, so the two always have to add up to
+// the total
+
+use alloy::primitives::U256;
+
+use crate::state::State;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TotalSupply {
+    // the running total the state keeps
+    pub total: U256,
+    // summed over every account
+    pub held: U256,
+    // summed over the deposits of every open channel, the amounts of every escrow and htlc and
+    // what is in flight between shards
+    pub locked: U256,
+}
+
+impl TotalSupply {
+    // walks every account and channel, meant for audits rather than for every tx
+    pub fn of(state: &dyn State) -> Self {
+        let accounts = state.accounts();
+        let locked = accounts.iter().fold(state.in_flight(), |locked, account| {
+            locked.saturating_add(account.locked())
+        });
+        Self {
+            total: state.total_supply(),
+            held: accounts.iter().fold(U256::ZERO, |held, account| {
+                held.saturating_add(account.balance())
+            }),
+            locked: state
+                .channels()
+                .iter()
+                .fold(locked, |locked, (_, channel)| {
+                    locked.saturating_add(channel.deposit())
+                }),
+        }
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.held.checked_add(self.locked) == Some(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{account::Account, channel::Channel, escrow::Escrow, memory::MemoryState};
+    use alloy::primitives::{Address, B256};
+
+    #[test]
+    fn test_total_supply() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let mut state = MemoryState::new();
+        state
+            .update_account(&alice, Account::new(alice, U256::from(70)))
+            .unwrap();
+        state
+            .update_account(&bob, Account::new(bob, U256::from(20)))
+            .unwrap();
+        state
+            .update_channel(
+                &B256::repeat_byte(1),
+                Some(Channel::new(alice, bob, U256::from(10), 5)),
+            )
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+
+        let supply = TotalSupply::of(&state);
+        assert_eq!(supply.held, U256::from(90));
+        assert_eq!(supply.locked, U256::from(10));
+        assert!(supply.is_balanced());
+
+        // Escrowed funds are locked too
+        let mut alice_account = Account::new(alice, U256::from(60));
+        alice_account.set_escrows(vec![Escrow {
+            id: B256::repeat_byte(2),
+            to: bob,
+            arbiter: Address::repeat_byte(3),
+            amount: U256::from(10),
+            expires_at: 5,
+        }]);
+        state.update_account(&alice, alice_account).unwrap();
+        let supply = TotalSupply::of(&state);
+        assert_eq!(supply.locked, U256::from(20));
+        assert!(supply.is_balanced());
+
+        // Funds that appear out of nowhere show up as an imbalance
+        state
+            .update_account(&bob, Account::new(bob, U256::from(21)))
+            .unwrap();
+        assert!(!TotalSupply::of(&state).is_balanced());
+    }
+}
@@ -0,0 +1,295 @@
+// opt-in reporting of anonymized node statistics to a public dashboard
+//
+// nothing is ever sent unless telemetry is enabled and an endpoint is set. reports only carry
+// aggregate numbers and a random id drawn at startup, no addresses, keys or hostnames
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TelemetryError {
+    Disabled,
+    MissingEndpoint,
+    Transport(String),
+    Http(u16),
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+    pub interval: Duration,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: None,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStats {
+    pub version: String,
+    pub peer_count: u64,
+    pub head_height: u64,
+    pub tps: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report<'a> {
+    node_id: &'a str,
+    #[serde(flatten)]
+    stats: &'a NodeStats,
+}
+
+// NodeMetrics is fed by the node as it runs, and read by the reporter
+#[derive(Debug)]
+pub struct NodeMetrics {
+    peer_count: AtomicU64,
+    head_height: AtomicU64,
+    txs: AtomicU64,
+    // tx count and time of the last tps sample
+    last_sample: Mutex<(u64, Instant)>,
+}
+
+impl NodeMetrics {
+    pub fn new() -> Self {
+        Self {
+            peer_count: AtomicU64::new(0),
+            head_height: AtomicU64::new(0),
+            txs: AtomicU64::new(0),
+            last_sample: Mutex::new((0, Instant::now())),
+        }
+    }
+
+    pub fn set_peer_count(&self, peer_count: u64) {
+        self.peer_count.store(peer_count, Ordering::Relaxed);
+    }
+
+    pub fn set_head_height(&self, head_height: u64) {
+        self.head_height.store(head_height, Ordering::Relaxed);
+    }
+
+    pub fn record_txs(&self, count: u64) {
+        self.txs.fetch_add(count, Ordering::Relaxed);
+    }
+
+    // tps is averaged over the time since the previous snapshot
+    pub fn snapshot(&self) -> NodeStats {
+        let txs = self.txs.load(Ordering::Relaxed);
+        let mut last_sample = self.last_sample.lock().unwrap();
+        let (last_txs, last_time) = *last_sample;
+
+        let elapsed = last_time.elapsed().as_secs_f64();
+        let tps = if elapsed > 0.0 {
+            (txs - last_txs) as f64 / elapsed
+        } else {
+            0.0
+        };
+        *last_sample = (txs, Instant::now());
+
+        NodeStats {
+            version: VERSION.to_string(),
+            peer_count: self.peer_count.load(Ordering::Relaxed),
+            head_height: self.head_height.load(Ordering::Relaxed),
+            tps,
+        }
+    }
+}
+
+impl Default for NodeMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Reporter {
+    http: reqwest::Client,
+    endpoint: String,
+    node_id: String,
+}
+
+impl Reporter {
+    pub fn new(config: &TelemetryConfig) -> Result<Self, TelemetryError> {
+        if !config.enabled {
+            return Err(TelemetryError::Disabled);
+        }
+        let endpoint = config
+            .endpoint
+            .clone()
+            .ok_or(TelemetryError::MissingEndpoint)?;
+
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| TelemetryError::Transport(e.to_string()))?;
+
+        Ok(Self {
+            http,
+            endpoint,
+            node_id: format!("{:016x}", rand::random::<u64>()),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub async fn report(&self, stats: &NodeStats) -> Result<(), TelemetryError> {
+        let report = Report {
+            node_id: &self.node_id,
+            stats,
+        };
+
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .json(&report)
+            .send()
+            .await
+            .map_err(|e| TelemetryError::Transport(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(TelemetryError::Http(response.status().as_u16()));
+        }
+        Ok(())
+    }
+}
+
+// reports every `config.interval` until the task is aborted, returns None when telemetry is off
+pub fn spawn(config: &TelemetryConfig, metrics: Arc<NodeMetrics>) -> Option<JoinHandle<()>> {
+    let reporter = Reporter::new(config).ok()?;
+    let interval = config.interval;
+
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // the first tick completes immediately, skip it so tps covers a full interval
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            // a dashboard being down must never affect the node
+            let _ = reporter.report(&metrics.snapshot()).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    // accepts connections and forwards each request body
+    async fn collector() -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = vec![0u8; 4096];
+                // headers and body may arrive in separate reads
+                let report = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let body = text.split("\r\n\r\n").nth(1).unwrap_or_default();
+                    if let Ok(report) = serde_json::from_str::<serde_json::Value>(body) {
+                        break report;
+                    }
+                };
+                let _ = sender.send(report);
+
+                let response = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (format!("http://{}", addr), receiver)
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let config = TelemetryConfig::default();
+        assert!(!config.enabled);
+        assert_eq!(Reporter::new(&config).err(), Some(TelemetryError::Disabled));
+
+        let enabled_without_endpoint = TelemetryConfig {
+            enabled: true,
+            ..TelemetryConfig::default()
+        };
+        assert_eq!(
+            Reporter::new(&enabled_without_endpoint).err(),
+            Some(TelemetryError::MissingEndpoint)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn_does_nothing_when_disabled() {
+        let config = TelemetryConfig {
+            endpoint: Some("http://127.0.0.1:1".to_string()),
+            ..TelemetryConfig::default()
+        };
+        assert!(spawn(&config, Arc::new(NodeMetrics::new())).is_none());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let metrics = NodeMetrics::new();
+        metrics.set_peer_count(3);
+        metrics.set_head_height(26);
+        metrics.record_txs(50);
+
+        std::thread::sleep(Duration::from_millis(10));
+        let stats = metrics.snapshot();
+        assert_eq!(stats.version, VERSION);
+        assert_eq!(stats.peer_count, 3);
+        assert_eq!(stats.head_height, 26);
+        assert!(stats.tps > 0.0);
+
+        // no new transactions since the last snapshot
+        assert_eq!(metrics.snapshot().tps, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_reports_anonymized_stats() {
+        let (endpoint, mut reports) = collector().await;
+        let config = TelemetryConfig {
+            enabled: true,
+            endpoint: Some(endpoint),
+            interval: Duration::from_millis(20),
+        };
+
+        let metrics = Arc::new(NodeMetrics::new());
+        metrics.set_head_height(7);
+        let handle = spawn(&config, metrics).unwrap();
+
+        let report = reports.recv().await.unwrap();
+        handle.abort();
+
+        assert_eq!(report["version"], VERSION);
+        assert_eq!(report["headHeight"], 7);
+        assert_eq!(report["peerCount"], 0);
+        assert!(report["nodeId"].as_str().unwrap().len() == 16);
+        let mut keys: Vec<&String> = report.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["headHeight", "nodeId", "peerCount", "tps", "version"]
+        );
+    }
+}
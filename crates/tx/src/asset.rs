@@ -0,0 +1,48 @@
+// assets other than the native token. Any account can issue its own, an asset's id is derived from
+// its issuer and its symbol so nobody else can issue more of it. Wallets see each asset as a token
+// contract at an address derived from its id, nothing is deployed there
+
+use alloy::primitives::{keccak256, Address, B256};
+
+const ASSET_ID_DOMAIN: &[u8] = b"fastpay-asset";
+
+pub type AssetId = B256;
+
+// the chain's own token, the one balances, fees and the total supply are kept in
+pub const NATIVE_ASSET: AssetId = B256::ZERO;
+
+pub fn is_native(asset: &AssetId) -> bool {
+    *asset == NATIVE_ASSET
+}
+
+pub fn asset_id(issuer: Address, symbol: &str) -> AssetId {
+    let mut encoded = ASSET_ID_DOMAIN.to_vec();
+    encoded.extend_from_slice(issuer.as_slice());
+    encoded.extend_from_slice(symbol.as_bytes());
+    keccak256(encoded)
+}
+
+// the address logs of the asset come from, the zero address for the native token
+pub fn token_address(asset: &AssetId) -> Address {
+    if is_native(asset) {
+        return Address::ZERO;
+    }
+    Address::from_word(*asset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_id() {
+        let issuer = Address::repeat_byte(1);
+        let usd = asset_id(issuer, "USD");
+        assert_ne!(usd, asset_id(issuer, "EUR"));
+        assert_ne!(usd, asset_id(Address::repeat_byte(2), "USD"));
+        assert!(!is_native(&usd));
+
+        assert_eq!(token_address(&NATIVE_ASSET), Address::ZERO);
+        assert_eq!(token_address(&usd).as_slice(), &usd[12..]);
+    }
+}
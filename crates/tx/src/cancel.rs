@@ -0,0 +1,53 @@
+// cancelling a tx that is still in the mempool. The sender signs a message naming the tx's hash,
+// not the hash itself: the tx's own signature is over that, and anyone who has seen the tx could
+// cancel it
+
+use alloc::vec::Vec;
+
+use alloy::primitives::{Address, PrimitiveSignature};
+
+const CANCELLATION_PREFIX: &[u8] = b"fastpay cancel:";
+
+// what the sender signs, as an EIP-191 message, to cancel the tx with `tx_hash`
+pub fn cancellation_message(tx_hash: &[u8]) -> Vec<u8> {
+    [CANCELLATION_PREFIX, tx_hash].concat()
+}
+
+// who signed the cancellation of the tx with `tx_hash`
+pub fn cancelled_by(tx_hash: &[u8], signature: &PrimitiveSignature) -> Option<Address> {
+    signature
+        .recover_address_from_msg(cancellation_message(tx_hash))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::Tx;
+    use alloy::primitives::U256;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_cancelled_by() {
+        let signer = PrivateKeySigner::random();
+        let tx = Tx::new(
+            signer.address(),
+            Address::repeat_byte(2),
+            U256::from(10),
+            None,
+        );
+        let tx_hash = tx.tx_hash();
+        let cancellation = signer
+            .sign_message_sync(&cancellation_message(&tx_hash))
+            .unwrap();
+        assert_eq!(
+            cancelled_by(&tx_hash, &cancellation),
+            Some(signer.address())
+        );
+
+        // The tx's own signature doesn't cancel it
+        let signature = signer.sign_message_sync(&tx_hash).unwrap();
+        assert_ne!(cancelled_by(&tx_hash, &signature), Some(signer.address()));
+    }
+}
@@ -0,0 +1,60 @@
+// off-chain balance updates of a payment channel: the payer signs how much of the deposit the payee
+// is owed so far, amounts only ever go up and the payee closes the channel with the highest one
+
+use alloy::primitives::{Address, PrimitiveSignature, B256, U256};
+use sha3::{Digest, Keccak256};
+
+use crate::tx::Tx;
+
+const CHANNEL_UPDATE_DOMAIN: &[u8] = b"fastpay-channel-update";
+
+// a channel is identified by the hash of the tx that opened it
+pub fn channel_id(open_channel: &Tx) -> B256 {
+    B256::from_slice(&open_channel.tx_hash())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelUpdate {
+    pub channel_id: B256,
+    // total paid to the payee since the channel was opened
+    pub amount: U256,
+}
+
+impl ChannelUpdate {
+    pub fn new(channel_id: B256, amount: U256) -> Self {
+        Self { channel_id, amount }
+    }
+
+    // the message the payer signs
+    pub fn hash(&self) -> B256 {
+        let mut hasher = Keccak256::new();
+        hasher.update(CHANNEL_UPDATE_DOMAIN);
+        hasher.update(self.channel_id);
+        hasher.update(self.amount.to_be_bytes::<32>());
+        B256::from_slice(&hasher.finalize())
+    }
+
+    pub fn signer(&self, signature: &PrimitiveSignature) -> Option<Address> {
+        signature.recover_address_from_msg(self.hash()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_signer() {
+        let payer = PrivateKeySigner::random();
+        let update = ChannelUpdate::new(B256::repeat_byte(1), U256::from(40));
+        let signature = payer.sign_message_sync(update.hash().as_slice()).unwrap();
+
+        assert_eq!(update.signer(&signature), Some(payer.address()));
+
+        // The signature doesn't carry over to a larger amount
+        let larger = ChannelUpdate::new(update.channel_id, U256::from(41));
+        assert_ne!(larger.signer(&signature), Some(payer.address()));
+    }
+}
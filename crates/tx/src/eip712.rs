@@ -0,0 +1,133 @@
+// EIP-712 typed data for transfers. Wallets show the fields of a typed signature instead of an
+// opaque hash, and the domain keeps it from being mistaken for a signature meant for another
+// chain or application. Transfers signed over their tx hash are still accepted
+
+use alloy::primitives::B256;
+use alloy::sol;
+use alloy::sol_types::{eip712_domain, Eip712Domain, SolStruct};
+
+use crate::asset::is_native;
+use crate::tx::Tx;
+
+pub const DOMAIN_NAME: &str = "fastpay";
+pub const DOMAIN_VERSION: &str = "1";
+// the chain id of the domain of txs that don't set one
+pub const CHAIN_ID: u64 = 1337;
+
+sol! {
+    struct Transfer {
+        address from;
+        address to;
+        uint256 amount;
+        uint64 fee;
+    }
+
+    struct TransferOrder {
+        address from;
+        address to;
+        uint256 amount;
+        uint64 sequence;
+        uint64 fee;
+    }
+}
+
+pub fn domain(chain_id: u64) -> Eip712Domain {
+    eip712_domain! {
+        name: DOMAIN_NAME,
+        version: DOMAIN_VERSION,
+        chain_id: chain_id,
+    }
+}
+
+// the digest a typed signature of the tx covers, only transfers of the native token have a typed
+// form. The typed structs have no asset, a signature of one could be replayed for another
+pub fn signing_hash(tx: &Tx) -> Option<B256> {
+    let Tx::Transfer {
+        from,
+        to,
+        amount,
+        sequence,
+        fee,
+        asset,
+        ..
+    } = *tx
+    else {
+        return None;
+    };
+    if !is_native(&asset) {
+        return None;
+    }
+
+    let domain = domain(tx.chain_id().unwrap_or(CHAIN_ID));
+    Some(match sequence {
+        Some(sequence) => TransferOrder {
+            from,
+            to,
+            amount,
+            sequence,
+            fee,
+        }
+        .eip712_signing_hash(&domain),
+        None => Transfer {
+            from,
+            to,
+            amount,
+            fee,
+        }
+        .eip712_signing_hash(&domain),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, keccak256, U256};
+
+    #[test]
+    fn test_signing_hash() {
+        let from = address!("0x0101010101010101010101010101010101010101");
+        let to = address!("0x0202020202020202020202020202020202020202");
+
+        let transfer = signing_hash(&Tx::new(from, to, U256::from(100), None)).unwrap();
+        let order = signing_hash(&Tx::transfer_order(from, to, U256::from(100), 0, None)).unwrap();
+        // an order can't be passed off as a plain transfer
+        assert_ne!(transfer, order);
+        assert_ne!(
+            signing_hash(&Tx::new(from, to, U256::from(100), None).with_fee(1)).unwrap(),
+            transfer
+        );
+        assert_eq!(
+            TransferOrder::eip712_encode_type(),
+            "TransferOrder(address from,address to,uint256 amount,uint64 sequence,uint64 fee)"
+        );
+        let separator = keccak256(
+            [
+                keccak256("EIP712Domain(string name,string version,uint256 chainId)"),
+                keccak256(DOMAIN_NAME),
+                keccak256(DOMAIN_VERSION),
+                B256::from(U256::from(CHAIN_ID)),
+            ]
+            .concat(),
+        );
+        assert_eq!(domain(CHAIN_ID).separator(), separator);
+        assert_eq!(
+            signing_hash(&Tx::new(from, to, U256::from(100), None).with_chain_id(CHAIN_ID))
+                .unwrap(),
+            transfer
+        );
+        assert_ne!(
+            signing_hash(&Tx::new(from, to, U256::from(100), None).with_chain_id(1)).unwrap(),
+            transfer
+        );
+
+        assert_eq!(
+            signing_hash(&Tx::open_channel(from, to, U256::from(100), 10, None)),
+            None
+        );
+        let asset = crate::asset::asset_id(from, "USD");
+        assert_eq!(
+            signing_hash(&Tx::new(from, to, U256::from(100), None).with_asset(asset)),
+            None
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// the least a transfer has to pay: a flat part and a part per byte it takes, so a bigger tx pays
+// for the room it takes in blocks and in the mempool. The mempool refuses what the vm would, with
+// the same schedule. Only transfers pay fees, other txs are left out
+
+use alloc::format;
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+use crate::tx::Tx;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSchedule {
+    pub base_fee: u64,
+    pub fee_per_byte: u64,
+}
+
+impl FeeSchedule {
+    pub fn new(base_fee: u64, fee_per_byte: u64) -> Self {
+        Self {
+            base_fee,
+            fee_per_byte,
+        }
+    }
+
+    pub fn min_fee(&self, tx: &Tx) -> u64 {
+        if !matches!(tx, Tx::Transfer { .. }) {
+            return 0;
+        }
+        self.fee_per_byte
+            .saturating_mul(tx.encoded_len() as u64)
+            .saturating_add(self.base_fee)
+    }
+
+    pub fn check(&self, tx: &Tx) -> Result<(), String> {
+        let min_fee = self.min_fee(tx);
+        if tx.fee() < min_fee {
+            return Err(format!(
+                "Transaction pays a fee of {}, its {} bytes need at least {}",
+                tx.fee(),
+                tx.encoded_len(),
+                min_fee
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn test_min_fee() {
+        let (from, to) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let schedule = FeeSchedule::new(10, 2);
+        // 81 bytes of transfer and 65 of signature
+        let tx = Tx::new(from, to, U256::from(100), None).with_fee(5);
+        assert_eq!(tx.encoded_len(), 146);
+        assert_eq!(schedule.min_fee(&tx), 302);
+        assert!(schedule.check(&tx).is_err());
+        assert!(schedule.check(&tx.clone().with_fee(302)).is_ok());
+        assert!(FeeSchedule::default().check(&tx).is_ok());
+
+        // A sequence number makes the transfer bigger
+        let order = Tx::transfer_order(from, to, U256::from(100), 0, None).with_fee(5);
+        assert_eq!(schedule.min_fee(&order), 318);
+
+        // Channel txs don't pay fees
+        assert_eq!(
+            schedule.min_fee(&Tx::open_channel(from, to, U256::from(100), 10, None)),
+            0
+        );
+    }
+}
@@ -1 +1,18 @@
+// txs, their hashes and their signatures. The crate builds without std, with `alloc`, so txs can
+// be built and verified in a zkVM guest or on an embedded signer
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod asset;
+pub mod cancel;
+pub mod channel;
+pub mod eip712;
+pub mod fee;
+pub mod log;
+pub mod merkle;
+pub mod netting;
+#[cfg(feature = "std")]
+pub mod signature_cache;
 pub mod tx;
+pub mod verify;
@@ -0,0 +1,77 @@
+// logs the vm emits as txs execute, laid out like EVM logs so eth_getLogs clients and indexers
+// read them as they are. The native token has no contract, its logs come from the zero address.
+// Other assets' come from their token address, see asset.rs
+
+use alloc::{vec, vec::Vec};
+use alloy::primitives::{keccak256, Address, Bloom, BloomInput, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::asset::{token_address, AssetId, NATIVE_ASSET};
+
+pub const TRANSFER_EVENT_SIGNATURE: &str = "Transfer(address,address,uint256)";
+
+pub fn transfer_topic() -> B256 {
+    keccak256(TRANSFER_EVENT_SIGNATURE)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Log {
+    pub address: Address,
+    // the event signature first, then the indexed fields
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+impl Log {
+    // Transfer(address indexed from, address indexed to, uint256 amount)
+    pub fn transfer(from: Address, to: Address, amount: U256) -> Self {
+        Self::asset_transfer(&NATIVE_ASSET, from, to, amount)
+    }
+
+    // the same event, from the token address of the asset like an ERC-20 contract's
+    pub fn asset_transfer(asset: &AssetId, from: Address, to: Address, amount: U256) -> Self {
+        Self {
+            address: token_address(asset),
+            topics: vec![transfer_topic(), from.into_word(), to.into_word()],
+            data: Bytes::copy_from_slice(&amount.to_be_bytes::<32>()),
+        }
+    }
+}
+
+// the bloom of the logs' addresses and topics, as in an EVM block header
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = Bloom::ZERO;
+    for log in logs {
+        bloom.accrue(BloomInput::Raw(log.address.as_slice()));
+        for topic in &log.topics {
+            bloom.accrue(BloomInput::Raw(topic.as_slice()));
+        }
+    }
+    bloom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_log() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let log = Log::transfer(from, to, U256::from(500));
+        assert_eq!(
+            log.topics[0],
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                .parse::<B256>()
+                .unwrap()
+        );
+        assert_eq!(Address::from_word(log.topics[1]), from);
+        assert_eq!(U256::from_be_slice(&log.data), U256::from(500));
+
+        let bloom = logs_bloom([&log]);
+        assert!(bloom.contains_input(BloomInput::Raw(log.topics[2].as_slice())));
+        assert!(!bloom.contains_input(BloomInput::Raw(
+            Address::repeat_byte(3).into_word().as_slice()
+        )));
+    }
+}
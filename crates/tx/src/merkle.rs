@@ -0,0 +1,158 @@
+// binary merkle trees over 32 byte leaves, what blocks commit to their txs and receipts with and
+// the state to its accounts. A proof of log2(n) hashes shows a leaf is in the tree without the
+// rest of it. Leaves and inner nodes are hashed under different prefixes, so an inner node can't
+// pass for a leaf. A node left without a sibling at the end of a level moves up unchanged
+
+use alloc::{vec, vec::Vec};
+use alloy::primitives::{keccak256, B256};
+use serde::{Deserialize, Serialize};
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+fn leaf(hash: &B256) -> B256 {
+    let mut encoded = vec![LEAF_PREFIX];
+    encoded.extend_from_slice(hash.as_slice());
+    keccak256(encoded)
+}
+
+fn node(left: &B256, right: &B256) -> B256 {
+    let mut encoded = vec![NODE_PREFIX];
+    encoded.extend_from_slice(left.as_slice());
+    encoded.extend_from_slice(right.as_slice());
+    keccak256(encoded)
+}
+
+fn next_level(level: &[B256]) -> Vec<B256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+// zero for a tree without leaves
+pub fn merkle_root(leaves: &[B256]) -> B256 {
+    if leaves.is_empty() {
+        return B256::ZERO;
+    }
+    let mut level: Vec<B256> = leaves.iter().map(leaf).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+// that the leaf at `index` of a tree over `leaf_count` leaves is `leaf`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionProof {
+    pub leaf: B256,
+    pub index: u64,
+    pub leaf_count: u64,
+    // from the leaf up, levels where the node had no sibling have no entry
+    pub siblings: Vec<B256>,
+}
+
+impl InclusionProof {
+    // None when `index` is out of range
+    pub fn new(leaves: &[B256], index: usize) -> Option<Self> {
+        let leaf_hash = *leaves.get(index)?;
+        let mut level: Vec<B256> = leaves.iter().map(leaf).collect();
+        let mut position = index;
+        let mut siblings = Vec::new();
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(*sibling);
+            }
+            level = next_level(&level);
+            position /= 2;
+        }
+
+        Some(Self {
+            leaf: leaf_hash,
+            index: index as u64,
+            leaf_count: leaves.len() as u64,
+            siblings,
+        })
+    }
+
+    // the root the proof leads to, None if it doesn't have the siblings its shape asks for
+    pub fn root(&self) -> Option<B256> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+        let mut hash = leaf(&self.leaf);
+        let mut siblings = self.siblings.iter();
+        let mut position = self.index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            if position % 2 == 1 {
+                hash = node(siblings.next()?, &hash);
+            } else if position + 1 < width {
+                hash = node(&hash, siblings.next()?);
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none().then_some(hash)
+    }
+
+    pub fn verify(&self, root: B256) -> bool {
+        self.root() == Some(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(count: u8) -> Vec<B256> {
+        (0..count).map(B256::repeat_byte).collect()
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        assert_eq!(merkle_root(&[]), B256::ZERO);
+        assert_eq!(merkle_root(&hashes(1)), leaf(&B256::repeat_byte(0)));
+
+        let three = hashes(3);
+        let expected = node(&node(&leaf(&three[0]), &leaf(&three[1])), &leaf(&three[2]));
+        assert_eq!(merkle_root(&three), expected);
+
+        // Order matters
+        let mut swapped = three.clone();
+        swapped.swap(0, 1);
+        assert_ne!(merkle_root(&swapped), expected);
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        for count in 1..=9 {
+            let tx_hashes = hashes(count);
+            let root = merkle_root(&tx_hashes);
+            for index in 0..count as usize {
+                let proof = InclusionProof::new(&tx_hashes, index).unwrap();
+                assert!(proof.verify(root), "{} of {}", index, count);
+
+                let mut wrong_tx = proof.clone();
+                wrong_tx.leaf = B256::repeat_byte(0xff);
+                assert!(!wrong_tx.verify(root));
+
+                let mut extra_sibling = proof.clone();
+                extra_sibling.siblings.push(B256::ZERO);
+                assert!(!extra_sibling.verify(root));
+            }
+            assert!(InclusionProof::new(&tx_hashes, count as usize).is_none());
+        }
+
+        // A proof moved to another index doesn't hold
+        let tx_hashes = hashes(4);
+        let mut moved = InclusionProof::new(&tx_hashes, 1).unwrap();
+        moved.index = 0;
+        assert!(!moved.verify(merkle_root(&tx_hashes)));
+    }
+}
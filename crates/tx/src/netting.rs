@@ -0,0 +1,192 @@
+// payment netting: instead of settling every payment between members of a closed group, members sign
+// intents during a window and only the net result is settled, in as few transfers as possible
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::{format, vec::Vec};
+
+use alloy::primitives::{Address, PrimitiveSignature, B256};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+const INTENT_DOMAIN: &[u8] = b"fastpay-netting-intent";
+
+// `from` wants to pay `to` as part of the given netting window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Intent {
+    pub from: Address,
+    pub to: Address,
+    pub amount: u64,
+    pub window: u64,
+}
+
+impl Intent {
+    // the message `from` signs
+    pub fn hash(&self) -> B256 {
+        let mut hasher = Keccak256::new();
+        hasher.update(INTENT_DOMAIN);
+        hasher.update(self.from);
+        hasher.update(self.to);
+        hasher.update(self.amount.to_be_bytes());
+        hasher.update(self.window.to_be_bytes());
+        B256::from_slice(&hasher.finalize())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedIntent {
+    pub intent: Intent,
+    pub signature: PrimitiveSignature,
+}
+
+impl SignedIntent {
+    pub fn verify(&self) -> Result<(), String> {
+        let signer = self
+            .signature
+            .recover_address_from_msg(self.intent.hash())
+            .map_err(|e| format!("invalid intent signature: {}", e))?;
+        if signer != self.intent.from {
+            return Err("intent is not signed by its sender".to_string());
+        }
+        Ok(())
+    }
+}
+
+// a transfer that settles part of the net result of a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Obligation {
+    pub from: Address,
+    pub to: Address,
+    pub amount: u64,
+}
+
+// nets the intents per address and pairs the largest debtors with the largest creditors, which
+// settles everything in at most one transfer less than the number of addresses involved.
+// the result only depends on the intents, so anyone can check what a netting authority settles
+pub fn net_obligations(intents: &[Intent]) -> Result<Vec<Obligation>, String> {
+    let mut positions: BTreeMap<Address, i128> = BTreeMap::new();
+    for intent in intents {
+        *positions.entry(intent.from).or_default() -= intent.amount as i128;
+        *positions.entry(intent.to).or_default() += intent.amount as i128;
+    }
+
+    let mut debtors: Vec<(Address, i128)> = positions
+        .iter()
+        .filter(|(_, position)| **position < 0)
+        .map(|(address, position)| (*address, -position))
+        .collect();
+    let mut creditors: Vec<(Address, i128)> = positions
+        .into_iter()
+        .filter(|(_, position)| *position > 0)
+        .collect();
+    // largest first, ties broken by address so every node computes the same transfers
+    debtors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    creditors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut obligations = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < debtors.len() && j < creditors.len() {
+        let amount = debtors[i].1.min(creditors[j].1);
+        obligations.push(Obligation {
+            from: debtors[i].0,
+            to: creditors[j].0,
+            amount: u64::try_from(amount)
+                .map_err(|_| "net obligation does not fit in a transfer".to_string())?,
+        });
+
+        debtors[i].1 -= amount;
+        creditors[j].1 -= amount;
+        if debtors[i].1 == 0 {
+            i += 1;
+        }
+        if creditors[j].1 == 0 {
+            j += 1;
+        }
+    }
+
+    Ok(obligations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    fn intent(from: u8, to: u8, amount: u64) -> Intent {
+        Intent {
+            from: Address::repeat_byte(from),
+            to: Address::repeat_byte(to),
+            amount,
+            window: 0,
+        }
+    }
+
+    #[test]
+    fn test_net_obligations() {
+        // a -> b 100, b -> c 100, c -> a 30: b is even, c ends up with 70 from a
+        let intents = vec![intent(1, 2, 100), intent(2, 3, 100), intent(3, 1, 30)];
+
+        assert_eq!(
+            net_obligations(&intents).unwrap(),
+            vec![Obligation {
+                from: Address::repeat_byte(1),
+                to: Address::repeat_byte(3),
+                amount: 70,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_net_obligations_balanced_cycle() {
+        let intents = vec![intent(1, 2, 5), intent(2, 3, 5), intent(3, 1, 5)];
+        assert!(net_obligations(&intents).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_net_obligations_conserve_positions() {
+        let intents = vec![
+            intent(1, 2, 40),
+            intent(1, 3, 25),
+            intent(4, 2, 10),
+            intent(3, 4, 5),
+            intent(2, 5, 15),
+        ];
+        let obligations = net_obligations(&intents).unwrap();
+
+        let mut net: BTreeMap<Address, i128> = BTreeMap::new();
+        for intent in &intents {
+            *net.entry(intent.from).or_default() -= intent.amount as i128;
+            *net.entry(intent.to).or_default() += intent.amount as i128;
+        }
+        for obligation in &obligations {
+            *net.entry(obligation.from).or_default() += obligation.amount as i128;
+            *net.entry(obligation.to).or_default() -= obligation.amount as i128;
+        }
+        assert!(net.values().all(|position| *position == 0));
+        assert!(obligations.len() < net.len());
+    }
+
+    #[test]
+    fn test_signed_intent() {
+        let signer = PrivateKeySigner::random();
+        let intent = Intent {
+            from: signer.address(),
+            ..intent(0, 2, 10)
+        };
+        let signature = signer.sign_message_sync(intent.hash().as_slice()).unwrap();
+
+        assert!(SignedIntent { intent, signature }.verify().is_ok());
+
+        let inflated = Intent {
+            amount: 11,
+            ..intent
+        };
+        assert!(SignedIntent {
+            intent: inflated,
+            signature
+        }
+        .verify()
+        .is_err());
+    }
+}
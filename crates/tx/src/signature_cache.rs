@@ -0,0 +1,174 @@
+// recovering who signed a tx is the most expensive part of checking it, and the same tx is checked
+// when the mempool admits it, when it is executed for a block and again when the block is
+// imported. The cache keeps what was recovered from the most recent txs by tx hash, one is meant
+// to be shared behind an Arc by everything on a node that checks signatures
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use alloc::{string::String, vec::Vec};
+use alloy::primitives::{Address, PrimitiveSignature};
+use bytes::Bytes;
+use lru::LruCache;
+
+use crate::tx::Tx;
+use crate::verify::check_signature;
+
+#[derive(Debug, Clone)]
+struct Recovered {
+    // the tx hash doesn't cover the signatures, an entry only answers for the ones it was
+    // recovered from
+    signatures: Vec<PrimitiveSignature>,
+    // who signed the tx hash
+    signers: Vec<Address>,
+    // whether the sender signed, over the tx hash or over its typed data
+    signed_by_sender: bool,
+}
+
+pub struct SignatureCache {
+    entries: Mutex<LruCache<Bytes, Recovered>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl SignatureCache {
+    // keeps what was recovered from the last `capacity` txs looked up, at least one
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(
+                NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN),
+            )),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // the same checks as verify::verify_signature
+    pub fn verify_signature(&self, tx: &Tx) -> Result<(), String> {
+        check_signature(tx, |tx| self.recovered(tx).signed_by_sender)
+    }
+
+    // the addresses that signed the tx hash, as Tx::signers
+    pub fn signers(&self, tx: &Tx) -> Vec<Address> {
+        self.recovered(tx).signers
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    // the share of lookups answered without recovering, 0 before the first one
+    pub fn hit_rate(&self) -> f64 {
+        let (hits, misses) = (self.hits(), self.misses());
+        if hits + misses == 0 {
+            return 0.0;
+        }
+        hits as f64 / (hits + misses) as f64
+    }
+
+    fn recovered(&self, tx: &Tx) -> Recovered {
+        let tx_hash = tx.tx_hash();
+        let signatures = tx.signatures();
+        if let Some(recovered) = self.entries.lock().unwrap().get(&tx_hash) {
+            if recovered.signatures == signatures {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return recovered.clone();
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // recovered without holding the lock, threads missing on the same tx both recover it
+        let signers = tx.signers();
+        let signed_by_sender = signers.contains(&tx.from()) || tx.typed_signer() == Some(tx.from());
+        let recovered = Recovered {
+            signatures,
+            signers,
+            signed_by_sender,
+        };
+        self.entries.lock().unwrap().put(tx_hash, recovered.clone());
+        recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    fn signed(signer: &PrivateKeySigner, amount: u64) -> Tx {
+        let tx = Tx::new(
+            signer.address(),
+            Address::repeat_byte(2),
+            U256::from(amount),
+            None,
+        );
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        tx.with_signature(signature)
+    }
+
+    #[test]
+    fn test_signature_cache() {
+        let cache = SignatureCache::new(2);
+        let signer = PrivateKeySigner::random();
+        let tx = signed(&signer, 10);
+        assert_eq!(cache.hit_rate(), 0.0);
+
+        assert_eq!(cache.verify_signature(&tx), Ok(()));
+        assert_eq!(cache.verify_signature(&tx), Ok(()));
+        assert_eq!(cache.signers(&tx), vec![signer.address()]);
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+        assert!((cache.hit_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        // The same tx signed by someone else isn't answered from the entry
+        let other = PrivateKeySigner::random()
+            .sign_message_sync(&tx.tx_hash())
+            .unwrap();
+        let forged = tx.clone().with_signature(other);
+        assert!(cache.verify_signature(&forged).is_err());
+        assert_eq!(cache.misses(), 2);
+
+        // A tx without a signature isn't looked up at all
+        let unsigned = Tx::new(
+            signer.address(),
+            Address::repeat_byte(2),
+            U256::from(10),
+            None,
+        );
+        assert!(cache.verify_signature(&unsigned).is_err());
+        assert_eq!(cache.hits() + cache.misses(), 4);
+    }
+
+    #[test]
+    fn test_signature_cache_evicts_least_recently_used() {
+        let cache = SignatureCache::new(2);
+        let signer = PrivateKeySigner::random();
+        let (first, second, third) = (signed(&signer, 1), signed(&signer, 2), signed(&signer, 3));
+
+        cache.signers(&first);
+        cache.signers(&second);
+        cache.signers(&first);
+        cache.signers(&third);
+        assert_eq!(cache.len(), 2);
+
+        let misses = cache.misses();
+        cache.signers(&first);
+        assert_eq!(cache.misses(), misses);
+        cache.signers(&second);
+        assert_eq!(cache.misses(), misses + 1);
+    }
+}
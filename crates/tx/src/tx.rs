@@ -1,23 +1,297 @@
-use alloy::primitives::{Address, PrimitiveSignature};
+use alloc::string::String;
+use alloc::{vec, vec::Vec};
+use alloy::primitives::{Address, PrimitiveSignature, B256, U256};
 use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 
-#[derive(Debug, Clone)]
+use crate::asset::{asset_id, is_native, AssetId, NATIVE_ASSET};
+use crate::netting::{Obligation, SignedIntent};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Tx {
     Transfer {
         from: Address,
         // TODO: we want to allow transfer to multiple addresses, this later on needs to be an array
         to: Address,
-        amount: u64,
+        amount: U256,
+        signature: Option<PrimitiveSignature>,
+        // the sender's sequence number when the transfer is a FastPay transfer order, authorities
+        // only vote for the order matching the account's next sequence number
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence: Option<u64>,
+        // paid on top of the amount to get ahead of other txs in the mempool, it is burned
+        #[serde(default, skip_serializing_if = "is_zero")]
+        fee: u64,
+        // what is transferred, the native token unless set. The fee is always paid in it
+        #[serde(default, skip_serializing_if = "is_native")]
+        asset: AssetId,
+        // the chain the tx is meant for, so it can't be replayed on another. Txs signed before
+        // chain ids have none
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // locks `amount` of the payer in a new channel to `to`, the channel id is the hash of this tx
+    OpenChannel {
+        from: Address,
+        to: Address,
+        amount: U256,
+        challenge_period: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // sent by the payee with the latest balance update signed by the payer, `amount` goes to the
+    // payee and the rest of the deposit back to the payer
+    CloseChannel {
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        amount: U256,
+        update_signature: PrimitiveSignature,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // sent by the payer when the payee doesn't close the channel, starts the challenge period
+    StartChannelTimeout {
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // sent by the payer once the challenge period is over, returns the whole deposit
+    ClaimChannelTimeout {
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // sent by a netting authority at the end of a window, settles the net result of the intents
+    // with the obligations they produce, see netting::net_obligations
+    Settlement {
+        from: Address,
+        window: u64,
+        intents: Vec<SignedIntent>,
+        obligations: Vec<Obligation>,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // a transfer out of a multisig account, signed over its tx hash by enough of the account's
+    // owners. The sequence number keeps the signatures from being replayed
+    MultisigTransfer {
+        from: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signatures: Vec<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // creates `amount` in the account of `to`, only the chain's issuer can send it. The sequence
+    // number is the issuer's, so a mint can't be replayed
+    Mint {
+        from: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // destroys `amount` of the issuer's own balance, funds leaving the chain are sent to the
+    // issuer first
+    Burn {
+        from: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // credits a deposit made to the bridge contract on the primary chain, only the bridge
+    // operator can send it. The contract numbers deposits from 0 and the operator's sequence
+    // number has to match, so every deposit is credited once and in order
+    FundFromPrimary {
+        from: Address,
+        to: Address,
+        amount: U256,
+        deposit_nonce: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // takes `amount` out of the sender's account to be withdrawn to `recipient` on the primary
+    // chain, once the committee has certified it
+    RedeemToPrimary {
+        from: Address,
+        recipient: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // sets the limits on what leaves the sender's account, replacing the ones it had. Without any
+    // limits it removes them
+    SetPolicy {
+        from: Address,
+        max_per_tx: Option<U256>,
+        daily_limit: Option<U256>,
+        allowlist: Vec<Address>,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // pays `amount` to `to` every `interval` blocks, the first payment `interval` blocks after the
+    // tx is executed and the last no later than block `end`. The schedule id is the hash of this tx
+    Schedule {
+        from: Address,
+        to: Address,
+        amount: U256,
+        interval: u64,
+        end: Option<u64>,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // stops one of the sender's schedules, payments already made stay made
+    CancelSchedule {
+        from: Address,
+        schedule_id: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // locks `amount` of the sender's funds for `to` until `arbiter` settles them, the sender can
+    // take them back once `timeout` blocks have passed. The escrow id is the hash of this tx, see
+    // state::escrow
+    EscrowCreate {
+        from: Address,
+        to: Address,
+        arbiter: Address,
+        amount: U256,
+        timeout: u64,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // pays an escrow `payer` locked out to its payee
+    EscrowRelease {
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // returns an escrow to the `payer` who locked it
+    EscrowRefund {
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // locks `amount` for `recipient` until block `timelock`, under the sha256 `hashlock` of a
+    // secret, see state::htlc
+    HtlcLock {
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        timelock: u64,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // takes the funds `sender` locked for the claimer under the hash of `preimage`, revealing it
+    HtlcClaim {
+        from: Address,
+        sender: Address,
+        preimage: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // takes back what the sender locked for `recipient` under `hashlock`, once the timelock is
+    // reached
+    HtlcRefund {
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
+    },
+    // creates `amount` of the sender's asset `symbol` for `to`, see asset.rs. The first issue
+    // creates the asset, only the sender can ever issue more of it
+    IssueAsset {
+        from: Address,
+        symbol: String,
+        to: Address,
+        amount: U256,
+        sequence: u64,
         signature: Option<PrimitiveSignature>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        chain_id: Option<u64>,
     },
 }
 
+// prefixes the encoding of every tx but transfers, so two kinds of tx never hash the same
+const OPEN_CHANNEL_TAG: u8 = 1;
+const CLOSE_CHANNEL_TAG: u8 = 2;
+const START_CHANNEL_TIMEOUT_TAG: u8 = 3;
+const CLAIM_CHANNEL_TIMEOUT_TAG: u8 = 4;
+const SETTLEMENT_TAG: u8 = 5;
+// precedes the fee of a transfer that pays one
+const FEE_TAG: u8 = 6;
+// precedes the chain id of a tx that has one
+const CHAIN_ID_TAG: u8 = 7;
+const MULTISIG_TRANSFER_TAG: u8 = 8;
+const MINT_TAG: u8 = 9;
+const BURN_TAG: u8 = 10;
+const FUND_FROM_PRIMARY_TAG: u8 = 11;
+const REDEEM_TO_PRIMARY_TAG: u8 = 12;
+const SET_POLICY_TAG: u8 = 13;
+const SCHEDULE_TAG: u8 = 14;
+const CANCEL_SCHEDULE_TAG: u8 = 15;
+const ESCROW_CREATE_TAG: u8 = 16;
+const ESCROW_RELEASE_TAG: u8 = 17;
+const ESCROW_REFUND_TAG: u8 = 18;
+const HTLC_LOCK_TAG: u8 = 19;
+const HTLC_CLAIM_TAG: u8 = 20;
+const HTLC_REFUND_TAG: u8 = 21;
+// precedes the asset of a transfer of anything but the native token
+const ASSET_TAG: u8 = 22;
+const ISSUE_ASSET_TAG: u8 = 23;
+// r, s and the parity
+const SIGNATURE_LEN: usize = 65;
+
+fn is_zero(fee: &u64) -> bool {
+    *fee == 0
+}
+
 impl Tx {
     pub fn new(
         from: Address,
         to: Address,
-        amount: u64,
+        amount: U256,
         signature: Option<PrimitiveSignature>,
     ) -> Self {
         Self::Transfer {
@@ -25,6 +299,379 @@ impl Tx {
             to,
             amount,
             signature,
+            sequence: None,
+            fee: 0,
+            asset: NATIVE_ASSET,
+            chain_id: None,
+        }
+    }
+
+    pub fn transfer_order(
+        from: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::Transfer {
+            from,
+            to,
+            amount,
+            signature,
+            sequence: Some(sequence),
+            fee: 0,
+            asset: NATIVE_ASSET,
+            chain_id: None,
+        }
+    }
+
+    pub fn open_channel(
+        from: Address,
+        to: Address,
+        amount: U256,
+        challenge_period: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::OpenChannel {
+            from,
+            to,
+            amount,
+            challenge_period,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn close_channel(
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        amount: U256,
+        update_signature: PrimitiveSignature,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::CloseChannel {
+            from,
+            to,
+            channel_id,
+            amount,
+            update_signature,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn start_channel_timeout(
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::StartChannelTimeout {
+            from,
+            to,
+            channel_id,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn claim_channel_timeout(
+        from: Address,
+        to: Address,
+        channel_id: B256,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::ClaimChannelTimeout {
+            from,
+            to,
+            channel_id,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn settlement(
+        from: Address,
+        window: u64,
+        intents: Vec<SignedIntent>,
+        obligations: Vec<Obligation>,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::Settlement {
+            from,
+            window,
+            intents,
+            obligations,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn multisig_transfer(
+        from: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signatures: Vec<PrimitiveSignature>,
+    ) -> Self {
+        Self::MultisigTransfer {
+            from,
+            to,
+            amount,
+            sequence,
+            signatures,
+            chain_id: None,
+        }
+    }
+
+    pub fn mint(
+        from: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::Mint {
+            from,
+            to,
+            amount,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn burn(
+        from: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::Burn {
+            from,
+            amount,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn fund_from_primary(
+        from: Address,
+        to: Address,
+        amount: U256,
+        deposit_nonce: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::FundFromPrimary {
+            from,
+            to,
+            amount,
+            deposit_nonce,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn redeem_to_primary(
+        from: Address,
+        recipient: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::RedeemToPrimary {
+            from,
+            recipient,
+            amount,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn set_policy(
+        from: Address,
+        max_per_tx: Option<U256>,
+        daily_limit: Option<U256>,
+        allowlist: Vec<Address>,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::SetPolicy {
+            from,
+            max_per_tx,
+            daily_limit,
+            allowlist,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn schedule(
+        from: Address,
+        to: Address,
+        amount: U256,
+        interval: u64,
+        end: Option<u64>,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::Schedule {
+            from,
+            to,
+            amount,
+            interval,
+            end,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn cancel_schedule(
+        from: Address,
+        schedule_id: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::CancelSchedule {
+            from,
+            schedule_id,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn escrow_create(
+        from: Address,
+        to: Address,
+        arbiter: Address,
+        amount: U256,
+        timeout: u64,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::EscrowCreate {
+            from,
+            to,
+            arbiter,
+            amount,
+            timeout,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn escrow_release(
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::EscrowRelease {
+            from,
+            payer,
+            escrow_id,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn escrow_refund(
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::EscrowRefund {
+            from,
+            payer,
+            escrow_id,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn htlc_lock(
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        timelock: u64,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::HtlcLock {
+            from,
+            recipient,
+            hashlock,
+            timelock,
+            amount,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn htlc_claim(
+        from: Address,
+        sender: Address,
+        preimage: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::HtlcClaim {
+            from,
+            sender,
+            preimage,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn htlc_refund(
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::HtlcRefund {
+            from,
+            recipient,
+            hashlock,
+            sequence,
+            signature,
+            chain_id: None,
+        }
+    }
+
+    pub fn issue_asset(
+        from: Address,
+        symbol: impl Into<String>,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+        signature: Option<PrimitiveSignature>,
+    ) -> Self {
+        Self::IssueAsset {
+            from,
+            symbol: symbol.into(),
+            to,
+            amount,
+            sequence,
+            signature,
+            chain_id: None,
         }
     }
 
@@ -34,26 +681,347 @@ impl Tx {
 
     pub fn from(&self) -> Address {
         match self {
-            Self::Transfer { from, .. } => from.clone(),
+            Self::Transfer { from, .. }
+            | Self::OpenChannel { from, .. }
+            | Self::CloseChannel { from, .. }
+            | Self::StartChannelTimeout { from, .. }
+            | Self::ClaimChannelTimeout { from, .. }
+            | Self::Settlement { from, .. }
+            | Self::MultisigTransfer { from, .. }
+            | Self::Mint { from, .. }
+            | Self::Burn { from, .. }
+            | Self::FundFromPrimary { from, .. }
+            | Self::RedeemToPrimary { from, .. }
+            | Self::SetPolicy { from, .. }
+            | Self::Schedule { from, .. }
+            | Self::CancelSchedule { from, .. }
+            | Self::EscrowCreate { from, .. }
+            | Self::EscrowRelease { from, .. }
+            | Self::EscrowRefund { from, .. }
+            | Self::HtlcLock { from, .. }
+            | Self::HtlcClaim { from, .. }
+            | Self::HtlcRefund { from, .. }
+            | Self::IssueAsset { from, .. } => *from,
         }
     }
 
+    // settlements move funds between many accounts, the authority is both their sender and receiver.
+    // A burn has no receiver either, it is the issuer's own balance that goes, and neither does a
+    // redemption: its recipient is on the primary chain. A policy is the sender's own, and so is
+    // a cancelled schedule. Settling an escrow changes the account of the payer who locked it, and
+    // so does claiming an htlc, refunding one changes only the sender's own
     pub fn to(&self) -> Address {
         match self {
-            Self::Transfer { to, .. } => to.clone(),
+            Self::Transfer { to, .. }
+            | Self::OpenChannel { to, .. }
+            | Self::CloseChannel { to, .. }
+            | Self::StartChannelTimeout { to, .. }
+            | Self::ClaimChannelTimeout { to, .. }
+            | Self::MultisigTransfer { to, .. }
+            | Self::Mint { to, .. }
+            | Self::FundFromPrimary { to, .. }
+            | Self::Schedule { to, .. }
+            | Self::EscrowCreate { to, .. }
+            | Self::IssueAsset { to, .. } => *to,
+            Self::HtlcLock { recipient, .. } => *recipient,
+            Self::Settlement { from, .. }
+            | Self::Burn { from, .. }
+            | Self::RedeemToPrimary { from, .. }
+            | Self::SetPolicy { from, .. }
+            | Self::CancelSchedule { from, .. }
+            | Self::HtlcRefund { from, .. } => *from,
+            Self::EscrowRelease { payer, .. } | Self::EscrowRefund { payer, .. } => *payer,
+            Self::HtlcClaim { sender, .. } => *sender,
         }
     }
 
-    pub fn amount(&self) -> u64 {
+    // the value moved by the tx, timeouts only ever move the deposit the channel already holds. A
+    // schedule moves nothing itself, its payments are made later, and settling an escrow only
+    // moves what it locked, and so does settling an htlc
+    pub fn amount(&self) -> U256 {
         match self {
-            Self::Transfer { amount, .. } => *amount,
+            Self::Transfer { amount, .. }
+            | Self::OpenChannel { amount, .. }
+            | Self::CloseChannel { amount, .. }
+            | Self::MultisigTransfer { amount, .. }
+            | Self::Mint { amount, .. }
+            | Self::Burn { amount, .. }
+            | Self::FundFromPrimary { amount, .. }
+            | Self::RedeemToPrimary { amount, .. }
+            | Self::EscrowCreate { amount, .. }
+            | Self::HtlcLock { amount, .. }
+            | Self::IssueAsset { amount, .. } => *amount,
+            Self::StartChannelTimeout { .. }
+            | Self::ClaimChannelTimeout { .. }
+            | Self::SetPolicy { .. }
+            | Self::Schedule { .. }
+            | Self::CancelSchedule { .. }
+            | Self::EscrowRelease { .. }
+            | Self::EscrowRefund { .. }
+            | Self::HtlcClaim { .. }
+            | Self::HtlcRefund { .. } => U256::ZERO,
+            Self::Settlement { obligations, .. } => {
+                obligations.iter().fold(U256::ZERO, |total, obligation| {
+                    total.saturating_add(U256::from(obligation.amount))
+                })
+            }
+        }
+    }
+
+    pub fn channel_id(&self) -> Option<B256> {
+        match self {
+            Self::Transfer { .. }
+            | Self::OpenChannel { .. }
+            | Self::Settlement { .. }
+            | Self::MultisigTransfer { .. }
+            | Self::Mint { .. }
+            | Self::Burn { .. }
+            | Self::FundFromPrimary { .. }
+            | Self::RedeemToPrimary { .. }
+            | Self::SetPolicy { .. }
+            | Self::Schedule { .. }
+            | Self::CancelSchedule { .. }
+            | Self::EscrowCreate { .. }
+            | Self::EscrowRelease { .. }
+            | Self::EscrowRefund { .. }
+            | Self::HtlcLock { .. }
+            | Self::HtlcClaim { .. }
+            | Self::HtlcRefund { .. }
+            | Self::IssueAsset { .. } => None,
+            Self::CloseChannel { channel_id, .. }
+            | Self::StartChannelTimeout { channel_id, .. }
+            | Self::ClaimChannelTimeout { channel_id, .. } => Some(*channel_id),
+        }
+    }
+
+    // every account the tx can change, a settlement touches everyone it nets
+    pub fn touched_addresses(&self) -> Vec<Address> {
+        match self {
+            Self::Settlement {
+                from,
+                intents,
+                obligations,
+                ..
+            } => {
+                let mut addresses = vec![*from];
+                for intent in intents {
+                    addresses.push(intent.intent.from);
+                    addresses.push(intent.intent.to);
+                }
+                for obligation in obligations {
+                    addresses.push(obligation.from);
+                    addresses.push(obligation.to);
+                }
+                addresses.sort();
+                addresses.dedup();
+                addresses
+            }
+            _ => vec![self.from(), self.to()],
+        }
+    }
+
+    // the chain id is part of what the sender signs
+    pub fn with_chain_id(mut self, new_chain_id: u64) -> Self {
+        match &mut self {
+            Self::Transfer { chain_id, .. }
+            | Self::OpenChannel { chain_id, .. }
+            | Self::CloseChannel { chain_id, .. }
+            | Self::StartChannelTimeout { chain_id, .. }
+            | Self::ClaimChannelTimeout { chain_id, .. }
+            | Self::Settlement { chain_id, .. }
+            | Self::MultisigTransfer { chain_id, .. }
+            | Self::Mint { chain_id, .. }
+            | Self::Burn { chain_id, .. }
+            | Self::FundFromPrimary { chain_id, .. }
+            | Self::RedeemToPrimary { chain_id, .. }
+            | Self::SetPolicy { chain_id, .. }
+            | Self::Schedule { chain_id, .. }
+            | Self::CancelSchedule { chain_id, .. }
+            | Self::EscrowCreate { chain_id, .. }
+            | Self::EscrowRelease { chain_id, .. }
+            | Self::EscrowRefund { chain_id, .. }
+            | Self::HtlcLock { chain_id, .. }
+            | Self::HtlcClaim { chain_id, .. }
+            | Self::HtlcRefund { chain_id, .. }
+            | Self::IssueAsset { chain_id, .. } => *chain_id = Some(new_chain_id),
         }
+        self
     }
 
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Self::Transfer { chain_id, .. }
+            | Self::OpenChannel { chain_id, .. }
+            | Self::CloseChannel { chain_id, .. }
+            | Self::StartChannelTimeout { chain_id, .. }
+            | Self::ClaimChannelTimeout { chain_id, .. }
+            | Self::Settlement { chain_id, .. }
+            | Self::MultisigTransfer { chain_id, .. }
+            | Self::Mint { chain_id, .. }
+            | Self::Burn { chain_id, .. }
+            | Self::FundFromPrimary { chain_id, .. }
+            | Self::RedeemToPrimary { chain_id, .. }
+            | Self::SetPolicy { chain_id, .. }
+            | Self::Schedule { chain_id, .. }
+            | Self::CancelSchedule { chain_id, .. }
+            | Self::EscrowCreate { chain_id, .. }
+            | Self::EscrowRelease { chain_id, .. }
+            | Self::EscrowRefund { chain_id, .. }
+            | Self::HtlcLock { chain_id, .. }
+            | Self::HtlcClaim { chain_id, .. }
+            | Self::HtlcRefund { chain_id, .. }
+            | Self::IssueAsset { chain_id, .. } => *chain_id,
+        }
+    }
+
+    // only transfers pay fees, the fee is part of what the sender signs
+    pub fn with_fee(mut self, new_fee: u64) -> Self {
+        if let Self::Transfer { fee, .. } = &mut self {
+            *fee = new_fee;
+        }
+        self
+    }
+
+    // only transfers move other assets than the native token, the asset is part of what the
+    // sender signs
+    pub fn with_asset(mut self, new_asset: AssetId) -> Self {
+        if let Self::Transfer { asset, .. } = &mut self {
+            *asset = new_asset;
+        }
+        self
+    }
+
+    // what the amount of the tx is counted in, an issue counts in the asset it issues
+    pub fn asset(&self) -> AssetId {
+        match self {
+            Self::Transfer { asset, .. } => *asset,
+            Self::IssueAsset { from, symbol, .. } => asset_id(*from, symbol),
+            _ => NATIVE_ASSET,
+        }
+    }
+
+    pub fn fee(&self) -> u64 {
+        match self {
+            Self::Transfer { fee, .. } => *fee,
+            _ => 0,
+        }
+    }
+
+    // the bytes the tx takes on the wire, its encoding and its signatures. A tx that isn't signed
+    // yet is counted with the signature it needs
+    pub fn encoded_len(&self) -> usize {
+        self.to_bytes().len() + SIGNATURE_LEN * self.signatures().len().max(1)
+    }
+
+    pub fn sequence(&self) -> Option<u64> {
+        match self {
+            Self::Transfer { sequence, .. } => *sequence,
+            Self::MultisigTransfer { sequence, .. }
+            | Self::Mint { sequence, .. }
+            | Self::Burn { sequence, .. }
+            | Self::RedeemToPrimary { sequence, .. }
+            | Self::SetPolicy { sequence, .. }
+            | Self::Schedule { sequence, .. }
+            | Self::CancelSchedule { sequence, .. }
+            | Self::EscrowCreate { sequence, .. }
+            | Self::EscrowRelease { sequence, .. }
+            | Self::EscrowRefund { sequence, .. }
+            | Self::HtlcLock { sequence, .. }
+            | Self::HtlcClaim { sequence, .. }
+            | Self::HtlcRefund { sequence, .. }
+            | Self::IssueAsset { sequence, .. } => Some(*sequence),
+            Self::FundFromPrimary { deposit_nonce, .. } => Some(*deposit_nonce),
+            _ => None,
+        }
+    }
+
+    // the first owner signature of a multisig transfer
     pub fn signature(&self) -> Option<PrimitiveSignature> {
         match self {
-            Self::Transfer { signature, .. } => signature.clone(),
+            Self::Transfer { signature, .. }
+            | Self::OpenChannel { signature, .. }
+            | Self::CloseChannel { signature, .. }
+            | Self::StartChannelTimeout { signature, .. }
+            | Self::ClaimChannelTimeout { signature, .. }
+            | Self::Settlement { signature, .. }
+            | Self::Mint { signature, .. }
+            | Self::Burn { signature, .. }
+            | Self::FundFromPrimary { signature, .. }
+            | Self::RedeemToPrimary { signature, .. }
+            | Self::SetPolicy { signature, .. }
+            | Self::Schedule { signature, .. }
+            | Self::CancelSchedule { signature, .. }
+            | Self::EscrowCreate { signature, .. }
+            | Self::EscrowRelease { signature, .. }
+            | Self::EscrowRefund { signature, .. }
+            | Self::HtlcLock { signature, .. }
+            | Self::HtlcClaim { signature, .. }
+            | Self::HtlcRefund { signature, .. }
+            | Self::IssueAsset { signature, .. } => *signature,
+            Self::MultisigTransfer { signatures, .. } => signatures.first().copied(),
+        }
+    }
+
+    pub fn signatures(&self) -> Vec<PrimitiveSignature> {
+        match self {
+            Self::MultisigTransfer { signatures, .. } => signatures.clone(),
+            _ => self.signature().into_iter().collect(),
+        }
+    }
+
+    // a multisig transfer collects signatures, each owner adds theirs
+    pub fn with_signature(mut self, new_signature: PrimitiveSignature) -> Self {
+        match &mut self {
+            Self::Transfer { signature, .. }
+            | Self::OpenChannel { signature, .. }
+            | Self::CloseChannel { signature, .. }
+            | Self::StartChannelTimeout { signature, .. }
+            | Self::ClaimChannelTimeout { signature, .. }
+            | Self::Settlement { signature, .. }
+            | Self::Mint { signature, .. }
+            | Self::Burn { signature, .. }
+            | Self::FundFromPrimary { signature, .. }
+            | Self::RedeemToPrimary { signature, .. }
+            | Self::SetPolicy { signature, .. }
+            | Self::Schedule { signature, .. }
+            | Self::CancelSchedule { signature, .. }
+            | Self::EscrowCreate { signature, .. }
+            | Self::EscrowRelease { signature, .. }
+            | Self::EscrowRefund { signature, .. }
+            | Self::HtlcLock { signature, .. }
+            | Self::HtlcClaim { signature, .. }
+            | Self::HtlcRefund { signature, .. }
+            | Self::IssueAsset { signature, .. } => *signature = Some(new_signature),
+            Self::MultisigTransfer { signatures, .. } => signatures.push(new_signature),
         }
+        self
+    }
+
+    // the addresses that signed the tx hash, signatures that don't recover are skipped
+    pub fn signers(&self) -> Vec<Address> {
+        let tx_hash = self.tx_hash();
+        self.signatures()
+            .iter()
+            .filter_map(|signature| signature.recover_address_from_msg(&tx_hash).ok())
+            .collect()
+    }
+
+    // the EIP-712 digest a wallet can sign instead of the tx hash, None for anything but transfers
+    pub fn typed_hash(&self) -> Option<B256> {
+        crate::eip712::signing_hash(self)
+    }
+
+    // whether the tx carries a signature of `address`, over its tx hash or over its typed data
+    pub fn is_signed_by(&self, address: Address) -> bool {
+        self.signers().contains(&address) || self.typed_signer() == Some(address)
+    }
+
+    // who signed the typed data, for a transfer signed that way
+    pub fn typed_signer(&self) -> Option<Address> {
+        let (Some(signature), Some(hash)) = (self.signature(), self.typed_hash()) else {
+            return None;
+        };
+        signature.recover_address_from_prehash(&hash).ok()
     }
 
     pub fn tx_hash(&self) -> Bytes {
@@ -62,9 +1030,7 @@ impl Tx {
         let mut hasher = Keccak256::new();
         hasher.update(value);
 
-        let hash = Bytes::from(hasher.finalize().to_vec());
-
-        hash
+        Bytes::from(hasher.finalize().to_vec())
     }
 
     pub fn to_bytes(&self) -> Bytes {
@@ -74,14 +1040,330 @@ impl Tx {
                 from,
                 to,
                 amount,
-                signature: _,
+                sequence,
+                fee,
+                asset,
+                ..
+            } => {
+                value.extend_from_slice(from.as_ref());
+                value.extend_from_slice(to.as_ref());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                // plain transfers keep the encoding they had before sequence numbers, fees and
+                // assets
+                if let Some(sequence) = sequence {
+                    value.extend_from_slice(&sequence.to_be_bytes());
+                }
+                if *fee > 0 {
+                    value.extend_from_slice(&[FEE_TAG]);
+                    value.extend_from_slice(&fee.to_be_bytes());
+                }
+                if !is_native(asset) {
+                    value.extend_from_slice(&[ASSET_TAG]);
+                    value.extend_from_slice(asset.as_slice());
+                }
+            }
+            Self::OpenChannel {
+                from,
+                to,
+                amount,
+                challenge_period,
+                ..
+            } => {
+                value.extend_from_slice(&[OPEN_CHANNEL_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&challenge_period.to_be_bytes());
+            }
+            Self::CloseChannel {
+                from,
+                to,
+                channel_id,
+                amount,
+                update_signature,
+                ..
+            } => {
+                value.extend_from_slice(&[CLOSE_CHANNEL_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(channel_id.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&update_signature.as_bytes());
+            }
+            Self::StartChannelTimeout {
+                from,
+                to,
+                channel_id,
+                ..
+            }
+            | Self::ClaimChannelTimeout {
+                from,
+                to,
+                channel_id,
+                ..
+            } => {
+                let tag = if matches!(self, Self::StartChannelTimeout { .. }) {
+                    START_CHANNEL_TIMEOUT_TAG
+                } else {
+                    CLAIM_CHANNEL_TIMEOUT_TAG
+                };
+                value.extend_from_slice(&[tag]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(channel_id.as_slice());
+            }
+            Self::Settlement {
+                from,
+                window,
+                intents,
+                obligations,
+                ..
+            } => {
+                value.extend_from_slice(&[SETTLEMENT_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(&window.to_be_bytes());
+                value.extend_from_slice(&(intents.len() as u32).to_be_bytes());
+                for signed in intents {
+                    value.extend_from_slice(signed.intent.hash().as_slice());
+                    value.extend_from_slice(&signed.signature.as_bytes());
+                }
+                value.extend_from_slice(&(obligations.len() as u32).to_be_bytes());
+                for obligation in obligations {
+                    value.extend_from_slice(obligation.from.as_slice());
+                    value.extend_from_slice(obligation.to.as_slice());
+                    value.extend_from_slice(&obligation.amount.to_be_bytes());
+                }
+            }
+            Self::MultisigTransfer {
+                from,
+                to,
+                amount,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[MULTISIG_TRANSFER_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::Mint {
+                from,
+                to,
+                amount,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[MINT_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::Burn {
+                from,
+                amount,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[BURN_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::FundFromPrimary {
+                from,
+                to,
+                amount,
+                deposit_nonce,
+                ..
+            } => {
+                value.extend_from_slice(&[FUND_FROM_PRIMARY_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&deposit_nonce.to_be_bytes());
+            }
+            Self::RedeemToPrimary {
+                from,
+                recipient,
+                amount,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[REDEEM_TO_PRIMARY_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(recipient.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::SetPolicy {
+                from,
+                max_per_tx,
+                daily_limit,
+                allowlist,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[SET_POLICY_TAG]);
+                value.extend_from_slice(from.as_slice());
+                for limit in [max_per_tx, daily_limit] {
+                    match limit {
+                        Some(limit) => {
+                            value.extend_from_slice(&[1]);
+                            value.extend_from_slice(&limit.to_be_bytes::<32>());
+                        }
+                        None => value.extend_from_slice(&[0]),
+                    }
+                }
+                value.extend_from_slice(&(allowlist.len() as u32).to_be_bytes());
+                for recipient in allowlist {
+                    value.extend_from_slice(recipient.as_slice());
+                }
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::Schedule {
+                from,
+                to,
+                amount,
+                interval,
+                end,
+                sequence,
+                ..
             } => {
-                value.extend_from_slice(&from.to_vec());
-                value.extend_from_slice(&to.to_vec());
-                value.extend_from_slice(&amount.to_be_bytes());
-                value.freeze()
+                value.extend_from_slice(&[SCHEDULE_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&interval.to_be_bytes());
+                match end {
+                    Some(end) => {
+                        value.extend_from_slice(&[1]);
+                        value.extend_from_slice(&end.to_be_bytes());
+                    }
+                    None => value.extend_from_slice(&[0]),
+                }
+                value.extend_from_slice(&sequence.to_be_bytes());
             }
+            Self::CancelSchedule {
+                from,
+                schedule_id,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[CANCEL_SCHEDULE_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(schedule_id.as_slice());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::EscrowCreate {
+                from,
+                to,
+                arbiter,
+                amount,
+                timeout,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[ESCROW_CREATE_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(arbiter.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&timeout.to_be_bytes());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::EscrowRelease {
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                ..
+            }
+            | Self::EscrowRefund {
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                ..
+            } => {
+                let tag = if matches!(self, Self::EscrowRelease { .. }) {
+                    ESCROW_RELEASE_TAG
+                } else {
+                    ESCROW_REFUND_TAG
+                };
+                value.extend_from_slice(&[tag]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(payer.as_slice());
+                value.extend_from_slice(escrow_id.as_slice());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::HtlcLock {
+                from,
+                recipient,
+                hashlock,
+                timelock,
+                amount,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[HTLC_LOCK_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(recipient.as_slice());
+                value.extend_from_slice(hashlock.as_slice());
+                value.extend_from_slice(&timelock.to_be_bytes());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::HtlcClaim {
+                from,
+                sender,
+                preimage,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[HTLC_CLAIM_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(sender.as_slice());
+                value.extend_from_slice(preimage.as_slice());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::HtlcRefund {
+                from,
+                recipient,
+                hashlock,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[HTLC_REFUND_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(recipient.as_slice());
+                value.extend_from_slice(hashlock.as_slice());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+            Self::IssueAsset {
+                from,
+                symbol,
+                to,
+                amount,
+                sequence,
+                ..
+            } => {
+                value.extend_from_slice(&[ISSUE_ASSET_TAG]);
+                value.extend_from_slice(from.as_slice());
+                value.extend_from_slice(&(symbol.len() as u32).to_be_bytes());
+                value.extend_from_slice(symbol.as_bytes());
+                value.extend_from_slice(to.as_slice());
+                value.extend_from_slice(&amount.to_be_bytes::<32>());
+                value.extend_from_slice(&sequence.to_be_bytes());
+            }
+        }
+        // txs without a chain id keep the encoding they had before chain ids
+        if let Some(chain_id) = self.chain_id() {
+            value.extend_from_slice(&[CHAIN_ID_TAG]);
+            value.extend_from_slice(&chain_id.to_be_bytes());
         }
+        value.freeze()
     }
 }
 
@@ -89,6 +1371,7 @@ impl Tx {
 mod tests {
     use super::*;
     use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
 
     #[test]
     fn test_new_transfer() {
@@ -98,9 +1381,9 @@ mod tests {
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
 
-        let amount = 100u64;
+        let amount = U256::from(100);
 
-        let tx = Tx::new(from.clone(), to.clone(), amount, None);
+        let tx = Tx::new(from, to, amount, None);
 
         assert!(tx.is_transfer());
 
@@ -109,12 +1392,134 @@ mod tests {
             to: t,
             amount: a,
             signature: s,
-        } = tx;
+            sequence: q,
+            fee,
+            asset,
+            chain_id,
+        } = tx
+        else {
+            panic!("expected a transfer");
+        };
 
         assert_eq!(f, from);
         assert_eq!(t, to);
         assert_eq!(a, amount);
         assert_eq!(s, None);
+        assert_eq!(q, None);
+        assert_eq!(fee, 0);
+        assert_eq!(asset, NATIVE_ASSET);
+        assert_eq!(chain_id, None);
+    }
+
+    #[test]
+    fn test_transfer_order() {
+        let from = PrivateKeySigner::random().address();
+        let to = PrivateKeySigner::random().address();
+
+        let order = Tx::transfer_order(from, to, U256::from(100), 3, None);
+        assert!(order.is_transfer());
+        assert_eq!(order.sequence(), Some(3));
+        assert_eq!(order.to_bytes().len(), 80);
+        assert_ne!(
+            order.tx_hash(),
+            Tx::new(from, to, U256::from(100), None).tx_hash()
+        );
+        assert_ne!(
+            order.tx_hash(),
+            Tx::transfer_order(from, to, U256::from(100), 4, None).tx_hash()
+        );
+
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&order).unwrap()).unwrap();
+        assert_eq!(decoded.sequence(), Some(3));
+        // Transfers serialized before sequence numbers existed still decode
+        let plain = serde_json::to_string(&Tx::new(from, to, U256::from(100), None)).unwrap();
+        assert!(!plain.contains("sequence"));
+        assert_eq!(serde_json::from_str::<Tx>(&plain).unwrap().sequence(), None);
+    }
+
+    #[test]
+    fn test_is_signed_by() {
+        let signer = PrivateKeySigner::random();
+        let to = PrivateKeySigner::random().address();
+        let tx = Tx::transfer_order(signer.address(), to, U256::from(100), 1, None);
+        assert!(!tx.is_signed_by(signer.address()));
+
+        let over_hash = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let typed = signer.sign_hash_sync(&tx.typed_hash().unwrap()).unwrap();
+        for signature in [over_hash, typed] {
+            let signed = tx.clone().with_signature(signature);
+            assert!(signed.is_signed_by(signer.address()));
+            assert!(!signed.is_signed_by(to));
+        }
+
+        // A typed signature only covers the tx it was made for
+        let other = Tx::transfer_order(signer.address(), to, U256::from(100), 2, None)
+            .with_signature(typed);
+        assert!(!other.is_signed_by(signer.address()));
+    }
+
+    #[test]
+    fn test_transfer_fee() {
+        let from = PrivateKeySigner::random().address();
+        let to = PrivateKeySigner::random().address();
+
+        let tx = Tx::new(from, to, U256::from(100), None).with_fee(5);
+        assert_eq!(tx.fee(), 5);
+        assert_eq!(tx.to_bytes().len(), 81);
+        assert_ne!(
+            tx.tx_hash(),
+            Tx::new(from, to, U256::from(100), None).tx_hash()
+        );
+        // A fee can't pass for a sequence number
+        assert_ne!(
+            Tx::transfer_order(from, to, U256::from(100), 5, None).tx_hash(),
+            tx.tx_hash()
+        );
+        assert_eq!(
+            Tx::transfer_order(from, to, U256::from(100), 5, None)
+                .with_fee(5)
+                .to_bytes()
+                .len(),
+            89
+        );
+
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&tx).unwrap()).unwrap();
+        assert_eq!(decoded.fee(), 5);
+        // Channel txs don't pay fees
+        assert_eq!(
+            Tx::open_channel(from, to, U256::from(100), 10, None)
+                .with_fee(5)
+                .fee(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_chain_id() {
+        let from = PrivateKeySigner::random().address();
+        let to = PrivateKeySigner::random().address();
+
+        let tx = Tx::new(from, to, U256::from(100), None);
+        assert_eq!(tx.chain_id(), None);
+        let on_chain = tx.clone().with_chain_id(1);
+        assert_eq!(on_chain.chain_id(), Some(1));
+        assert_eq!(on_chain.to_bytes().len(), 81);
+        assert_ne!(on_chain.tx_hash(), tx.tx_hash());
+        assert_ne!(on_chain.tx_hash(), tx.clone().with_chain_id(2).tx_hash());
+        // Nor can a chain id pass for a fee
+        assert_ne!(on_chain.tx_hash(), tx.with_fee(1).tx_hash());
+
+        let channel = Tx::open_channel(from, to, U256::from(100), 10, None);
+        assert_ne!(
+            channel.clone().with_chain_id(1).tx_hash(),
+            channel.tx_hash()
+        );
+
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&on_chain).unwrap()).unwrap();
+        assert_eq!(decoded.chain_id(), Some(1));
+        assert!(!serde_json::to_string(&channel)
+            .unwrap()
+            .contains("chain_id"));
     }
 
     #[test]
@@ -125,7 +1530,7 @@ mod tests {
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
 
-        let amount = 100u64;
+        let amount = U256::from(100);
 
         let tx = Tx::new(from, to, amount, None);
         assert!(tx.is_transfer());
@@ -139,20 +1544,20 @@ mod tests {
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
 
-        let amount = 100u64;
+        let amount = U256::from(100);
 
-        let tx = Tx::new(from.clone(), to.clone(), amount, None);
+        let tx = Tx::new(from, to, amount, None);
         let bytes = tx.to_bytes();
 
-        // Expected length: 20 (from) + 20 (to) + 8 (amount) = 48 bytes
-        assert_eq!(bytes.len(), 48);
+        // Expected length: 20 (from) + 20 (to) + 32 (amount) = 72 bytes
+        assert_eq!(bytes.len(), 72);
 
         // Verify from address
         assert_eq!(&bytes[0..20], &from.to_vec());
         // Verify to address
         assert_eq!(&bytes[20..40], &to.to_vec());
         // Verify amount
-        assert_eq!(&bytes[40..48], &amount.to_be_bytes());
+        assert_eq!(&bytes[40..72], &amount.to_be_bytes::<32>());
     }
 
     #[test]
@@ -163,9 +1568,9 @@ mod tests {
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
 
-        let amount = 100u64;
+        let amount = U256::from(100);
 
-        let tx = Tx::new(from.clone(), to.clone(), amount, None);
+        let tx = Tx::new(from, to, amount, None);
         let hash = tx.tx_hash();
 
         // Keccak256 hash should be 32 bytes
@@ -176,8 +1581,270 @@ mod tests {
         assert_eq!(hash, hash2);
 
         // Different transaction should have different hash
-        let tx2 = Tx::new(from, to, amount + 1, None);
+        let tx2 = Tx::new(from, to, amount + U256::from(1), None);
         let hash3 = tx2.tx_hash();
         assert_ne!(hash, hash3);
     }
+
+    #[test]
+    fn test_channel_txs() {
+        let from = PrivateKeySigner::random().address();
+        let to = PrivateKeySigner::random().address();
+        let channel_id = B256::repeat_byte(1);
+
+        let open = Tx::open_channel(from, to, U256::from(100), 10, None);
+        assert!(!open.is_transfer());
+        assert_eq!(open.amount(), U256::from(100));
+        assert_eq!(open.channel_id(), None);
+
+        // Same fields as a transfer, but the encodings never collide
+        assert_ne!(
+            open.tx_hash(),
+            Tx::new(from, to, U256::from(100), None).tx_hash()
+        );
+
+        let start = Tx::start_channel_timeout(from, to, channel_id, None);
+        let claim = Tx::claim_channel_timeout(from, to, channel_id, None);
+        assert_eq!(start.channel_id(), Some(channel_id));
+        assert_eq!(claim.amount(), U256::ZERO);
+        assert_ne!(start.tx_hash(), claim.tx_hash());
+    }
+
+    #[test]
+    fn test_multisig_transfer() {
+        let owners = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+
+        let tx = Tx::multisig_transfer(from, to, U256::from(100), 0, vec![]);
+        assert_eq!(tx.sequence(), Some(0));
+        assert_eq!(tx.signature(), None);
+        // Never the same hash as the transfer order with the same fields
+        assert_ne!(
+            tx.tx_hash(),
+            Tx::transfer_order(from, to, U256::from(100), 0, None).tx_hash()
+        );
+
+        let signed = owners.iter().fold(tx.clone(), |tx, owner| {
+            let signature = owner.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        });
+        assert_eq!(signed.tx_hash(), tx.tx_hash());
+        assert_eq!(
+            signed.signers(),
+            vec![owners[0].address(), owners[1].address()]
+        );
+        assert!(signed.is_signed_by(owners[1].address()));
+        assert!(!signed.is_signed_by(from));
+
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        assert_eq!(decoded.signatures(), signed.signatures());
+    }
+
+    #[test]
+    fn test_mint_and_burn() {
+        let issuer = PrivateKeySigner::random();
+        let to = Address::repeat_byte(2);
+
+        let mint = Tx::mint(issuer.address(), to, U256::from(100), 0, None);
+        assert_eq!(mint.from(), issuer.address());
+        assert_eq!(mint.to(), to);
+        assert_eq!(mint.amount(), U256::from(100));
+        assert_eq!(mint.sequence(), Some(0));
+        // Never the same hash as the transfer order with the same fields
+        assert_ne!(
+            mint.tx_hash(),
+            Tx::transfer_order(issuer.address(), to, U256::from(100), 0, None).tx_hash()
+        );
+
+        let burn = Tx::burn(issuer.address(), U256::from(100), 1, None);
+        assert_eq!(burn.to(), issuer.address());
+        assert_eq!(burn.amount(), U256::from(100));
+
+        let signature = issuer.sign_message_sync(&burn.tx_hash()).unwrap();
+        let signed = burn.clone().with_signature(signature);
+        assert_eq!(signed.tx_hash(), burn.tx_hash());
+        assert!(signed.is_signed_by(issuer.address()));
+
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        assert_eq!(decoded.tx_hash(), signed.tx_hash());
+        assert_eq!(decoded.signature(), signed.signature());
+    }
+
+    #[test]
+    fn test_bridge_txs() {
+        let operator = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+
+        let fund = Tx::fund_from_primary(operator, to, U256::from(100), 7, None);
+        assert_eq!(fund.from(), operator);
+        assert_eq!(fund.to(), to);
+        assert_eq!(fund.sequence(), Some(7));
+        // Never the same hash as a mint with the same fields
+        assert_ne!(
+            fund.tx_hash(),
+            Tx::mint(operator, to, U256::from(100), 7, None).tx_hash()
+        );
+
+        let redeem = Tx::redeem_to_primary(to, operator, U256::from(40), 0, None);
+        assert_eq!(redeem.from(), to);
+        assert_eq!(redeem.to(), to);
+        assert_eq!(redeem.amount(), U256::from(40));
+        // The recipient on the primary chain is part of what the sender signs
+        assert_ne!(
+            redeem.tx_hash(),
+            Tx::redeem_to_primary(to, to, U256::from(40), 0, None).tx_hash()
+        );
+    }
+
+    #[test]
+    fn test_set_policy() {
+        let owner = PrivateKeySigner::random();
+        let recipient = Address::repeat_byte(2);
+
+        let tx = Tx::set_policy(
+            owner.address(),
+            Some(U256::from(10)),
+            None,
+            vec![recipient],
+            3,
+            None,
+        );
+        assert_eq!(tx.to(), owner.address());
+        assert_eq!(tx.amount(), U256::ZERO);
+        assert_eq!(tx.sequence(), Some(3));
+        // Which limit is set is part of what the owner signs
+        assert_ne!(
+            tx.tx_hash(),
+            Tx::set_policy(
+                owner.address(),
+                None,
+                Some(U256::from(10)),
+                vec![recipient],
+                3,
+                None
+            )
+            .tx_hash()
+        );
+        assert_ne!(
+            tx.tx_hash(),
+            Tx::set_policy(owner.address(), Some(U256::from(10)), None, vec![], 3, None).tx_hash()
+        );
+
+        let signature = owner.sign_message_sync(&tx.tx_hash()).unwrap();
+        let signed = tx.with_signature(signature);
+        assert!(signed.is_signed_by(owner.address()));
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        assert_eq!(decoded.tx_hash(), signed.tx_hash());
+    }
+
+    #[test]
+    fn test_schedule() {
+        let payer = PrivateKeySigner::random();
+        let payee = Address::repeat_byte(2);
+
+        let tx = Tx::schedule(payer.address(), payee, U256::from(10), 100, None, 0, None);
+        assert_eq!(tx.to(), payee);
+        // Nothing moves until the first payment is due
+        assert_eq!(tx.amount(), U256::ZERO);
+        assert_ne!(
+            tx.tx_hash(),
+            Tx::schedule(
+                payer.address(),
+                payee,
+                U256::from(10),
+                100,
+                Some(1_000),
+                0,
+                None
+            )
+            .tx_hash()
+        );
+
+        let schedule_id = B256::from_slice(&tx.tx_hash());
+        let cancel = Tx::cancel_schedule(payer.address(), schedule_id, 1, None);
+        assert_eq!(cancel.to(), payer.address());
+        assert_eq!(cancel.sequence(), Some(1));
+        let signature = payer.sign_message_sync(&cancel.tx_hash()).unwrap();
+        let signed = cancel.with_signature(signature);
+        assert!(signed.is_signed_by(payer.address()));
+        let decoded: Tx = serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        assert_eq!(decoded.tx_hash(), signed.tx_hash());
+    }
+
+    #[test]
+    fn test_escrow() {
+        let payer = Address::repeat_byte(1);
+        let arbiter = Address::repeat_byte(3);
+        let create = Tx::escrow_create(
+            payer,
+            Address::repeat_byte(2),
+            arbiter,
+            U256::from(10),
+            100,
+            0,
+            None,
+        );
+        assert_eq!(create.amount(), U256::from(10));
+
+        // Settling an escrow is signed by whoever settles it, and changes the payer's account
+        let escrow_id = B256::from_slice(&create.tx_hash());
+        let release = Tx::escrow_release(arbiter, payer, escrow_id, 0, None);
+        let refund = Tx::escrow_refund(arbiter, payer, escrow_id, 0, None);
+        assert_eq!((release.from(), release.to()), (arbiter, payer));
+        assert_eq!(release.amount(), U256::ZERO);
+        // A release can't be passed off as a refund
+        assert_ne!(release.tx_hash(), refund.tx_hash());
+    }
+
+    #[test]
+    fn test_htlc() {
+        let sender = Address::repeat_byte(1);
+        let recipient = Address::repeat_byte(2);
+        let hashlock = B256::repeat_byte(3);
+
+        let lock = Tx::htlc_lock(sender, recipient, hashlock, 100, U256::from(10), 0, None);
+        assert_eq!((lock.to(), lock.amount()), (recipient, U256::from(10)));
+        // Both are kept on the sender's account
+        let claim = Tx::htlc_claim(recipient, sender, B256::repeat_byte(4), 0, None);
+        assert_eq!((claim.from(), claim.to()), (recipient, sender));
+        let refund = Tx::htlc_refund(sender, recipient, hashlock, 1, None);
+        assert_eq!(refund.to(), sender);
+        assert_eq!(refund.amount(), U256::ZERO);
+        assert_eq!(refund.sequence(), Some(1));
+    }
+
+    #[test]
+    fn test_assets() {
+        let issuer = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let usd = asset_id(issuer, "USD");
+
+        let issue = Tx::issue_asset(issuer, "USD", to, U256::from(100), 0, None);
+        assert_eq!(issue.asset(), usd);
+        assert_eq!((issue.to(), issue.amount()), (to, U256::from(100)));
+
+        // Native transfers keep their hash, other assets are part of it
+        let transfer = Tx::new(issuer, to, U256::from(10), None);
+        let tx_hash = transfer.tx_hash();
+        assert_eq!(transfer.clone().with_asset(NATIVE_ASSET).tx_hash(), tx_hash);
+        let usd_transfer = transfer.with_asset(usd);
+        assert_eq!(usd_transfer.asset(), usd);
+        assert_ne!(usd_transfer.tx_hash(), tx_hash);
+        let decoded: Tx =
+            serde_json::from_str(&serde_json::to_string(&usd_transfer).unwrap()).unwrap();
+        assert_eq!(decoded.tx_hash(), usd_transfer.tx_hash());
+    }
+
+    #[test]
+    fn test_with_signature() {
+        let signer = PrivateKeySigner::random();
+        let tx = Tx::open_channel(signer.address(), Address::ZERO, U256::from(100), 10, None);
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+
+        let signed = tx.clone().with_signature(signature);
+        assert_eq!(signed.signature(), Some(signature));
+        // The signature is not part of the hash it signs
+        assert_eq!(signed.tx_hash(), tx.tx_hash());
+    }
 }
@@ -0,0 +1,65 @@
+// the signature checks that need nothing but the tx, the same the vm runs before it looks at the
+// state. The owners of a multisig account are in the state, so a multisig transfer only has to
+// carry a signature here
+
+use alloc::string::{String, ToString};
+
+use crate::tx::Tx;
+
+pub fn verify_signature(tx: &Tx) -> Result<(), String> {
+    check_signature(tx, |tx| tx.is_signed_by(tx.from()))
+}
+
+// `signed_by_sender` does the recovery, the signature cache answers it from what it recovered
+// before
+pub(crate) fn check_signature(
+    tx: &Tx,
+    signed_by_sender: impl FnOnce(&Tx) -> bool,
+) -> Result<(), String> {
+    if tx.signature().is_none() {
+        return Err("Transaction has no signature".to_string());
+    }
+
+    if matches!(tx, Tx::MultisigTransfer { .. }) {
+        return Ok(());
+    }
+
+    // transfers may be signed over their EIP-712 typed data instead of their hash
+    if !signed_by_sender(tx) {
+        return Err("Transaction signature is invalid".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+
+    #[test]
+    fn test_verify_signature() {
+        let signer = PrivateKeySigner::random();
+        let tx = Tx::new(
+            signer.address(),
+            Address::repeat_byte(2),
+            U256::from(10),
+            None,
+        );
+        assert!(verify_signature(&tx).is_err());
+
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        assert_eq!(
+            verify_signature(&tx.clone().with_signature(signature)),
+            Ok(())
+        );
+
+        // Signed by someone other than the sender
+        let other = PrivateKeySigner::random()
+            .sign_message_sync(&tx.tx_hash())
+            .unwrap();
+        assert!(verify_signature(&tx.with_signature(other)).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use alloy::primitives::U256;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use state::{account::Account, memory::MemoryState, state::State};
+use tx::tx::Tx;
+use vm::VM;
+
+// every transfer has its own sender so the batch only differs from a loop of `execute` in
+// how signatures are checked
+fn signed_transfers(size: usize) -> Vec<Tx> {
+    let to = PrivateKeySigner::random().address();
+
+    (0..size)
+        .map(|_| {
+            let signer = PrivateKeySigner::random();
+            let from = signer.address();
+            let tx = Tx::new(from, to, U256::from(1), None);
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            Tx::new(from, to, U256::from(1), Some(signature))
+        })
+        .collect()
+}
+
+fn funded_vm(txs: &[Tx]) -> VM {
+    let mut state = MemoryState::new();
+    for tx in txs {
+        state
+            .update_account(&tx.from(), Account::new(tx.from(), U256::from(1000)))
+            .unwrap();
+    }
+    VM::new(Box::new(state))
+}
+
+fn bench_execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execute");
+
+    for size in [100, 1000] {
+        let txs = signed_transfers(size);
+
+        group.bench_with_input(BenchmarkId::new("sequential", size), &txs, |b, txs| {
+            b.iter_batched(
+                || funded_vm(txs),
+                |mut vm| {
+                    for tx in txs {
+                        let _ = vm.execute(tx);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", size), &txs, |b, txs| {
+            b.iter_batched(
+                || funded_vm(txs),
+                |mut vm| vm.execute_batch(txs),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_execute);
+criterion_main!(benches);
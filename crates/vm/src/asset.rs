@@ -0,0 +1,273 @@
+// assets other than the native token, see tx::asset. An issuer creates its asset by issuing it and
+// is the only one who can issue more. Transfers of an asset move its balances the way native
+// transfers do, the fee is still paid in the native token. Dust rules and spending limits are in
+// the native token and don't apply, a policy's allowlist does. Nor is an asset's supply kept next
+// to the native one
+
+use alloy::primitives::{Address, U256};
+use tx::{
+    asset::{asset_id, AssetId},
+    log::Log,
+    tx::Tx,
+};
+
+use crate::{VMError, VM};
+
+pub const MAX_SYMBOL_LEN: usize = 12;
+
+fn invalid(message: String) -> VMError {
+    VMError::InvalidTransaction(message)
+}
+
+impl VM {
+    pub(crate) fn apply_issue_asset(
+        &mut self,
+        from: Address,
+        symbol: &str,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        if symbol.is_empty()
+            || symbol.len() > MAX_SYMBOL_LEN
+            || !symbol.bytes().all(|byte| byte.is_ascii_alphanumeric())
+        {
+            return Err(invalid(format!(
+                "Asset symbol has to be 1 to {} ascii letters or digits",
+                MAX_SYMBOL_LEN
+            )));
+        }
+        if amount.is_zero() {
+            return Err(invalid("Issued amount must be more than 0".to_string()));
+        }
+
+        let asset = asset_id(from, symbol);
+        let issuer = self.next_sequence(from, sequence)?;
+        // the credit is checked before anything is written, a failed issue leaves the issuer's
+        // sequence number alone
+        let mut recipient = if to == from {
+            issuer.clone()
+        } else {
+            self.account_or_new(to)
+        };
+        let balance = recipient
+            .asset_balance(&asset)
+            .checked_add(amount)
+            .ok_or(VMError::BalanceOverflow(to))?;
+        recipient.set_asset_balance(asset, balance);
+        if to != from {
+            self.write_account(from, issuer)?;
+        }
+        self.write_account(to, recipient)?;
+        self.logs
+            .push(Log::asset_transfer(&asset, Address::ZERO, to, amount));
+        Ok(())
+    }
+
+    // checked before anything is written, like a native transfer
+    pub(crate) fn apply_asset_transfer(&mut self, tx: &Tx, asset: AssetId) -> Result<(), VMError> {
+        let (from, to, amount) = (tx.from(), tx.to(), tx.amount());
+        let Some(mut sender) = self.state.get_account(&from) else {
+            return Err(invalid(
+                "Transaction sender account does not exist".to_string(),
+            ));
+        };
+        let Some(native_left) = sender.balance().checked_sub(U256::from(tx.fee())) else {
+            return Err(invalid(
+                "Transaction sender account does not have enough balance for the fee".to_string(),
+            ));
+        };
+        let Some(sender_left) = sender.asset_balance(&asset).checked_sub(amount) else {
+            return Err(invalid(format!(
+                "Transaction sender account does not have enough of asset {}",
+                asset
+            )));
+        };
+        if let Some(sequence) = tx.sequence() {
+            sender.set_sequence(sequence + 1);
+        }
+        sender.set_balance(native_left);
+
+        if to == from {
+            self.write_account(from, sender)?;
+        } else {
+            let mut recipient = self.account_or_new(to);
+            let credited = recipient
+                .asset_balance(&asset)
+                .checked_add(amount)
+                .ok_or(VMError::BalanceOverflow(to))?;
+            sender.set_asset_balance(asset, sender_left);
+            // an account left with nothing is dropped, it is the same as one that never existed
+            if sender.is_empty() {
+                self.state
+                    .remove_account(&from)
+                    .map_err(|_| invalid("Failed to update the account".to_string()))?;
+            } else {
+                self.write_account(from, sender)?;
+            }
+            recipient.set_asset_balance(asset, credited);
+            self.write_account(to, recipient)?;
+        }
+
+        if tx.fee() > 0 {
+            let supply = self
+                .state
+                .total_supply()
+                .saturating_sub(U256::from(tx.fee()));
+            self.state
+                .set_total_supply(supply)
+                .map_err(|_| invalid("Failed to update the total supply".to_string()))?;
+        }
+        self.logs
+            .push(Log::asset_transfer(&asset, from, to, amount));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use state::{account::Account, memory::MemoryState, state::State};
+    use tx::asset::{token_address, NATIVE_ASSET};
+
+    use crate::test_utils::sign;
+
+    fn asset_balance(vm: &VM, address: Address, asset: &AssetId) -> U256 {
+        vm.state()
+            .get_account(&address)
+            .map_or(U256::ZERO, |account| account.asset_balance(asset))
+    }
+
+    #[test]
+    fn test_issue_and_transfer() {
+        let issuer = PrivateKeySigner::random();
+        let holder = PrivateKeySigner::random();
+        let to = Address::repeat_byte(3);
+        let mut state = MemoryState::new();
+        state
+            .update_account(
+                &holder.address(),
+                Account::new(holder.address(), U256::from(10)),
+            )
+            .unwrap();
+        state.set_total_supply(U256::from(10)).unwrap();
+        let mut vm = VM::new(Box::new(state));
+        let usd = asset_id(issuer.address(), "USD");
+
+        let issue = sign(
+            &issuer,
+            Tx::issue_asset(
+                issuer.address(),
+                "USD",
+                holder.address(),
+                U256::from(100),
+                0,
+                None,
+            ),
+        );
+        let receipt = vm.execute_batch(&[issue]).remove(0).ok().unwrap();
+        assert_eq!(receipt.logs()[0].address, token_address(&usd));
+        assert_eq!(receipt.asset(), usd);
+        assert_eq!(asset_balance(&vm, holder.address(), &usd), U256::from(100));
+        // The native balance and supply are left alone
+        assert_eq!(
+            asset_balance(&vm, holder.address(), &NATIVE_ASSET),
+            U256::from(10)
+        );
+        assert_eq!(vm.state().total_supply(), U256::from(10));
+
+        let transfer = |amount: u64, sequence: u64| {
+            sign(
+                &holder,
+                Tx::transfer_order(holder.address(), to, U256::from(amount), sequence, None)
+                    .with_asset(usd)
+                    .with_fee(1),
+            )
+        };
+        assert!(vm.execute(&transfer(101, 0)).is_err());
+        assert!(vm.execute(&transfer(40, 0)).is_ok());
+        assert_eq!(asset_balance(&vm, holder.address(), &usd), U256::from(60));
+        assert_eq!(asset_balance(&vm, to, &usd), U256::from(40));
+        // The fee is paid in the native token and burned
+        assert_eq!(
+            asset_balance(&vm, holder.address(), &NATIVE_ASSET),
+            U256::from(9)
+        );
+        assert_eq!(vm.state().total_supply(), U256::from(9));
+        assert_eq!(
+            vm.state()
+                .get_account(&holder.address())
+                .unwrap()
+                .sequence(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_failed_issue_changes_nothing() {
+        let issuer = PrivateKeySigner::random();
+        let holder = Address::repeat_byte(2);
+        let mut vm = VM::new(Box::new(MemoryState::new()));
+        let issue = |amount: U256, sequence: u64| {
+            sign(
+                &issuer,
+                Tx::issue_asset(issuer.address(), "USD", holder, amount, sequence, None),
+            )
+        };
+
+        assert!(vm.execute(&issue(U256::MAX, 0)).is_ok());
+        let root = vm.state().state_root();
+        // The holder's balance would overflow, so the issuer's sequence number isn't used up
+        assert!(vm.execute(&issue(U256::from(1), 1)).is_err());
+        assert_eq!(vm.state().state_root(), root);
+        assert_eq!(
+            vm.state()
+                .get_account(&issuer.address())
+                .unwrap()
+                .sequence(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_only_the_issuer_issues() {
+        let issuer = PrivateKeySigner::random();
+        let mut vm = VM::new(Box::new(MemoryState::new()));
+
+        // Someone else issuing "USD" issues an asset of their own
+        let other = PrivateKeySigner::random();
+        let issue = Tx::issue_asset(
+            other.address(),
+            "USD",
+            other.address(),
+            U256::from(5),
+            0,
+            None,
+        );
+        assert!(vm.execute(&sign(&other, issue)).is_ok());
+        let usd = asset_id(issuer.address(), "USD");
+        assert_eq!(asset_balance(&vm, other.address(), &usd), U256::ZERO);
+        let forged = Tx::issue_asset(
+            issuer.address(),
+            "USD",
+            other.address(),
+            U256::from(5),
+            0,
+            None,
+        );
+        assert!(vm.execute(&sign(&other, forged)).is_err());
+
+        for symbol in ["", "U$D", "WAYTOOLONGSYMBOL"] {
+            let issue = Tx::issue_asset(
+                issuer.address(),
+                symbol,
+                issuer.address(),
+                U256::from(5),
+                0,
+                None,
+            );
+            assert!(vm.execute(&sign(&issuer, issue)).is_err());
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// the bridge to the primary chain: deposits into its contract are credited by the bridge
+// operator, redemptions take funds out for the committee to certify, see the bridge crate. Both
+// change the supply the way mints and burns do
+
+use alloy::primitives::{Address, U256};
+
+use crate::{VMError, VM};
+
+impl VM {
+    pub(crate) fn fund_from_primary(
+        &mut self,
+        operator: Address,
+        to: Address,
+        amount: U256,
+        deposit_nonce: u64,
+    ) -> Result<(), VMError> {
+        if self.bridge_operator != Some(operator) {
+            return Err(VMError::InvalidTransaction(
+                "Transaction sender is not the bridge operator of this chain".to_string(),
+            ));
+        }
+        self.create_funds(operator, to, amount, deposit_nonce)
+    }
+
+    pub(crate) fn redeem_to_primary(
+        &mut self,
+        from: Address,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        if self.state.get_account(&from).is_none() {
+            return Err(VMError::InvalidTransaction(
+                "Transaction sender account does not exist".to_string(),
+            ));
+        }
+        self.destroy_funds(from, amount, sequence)
+    }
+}
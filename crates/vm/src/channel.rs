@@ -0,0 +1,344 @@
+// state transitions of payment channels, see state::channel for the lifecycle
+
+use alloy::primitives::{Address, PrimitiveSignature, B256, U256};
+use state::channel::Channel;
+use tx::channel::{channel_id, ChannelUpdate};
+use tx::tx::Tx;
+
+use crate::{VMError, VM};
+
+fn invalid(message: &str) -> VMError {
+    VMError::InvalidTransaction(message.to_string())
+}
+
+impl VM {
+    pub(crate) fn open_channel(
+        &mut self,
+        tx: &Tx,
+        payer: Address,
+        payee: Address,
+        deposit: U256,
+        challenge_period: u64,
+    ) -> Result<(), VMError> {
+        let id = channel_id(tx);
+        if self.state.get_channel(&id).is_some() {
+            return Err(invalid("Channel already exists"));
+        }
+
+        let payer_account = self
+            .state
+            .get_account(&payer)
+            .ok_or_else(|| invalid("Transaction sender account does not exist"))?;
+        let Some(balance) = payer_account.balance().checked_sub(deposit) else {
+            return Err(invalid(
+                "Transaction sender account does not have enough balance",
+            ));
+        };
+        let mut updated_payer = payer_account.clone();
+        updated_payer.set_balance(balance);
+        self.state
+            .update_account(&payer, updated_payer)
+            .map_err(|_| invalid("Failed to lock the channel deposit"))?;
+        self.update_channel(
+            &id,
+            Some(Channel::new(payer, payee, deposit, challenge_period)),
+        )
+    }
+
+    pub(crate) fn close_channel(
+        &mut self,
+        payee: Address,
+        payer: Address,
+        id: &B256,
+        amount: U256,
+        update_signature: &PrimitiveSignature,
+    ) -> Result<(), VMError> {
+        let channel = self.channel(id)?;
+        if channel.payee() != payee || channel.payer() != payer {
+            return Err(invalid("Channel can only be closed by its payee"));
+        }
+        if amount > channel.deposit() {
+            return Err(invalid("Channel update exceeds the deposit"));
+        }
+
+        let update = ChannelUpdate::new(*id, amount);
+        if update.signer(update_signature) != Some(payer) {
+            return Err(invalid("Channel update is not signed by the payer"));
+        }
+
+        // both payouts are checked before anything is written
+        let refund = channel.deposit() - amount;
+        if payee == payer {
+            self.check_credit(payee, channel.deposit())?;
+        } else {
+            self.check_credit(payee, amount)?;
+            self.check_credit(payer, refund)?;
+        }
+        self.update_channel(id, None)?;
+        self.credit(payee, amount)?;
+        self.credit(payer, refund)
+    }
+
+    pub(crate) fn start_channel_timeout(
+        &mut self,
+        payer: Address,
+        payee: Address,
+        id: &B256,
+    ) -> Result<(), VMError> {
+        let mut channel = self.channel(id)?;
+        if channel.payer() != payer || channel.payee() != payee {
+            return Err(invalid("Channel timeout can only be started by its payer"));
+        }
+        if channel.expires_at().is_some() {
+            return Err(invalid("Channel timeout already started"));
+        }
+
+        channel.start_timeout(self.current_block);
+        self.update_channel(id, Some(channel))
+    }
+
+    pub(crate) fn claim_channel_timeout(
+        &mut self,
+        payer: Address,
+        payee: Address,
+        id: &B256,
+    ) -> Result<(), VMError> {
+        let channel = self.channel(id)?;
+        if channel.payer() != payer || channel.payee() != payee {
+            return Err(invalid("Channel timeout can only be claimed by its payer"));
+        }
+        if !channel.is_expired(self.current_block) {
+            return Err(invalid("Channel challenge period has not passed"));
+        }
+
+        self.check_credit(payer, channel.deposit())?;
+        self.update_channel(id, None)?;
+        self.credit(payer, channel.deposit())
+    }
+
+    fn channel(&self, id: &B256) -> Result<Channel, VMError> {
+        self.state
+            .get_channel(id)
+            .ok_or_else(|| invalid("Channel does not exist"))
+    }
+
+    fn update_channel(&mut self, id: &B256, channel: Option<Channel>) -> Result<(), VMError> {
+        self.state
+            .update_channel(id, channel)
+            .map_err(|_| invalid("Failed to update the channel"))
+    }
+
+    pub(crate) fn check_credit(&self, address: Address, amount: U256) -> Result<(), VMError> {
+        let balance = self
+            .state
+            .get_account(&address)
+            .map_or(U256::ZERO, |account| account.balance());
+        match balance.checked_add(amount) {
+            Some(_) => Ok(()),
+            None => Err(VMError::BalanceOverflow(address)),
+        }
+    }
+
+    pub(crate) fn credit(&mut self, address: Address, amount: U256) -> Result<(), VMError> {
+        let mut account = self.account_or_new(address);
+        let balance = account
+            .balance()
+            .checked_add(amount)
+            .ok_or(VMError::BalanceOverflow(address))?;
+        account.set_balance(balance);
+        self.state
+            .update_account(&address, account)
+            .map_err(|_| invalid("Failed to credit the account"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use state::{account::Account, memory::MemoryState, state::State};
+
+    use crate::test_utils::{balance, sign};
+
+    struct Parties {
+        payer: PrivateKeySigner,
+        payee: PrivateKeySigner,
+    }
+
+    // a vm where the payer has 100 and a channel with a deposit of 60 open to the payee
+    fn open(challenge_period: u64) -> (VM, Parties, B256) {
+        let parties = Parties {
+            payer: PrivateKeySigner::random(),
+            payee: PrivateKeySigner::random(),
+        };
+        let payer = parties.payer.address();
+
+        let mut state = MemoryState::new();
+        state
+            .update_account(&payer, Account::new(payer, U256::from(100)))
+            .unwrap();
+        let mut vm = VM::new(Box::new(state));
+
+        let tx = sign(
+            &parties.payer,
+            Tx::open_channel(
+                payer,
+                parties.payee.address(),
+                U256::from(60),
+                challenge_period,
+                None,
+            ),
+        );
+        assert!(vm.execute(&tx).is_ok());
+        (vm, parties, channel_id(&tx))
+    }
+
+    fn close(parties: &Parties, id: B256, amount: u64, update_signer: &PrivateKeySigner) -> Tx {
+        let amount = U256::from(amount);
+        let update = ChannelUpdate::new(id, amount);
+        let update_signature = update_signer
+            .sign_message_sync(update.hash().as_slice())
+            .unwrap();
+        sign(
+            &parties.payee,
+            Tx::close_channel(
+                parties.payee.address(),
+                parties.payer.address(),
+                id,
+                amount,
+                update_signature,
+                None,
+            ),
+        )
+    }
+
+    fn message(result: Result<(), VMError>) -> String {
+        match result {
+            Err(error) => error.reason(),
+            Ok(()) => panic!("expected the tx to fail"),
+        }
+    }
+
+    #[test]
+    fn test_open_channel_locks_deposit() {
+        let (vm, parties, id) = open(10);
+
+        assert_eq!(balance(&vm, parties.payer.address()), U256::from(40));
+        let channel = vm.state().get_channel(&id).unwrap();
+        assert_eq!(channel.deposit(), U256::from(60));
+        assert_eq!(channel.payee(), parties.payee.address());
+    }
+
+    #[test]
+    fn test_open_channel_insufficient_balance() {
+        let (mut vm, parties, _) = open(10);
+        let payer = parties.payer.address();
+
+        let tx = sign(
+            &parties.payer,
+            Tx::open_channel(payer, parties.payee.address(), U256::from(41), 10, None),
+        );
+        assert!(message(vm.execute(&tx)).contains("does not have enough balance"));
+        assert_eq!(balance(&vm, payer), U256::from(40));
+    }
+
+    #[test]
+    fn test_cooperative_close() {
+        let (mut vm, parties, id) = open(10);
+
+        let tx = close(&parties, id, 25, &parties.payer);
+        assert!(vm.execute(&tx).is_ok());
+
+        assert_eq!(balance(&vm, parties.payee.address()), U256::from(25));
+        assert_eq!(balance(&vm, parties.payer.address()), U256::from(75));
+        assert!(vm.state().get_channel(&id).is_none());
+
+        // A closed channel can't be settled twice, neither by a replay nor by another close
+        assert!(message(vm.execute(&tx)).contains("already executed"));
+        let again = close(&parties, id, 20, &parties.payer);
+        assert!(message(vm.execute(&again)).contains("does not exist"));
+    }
+
+    #[test]
+    fn test_close_rejects_bad_updates() {
+        let (mut vm, parties, id) = open(10);
+
+        let forged = close(&parties, id, 25, &parties.payee);
+        assert!(message(vm.execute(&forged)).contains("not signed by the payer"));
+
+        let too_much = close(&parties, id, 61, &parties.payer);
+        assert!(message(vm.execute(&too_much)).contains("exceeds the deposit"));
+
+        assert!(vm.state().get_channel(&id).is_some());
+    }
+
+    #[test]
+    fn test_unilateral_close_after_challenge_period() {
+        let (mut vm, parties, id) = open(10);
+        let (payer, payee) = (parties.payer.address(), parties.payee.address());
+        let claim = sign(
+            &parties.payer,
+            Tx::claim_channel_timeout(payer, payee, id, None),
+        );
+
+        // Nothing to claim before the timeout is started
+        assert!(message(vm.execute(&claim)).contains("has not passed"));
+
+        vm.set_current_block(5);
+        let start = sign(
+            &parties.payer,
+            Tx::start_channel_timeout(payer, payee, id, None),
+        );
+        assert!(vm.execute(&start).is_ok());
+        let restart = sign(
+            &parties.payer,
+            Tx::start_channel_timeout(payer, payee, id, None).with_chain_id(1),
+        );
+        assert!(message(vm.execute(&restart)).contains("already started"));
+
+        vm.set_current_block(14);
+        assert!(message(vm.execute(&claim)).contains("has not passed"));
+
+        vm.set_current_block(15);
+        assert!(vm.execute(&claim).is_ok());
+        assert_eq!(balance(&vm, payer), U256::from(100));
+        assert!(vm.state().get_channel(&id).is_none());
+    }
+
+    #[test]
+    fn test_payee_can_close_during_challenge_period() {
+        let (mut vm, parties, id) = open(10);
+        let (payer, payee) = (parties.payer.address(), parties.payee.address());
+
+        let start = sign(
+            &parties.payer,
+            Tx::start_channel_timeout(payer, payee, id, None),
+        );
+        assert!(vm.execute(&start).is_ok());
+
+        vm.set_current_block(9);
+        assert!(vm.execute(&close(&parties, id, 30, &parties.payer)).is_ok());
+        assert_eq!(balance(&vm, payee), U256::from(30));
+
+        vm.set_current_block(10);
+        let claim = sign(
+            &parties.payer,
+            Tx::claim_channel_timeout(payer, payee, id, None),
+        );
+        assert!(message(vm.execute(&claim)).contains("does not exist"));
+        assert_eq!(balance(&vm, payer), U256::from(70));
+    }
+
+    #[test]
+    fn test_only_the_payer_can_time_out() {
+        let (mut vm, parties, id) = open(10);
+        let (payer, payee) = (parties.payer.address(), parties.payee.address());
+
+        let start = sign(
+            &parties.payee,
+            Tx::start_channel_timeout(payee, payer, id, None),
+        );
+        assert!(message(vm.execute(&start)).contains("started by its payer"));
+    }
+}
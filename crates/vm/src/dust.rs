@@ -0,0 +1,84 @@
+// keeps transfers from scattering balances too small to be worth an account: with a minimum
+// balance set, a transfer can't leave its sender or its recipient holding less than it, though
+// either can end up with nothing
+
+use alloy::primitives::U256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DustMode {
+    // the transfer fails
+    #[default]
+    Reject,
+    // what would be left to the sender goes along to the recipient
+    Sweep,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DustPolicy {
+    // 0 turns the policy off
+    pub min_balance: U256,
+    pub mode: DustMode,
+}
+
+impl DustPolicy {
+    pub fn new(min_balance: U256, mode: DustMode) -> Self {
+        Self { min_balance, mode }
+    }
+
+    // what is swept from the sender to the recipient on top of the amount, given what each would
+    // be left with after the transfer
+    pub fn settle(&self, sender_left: U256, recipient_left: U256) -> Result<U256, String> {
+        let swept = if self.is_dust(sender_left) {
+            match self.mode {
+                DustMode::Reject => {
+                    return Err(format!(
+                        "Transaction would leave the sender below the minimum balance of {}",
+                        self.min_balance
+                    ))
+                }
+                DustMode::Sweep => sender_left,
+            }
+        } else {
+            U256::ZERO
+        };
+
+        if self.is_dust(recipient_left.saturating_add(swept)) {
+            return Err(format!(
+                "Transaction would leave the recipient below the minimum balance of {}",
+                self.min_balance
+            ));
+        }
+        Ok(swept)
+    }
+
+    fn is_dust(&self, balance: U256) -> bool {
+        !balance.is_zero() && balance < self.min_balance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settle() {
+        let settle = |policy: &DustPolicy, sender_left: u64, recipient_left: u64| {
+            policy
+                .settle(U256::from(sender_left), U256::from(recipient_left))
+                .map(|swept| swept.to::<u64>())
+        };
+        assert_eq!(settle(&DustPolicy::default(), 1, 1), Ok(0));
+
+        let reject = DustPolicy::new(U256::from(10), DustMode::Reject);
+        assert_eq!(settle(&reject, 10, 10), Ok(0));
+        assert_eq!(settle(&reject, 0, 25), Ok(0));
+        assert!(settle(&reject, 9, 25).unwrap_err().contains("sender"));
+        assert!(settle(&reject, 25, 9).unwrap_err().contains("recipient"));
+
+        let sweep = DustPolicy::new(U256::from(10), DustMode::Sweep);
+        assert_eq!(settle(&sweep, 9, 25), Ok(9));
+        // the swept dust can lift the recipient over the minimum
+        assert_eq!(settle(&sweep, 4, 6), Ok(4));
+        assert!(settle(&sweep, 2, 6).is_err());
+    }
+}
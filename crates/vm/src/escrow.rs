@@ -0,0 +1,280 @@
+// escrows, see state::escrow. Creating one moves the funds out of the payer's balance into the
+// escrow kept on its account, where they count as locked like a channel deposit. Settling it pays
+// them to the payee or back to the payer, whoever settles it pays for it with their sequence number
+
+use alloy::primitives::{Address, B256, U256};
+use state::escrow::Escrow;
+use tx::{log::Log, tx::Tx};
+
+use crate::{VMError, VM};
+
+// bounds what a single account can keep locked at once
+pub const MAX_ESCROWS_PER_ACCOUNT: usize = 16;
+
+fn invalid(message: String) -> VMError {
+    VMError::InvalidTransaction(message)
+}
+
+impl VM {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn apply_escrow_create(
+        &mut self,
+        tx: &Tx,
+        from: Address,
+        to: Address,
+        arbiter: Address,
+        amount: U256,
+        timeout: u64,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        if amount.is_zero() {
+            return Err(invalid("Escrowed amount must be more than 0".to_string()));
+        }
+        if timeout == 0 {
+            return Err(invalid(
+                "Escrow timeout must be at least one block".to_string(),
+            ));
+        }
+        if to == from {
+            return Err(invalid("Escrow payee can't be its payer".to_string()));
+        }
+        // an arbiter that is also a party could settle it in their own favour
+        if arbiter == from || arbiter == to {
+            return Err(invalid(
+                "Escrow arbiter can't be its payer or payee".to_string(),
+            ));
+        }
+        if self.state.get_account(&from).is_none() {
+            return Err(invalid(
+                "Transaction sender account does not exist".to_string(),
+            ));
+        }
+
+        let mut account = self.next_sequence(from, sequence)?;
+        if account.escrows().len() >= MAX_ESCROWS_PER_ACCOUNT {
+            return Err(invalid(format!(
+                "Account already has {} escrows",
+                MAX_ESCROWS_PER_ACCOUNT
+            )));
+        }
+        let Some(balance) = account.balance().checked_sub(amount) else {
+            return Err(invalid(
+                "Transaction sender account does not have enough balance".to_string(),
+            ));
+        };
+
+        let mut escrows = account.escrows().to_vec();
+        escrows.push(Escrow {
+            id: B256::from_slice(&tx.tx_hash()),
+            to,
+            arbiter,
+            amount,
+            expires_at: self.current_block.saturating_add(timeout),
+        });
+        account.set_balance(balance);
+        account.set_escrows(escrows);
+        self.write_account(from, account)
+    }
+
+    // pays the escrow out to the payee
+    pub(crate) fn apply_escrow_release(
+        &mut self,
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        self.settle_escrow(from, payer, escrow_id, sequence, true)
+    }
+
+    // returns the escrow to the payer
+    pub(crate) fn apply_escrow_refund(
+        &mut self,
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        self.settle_escrow(from, payer, escrow_id, sequence, false)
+    }
+
+    // everything is checked before anything is written
+    fn settle_escrow(
+        &mut self,
+        from: Address,
+        payer: Address,
+        escrow_id: B256,
+        sequence: u64,
+        release: bool,
+    ) -> Result<(), VMError> {
+        let sender = self.next_sequence(from, sequence)?;
+        let mut payer_account = if from == payer {
+            sender.clone()
+        } else {
+            self.state
+                .get_account(&payer)
+                .ok_or_else(|| invalid(format!("No escrow {} locked by {}", escrow_id, payer)))?
+        };
+        let mut escrows = payer_account.escrows().to_vec();
+        let Some(index) = escrows.iter().position(|escrow| escrow.id == escrow_id) else {
+            return Err(invalid(format!(
+                "No escrow {} locked by {}",
+                escrow_id, payer
+            )));
+        };
+        let escrow = escrows.remove(index);
+
+        let recipient = if release {
+            if !escrow.can_release(payer, from) {
+                return Err(invalid(
+                    "Escrow can only be released by its arbiter or payer".to_string(),
+                ));
+            }
+            escrow.to
+        } else {
+            if !escrow.can_refund(payer, from, self.current_block) {
+                return Err(invalid(format!(
+                    "Escrow can only be refunded by its arbiter or payee before block {}",
+                    escrow.expires_at
+                )));
+            }
+            payer
+        };
+        self.check_credit(recipient, escrow.amount)?;
+
+        if from != payer {
+            self.write_account(from, sender)?;
+        }
+        payer_account.set_escrows(escrows);
+        self.write_account(payer, payer_account)?;
+        self.credit(recipient, escrow.amount)?;
+        if release {
+            self.logs
+                .push(Log::transfer(payer, escrow.to, escrow.amount));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use state::{account::Account, memory::MemoryState, state::State, supply::TotalSupply};
+
+    use crate::test_utils::{balance, sign};
+
+    struct Parties {
+        payer: PrivateKeySigner,
+        payee: PrivateKeySigner,
+        arbiter: PrivateKeySigner,
+    }
+
+    // the payer starts with 100 and escrows 40 for the payee, timing out after 10 blocks
+    fn setup() -> (VM, Parties, B256) {
+        let parties = Parties {
+            payer: PrivateKeySigner::random(),
+            payee: PrivateKeySigner::random(),
+            arbiter: PrivateKeySigner::random(),
+        };
+        let payer = parties.payer.address();
+        let mut state = MemoryState::new();
+        state
+            .update_account(&payer, Account::new(payer, U256::from(100)))
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+        let mut vm = VM::new(Box::new(state));
+
+        let create = sign(
+            &parties.payer,
+            Tx::escrow_create(
+                payer,
+                parties.payee.address(),
+                parties.arbiter.address(),
+                U256::from(40),
+                10,
+                0,
+                None,
+            ),
+        );
+        assert!(vm.execute(&create).is_ok());
+        (vm, parties, B256::from_slice(&create.tx_hash()))
+    }
+
+    #[test]
+    fn test_release() {
+        let (mut vm, parties, id) = setup();
+        let payer = parties.payer.address();
+        let payee = parties.payee.address();
+        assert_eq!(balance(&vm, payer), U256::from(60));
+        let supply = TotalSupply::of(vm.state());
+        assert_eq!(supply.locked, U256::from(40));
+        assert!(supply.is_balanced());
+
+        // The payee can't release it to themselves
+        let by_payee = sign(
+            &parties.payee,
+            Tx::escrow_release(payee, payer, id, 0, None),
+        );
+        assert!(vm.execute(&by_payee).is_err());
+
+        let release = sign(
+            &parties.arbiter,
+            Tx::escrow_release(parties.arbiter.address(), payer, id, 0, None),
+        );
+        let receipt = vm.execute_batch(&[release]).remove(0).ok().unwrap();
+        assert_eq!(
+            receipt.logs(),
+            &[Log::transfer(payer, payee, U256::from(40))]
+        );
+        assert_eq!(balance(&vm, payee), U256::from(40));
+        assert!(vm.state().get_account(&payer).unwrap().escrows().is_empty());
+        assert!(TotalSupply::of(vm.state()).is_balanced());
+
+        // Settled once only
+        let refund = sign(
+            &parties.arbiter,
+            Tx::escrow_refund(parties.arbiter.address(), payer, id, 1, None),
+        );
+        assert!(vm.execute(&refund).is_err());
+    }
+
+    #[test]
+    fn test_refund() {
+        let (mut vm, parties, id) = setup();
+        let payer = parties.payer.address();
+
+        // The payer has to wait for the timeout to take it back alone
+        let early = sign(&parties.payer, Tx::escrow_refund(payer, payer, id, 1, None));
+        assert!(vm.execute(&early).is_err());
+        vm.set_current_block(10);
+        let refund = sign(&parties.payer, Tx::escrow_refund(payer, payer, id, 1, None));
+        assert!(vm.execute(&refund).is_ok());
+        assert_eq!(balance(&vm, payer), U256::from(100));
+        assert_eq!(vm.state().get_account(&payer).unwrap().sequence(), 2);
+        assert!(vm.state().get_account(&payer).unwrap().escrows().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_escrows() {
+        let (mut vm, parties, _) = setup();
+        let payer = parties.payer.address();
+        let payee = parties.payee.address();
+        let create = |arbiter: Address, amount: u64, timeout: u64| {
+            sign(
+                &parties.payer,
+                Tx::escrow_create(payer, payee, arbiter, U256::from(amount), timeout, 1, None),
+            )
+        };
+        let arbiter = parties.arbiter.address();
+
+        assert!(vm.execute(&create(arbiter, 61, 10)).is_err());
+        assert!(vm.execute(&create(arbiter, 0, 10)).is_err());
+        assert!(vm.execute(&create(arbiter, 10, 0)).is_err());
+        assert!(vm.execute(&create(payer, 10, 10)).is_err());
+        assert!(vm.execute(&create(payee, 10, 10)).is_err());
+        // Nothing was taken by the ones that failed
+        assert_eq!(balance(&vm, payer), U256::from(60));
+        assert!(vm.execute(&create(arbiter, 60, 10)).is_ok());
+    }
+}
@@ -0,0 +1,381 @@
+// hash time-locked contracts, see state::htlc. Locking moves the funds out of the sender's balance
+// into an htlc kept on its own account, where they count as locked until the recipient claims
+// them with the secret or the sender takes them back past the timelock. Nobody but the sender can
+// fill its htlcs, and none is locked for longer than MAX_HTLC_DURATION
+
+use alloy::primitives::{Address, B256, U256};
+use sha2::{Digest, Sha256};
+use state::htlc::Htlc;
+use tx::log::Log;
+
+use crate::{policy::DAY_IN_BLOCKS, VMError, VM};
+
+// bounds how many htlcs a single account can keep locked at once
+pub const MAX_HTLCS_PER_ACCOUNT: usize = 16;
+
+// how far past the current block a timelock can be, in blocks
+pub const MAX_HTLC_DURATION: u64 = 7 * DAY_IN_BLOCKS;
+
+fn invalid(message: String) -> VMError {
+    VMError::InvalidTransaction(message)
+}
+
+// the hashlock a preimage opens
+pub fn hashlock(preimage: &B256) -> B256 {
+    B256::from_slice(&Sha256::digest(preimage.as_slice()))
+}
+
+impl VM {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn apply_htlc_lock(
+        &mut self,
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        timelock: u64,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        if amount.is_zero() {
+            return Err(invalid("Locked amount must be more than 0".to_string()));
+        }
+        if recipient == from {
+            return Err(invalid("Htlc recipient can't be its sender".to_string()));
+        }
+        if timelock <= self.current_block {
+            return Err(invalid(format!(
+                "Htlc timelock {} has already been reached",
+                timelock
+            )));
+        }
+        if timelock - self.current_block > MAX_HTLC_DURATION {
+            return Err(invalid(format!(
+                "Htlc timelock {} is more than {} blocks away",
+                timelock, MAX_HTLC_DURATION
+            )));
+        }
+        if self.state.get_account(&from).is_none() {
+            return Err(invalid(
+                "Transaction sender account does not exist".to_string(),
+            ));
+        }
+
+        let mut account = self.next_sequence(from, sequence)?;
+        if account.htlcs().len() >= MAX_HTLCS_PER_ACCOUNT {
+            return Err(invalid(format!(
+                "Account already has {} htlcs",
+                MAX_HTLCS_PER_ACCOUNT
+            )));
+        }
+        // a claim has to find a single htlc for the secret it reveals
+        if account
+            .htlcs()
+            .iter()
+            .any(|htlc| htlc.recipient == recipient && htlc.hashlock == hashlock)
+        {
+            return Err(invalid(format!(
+                "Sender already has an htlc locked for {} under {}",
+                recipient, hashlock
+            )));
+        }
+        let Some(balance) = account.balance().checked_sub(amount) else {
+            return Err(invalid(
+                "Transaction sender account does not have enough balance".to_string(),
+            ));
+        };
+
+        let mut htlcs = account.htlcs().to_vec();
+        htlcs.push(Htlc {
+            recipient,
+            hashlock,
+            amount,
+            timelock,
+        });
+        account.set_balance(balance);
+        account.set_htlcs(htlcs);
+        self.write_account(from, account)
+    }
+
+    pub(crate) fn apply_htlc_claim(
+        &mut self,
+        from: Address,
+        sender: Address,
+        preimage: &B256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        let hashlock = hashlock(preimage);
+        let no_htlc = || {
+            invalid(format!(
+                "No htlc locked by {} for the sender under {}",
+                sender, hashlock
+            ))
+        };
+        let mut locker = self.state.get_account(&sender).ok_or_else(no_htlc)?;
+        let mut htlcs = locker.htlcs().to_vec();
+        let index = htlcs
+            .iter()
+            .position(|htlc| htlc.recipient == from && htlc.hashlock == hashlock)
+            .ok_or_else(no_htlc)?;
+        let htlc = htlcs.remove(index);
+        // past it the sender may already have refunded the other side of the swap
+        if htlc.is_expired(self.current_block) {
+            return Err(invalid(format!(
+                "Htlc timelock {} has been reached, it can only be refunded",
+                htlc.timelock
+            )));
+        }
+        let mut account = self.next_sequence(from, sequence)?;
+        let balance = account
+            .balance()
+            .checked_add(htlc.amount)
+            .ok_or(VMError::BalanceOverflow(from))?;
+
+        locker.set_htlcs(htlcs);
+        self.write_account(sender, locker)?;
+        account.set_balance(balance);
+        self.write_account(from, account)?;
+        self.logs.push(Log::transfer(sender, from, htlc.amount));
+        Ok(())
+    }
+
+    pub(crate) fn apply_htlc_refund(
+        &mut self,
+        from: Address,
+        recipient: Address,
+        hashlock: B256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        let mut account = self.next_sequence(from, sequence)?;
+        let mut htlcs = account.htlcs().to_vec();
+        let index = htlcs
+            .iter()
+            .position(|htlc| htlc.recipient == recipient && htlc.hashlock == hashlock)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "No htlc locked by the sender for {} under {}",
+                    recipient, hashlock
+                ))
+            })?;
+        let htlc = htlcs.remove(index);
+        if !htlc.is_expired(self.current_block) {
+            return Err(invalid(format!(
+                "Htlc can't be refunded before its timelock {}",
+                htlc.timelock
+            )));
+        }
+        let balance = account
+            .balance()
+            .checked_add(htlc.amount)
+            .ok_or(VMError::BalanceOverflow(from))?;
+
+        account.set_balance(balance);
+        account.set_htlcs(htlcs);
+        self.write_account(from, account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use state::{account::Account, memory::MemoryState, state::State, supply::TotalSupply};
+    use tx::tx::Tx;
+
+    use crate::test_utils::{balance, sign};
+
+    // the sender starts with 100 and locks 40 for the recipient until block 10
+    fn setup(
+        sender: &PrivateKeySigner,
+        recipient: Address,
+        preimage: &B256,
+    ) -> (VM, Result<(), VMError>) {
+        let from = sender.address();
+        let mut state = MemoryState::new();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+        let mut vm = VM::new(Box::new(state));
+
+        let lock = Tx::htlc_lock(
+            from,
+            recipient,
+            hashlock(preimage),
+            10,
+            U256::from(40),
+            0,
+            None,
+        );
+        let result = vm.execute(&sign(sender, lock));
+        (vm, result)
+    }
+
+    #[test]
+    fn test_hashlock() {
+        // sha256 of 32 zero bytes, the hash a Bitcoin or Ethereum counterparty checks too
+        assert_eq!(
+            hashlock(&B256::ZERO).to_string(),
+            "0x66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925"
+        );
+    }
+
+    #[test]
+    fn test_claim() {
+        let sender = PrivateKeySigner::random();
+        let recipient = PrivateKeySigner::random();
+        let preimage = B256::repeat_byte(7);
+        let (mut vm, locked) = setup(&sender, recipient.address(), &preimage);
+        assert!(locked.is_ok());
+        assert_eq!(balance(&vm, sender.address()), U256::from(60));
+        let supply = TotalSupply::of(vm.state());
+        assert_eq!(supply.locked, U256::from(40));
+        assert!(supply.is_balanced());
+
+        let wrong = Tx::htlc_claim(
+            recipient.address(),
+            sender.address(),
+            B256::repeat_byte(8),
+            0,
+            None,
+        );
+        assert!(vm.execute(&sign(&recipient, wrong)).is_err());
+        let claim = Tx::htlc_claim(recipient.address(), sender.address(), preimage, 0, None);
+        let receipt = vm
+            .execute_batch(&[sign(&recipient, claim)])
+            .remove(0)
+            .ok()
+            .unwrap();
+        assert_eq!(
+            receipt.logs(),
+            &[Log::transfer(
+                sender.address(),
+                recipient.address(),
+                U256::from(40)
+            )]
+        );
+        assert_eq!(balance(&vm, recipient.address()), U256::from(40));
+        assert!(TotalSupply::of(vm.state()).is_balanced());
+
+        // Nothing left to refund
+        vm.set_current_block(10);
+        let refund = Tx::htlc_refund(
+            sender.address(),
+            recipient.address(),
+            hashlock(&preimage),
+            1,
+            None,
+        );
+        assert!(vm.execute(&sign(&sender, refund)).is_err());
+    }
+
+    #[test]
+    fn test_refund() {
+        let sender = PrivateKeySigner::random();
+        let recipient = PrivateKeySigner::random();
+        let preimage = B256::repeat_byte(7);
+        let (mut vm, _) = setup(&sender, recipient.address(), &preimage);
+        let refund = |sequence| {
+            sign(
+                &sender,
+                Tx::htlc_refund(
+                    sender.address(),
+                    recipient.address(),
+                    hashlock(&preimage),
+                    sequence,
+                    None,
+                ),
+            )
+        };
+
+        vm.set_current_block(9);
+        assert!(vm.execute(&refund(1)).is_err());
+        vm.set_current_block(10);
+        // The recipient is too late to claim
+        let claim = Tx::htlc_claim(recipient.address(), sender.address(), preimage, 0, None);
+        assert!(vm.execute(&sign(&recipient, claim)).is_err());
+        assert!(vm.execute(&refund(1)).is_ok());
+        assert_eq!(balance(&vm, sender.address()), U256::from(100));
+        // The htlc was kept on the sender's account, the recipient never needed one
+        assert!(vm.state().get_account(&recipient.address()).is_none());
+        assert!(TotalSupply::of(vm.state()).is_balanced());
+    }
+
+    #[test]
+    fn test_invalid_locks() {
+        let sender = PrivateKeySigner::random();
+        let recipient = Address::repeat_byte(2);
+        let preimage = B256::repeat_byte(7);
+        let (mut vm, _) = setup(&sender, recipient, &preimage);
+        let from = sender.address();
+        let lock = |to: Address, hashlock: B256, timelock: u64, amount: u64| {
+            sign(
+                &sender,
+                Tx::htlc_lock(from, to, hashlock, timelock, U256::from(amount), 1, None),
+            )
+        };
+
+        // Already locked under that hash
+        assert!(vm
+            .execute(&lock(recipient, hashlock(&preimage), 10, 1))
+            .is_err());
+        let other = B256::repeat_byte(1);
+        assert!(vm.execute(&lock(recipient, other, 0, 1)).is_err());
+        assert!(vm
+            .execute(&lock(recipient, other, MAX_HTLC_DURATION + 1, 1))
+            .is_err());
+        assert!(vm.execute(&lock(recipient, other, 10, 61)).is_err());
+        assert!(vm.execute(&lock(from, other, 10, 1)).is_err());
+        assert!(vm.execute(&lock(recipient, other, 10, 60)).is_ok());
+    }
+
+    #[test]
+    fn test_locks_are_the_senders() {
+        let sender = PrivateKeySigner::random();
+        let recipient = PrivateKeySigner::random();
+        let preimage = B256::repeat_byte(7);
+        let (mut vm, _) = setup(&sender, recipient.address(), &preimage);
+
+        // Someone else filling its htlcs for the recipient, one under the same hash, only uses up
+        // its own
+        let other = PrivateKeySigner::random();
+        vm.state_mut()
+            .update_account(
+                &other.address(),
+                Account::new(other.address(), U256::from(100)),
+            )
+            .unwrap();
+        vm.state_mut().set_total_supply(U256::from(200)).unwrap();
+        let lock = |signer: &PrivateKeySigner, hashlock: B256, sequence: u64| {
+            let tx = Tx::htlc_lock(
+                signer.address(),
+                recipient.address(),
+                hashlock,
+                10,
+                U256::from(1),
+                sequence,
+                None,
+            );
+            sign(signer, tx)
+        };
+        for sequence in 0..MAX_HTLCS_PER_ACCOUNT as u64 {
+            let hashlock = B256::repeat_byte(sequence as u8);
+            assert!(vm.execute(&lock(&other, hashlock, sequence)).is_ok());
+        }
+        let full = lock(&other, B256::repeat_byte(99), MAX_HTLCS_PER_ACCOUNT as u64);
+        assert!(vm.execute(&full).is_err());
+        assert!(vm.execute(&lock(&sender, B256::repeat_byte(99), 1)).is_ok());
+
+        let claim = Tx::htlc_claim(recipient.address(), sender.address(), preimage, 0, None);
+        assert!(vm.execute(&sign(&recipient, claim)).is_ok());
+        assert_eq!(balance(&vm, recipient.address()), U256::from(40));
+        assert_eq!(
+            vm.state()
+                .get_account(&other.address())
+                .unwrap()
+                .htlcs()
+                .len(),
+            MAX_HTLCS_PER_ACCOUNT
+        );
+        assert!(TotalSupply::of(vm.state()).is_balanced());
+    }
+}
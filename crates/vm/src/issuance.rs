@@ -0,0 +1,135 @@
+// mint and burn, the only txs that change how much exists besides the bridge's. Only the chain's
+// issuer sends them, its signature is checked like any sender's and its sequence number keeps
+// them from being replayed. Their logs are the ERC-20 ones, a transfer from or to the zero address
+
+use alloy::primitives::{Address, U256};
+use state::account::Account;
+use tx::log::Log;
+
+use crate::{VMError, VM};
+
+fn invalid(message: &str) -> VMError {
+    VMError::InvalidTransaction(message.to_string())
+}
+
+impl VM {
+    pub(crate) fn apply_mint(
+        &mut self,
+        issuer: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        if self.issuer != Some(issuer) {
+            return Err(invalid(
+                "Transaction sender is not the issuer of this chain",
+            ));
+        }
+        self.create_funds(issuer, to, amount, sequence)
+    }
+
+    pub(crate) fn apply_burn(
+        &mut self,
+        issuer: Address,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        if self.issuer != Some(issuer) {
+            return Err(invalid(
+                "Transaction sender is not the issuer of this chain",
+            ));
+        }
+        self.destroy_funds(issuer, amount, sequence)
+    }
+
+    // credits `to` with funds that didn't exist, on behalf of `sender` whose sequence number moves
+    // on. Whether the sender may do so is up to the caller
+    pub(crate) fn create_funds(
+        &mut self,
+        sender: Address,
+        to: Address,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        let sender_account = self.next_sequence(sender, sequence)?;
+        let mut to_account = if to == sender {
+            sender_account.clone()
+        } else {
+            self.account_or_new(to)
+        };
+        let balance = to_account
+            .balance()
+            .checked_add(amount)
+            .ok_or(VMError::BalanceOverflow(to))?;
+        let supply = self
+            .state
+            .total_supply()
+            .checked_add(amount)
+            .ok_or_else(|| invalid("Minting would take the total supply past U256::MAX"))?;
+        to_account.set_balance(balance);
+
+        if to != sender {
+            self.write_account(sender, sender_account)?;
+        }
+        self.write_account(to, to_account)?;
+        self.write_total_supply(supply)?;
+        self.logs.push(Log::transfer(Address::ZERO, to, amount));
+        Ok(())
+    }
+
+    // takes `amount` out of the sender's account and out of the supply
+    pub(crate) fn destroy_funds(
+        &mut self,
+        sender: Address,
+        amount: U256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        let mut sender_account = self.next_sequence(sender, sequence)?;
+        let Some(balance) = sender_account.balance().checked_sub(amount) else {
+            return Err(invalid(
+                "Transaction sender account does not have enough balance",
+            ));
+        };
+        // the supply counts every balance, it can only fall short if it was set by hand
+        let Some(supply) = self.state.total_supply().checked_sub(amount) else {
+            return Err(invalid("Burning more than the total supply"));
+        };
+        sender_account.set_balance(balance);
+
+        self.write_account(sender, sender_account)?;
+        self.write_total_supply(supply)?;
+        self.logs.push(Log::transfer(sender, Address::ZERO, amount));
+        Ok(())
+    }
+
+    // the sender's account with its sequence number moved past `sequence`, once it is checked to
+    // be the next one
+    pub(crate) fn next_sequence(&self, from: Address, sequence: u64) -> Result<Account, VMError> {
+        let mut account = self.account_or_new(from);
+        if sequence != account.sequence() {
+            return Err(VMError::InvalidTransaction(format!(
+                "Transaction sequence number {} does not match the sender's {}",
+                sequence,
+                account.sequence()
+            )));
+        }
+        account.set_sequence(sequence + 1);
+        Ok(account)
+    }
+
+    pub(crate) fn write_account(
+        &mut self,
+        address: Address,
+        account: Account,
+    ) -> Result<(), VMError> {
+        self.state
+            .update_account(&address, account)
+            .map_err(|_| invalid("Failed to update the account"))
+    }
+
+    fn write_total_supply(&mut self, supply: U256) -> Result<(), VMError> {
+        self.state
+            .set_total_supply(supply)
+            .map_err(|_| invalid("Failed to update the total supply"))
+    }
+}
@@ -1,52 +1,671 @@
-use alloy::primitives::Address;
-use state::{account::Account, state::State};
-use tx::tx::Tx;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256, U256};
+use bytes::Bytes;
+use committee::{certificate::Certificate, committee::Committee};
+use dust::DustPolicy;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use simulator::Simulator;
+use state::{
+    account::Account,
+    pending::PendingState,
+    state::{State, SEEN_FOR_GOOD},
+    supply::TotalSupply,
+};
+use tx::{
+    asset::{is_native, AssetId},
+    fee::FeeSchedule,
+    log::Log,
+    signature_cache::SignatureCache,
+    tx::Tx,
+};
+use validator::{DefaultValidator, TxValidator};
+
+pub mod asset;
+mod bridge;
+mod channel;
+pub mod dust;
+pub mod escrow;
+pub mod htlc;
+mod issuance;
+mod netting;
+pub mod policy;
+#[cfg(feature = "rent")]
+pub mod rent;
+pub mod schedule;
+#[cfg(feature = "parallel")]
+pub mod scheduler;
+pub mod simulator;
+pub mod validator;
+
+// how many blocks an executed tx is remembered for, it can't be executed again before. Older ones
+// are forgotten so the set stays bounded, a tx carrying a sequence number is still protected by it.
+// One without a sequence number has nothing else stopping a replay, so it is remembered for good
+pub const REPLAY_WINDOW: u64 = 100_000;
+
+// what a tx executed in `block` is recorded as among the seen txs
+pub(crate) fn seen_record(tx: &Tx, block: u64) -> u64 {
+    match tx.sequence() {
+        Some(_) => block,
+        None => block | SEEN_FOR_GOOD,
+    }
+}
+
+// the block a tx recorded as `seen` was executed in, if that stops it from executing at `block`
+pub(crate) fn executed_before(seen: Option<u64>, block: u64) -> Option<u64> {
+    let seen = seen?;
+    let executed = seen & !SEEN_FOR_GOOD;
+    (seen & SEEN_FOR_GOOD != 0 || block.saturating_sub(executed) < REPLAY_WINDOW)
+        .then_some(executed)
+}
 
 pub enum VMError {
     InvalidTransaction(String),
+    // crediting the account would take its balance past U256::MAX
+    BalanceOverflow(Address),
+}
+
+impl VMError {
+    pub fn reason(&self) -> String {
+        match self {
+            Self::InvalidTransaction(reason) => reason.clone(),
+            Self::BalanceOverflow(address) => format!("Balance of {} would overflow", address),
+        }
+    }
+}
+
+// Receipt records a transfer that was applied to the state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    tx_hash: Bytes,
+    from: Address,
+    to: Address,
+    amount: U256,
+    // the asset `amount` is in, fees are always in the native token
+    asset: AssetId,
+    // what the sender was charged up front and what it got back, kept apart so accounting can
+    // tell the fee offered from the fee paid. Fees are flat for now, the whole fee is charged and
+    // nothing comes back
+    fee_charged: u64,
+    fee_refunded: u64,
+    // emitted while the tx executed, in order
+    logs: Vec<Log>,
+}
+
+impl Receipt {
+    pub fn new(tx: &Tx) -> Self {
+        Self {
+            tx_hash: tx.tx_hash(),
+            from: tx.from(),
+            to: tx.to(),
+            amount: tx.amount(),
+            asset: tx.asset(),
+            fee_charged: tx.fee(),
+            fee_refunded: 0,
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn with_logs(mut self, logs: Vec<Log>) -> Self {
+        self.logs = logs;
+        self
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    pub fn tx_hash(&self) -> &Bytes {
+        &self.tx_hash
+    }
+
+    pub fn from(&self) -> Address {
+        self.from
+    }
+
+    pub fn to(&self) -> Address {
+        self.to
+    }
+
+    pub fn amount(&self) -> U256 {
+        self.amount
+    }
+
+    pub fn asset(&self) -> AssetId {
+        self.asset
+    }
+
+    pub fn fee_charged(&self) -> u64 {
+        self.fee_charged
+    }
+
+    pub fn fee_refunded(&self) -> u64 {
+        self.fee_refunded
+    }
+
+    // what the tx actually cost its sender on top of the amount
+    pub fn effective_fee(&self) -> u64 {
+        self.fee_charged - self.fee_refunded
+    }
+}
+
+// fields are only computed when the span is enabled, hashing every tx costs nothing otherwise
+fn tx_span(tx: &Tx) -> tracing::Span {
+    tracing::debug_span!(
+        "execute_tx",
+        tx_hash = %alloy::primitives::hex::encode_prefixed(tx.tx_hash()),
+        from = %tx.from(),
+    )
+}
+
+// rejected txs are expected, a mempool full of bad ones shouldn't flood the logs above debug
+fn trace_outcome<T>(result: &Result<T, VMError>) {
+    match result {
+        Ok(_) => tracing::debug!("tx applied"),
+        Err(error) => tracing::debug!(reason = %error.reason(), "tx rejected"),
+    }
 }
 
 pub struct VM {
     state: Box<dyn State>,
+    // the block being executed, channel timeouts are counted in blocks
+    current_block: u64,
+    dust_policy: DustPolicy,
+    fee_schedule: FeeSchedule,
+    // txs for another chain are rejected, None accepts any
+    chain_id: Option<u64>,
+    // the only sender of mints and burns, None refuses them all
+    issuer: Option<Address>,
+    // credits deposits made on the primary chain, None refuses them
+    bridge_operator: Option<Address>,
+    // consulted in order before a tx is applied, see validator.rs
+    validators: Vec<Arc<dyn TxValidator>>,
+    // whether the first of them is still the DefaultValidator the VM started with
+    default_validator: bool,
+    #[cfg(feature = "rent")]
+    rent: Option<rent::Rent>,
+    // what the tx being executed has emitted so far, they end up in its receipt
+    logs: Vec<Log>,
 }
 
 impl VM {
     pub fn new(state: Box<dyn State>) -> Self {
-        Self { state }
+        Self {
+            state,
+            current_block: 0,
+            dust_policy: DustPolicy::default(),
+            fee_schedule: FeeSchedule::default(),
+            chain_id: None,
+            issuer: None,
+            bridge_operator: None,
+            validators: vec![Arc::new(DefaultValidator::default())],
+            default_validator: true,
+            #[cfg(feature = "rent")]
+            rent: None,
+            logs: Vec::new(),
+        }
+    }
+
+    // adds a validator next to the ones the VM already has
+    pub fn with_validator(mut self, validator: impl TxValidator + 'static) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    // replaces every validator, including the default one
+    pub fn with_validators(mut self, validators: Vec<Arc<dyn TxValidator>>) -> Self {
+        self.validators = validators;
+        self.default_validator = false;
+        self
+    }
+
+    // the default validator recovers signers through `signature_cache`, shared with the mempool so
+    // a tx it admitted isn't recovered again. Validators set with with_validators are left alone,
+    // give them DefaultValidator::with_signature_cache instead
+    pub fn with_signature_cache(mut self, signature_cache: Arc<SignatureCache>) -> Self {
+        if self.default_validator {
+            self.validators[0] = Arc::new(DefaultValidator::with_signature_cache(signature_cache));
+        }
+        self
+    }
+
+    pub fn validators(&self) -> &[Arc<dyn TxValidator>] {
+        &self.validators
+    }
+
+    pub fn with_dust_policy(mut self, dust_policy: DustPolicy) -> Self {
+        self.dust_policy = dust_policy;
+        self
+    }
+
+    pub fn dust_policy(&self) -> DustPolicy {
+        self.dust_policy
+    }
+
+    pub fn with_fee_schedule(mut self, fee_schedule: FeeSchedule) -> Self {
+        self.fee_schedule = fee_schedule;
+        self
+    }
+
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        self.fee_schedule
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    pub fn with_issuer(mut self, issuer: Address) -> Self {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    pub fn issuer(&self) -> Option<Address> {
+        self.issuer
+    }
+
+    pub fn with_bridge_operator(mut self, operator: Address) -> Self {
+        self.bridge_operator = Some(operator);
+        self
+    }
+
+    pub fn bridge_operator(&self) -> Option<Address> {
+        self.bridge_operator
+    }
+
+    #[cfg(feature = "rent")]
+    pub fn with_rent(mut self, policy: rent::RentPolicy) -> Self {
+        self.rent = Some(rent::Rent::new(policy));
+        self
+    }
+
+    #[cfg(feature = "rent")]
+    pub fn rent(&self) -> Option<&rent::Rent> {
+        self.rent.as_ref()
+    }
+
+    // hibernates the accounts that have been idle too long as of the current block, nothing
+    // without a rent policy. The records are what revives them
+    #[cfg(feature = "rent")]
+    pub fn hibernate_dormant(&mut self) -> Vec<rent::Hibernated> {
+        match &mut self.rent {
+            Some(rent) => rent.hibernate_dormant(self.state.as_mut(), self.current_block),
+            None => Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "rent")]
+    pub fn revive(&mut self, record: &rent::Hibernated) -> Result<(), VMError> {
+        let rent = self.rent.as_mut().ok_or_else(|| {
+            VMError::InvalidTransaction("Rent is not enabled on this chain".to_string())
+        })?;
+        rent.revive(self.state.as_mut(), record, self.current_block)
+            .map_err(VMError::InvalidTransaction)
+    }
+
+    // the total supply against where it should all be, balances hibernated by rent count as held
+    pub fn total_supply(&self) -> TotalSupply {
+        let mut supply = TotalSupply::of(self.state.as_ref());
+        supply.held = supply.held.saturating_add(self.hibernated_balance());
+        supply
+    }
+
+    #[cfg(not(feature = "rent"))]
+    fn hibernated_balance(&self) -> U256 {
+        U256::ZERO
+    }
+
+    #[cfg(feature = "rent")]
+    fn hibernated_balance(&self) -> U256 {
+        self.rent
+            .as_ref()
+            .map_or(U256::ZERO, |rent| rent.hibernated_balance())
+    }
+
+    pub fn current_block(&self) -> u64 {
+        self.current_block
+    }
+
+    // every REPLAY_WINDOW blocks the txs that fell out of the window are forgotten, at the same
+    // blocks on every node
+    pub fn set_current_block(&mut self, block: u64) {
+        self.current_block = block;
+        if block > 0 && block.is_multiple_of(REPLAY_WINDOW) {
+            self.forget_seen_txs();
+        }
+    }
+
+    fn forget_seen_txs(&mut self) {
+        for (hash, block) in self.state.seen_txs() {
+            if block & SEEN_FOR_GOOD == 0 && block + REPLAY_WINDOW <= self.current_block {
+                // forgetting is only ever a removal
+                let _ = self.state.update_seen_tx(&hash, None);
+            }
+        }
     }
 
     // TODO: we need to make sure that we can rollback the state if the transaction fails
     pub fn execute(&mut self, tx: &Tx) -> Result<(), VMError> {
-        let from = tx.from();
-        let to = tx.to();
-        let amount = tx.amount();
+        let _span = tx_span(tx).entered();
+        let result = self
+            .verify_chain_id(tx)
+            .and_then(|()| self.verify_fee(tx))
+            .and_then(|()| Self::validate_tx(&self.validators, tx))
+            .and_then(|()| self.execute_verified(tx));
+        trace_outcome(&result);
+        result
+    }
 
-        let signature = tx.signature();
+    // signatures are checked in parallel since ECDSA recovery is most of the cost of a transfer,
+    // state changes are then applied one by one in the order of `txs`. Without the `parallel`
+    // feature the signatures are checked one by one too
+    pub fn execute_batch(&mut self, txs: &[Tx]) -> Vec<Result<Receipt, VMError>> {
+        let validators = &self.validators;
+        #[cfg(feature = "parallel")]
+        let txs_iter = txs.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let txs_iter = txs.iter();
+        let verified: Vec<Result<(), VMError>> = txs_iter
+            .map(|tx| Self::validate_tx(validators, tx))
+            .collect();
 
-        if signature.is_none() {
-            return Err(VMError::InvalidTransaction(
-                "Transaction has no signature".to_string(),
-            ));
+        txs.iter()
+            .zip(verified)
+            .map(|(tx, verified)| {
+                let _span = tx_span(tx).entered();
+                let result = self
+                    .verify_chain_id(tx)
+                    .and_then(|()| self.verify_fee(tx))
+                    .and(verified)
+                    .and_then(|()| self.execute_verified(tx))
+                    .map(|()| Receipt::new(tx).with_logs(std::mem::take(&mut self.logs)));
+                trace_outcome(&result);
+                result
+            })
+            .collect()
+    }
+
+    // executes `tx` as it would be and undoes it, the state is left as it was. The checks on the
+    // tx alone are skipped, signatures among them: a tx being tried out isn't signed yet
+    pub fn simulate(&mut self, tx: &Tx) -> Result<Receipt, VMError> {
+        let _span = tx_span(tx).entered();
+        let snapshot = self.state.snapshot();
+        let result = self
+            .verify_chain_id(tx)
+            .and_then(|()| self.verify_fee(tx))
+            .and_then(|()| self.execute_verified(tx))
+            .map(|()| Receipt::new(tx).with_logs(std::mem::take(&mut self.logs)));
+        self.state
+            .revert_to(snapshot)
+            .expect("the snapshot was just taken");
+        trace_outcome(&result);
+        result
+    }
+
+    // a simulator with the rules of this vm, see simulator.rs
+    pub fn simulator(&self, pending: PendingState) -> Simulator {
+        Simulator::new(pending)
+            .with_dust_policy(self.dust_policy)
+            .with_fee_schedule(self.fee_schedule)
+            .with_chain_id(self.chain_id)
+            .with_issuer(self.issuer)
+            .with_bridge_operator(self.bridge_operator)
+            .with_validators(self.validators.clone())
+    }
+
+    // what's left once the chain id and signatures are checked
+    fn execute_verified(&mut self, tx: &Tx) -> Result<(), VMError> {
+        // a failed tx leaves nothing behind, neither does one whose receipt was not asked for
+        self.logs.clear();
+        let hash = B256::from_slice(tx.tx_hash().as_ref());
+        self.verify_not_seen(&hash)?;
+        self.validate(tx)?;
+        let spending = self.check_policies(tx)?;
+        self.apply(tx)?;
+        self.record_spending(spending)?;
+        self.state
+            .update_seen_tx(&hash, Some(seen_record(tx, self.current_block)))
+            .map_err(|_| {
+                VMError::InvalidTransaction("Failed to record the transaction".to_string())
+            })
+    }
+
+    // a plain transfer has no sequence number to stop a replay, and a certificate could be
+    // settled twice, so the exact same tx is refused, see REPLAY_WINDOW
+    fn verify_not_seen(&self, hash: &B256) -> Result<(), VMError> {
+        match executed_before(self.state.seen_tx(hash), self.current_block) {
+            Some(block) => Err(VMError::InvalidTransaction(format!(
+                "Transaction was already executed in block {}",
+                block
+            ))),
+            None => Ok(()),
         }
+    }
 
-        let signature = signature.unwrap();
+    // takes the validators alone so a batch can share them across threads
+    fn validate_tx(validators: &[Arc<dyn TxValidator>], tx: &Tx) -> Result<(), VMError> {
+        validators
+            .iter()
+            .try_for_each(|validator| validator.validate_tx(tx))
+    }
 
-        let recovered_address = signature.recover_address_from_msg(tx.tx_hash());
+    fn validate(&self, tx: &Tx) -> Result<(), VMError> {
+        self.validators
+            .iter()
+            .try_for_each(|validator| validator.validate(tx, self.state.as_ref()))
+    }
 
-        // TODO: ideally we need to wrap this error in VM error
-        if recovered_address.is_err() {
-            return Err(VMError::InvalidTransaction(
-                "Transaction signature is invalid".to_string(),
-            ));
+    // txs signed before chain ids don't have one, they are still accepted
+    fn verify_chain_id(&self, tx: &Tx) -> Result<(), VMError> {
+        match (self.chain_id, tx.chain_id()) {
+            (Some(expected), Some(chain_id)) if chain_id != expected => {
+                Err(VMError::InvalidTransaction(format!(
+                    "Transaction is for chain {}, this is chain {}",
+                    chain_id, expected
+                )))
+            }
+            _ => Ok(()),
         }
+    }
 
-        let recovered_address = recovered_address.unwrap();
+    fn verify_fee(&self, tx: &Tx) -> Result<(), VMError> {
+        self.fee_schedule
+            .check(tx)
+            .map_err(VMError::InvalidTransaction)
+    }
 
-        if recovered_address != from {
+    // the owners of a multisig are in the state, the default validator checks them
+    pub(crate) fn verify_signature(tx: &Tx) -> Result<(), VMError> {
+        tx::verify::verify_signature(tx).map_err(VMError::InvalidTransaction)
+    }
+
+    // the account at `address`, or a new empty one created in the current block
+    pub(crate) fn account_or_new(&self, address: Address) -> Account {
+        self.state.get_account(&address).unwrap_or_else(|| {
+            Account::new(address, U256::ZERO).with_created_at(self.current_block)
+        })
+    }
+
+    #[cfg(not(feature = "rent"))]
+    fn apply(&mut self, tx: &Tx) -> Result<(), VMError> {
+        self.apply_tx(tx)
+    }
+
+    // with rent, hibernated senders are refused and both ends of an applied tx count as active
+    #[cfg(feature = "rent")]
+    fn apply(&mut self, tx: &Tx) -> Result<(), VMError> {
+        let Some(rent) = &self.rent else {
+            return self.apply_tx(tx);
+        };
+        if rent.is_hibernated(&tx.from()) {
             return Err(VMError::InvalidTransaction(
-                "Transaction signature is invalid".to_string(),
+                "Transaction sender account is hibernated, revive it first".to_string(),
             ));
         }
+        self.apply_tx(tx)?;
+        if let Some(rent) = &mut self.rent {
+            rent.touch(tx.from(), self.current_block);
+            rent.touch(tx.to(), self.current_block);
+        }
+        Ok(())
+    }
+
+    fn apply_tx(&mut self, tx: &Tx) -> Result<(), VMError> {
+        match tx {
+            Tx::Transfer { asset, .. } if !is_native(asset) => {
+                self.apply_asset_transfer(tx, *asset)
+            }
+            Tx::Transfer { .. } => self.apply_transfer(tx),
+            Tx::OpenChannel {
+                from,
+                to,
+                amount,
+                challenge_period,
+                ..
+            } => self.open_channel(tx, *from, *to, *amount, *challenge_period),
+            Tx::CloseChannel {
+                from,
+                to,
+                channel_id,
+                amount,
+                update_signature,
+                ..
+            } => self.close_channel(*from, *to, channel_id, *amount, update_signature),
+            Tx::StartChannelTimeout {
+                from,
+                to,
+                channel_id,
+                ..
+            } => self.start_channel_timeout(*from, *to, channel_id),
+            Tx::ClaimChannelTimeout {
+                from,
+                to,
+                channel_id,
+                ..
+            } => self.claim_channel_timeout(*from, *to, channel_id),
+            Tx::Settlement {
+                window,
+                intents,
+                obligations,
+                ..
+            } => self.apply_settlement(*window, intents, obligations),
+            // the owners' signatures are checked by the default validator
+            Tx::MultisigTransfer { .. } => self.apply_transfer(tx),
+            Tx::Mint {
+                from,
+                to,
+                amount,
+                sequence,
+                ..
+            } => self.apply_mint(*from, *to, *amount, *sequence),
+            Tx::Burn {
+                from,
+                amount,
+                sequence,
+                ..
+            } => self.apply_burn(*from, *amount, *sequence),
+            Tx::FundFromPrimary {
+                from,
+                to,
+                amount,
+                deposit_nonce,
+                ..
+            } => self.fund_from_primary(*from, *to, *amount, *deposit_nonce),
+            Tx::RedeemToPrimary {
+                from,
+                amount,
+                sequence,
+                ..
+            } => self.redeem_to_primary(*from, *amount, *sequence),
+            Tx::SetPolicy {
+                from,
+                max_per_tx,
+                daily_limit,
+                allowlist,
+                sequence,
+                ..
+            } => self.apply_set_policy(*from, *max_per_tx, *daily_limit, allowlist, *sequence),
+            Tx::Schedule {
+                from,
+                to,
+                amount,
+                interval,
+                end,
+                ..
+            } => self.apply_schedule(tx, *from, *to, *amount, *interval, *end),
+            Tx::CancelSchedule {
+                from,
+                schedule_id,
+                sequence,
+                ..
+            } => self.apply_cancel_schedule(*from, *schedule_id, *sequence),
+            Tx::EscrowCreate {
+                from,
+                to,
+                arbiter,
+                amount,
+                timeout,
+                sequence,
+                ..
+            } => self.apply_escrow_create(tx, *from, *to, *arbiter, *amount, *timeout, *sequence),
+            Tx::EscrowRelease {
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                ..
+            } => self.apply_escrow_release(*from, *payer, *escrow_id, *sequence),
+            Tx::EscrowRefund {
+                from,
+                payer,
+                escrow_id,
+                sequence,
+                ..
+            } => self.apply_escrow_refund(*from, *payer, *escrow_id, *sequence),
+            Tx::HtlcLock {
+                from,
+                recipient,
+                hashlock,
+                timelock,
+                amount,
+                sequence,
+                ..
+            } => self.apply_htlc_lock(*from, *recipient, *hashlock, *timelock, *amount, *sequence),
+            Tx::HtlcClaim {
+                from,
+                sender,
+                preimage,
+                sequence,
+                ..
+            } => self.apply_htlc_claim(*from, *sender, preimage, *sequence),
+            Tx::HtlcRefund {
+                from,
+                recipient,
+                hashlock,
+                sequence,
+                ..
+            } => self.apply_htlc_refund(*from, *recipient, *hashlock, *sequence),
+            Tx::IssueAsset {
+                from,
+                symbol,
+                to,
+                amount,
+                sequence,
+                ..
+            } => self.apply_issue_asset(*from, symbol, *to, *amount, *sequence),
+        }
+    }
+
+    fn apply_transfer(&mut self, tx: &Tx) -> Result<(), VMError> {
+        let from = tx.from();
+        let to = tx.to();
+        let amount = tx.amount();
 
         let from_account = self.state.get_account(&from);
 
@@ -59,14 +678,54 @@ impl VM {
         let from_account = from_account.unwrap();
         let from_balance = from_account.balance();
 
-        if from_balance < amount {
+        // the fee leaves the sender on top of the amount and isn't credited to anyone
+        let Some(sender_left) = amount
+            .checked_add(U256::from(tx.fee()))
+            .and_then(|debit| from_balance.checked_sub(debit))
+        else {
             return Err(VMError::InvalidTransaction(
                 "Transaction sender account does not have enough balance".to_string(),
             ));
+        };
+
+        // a transfer order carries the sender's sequence number, the default validator has
+        // checked it is the next one
+        let mut updated_from_account = from_account.clone();
+        if let Some(sequence) = tx.sequence() {
+            updated_from_account.set_sequence(sequence + 1);
+        }
+
+        let recipient_balance = if to == from {
+            sender_left
+        } else {
+            self.state
+                .get_account(&to)
+                .map_or(U256::ZERO, |account| account.balance())
+        };
+        let swept = self
+            .dust_policy
+            .settle(sender_left, recipient_balance.saturating_add(amount))
+            .map_err(VMError::InvalidTransaction)?;
+        let amount = amount
+            .checked_add(swept)
+            .ok_or(VMError::BalanceOverflow(to))?;
+        // checked before anything is written, so a transfer that fails leaves both accounts as
+        // they were
+        let credited = if to == from {
+            sender_left.checked_add(amount - swept)
+        } else {
+            recipient_balance.checked_add(amount)
         }
+        .ok_or(VMError::BalanceOverflow(to))?;
+        updated_from_account.set_balance(sender_left - swept);
 
-        let updated_from_account = Account::new(from, from_balance - amount);
-        match self.state.update_account(&from, updated_from_account) {
+        // an account left with nothing is dropped, it is the same as one that never existed
+        let update_result = if updated_from_account.is_empty() {
+            self.state.remove_account(&from)
+        } else {
+            self.state.update_account(&from, updated_from_account)
+        };
+        match update_result {
             Ok(_) => (),
             Err(_) => {
                 return Err(VMError::InvalidTransaction(
@@ -75,36 +734,48 @@ impl VM {
             }
         };
 
-        let to_account_exists = self.state.get_account(&to).is_none();
-
-        if to_account_exists {
-            let to_account = Account::new(to, amount);
-            let update_result = self.state.update_account(&to, to_account);
+        let mut to_account = self.account_or_new(to);
+        to_account.set_balance(credited);
+        if self.state.update_account(&to, to_account).is_err() {
+            return Err(VMError::InvalidTransaction(
+                "Transaction sender account does not have enough balance".to_string(),
+            ));
+        }
 
-            if update_result.is_err() {
-                return Err(VMError::InvalidTransaction(
-                    "Transaction sender account does not have enough balance".to_string(),
-                ));
-            };
-        } else {
-            let to_account = self.state.get_account(&to).unwrap();
-            let to_balance = to_account.balance();
+        // the burned fee leaves the supply with it. A supply short of it was set by hand
+        if tx.fee() > 0 {
+            let supply = self
+                .state
+                .total_supply()
+                .saturating_sub(U256::from(tx.fee()));
+            self.state.set_total_supply(supply).map_err(|_| {
+                VMError::InvalidTransaction("Failed to update the total supply".to_string())
+            })?;
+        }
 
-            let updated_to_account = Account::new(to, to_balance + amount);
-            let update_result = self.state.update_account(&to, updated_to_account);
+        // what the recipient got, swept dust included
+        self.logs.push(Log::transfer(from, to, amount));
+        Ok(())
+    }
 
-            if update_result.is_err() {
-                return Err(VMError::InvalidTransaction(
-                    "Transaction sender account does not have enough balance".to_string(),
-                ));
-            };
-        };
+    // settles a transfer that a quorum of the committee has already voted for
+    pub fn execute_certificate(
+        &mut self,
+        certificate: &Certificate,
+        committee: &Committee,
+    ) -> Result<(), VMError> {
+        if let Err(e) = certificate.verify(committee) {
+            return Err(VMError::InvalidTransaction(format!(
+                "Transaction certificate is invalid: {:?}",
+                e
+            )));
+        }
 
-        Ok(())
+        self.execute(certificate.tx())
     }
 
-    pub fn state(&self) -> &Box<dyn State> {
-        &self.state
+    pub fn state(&self) -> &dyn State {
+        self.state.as_ref()
     }
 
     pub fn state_mut(&mut self) -> &mut Box<dyn State> {
@@ -112,11 +783,35 @@ impl VM {
     }
 }
 
+// fixtures shared by the tests of the vm's modules
+#[cfg(test)]
+mod test_utils {
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use tx::tx::Tx;
+
+    use crate::VM;
+
+    pub fn sign(signer: &PrivateKeySigner, tx: Tx) -> Tx {
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        tx.with_signature(signature)
+    }
+
+    pub fn balance(vm: &VM, address: Address) -> U256 {
+        vm.state()
+            .get_account(&address)
+            .map_or(U256::ZERO, |account| account.balance())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use alloy::signers::local::PrivateKeySigner;
     use alloy::signers::SignerSync;
+    use committee::authority::Authority;
+    use dust::DustMode;
+    use proptest::prelude::*;
     use state::memory::MemoryState;
 
     #[test]
@@ -133,7 +828,7 @@ mod tests {
         let from = from_signer.address();
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
-        let initial_balance = 100;
+        let initial_balance = U256::from(100);
 
         // Create and add sender account
         let from_account = Account::new(from, initial_balance);
@@ -143,10 +838,10 @@ mod tests {
         let mut vm = vm;
 
         // Create a valid transaction
-        let tx = Tx::new(from, to, 50, None);
+        let tx = Tx::new(from, to, U256::from(50), None);
         let tx_hash = tx.tx_hash();
         let signature = from_signer.sign_message_sync(&tx_hash).unwrap();
-        let tx = Tx::new(from, to, 50, Some(signature));
+        let tx = Tx::new(from, to, U256::from(50), Some(signature));
 
         // Execute transaction
         let result = vm.execute(&tx);
@@ -155,8 +850,8 @@ mod tests {
         // Verify balances
         let from_account = vm.state.get_account(&from).unwrap();
         let to_account = vm.state.get_account(&to).unwrap();
-        assert_eq!(from_account.balance(), initial_balance - 50);
-        assert_eq!(to_account.balance(), 50);
+        assert_eq!(from_account.balance(), initial_balance - U256::from(50));
+        assert_eq!(to_account.balance(), U256::from(50));
     }
 
     #[test]
@@ -166,7 +861,7 @@ mod tests {
         let from = from_signer.address();
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
-        let initial_balance = 30;
+        let initial_balance = U256::from(30);
 
         // Create and add sender account with insufficient balance
         let from_account = Account::new(from, initial_balance);
@@ -176,19 +871,16 @@ mod tests {
         let mut vm = vm;
 
         // Create a transaction with amount > balance
-        let tx = Tx::new(from, to, 50, None);
+        let tx = Tx::new(from, to, U256::from(50), None);
         let tx_hash = tx.tx_hash();
         let signature = from_signer.sign_message_sync(&tx_hash).unwrap();
-        let tx = Tx::new(from, to, 50, Some(signature));
+        let tx = Tx::new(from, to, U256::from(50), Some(signature));
 
         // Execute transaction
         let result = vm.execute(&tx);
         assert!(result.is_err());
-        match result.unwrap_err() {
-            VMError::InvalidTransaction(msg) => {
-                assert!(msg.contains("does not have enough balance"));
-            }
-        }
+        let msg = result.unwrap_err().reason();
+        assert!(msg.contains("does not have enough balance"));
     }
 
     #[test]
@@ -198,7 +890,7 @@ mod tests {
         let from = from_signer.address();
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
-        let initial_balance = 100;
+        let initial_balance = U256::from(100);
 
         // Create and add sender account
         let from_account = Account::new(from, initial_balance);
@@ -208,20 +900,17 @@ mod tests {
         let mut vm = vm;
 
         // Create a transaction with invalid signature
-        let tx = Tx::new(from, to, 50, None);
+        let tx = Tx::new(from, to, U256::from(50), None);
         let tx_hash = tx.tx_hash();
         let wrong_signer = PrivateKeySigner::random();
         let signature = wrong_signer.sign_message_sync(&tx_hash).unwrap();
-        let tx = Tx::new(from, to, 50, Some(signature));
+        let tx = Tx::new(from, to, U256::from(50), Some(signature));
 
         // Execute transaction
         let result = vm.execute(&tx);
         assert!(result.is_err());
-        match result.unwrap_err() {
-            VMError::InvalidTransaction(msg) => {
-                assert!(msg.contains("signature is invalid"));
-            }
-        }
+        let msg = result.unwrap_err().reason();
+        assert!(msg.contains("signature is invalid"));
     }
 
     #[test]
@@ -236,17 +925,785 @@ mod tests {
         let mut vm = vm;
 
         // Create a transaction from non-existent account
-        let tx = Tx::new(from, to, 50, None);
+        let tx = Tx::new(from, to, U256::from(50), None);
         let tx_hash = tx.tx_hash();
         let signature = from_signer.sign_message_sync(&tx_hash).unwrap();
-        let tx = Tx::new(from, to, 50, Some(signature));
+        let tx = Tx::new(from, to, U256::from(50), Some(signature));
 
         // Execute transaction
         let result = vm.execute(&tx);
         assert!(result.is_err());
-        match result.unwrap_err() {
-            VMError::InvalidTransaction(msg) => {
-                assert!(msg.contains("sender account does not exist"));
+        let msg = result.unwrap_err().reason();
+        assert!(msg.contains("sender account does not exist"));
+    }
+
+    fn signed_transfer(from_signer: &PrivateKeySigner, to: Address, amount: u64) -> Tx {
+        let (from, amount) = (from_signer.address(), U256::from(amount));
+        let tx = Tx::new(from, to, amount, None);
+        let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        Tx::new(from, to, amount, Some(signature))
+    }
+
+    #[test]
+    fn test_execute_batch() {
+        let mut state = MemoryState::new();
+        let alice = PrivateKeySigner::random();
+        let bob = PrivateKeySigner::random();
+        let carol = PrivateKeySigner::random().address();
+        state
+            .update_account(
+                &alice.address(),
+                Account::new(alice.address(), U256::from(100)),
+            )
+            .unwrap();
+
+        let mut vm = VM::new(Box::new(state));
+
+        // signed by bob but claiming to come from alice
+        let bobs = signed_transfer(&bob, carol, 10);
+        let forged = Tx::new(alice.address(), carol, U256::from(10), bobs.signature());
+
+        let txs = vec![
+            signed_transfer(&alice, bob.address(), 60),
+            // bob can spend what alice sent him earlier in the batch
+            signed_transfer(&bob, carol, 20),
+            forged,
+            // alice only has 40 left
+            signed_transfer(&alice, carol, 50),
+            signed_transfer(&alice, carol, 40),
+        ];
+
+        let results = vm.execute_batch(&txs);
+        assert_eq!(results.len(), 5);
+
+        let receipt = results[0].as_ref().ok().unwrap();
+        assert_eq!(receipt.tx_hash(), &txs[0].tx_hash());
+        assert_eq!(receipt.from(), alice.address());
+        assert_eq!(receipt.to(), bob.address());
+        assert_eq!(receipt.amount(), U256::from(60));
+        assert_eq!(
+            receipt.logs(),
+            &[Log::transfer(
+                alice.address(),
+                bob.address(),
+                U256::from(60)
+            )]
+        );
+        assert!(results[1].is_ok());
+        match &results[2] {
+            Err(error) => assert!(error.reason().contains("signature is invalid")),
+            Ok(_) => panic!("forged transfer was applied"),
+        }
+        match &results[3] {
+            Err(error) => {
+                assert!(error.reason().contains("does not have enough balance"))
+            }
+            Ok(_) => panic!("overdrawing transfer was applied"),
+        }
+        assert!(results[4].is_ok());
+
+        // alice spent everything, so her account is gone
+        assert_eq!(vm.state.get_account(&alice.address()), None);
+        assert_eq!(
+            vm.state.get_account(&bob.address()).unwrap().balance(),
+            U256::from(40)
+        );
+        assert_eq!(
+            vm.state.get_account(&carol).unwrap().balance(),
+            U256::from(60)
+        );
+    }
+
+    fn certified_transfer(
+        authorities: &[Authority],
+        from_signer: &PrivateKeySigner,
+        to: Address,
+        amount: u64,
+    ) -> Certificate {
+        let (from, amount) = (from_signer.address(), U256::from(amount));
+        let tx = Tx::new(from, to, amount, None);
+        let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = Tx::new(from, to, amount, Some(signature));
+
+        let mut certificate = Certificate::new(tx.clone());
+        for authority in authorities {
+            certificate.add_vote(authority.handle_transfer_order(&tx).unwrap());
+        }
+        certificate
+    }
+
+    #[test]
+    fn test_execute_certificate() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+
+        let authorities: Vec<Authority> = (0..4).map(|_| Authority::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+
+        let mut vm = VM::new(Box::new(state));
+
+        // Votes from 3 of 4 authorities form a quorum
+        let certificate = certified_transfer(&authorities[..3], &from_signer, to, 40);
+        assert!(vm.execute_certificate(&certificate, &committee).is_ok());
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(60)
+        );
+        assert_eq!(vm.state.get_account(&to).unwrap().balance(), U256::from(40));
+    }
+
+    #[test]
+    fn test_execute_certificate_without_quorum() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+
+        let authorities: Vec<Authority> = (0..4).map(|_| Authority::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+
+        let mut vm = VM::new(Box::new(state));
+
+        let certificate = certified_transfer(&authorities[..2], &from_signer, to, 40);
+        let msg = vm
+            .execute_certificate(&certificate, &committee)
+            .unwrap_err()
+            .reason();
+        assert!(msg.contains("certificate is invalid"));
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(100)
+        );
+        assert!(vm.state.get_account(&to).is_none());
+    }
+
+    #[test]
+    fn test_execute_transfer_order() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+
+        let mut vm = VM::new(Box::new(state));
+        let order = |sequence: u64, amount: u64| {
+            let tx = Tx::transfer_order(from, to, U256::from(amount), sequence, None);
+            let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        assert!(vm.execute(&order(0, 10)).is_ok());
+        assert_eq!(vm.state.get_account(&from).unwrap().sequence(), 1);
+
+        // Replaying the order is refused as a replay before its sequence number is looked at
+        match vm.execute(&order(0, 10)) {
+            Err(error) => assert!(error.reason().contains("already executed in block 0")),
+            Ok(_) => panic!("replayed order was applied"),
+        }
+        // Another order reusing a sequence number or skipping ahead are both rejected
+        for sequence in [0, 2] {
+            match vm.execute(&order(sequence, 5)) {
+                Err(error) => {
+                    assert!(error.reason().contains("does not match the sender's 1"))
+                }
+                Ok(_) => panic!("out of sequence order was applied"),
+            }
+        }
+        assert!(vm.execute(&order(1, 10)).is_ok());
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(80)
+        );
+        assert_eq!(vm.state.get_account(&from).unwrap().sequence(), 2);
+        // Receiving doesn't touch the receiver's sequence number
+        assert_eq!(vm.state.get_account(&to).unwrap().sequence(), 0);
+    }
+
+    #[test]
+    fn test_replayed_tx_is_refused() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        let mut vm = VM::new(Box::new(state));
+        let tx = Tx::new(from, to, U256::from(10), None);
+        let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = tx.with_signature(signature);
+        let hash = B256::from_slice(tx.tx_hash().as_ref());
+
+        vm.set_current_block(3);
+        assert!(vm.execute(&tx).is_ok());
+        assert_eq!(vm.state.seen_tx(&hash), Some(3 | SEEN_FOR_GOOD));
+
+        // A plain transfer has no sequence number, only the seen txs stop it
+        vm.set_current_block(4);
+        let results = vm.execute_batch(std::slice::from_ref(&tx));
+        match &results[0] {
+            Err(error) => assert!(error.reason().contains("already executed in block 3")),
+            Ok(_) => panic!("replayed transfer was applied"),
+        }
+        assert!(vm.simulate(&tx).is_err());
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(90)
+        );
+
+        // Nor is it ever forgotten
+        vm.set_current_block(2 * REPLAY_WINDOW);
+        assert_eq!(vm.state.seen_tx(&hash), Some(3 | SEEN_FOR_GOOD));
+        assert!(vm.simulate(&tx).is_err());
+
+        // A tx with a sequence number is, once out of the window the number stops it
+        let ordered = Tx::transfer_order(from, to, U256::from(10), 0, None);
+        let signature = from_signer.sign_message_sync(&ordered.tx_hash()).unwrap();
+        let ordered = ordered.with_signature(signature);
+        let ordered_hash = B256::from_slice(ordered.tx_hash().as_ref());
+        vm.set_current_block(2 * REPLAY_WINDOW + 1);
+        assert!(vm.execute(&ordered).is_ok());
+        assert_eq!(vm.state.seen_tx(&ordered_hash), Some(2 * REPLAY_WINDOW + 1));
+        vm.set_current_block(4 * REPLAY_WINDOW);
+        assert_eq!(vm.state.seen_tx(&ordered_hash), None);
+        assert!(vm
+            .simulate(&ordered)
+            .is_err_and(|error| error.reason().contains("sequence number")));
+    }
+
+    #[test]
+    fn test_dust_policy() {
+        let alice = PrivateKeySigner::random();
+        let bob = PrivateKeySigner::random().address();
+        let vm = |mode| {
+            let mut state = MemoryState::new();
+            state
+                .update_account(
+                    &alice.address(),
+                    Account::new(alice.address(), U256::from(100)),
+                )
+                .unwrap();
+            VM::new(Box::new(state)).with_dust_policy(DustPolicy::new(U256::from(10), mode))
+        };
+
+        let mut rejecting = vm(DustMode::Reject);
+        for amount in [95, 5] {
+            match rejecting.execute(&signed_transfer(&alice, bob, amount)) {
+                Err(error) => {
+                    assert!(error.reason().contains("below the minimum balance of 10"))
+                }
+                Ok(_) => panic!("transfer leaving dust was applied"),
+            }
+        }
+        assert!(rejecting.execute(&signed_transfer(&alice, bob, 90)).is_ok());
+        assert_eq!(
+            rejecting
+                .state
+                .get_account(&alice.address())
+                .unwrap()
+                .balance(),
+            U256::from(10)
+        );
+
+        // Sweeping sends the dust along and the emptied sender goes away
+        let mut sweeping = vm(DustMode::Sweep);
+        assert!(sweeping.execute(&signed_transfer(&alice, bob, 95)).is_ok());
+        assert_eq!(sweeping.state.get_account(&alice.address()), None);
+        assert_eq!(
+            sweeping.state.get_account(&bob).unwrap().balance(),
+            U256::from(100)
+        );
+    }
+
+    #[test]
+    fn test_execute_transfer_with_fee() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+
+        state.set_total_supply(U256::from(100)).unwrap();
+        let mut vm = VM::new(Box::new(state));
+        let transfer = |amount: u64, fee: u64| {
+            let tx = Tx::new(from, to, U256::from(amount), None).with_fee(fee);
+            let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        // The fee is burned, all of it
+        let results = vm.execute_batch(&[transfer(50, 10)]);
+        let receipt = results[0].as_ref().ok().unwrap();
+        assert_eq!(receipt.fee_charged(), 10);
+        assert_eq!(receipt.fee_refunded(), 0);
+        assert_eq!(receipt.effective_fee(), 10);
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(40)
+        );
+        assert_eq!(vm.state.get_account(&to).unwrap().balance(), U256::from(50));
+        // And it leaves the supply with it
+        assert_eq!(vm.state.total_supply(), U256::from(90));
+        assert!(vm.total_supply().is_balanced());
+
+        // The sender has to afford both
+        for (amount, fee) in [(40, 1), (1, u64::MAX)] {
+            match vm.execute(&transfer(amount, fee)) {
+                Err(error) => assert!(error.reason().contains("enough balance")),
+                Ok(_) => panic!("transfer the sender can't pay for was applied"),
+            }
+        }
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(40)
+        );
+    }
+
+    #[test]
+    fn test_fee_schedule() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(1000)))
+            .unwrap();
+
+        // 146 bytes at 1 each on top of 10
+        let mut vm = VM::new(Box::new(state)).with_fee_schedule(FeeSchedule::new(10, 1));
+        let transfer = |fee: u64| {
+            let tx = Tx::new(from, to, U256::from(50), None).with_fee(fee);
+            let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+        match vm.execute(&transfer(155)) {
+            Err(error) => assert!(error.reason().contains("need at least 156")),
+            Ok(_) => panic!("underpaying transfer was applied"),
+        }
+        assert!(vm.execute_batch(&[transfer(155)])[0].is_err());
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(1000)
+        );
+
+        assert!(vm.execute(&transfer(156)).is_ok());
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(794)
+        );
+    }
+
+    #[test]
+    fn test_execute_typed_transfer() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+
+        let mut vm = VM::new(Box::new(state));
+        let tx = Tx::new(from, to, U256::from(50), None);
+        let typed_hash = tx.typed_hash().unwrap();
+        let signature = from_signer.sign_hash_sync(&typed_hash).unwrap();
+
+        // Signed over the typed data of a different amount
+        let forged = Tx::new(from, to, U256::from(60), None).with_signature(signature);
+        match vm.execute(&forged) {
+            Err(error) => assert!(error.reason().contains("signature is invalid")),
+            Ok(_) => panic!("transfer with a mismatched typed signature was applied"),
+        }
+
+        assert!(vm.execute(&tx.with_signature(signature)).is_ok());
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(50)
+        );
+        assert_eq!(vm.state.get_account(&to).unwrap().balance(), U256::from(50));
+    }
+
+    #[test]
+    fn test_chain_id() {
+        let mut state = MemoryState::new();
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = PrivateKeySigner::random().address();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+
+        let mut vm = VM::new(Box::new(state)).with_chain_id(7);
+        let sign = |tx: Tx| {
+            let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        let replayed = sign(Tx::new(from, to, U256::from(10), None).with_chain_id(8));
+        match vm.execute(&replayed) {
+            Err(error) => assert!(error.reason().contains("chain 8")),
+            Ok(_) => panic!("transaction for another chain was applied"),
+        }
+        assert!(vm.execute_batch(&[replayed])[0].is_err());
+
+        assert!(vm
+            .execute(&sign(
+                Tx::new(from, to, U256::from(10), None).with_chain_id(7)
+            ))
+            .is_ok());
+        // Txs without a chain id predate them
+        assert!(vm
+            .execute(&sign(Tx::new(from, to, U256::from(10), None)))
+            .is_ok());
+        assert_eq!(vm.state.get_account(&to).unwrap().balance(), U256::from(20));
+    }
+
+    #[cfg(feature = "rent")]
+    #[test]
+    fn test_rent() {
+        let signer = PrivateKeySigner::random();
+        let from = signer.address();
+        let to = Address::repeat_byte(9);
+        let mut state = MemoryState::new();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        state.set_total_supply(U256::from(100)).unwrap();
+        let mut vm = VM::new(Box::new(state)).with_rent(rent::RentPolicy::new(10, 1));
+
+        vm.set_current_block(20);
+        let hibernated = vm.hibernate_dormant();
+        assert_eq!(hibernated.len(), 1);
+        // Hibernated balances are still part of the supply
+        assert!(vm.total_supply().is_balanced());
+        match vm.execute(&signed_transfer(&signer, to, 10)) {
+            Err(error) => assert!(error.reason().contains("hibernated")),
+            Ok(_) => panic!("hibernated account sent a transfer"),
+        }
+
+        assert!(vm.revive(&hibernated[0]).is_ok());
+        assert!(vm.execute(&signed_transfer(&signer, to, 10)).is_ok());
+        // Both ends were just active
+        vm.set_current_block(39);
+        assert!(vm.hibernate_dormant().is_empty());
+    }
+
+    #[test]
+    fn test_multisig_transfer() {
+        let owners: Vec<_> = (0..3).map(|_| PrivateKeySigner::random()).collect();
+        let multisig =
+            state::account::Multisig::new(owners.iter().map(|owner| owner.address()).collect(), 2)
+                .unwrap();
+        let from = multisig.address();
+        let to = Address::repeat_byte(1);
+        let mut state = MemoryState::new();
+        state
+            .update_account(
+                &from,
+                Account::new(from, U256::from(100)).with_multisig(multisig),
+            )
+            .unwrap();
+        let mut vm = VM::new(Box::new(state));
+
+        let sign = |tx: Tx, signers: &[&PrivateKeySigner]| {
+            signers.iter().fold(tx, |tx, signer| {
+                let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+                tx.with_signature(signature)
+            })
+        };
+
+        // One owner, even twice, is not enough
+        let tx = Tx::multisig_transfer(from, to, U256::from(60), 0, vec![]);
+        for signers in [vec![&owners[0]], vec![&owners[0], &owners[0]]] {
+            match vm.execute(&sign(tx.clone(), &signers)) {
+                Err(error) => assert!(error.reason().contains("signatures of 2")),
+                Ok(_) => panic!("multisig transfer applied below the threshold"),
+            }
+        }
+        let stranger = PrivateKeySigner::random();
+        assert!(vm
+            .execute(&sign(tx.clone(), &[&owners[0], &stranger]))
+            .is_err());
+
+        let signed = sign(tx, &[&owners[2], &owners[0]]);
+        assert!(vm.execute_batch(std::slice::from_ref(&signed))[0].is_ok());
+        assert_eq!(vm.state.get_account(&to).unwrap().balance(), U256::from(60));
+        // The sequence number moved on, the signatures can't be replayed
+        assert!(vm.execute(&signed).is_err());
+
+        // The rest leaves the account, its owners are still known
+        let drain = sign(
+            Tx::multisig_transfer(from, to, U256::from(40), 1, vec![]),
+            &[&owners[1], &owners[2]],
+        );
+        assert!(vm.execute(&drain).is_ok());
+        assert!(vm.state.get_account(&from).unwrap().multisig().is_some());
+
+        // Only multisig accounts take multisig transfers
+        let owner = owners[0].address();
+        vm.state
+            .update_account(&owner, Account::new(owner, U256::from(10)))
+            .unwrap();
+        let plain = sign(
+            Tx::multisig_transfer(owner, to, U256::from(5), 0, vec![]),
+            &[&owners[0], &owners[1]],
+        );
+        assert!(vm.execute(&plain).is_err());
+    }
+
+    #[test]
+    fn test_balance_overflow() {
+        let from_signer = PrivateKeySigner::random();
+        let from = from_signer.address();
+        let to = Address::repeat_byte(7);
+        let mut state = MemoryState::new();
+        state
+            .update_account(&from, Account::new(from, U256::from(10)))
+            .unwrap();
+        state
+            .update_account(&to, Account::new(to, U256::MAX - U256::from(1)))
+            .unwrap();
+        let mut vm = VM::new(Box::new(state));
+
+        match vm.execute(&signed_transfer(&from_signer, to, 2)) {
+            Err(VMError::BalanceOverflow(address)) => assert_eq!(address, to),
+            _ => panic!("transfer past U256::MAX was applied"),
+        }
+        // Neither account was touched
+        assert_eq!(
+            vm.state.get_account(&from).unwrap().balance(),
+            U256::from(10)
+        );
+        assert_eq!(
+            vm.state.get_account(&to).unwrap().balance(),
+            U256::MAX - U256::from(1)
+        );
+        assert!(vm.execute(&signed_transfer(&from_signer, to, 1)).is_ok());
+        assert_eq!(vm.state.get_account(&to).unwrap().balance(), U256::MAX);
+    }
+
+    #[test]
+    fn test_mint_and_burn() {
+        let issuer = PrivateKeySigner::random();
+        let to = Address::repeat_byte(1);
+        let mut vm = VM::new(Box::new(MemoryState::new())).with_issuer(issuer.address());
+        let sign = |signer: &PrivateKeySigner, tx: Tx| {
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        let mint = sign(
+            &issuer,
+            Tx::mint(issuer.address(), to, U256::from(100), 0, None),
+        );
+        let receipts = vm.execute_batch(std::slice::from_ref(&mint));
+        let receipt = receipts[0].as_ref().ok().unwrap();
+        assert_eq!(
+            receipt.logs(),
+            &[Log::transfer(Address::ZERO, to, U256::from(100))]
+        );
+        assert_eq!(
+            vm.state.get_account(&to).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_eq!(vm.state.total_supply(), U256::from(100));
+        // The issuer's sequence number moved on, the mint can't be replayed
+        assert!(vm.execute(&mint).is_err());
+
+        // Nobody else mints, not even with a valid signature
+        let stranger = PrivateKeySigner::random();
+        let forged = sign(
+            &stranger,
+            Tx::mint(stranger.address(), to, U256::from(100), 0, None),
+        );
+        match vm.execute(&forged) {
+            Err(error) => assert!(error.reason().contains("not the issuer")),
+            Ok(_) => panic!("mint from a stranger was applied"),
+        }
+        // Nor does the issuer without its signature
+        let unsigned = sign(
+            &stranger,
+            Tx::mint(issuer.address(), to, U256::from(100), 1, None),
+        );
+        assert!(vm.execute(&unsigned).is_err());
+
+        // Funds leave the chain through the issuer
+        let mint = sign(
+            &issuer,
+            Tx::mint(issuer.address(), issuer.address(), U256::from(30), 1, None),
+        );
+        assert!(vm.execute(&mint).is_ok());
+        let burn = sign(&issuer, Tx::burn(issuer.address(), U256::from(31), 2, None));
+        assert!(vm.execute(&burn).is_err());
+        let burn = sign(&issuer, Tx::burn(issuer.address(), U256::from(20), 2, None));
+        assert!(vm.execute(&burn).is_ok());
+        let account = vm.state.get_account(&issuer.address()).unwrap();
+        assert_eq!(account.balance(), U256::from(10));
+        assert_eq!(account.sequence(), 3);
+        assert_eq!(vm.state.total_supply(), U256::from(110));
+        assert!(vm.total_supply().is_balanced());
+
+        // Minting past U256::MAX is refused before anything changes
+        let mint = sign(&issuer, Tx::mint(issuer.address(), to, U256::MAX, 3, None));
+        assert!(vm.execute(&mint).is_err());
+        assert_eq!(vm.state.total_supply(), U256::from(110));
+
+        // Without an issuer nothing is minted
+        let mut vm = VM::new(Box::new(MemoryState::new()));
+        let mint = sign(
+            &issuer,
+            Tx::mint(issuer.address(), to, U256::from(1), 0, None),
+        );
+        assert!(vm.execute(&mint).is_err());
+    }
+
+    #[test]
+    fn test_bridge() {
+        let operator = PrivateKeySigner::random();
+        let user = PrivateKeySigner::random();
+        let mut vm = VM::new(Box::new(MemoryState::new())).with_bridge_operator(operator.address());
+        let sign = |signer: &PrivateKeySigner, tx: Tx| {
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+        let deposit = |amount: u64, nonce: u64| {
+            sign(
+                &operator,
+                Tx::fund_from_primary(
+                    operator.address(),
+                    user.address(),
+                    U256::from(amount),
+                    nonce,
+                    None,
+                ),
+            )
+        };
+
+        // Deposits are credited once, in the order the contract numbered them
+        assert!(vm.execute(&deposit(100, 0)).is_ok());
+        assert!(vm.execute(&deposit(100, 0)).is_err());
+        assert!(vm.execute(&deposit(50, 2)).is_err());
+        assert!(vm.execute(&deposit(50, 1)).is_ok());
+        assert_eq!(
+            vm.state.get_account(&user.address()).unwrap().balance(),
+            U256::from(150)
+        );
+        assert_eq!(vm.state.total_supply(), U256::from(150));
+
+        // Only the operator credits deposits
+        let forged = sign(
+            &user,
+            Tx::fund_from_primary(user.address(), user.address(), U256::from(1), 0, None),
+        );
+        match vm.execute(&forged) {
+            Err(error) => assert!(error.reason().contains("bridge operator")),
+            Ok(_) => panic!("deposit credited by someone else than the operator"),
+        }
+
+        // Redemptions leave the chain, anyone can redeem what they hold
+        let recipient = Address::repeat_byte(9);
+        let redeem = |amount: u64, sequence: u64| {
+            sign(
+                &user,
+                Tx::redeem_to_primary(
+                    user.address(),
+                    recipient,
+                    U256::from(amount),
+                    sequence,
+                    None,
+                ),
+            )
+        };
+        assert!(vm.execute(&redeem(151, 0)).is_err());
+        let receipts = vm.execute_batch(&[redeem(120, 0)]);
+        assert_eq!(
+            receipts[0].as_ref().ok().unwrap().logs(),
+            &[Log::transfer(
+                user.address(),
+                Address::ZERO,
+                U256::from(120)
+            )]
+        );
+        assert!(vm.execute(&redeem(10, 0)).is_err());
+        assert_eq!(
+            vm.state.get_account(&user.address()).unwrap().balance(),
+            U256::from(30)
+        );
+        assert_eq!(vm.state.total_supply(), U256::from(30));
+        assert!(vm.total_supply().is_balanced());
+    }
+
+    // Boundary values show up far more often than they would drawn uniformly
+    fn balance() -> impl Strategy<Value = U256> {
+        prop_oneof![
+            Just(U256::ZERO),
+            Just(U256::from(1)),
+            Just(U256::MAX - U256::from(1)),
+            Just(U256::MAX),
+            any::<u64>().prop_map(U256::from),
+            any::<u64>().prop_map(|n| U256::MAX - U256::from(n)),
+            any::<[u64; 4]>().prop_map(U256::from_limbs),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_transfer_arithmetic(
+            from_balance in balance(),
+            to_balance in balance(),
+            amount in balance(),
+            fee in prop_oneof![Just(0), Just(u64::MAX), any::<u64>()],
+        ) {
+            let from_signer = PrivateKeySigner::random();
+            let from = from_signer.address();
+            let to = Address::repeat_byte(7);
+            let mut state = MemoryState::new();
+            state.update_account(&from, Account::new(from, from_balance)).unwrap();
+            state.update_account(&to, Account::new(to, to_balance)).unwrap();
+            let mut vm = VM::new(Box::new(state));
+
+            let tx = Tx::new(from, to, amount, None).with_fee(fee);
+            let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            let result = vm.execute(&tx.with_signature(signature));
+
+            let balance = |vm: &VM, address| {
+                vm.state.get_account(&address).map_or(U256::ZERO, |account| account.balance())
+            };
+            let (from_after, to_after) = (balance(&vm, from), balance(&vm, to));
+            let debit = amount.checked_add(U256::from(fee));
+            match result {
+                Ok(_) => {
+                    // The sender paid exactly the amount and the fee, the recipient got the amount
+                    prop_assert_eq!(Some(from_balance - from_after), debit);
+                    prop_assert_eq!(to_after - to_balance, amount);
+                }
+                Err(error) => {
+                    // A failed transfer leaves both balances as they were
+                    prop_assert_eq!((from_after, to_after), (from_balance, to_balance));
+                    let affordable = debit.is_some_and(|debit| debit <= from_balance);
+                    match error {
+                        VMError::BalanceOverflow(address) => {
+                            prop_assert_eq!(address, to);
+                            prop_assert!(affordable);
+                            prop_assert!(to_balance.checked_add(amount).is_none());
+                        }
+                        VMError::InvalidTransaction(_) => prop_assert!(!affordable),
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,236 @@
+// settlements of netting windows. the authority only orders and submits: every intent is signed by
+// the account it debits, and the obligations must be exactly what the intents net to
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, U256};
+use tx::netting::{net_obligations, Obligation, SignedIntent};
+
+use crate::{VMError, VM};
+
+fn invalid(message: String) -> VMError {
+    VMError::InvalidTransaction(message)
+}
+
+impl VM {
+    pub(crate) fn apply_settlement(
+        &mut self,
+        window: u64,
+        intents: &[SignedIntent],
+        obligations: &[Obligation],
+    ) -> Result<(), VMError> {
+        for signed in intents {
+            if signed.intent.window != window {
+                return Err(invalid(format!(
+                    "Settlement includes an intent of window {}",
+                    signed.intent.window
+                )));
+            }
+            signed.verify().map_err(invalid)?;
+        }
+
+        let intents: Vec<_> = intents.iter().map(|signed| signed.intent).collect();
+        let expected = net_obligations(&intents).map_err(invalid)?;
+        if expected != obligations {
+            return Err(invalid(
+                "Settlement obligations don't match its intents".to_string(),
+            ));
+        }
+
+        // either every obligation settles or none does
+        let mut debits: HashMap<Address, U256> = HashMap::new();
+        let mut credits: HashMap<Address, U256> = HashMap::new();
+        for obligation in obligations {
+            let debit = debits.entry(obligation.from).or_default();
+            *debit = debit.saturating_add(U256::from(obligation.amount));
+            let credit = credits.entry(obligation.to).or_default();
+            *credit = credit.saturating_add(U256::from(obligation.amount));
+        }
+        for (address, debit) in &debits {
+            if self.balance(address) < *debit {
+                return Err(invalid(format!(
+                    "Settlement debtor {} does not have enough balance",
+                    address
+                )));
+            }
+        }
+        for (address, credit) in &credits {
+            let balance = self.balance(address);
+            if balance.checked_add(*credit).is_none() {
+                return Err(VMError::BalanceOverflow(*address));
+            }
+        }
+
+        for obligation in obligations {
+            self.move_funds(
+                obligation.from,
+                obligation.to,
+                U256::from(obligation.amount),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn balance(&self, address: &Address) -> U256 {
+        self.state
+            .get_account(address)
+            .map(|account| account.balance())
+            .unwrap_or_default()
+    }
+
+    fn move_funds(&mut self, from: Address, to: Address, amount: U256) -> Result<(), VMError> {
+        let mut from_account = self.account_or_new(from);
+        let balance = from_account.balance().checked_sub(amount).ok_or_else(|| {
+            invalid(format!(
+                "Settlement debtor {} does not have enough balance",
+                from
+            ))
+        })?;
+        from_account.set_balance(balance);
+        self.state
+            .update_account(&from, from_account)
+            .map_err(|_| invalid("Failed to settle obligation".to_string()))?;
+
+        let mut to_account = self.account_or_new(to);
+        let balance = to_account
+            .balance()
+            .checked_add(amount)
+            .ok_or(VMError::BalanceOverflow(to))?;
+        to_account.set_balance(balance);
+        self.state
+            .update_account(&to, to_account)
+            .map_err(|_| invalid("Failed to settle obligation".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use state::{account::Account, memory::MemoryState, state::State};
+    use tx::netting::Intent;
+    use tx::tx::Tx;
+
+    fn signed_intent(signer: &PrivateKeySigner, to: Address, amount: u64) -> SignedIntent {
+        let intent = Intent {
+            from: signer.address(),
+            to,
+            amount,
+            window: 1,
+        };
+        SignedIntent {
+            intent,
+            signature: signer.sign_message_sync(intent.hash().as_slice()).unwrap(),
+        }
+    }
+
+    fn settlement(
+        authority: &PrivateKeySigner,
+        intents: Vec<SignedIntent>,
+        obligations: Vec<Obligation>,
+    ) -> Tx {
+        let tx = Tx::settlement(authority.address(), 1, intents, obligations, None);
+        let signature = authority.sign_message_sync(&tx.tx_hash()).unwrap();
+        tx.with_signature(signature)
+    }
+
+    fn balance(vm: &VM, address: Address) -> u64 {
+        vm.state()
+            .get_account(&address)
+            .map_or(0, |account| account.balance().to())
+    }
+
+    struct Group {
+        vm: VM,
+        members: Vec<PrivateKeySigner>,
+        intents: Vec<SignedIntent>,
+    }
+
+    // three exchanges with 100 each paying each other in a circle, a owes c 70 net
+    fn group() -> Group {
+        let members: Vec<PrivateKeySigner> = (0..3).map(|_| PrivateKeySigner::random()).collect();
+        let mut state = MemoryState::new();
+        for member in &members {
+            let address = member.address();
+            state
+                .update_account(&address, Account::new(address, U256::from(100)))
+                .unwrap();
+        }
+
+        let intents = vec![
+            signed_intent(&members[0], members[1].address(), 100),
+            signed_intent(&members[1], members[2].address(), 100),
+            signed_intent(&members[2], members[0].address(), 30),
+        ];
+        Group {
+            vm: VM::new(Box::new(state)),
+            members,
+            intents,
+        }
+    }
+
+    #[test]
+    fn test_settlement_applies_net_obligations() {
+        let mut group = group();
+        let authority = PrivateKeySigner::random();
+        let intents: Vec<Intent> = group.intents.iter().map(|s| s.intent).collect();
+        let obligations = net_obligations(&intents).unwrap();
+        assert_eq!(obligations.len(), 1);
+
+        let tx = settlement(&authority, group.intents.clone(), obligations);
+        assert!(group.vm.execute(&tx).is_ok());
+
+        let balances: Vec<u64> = group
+            .members
+            .iter()
+            .map(|member| balance(&group.vm, member.address()))
+            .collect();
+        assert_eq!(balances, vec![30, 100, 170]);
+    }
+
+    #[test]
+    fn test_settlement_rejects_made_up_obligations() {
+        let mut group = group();
+        let authority = PrivateKeySigner::random();
+
+        let stolen = vec![Obligation {
+            from: group.members[1].address(),
+            to: authority.address(),
+            amount: 50,
+        }];
+        let tx = settlement(&authority, group.intents.clone(), stolen);
+        assert!(group.vm.execute(&tx).is_err());
+        assert_eq!(balance(&group.vm, authority.address()), 0);
+    }
+
+    #[test]
+    fn test_settlement_rejects_forged_intents() {
+        let mut group = group();
+        let authority = PrivateKeySigner::random();
+
+        // The authority can't sign an intent for someone else
+        let mut forged = signed_intent(&authority, authority.address(), 50);
+        forged.intent.from = group.members[0].address();
+        let intents = vec![forged.intent];
+        let tx = settlement(&authority, vec![forged], net_obligations(&intents).unwrap());
+        assert!(group.vm.execute(&tx).is_err());
+        assert_eq!(balance(&group.vm, group.members[0].address()), 100);
+    }
+
+    #[test]
+    fn test_settlement_is_all_or_nothing() {
+        let mut group = group();
+        let authority = PrivateKeySigner::random();
+        let poor = PrivateKeySigner::random();
+
+        let mut intents = group.intents.clone();
+        intents.push(signed_intent(&poor, group.members[0].address(), 10));
+        let plain: Vec<Intent> = intents.iter().map(|s| s.intent).collect();
+        let tx = settlement(&authority, intents, net_obligations(&plain).unwrap());
+
+        assert!(group.vm.execute(&tx).is_err());
+        assert_eq!(balance(&group.vm, group.members[0].address()), 100);
+        assert_eq!(balance(&group.vm, group.members[2].address()), 100);
+    }
+}
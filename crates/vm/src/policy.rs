@@ -0,0 +1,222 @@
+// spending policies, see state::policy. A tx is checked against the policies of every account it
+// spends from once it is otherwise valid, and what it spent is recorded once it applied, so a tx
+// that fails doesn't count against the day. Only the account's owner sets its policy
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, U256};
+use state::policy::{DailySpending, SpendingPolicy};
+use tx::{asset::is_native, tx::Tx};
+
+use crate::{VMError, VM};
+
+// a day at the default block time of a second
+pub const DAY_IN_BLOCKS: u64 = 86_400;
+
+// who `tx` takes funds from, who they go to and how much. Limits are in the native token, so a
+// transfer of another asset has no amount and is only held to the allowlist. A settlement spends
+// what each of its intents does, closing a channel only pays out the deposit spent when it was
+// opened and settling an escrow or an htlc what was spent when it was locked
+fn spends(tx: &Tx) -> Vec<(Address, Address, Option<U256>)> {
+    match tx {
+        Tx::Transfer { asset, .. } if !is_native(asset) => vec![(tx.from(), tx.to(), None)],
+        Tx::Transfer { .. }
+        | Tx::MultisigTransfer { .. }
+        | Tx::OpenChannel { .. }
+        | Tx::EscrowCreate { .. }
+        | Tx::HtlcLock { .. } => {
+            vec![(tx.from(), tx.to(), Some(tx.amount()))]
+        }
+        Tx::RedeemToPrimary {
+            from,
+            recipient,
+            amount,
+            ..
+        } => vec![(*from, *recipient, Some(*amount))],
+        Tx::Settlement { intents, .. } => intents
+            .iter()
+            .map(|signed| {
+                let intent = signed.intent;
+                (intent.from, intent.to, Some(U256::from(intent.amount)))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl VM {
+    // an account that doesn't exist yet can be given a policy before it is funded
+    pub(crate) fn apply_set_policy(
+        &mut self,
+        from: Address,
+        max_per_tx: Option<U256>,
+        daily_limit: Option<U256>,
+        allowlist: &[Address],
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        let mut account = self.next_sequence(from, sequence)?;
+        let mut allowlist = allowlist.to_vec();
+        allowlist.sort();
+        allowlist.dedup();
+        account.set_policy(Some(SpendingPolicy {
+            max_per_tx,
+            daily_limit,
+            allowlist,
+        }));
+        self.write_account(from, account)
+    }
+
+    // what each account with a policy will have spent today once `tx` applies, refused if that
+    // breaks the policy
+    pub(crate) fn check_policies(
+        &self,
+        tx: &Tx,
+    ) -> Result<HashMap<Address, DailySpending>, VMError> {
+        let day = self.current_block / DAY_IN_BLOCKS;
+        let mut spending = HashMap::new();
+        for (from, to, amount) in spends(tx) {
+            let Some(account) = self.state.get_account(&from) else {
+                continue;
+            };
+            let Some(policy) = account.policy() else {
+                continue;
+            };
+            let today = spending.entry(from).or_insert(account.spending());
+            let reason = match amount {
+                Some(amount) => policy.check(to, amount, today.spent_on(day)),
+                None => (!policy.allows_recipient(to))
+                    .then(|| format!("{} is not an allowed recipient", to)),
+            };
+            if let Some(reason) = reason {
+                return Err(VMError::InvalidTransaction(format!(
+                    "Transaction breaks the spending policy of {}: {}",
+                    from, reason
+                )));
+            }
+            if let Some(amount) = amount {
+                *today = today.add(day, amount);
+            }
+        }
+        Ok(spending)
+    }
+
+    pub(crate) fn record_spending(
+        &mut self,
+        spending: HashMap<Address, DailySpending>,
+    ) -> Result<(), VMError> {
+        for (address, spending) in spending {
+            // an account with a policy is never dropped, even once drained
+            if let Some(mut account) = self.state.get_account(&address) {
+                account.set_spending(spending);
+                self.write_account(address, account)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use state::{account::Account, memory::MemoryState, state::State};
+    use tx::asset::asset_id;
+
+    use crate::test_utils::sign;
+
+    fn message(result: Result<(), VMError>) -> String {
+        match result {
+            Err(error) => error.reason(),
+            Ok(()) => panic!("expected the tx to fail"),
+        }
+    }
+
+    #[test]
+    fn test_spending_policy() {
+        let owner = PrivateKeySigner::random();
+        let from = owner.address();
+        let allowed = Address::repeat_byte(1);
+        let usd = asset_id(Address::repeat_byte(9), "USD");
+        let mut account = Account::new(from, U256::from(1_000));
+        account.set_asset_balance(usd, U256::from(1_000));
+        let mut state = MemoryState::new();
+        state.update_account(&from, account).unwrap();
+        let mut vm = VM::new(Box::new(state));
+        let transfer = |amount: u64, sequence: u64, to: Address| {
+            sign(
+                &owner,
+                Tx::transfer_order(from, to, U256::from(amount), sequence, None),
+            )
+        };
+
+        let policy = Tx::set_policy(
+            from,
+            Some(U256::from(50)),
+            Some(U256::from(80)),
+            vec![allowed],
+            0,
+            None,
+        );
+        assert!(vm.execute(&sign(&owner, policy)).is_ok());
+        let account = vm.state().get_account(&from).unwrap();
+        assert_eq!(account.policy().unwrap().allowlist, vec![allowed]);
+        assert_eq!(account.sequence(), 1);
+
+        assert!(message(vm.execute(&transfer(51, 1, allowed))).contains("per transaction"));
+        assert!(
+            message(vm.execute(&transfer(1, 1, Address::repeat_byte(2))))
+                .contains("not an allowed recipient")
+        );
+        assert!(vm.execute(&transfer(50, 1, allowed)).is_ok());
+        assert!(message(vm.execute(&transfer(31, 2, allowed))).contains("daily limit"));
+        assert!(vm.execute(&transfer(30, 2, allowed)).is_ok());
+        assert_eq!(
+            vm.state().get_account(&from).unwrap().spending().spent,
+            U256::from(80)
+        );
+
+        // The limit starts over the next day
+        vm.set_current_block(DAY_IN_BLOCKS);
+        assert!(vm.execute(&transfer(50, 3, allowed)).is_ok());
+
+        // Other assets aren't held to the limits, only to the allowlist
+        let asset_transfer = |amount: u64, to: Address| {
+            sign(
+                &owner,
+                Tx::transfer_order(from, to, U256::from(amount), 4, None).with_asset(usd),
+            )
+        };
+        assert!(
+            message(vm.execute(&asset_transfer(1, Address::repeat_byte(2))))
+                .contains("not an allowed recipient")
+        );
+        assert!(vm.execute(&asset_transfer(500, allowed)).is_ok());
+        assert_eq!(
+            vm.state().get_account(&from).unwrap().spending().spent,
+            U256::from(50)
+        );
+
+        // Lifting every limit removes the policy
+        let lifted = sign(&owner, Tx::set_policy(from, None, None, vec![], 5, None));
+        assert!(vm.execute(&lifted).is_ok());
+        assert_eq!(vm.state().get_account(&from).unwrap().policy(), None);
+        assert!(vm
+            .execute(&transfer(500, 6, Address::repeat_byte(2)))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_policy_of_a_new_account() {
+        let owner = PrivateKeySigner::random();
+        let from = owner.address();
+        let mut vm = VM::new(Box::new(MemoryState::new()));
+
+        let policy = Tx::set_policy(from, Some(U256::from(5)), None, vec![], 0, None);
+        assert!(vm.execute(&sign(&owner, policy)).is_ok());
+        // Kept while the account holds nothing, and signed by its owner only
+        assert!(vm.state().get_account(&from).is_some());
+        let stranger = PrivateKeySigner::random();
+        let forged = Tx::set_policy(from, None, None, vec![], 1, None);
+        assert!(vm.execute(&sign(&stranger, forged)).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+// experimental state rent, behind the `rent` feature. Accounts nobody has sent from or to for
+// longer than the policy allows are hibernated: they leave the state and only a commitment to
+// their balance and sequence number is kept. Anyone holding the hibernated record, which is
+// handed out when the account is hibernated, can revive the account with it.
+// Activity and commitments aren't part of the state root yet, nodes only agree on them by
+// executing the same blocks from genesis
+
+use std::collections::HashMap;
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+use state::{account::Account, state::State};
+
+const HIBERNATION_DOMAIN: &[u8] = b"fastpay-hibernated";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RentPolicy {
+    // in blocks
+    pub epoch_length: u64,
+    // an account idle for more epochs than this is hibernated
+    pub max_inactive_epochs: u64,
+}
+
+impl RentPolicy {
+    pub fn new(epoch_length: u64, max_inactive_epochs: u64) -> Self {
+        assert!(epoch_length > 0, "rent epochs need at least one block");
+        Self {
+            epoch_length,
+            max_inactive_epochs,
+        }
+    }
+
+    pub fn epoch(&self, block: u64) -> u64 {
+        block / self.epoch_length
+    }
+}
+
+// what a hibernated account held, the proof that revives it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hibernated {
+    pub address: Address,
+    pub balance: U256,
+    pub sequence: u64,
+    // the epoch it was hibernated in
+    pub epoch: u64,
+}
+
+impl Hibernated {
+    pub fn commitment(&self) -> B256 {
+        let mut encoded = HIBERNATION_DOMAIN.to_vec();
+        encoded.extend_from_slice(self.address.as_slice());
+        encoded.extend_from_slice(&self.balance.to_be_bytes::<32>());
+        encoded.extend_from_slice(&self.sequence.to_be_bytes());
+        encoded.extend_from_slice(&self.epoch.to_be_bytes());
+        keccak256(encoded)
+    }
+}
+
+// whether the account holds nothing but a balance and a sequence number, which is all a
+// Hibernated record keeps
+fn held_by_record(account: &Account) -> bool {
+    let mut rest = account.clone();
+    rest.set_balance(U256::ZERO);
+    rest.set_sequence(0);
+    rest.is_empty()
+}
+
+#[derive(Debug)]
+pub struct Rent {
+    policy: RentPolicy,
+    // the last epoch each account was active in, accounts missing were last active in epoch 0
+    last_active: HashMap<Address, u64>,
+    hibernated: HashMap<Address, B256>,
+    // what the hibernated accounts hold, it left the state with them but is still part of the
+    // total supply
+    hibernated_balance: U256,
+}
+
+impl Rent {
+    pub fn new(policy: RentPolicy) -> Self {
+        Self {
+            policy,
+            last_active: HashMap::new(),
+            hibernated: HashMap::new(),
+            hibernated_balance: U256::ZERO,
+        }
+    }
+
+    pub fn policy(&self) -> RentPolicy {
+        self.policy
+    }
+
+    pub fn hibernated_balance(&self) -> U256 {
+        self.hibernated_balance
+    }
+
+    pub fn touch(&mut self, address: Address, block: u64) {
+        self.last_active.insert(address, self.policy.epoch(block));
+    }
+
+    // a hibernated account can still receive, it can't send until it is revived
+    pub fn is_hibernated(&self, address: &Address) -> bool {
+        self.hibernated.contains_key(address)
+    }
+
+    // removes every dormant account from `state`, sorted by address. Only an account the record
+    // holds all of is hibernated: not a multisig or module account, whose owners would be lost,
+    // nor one with a policy, scheduled payments, escrows, htlcs or other assets. Neither is an
+    // account hibernated already
+    pub fn hibernate_dormant(&mut self, state: &mut dyn State, block: u64) -> Vec<Hibernated> {
+        let epoch = self.policy.epoch(block);
+        let mut dormant: Vec<Account> = state
+            .accounts()
+            .into_iter()
+            .filter(|account| {
+                let address = account.get_address();
+                let last_active = self.last_active.get(&address).copied().unwrap_or(0);
+                held_by_record(account)
+                    && !self.is_hibernated(&address)
+                    && epoch.saturating_sub(last_active) > self.policy.max_inactive_epochs
+            })
+            .collect();
+        dormant.sort_by_key(|account| account.get_address());
+
+        dormant
+            .into_iter()
+            .filter_map(|account| {
+                let address = account.get_address();
+                state.remove_account(&address).ok()?;
+                let record = Hibernated {
+                    address,
+                    balance: account.balance(),
+                    sequence: account.sequence(),
+                    epoch,
+                };
+                self.last_active.remove(&address);
+                self.hibernated.insert(address, record.commitment());
+                self.hibernated_balance = self.hibernated_balance.saturating_add(record.balance);
+                Some(record)
+            })
+            .collect()
+    }
+
+    // puts a hibernated account back, on top of whatever it received while hibernated
+    pub fn revive(
+        &mut self,
+        state: &mut dyn State,
+        record: &Hibernated,
+        block: u64,
+    ) -> Result<(), String> {
+        if self.hibernated.get(&record.address) != Some(&record.commitment()) {
+            return Err(format!(
+                "no hibernated account {} matches the record",
+                record.address
+            ));
+        }
+
+        let mut account = state
+            .get_account(&record.address)
+            .unwrap_or_else(|| Account::new(record.address, U256::ZERO));
+        let balance = account
+            .balance()
+            .checked_add(record.balance)
+            .ok_or_else(|| format!("reviving {} overflows its balance", record.address))?;
+        account.set_balance(balance);
+        // it couldn't send while hibernated, so the sequence number is the one it had
+        account.set_sequence(record.sequence);
+        state
+            .update_account(&record.address, account)
+            .map_err(|e| format!("failed to revive {}: {:?}", record.address, e))?;
+        self.hibernated.remove(&record.address);
+        self.hibernated_balance = self.hibernated_balance.saturating_sub(record.balance);
+        self.touch(record.address, block);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::{escrow::Escrow, memory::MemoryState};
+    use tx::asset::asset_id;
+
+    #[test]
+    fn test_hibernate_and_revive() {
+        let mut rent = Rent::new(RentPolicy::new(10, 2));
+        let mut state = MemoryState::new();
+        let idle = Address::repeat_byte(1);
+        let busy = Address::repeat_byte(2);
+        let mut account = Account::new(idle, U256::from(100));
+        account.set_sequence(3);
+        state.update_account(&idle, account).unwrap();
+        state
+            .update_account(&busy, Account::new(busy, U256::from(5)))
+            .unwrap();
+
+        // Two idle epochs are allowed, the third isn't
+        rent.touch(busy, 25);
+        assert!(rent.hibernate_dormant(&mut state, 29).is_empty());
+        let hibernated = rent.hibernate_dormant(&mut state, 30);
+        assert_eq!(
+            hibernated,
+            vec![Hibernated {
+                address: idle,
+                balance: U256::from(100),
+                sequence: 3,
+                epoch: 3
+            }]
+        );
+        assert!(state.get_account(&idle).is_none());
+        assert!(rent.is_hibernated(&idle));
+        assert_eq!(rent.hibernated_balance(), U256::from(100));
+        assert!(state.get_account(&busy).is_some());
+
+        // A forged record doesn't revive anything
+        let mut forged = hibernated[0].clone();
+        forged.balance = U256::from(1000);
+        assert!(rent.revive(&mut state, &forged, 31).is_err());
+
+        // Funds received while hibernated are kept
+        state
+            .update_account(&idle, Account::new(idle, U256::from(7)))
+            .unwrap();
+        rent.revive(&mut state, &hibernated[0], 31).unwrap();
+        let revived = state.get_account(&idle).unwrap();
+        assert_eq!(revived.balance(), U256::from(107));
+        assert_eq!(revived.sequence(), 3);
+        assert!(!rent.is_hibernated(&idle));
+        assert_eq!(rent.hibernated_balance(), U256::ZERO);
+        assert!(rent.revive(&mut state, &hibernated[0], 31).is_err());
+    }
+
+    #[test]
+    fn test_only_plain_accounts_hibernate() {
+        let mut rent = Rent::new(RentPolicy::new(10, 2));
+        let mut state = MemoryState::new();
+        let holder = Address::repeat_byte(1);
+        let payer = Address::repeat_byte(2);
+        let mut holding = Account::new(holder, U256::from(1));
+        holding.set_asset_balance(asset_id(Address::repeat_byte(9), "USD"), U256::from(50));
+        state.update_account(&holder, holding).unwrap();
+        let mut paying = Account::new(payer, U256::ZERO);
+        paying.set_escrows(vec![Escrow {
+            id: B256::repeat_byte(8),
+            to: holder,
+            arbiter: Address::repeat_byte(3),
+            amount: U256::from(5),
+            expires_at: 10,
+        }]);
+        state.update_account(&payer, paying).unwrap();
+
+        // The record would keep neither the asset nor the escrowed funds
+        assert!(rent.hibernate_dormant(&mut state, 100).is_empty());
+        assert!(state.get_account(&holder).is_some());
+        assert_eq!(state.get_account(&payer).unwrap().locked(), U256::from(5));
+        assert!(!rent.is_hibernated(&holder) && !rent.is_hibernated(&payer));
+    }
+}
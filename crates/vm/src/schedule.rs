@@ -0,0 +1,313 @@
+// scheduled payments, see state::schedule. The vm makes the payments due in a block before the
+// block's txs, every node at the same point, so they need no tx of their own. A payment the payer
+// can't cover, or that its spending policy refuses, is missed and the schedule moves on to the next
+
+use alloy::primitives::{Address, B256, U256};
+use state::schedule::Schedule;
+use tx::{log::Log, tx::Tx};
+
+use crate::{VMError, VM};
+
+// bounds the work every block does for a single account
+pub const MAX_SCHEDULES_PER_ACCOUNT: usize = 16;
+
+// a payment made, or missed, for a schedule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledPayment {
+    pub schedule_id: B256,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    pub block: u64,
+    // why the payment was missed, None once it is made
+    pub error: Option<String>,
+    // emitted while it was made
+    pub logs: Vec<Log>,
+}
+
+fn invalid(message: String) -> VMError {
+    VMError::InvalidTransaction(message)
+}
+
+impl VM {
+    pub(crate) fn apply_schedule(
+        &mut self,
+        tx: &Tx,
+        from: Address,
+        to: Address,
+        amount: U256,
+        interval: u64,
+        end: Option<u64>,
+    ) -> Result<(), VMError> {
+        if interval == 0 {
+            return Err(invalid(
+                "Schedule interval must be at least one block".to_string(),
+            ));
+        }
+        if amount.is_zero() {
+            return Err(invalid("Scheduled amount must be more than 0".to_string()));
+        }
+        let next_due = self.current_block.saturating_add(interval);
+        if let Some(end) = end.filter(|end| *end < next_due) {
+            return Err(invalid(format!(
+                "Schedule ends at block {}, before its first payment at block {}",
+                end, next_due
+            )));
+        }
+        let Some(sequence) = tx.sequence() else {
+            return Err(invalid("Schedule has no sequence number".to_string()));
+        };
+        let mut account = self.next_sequence(from, sequence)?;
+        if account.schedules().len() >= MAX_SCHEDULES_PER_ACCOUNT {
+            return Err(invalid(format!(
+                "Account already has {} schedules",
+                MAX_SCHEDULES_PER_ACCOUNT
+            )));
+        }
+
+        let mut schedules = account.schedules().to_vec();
+        schedules.push(Schedule {
+            id: B256::from_slice(&tx.tx_hash()),
+            to,
+            amount,
+            interval,
+            next_due,
+            end,
+        });
+        account.set_schedules(schedules);
+        self.write_account(from, account)
+    }
+
+    pub(crate) fn apply_cancel_schedule(
+        &mut self,
+        from: Address,
+        schedule_id: B256,
+        sequence: u64,
+    ) -> Result<(), VMError> {
+        let mut account = self.next_sequence(from, sequence)?;
+        let mut schedules = account.schedules().to_vec();
+        let Some(index) = schedules
+            .iter()
+            .position(|schedule| schedule.id == schedule_id)
+        else {
+            return Err(invalid(format!(
+                "No schedule {} on the sender's account",
+                schedule_id
+            )));
+        };
+        schedules.remove(index);
+        account.set_schedules(schedules);
+        self.write_account(from, account)
+    }
+
+    // makes every payment due in the current block, payers sorted by address and each payer's
+    // schedules in the order they were registered. A schedule is paid at most once per block, so
+    // calling this twice for the same block pays nothing the second time
+    pub fn execute_due_payments(&mut self) -> Vec<ScheduledPayment> {
+        let block = self.current_block;
+        let mut payers: Vec<Address> = self
+            .state
+            .accounts()
+            .into_iter()
+            .filter(|account| {
+                account
+                    .schedules()
+                    .iter()
+                    .any(|schedule| schedule.is_due(block))
+            })
+            .map(|account| account.get_address())
+            .collect();
+        payers.sort();
+
+        let mut payments = Vec::new();
+        for from in payers {
+            let due: Vec<Schedule> = self
+                .state
+                .get_account(&from)
+                .map(|account| account.schedules().to_vec())
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|schedule| schedule.is_due(block))
+                .collect();
+            for schedule in due {
+                payments.push(self.pay_schedule(from, &schedule));
+            }
+        }
+        payments
+    }
+
+    fn pay_schedule(&mut self, from: Address, schedule: &Schedule) -> ScheduledPayment {
+        self.logs.clear();
+        let transfer = Tx::new(from, schedule.to, schedule.amount, None);
+        let error = self
+            .check_policies(&transfer)
+            .and_then(|spending| {
+                self.apply_transfer(&transfer)?;
+                self.record_spending(spending)
+            })
+            .and_then(|()| self.advance_schedule(from, schedule.id))
+            .err()
+            .map(|error| {
+                // a missed payment still counts, or the schedule would be retried every block
+                let _ = self.advance_schedule(from, schedule.id);
+                error.reason()
+            });
+        ScheduledPayment {
+            schedule_id: schedule.id,
+            from,
+            to: schedule.to,
+            amount: schedule.amount,
+            block: self.current_block,
+            error,
+            logs: std::mem::take(&mut self.logs),
+        }
+    }
+
+    // once past its end the schedule is dropped
+    fn advance_schedule(&mut self, from: Address, schedule_id: B256) -> Result<(), VMError> {
+        let Some(mut account) = self.state.get_account(&from) else {
+            return Ok(());
+        };
+        let schedules = account
+            .schedules()
+            .iter()
+            .cloned()
+            .filter_map(|mut schedule| {
+                if schedule.id != schedule_id {
+                    return Some(schedule);
+                }
+                schedule.advance().then_some(schedule)
+            })
+            .collect();
+        account.set_schedules(schedules);
+        self.write_account(from, account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+    use state::{account::Account, memory::MemoryState, state::State};
+
+    use crate::test_utils::{balance, sign};
+
+    fn funded_vm(payer: Address, balance: u64) -> VM {
+        let mut state = MemoryState::new();
+        state
+            .update_account(&payer, Account::new(payer, U256::from(balance)))
+            .unwrap();
+        VM::new(Box::new(state))
+    }
+
+    #[test]
+    fn test_scheduled_payments() {
+        let payer = PrivateKeySigner::random();
+        let from = payer.address();
+        let to = Address::repeat_byte(2);
+        let mut vm = funded_vm(from, 100);
+        vm.set_current_block(10);
+
+        let schedule = sign(
+            &payer,
+            Tx::schedule(from, to, U256::from(30), 5, Some(20), 0, None),
+        );
+        assert!(vm.execute(&schedule).is_ok());
+        let id = B256::from_slice(&schedule.tx_hash());
+        assert_eq!(
+            vm.state().get_account(&from).unwrap().schedules()[0].next_due,
+            15
+        );
+
+        // Nothing is due before the first interval is over
+        vm.set_current_block(14);
+        assert!(vm.execute_due_payments().is_empty());
+
+        vm.set_current_block(15);
+        let payments = vm.execute_due_payments();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].schedule_id, id);
+        assert_eq!(payments[0].error, None);
+        assert_eq!(
+            payments[0].logs,
+            vec![Log::transfer(from, to, U256::from(30))]
+        );
+        assert_eq!(balance(&vm, to), U256::from(30));
+        // Paid once per block however often it is asked
+        assert!(vm.execute_due_payments().is_empty());
+
+        vm.set_current_block(20);
+        assert_eq!(vm.execute_due_payments().len(), 1);
+        assert_eq!(balance(&vm, to), U256::from(60));
+        // 25 is past the end, the schedule is gone
+        assert!(vm
+            .state()
+            .get_account(&from)
+            .unwrap()
+            .schedules()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_missed_payment_and_cancel() {
+        let payer = PrivateKeySigner::random();
+        let from = payer.address();
+        let to = Address::repeat_byte(2);
+        let mut vm = funded_vm(from, 50);
+
+        let schedule = sign(
+            &payer,
+            Tx::schedule(from, to, U256::from(40), 1, None, 0, None),
+        );
+        assert!(vm.execute(&schedule).is_ok());
+        vm.set_current_block(1);
+        assert_eq!(vm.execute_due_payments()[0].error, None);
+
+        // Short of funds the payment is missed, the schedule carries on
+        vm.set_current_block(2);
+        let missed = vm.execute_due_payments();
+        assert!(missed[0].error.as_ref().unwrap().contains("enough balance"));
+        assert!(missed[0].logs.is_empty());
+        assert_eq!(
+            vm.state().get_account(&from).unwrap().schedules()[0].next_due,
+            3
+        );
+
+        let id = B256::from_slice(&schedule.tx_hash());
+        let cancel = sign(&payer, Tx::cancel_schedule(from, id, 1, None));
+        assert!(vm.execute(&cancel).is_ok());
+        vm.set_current_block(3);
+        assert!(vm.execute_due_payments().is_empty());
+        // Nothing left to cancel
+        let again = sign(&payer, Tx::cancel_schedule(from, id, 2, None));
+        assert!(vm.execute(&again).is_err());
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        let payer = PrivateKeySigner::random();
+        let from = payer.address();
+        let to = Address::repeat_byte(2);
+        let mut vm = funded_vm(from, 50);
+
+        let no_interval = Tx::schedule(from, to, U256::from(1), 0, None, 0, None);
+        assert!(vm.execute(&sign(&payer, no_interval)).is_err());
+        let ends_first = Tx::schedule(from, to, U256::from(1), 10, Some(5), 0, None);
+        assert!(vm.execute(&sign(&payer, ends_first)).is_err());
+
+        for sequence in 0..MAX_SCHEDULES_PER_ACCOUNT as u64 {
+            let tx = Tx::schedule(from, to, U256::from(1), 10, None, sequence, None);
+            assert!(vm.execute(&sign(&payer, tx)).is_ok());
+        }
+        let one_too_many = Tx::schedule(
+            from,
+            to,
+            U256::from(1),
+            10,
+            None,
+            MAX_SCHEDULES_PER_ACCOUNT as u64,
+            None,
+        );
+        assert!(vm.execute(&sign(&payer, one_too_many)).is_err());
+    }
+}
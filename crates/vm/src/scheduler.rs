@@ -0,0 +1,292 @@
+// parallel execution: transfers are grouped into waves in which no two transfers touch the same
+// account, each wave is applied concurrently on a sharded state, and conflicting transfers end up
+// in later waves so they still apply in the order they were given
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, B256};
+use rayon::prelude::*;
+use state::{sharded::ShardedState, state::State, state::StateError};
+use tx::{asset::is_native, tx::Tx};
+
+use crate::{executed_before, seen_record, Receipt, VMError, VM};
+
+// returns the indices of `txs` grouped in waves, a transfer is placed in the wave right after the
+// last earlier transfer it shares an account with
+pub fn schedule(txs: &[Tx]) -> Vec<Vec<usize>> {
+    let mut waves: Vec<Vec<usize>> = Vec::new();
+    // the wave of the last transfer that touched each account
+    let mut last_wave: HashMap<Address, usize> = HashMap::new();
+
+    for (i, tx) in txs.iter().enumerate() {
+        let (from, to) = (tx.from(), tx.to());
+        let wave = [from, to]
+            .iter()
+            .filter_map(|address| last_wave.get(address))
+            .map(|wave| wave + 1)
+            .max()
+            .unwrap_or(0);
+
+        if wave == waves.len() {
+            waves.push(Vec::new());
+        }
+        waves[wave].push(i);
+        last_wave.insert(from, wave);
+        last_wave.insert(to, wave);
+    }
+
+    waves
+}
+
+// `block` is the block the txs are executed in, for refusing replays like the vm
+pub fn execute_parallel<S>(
+    state: &ShardedState<S>,
+    txs: &[Tx],
+    block: u64,
+) -> Vec<Result<Receipt, VMError>>
+where
+    S: State + Send + Sync,
+{
+    let mut results: Vec<Result<Receipt, VMError>> = txs
+        .par_iter()
+        .map(|tx| VM::verify_signature(tx).map(|_| Receipt::new(tx)))
+        .collect();
+
+    for wave in schedule(txs) {
+        // a wave of one is the conflict case, no point in handing it to the thread pool
+        let applied: Vec<(usize, Result<(), VMError>)> = if wave.len() == 1 {
+            wave.iter()
+                .map(|&i| (i, apply_transfer(state, &txs[i], &results[i], block)))
+                .collect()
+        } else {
+            wave.par_iter()
+                .map(|&i| (i, apply_transfer(state, &txs[i], &results[i], block)))
+                .collect()
+        };
+
+        for (i, result) in applied {
+            if let Err(e) = result {
+                results[i] = Err(e);
+            }
+        }
+    }
+
+    results
+}
+
+fn apply_transfer<S>(
+    state: &ShardedState<S>,
+    tx: &Tx,
+    verified: &Result<Receipt, VMError>,
+    block: u64,
+) -> Result<(), VMError>
+where
+    S: State + Send + Sync,
+{
+    if verified.is_err() {
+        return Ok(());
+    }
+    // channel txs read and write channel entries the schedule doesn't know about
+    if !tx.is_transfer() {
+        return Err(VMError::InvalidTransaction(
+            "Only transfers can be executed in parallel".to_string(),
+        ));
+    }
+    // the sharded state only moves balances, it doesn't check sequence numbers
+    if tx.sequence().is_some() {
+        return Err(VMError::InvalidTransaction(
+            "Transfer orders can't be executed in parallel".to_string(),
+        ));
+    }
+    // nor burns fees
+    if tx.fee() > 0 {
+        return Err(VMError::InvalidTransaction(
+            "Transfers paying a fee can't be executed in parallel".to_string(),
+        ));
+    }
+
+    // nor moves other assets
+    if !is_native(&tx.asset()) {
+        return Err(VMError::InvalidTransaction(
+            "Transfers of other assets than the native token can't be executed in parallel"
+                .to_string(),
+        ));
+    }
+
+    // nor checks spending policies
+    if state
+        .read_account(&tx.from())
+        .is_some_and(|account| account.policy().is_some())
+    {
+        return Err(VMError::InvalidTransaction(
+            "Transfers from accounts with a spending policy can't be executed in parallel"
+                .to_string(),
+        ));
+    }
+
+    // the same tx shares its accounts with itself, so a copy of it is always in a later wave
+    let hash = B256::from_slice(tx.tx_hash().as_ref());
+    if let Some(executed) = executed_before(state.read_seen_tx(&hash), block) {
+        return Err(VMError::InvalidTransaction(format!(
+            "Transaction was already executed in block {}",
+            executed
+        )));
+    }
+
+    state
+        .apply_transfer(&tx.from(), &tx.to(), tx.amount())
+        .and_then(|()| state.write_seen_tx(&hash, Some(seen_record(tx, block))))
+        .map_err(|e| match e {
+            StateError::AccountNotFound => {
+                VMError::InvalidTransaction("Transaction sender account does not exist".to_string())
+            }
+            StateError::AccountBalanceTooLow => VMError::InvalidTransaction(
+                "Transaction sender account does not have enough balance".to_string(),
+            ),
+            StateError::BalanceOverflow => VMError::BalanceOverflow(tx.to()),
+            // apply_transfer doesn't touch snapshots or cross-shard messages
+            StateError::UnknownSnapshot | StateError::DuplicateMessage => {
+                unreachable!("transfer failed on a snapshot or a cross-shard message")
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use state::account::Account;
+
+    fn signed_transfer(from_signer: &PrivateKeySigner, to: Address, amount: u64) -> Tx {
+        let (from, amount) = (from_signer.address(), U256::from(amount));
+        let tx = Tx::new(from, to, amount, None);
+        let signature = from_signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        Tx::new(from, to, amount, Some(signature))
+    }
+
+    fn address() -> Address {
+        PrivateKeySigner::random().address()
+    }
+
+    #[test]
+    fn test_schedule_disjoint_transfers_share_a_wave() {
+        let txs: Vec<Tx> = (0..4)
+            .map(|_| Tx::new(address(), address(), U256::from(1), None))
+            .collect();
+
+        assert_eq!(schedule(&txs), vec![vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn test_schedule_conflicts_go_to_later_waves() {
+        let (a, b, c, d) = (address(), address(), address(), address());
+        let txs = vec![
+            Tx::new(a, b, U256::from(1), None),
+            Tx::new(c, d, U256::from(1), None),
+            // touches b after the first transfer
+            Tx::new(b, c, U256::from(1), None),
+            Tx::new(a, d, U256::from(1), None),
+            Tx::new(c, a, U256::from(1), None),
+        ];
+
+        assert_eq!(schedule(&txs), vec![vec![0, 1], vec![2, 3], vec![4]]);
+    }
+
+    #[test]
+    fn test_execute_parallel() {
+        let state = ShardedState::in_memory(8);
+        let alice = PrivateKeySigner::random();
+        let bob = PrivateKeySigner::random();
+        let carol = PrivateKeySigner::random();
+        let dave = address();
+
+        for signer in [&alice, &carol] {
+            state
+                .write_account(
+                    &signer.address(),
+                    Account::new(signer.address(), U256::from(100)),
+                )
+                .unwrap();
+        }
+
+        let bobs = signed_transfer(&bob, dave, 5);
+        let txs = vec![
+            signed_transfer(&alice, bob.address(), 60),
+            signed_transfer(&carol, dave, 10),
+            // depends on the first transfer
+            signed_transfer(&bob, dave, 50),
+            // alice only has 40 left
+            signed_transfer(&alice, dave, 50),
+            Tx::new(carol.address(), dave, U256::from(5), bobs.signature()),
+        ];
+
+        let results = execute_parallel(&state, &txs, 0);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert!(results[2].is_ok());
+        match &results[3] {
+            Err(error) => {
+                assert!(error.reason().contains("does not have enough balance"))
+            }
+            Ok(_) => panic!("overdrawing transfer was applied"),
+        }
+        match &results[4] {
+            Err(error) => assert!(error.reason().contains("signature is invalid")),
+            Ok(_) => panic!("forged transfer was applied"),
+        }
+
+        assert_eq!(
+            state.read_account(&alice.address()).unwrap().balance(),
+            U256::from(40)
+        );
+        assert_eq!(
+            state.read_account(&bob.address()).unwrap().balance(),
+            U256::from(10)
+        );
+        assert_eq!(
+            state.read_account(&carol.address()).unwrap().balance(),
+            U256::from(90)
+        );
+        assert_eq!(state.read_account(&dave).unwrap().balance(), U256::from(60));
+    }
+
+    #[test]
+    fn test_execute_parallel_matches_sequential() {
+        let signers: Vec<PrivateKeySigner> = (0..6).map(|_| PrivateKeySigner::random()).collect();
+        let txs: Vec<Tx> = (0..40)
+            .map(|i| {
+                let from = &signers[(i * 7) % signers.len()];
+                let to = signers[(i * 3 + 1) % signers.len()].address();
+                signed_transfer(from, to, (i as u64 % 5) * 10)
+            })
+            .collect();
+
+        let sharded = ShardedState::in_memory(4);
+        let mut memory = state::memory::MemoryState::new();
+        for signer in &signers {
+            let account = Account::new(signer.address(), U256::from(50));
+            sharded
+                .write_account(&signer.address(), account.clone())
+                .unwrap();
+            memory.update_account(&signer.address(), account).unwrap();
+        }
+
+        let parallel = execute_parallel(&sharded, &txs, 0);
+        let mut vm = VM::new(Box::new(memory));
+        let sequential = vm.execute_batch(&txs);
+
+        for (p, s) in parallel.iter().zip(&sequential) {
+            assert_eq!(p.is_ok(), s.is_ok());
+        }
+        for signer in &signers {
+            assert_eq!(
+                sharded.read_account(&signer.address()).map(|a| a.balance()),
+                vm.state()
+                    .get_account(&signer.address())
+                    .map(|a| a.balance())
+            );
+        }
+    }
+}
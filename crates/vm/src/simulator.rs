@@ -0,0 +1,152 @@
+// dry runs for eth_call: a tx executed on a throwaway copy of the state, with the rules of the
+// chain, to find out whether it would go through before it is signed and sent. Clones share the
+// state they read, so the rpc can hold one while the node keeps the state up to date
+
+use std::sync::Arc;
+
+use alloy::primitives::Address;
+use state::pending::PendingState;
+use tx::{fee::FeeSchedule, tx::Tx};
+
+use crate::{
+    dust::DustPolicy,
+    validator::{DefaultValidator, TxValidator},
+    Receipt, VMError, VM,
+};
+
+#[derive(Clone)]
+pub struct Simulator {
+    // the committed state, and the mempool executed on top of it
+    pending: PendingState,
+    dust_policy: DustPolicy,
+    fee_schedule: FeeSchedule,
+    chain_id: Option<u64>,
+    issuer: Option<Address>,
+    bridge_operator: Option<Address>,
+    validators: Vec<Arc<dyn TxValidator>>,
+}
+
+impl std::fmt::Debug for Simulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Simulator")
+            .field("dust_policy", &self.dust_policy)
+            .field("fee_schedule", &self.fee_schedule)
+            .field("chain_id", &self.chain_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Simulator {
+    pub fn new(pending: PendingState) -> Self {
+        Self {
+            pending,
+            dust_policy: DustPolicy::default(),
+            fee_schedule: FeeSchedule::default(),
+            chain_id: None,
+            issuer: None,
+            bridge_operator: None,
+            validators: vec![Arc::new(DefaultValidator::default())],
+        }
+    }
+
+    pub fn with_dust_policy(mut self, dust_policy: DustPolicy) -> Self {
+        self.dust_policy = dust_policy;
+        self
+    }
+
+    pub fn with_fee_schedule(mut self, fee_schedule: FeeSchedule) -> Self {
+        self.fee_schedule = fee_schedule;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: Option<u64>) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: Option<Address>) -> Self {
+        self.issuer = issuer;
+        self
+    }
+
+    pub fn with_bridge_operator(mut self, operator: Option<Address>) -> Self {
+        self.bridge_operator = operator;
+        self
+    }
+
+    pub fn with_validators(mut self, validators: Vec<Arc<dyn TxValidator>>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    // executes `tx` in block `block`, on the committed state or with the mempool's txs applied
+    // first when `pending`
+    pub fn simulate(&self, tx: &Tx, block: u64, pending: bool) -> Result<Receipt, VMError> {
+        let state = if pending {
+            self.pending.fork()
+        } else {
+            self.pending.fresh()
+        };
+        let mut vm = VM::new(Box::new(state))
+            .with_dust_policy(self.dust_policy)
+            .with_fee_schedule(self.fee_schedule)
+            .with_validators(self.validators.clone());
+        vm.chain_id = self.chain_id;
+        vm.issuer = self.issuer;
+        vm.bridge_operator = self.bridge_operator;
+        vm.set_current_block(block);
+        vm.simulate(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use state::{account::Account, sharded::ShardedState, state::State};
+
+    #[test]
+    fn test_simulate() {
+        let committed = Arc::new(ShardedState::in_memory(2));
+        let sender = PrivateKeySigner::random().address();
+        let to = Address::repeat_byte(1);
+        committed
+            .write_account(&sender, Account::new(sender, U256::from(100)))
+            .unwrap();
+        let mut pending = PendingState::new(committed.clone());
+        pending
+            .update_account(&sender, Account::new(sender, U256::from(20)))
+            .unwrap();
+        let simulator = VM::new(Box::new(pending.fresh()))
+            .with_fee_schedule(FeeSchedule::new(1, 0))
+            .simulator(pending.clone());
+
+        // Unsigned, and checked against the committed state or the pending one
+        let tx = Tx::new(sender, to, U256::from(50), None).with_fee(1);
+        let receipt = simulator
+            .simulate(&tx, 1, false)
+            .as_ref()
+            .ok()
+            .unwrap()
+            .clone();
+        assert_eq!(receipt.amount(), U256::from(50));
+        assert!(simulator.simulate(&tx, 1, true).is_err());
+
+        // The chain's rules hold
+        assert!(simulator
+            .simulate(&Tx::new(sender, to, U256::from(50), None), 1, false)
+            .is_err());
+
+        // Nothing sticks
+        assert_eq!(
+            committed.read_account(&sender).unwrap().balance(),
+            U256::from(100)
+        );
+        assert_eq!(
+            pending.get_account(&sender).unwrap().balance(),
+            U256::from(20)
+        );
+        assert!(pending.get_account(&to).is_none());
+    }
+}
@@ -0,0 +1,222 @@
+// validators decide whether a tx may be applied, the VM consults each of them in turn before it
+// touches the state. The default one holds the rules every fastpay chain has, downstream users
+// can add their own next to it, e.g. spending limits or allowlists, or replace it altogether
+
+use std::sync::Arc;
+
+use state::state::State;
+use tx::{signature_cache::SignatureCache, tx::Tx};
+
+use crate::{VMError, VM};
+
+pub trait TxValidator: Send + Sync {
+    // checks that need nothing but the tx, a batch runs them in parallel
+    fn validate_tx(&self, _tx: &Tx) -> Result<(), VMError> {
+        Ok(())
+    }
+
+    // checks against the state the tx is about to be applied to
+    fn validate(&self, _tx: &Tx, _state: &dyn State) -> Result<(), VMError> {
+        Ok(())
+    }
+}
+
+// the sender's signature, or enough of its owners' for a multisig, and the sequence number of
+// transfer orders
+#[derive(Clone, Default)]
+pub struct DefaultValidator {
+    signature_cache: Option<Arc<SignatureCache>>,
+}
+
+impl DefaultValidator {
+    // recovers signers through `signature_cache` rather than on every check, see
+    // VM::with_signature_cache
+    pub fn with_signature_cache(signature_cache: Arc<SignatureCache>) -> Self {
+        Self {
+            signature_cache: Some(signature_cache),
+        }
+    }
+
+    fn signers(&self, tx: &Tx) -> Vec<alloy::primitives::Address> {
+        match &self.signature_cache {
+            Some(signature_cache) => signature_cache.signers(tx),
+            None => tx.signers(),
+        }
+    }
+}
+
+impl TxValidator for DefaultValidator {
+    fn validate_tx(&self, tx: &Tx) -> Result<(), VMError> {
+        match &self.signature_cache {
+            Some(signature_cache) => signature_cache
+                .verify_signature(tx)
+                .map_err(VMError::InvalidTransaction),
+            None => VM::verify_signature(tx),
+        }
+    }
+
+    fn validate(&self, tx: &Tx, state: &dyn State) -> Result<(), VMError> {
+        let account = state.get_account(&tx.from());
+        let multisig = account.as_ref().and_then(|account| account.multisig());
+        match (tx, multisig) {
+            (Tx::MultisigTransfer { .. }, None) => {
+                return Err(VMError::InvalidTransaction(
+                    "Transaction sender account is not a multisig".to_string(),
+                ))
+            }
+            (Tx::MultisigTransfer { .. }, Some(multisig))
+                if !multisig.is_met_by(&self.signers(tx)) =>
+            {
+                return Err(VMError::InvalidTransaction(format!(
+                    "Transaction needs signatures of {} of the sender's owners",
+                    multisig.threshold()
+                )))
+            }
+            // a key that happens to match a multisig's address can't move its funds alone
+            (
+                Tx::Transfer { .. }
+                | Tx::RedeemToPrimary { .. }
+                | Tx::SetPolicy { .. }
+                | Tx::Schedule { .. }
+                | Tx::CancelSchedule { .. }
+                | Tx::EscrowCreate { .. }
+                | Tx::EscrowRelease { .. }
+                | Tx::EscrowRefund { .. }
+                | Tx::HtlcLock { .. }
+                | Tx::HtlcClaim { .. }
+                | Tx::HtlcRefund { .. }
+                | Tx::IssueAsset { .. },
+                Some(_),
+            ) => {
+                return Err(VMError::InvalidTransaction(
+                    "Transaction sender account is a multisig".to_string(),
+                ))
+            }
+            _ => (),
+        }
+
+        // replaying an executed order or skipping ahead of one that hasn't been executed yet are
+        // both rejected. A sender that doesn't exist is left to the VM to reject
+        if let (Some(sequence), Some(account)) = (tx.sequence(), account) {
+            if sequence != account.sequence() {
+                return Err(VMError::InvalidTransaction(format!(
+                    "Transaction sequence number {} does not match the sender's {}",
+                    sequence,
+                    account.sequence()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use state::{account::Account, memory::MemoryState};
+
+    struct SpendingLimit(u64);
+
+    impl TxValidator for SpendingLimit {
+        fn validate_tx(&self, tx: &Tx) -> Result<(), VMError> {
+            if tx.amount() > U256::from(self.0) {
+                return Err(VMError::InvalidTransaction(format!(
+                    "Transaction is over the spending limit of {}",
+                    self.0
+                )));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_validators() {
+        let signer = PrivateKeySigner::random();
+        let from = signer.address();
+        let to = Address::repeat_byte(1);
+        let vm = || {
+            let mut state = MemoryState::new();
+            state
+                .update_account(&from, Account::new(from, U256::from(100)))
+                .unwrap();
+            VM::new(Box::new(state))
+        };
+        let sign = |tx: Tx| {
+            let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+            tx.with_signature(signature)
+        };
+
+        let mut limited = vm().with_validator(SpendingLimit(10));
+        let results = limited.execute_batch(&[
+            sign(Tx::new(from, to, U256::from(20), None)),
+            sign(Tx::new(from, to, U256::from(10), None)),
+        ]);
+        match &results[0] {
+            Err(error) => assert!(error.reason().contains("spending limit")),
+            Ok(_) => panic!("transfer over the limit was applied"),
+        }
+        assert!(results[1].is_ok());
+        // The default validator still runs next to it
+        let wrong_signer = PrivateKeySigner::random();
+        let tx = Tx::new(from, to, U256::from(5), None);
+        let forged = tx
+            .clone()
+            .with_signature(wrong_signer.sign_message_sync(&tx.tx_hash()).unwrap());
+        assert!(limited.execute(&forged).is_err());
+
+        // Without any validator nothing but the state transition is checked
+        let mut unchecked = vm().with_validators(vec![]);
+        assert!(unchecked.execute(&forged).is_ok());
+        assert_eq!(
+            unchecked.state().get_account(&to).unwrap().balance(),
+            U256::from(5)
+        );
+    }
+
+    #[test]
+    fn test_signature_cache() {
+        let signer = PrivateKeySigner::random();
+        let from = signer.address();
+        let mut state = MemoryState::new();
+        state
+            .update_account(&from, Account::new(from, U256::from(100)))
+            .unwrap();
+        let signature_cache = Arc::new(SignatureCache::new(16));
+        let mut vm = VM::new(Box::new(state)).with_signature_cache(signature_cache.clone());
+
+        let tx = Tx::new(from, Address::repeat_byte(1), U256::from(5), None);
+        let signature = signer.sign_message_sync(&tx.tx_hash()).unwrap();
+        let tx = tx.with_signature(signature);
+        // As the mempool would have on admission
+        signature_cache.verify_signature(&tx).unwrap();
+
+        assert!(vm.execute(&tx).is_ok());
+        assert_eq!((signature_cache.hits(), signature_cache.misses()), (1, 1));
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut state = MemoryState::new();
+        let from = Address::repeat_byte(1);
+        let mut account = Account::new(from, U256::from(100));
+        account.set_sequence(2);
+        state.update_account(&from, account).unwrap();
+
+        let order =
+            |sequence| Tx::transfer_order(from, Address::ZERO, U256::from(1), sequence, None);
+        assert!(DefaultValidator::default()
+            .validate(&order(2), &state)
+            .is_ok());
+        for sequence in [1, 3] {
+            match DefaultValidator::default().validate(&order(sequence), &state) {
+                Err(error) => {
+                    assert!(error.reason().contains("does not match the sender's 2"))
+                }
+                Ok(_) => panic!("order with the wrong sequence number passed"),
+            }
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use alloy::primitives::{Address, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::Signer;
+use async_trait::async_trait;
+use committee::authority::Authority;
+use committee::certificate::{Certificate, Vote};
+use committee::committee::Committee;
+use committee::CommitteeError;
+use futures::stream::{FuturesUnordered, StreamExt};
+use tx::tx::Tx;
+
+use crate::{Wallet, WalletError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthorityClientError {
+    // the authority couldn't be reached, worth trying again
+    Unavailable(String),
+    // the authority looked at the order and refused to vote for it
+    Rejected(CommitteeError),
+}
+
+// AuthorityClient is how a client reaches a single authority, over the network or in process
+#[async_trait]
+pub trait AuthorityClient: Send + Sync {
+    async fn handle_transfer_order(&self, tx: Tx) -> Result<Vote, AuthorityClientError>;
+
+    async fn handle_confirmation_order(
+        &self,
+        certificate: Certificate,
+    ) -> Result<(), AuthorityClientError>;
+
+    async fn next_sequence(&self, address: Address) -> Result<u64, AuthorityClientError>;
+}
+
+#[async_trait]
+impl AuthorityClient for Authority {
+    async fn handle_transfer_order(&self, tx: Tx) -> Result<Vote, AuthorityClientError> {
+        Authority::handle_transfer_order(self, &tx).map_err(AuthorityClientError::Rejected)
+    }
+
+    async fn handle_confirmation_order(
+        &self,
+        certificate: Certificate,
+    ) -> Result<(), AuthorityClientError> {
+        Authority::handle_confirmation_order(self, &certificate)
+            .map_err(AuthorityClientError::Rejected)
+    }
+
+    async fn next_sequence(&self, address: Address) -> Result<u64, AuthorityClientError> {
+        Ok(Authority::next_sequence(self, address))
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    SigningError(WalletError),
+    QuorumNotReached {
+        collected: u64,
+        required: u64,
+        failures: Vec<(Address, AuthorityClientError)>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    // attempts per authority, including the first one
+    pub max_attempts: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+// Client drives the FastPay transfer flow: sign a transfer order, send it to every authority,
+// turn the first quorum of votes into a certificate and confirm it back to the authorities
+pub struct Client<A, S = PrivateKeySigner> {
+    wallet: Wallet<S>,
+    committee: Committee,
+    authorities: Vec<(Address, A)>,
+    retry: RetryConfig,
+    // the sequence number of the next transfer order
+    sequence: AtomicU64,
+}
+
+impl<A: AuthorityClient, S: Signer + Send + Sync> Client<A, S> {
+    pub fn new(wallet: Wallet<S>, committee: Committee, authorities: Vec<(Address, A)>) -> Self {
+        Self {
+            wallet,
+            committee,
+            authorities,
+            retry: RetryConfig::default(),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    // for an account that already sent transfer orders, `sync_sequence` finds it out otherwise
+    pub fn with_sequence(self, sequence: u64) -> Self {
+        self.sequence.store(sequence, Ordering::SeqCst);
+        self
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence.load(Ordering::SeqCst)
+    }
+
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub async fn transfer(&self, to: Address, amount: U256) -> Result<Certificate, ClientError> {
+        let from = self.wallet.address();
+        let sequence = self.sequence();
+        let tx = Tx::transfer_order(from, to, amount, sequence, None);
+        let signature = self
+            .wallet
+            .sign_transaction(tx.clone())
+            .await
+            .map_err(ClientError::SigningError)?;
+        let tx = tx.with_signature(signature);
+
+        let mut pending: FuturesUnordered<_> = self
+            .authorities
+            .iter()
+            .map(|(address, authority)| {
+                let tx = tx.clone();
+                async move { (*address, self.request_vote(authority, tx).await) }
+            })
+            .collect();
+
+        let mut certificate = Certificate::new(tx.clone());
+        let mut failures = Vec::new();
+        let required = self.committee.quorum_threshold();
+
+        while let Some((address, result)) = pending.next().await {
+            let vote = match result {
+                Ok(vote) => vote,
+                Err(e) => {
+                    failures.push((address, e));
+                    continue;
+                }
+            };
+
+            // a vote has to come from the authority we asked and be signed by it
+            if vote.authority != address || !self.committee.is_member(&address) {
+                failures.push((
+                    address,
+                    AuthorityClientError::Rejected(CommitteeError::UnknownAuthority(
+                        vote.authority,
+                    )),
+                ));
+                continue;
+            }
+            if let Err(e) = vote.verify(&tx) {
+                failures.push((address, AuthorityClientError::Rejected(e)));
+                continue;
+            }
+
+            certificate.add_vote(vote);
+
+            // no need to wait for the stragglers once there is a quorum
+            if certificate.stake(&self.committee) >= required {
+                self.sequence.fetch_max(sequence + 1, Ordering::SeqCst);
+                self.confirm(&certificate).await;
+                // the authorities are the source of truth, e.g. when another device shares the
+                // wallet. Not hearing back from enough of them doesn't undo a final transfer
+                let _ = self.sync_sequence().await;
+                return Ok(certificate);
+            }
+        }
+
+        Err(ClientError::QuorumNotReached {
+            collected: certificate.stake(&self.committee),
+            required,
+            failures,
+        })
+    }
+
+    // asks the authorities for the account's next sequence number and keeps the highest one
+    // vouched for by at least one honest authority
+    pub async fn sync_sequence(&self) -> Result<u64, ClientError> {
+        let address = self.address();
+        let mut pending: FuturesUnordered<_> = self
+            .authorities
+            .iter()
+            .map(|(authority_address, authority)| async move {
+                (*authority_address, authority.next_sequence(address).await)
+            })
+            .collect();
+
+        let mut reported = Vec::new();
+        let mut failures = Vec::new();
+        while let Some((address, result)) = pending.next().await {
+            match result {
+                Ok(sequence) => reported.push((sequence, self.committee.stake(&address))),
+                Err(e) => failures.push((address, e)),
+            }
+        }
+
+        reported.sort_by_key(|(sequence, _)| std::cmp::Reverse(*sequence));
+        let required = self.committee.validity_threshold();
+        let mut collected = 0;
+        for (sequence, stake) in reported {
+            collected += stake;
+            if collected >= required {
+                self.sequence.fetch_max(sequence, Ordering::SeqCst);
+                return Ok(self.sequence());
+            }
+        }
+
+        Err(ClientError::QuorumNotReached {
+            collected,
+            required,
+            failures,
+        })
+    }
+
+    // tells every authority the order is certified so they unlock the account, best effort as
+    // the certificate is final either way
+    async fn confirm(&self, certificate: &Certificate) {
+        let confirmations: FuturesUnordered<_> = self
+            .authorities
+            .iter()
+            .map(|(_, authority)| authority.handle_confirmation_order(certificate.clone()))
+            .collect();
+        confirmations.collect::<Vec<_>>().await;
+    }
+
+    async fn request_vote(&self, authority: &A, tx: Tx) -> Result<Vote, AuthorityClientError> {
+        let mut attempt = 1;
+        loop {
+            match authority.handle_transfer_order(tx.clone()).await {
+                Err(AuthorityClientError::Unavailable(_)) if attempt < self.retry.max_attempts => {
+                    tokio::time::sleep(self.retry.backoff * attempt).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    // an authority that is unreachable for the first `failures` requests
+    struct FlakyAuthority {
+        authority: Authority,
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl FlakyAuthority {
+        fn new(failures: u32) -> Self {
+            Self {
+                authority: Authority::random(),
+                failures,
+                calls: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AuthorityClient for FlakyAuthority {
+        async fn handle_transfer_order(&self, tx: Tx) -> Result<Vote, AuthorityClientError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                return Err(AuthorityClientError::Unavailable(
+                    "connection refused".to_string(),
+                ));
+            }
+            AuthorityClient::handle_transfer_order(&self.authority, tx).await
+        }
+
+        async fn handle_confirmation_order(
+            &self,
+            certificate: Certificate,
+        ) -> Result<(), AuthorityClientError> {
+            AuthorityClient::handle_confirmation_order(&self.authority, certificate).await
+        }
+
+        async fn next_sequence(&self, address: Address) -> Result<u64, AuthorityClientError> {
+            AuthorityClient::next_sequence(&self.authority, address).await
+        }
+    }
+
+    fn client(authorities: Vec<FlakyAuthority>) -> Client<FlakyAuthority> {
+        let committee = Committee::new(authorities.iter().map(|a| (a.authority.address(), 1)));
+        let authorities = authorities
+            .into_iter()
+            .map(|a| (a.authority.address(), a))
+            .collect();
+
+        Client::new(Wallet::random(), committee, authorities).with_retry(RetryConfig {
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_transfer_reaches_quorum() {
+        let client = client((0..4).map(|_| FlakyAuthority::new(0)).collect());
+        let to = Wallet::random().address();
+
+        let certificate = client.transfer(to, U256::from(10)).await.unwrap();
+
+        assert_eq!(certificate.tx().from(), client.address());
+        assert_eq!(certificate.tx().to(), to);
+        assert_eq!(certificate.tx().amount(), U256::from(10));
+        assert!(certificate.verify(&client.committee).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_retries_unavailable_authorities() {
+        let client = client((0..4).map(|_| FlakyAuthority::new(2)).collect());
+
+        let certificate = client
+            .transfer(Wallet::random().address(), U256::from(10))
+            .await
+            .unwrap();
+        assert!(certificate.verify(&client.committee).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_tolerates_partial_failure() {
+        // one authority out of four never answers
+        let mut authorities: Vec<FlakyAuthority> = (0..3).map(|_| FlakyAuthority::new(0)).collect();
+        authorities.push(FlakyAuthority::new(u32::MAX));
+        let client = client(authorities);
+
+        let certificate = client
+            .transfer(Wallet::random().address(), U256::from(10))
+            .await
+            .unwrap();
+        assert_eq!(certificate.votes().len(), 3);
+        assert!(certificate.verify(&client.committee).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_without_quorum() {
+        // two authorities out of four never answer
+        let mut authorities: Vec<FlakyAuthority> = (0..2).map(|_| FlakyAuthority::new(0)).collect();
+        authorities.push(FlakyAuthority::new(u32::MAX));
+        authorities.push(FlakyAuthority::new(u32::MAX));
+        let client = client(authorities);
+
+        match client
+            .transfer(Wallet::random().address(), U256::from(10))
+            .await
+        {
+            Err(ClientError::QuorumNotReached {
+                collected,
+                required,
+                failures,
+            }) => {
+                assert_eq!(collected, 2);
+                assert_eq!(required, 3);
+                assert_eq!(failures.len(), 2);
+            }
+            other => panic!("expected quorum failure, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transfer_refreshes_sequence() {
+        let authorities: Vec<Authority> = (0..4).map(|_| Authority::random()).collect();
+        let committee = Committee::new(authorities.iter().map(|a| (a.address(), 1)));
+        let authorities: Vec<(Address, Authority)> = authorities
+            .into_iter()
+            .map(|a| (a.address(), a.with_sequence_numbers(committee.clone())))
+            .collect();
+        let client = Client::new(Wallet::random(), committee, authorities);
+        let to = Wallet::random().address();
+
+        for sequence in 0..3 {
+            let certificate = client.transfer(to, U256::from(10)).await.unwrap();
+            assert_eq!(certificate.tx().sequence(), Some(sequence));
+        }
+        assert_eq!(client.sequence(), 3);
+        for (_, authority) in &client.authorities {
+            assert_eq!(authority.next_sequence(client.address()), 3);
+        }
+
+        // A client that lost track of the sequence number is told about it
+        let stale = client.with_sequence(1);
+        assert!(matches!(
+            stale.transfer(to, U256::from(10)).await,
+            Err(ClientError::QuorumNotReached { .. })
+        ));
+        assert_eq!(stale.sync_sequence().await.unwrap(), 3);
+        assert!(stale.transfer(to, U256::from(10)).await.is_ok());
+    }
+}
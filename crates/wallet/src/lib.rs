@@ -1,21 +1,26 @@
-use alloy::primitives::PrimitiveSignature;
-use alloy::signers::k256::ecdsa::SigningKey;
-use alloy::signers::local::{LocalSigner, PrivateKeySigner};
-use alloy::signers::SignerSync;
+use alloy::primitives::{Address, PrimitiveSignature};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::{Signer, SignerSync};
 use bytes::Bytes;
 use tx::tx::Tx;
 
+pub mod client;
+
 #[derive(Debug)]
 pub enum WalletError {
     SigningError(alloy::signers::Error),
+    // the tx has no typed data form, only transfers do
+    NotTyped,
 }
 
-pub struct Wallet<T> {
-    signer: LocalSigner<T>,
+// a wallet signs with any alloy signer, a local key by default. Remote ones, e.g. backed by a KMS
+// or a Ledger, only sign asynchronously
+pub struct Wallet<S = PrivateKeySigner> {
+    signer: S,
 }
 
-impl Wallet<SigningKey> {
-    pub fn new(signer: LocalSigner<SigningKey>) -> Self {
+impl Wallet<PrivateKeySigner> {
+    pub fn new(signer: PrivateKeySigner) -> Self {
         Self { signer }
     }
 
@@ -24,11 +29,7 @@ impl Wallet<SigningKey> {
         Self { signer }
     }
 
-    pub fn address(&self) -> alloy::primitives::Address {
-        self.signer.address()
-    }
-
-    pub fn sign_message(&self, message: Bytes) -> Result<PrimitiveSignature, WalletError> {
+    pub fn sign_message_sync(&self, message: Bytes) -> Result<PrimitiveSignature, WalletError> {
         let signature = self.signer.sign_message_sync(&message);
 
         match signature {
@@ -37,17 +38,62 @@ impl Wallet<SigningKey> {
         }
     }
 
-    pub fn sign_transaction(&self, transaction: Tx) -> Result<PrimitiveSignature, WalletError> {
+    pub fn sign_transaction_sync(
+        &self,
+        transaction: Tx,
+    ) -> Result<PrimitiveSignature, WalletError> {
         let message = transaction.tx_hash();
 
-        self.sign_message(message)
+        self.sign_message_sync(message)
+    }
+
+    // signs the EIP-712 typed data of a transfer, which hardware wallets can show field by field
+    pub fn sign_typed_tx_sync(&self, transaction: Tx) -> Result<PrimitiveSignature, WalletError> {
+        let hash = transaction.typed_hash().ok_or(WalletError::NotTyped)?;
+
+        self.signer
+            .sign_hash_sync(&hash)
+            .map_err(WalletError::SigningError)
+    }
+}
+
+impl<S: Signer + Send + Sync> Wallet<S> {
+    pub fn from_signer(signer: S) -> Self {
+        Self { signer }
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub async fn sign_message(&self, message: Bytes) -> Result<PrimitiveSignature, WalletError> {
+        self.signer
+            .sign_message(&message)
+            .await
+            .map_err(WalletError::SigningError)
+    }
+
+    pub async fn sign_transaction(
+        &self,
+        transaction: Tx,
+    ) -> Result<PrimitiveSignature, WalletError> {
+        self.sign_message(transaction.tx_hash()).await
+    }
+
+    pub async fn sign_typed_tx(&self, transaction: Tx) -> Result<PrimitiveSignature, WalletError> {
+        let hash = transaction.typed_hash().ok_or(WalletError::NotTyped)?;
+
+        self.signer
+            .sign_hash(&hash)
+            .await
+            .map_err(WalletError::SigningError)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy::primitives::hex;
+    use alloy::primitives::{hex, U256};
     use alloy::signers::local::PrivateKeySigner;
     use tx::tx::Tx;
 
@@ -77,13 +123,13 @@ mod tests {
         let wallet = Wallet::random();
         let message = Bytes::from_static(b"Hello, World!");
 
-        let signature = wallet.sign_message(message.clone()).unwrap();
+        let signature = wallet.sign_message_sync(message.clone()).unwrap();
 
         // Verify signature length (65 bytes for secp256k1: r (32 bytes) + s (32 bytes) + v (1 byte))
         assert_eq!(signature.as_bytes().len(), 65);
 
         // Verify we get the same signature for the same message
-        let signature2 = wallet.sign_message(message).unwrap();
+        let signature2 = wallet.sign_message_sync(message).unwrap();
         assert_eq!(signature.as_bytes(), signature2.as_bytes());
     }
 
@@ -93,8 +139,8 @@ mod tests {
         let message1 = Bytes::from_static(b"Hello, World!");
         let message2 = Bytes::from_static(b"Different message");
 
-        let signature1 = wallet.sign_message(message1).unwrap();
-        let signature2 = wallet.sign_message(message2).unwrap();
+        let signature1 = wallet.sign_message_sync(message1).unwrap();
+        let signature2 = wallet.sign_message_sync(message2).unwrap();
 
         // Different messages should produce different signatures
         assert_ne!(signature1.as_bytes(), signature2.as_bytes());
@@ -110,17 +156,17 @@ mod tests {
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
 
-        let amount = 100u64;
+        let amount = U256::from(100);
 
-        let tx = Tx::new(from.clone(), to.clone(), amount, None);
-        let signature = wallet.sign_transaction(tx).unwrap();
+        let tx = Tx::new(from, to, amount, None);
+        let signature = wallet.sign_transaction_sync(tx).unwrap();
 
         // Verify signature length
         assert_eq!(signature.as_bytes().len(), 65);
 
         // Create a new transaction with the same parameters
         let tx2 = Tx::new(from, to, amount, None);
-        let signature2 = wallet.sign_transaction(tx2).unwrap();
+        let signature2 = wallet.sign_transaction_sync(tx2).unwrap();
 
         // Verify we get the same signature for the same transaction
         assert_eq!(signature.as_bytes(), signature2.as_bytes());
@@ -136,15 +182,17 @@ mod tests {
         let to_signer = PrivateKeySigner::random();
         let to = to_signer.address();
 
-        let tx1 = Tx::new(from, to, 100, None);
+        let tx1 = Tx::new(from, to, U256::from(100), None);
 
         let tx2 = Tx::new(
-            from, to, 200, // Different amount
+            from,
+            to,
+            U256::from(200), // Different amount
             None,
         );
 
-        let signature1 = wallet.sign_transaction(tx1).unwrap();
-        let signature2 = wallet.sign_transaction(tx2).unwrap();
+        let signature1 = wallet.sign_transaction_sync(tx1).unwrap();
+        let signature2 = wallet.sign_transaction_sync(tx2).unwrap();
 
         // Different transactions should produce different signatures
         assert_ne!(signature1.as_bytes(), signature2.as_bytes());
@@ -156,10 +204,74 @@ mod tests {
         let wallet2 = Wallet::random();
         let message = Bytes::from_static(b"Hello, World!");
 
-        let signature1 = wallet1.sign_message(message.clone()).unwrap();
-        let signature2 = wallet2.sign_message(message).unwrap();
+        let signature1 = wallet1.sign_message_sync(message.clone()).unwrap();
+        let signature2 = wallet2.sign_message_sync(message).unwrap();
 
         // Different wallets should produce different signatures for the same message
         assert_ne!(signature1.as_bytes(), signature2.as_bytes());
     }
+
+    #[test]
+    fn test_sign_typed_tx() {
+        let wallet = Wallet::random();
+        let to = PrivateKeySigner::random().address();
+
+        let tx = Tx::transfer_order(wallet.address(), to, U256::from(100), 0, None);
+        let signature = wallet.sign_typed_tx_sync(tx.clone()).unwrap();
+        assert_ne!(signature, wallet.sign_transaction_sync(tx.clone()).unwrap());
+        assert!(tx.with_signature(signature).is_signed_by(wallet.address()));
+
+        let channel = Tx::open_channel(wallet.address(), to, U256::from(100), 10, None);
+        assert!(matches!(
+            wallet.sign_typed_tx_sync(channel),
+            Err(WalletError::NotTyped)
+        ));
+    }
+
+    // stands in for a remote signer, it can only sign asynchronously
+    struct RemoteSigner(PrivateKeySigner);
+
+    #[async_trait::async_trait]
+    impl Signer for RemoteSigner {
+        async fn sign_hash(
+            &self,
+            hash: &alloy::primitives::B256,
+        ) -> alloy::signers::Result<PrimitiveSignature> {
+            tokio::task::yield_now().await;
+            self.0.sign_hash(hash).await
+        }
+
+        fn address(&self) -> Address {
+            self.0.address()
+        }
+
+        fn chain_id(&self) -> Option<alloy::primitives::ChainId> {
+            None
+        }
+
+        fn set_chain_id(&mut self, _chain_id: Option<alloy::primitives::ChainId>) {}
+    }
+
+    #[tokio::test]
+    async fn test_async_signer() {
+        let key = PrivateKeySigner::random();
+        let local = Wallet::new(key.clone());
+        let remote = Wallet::from_signer(RemoteSigner(key));
+        assert_eq!(remote.address(), local.address());
+
+        let tx = Tx::transfer_order(
+            remote.address(),
+            Address::repeat_byte(1),
+            U256::from(10),
+            0,
+            None,
+        );
+        let signature = remote.sign_transaction(tx.clone()).await.unwrap();
+        assert_eq!(signature, local.sign_transaction_sync(tx.clone()).unwrap());
+        assert_eq!(
+            remote.sign_typed_tx(tx.clone()).await.unwrap(),
+            local.sign_typed_tx_sync(tx.clone()).unwrap()
+        );
+        assert!(tx.with_signature(signature).is_signed_by(remote.address()));
+    }
 }
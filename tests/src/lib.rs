@@ -0,0 +1,196 @@
+// an embedded node for end-to-end tests: a dev genesis, the rpc server on a free local port and
+// blocks produced by the test itself, so the whole pipeline runs in one process the way
+// fastpay-node wires it
+
+pub mod testnet;
+
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use block_builder::{Block, BlockBuilder};
+use client::{transport::Transport, Client};
+use mempool::{Mempool, MempoolConfig};
+use node::{genesis::Genesis, Node};
+use rpc::{transaction::TransactionReceipt, RpcConfig, MAX_BLOCKS_PER_REQUEST};
+use serde_json::json;
+use state::{pending::PendingState, sharded::ShardedState};
+use tokio::task::JoinHandle;
+
+// what every dev account starts with
+pub const DEV_BALANCE: u64 = 1_000_000;
+const STATE_SHARDS: usize = 4;
+const MAX_BLOCK_TXS: usize = 1_000;
+
+// a genesis funding each of `accounts` with DEV_BALANCE
+pub fn dev_genesis(accounts: &[Address]) -> Genesis {
+    let mut genesis = Genesis::default();
+    for account in accounts {
+        genesis.fund(*account, U256::from(DEV_BALANCE));
+    }
+    genesis
+}
+
+pub struct TestNode {
+    node: Node,
+    block_builder: BlockBuilder,
+    mempool: Mempool,
+    pending: PendingState,
+    rpc_url: String,
+    server: JoinHandle<anyhow::Result<()>>,
+}
+
+impl TestNode {
+    // returns once the rpc server answers
+    pub async fn start(genesis: Genesis) -> anyhow::Result<Self> {
+        let mut state = Arc::new(ShardedState::in_memory(STATE_SHARDS));
+        genesis.apply(&mut state)?;
+        let node = Node::from_genesis(Box::new(state.clone()), &genesis);
+        let pending = PendingState::new(state.clone());
+        let mempool = Mempool::with_config(MempoolConfig {
+            fee_schedule: genesis.fee_schedule(),
+            ..MempoolConfig::default()
+        })
+        .with_sequences(state.clone());
+        let block_builder = BlockBuilder::new();
+
+        let addr = free_addr()?;
+        let config = RpcConfig {
+            addr,
+            chain_id: genesis.chain_id(),
+            pending: Some(pending.clone()),
+            block_capacity: MAX_BLOCK_TXS,
+            simulator: Some(node.simulator(pending.clone())),
+            ..RpcConfig::default()
+        };
+        let server = tokio::spawn(rpc::start_rpc_server(
+            config,
+            block_builder.clone(),
+            mempool.clone(),
+            state,
+        ));
+
+        let test_node = Self {
+            node,
+            block_builder,
+            mempool,
+            pending,
+            rpc_url: format!("http://{}", addr),
+            server,
+        };
+        test_node.wait_for_rpc().await?;
+        Ok(test_node)
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    pub fn client(&self) -> Client {
+        Client::new(self.rpc_url.clone()).expect("the node's url is valid")
+    }
+
+    // seals what's waiting in the mempool, as fastpay-node does every tick
+    pub async fn produce_block(&mut self) -> anyhow::Result<Block> {
+        let txs = self.mempool.take_batch(MAX_BLOCK_TXS).await;
+        let block = self
+            .node
+            .produce_block(&self.block_builder, txs, Address::ZERO)
+            .await?;
+        self.node
+            .execute_pending(&self.pending, &self.mempool.pending().await);
+        Ok(block)
+    }
+
+    pub async fn head(&self) -> Option<Block> {
+        self.block_builder.get_latest_block().await
+    }
+
+    pub async fn block(&self, number: u64) -> Option<Block> {
+        self.block_builder.get_block(U256::from(number)).await
+    }
+
+    // imports the blocks `peer` has beyond this node's head, validating and executing each the way
+    // a fastpay-node following its peers does. Returns how many it imported
+    pub async fn sync_from(&mut self, peer: &Client) -> anyhow::Result<u64> {
+        let mut imported = 0;
+        loop {
+            let next = self
+                .head()
+                .await
+                .map_or(0, |head| head.number.saturating_to::<u64>() + 1);
+            let blocks = peer
+                .transport()
+                .request_value("fastpay_getBlocks", json!([next, MAX_BLOCKS_PER_REQUEST]))
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to get blocks: {:?}", e))?;
+            let blocks: Vec<Block> = serde_json::from_value(blocks)?;
+            if blocks.is_empty() {
+                break;
+            }
+            for block in blocks {
+                self.node.import_block(&self.block_builder, block).await?;
+                imported += 1;
+            }
+        }
+        self.node
+            .execute_pending(&self.pending, &self.mempool.pending().await);
+        Ok(imported)
+    }
+
+    // produces a block every `block_time` until `until` completes
+    pub async fn run_until<F: Future>(
+        &mut self,
+        block_time: Duration,
+        until: F,
+    ) -> anyhow::Result<F::Output> {
+        tokio::pin!(until);
+        let mut ticker = tokio::time::interval(block_time);
+        loop {
+            tokio::select! {
+                output = &mut until => return Ok(output),
+                _ = ticker.tick() => {
+                    self.produce_block().await?;
+                }
+            }
+        }
+    }
+
+    async fn wait_for_rpc(&self) -> anyhow::Result<()> {
+        let client = self.client();
+        for _ in 0..100 {
+            if client.block_number().await.is_ok() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        anyhow::bail!("rpc server on {} didn't come up", self.rpc_url)
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+// the client has no call for receipts, they are read as the rpc serves them
+pub async fn get_receipt(
+    client: &Client,
+    tx_hash: B256,
+) -> anyhow::Result<Option<TransactionReceipt>> {
+    let receipt = client
+        .transport()
+        .request_value("eth_getTransactionReceipt", json!([tx_hash]))
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get receipt: {:?}", e))?;
+    Ok(serde_json::from_value(receipt)?)
+}
+
+// a local address nothing listens on, for the rpc server to take
+fn free_addr() -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?)
+}
@@ -0,0 +1,121 @@
+// several embedded nodes on one genesis, connected the way fastpay-node peers are: the first one
+// produces the blocks and the others follow it over its rpc, so tests can check what reaches the
+// followers. Nodes don't relay transactions to each other, they are sent to the producer
+
+use std::time::Duration;
+
+use alloy::primitives::B256;
+use block_builder::Block;
+use node::genesis::Genesis;
+
+use crate::TestNode;
+
+pub const DEFAULT_BLOCK_TIME: Duration = Duration::from_millis(50);
+// how long wait_for_block keeps producing before it gives up
+pub const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Testnet {
+    // the producer first
+    nodes: Vec<TestNode>,
+    block_time: Duration,
+}
+
+impl Testnet {
+    // returns once every node's rpc server answers
+    pub async fn start(size: usize, genesis: Genesis) -> anyhow::Result<Self> {
+        if size == 0 {
+            anyhow::bail!("a testnet needs at least one node");
+        }
+        let mut nodes = Vec::with_capacity(size);
+        for _ in 0..size {
+            nodes.push(TestNode::start(genesis.clone()).await?);
+        }
+        Ok(Self {
+            nodes,
+            block_time: DEFAULT_BLOCK_TIME,
+        })
+    }
+
+    pub fn with_block_time(mut self, block_time: Duration) -> Self {
+        self.block_time = block_time;
+        self
+    }
+
+    pub fn producer(&self) -> &TestNode {
+        &self.nodes[0]
+    }
+
+    pub fn node(&self, index: usize) -> &TestNode {
+        &self.nodes[index]
+    }
+
+    pub fn nodes(&self) -> &[TestNode] {
+        &self.nodes
+    }
+
+    // the producer seals what's waiting in its mempool and every follower imports the block
+    pub async fn produce_block(&mut self) -> anyhow::Result<Block> {
+        let block = self.nodes[0].produce_block().await?;
+        self.sync().await?;
+        Ok(block)
+    }
+
+    // every follower catches up with the producer, returns how many blocks they imported in all
+    pub async fn sync(&mut self) -> anyhow::Result<u64> {
+        let (producer, followers) = self.nodes.split_first_mut().expect("never empty");
+        let peer = producer.client();
+        let mut imported = 0;
+        for follower in followers {
+            imported += follower.sync_from(&peer).await?;
+        }
+        Ok(imported)
+    }
+
+    // produces a block every block time until every node has block `number`, which they have to
+    // agree on
+    pub async fn wait_for_block(&mut self, number: u64) -> anyhow::Result<Block> {
+        let block_time = self.block_time;
+        let wait = async {
+            let mut ticker = tokio::time::interval(block_time);
+            loop {
+                if let Some(block) = self.agreed_block(number).await? {
+                    return Ok(block);
+                }
+                ticker.tick().await;
+                self.produce_block().await?;
+            }
+        };
+        tokio::time::timeout(WAIT_TIMEOUT, wait)
+            .await
+            .map_err(|_| anyhow::anyhow!("block {} wasn't reached in {:?}", number, WAIT_TIMEOUT))?
+    }
+
+    async fn agreed_block(&self, number: u64) -> anyhow::Result<Option<Block>> {
+        let Some(block) = self.nodes[0].block(number).await else {
+            return Ok(None);
+        };
+        for (index, node) in self.nodes.iter().enumerate().skip(1) {
+            match node.block(number).await.map(|block| block.hash) {
+                None => return Ok(None),
+                Some(hash) if hash != block.hash => anyhow::bail!(
+                    "node {} has block {} as {} but the producer has {}",
+                    index,
+                    number,
+                    hash,
+                    block.hash
+                ),
+                Some(_) => {}
+            }
+        }
+        Ok(Some(block))
+    }
+
+    // the hash of every node's head, in node order
+    pub async fn heads(&self) -> Vec<Option<B256>> {
+        let mut heads = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            heads.push(node.head().await.map(|head| head.hash));
+        }
+        heads
+    }
+}
@@ -0,0 +1,106 @@
+// a signed transfer from a wallet through the rpc client, the mempool and a block to its receipt,
+// checked from the outside the way a wallet or an explorer sees it
+
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use e2e::{dev_genesis, get_receipt, TestNode, DEV_BALANCE};
+use tx::{log::transfer_topic, tx::Tx};
+use wallet::Wallet;
+
+const BLOCK_TIME: Duration = Duration::from_millis(50);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn test_transfer_golden_path() {
+    let sender = Wallet::random();
+    let recipient = Address::repeat_byte(0x42);
+    let mut node = TestNode::start(dev_genesis(&[sender.address()]))
+        .await
+        .unwrap();
+    let client = node.client();
+
+    // An empty block to compare the state root against
+    let before = node.produce_block().await.unwrap();
+    assert!(before.transactions.is_empty());
+
+    let chain_id = client.chain_id().await.unwrap();
+    let sequence = client
+        .get_transaction_count(sender.address(), true)
+        .await
+        .unwrap();
+    let tx = Tx::transfer_order(sender.address(), recipient, U256::from(250), sequence, None)
+        .with_chain_id(chain_id);
+    let signature = sender.sign_transaction_sync(tx.clone()).unwrap();
+    let tx_hash = client
+        .send_transfer(&tx.with_signature(signature))
+        .await
+        .unwrap();
+    assert_eq!(
+        client
+            .get_transaction_count(sender.address(), true)
+            .await
+            .unwrap(),
+        sequence + 1
+    );
+
+    // The node keeps producing blocks until the transfer lands in one
+    let wait_for_receipt = async {
+        loop {
+            if let Some(receipt) = get_receipt(&client, tx_hash).await.unwrap() {
+                return receipt;
+            }
+            tokio::time::sleep(BLOCK_TIME / 2).await;
+        }
+    };
+    let receipt = tokio::time::timeout(TIMEOUT, node.run_until(BLOCK_TIME, wait_for_receipt))
+        .await
+        .expect("the transfer wasn't included in time")
+        .unwrap();
+
+    assert_eq!(receipt.status, "0x1");
+    assert_eq!(receipt.transaction_hash, tx_hash);
+    assert_eq!((receipt.from, receipt.to), (sender.address(), recipient));
+    assert_eq!(receipt.logs.len(), 1);
+    assert_eq!(receipt.logs[0].topics[0], transfer_topic());
+    assert_eq!(receipt.logs[0].transaction_hash, tx_hash);
+
+    // The block holds the transfer and moved the state root
+    let number = u64::from_str_radix(receipt.block_number.trim_start_matches("0x"), 16).unwrap();
+    let block = client
+        .get_block_by_number(Some(number))
+        .await
+        .unwrap()
+        .expect("the receipt's block is served");
+    assert_eq!(block.hash, receipt.block_hash);
+    assert_eq!(block.transactions, vec![tx_hash]);
+    assert_ne!(block.state_root, Some(before.state_root));
+    assert_ne!(block.state_root, Some(B256::ZERO));
+
+    assert_eq!(
+        client.get_balance(sender.address()).await.unwrap(),
+        U256::from(DEV_BALANCE - 250)
+    );
+    assert_eq!(
+        client.get_balance(recipient).await.unwrap(),
+        U256::from(250)
+    );
+    assert_eq!(
+        client
+            .get_transaction_count(sender.address(), false)
+            .await
+            .unwrap(),
+        sequence + 1
+    );
+
+    let events = client.get_transfer_events(0, number, None).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        (events[0].from, events[0].to),
+        (sender.address(), recipient)
+    );
+    assert_eq!(events[0].amount, U256::from(250));
+    assert_eq!(events[0].block_number, Some(number));
+    assert_eq!(events[0].tx_hash, Some(tx_hash));
+    assert!(!events[0].removed);
+}
@@ -0,0 +1,52 @@
+// a transfer sent to the producer of a small testnet reaches every follower through sync, checked
+// over each node's own rpc
+
+use alloy::primitives::{Address, U256};
+use e2e::{dev_genesis, testnet::Testnet, DEV_BALANCE};
+use tx::tx::Tx;
+use wallet::Wallet;
+
+#[tokio::test]
+async fn test_testnet_followers_sync() {
+    let sender = Wallet::random();
+    let recipient = Address::repeat_byte(0x42);
+    let mut testnet = Testnet::start(3, dev_genesis(&[sender.address()]))
+        .await
+        .unwrap();
+
+    let client = testnet.producer().client();
+    let chain_id = client.chain_id().await.unwrap();
+    let tx = Tx::transfer_order(sender.address(), recipient, U256::from(250), 0, None)
+        .with_chain_id(chain_id);
+    let signature = sender.sign_transaction_sync(tx.clone()).unwrap();
+    client
+        .send_transfer(&tx.with_signature(signature))
+        .await
+        .unwrap();
+
+    // The first block takes the transfer, the ones after are empty
+    let block = testnet.wait_for_block(3).await.unwrap();
+    assert_eq!(block.number, U256::from(3));
+    let heads = testnet.heads().await;
+    assert!(heads.iter().all(|head| *head == Some(block.hash)));
+
+    for node in testnet.nodes() {
+        let client = node.client();
+        assert_eq!(client.block_number().await.unwrap(), U256::from(3));
+        assert_eq!(
+            client.get_balance(recipient).await.unwrap(),
+            U256::from(250)
+        );
+        assert_eq!(
+            client.get_balance(sender.address()).await.unwrap(),
+            U256::from(DEV_BALANCE - 250)
+        );
+    }
+
+    // Nothing left to import once the followers are caught up
+    assert_eq!(testnet.sync().await.unwrap(), 0);
+    assert_eq!(
+        testnet.wait_for_block(2).await.unwrap().hash,
+        testnet.node(2).block(2).await.unwrap().hash
+    );
+}